@@ -1,13 +1,29 @@
 //! Performance benchmarks
 
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use tabssh::terminal::benchmark;
+use tabssh::VtParser;
 
-fn terminal_render_benchmark(c: &mut Criterion) {
-    c.bench_function("terminal_render", |b| {
-        b.iter(|| {
-            // Benchmark terminal rendering
-            black_box(());
+fn terminal_parse_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("terminal_parse");
+    for size in [64 * 1024, 1024 * 1024] {
+        let corpus = benchmark::corpus(size);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &corpus, |b, corpus| {
+            b.iter(|| {
+                let mut parser = VtParser::new();
+                for byte in corpus {
+                    black_box(parser.parse(*byte));
+                }
+            });
         });
+    }
+    group.finish();
+}
+
+fn terminal_corpus_benchmark(c: &mut Criterion) {
+    c.bench_function("terminal_corpus_1mb", |b| {
+        b.iter(|| black_box(benchmark::corpus(1024 * 1024)));
     });
 }
 
@@ -20,5 +36,10 @@ fn theme_parse_benchmark(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches,terminal_render_benchmark,theme_parse_benchmark);
+criterion_group!(
+    benches,
+    terminal_parse_benchmark,
+    terminal_corpus_benchmark,
+    theme_parse_benchmark
+);
 criterion_main!(benches);
@@ -1,51 +1,619 @@
 //! Main application structure
 
-use crate::ui::app_state::AppState;
+use crate::platform::transparency::{self, TransparencySupport};
+use crate::ui::app_state::{AppState, TabType};
 use crate::ui::keyboard::{KeyboardHandler, KeyboardAction};
-use crate::ui::components::{TabBar, Toolbar, StatusBar};
+use crate::ui::lock::AppLock;
+use crate::ui::markdown;
+use crate::ui::overview::{OverviewTile, TabOverview};
+use crate::ui::components::{TabBar, TabBarAction, Toolbar, StatusBar};
+use crate::ui::quake::QuakeWindow;
+use crate::ui::screens::{
+    FileViewerAction, HostKeyDialog, MfaDialog, SettingsAction, SettingsScreen, TransferHistoryAction, TransferHistoryScreen,
+    UsageStatsAction, UsageStatsScreen,
+};
+use crate::utils::crash;
+use crate::utils::startup::StartupProfiler;
+use crate::utils::updater::{UpdateChecker, APP_VERSION};
+use anyhow::Result;
 use egui::Context;
+use std::path::PathBuf;
+use std::sync::mpsc;
 
 pub struct TabSshApp {
+    profiler: StartupProfiler,
+    transparency: TransparencySupport,
+    /// Delivers the state and fonts once the background load finishes
+    loader: Option<mpsc::Receiver<Result<Loaded>>>,
+    /// `None` while loading; a skeleton of the window shows meanwhile
+    window: Option<MainWindow>,
+}
+
+/// What the background load hands to the UI thread
+struct Loaded {
+    state: AppState,
+    fonts: egui::FontDefinitions,
+}
+
+struct MainWindow {
     state: AppState,
     tab_bar: TabBar,
     toolbar: Toolbar,
     status_bar: StatusBar,
+    /// Drop-down terminal on a global hotkey
+    quake: QuakeWindow,
+    /// Auto-lock screen
+    lock: AppLock,
+    /// Shown when closing would cut off sessions or transfers
+    exit_warning: Option<String>,
+    /// Tab waiting for the user to confirm closing it over a running job,
+    /// with what runs there
+    close_confirm: Option<(String, String)>,
+    /// Set once shutdown has run and the window may close
+    shut_down: bool,
+    transparency: TransparencySupport,
+    /// Blur setting last applied to the window
+    applied_blur: Option<bool>,
+    updates: UpdateChecker,
+    release_notes_open: bool,
+    /// Report left by the last crash, offered once at startup
+    crash_report: Option<(PathBuf, String)>,
+    /// A jump host's verification questions
+    mfa_dialog: Option<MfaDialog>,
+    /// A server key waiting to be trusted or rejected
+    host_key_dialog: Option<HostKeyDialog>,
+    /// Grid of all tabs, shown instead of the active one
+    overview: TabOverview,
+    /// Usage statistics page; `None` until its tab is first shown
+    usage_stats: Option<UsageStatsScreen>,
+    /// Transfer history page; `None` until its tab is first shown
+    transfer_history: Option<TransferHistoryScreen>,
+    /// Settings page; `None` until its tab is first shown
+    settings_screen: Option<SettingsScreen>,
 }
 
 impl TabSshApp {
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        // Configure fonts
-        let mut fonts = egui::FontDefinitions::default();
-        // Could load custom fonts here
-        cc.egui_ctx.set_fonts(fonts);
-        
-        let state = AppState::new().unwrap_or_else(|e| {
+    /// Show the window right away and load the database, themes, SSH config
+    /// and fonts on a background thread
+    pub fn new(cc: &eframe::CreationContext<'_>, profiler: StartupProfiler, transparency: TransparencySupport) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let ctx = cc.egui_ctx.clone();
+        let background = profiler.clone();
+        std::thread::Builder::new()
+            .name("startup".to_string())
+            .spawn(move || {
+                let loaded = AppState::load(&background).map(|state| Loaded {
+                    // Could load custom fonts here
+                    fonts: background.phase("fonts", egui::FontDefinitions::default),
+                    state,
+                });
+                let _ = tx.send(loaded);
+                ctx.request_repaint();
+            })
+            .expect("Failed to spawn startup thread");
+
+        Self {
+            profiler,
+            transparency,
+            loader: Some(rx),
+            window: None,
+        }
+    }
+
+    /// Take over the loaded state once the background thread delivers it
+    fn poll_loader(&mut self, ctx: &Context) {
+        let Some(rx) = &self.loader else { return };
+        let loaded = match rx.try_recv() {
+            Ok(loaded) => loaded,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => Err(anyhow::anyhow!("startup thread panicked")),
+        };
+        self.loader = None;
+
+        let loaded = loaded.unwrap_or_else(|e| {
             eprintln!("Failedtoinitializeappstate:{}",e);
             std::process::exit(1);
         });
-        
+        ctx.set_fonts(loaded.fonts);
+        loaded.state.executor.set_repaint_context(ctx.clone());
+        self.window = Some(MainWindow::new(loaded.state, self.transparency));
+        self.profiler.finish();
+    }
+
+    /// Placeholder panels in the real layout until the state is loaded
+    fn render_skeleton(&self, ctx: &Context) {
+        egui::TopBottomPanel::top("toolbar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                for width in [28.0, 28.0, 28.0, 120.0] {
+                    skeleton_block(ui, width);
+                }
+            });
+        });
+        egui::TopBottomPanel::top("tabs").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                for width in [140.0, 110.0, 125.0] {
+                    skeleton_block(ui, width);
+                }
+            });
+        });
+        egui::TopBottomPanel::bottom("status").show(ctx, |ui| {
+            ui.label(egui::RichText::new("Loading…").weak());
+        });
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.add_space(100.0);
+                ui.spinner();
+                ui.label(egui::RichText::new("Loading profiles").weak());
+            });
+        });
+    }
+}
+
+/// Grey bar standing in for a widget that is not loaded yet
+fn skeleton_block(ui: &mut egui::Ui, width: f32) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(width, 18.0), egui::Sense::hover());
+    let fill = ui.visuals().widgets.noninteractive.weak_bg_fill;
+    ui.painter().rect_filled(rect, 4.0, fill);
+}
+
+impl MainWindow {
+    fn new(state: AppState, transparency: TransparencySupport) -> Self {
+        // Portable copies start locked so the master password opens the vault,
+        // or ask for one first so the vault can hold anything
+        let mut lock = AppLock::new();
+        if crate::platform::portable::is_portable() {
+            lock.open_vault(&state.db, &state.settings);
+        }
         Self {
+            updates: UpdateChecker::new(&state.db),
+            release_notes_open: false,
+            crash_report: if state.settings.crash_reports { crash::next_report() } else { None },
             state,
             tab_bar: TabBar::new(),
             toolbar: Toolbar,
             status_bar: StatusBar::new(),
+            quake: QuakeWindow::new(),
+            lock,
+            exit_warning: None,
+            close_confirm: None,
+            shut_down: false,
+            transparency,
+            applied_blur: None,
+            mfa_dialog: None,
+            host_key_dialog: None,
+            overview: TabOverview::new(),
+            usage_stats: None,
+            transfer_history: None,
+            settings_screen: None,
+        }
+    }
+}
+
+impl MainWindow {
+    /// Intercept the window close to warn about running work and shut down cleanly
+    fn handle_close_request(&mut self, ctx: &Context) {
+        if !ctx.input(|i| i.viewport().close_requested()) || self.shut_down {
+            return;
+        }
+
+        match self.state.exit_warning() {
+            Some(warning) if self.exit_warning.is_none() => {
+                ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+                self.exit_warning = Some(warning);
+            }
+            Some(_) => ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose),
+            None => {
+                self.state.shutdown();
+                self.shut_down = true;
+            }
+        }
+    }
+
+    /// Toggle the drop-down window on its hotkey and draw it while it is down
+    fn update_quake(&mut self, ctx: &Context) {
+        self.quake.apply(&self.state.settings.quake, ctx);
+        if self.quake.poll() {
+            let tab_id = self.state.open_quake_tab(self.state.settings.quake.profile_id.clone());
+            self.quake.tab_id = Some(tab_id);
+        }
+
+        let tab = self.quake.tab_id.as_ref()
+            .and_then(|id| self.state.tabs.iter().find(|tab| &tab.id == id));
+        let Some(tab) = tab else {
+            // Its tab was closed; the next summon opens a new one
+            self.quake.tab_id = None;
+            self.quake.hide();
+            return;
+        };
+        let title = tab.title.clone();
+        self.quake.show(ctx, &self.state.settings.quake, |ui| {
+            ui.label(egui::RichText::new(title).strong());
+            ui.separator();
+            ui.label("Tab content here");
+        });
+    }
+
+    /// Follow the blur setting; where blur fails the window stays merely translucent
+    fn apply_blur(&mut self, frame: &eframe::Frame) {
+        let blur = self.state.settings.background_blur && self.state.settings.window_opacity < 100;
+        if self.applied_blur == Some(blur) {
+            return;
+        }
+        self.applied_blur = Some(blur);
+        if let Err(e) = transparency::set_blur(frame, blur) {
+            log::warn!("Could not set background blur: {:#}", e);
+        }
+    }
+
+    /// Terminal area frame, translucent at the configured opacity
+    fn terminal_frame(&self, ctx: &Context) -> egui::Frame {
+        let fill = ctx.style().visuals.panel_fill;
+        let alpha = transparency::background_alpha(self.state.settings.window_opacity, self.transparency);
+        egui::Frame::central_panel(&ctx.style())
+            .fill(egui::Color32::from_rgba_unmultiplied(fill.r(), fill.g(), fill.b(), alpha))
+    }
+
+    /// Corner notice while a newer release is on offer, with its notes on request
+    fn render_update_notice(&mut self, ctx: &Context) {
+        let Some(release) = self.updates.available().cloned() else {
+            self.release_notes_open = false;
+            return;
+        };
+
+        egui::Window::new("update_notice")
+            .title_bar(false)
+            .resizable(false)
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-12.0, -36.0])
+            .show(ctx, |ui| {
+                ui.label(egui::RichText::new(format!("TabSSH {} is available", release.version)).strong());
+                ui.label(egui::RichText::new(format!("You have {}", APP_VERSION)).weak());
+                ui.add_space(4.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Download").clicked() {
+                        ctx.open_url(egui::OpenUrl::new_tab(release.download_url()));
+                        self.updates.dismiss();
+                    }
+                    if ui.button("Release notes").clicked() {
+                        self.release_notes_open = true;
+                    }
+                    if ui.button("Later").clicked() {
+                        self.updates.dismiss();
+                    }
+                    if ui.button("Skip this version").clicked() {
+                        self.updates.skip(&self.state.db);
+                    }
+                });
+            });
+
+        if self.release_notes_open {
+            let mut open = true;
+            egui::Window::new(format!("What's new in TabSSH {}", release.version))
+                .open(&mut open)
+                .collapsible(false)
+                .default_size([480.0, 360.0])
+                .show(ctx, |ui| {
+                    egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                        if release.notes.trim().is_empty() {
+                            ui.label(egui::RichText::new("This release has no notes.").weak());
+                        } else {
+                            markdown::render(ui, &release.notes);
+                        }
+                    });
+                    ui.separator();
+                    ui.hyperlink_to("Open the release page", &release.page_url);
+                });
+            self.release_notes_open &= open;
+        }
+    }
+
+    /// Show the last crash report; it only leaves the machine if the user
+    /// opens an issue with it
+    fn render_crash_dialog(&mut self, ctx: &Context) {
+        let Some((path, report)) = &self.crash_report else { return };
+        let mut choice = None;
+
+        egui::Window::new("TabSSH quit unexpectedly")
+            .collapsible(false)
+            .default_size([560.0, 420.0])
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label("A crash report was saved. It holds the version, OS, backtrace and recent log lines, \
+                          with host names, user names, addresses and home paths masked.");
+                ui.label("Nothing has been sent. Opening an issue shows the report in your browser, \
+                          where you can edit it before submitting.");
+                ui.add_space(4.0);
+                egui::ScrollArea::vertical().max_height(260.0).show(ui, |ui| {
+                    ui.add(egui::TextEdit::multiline(&mut report.as_str())
+                        .code_editor()
+                        .desired_width(f32::INFINITY));
+                });
+                ui.add_space(4.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Open an issue").clicked() {
+                        ctx.open_url(egui::OpenUrl::new_tab(crash::issue_url(report)));
+                        choice = Some(true);
+                    }
+                    if ui.button("Copy report").clicked() {
+                        ui.output_mut(|o| o.copied_text = report.clone());
+                    }
+                    if ui.button("Delete").clicked() {
+                        choice = Some(true);
+                    }
+                    if ui.button("Not now").clicked() {
+                        choice = Some(false);
+                    }
+                });
+            });
+
+        match choice {
+            // Handled; show the next one, if several piled up
+            Some(true) => {
+                crash::discard(path);
+                self.crash_report = crash::next_report();
+            }
+            // Kept for the next start
+            Some(false) => self.crash_report = None,
+            None => {}
+        }
+    }
+
+    /// All tabs as thumbnails; choosing one makes it the active tab
+    fn render_overview(&mut self, ui: &mut egui::Ui) {
+        let tiles: Vec<OverviewTile> = self.state.tabs.iter().enumerate()
+            .map(|(index, tab)| OverviewTile {
+                title: tab.title.clone(),
+                detail: self.state.tab_job(index),
+                active: index == self.state.active_tab,
+            })
+            .collect();
+        let chosen = self.overview.show(ui, &tiles, |index, painter, rect| {
+            let kind = match &self.state.tabs[index].tab_type {
+                TabType::Terminal(_) => "Terminal",
+                TabType::Sftp(_) => "SFTP",
+                _ => "Tool",
+            };
+            painter.text(rect.center(), egui::Align2::CENTER_CENTER, kind, egui::FontId::proportional(12.0), egui::Color32::GRAY);
+        });
+        if let Some(index) = chosen {
+            self.state.active_tab = index;
+        }
+    }
+
+    fn render_usage_stats(&mut self, ui: &mut egui::Ui) {
+        if self.usage_stats.is_none() {
+            let mut screen = UsageStatsScreen::new();
+            if let Some(stats) = self.state.usage_stats(None) {
+                screen.set_stats(stats);
+            }
+            self.usage_stats = Some(screen);
+        }
+        let Some(screen) = self.usage_stats.as_mut() else { return };
+
+        let reload = match screen.render(ui) {
+            Some(UsageStatsAction::Refresh(since)) => Some(since),
+            Some(UsageStatsAction::ClearSessions) => {
+                if let Err(e) = self.state.db.clear_session_history() {
+                    self.state.notification_manager.error(format!("Could not clear session history: {}", e));
+                }
+                Some(screen.since())
+            }
+            Some(UsageStatsAction::Close) => {
+                self.usage_stats = None;
+                if let Some(index) = self.state.tabs.iter().position(|tab| matches!(tab.tab_type, TabType::UsageStats)) {
+                    self.state.close_tab(index);
+                }
+                None
+            }
+            None => None,
+        };
+        if let Some(since) = reload {
+            if let Some(stats) = self.state.usage_stats(since.as_deref()) {
+                if let Some(screen) = self.usage_stats.as_mut() {
+                    screen.set_stats(stats);
+                }
+            }
         }
     }
+
+    fn render_transfer_history(&mut self, ui: &mut egui::Ui) {
+        let screen = self.transfer_history.get_or_insert_with(|| {
+            let mut screen = TransferHistoryScreen::new();
+            match self.state.db.list_transfer_history(&screen.filter()) {
+                Ok(records) => screen.set_records(records),
+                Err(e) => self.state.notification_manager.error(format!("Could not read transfer history: {}", e)),
+            }
+            screen
+        });
+
+        let changed = match screen.render(ui) {
+            Some(TransferHistoryAction::Refresh(_)) => Ok(()),
+            Some(TransferHistoryAction::Rerun(record)) => {
+                self.state.rerun_transfer(&record);
+                return;
+            }
+            Some(TransferHistoryAction::Delete(id)) => self.state.db.delete_transfer_record(&id),
+            Some(TransferHistoryAction::Clear) => self.state.db.clear_transfer_history(),
+            Some(TransferHistoryAction::Close) => {
+                self.transfer_history = None;
+                if let Some(index) = self.state.tabs.iter().position(|tab| matches!(tab.tab_type, TabType::TransferHistory)) {
+                    self.state.close_tab(index);
+                }
+                return;
+            }
+            None => return,
+        };
+        let reloaded = changed.and_then(|()| self.state.db.list_transfer_history(&screen.filter()));
+        match reloaded {
+            Ok(records) => screen.set_records(records),
+            Err(e) => self.state.notification_manager.error(format!("Could not update transfer history: {}", e)),
+        }
+    }
+
+    fn render_settings(&mut self, ui: &mut egui::Ui) {
+        let ctx = ui.ctx().clone();
+        let screen = self.settings_screen.get_or_insert_with(|| SettingsScreen::new(self.state.settings.clone()));
+        match screen.render(&ctx, ui) {
+            Some(SettingsAction::Save(settings)) => {
+                if let Err(e) = self.state.update_settings(settings) {
+                    self.state.notification_manager.error(format!("Could not save settings: {}", e));
+                }
+            }
+            Some(SettingsAction::Close) => {
+                self.settings_screen = None;
+                if let Some(index) = self.state.tabs.iter().position(|tab| matches!(tab.tab_type, TabType::Settings)) {
+                    self.state.close_tab(index);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn render_file_viewer(&mut self, ui: &mut egui::Ui) {
+        let index = self.state.active_tab;
+        let Some(tab) = self.state.tabs.get(index) else { return };
+        let Some(screen) = self.state.file_viewers.get_mut(&tab.id) else { return };
+        if let Some(FileViewerAction::Close) = screen.render(ui) {
+            self.state.close_tab(index);
+        }
+    }
+
+    /// Close a tab, asking first when something seems to run in it
+    fn request_close_tab(&mut self, index: usize) {
+        let Some(tab) = self.state.tabs.get(index) else { return };
+        if matches!(tab.tab_type, TabType::Settings) {
+            // Unsaved settings ask first; the answer closes the tab via `SettingsAction::Close`
+            if self.settings_screen.as_mut().map_or(false, |screen| !screen.request_close()) {
+                self.state.active_tab = index;
+                return;
+            }
+            self.settings_screen = None;
+        }
+        match self.state.busy_tab(index) {
+            Some(job) => self.close_confirm = Some((tab.id.clone(), job)),
+            None => self.state.close_tab(index),
+        }
+    }
+
+    fn render_close_tab_dialog(&mut self, ctx: &Context) {
+        let Some((tab_id, job)) = self.close_confirm.clone() else { return };
+
+        egui::Window::new("Close tab?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label("This session seems to be busy:");
+                ui.label(egui::RichText::new(&job).monospace());
+                ui.label("Closing the tab disconnects it and stops what is running.");
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Close anyway").clicked() {
+                        self.close_confirm = None;
+                        if let Some(index) = self.state.tabs.iter().position(|tab| tab.id == tab_id) {
+                            self.state.close_tab(index);
+                        }
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.close_confirm = None;
+                    }
+                });
+            });
+    }
+
+    fn render_exit_dialog(&mut self, ctx: &Context) {
+        let Some(warning) = self.exit_warning.clone() else { return };
+        let busy = self.state.busy_tabs();
+
+        egui::Window::new("Quit TabSSH?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!("Still running: {}.", warning));
+                if !busy.is_empty() {
+                    ui.label("These sessions seem to be busy:");
+                    for job in &busy {
+                        ui.label(egui::RichText::new(format!("  • {}", job)).monospace());
+                    }
+                }
+                ui.label("Quitting will disconnect them.");
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Quit").clicked() {
+                        self.exit_warning = None;
+                        self.state.shutdown();
+                        self.shut_down = true;
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.exit_warning = None;
+                    }
+                });
+            });
+    }
 }
 
 impl eframe::App for TabSshApp {
     fn update(&mut self, ctx: &Context, frame: &mut eframe::Frame) {
+        self.poll_loader(ctx);
+        match &mut self.window {
+            Some(window) => window.update(ctx, frame),
+            None => self.render_skeleton(ctx),
+        }
+        self.profiler.first_paint();
+    }
+
+    /// Clear to nothing so a translucent terminal background shows the desktop
+    fn clear_color(&self, visuals: &egui::Visuals) -> [f32; 4] {
+        if self.transparency.transparent {
+            [0.0; 4]
+        } else {
+            visuals.panel_fill.to_normalized_gamma_f32()
+        }
+    }
+}
+
+impl MainWindow {
+    fn update(&mut self, ctx: &Context, frame: &eframe::Frame) {
+        // Answer scripting requests from the control socket
+        self.state.poll_ipc(self.lock.is_locked());
+        self.state.session_manager.reap();
+        self.state.poll_network();
+        self.state.poll_watches();
+        self.state.poll_sync();
+        self.state.poll_view_reports();
+        if let Some(wait) = self.state.poll_bastions() {
+            ctx.request_repaint_after(wait);
+        }
+        if self.mfa_dialog.is_none() {
+            self.mfa_dialog = self.state.next_mfa_prompt().map(MfaDialog::new);
+        }
+        if self.host_key_dialog.is_none() {
+            if let Some(prompt) = self.state.next_host_key_prompt() {
+                let offered = prompt.question.offered();
+                let history = self.state.db.list_host_key_changes(&offered.host, offered.port).unwrap_or_default();
+                self.host_key_dialog = Some(HostKeyDialog::new(prompt, history));
+            }
+        }
+        let updates_enabled = self.state.settings.check_for_updates;
+        if let Some(wait) = self.updates.poll(&self.state.executor, &self.state.db, updates_enabled) {
+            ctx.request_repaint_after(wait);
+        }
+        self.handle_close_request(ctx);
+        self.apply_blur(frame);
+        // Sessions keep running behind the lock; only the UI is hidden
+        if self.lock.update(ctx, &self.state.db, &self.state.settings) {
+            return;
+        }
+        self.update_quake(ctx);
+
         // Handle keyboard shortcuts
-        if let Some(action) = KeyboardHandler::handle_shortcuts(ctx) {
+        if let Some(action) = KeyboardHandler::handle_shortcuts(ctx, &self.state.settings.keymap) {
             match action {
                 KeyboardAction::NewTab => {
                     log::info!("Newtab");
                 }
-                KeyboardAction::CloseTab => {
-                    if self.state.active_tab < self.state.tabs.len() {
-                        self.state.close_tab(self.state.active_tab);
-                    }
-                }
+                KeyboardAction::CloseTab => self.request_close_tab(self.state.active_tab),
                 KeyboardAction::NextTab => {
                     self.state.next_tab();
                 }
@@ -56,11 +624,19 @@ impl eframe::App for TabSshApp {
                     log::info!("Newconnection");
                 }
                 KeyboardAction::OpenSettings => {
-                    log::info!("Opensettings");
+                    self.state.open_tool_tab(TabType::Settings, "Settings");
                 }
                 KeyboardAction::Quit => {
                     ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                 }
+                KeyboardAction::TabOverview => {
+                    self.overview.toggle(self.state.active_tab);
+                }
+                KeyboardAction::UsageStats => {
+                    // Reloaded when shown, so the figures are current
+                    self.usage_stats = None;
+                    self.state.open_tool_tab(TabType::UsageStats, "Usage Statistics");
+                }
                 _ => {}
             }
         }
@@ -72,9 +648,17 @@ impl eframe::App for TabSshApp {
         });
         
         // Top panel - Tabs
+        self.state.sync_tab_chrome();
         egui::TopBottomPanel::top("tabs").show(ctx, |ui| {
-            if let Some(action) = self.tab_bar.render(ui) {
-                // Handle tab actions
+            match self.tab_bar.render(ui, &self.state.tab_chrome) {
+                Some(TabBarAction::Close(id)) => {
+                    let index = self.state.tabs.iter().position(|tab| tab.id == id.to_string());
+                    if let Some(index) = index {
+                        self.request_close_tab(index);
+                    }
+                }
+                Some(action) => self.state.apply_tab_bar_action(action),
+                None => {}
             }
         });
         
@@ -84,19 +668,40 @@ impl eframe::App for TabSshApp {
         });
         
         // Central panel - Main content
-        egui::CentralPanel::default().show(ctx, |ui| {
+        egui::CentralPanel::default().frame(self.terminal_frame(ctx)).show(ctx, |ui| {
             if self.state.tabs.is_empty() {
                 ui.vertical_centered(|ui| {
                     ui.add_space(100.0);
                     ui.heading("Welcome to TabSSH Desktop");
                     ui.label("Press Ctrl+N to create a new connection");
                 });
+            } else if self.overview.is_open() {
+                self.render_overview(ui);
             } else {
                 // Render active tab content
-                ui.label("Tab content here");
+                match self.state.tabs.get(self.state.active_tab).map(|tab| &tab.tab_type) {
+                    Some(TabType::UsageStats) => self.render_usage_stats(ui),
+                    Some(TabType::FileViewer(..)) => self.render_file_viewer(ui),
+                    Some(TabType::TransferHistory) => self.render_transfer_history(ui),
+                    Some(TabType::Settings) => self.render_settings(ui),
+                    _ => {
+                        ui.label("Tab content here");
+                    }
+                }
             }
         });
         
+        self.render_exit_dialog(ctx);
+        self.render_close_tab_dialog(ctx);
+        self.render_update_notice(ctx);
+        self.render_crash_dialog(ctx);
+        if self.mfa_dialog.as_mut().is_some_and(|dialog| !dialog.render(ctx)) {
+            self.mfa_dialog = None;
+        }
+        if self.host_key_dialog.as_mut().is_some_and(|dialog| !dialog.render(ctx)) {
+            self.host_key_dialog = None;
+        }
+
         // Render notifications
         self.state.notification_manager.render(ctx);
     }
@@ -0,0 +1,57 @@
+//! tabsshctl - command-line control for a running TabSSH instance
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use tabssh::ipc::IpcClient;
+
+fn usage() -> ! {
+    eprintln!("Usage: tabsshctl <command> [args]");
+    eprintln!();
+    eprintln!("Commands:");
+    eprintln!("  status                               Show application status");
+    eprintln!("  list                                 List open sessions");
+    eprintln!("  open <profile>                       Open a saved connection by name");
+    eprintln!("  send <session> <text>                Send text to a terminal session");
+    eprintln!("  upload <session> <local> <remote>    Queue an upload");
+    eprintln!("  download <session> <remote> <local>  Queue a download");
+    eprintln!("  call <method> [json-params]          Send a raw JSON-RPC request");
+    std::process::exit(2);
+}
+
+fn request(args: &[String]) -> Result<(String, Value)> {
+    let arg = |i: usize| args.get(i).cloned().unwrap_or_else(|| usage());
+
+    let request = match args.first().map(String::as_str) {
+        Some("status") => ("status".to_string(), Value::Null),
+        Some("list") => ("session.list".to_string(), Value::Null),
+        Some("open") => ("session.open".to_string(), json!({ "profile": arg(1) })),
+        Some("send") => ("session.send".to_string(), json!({ "session": arg(1), "text": arg(2) })),
+        Some("upload") => ("transfer.start".to_string(), json!({
+            "session": arg(1), "direction": "upload", "local": arg(2), "remote": arg(3),
+        })),
+        Some("download") => ("transfer.start".to_string(), json!({
+            "session": arg(1), "direction": "download", "remote": arg(2), "local": arg(3),
+        })),
+        Some("call") => {
+            let params = match args.get(2) {
+                Some(raw) => serde_json::from_str(raw).map_err(|e| anyhow!("Invalid params: {}", e))?,
+                None => Value::Null,
+            };
+            (arg(1), params)
+        }
+        _ => usage(),
+    };
+
+    Ok(request)
+}
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (method, params) = request(&args)?;
+
+    let mut client = IpcClient::connect()?;
+    let result = client.call(&method, params)?;
+
+    println!("{}", serde_json::to_string_pretty(&result)?);
+    Ok(())
+}
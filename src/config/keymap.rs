@@ -0,0 +1,240 @@
+//! Application keybindings and the built-in presets
+//!
+//! Bindings are kept as text like `Ctrl+Shift+Tab` so they read well in an
+//! exported settings profile; `parse` turns one into a `Chord`, accepting
+//! only keys egui can report, and the UI maps the key name onto egui's key. Terminal input is not covered
+//! here, only the shortcuts handled by the window.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Actions that can be bound, as (id, label), in the order settings lists them
+pub const ACTIONS: [(&str, &str); 22] = [
+    ("new_tab", "New tab"),
+    ("close_tab", "Close tab"),
+    ("next_tab", "Next tab"),
+    ("previous_tab", "Previous tab"),
+    ("tab_1", "Go to tab 1"),
+    ("tab_2", "Go to tab 2"),
+    ("tab_3", "Go to tab 3"),
+    ("tab_4", "Go to tab 4"),
+    ("tab_5", "Go to tab 5"),
+    ("tab_6", "Go to tab 6"),
+    ("tab_7", "Go to tab 7"),
+    ("tab_8", "Go to tab 8"),
+    ("tab_9", "Go to tab 9"),
+    ("new_connection", "New connection"),
+    ("open_settings", "Settings"),
+    ("quit", "Quit"),
+    ("find", "Find"),
+    ("font_larger", "Larger font"),
+    ("font_smaller", "Smaller font"),
+    ("font_reset", "Reset font size"),
+    ("tab_overview", "Tab overview"),
+    ("usage_stats", "Usage statistics"),
+];
+
+/// Name of the preset new installs start with
+pub const DEFAULT_PRESET: &str = "Default";
+/// Shown once bindings were edited by hand
+pub const CUSTOM_PRESET: &str = "Custom";
+
+/// Built-in presets as (name, description)
+pub const PRESETS: [(&str, &str); 3] = [
+    (DEFAULT_PRESET, "Ctrl shortcuts, as on Linux and Windows"),
+    ("macOS defaults", "Cmd shortcuts like Terminal and iTerm2"),
+    ("Vim-style", "Alt with h/l for tabs, leaving every Ctrl key to the terminal"),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Keymap {
+    /// Preset the bindings came from, or `CUSTOM_PRESET`
+    pub preset: String,
+    /// Action id to shortcut; unbound actions are left out
+    pub bindings: BTreeMap<String, String>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::preset(DEFAULT_PRESET).expect("default preset exists")
+    }
+}
+
+impl Keymap {
+    /// One of the built-in `PRESETS`
+    pub fn preset(name: &str) -> Option<Self> {
+        let (modifier, next, previous, new_tab, close, find): (&str, &str, &str, &str, &str, &str) = match name {
+            DEFAULT_PRESET => ("Ctrl", "Ctrl+Tab", "Ctrl+Shift+Tab", "Ctrl+T", "Ctrl+W", "Ctrl+F"),
+            "macOS defaults" => ("Cmd", "Cmd+Shift+]", "Cmd+Shift+[", "Cmd+T", "Cmd+W", "Cmd+F"),
+            "Vim-style" => ("Alt", "Alt+L", "Alt+H", "Alt+T", "Alt+Q", "Alt+F"),
+            _ => return None,
+        };
+        // Tab numbers use Alt except on macOS, where Cmd+N is the convention
+        let tabs = if modifier == "Cmd" { "Cmd" } else { "Alt" };
+
+        let mut bindings = BTreeMap::new();
+        bindings.insert("new_tab".to_string(), new_tab.to_string());
+        bindings.insert("close_tab".to_string(), close.to_string());
+        bindings.insert("next_tab".to_string(), next.to_string());
+        bindings.insert("previous_tab".to_string(), previous.to_string());
+        for n in 1..=9 {
+            bindings.insert(format!("tab_{}", n), format!("{}+{}", tabs, n));
+        }
+        bindings.insert("new_connection".to_string(), format!("{}+N", modifier));
+        bindings.insert("open_settings".to_string(), format!("{}+,", modifier));
+        bindings.insert("quit".to_string(), format!("{}+Q", if modifier == "Alt" { "Ctrl+Shift" } else { modifier }));
+        bindings.insert("find".to_string(), find.to_string());
+        bindings.insert("font_larger".to_string(), format!("{}++", modifier));
+        bindings.insert("font_smaller".to_string(), format!("{}+-", modifier));
+        bindings.insert("font_reset".to_string(), format!("{}+0", modifier));
+        bindings.insert("tab_overview".to_string(), format!("{}+Shift+O", modifier));
+        bindings.insert("usage_stats".to_string(), format!("{}+Shift+S", modifier));
+        Some(Self { preset: name.to_string(), bindings })
+    }
+
+    pub fn binding(&self, action: &str) -> Option<&str> {
+        self.bindings.get(action).map(String::as_str).filter(|spec| !spec.trim().is_empty())
+    }
+
+    /// Bind `action`, or unbind it with an empty `spec`; the keymap is
+    /// custom from then on
+    pub fn set(&mut self, action: &str, spec: &str) {
+        let spec = spec.trim();
+        if spec.is_empty() {
+            self.bindings.remove(action);
+        } else {
+            self.bindings.insert(action.to_string(), spec.to_string());
+        }
+        self.preset = CUSTOM_PRESET.to_string();
+    }
+
+    /// Bindings that don't parse, and shortcuts bound to more than one
+    /// action, as messages for the settings screen
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut seen: Vec<(Chord, &str)> = Vec::new();
+        for (action, label) in ACTIONS {
+            let Some(spec) = self.binding(action) else { continue };
+            match parse(spec) {
+                None => problems.push(format!("{}: '{}' is not a shortcut", label, spec)),
+                Some(chord) => match seen.iter().find(|(other, _)| *other == chord) {
+                    Some((_, other)) => problems.push(format!("{} is used for both {} and {}", spec, other, label)),
+                    None => seen.push((chord, label)),
+                },
+            }
+        }
+        problems
+    }
+}
+
+/// A parsed shortcut: modifiers plus one key, named as egui names keys
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chord {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    /// Cmd; such shortcuts only work on macOS
+    pub command: bool,
+    pub key: String,
+}
+
+/// Parse `Ctrl+Shift+T`, `Cmd+,` or `Alt++`; modifiers are case-insensitive
+/// and the key may be given as its symbol
+pub fn parse(spec: &str) -> Option<Chord> {
+    let spec = spec.trim();
+    // A trailing "++" means the plus key
+    let (mods, key) = match spec.strip_suffix("++") {
+        Some(mods) => (mods, "+"),
+        None => match spec.rfind('+') {
+            Some(i) => (&spec[..i], &spec[i + 1..]),
+            None => ("", spec),
+        },
+    };
+
+    let key = key_name(key.trim()).filter(|name| egui::Key::from_name(name).is_some())?;
+    let mut chord = Chord { ctrl: false, shift: false, alt: false, command: false, key };
+    for modifier in mods.split('+').map(str::trim).filter(|m| !m.is_empty()) {
+        match modifier.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => chord.ctrl = true,
+            "shift" => chord.shift = true,
+            "alt" | "option" | "opt" => chord.alt = true,
+            "cmd" | "command" | "super" | "win" | "meta" => chord.command = true,
+            _ => return None,
+        }
+    }
+    Some(chord)
+}
+
+/// egui's name for a key typed as a symbol, letter or name
+fn key_name(key: &str) -> Option<String> {
+    let symbol = match key {
+        "" => return None,
+        "+" => "Plus",
+        "-" => "Minus",
+        "=" => "Equals",
+        "," => "Comma",
+        "." => "Period",
+        "\\" => "Backslash",
+        ";" => "Semicolon",
+        "[" => "OpenBracket",
+        "]" => "CloseBracket",
+        "`" => "Backtick",
+        _ => "",
+    };
+    if !symbol.is_empty() {
+        return Some(symbol.to_string());
+    }
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphanumeric() => Some(c.to_ascii_uppercase().to_string()),
+        _ => {
+            let mut name: Vec<char> = key.to_ascii_lowercase().chars().collect();
+            name[0] = name[0].to_ascii_uppercase();
+            Some(match name.iter().collect::<String>().as_str() {
+                "Esc" => "Escape".to_string(),
+                "Return" => "Enter".to_string(),
+                "Pgup" | "Pageup" => "PageUp".to_string(),
+                "Pgdn" | "Pagedown" => "PageDown".to_string(),
+                other => other.to_string(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(ctrl: bool, shift: bool, alt: bool, command: bool, key: &str) -> Chord {
+        Chord { ctrl, shift, alt, command, key: key.to_string() }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("Ctrl+Shift+Tab"), Some(chord(true, true, false, false, "Tab")));
+        assert_eq!(parse("cmd+,"), Some(chord(false, false, false, true, "Comma")));
+        assert_eq!(parse("Ctrl++"), Some(chord(true, false, false, false, "Plus")));
+        assert_eq!(parse("Alt+t"), Some(chord(false, false, true, false, "T")));
+        assert_eq!(parse("F5"), Some(chord(false, false, false, false, "F5")));
+        assert_eq!(parse("Hyper+T"), None);
+        assert_eq!(parse("Ctrl+"), None);
+        // egui has no key for these
+        assert_eq!(parse("Alt+/"), None);
+        assert_eq!(parse("Ctrl+Pause"), None);
+    }
+
+    #[test]
+    fn test_presets_are_clean() {
+        for (name, _) in PRESETS {
+            let keymap = Keymap::preset(name).unwrap();
+            assert_eq!(keymap.problems(), Vec::<String>::new(), "{}", name);
+            assert_eq!(keymap.bindings.len(), ACTIONS.len(), "{}", name);
+        }
+
+        let mut keymap = Keymap::default();
+        keymap.set("find", "Ctrl+T");
+        assert_eq!(keymap.preset, CUSTOM_PRESET);
+        assert_eq!(keymap.problems(), vec!["Ctrl+T is used for both New tab and Find".to_string()]);
+    }
+}
@@ -1,5 +1,6 @@
 //! Configuration module - settings and themes
 
+pub mod keymap;
 pub mod themes;
 
 pub use themes::Theme;
@@ -3,9 +3,10 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
+use crate::storage::database::Database;
 
 /// Terminal color theme
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Theme {
     pub name: String,
     pub background: String,
@@ -45,6 +46,74 @@ impl Theme {
         Some((r, g, b))
     }
 
+    /// Format RGB as the `#rrggbb` form themes store
+    pub fn format_color((r, g, b): (u8, u8, u8)) -> String {
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+
+    /// Every color slot with its label, in editor order: the basics, then
+    /// the 16 ANSI colors
+    pub fn colors_mut(&mut self) -> [(&'static str, &mut String); 20] {
+        [
+            ("Background", &mut self.background),
+            ("Foreground", &mut self.foreground),
+            ("Cursor", &mut self.cursor),
+            ("Selection", &mut self.selection),
+            ("Black", &mut self.black),
+            ("Red", &mut self.red),
+            ("Green", &mut self.green),
+            ("Yellow", &mut self.yellow),
+            ("Blue", &mut self.blue),
+            ("Magenta", &mut self.magenta),
+            ("Cyan", &mut self.cyan),
+            ("White", &mut self.white),
+            ("Bright Black", &mut self.bright_black),
+            ("Bright Red", &mut self.bright_red),
+            ("Bright Green", &mut self.bright_green),
+            ("Bright Yellow", &mut self.bright_yellow),
+            ("Bright Blue", &mut self.bright_blue),
+            ("Bright Magenta", &mut self.bright_magenta),
+            ("Bright Cyan", &mut self.bright_cyan),
+            ("Bright White", &mut self.bright_white),
+        ]
+    }
+
+    /// Read-only `colors_mut`
+    pub fn colors(&self) -> [(&'static str, &str); 20] {
+        [
+            ("Background", &self.background),
+            ("Foreground", &self.foreground),
+            ("Cursor", &self.cursor),
+            ("Selection", &self.selection),
+            ("Black", &self.black),
+            ("Red", &self.red),
+            ("Green", &self.green),
+            ("Yellow", &self.yellow),
+            ("Blue", &self.blue),
+            ("Magenta", &self.magenta),
+            ("Cyan", &self.cyan),
+            ("White", &self.white),
+            ("Bright Black", &self.bright_black),
+            ("Bright Red", &self.bright_red),
+            ("Bright Green", &self.bright_green),
+            ("Bright Yellow", &self.bright_yellow),
+            ("Bright Blue", &self.bright_blue),
+            ("Bright Magenta", &self.bright_magenta),
+            ("Bright Cyan", &self.bright_cyan),
+            ("Bright White", &self.bright_white),
+        ]
+    }
+
+    /// The 16 ANSI colors, normal then bright
+    pub fn ansi(&self) -> [&str; 16] {
+        [
+            &self.black, &self.red, &self.green, &self.yellow,
+            &self.blue, &self.magenta, &self.cyan, &self.white,
+            &self.bright_black, &self.bright_red, &self.bright_green, &self.bright_yellow,
+            &self.bright_blue, &self.bright_magenta, &self.bright_cyan, &self.bright_white,
+        ]
+    }
+
     /// Load theme from JSON file
     pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
         let content = std::fs::read_to_string(path)?;
@@ -90,6 +159,8 @@ impl Theme {
 /// Theme manager
 pub struct ThemeManager {
     themes: Vec<Theme>,
+    /// The first `builtin` themes ship with the app; the rest are custom
+    builtin: usize,
     current_theme: String,
 }
 
@@ -97,6 +168,7 @@ impl ThemeManager {
     pub fn new() -> Self {
         let mut manager = Self {
             themes: Vec::new(),
+            builtin: 0,
             current_theme: "Default Dark".to_string(),
         };
         
@@ -105,6 +177,7 @@ impl ThemeManager {
         
         // Load bundled themes
         manager.load_bundled_themes();
+        manager.builtin = manager.themes.len();
         
         manager
     }
@@ -147,6 +220,40 @@ impl ThemeManager {
     pub fn add_custom_theme(&mut self, theme: Theme) {
         self.themes.push(theme);
     }
+
+    /// Add the custom themes saved in the database
+    pub fn load_custom_themes(&mut self, db: &Database) {
+        match db.list_custom_themes() {
+            Ok(themes) => {
+                for custom in themes {
+                    self.set_custom_theme(custom.theme);
+                }
+            }
+            Err(e) => log::warn!("Could not load custom themes: {}", e),
+        }
+    }
+
+    /// Add a custom theme, or replace the custom theme of the same name
+    pub fn set_custom_theme(&mut self, theme: Theme) {
+        match self.themes[self.builtin..].iter_mut().find(|t| t.name == theme.name) {
+            Some(existing) => *existing = theme,
+            None => self.themes.push(theme),
+        }
+    }
+
+    /// Remove a custom theme; built-in themes stay
+    pub fn remove_custom_theme(&mut self, name: &str) {
+        let builtin = self.builtin;
+        let mut index = 0;
+        self.themes.retain(|t| {
+            index += 1;
+            index <= builtin || t.name != name
+        });
+    }
+
+    pub fn is_builtin(&self, name: &str) -> bool {
+        self.themes[..self.builtin].iter().any(|t| t.name == name)
+    }
 }
 
 impl Default for ThemeManager {
@@ -167,6 +274,26 @@ mod tests {
         assert_eq!(Theme::parse_color("invalid"),None);
     }
 
+    #[test]
+    fn test_custom_themes() {
+        let mut manager = ThemeManager::new();
+        let count = manager.list_themes().len();
+
+        let mut theme = Theme::default_dark();
+        theme.name = "Mine".to_string();
+        manager.set_custom_theme(theme.clone());
+        theme.background = "#000000".to_string();
+        manager.set_custom_theme(theme);
+        assert_eq!(manager.list_themes().len(), count + 1);
+        assert_eq!(manager.get_theme("Mine").unwrap().background, "#000000");
+
+        // Built-in themes cannot be removed
+        manager.remove_custom_theme("Default Dark");
+        manager.remove_custom_theme("Mine");
+        assert_eq!(manager.list_themes().len(), count);
+        assert!(manager.is_builtin("Default Dark"));
+    }
+
     #[test]
     fn test_theme_manager() {
         let manager = ThemeManager::new();
@@ -0,0 +1,236 @@
+//! Inventory of stored secrets
+//!
+//! Everything sensitive the app keeps, where it lives and when it was last
+//! used, so it can be reviewed and revoked from one place. The keychain
+//! cannot be listed portably, so entries are probed under the services the
+//! app writes them to.
+
+use super::keychain::{KeychainManager, PASSPHRASE_SERVICE, PASSWORD_SERVICE, TOTP_SERVICE};
+use crate::platform::PlatformManager;
+use crate::storage::sync::{self, SyncConfig, SyncTarget};
+use crate::storage::Database;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+
+/// Subdirectory of the data directory holding session logs
+pub const SESSION_LOG_DIR: &str = "session-logs";
+
+/// What kind of secret an item is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretKind {
+    Password,
+    KeyPassphrase,
+    TotpSecret,
+    /// Private key imported into the app
+    PrivateKey,
+    SyncPassword,
+    /// Recorded terminal output, which may contain anything typed
+    SessionLog,
+}
+
+impl SecretKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SecretKind::Password => "Password",
+            SecretKind::KeyPassphrase => "Key passphrase",
+            SecretKind::TotpSecret => "TOTP secret",
+            SecretKind::PrivateKey => "Private key",
+            SecretKind::SyncPassword => "Sync password",
+            SecretKind::SessionLog => "Session log",
+        }
+    }
+}
+
+/// Where a secret is kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SecretLocation {
+    /// Readable by anyone with access to the user's files
+    Plaintext,
+    EncryptedDatabase,
+    /// OS keychain / secret service
+    Keychain,
+}
+
+impl SecretLocation {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SecretLocation::Plaintext => "Plaintext file",
+            SecretLocation::EncryptedDatabase => "Encrypted database",
+            SecretLocation::Keychain => "OS keychain",
+        }
+    }
+}
+
+/// How to remove a secret
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretHandle {
+    Keychain { service: String, account: String },
+    /// Row in the ssh_keys table
+    SshKey(String),
+    File(PathBuf),
+}
+
+/// One stored secret
+#[derive(Debug, Clone)]
+pub struct StoredSecret {
+    pub kind: SecretKind,
+    /// What it belongs to, e.g. the connection name
+    pub name: String,
+    pub location: SecretLocation,
+    pub last_used: Option<DateTime<Utc>>,
+    pub handle: SecretHandle,
+}
+
+/// Collect every stored secret, least protected and most recently used first
+pub fn collect(db: &Database) -> Result<Vec<StoredSecret>> {
+    let keychain = KeychainManager::new();
+    let mut secrets = Vec::new();
+
+    let mut stmt = db.connection().prepare(
+        "SELECT id, name, username, host, last_connected FROM connections ORDER BY name COLLATE NOCASE"
+    )?;
+    let connections = stmt
+        .query_map([], |row| Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, Option<String>>(4)?,
+        )))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (id, name, user, host, last_connected) in connections {
+        let last_used = last_connected.as_deref().and_then(parse_time);
+        for (kind, service) in [
+            (SecretKind::Password, PASSWORD_SERVICE),
+            (SecretKind::KeyPassphrase, PASSPHRASE_SERVICE),
+            (SecretKind::TotpSecret, TOTP_SERVICE),
+        ] {
+            if keychain.has_password(service, &id) {
+                secrets.push(StoredSecret {
+                    kind,
+                    name: format!("{} ({}@{})", name, user, host),
+                    location: SecretLocation::Keychain,
+                    last_used,
+                    handle: SecretHandle::Keychain { service: service.to_string(), account: id.clone() },
+                });
+            }
+        }
+    }
+
+    // A key was last used when a connection using it last connected
+    let mut stmt = db.connection().prepare(
+        "SELECT k.id, k.name, k.fingerprint, MAX(c.last_connected)
+         FROM ssh_keys k LEFT JOIN connections c ON c.key_id = k.id
+         WHERE k.encrypted_private_key IS NOT NULL
+         GROUP BY k.id"
+    )?;
+    let keys = stmt.query_map([], |row| {
+        let fingerprint: String = row.get(2)?;
+        Ok(StoredSecret {
+            kind: SecretKind::PrivateKey,
+            name: format!("{} ({})", row.get::<_, String>(1)?, fingerprint),
+            location: SecretLocation::EncryptedDatabase,
+            last_used: row.get::<_, Option<String>>(3)?.as_deref().and_then(parse_time),
+            handle: SecretHandle::SshKey(row.get(0)?),
+        })
+    })?;
+    for key in keys {
+        secrets.push(key?);
+    }
+
+    if let Some(SyncTarget::WebDav { url, .. }) = SyncConfig::load(db).target {
+        if keychain.has_password(sync::KEYCHAIN_SERVICE, &url) {
+            secrets.push(StoredSecret {
+                kind: SecretKind::SyncPassword,
+                name: url.clone(),
+                location: SecretLocation::Keychain,
+                last_used: None,
+                handle: SecretHandle::Keychain { service: sync::KEYCHAIN_SERVICE.to_string(), account: url },
+            });
+        }
+    }
+
+    secrets.extend(session_logs());
+    sort_for_review(&mut secrets);
+    Ok(secrets)
+}
+
+/// Log files under the session log directory; last used is when last written
+fn session_logs() -> Vec<StoredSecret> {
+    let Some(dir) = PlatformManager::get_data_directory().map(|d| d.join(SESSION_LOG_DIR)) else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(&dir) else { return Vec::new() };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().map_or(false, |t| t.is_file()))
+        .map(|entry| StoredSecret {
+            kind: SecretKind::SessionLog,
+            name: entry.file_name().to_string_lossy().into_owned(),
+            location: SecretLocation::Plaintext,
+            last_used: entry.metadata().and_then(|m| m.modified()).ok().map(DateTime::<Utc>::from),
+            handle: SecretHandle::File(entry.path()),
+        })
+        .collect()
+}
+
+fn parse_time(text: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(text).ok().map(|t| t.with_timezone(&Utc))
+}
+
+/// Plaintext first, then encrypted, then keychain; most recently used first
+/// within each, never-used last
+fn sort_for_review(secrets: &mut [StoredSecret]) {
+    secrets.sort_by(|a, b| {
+        a.location.cmp(&b.location)
+            .then_with(|| b.last_used.cmp(&a.last_used))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+/// Delete a secret for good
+pub fn revoke(db: &Database, secret: &StoredSecret) -> Result<()> {
+    match &secret.handle {
+        SecretHandle::Keychain { service, account } => KeychainManager::new()
+            .delete_password(service, account)
+            .with_context(|| format!("Could not remove {} from the keychain", secret.kind.label().to_lowercase()))?,
+        SecretHandle::SshKey(id) => {
+            let conn = db.connection();
+            conn.execute("UPDATE connections SET key_id = NULL WHERE key_id = ?1", [id])?;
+            conn.execute("DELETE FROM ssh_keys WHERE id = ?1", [id])?;
+        }
+        SecretHandle::File(path) => std::fs::remove_file(path)
+            .with_context(|| format!("Could not delete {}", path.display()))?,
+    }
+    log::info!("Revoked {} for {}", secret.kind.label().to_lowercase(), secret.name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret(name: &str, location: SecretLocation, last_used: Option<&str>) -> StoredSecret {
+        StoredSecret {
+            kind: SecretKind::Password,
+            name: name.to_string(),
+            location,
+            last_used: last_used.and_then(parse_time),
+            handle: SecretHandle::File(PathBuf::from(name)),
+        }
+    }
+
+    #[test]
+    fn test_sort_for_review() {
+        let mut secrets = vec![
+            secret("keychain", SecretLocation::Keychain, Some("2024-05-01T10:00:00Z")),
+            secret("never", SecretLocation::Plaintext, None),
+            secret("recent", SecretLocation::Plaintext, Some("2024-05-02T10:00:00Z")),
+            secret("db", SecretLocation::EncryptedDatabase, None),
+        ];
+        sort_for_review(&mut secrets);
+        let names: Vec<&str> = secrets.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["recent", "never", "db", "keychain"]);
+    }
+}
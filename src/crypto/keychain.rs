@@ -1,6 +1,16 @@
 //! OS keychain integration
+//!
+//! In portable mode secrets go to the encrypted vault instead; see `vault`.
 
 use anyhow::Result;
+use crate::platform::portable;
+use super::vault;
+
+/// Keychain services for per-connection secrets; the account is the
+/// connection id
+pub const PASSWORD_SERVICE: &str = "tabssh-password";
+pub const PASSPHRASE_SERVICE: &str = "tabssh-passphrase";
+pub const TOTP_SERVICE: &str = "tabssh-totp";
 
 pub struct KeychainManager;
 
@@ -8,30 +18,56 @@ impl KeychainManager {
     pub fn new() -> Self {
         Self
     }
+
+    /// Whether an entry exists, without handing out the secret
+    pub fn has_password(&self, service: &str, account: &str) -> bool {
+        self.get_password(service, account).is_ok()
+    }
+
+    pub fn store_password(&self, service: &str, account: &str, password: &str) -> Result<()> {
+        if portable::is_portable() {
+            return vault::store(service, account, password);
+        }
+        self.os_store_password(service, account, password)
+    }
+
+    pub fn get_password(&self, service: &str, account: &str) -> Result<String> {
+        if portable::is_portable() {
+            return vault::get(service, account);
+        }
+        self.os_get_password(service, account)
+    }
+
+    pub fn delete_password(&self, service: &str, account: &str) -> Result<()> {
+        if portable::is_portable() {
+            return vault::delete(service, account);
+        }
+        self.os_delete_password(service, account)
+    }
     
     #[cfg(target_os = "macos")]
-    pub fn store_password(&self, service: &str, account: &str, password: &str) -> Result<()> {
+    fn os_store_password(&self, service: &str, account: &str, password: &str) -> Result<()> {
         use security_framework::passwords::*;
         set_generic_password(service, account, password.as_bytes())?;
         Ok(())
     }
     
     #[cfg(target_os = "macos")]
-    pub fn get_password(&self, service: &str, account: &str) -> Result<String> {
+    fn os_get_password(&self, service: &str, account: &str) -> Result<String> {
         use security_framework::passwords::*;
         let (password, _) = find_generic_password(service, account)?;
         Ok(String::from_utf8(password.to_vec())?)
     }
     
     #[cfg(target_os = "macos")]
-    pub fn delete_password(&self, service: &str, account: &str) -> Result<()> {
+    fn os_delete_password(&self, service: &str, account: &str) -> Result<()> {
         use security_framework::passwords::*;
         delete_generic_password(service, account)?;
         Ok(())
     }
     
     #[cfg(target_os = "linux")]
-    pub fn store_password(&self, service: &str, account: &str, password: &str) -> Result<()> {
+    fn os_store_password(&self, service: &str, account: &str, password: &str) -> Result<()> {
         use keyring::Entry;
         let entry = Entry::new(service, account)?;
         entry.set_password(password)?;
@@ -39,14 +75,14 @@ impl KeychainManager {
     }
     
     #[cfg(target_os = "linux")]
-    pub fn get_password(&self, service: &str, account: &str) -> Result<String> {
+    fn os_get_password(&self, service: &str, account: &str) -> Result<String> {
         use keyring::Entry;
         let entry = Entry::new(service, account)?;
         Ok(entry.get_password()?)
     }
     
     #[cfg(target_os = "linux")]
-    pub fn delete_password(&self, service: &str, account: &str) -> Result<()> {
+    fn os_delete_password(&self, service: &str, account: &str) -> Result<()> {
         use keyring::Entry;
         let entry = Entry::new(service, account)?;
         entry.delete_password()?;
@@ -54,7 +90,7 @@ impl KeychainManager {
     }
     
     #[cfg(target_os = "windows")]
-    pub fn store_password(&self, service: &str, account: &str, password: &str) -> Result<()> {
+    fn os_store_password(&self, service: &str, account: &str, password: &str) -> Result<()> {
         use keyring::Entry;
         let entry = Entry::new(service, account)?;
         entry.set_password(password)?;
@@ -62,14 +98,14 @@ impl KeychainManager {
     }
     
     #[cfg(target_os = "windows")]
-    pub fn get_password(&self, service: &str, account: &str) -> Result<String> {
+    fn os_get_password(&self, service: &str, account: &str) -> Result<String> {
         use keyring::Entry;
         let entry = Entry::new(service, account)?;
         Ok(entry.get_password()?)
     }
     
     #[cfg(target_os = "windows")]
-    pub fn delete_password(&self, service: &str, account: &str) -> Result<()> {
+    fn os_delete_password(&self, service: &str, account: &str) -> Result<()> {
         use keyring::Entry;
         let entry = Entry::new(service, account)?;
         entry.delete_credential()?;
@@ -77,7 +113,7 @@ impl KeychainManager {
     }
     
     #[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
-    pub fn store_password(&self, service: &str, account: &str, password: &str) -> Result<()> {
+    fn os_store_password(&self, service: &str, account: &str, password: &str) -> Result<()> {
         use keyring::Entry;
         let entry = Entry::new(service, account)?;
         entry.set_password(password)?;
@@ -85,14 +121,14 @@ impl KeychainManager {
     }
     
     #[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
-    pub fn get_password(&self, service: &str, account: &str) -> Result<String> {
+    fn os_get_password(&self, service: &str, account: &str) -> Result<String> {
         use keyring::Entry;
         let entry = Entry::new(service, account)?;
         Ok(entry.get_password()?)
     }
     
     #[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
-    pub fn delete_password(&self, service: &str, account: &str) -> Result<()> {
+    fn os_delete_password(&self, service: &str, account: &str) -> Result<()> {
         use keyring::Entry;
         let entry = Entry::new(service, account)?;
         entry.delete_credential()?;
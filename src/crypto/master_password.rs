@@ -0,0 +1,63 @@
+//! Master password for the lock screen
+//!
+//! Only an Argon2 hash is stored, in the settings table. Without a master
+//! password the app can still lock, but then only biometrics unlock it.
+//! In portable mode the master password also holds the vault key, so
+//! changing it re-encrypts the vault.
+
+use crate::platform::portable;
+use crate::storage::Database;
+use super::vault;
+use anyhow::{anyhow, Result};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+
+const SETTINGS_KEY: &str = "master_password";
+
+/// Whether a master password has been set
+pub fn is_set(db: &Database) -> bool {
+    stored_hash(db).is_some()
+}
+
+/// Set or replace the master password
+pub fn set(db: &Database, password: &str) -> Result<()> {
+    if password.is_empty() {
+        return Err(anyhow!("The master password cannot be empty"));
+    }
+    if portable::is_portable() {
+        vault::rekey(password)?;
+    }
+    let salt = SaltString::generate(&mut rand_core::OsRng);
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow!("Could not hash the master password: {}", e))?
+        .to_string();
+    db.connection().execute(
+        "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
+        [SETTINGS_KEY, &hash],
+    )?;
+    Ok(())
+}
+
+/// Remove the master password
+pub fn clear(db: &Database) -> Result<()> {
+    if portable::is_portable() && !vault::is_empty() {
+        return Err(anyhow!("The master password protects saved secrets in portable mode and cannot be removed"));
+    }
+    db.connection().execute("DELETE FROM settings WHERE key = ?1", [SETTINGS_KEY])?;
+    Ok(())
+}
+
+/// Check `password` against the stored hash; false when none is set
+pub fn verify(db: &Database, password: &str) -> bool {
+    let Some(hash) = stored_hash(db) else { return false };
+    PasswordHash::new(&hash)
+        .map(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+        .unwrap_or(false)
+}
+
+fn stored_hash(db: &Database) -> Option<String> {
+    db.connection()
+        .query_row("SELECT value FROM settings WHERE key = ?1", [SETTINGS_KEY], |row| row.get(0))
+        .ok()
+}
@@ -1,5 +1,8 @@
 //! Cryptography and key management
 
+pub mod audit;
 pub mod keychain;
+pub mod master_password;
+pub mod vault;
 
 pub use keychain::KeychainManager;
@@ -0,0 +1,231 @@
+//! Encrypted secret vault for portable mode
+//!
+//! A portable copy can't lean on the keychain of whichever machine it runs
+//! on, so saved passwords, passphrases and TOTP secrets go into `vault.json`
+//! in the portable directory instead. Each secret is sealed with
+//! XChaCha20-Poly1305 under a key derived from the master password with
+//! Argon2. The key is only held in memory, from unlocking until exit; while
+//! the vault is locked secrets can be neither read nor saved.
+
+use anyhow::{anyhow, Context, Result};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use crate::platform::portable;
+
+const VAULT_FILE: &str = "vault.json";
+
+type VaultKey = [u8; 32];
+
+/// Set while unlocked
+static KEY: Mutex<Option<VaultKey>> = Mutex::new(None);
+
+#[derive(Default, Serialize, Deserialize)]
+struct VaultFile {
+    /// Argon2 salt, base64; empty until first unlocked
+    #[serde(default)]
+    salt: String,
+    #[serde(default)]
+    entries: Vec<VaultEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct VaultEntry {
+    service: String,
+    account: String,
+    /// Base64
+    nonce: String,
+    /// Base64 ciphertext with tag
+    secret: String,
+}
+
+/// Derive the key from the master password and open the vault. Fails when
+/// the password doesn't decrypt what is already stored.
+pub fn unlock(password: &str) -> Result<()> {
+    let path = vault_path()?;
+    let mut vault = load(&path)?;
+    if vault.salt.is_empty() {
+        vault.salt = new_salt();
+        save(&path, &vault)?;
+    }
+    let key = derive_key(password, &vault.salt)?;
+    if let Some(entry) = vault.entries.first() {
+        open(&key, entry).map_err(|_| anyhow!("The master password does not open the vault"))?;
+    }
+    *KEY.lock().unwrap() = Some(key);
+    Ok(())
+}
+
+pub fn is_unlocked() -> bool {
+    KEY.lock().map(|key| key.is_some()).unwrap_or(false)
+}
+
+/// Whether nothing is stored yet
+pub fn is_empty() -> bool {
+    vault_path()
+        .and_then(|path| load(&path))
+        .map(|vault| vault.entries.is_empty())
+        .unwrap_or(true)
+}
+
+pub fn store(service: &str, account: &str, secret: &str) -> Result<()> {
+    let key = current_key()?;
+    let path = vault_path()?;
+    let mut vault = load(&path)?;
+    vault.entries.retain(|e| !(e.service == service && e.account == account));
+    vault.entries.push(seal(&key, service, account, secret)?);
+    save(&path, &vault)
+}
+
+pub fn get(service: &str, account: &str) -> Result<String> {
+    let key = current_key()?;
+    let vault = load(&vault_path()?)?;
+    let entry = vault
+        .entries
+        .iter()
+        .find(|e| e.service == service && e.account == account)
+        .ok_or_else(|| anyhow!("No {} secret for {}", service, account))?;
+    open(&key, entry)
+}
+
+pub fn delete(service: &str, account: &str) -> Result<()> {
+    let path = vault_path()?;
+    let mut vault = load(&path)?;
+    let before = vault.entries.len();
+    vault.entries.retain(|e| !(e.service == service && e.account == account));
+    if vault.entries.len() == before {
+        return Err(anyhow!("No {} secret for {}", service, account));
+    }
+    save(&path, &vault)
+}
+
+/// Re-encrypt everything under a new master password. The vault must be
+/// unlocked unless it is still empty.
+pub fn rekey(password: &str) -> Result<()> {
+    let path = vault_path()?;
+    let vault = load(&path)?;
+    let secrets = if vault.entries.is_empty() {
+        Vec::new()
+    } else {
+        let key = current_key()?;
+        vault
+            .entries
+            .iter()
+            .map(|entry| Ok((entry.service.clone(), entry.account.clone(), open(&key, entry)?)))
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    let salt = new_salt();
+    let key = derive_key(password, &salt)?;
+    let entries = secrets
+        .iter()
+        .map(|(service, account, secret)| seal(&key, service, account, secret))
+        .collect::<Result<Vec<_>>>()?;
+    save(&path, &VaultFile { salt, entries })?;
+    *KEY.lock().unwrap() = Some(key);
+    Ok(())
+}
+
+fn current_key() -> Result<VaultKey> {
+    KEY.lock()
+        .ok()
+        .and_then(|key| *key)
+        .ok_or_else(|| anyhow!("The vault is locked; unlock TabSSH with the master password, or set one in Settings > Security"))
+}
+
+fn vault_path() -> Result<PathBuf> {
+    portable::dir()
+        .map(|dir| dir.join(VAULT_FILE))
+        .ok_or_else(|| anyhow!("The vault is only used in portable mode"))
+}
+
+fn load(path: &Path) -> Result<VaultFile> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => serde_json::from_str(&text).context("The vault file is damaged"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(VaultFile::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Write beside and rename, so a pulled stick doesn't leave half a file
+fn save(path: &Path, vault: &VaultFile) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_string_pretty(vault)?)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn new_salt() -> String {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    BASE64.encode(salt)
+}
+
+fn derive_key(password: &str, salt: &str) -> Result<VaultKey> {
+    let salt = BASE64.decode(salt).context("The vault file is damaged")?;
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), &salt, &mut key)
+        .map_err(|e| anyhow!("Could not derive the vault key: {}", e))?;
+    Ok(key)
+}
+
+/// The service and account are bound in as associated data, so a sealed
+/// secret can't be moved to another entry
+fn associated_data(service: &str, account: &str) -> Vec<u8> {
+    format!("{}\0{}", service, account).into_bytes()
+}
+
+fn seal(key: &VaultKey, service: &str, account: &str, secret: &str) -> Result<VaultEntry> {
+    let mut nonce = [0u8; 24];
+    OsRng.fill_bytes(&mut nonce);
+    let aad = associated_data(service, account);
+    let sealed = XChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(XNonce::from_slice(&nonce), Payload { msg: secret.as_bytes(), aad: &aad })
+        .map_err(|_| anyhow!("Could not encrypt the secret"))?;
+    Ok(VaultEntry {
+        service: service.to_string(),
+        account: account.to_string(),
+        nonce: BASE64.encode(nonce),
+        secret: BASE64.encode(sealed),
+    })
+}
+
+fn open(key: &VaultKey, entry: &VaultEntry) -> Result<String> {
+    let nonce = BASE64.decode(&entry.nonce).context("The vault file is damaged")?;
+    let sealed = BASE64.decode(&entry.secret).context("The vault file is damaged")?;
+    if nonce.len() != 24 {
+        return Err(anyhow!("The vault file is damaged"));
+    }
+    let aad = associated_data(&entry.service, &entry.account);
+    let secret = XChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(XNonce::from_slice(&nonce), Payload { msg: &sealed, aad: &aad })
+        .map_err(|_| anyhow!("Could not decrypt the {} secret for {}", entry.service, entry.account))?;
+    Ok(String::from_utf8(secret)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let key = derive_key("correct horse", &new_salt()).unwrap();
+        let entry = seal(&key, "tabssh-password", "conn-1", "hunter2").unwrap();
+        assert_ne!(entry.secret, BASE64.encode("hunter2"));
+        assert_eq!(open(&key, &entry).unwrap(), "hunter2");
+
+        let other = derive_key("wrong horse", &new_salt()).unwrap();
+        assert!(open(&other, &entry).is_err());
+
+        // Moved to another connection
+        let moved = VaultEntry { account: "conn-2".to_string(), ..entry };
+        assert!(open(&key, &moved).is_err());
+    }
+}
@@ -0,0 +1,254 @@
+//! Cloud instance browser
+//!
+//! Lists running compute instances through the AWS, Google Cloud and Azure
+//! CLIs. Instances can be saved as profiles or reached through the provider's
+//! own tunnel (EC2 Instance Connect, IAP) when they have no public address.
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloudProvider {
+    Aws,
+    Gcp,
+    Azure,
+}
+
+impl CloudProvider {
+    pub const ALL: [CloudProvider; 3] = [Self::Aws, Self::Gcp, Self::Azure];
+
+    /// CLI binary that must be on PATH
+    pub fn cli(&self) -> &'static str {
+        match self {
+            Self::Aws => "aws",
+            Self::Gcp => "gcloud",
+            Self::Azure => "az",
+        }
+    }
+
+    /// What the scope field means for this provider
+    pub fn scope_label(&self) -> &'static str {
+        match self {
+            Self::Aws => "Profile",
+            Self::Gcp => "Project",
+            Self::Azure => "Subscription",
+        }
+    }
+}
+
+impl std::fmt::Display for CloudProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Aws => write!(f, "AWS"),
+            Self::Gcp => write!(f, "Google Cloud"),
+            Self::Azure => write!(f, "Azure"),
+        }
+    }
+}
+
+/// What to list; empty fields use the CLI's configured defaults
+#[derive(Debug, Clone, PartialEq)]
+pub struct CloudQuery {
+    pub provider: CloudProvider,
+    /// AWS profile, GCP project or Azure subscription
+    pub scope: String,
+    /// AWS region; unused elsewhere
+    pub region: String,
+}
+
+/// A running instance
+#[derive(Debug, Clone, PartialEq)]
+pub struct CloudInstance {
+    pub provider: CloudProvider,
+    pub id: String,
+    pub name: String,
+    pub public_ip: Option<String>,
+    pub private_ip: Option<String>,
+    /// AWS availability zone, GCP zone or Azure resource group
+    pub location: String,
+    pub tags: Vec<(String, String)>,
+}
+
+impl CloudInstance {
+    /// Address to connect to directly
+    pub fn address(&self) -> Option<&str> {
+        self.public_ip.as_deref().or(self.private_ip.as_deref())
+    }
+
+    /// Provider command that opens a shell through its tunnel, for instances
+    /// without a reachable address; run it in a local terminal
+    pub fn tunnel_command(&self, query: &CloudQuery) -> Option<Vec<String>> {
+        let mut args: Vec<String> = match self.provider {
+            CloudProvider::Aws => vec![
+                "aws".into(), "ec2-instance-connect".into(), "ssh".into(),
+                "--instance-id".into(), self.id.clone(),
+                "--connection-type".into(), "eice".into(),
+            ],
+            CloudProvider::Gcp => vec![
+                "gcloud".into(), "compute".into(), "ssh".into(), self.name.clone(),
+                "--zone".into(), self.location.clone(), "--tunnel-through-iap".into(),
+            ],
+            CloudProvider::Azure => return None,
+        };
+        args.extend(scope_args(query));
+        Some(args)
+    }
+}
+
+fn scope_args(query: &CloudQuery) -> Vec<String> {
+    let mut args = Vec::new();
+    let scope = query.scope.trim();
+    if !scope.is_empty() {
+        let flag = match query.provider {
+            CloudProvider::Aws => "--profile",
+            CloudProvider::Gcp => "--project",
+            CloudProvider::Azure => "--subscription",
+        };
+        args.extend([flag.to_string(), scope.to_string()]);
+    }
+    if query.provider == CloudProvider::Aws && !query.region.trim().is_empty() {
+        args.extend(["--region".to_string(), query.region.trim().to_string()]);
+    }
+    args
+}
+
+/// CLI arguments that list running instances as JSON
+fn list_args(query: &CloudQuery) -> Vec<String> {
+    let mut args: Vec<String> = match query.provider {
+        CloudProvider::Aws => ["ec2", "describe-instances", "--filters",
+            "Name=instance-state-name,Values=running", "--output", "json"]
+            .map(String::from).to_vec(),
+        CloudProvider::Gcp => ["compute", "instances", "list", "--filter=status=RUNNING", "--format=json"]
+            .map(String::from).to_vec(),
+        CloudProvider::Azure => ["vm", "list", "--show-details", "--query",
+            "[?powerState=='VM running']", "--output", "json"]
+            .map(String::from).to_vec(),
+    };
+    args.extend(scope_args(query));
+    args
+}
+
+/// Run the provider's CLI and parse the running instances
+pub async fn list_instances(query: CloudQuery) -> Result<Vec<CloudInstance>> {
+    let cli = query.provider.cli();
+    let output = tokio::process::Command::new(cli)
+        .args(list_args(&query))
+        .kill_on_drop(true)
+        .output()
+        .await
+        .with_context(|| format!("Could not run `{}`; is the {} CLI installed?", cli, query.provider))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("{} failed: {}", cli, stderr.trim()));
+    }
+    let json: Value = serde_json::from_slice(&output.stdout).context("Unexpected CLI output")?;
+    Ok(parse_instances(query.provider, &json))
+}
+
+/// Instances from the provider CLI's JSON
+pub fn parse_instances(provider: CloudProvider, json: &Value) -> Vec<CloudInstance> {
+    let text = |v: &Value, key: &str| v.get(key).and_then(Value::as_str).map(str::to_string);
+    let map_tags = |v: Option<&Value>| -> Vec<(String, String)> {
+        v.and_then(Value::as_object)
+            .map(|o| o.iter().map(|(k, v)| (k.clone(), v.as_str().unwrap_or_default().to_string())).collect())
+            .unwrap_or_default()
+    };
+
+    let mut instances = Vec::new();
+    match provider {
+        CloudProvider::Aws => {
+            let reservations = json.get("Reservations").and_then(Value::as_array).cloned().unwrap_or_default();
+            for instance in reservations.iter().filter_map(|r| r.get("Instances")?.as_array()).flatten() {
+                let tags: Vec<(String, String)> = instance.get("Tags").and_then(Value::as_array)
+                    .map(|tags| tags.iter().filter_map(|t| Some((text(t, "Key")?, text(t, "Value")?))).collect())
+                    .unwrap_or_default();
+                let id = text(instance, "InstanceId").unwrap_or_default();
+                let name = tags.iter().find(|(k, _)| k == "Name").map(|(_, v)| v.clone()).unwrap_or_else(|| id.clone());
+                instances.push(CloudInstance {
+                    provider,
+                    name,
+                    public_ip: text(instance, "PublicIpAddress"),
+                    private_ip: text(instance, "PrivateIpAddress"),
+                    location: instance.get("Placement").and_then(|p| text(p, "AvailabilityZone")).unwrap_or_default(),
+                    tags,
+                    id,
+                });
+            }
+        }
+        CloudProvider::Gcp => {
+            for instance in json.as_array().into_iter().flatten() {
+                let nic = instance.get("networkInterfaces").and_then(|n| n.get(0));
+                instances.push(CloudInstance {
+                    provider,
+                    id: text(instance, "id").unwrap_or_default(),
+                    name: text(instance, "name").unwrap_or_default(),
+                    public_ip: nic.and_then(|n| n.get("accessConfigs")?.get(0)).and_then(|a| text(a, "natIP")),
+                    private_ip: nic.and_then(|n| text(n, "networkIP")),
+                    // Zones come as URLs; the last segment is the name
+                    location: text(instance, "zone").and_then(|z| z.rsplit('/').next().map(str::to_string)).unwrap_or_default(),
+                    tags: map_tags(instance.get("labels")),
+                });
+            }
+        }
+        CloudProvider::Azure => {
+            let first = |v: Option<String>| v.and_then(|s| s.split(',').next().map(str::trim).map(str::to_string)).filter(|s| !s.is_empty());
+            for instance in json.as_array().into_iter().flatten() {
+                instances.push(CloudInstance {
+                    provider,
+                    id: text(instance, "id").unwrap_or_default(),
+                    name: text(instance, "name").unwrap_or_default(),
+                    public_ip: first(text(instance, "publicIps")),
+                    private_ip: first(text(instance, "privateIps")),
+                    location: text(instance, "resourceGroup").unwrap_or_default(),
+                    tags: map_tags(instance.get("tags")),
+                });
+            }
+        }
+    }
+    instances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_aws() {
+        let output = json!({"Reservations": [{"Instances": [{
+            "InstanceId": "i-0abc",
+            "PrivateIpAddress": "10.0.1.5",
+            "Placement": {"AvailabilityZone": "eu-west-1a"},
+            "Tags": [{"Key": "Name", "Value": "web-1"}, {"Key": "env", "Value": "prod"}]
+        }]}]});
+        let instances = parse_instances(CloudProvider::Aws, &output);
+        assert_eq!(instances.len(), 1);
+        assert_eq!(instances[0].name, "web-1");
+        assert_eq!(instances[0].address(), Some("10.0.1.5"));
+        assert_eq!(instances[0].location, "eu-west-1a");
+
+        let query = CloudQuery { provider: CloudProvider::Aws, scope: "prod".into(), region: "eu-west-1".into() };
+        let command = instances[0].tunnel_command(&query).unwrap();
+        assert!(command.ends_with(&["--profile".into(), "prod".into(), "--region".into(), "eu-west-1".into()]));
+    }
+
+    #[test]
+    fn test_parse_gcp_and_azure() {
+        let gcp = json!([{
+            "id": "123", "name": "db-1",
+            "zone": "https://www.googleapis.com/compute/v1/projects/p/zones/europe-west1-b",
+            "networkInterfaces": [{"networkIP": "10.1.0.2", "accessConfigs": [{"natIP": "34.1.2.3"}]}],
+            "labels": {"team": "data"}
+        }]);
+        let instance = &parse_instances(CloudProvider::Gcp, &gcp)[0];
+        assert_eq!(instance.location, "europe-west1-b");
+        assert_eq!(instance.address(), Some("34.1.2.3"));
+        assert_eq!(instance.tags, vec![("team".to_string(), "data".to_string())]);
+
+        let azure = json!([{"id": "/subs/x/vm1", "name": "vm1", "resourceGroup": "rg",
+            "publicIps": "", "privateIps": "10.2.0.4,10.2.0.5", "tags": null}]);
+        let instance = &parse_instances(CloudProvider::Azure, &azure)[0];
+        assert_eq!(instance.public_ip, None);
+        assert_eq!(instance.private_ip.as_deref(), Some("10.2.0.4"));
+    }
+}
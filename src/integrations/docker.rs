@@ -0,0 +1,69 @@
+//! Docker containers on a connected host
+//!
+//! Containers are listed by running `docker ps` over the existing SSH
+//! connection; a shell in one opens as a new tab running `docker exec -it`.
+
+use super::kubernetes::DEFAULT_SHELL;
+use crate::utils::helpers::shell_quote;
+use serde::Deserialize;
+
+/// Lists running containers, one JSON object per line
+pub const LIST_COMMAND: &str = "docker ps --no-trunc --format '{{json .}}'";
+
+/// A running container
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Container {
+    #[serde(rename = "ID")]
+    pub id: String,
+    #[serde(rename = "Names")]
+    pub name: String,
+    #[serde(rename = "Image")]
+    pub image: String,
+    #[serde(rename = "Status", default)]
+    pub status: String,
+}
+
+impl Container {
+    /// Short id as `docker ps` shows it by default
+    pub fn short_id(&self) -> &str {
+        &self.id[..self.id.len().min(12)]
+    }
+
+    /// Remote command that attaches a shell to the container, the same one
+    /// a pod gets
+    pub fn shell_command(&self) -> String {
+        format!("docker exec -it {} sh -c {}", shell_quote(&self.id), shell_quote(DEFAULT_SHELL))
+    }
+}
+
+/// Containers from [`LIST_COMMAND`] output; lines that do not parse are skipped
+pub fn parse_ps(output: &str) -> Vec<Container> {
+    output
+        .lines()
+        .filter(|line| line.trim_start().starts_with('{'))
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ps() {
+        let output = concat!(
+            r#"{"Command":"\"nginx -g\"","ID":"4f2a9c1e7b3d8a6f","Image":"nginx:1.25","Names":"web","Status":"Up 2 hours"}"#,
+            "\n",
+            "WARNING: something unrelated\n",
+            r#"{"ID":"9b1c","Image":"redis","Names":"cache","Status":"Up 5 minutes"}"#,
+        );
+        let containers = parse_ps(output);
+        assert_eq!(containers.len(), 2);
+        assert_eq!(containers[0].name, "web");
+        assert_eq!(containers[0].short_id(), "4f2a9c1e7b3d");
+        assert_eq!(
+            containers[1].shell_command(),
+            "docker exec -it 9b1c sh -c 'command -v bash >/dev/null && exec bash || exec sh'"
+        );
+    }
+}
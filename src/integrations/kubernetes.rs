@@ -0,0 +1,210 @@
+//! Kubernetes pod shells
+//!
+//! Lists contexts, namespaces and pods through `kubectl` and builds the
+//! `kubectl exec` command a terminal tab runs as a local process session.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Shell started in the container: bash when the image has it, else sh
+pub const DEFAULT_SHELL: &str = "command -v bash >/dev/null && exec bash || exec sh";
+
+/// Pod a profile opens a shell in
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KubeTarget {
+    /// kubeconfig context; None uses the current one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    pub namespace: String,
+    pub pod: String,
+    /// None uses the pod's default container
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+    /// Command run with `sh -c` in the container; empty uses [`DEFAULT_SHELL`]
+    #[serde(default)]
+    pub shell: String,
+}
+
+impl KubeTarget {
+    /// Short label for tab titles, e.g. `api-7f9c/web (prod)`
+    pub fn label(&self) -> String {
+        let mut label = self.pod.clone();
+        if let Some(container) = &self.container {
+            label.push('/');
+            label.push_str(container);
+        }
+        format!("{} ({})", label, self.namespace)
+    }
+
+    /// `kubectl` arguments for an interactive shell in the pod
+    pub fn exec_args(&self, tty: bool) -> Vec<String> {
+        let mut args = context_args(self.context.as_deref());
+        args.extend(["--namespace".to_string(), self.namespace.clone(), "exec".to_string()]);
+        args.push(if tty { "-it" } else { "-i" }.to_string());
+        args.push(self.pod.clone());
+        if let Some(container) = &self.container {
+            args.extend(["--container".to_string(), container.clone()]);
+        }
+        let shell = if self.shell.trim().is_empty() { DEFAULT_SHELL } else { self.shell.trim() };
+        args.extend(["--".to_string(), "sh".to_string(), "-c".to_string(), shell.to_string()]);
+        args
+    }
+
+    /// Program and arguments for a terminal of `cols` x `rows`
+    ///
+    /// kubectl only allocates a TTY in the container when its own stdin is a
+    /// terminal, so on Unix it runs under script(1) with the grid size set up
+    /// front. Later resizes are not forwarded. Elsewhere the shell runs
+    /// without a TTY.
+    pub fn command(&self, cols: u32, rows: u32) -> (String, Vec<String>) {
+        if cfg!(unix) {
+            let kubectl = std::iter::once("kubectl".to_string())
+                .chain(self.exec_args(true))
+                .map(|arg| crate::utils::helpers::shell_quote(&arg))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let inner = format!("stty cols {} rows {} 2>/dev/null; exec {}", cols, rows, kubectl);
+            let args = if cfg!(target_os = "linux") {
+                vec!["-qfec".to_string(), inner, "/dev/null".to_string()]
+            } else {
+                // BSD script takes the command after the file
+                vec!["-q".to_string(), "/dev/null".to_string(), "sh".to_string(), "-c".to_string(), inner]
+            };
+            ("script".to_string(), args)
+        } else {
+            ("kubectl".to_string(), self.exec_args(false))
+        }
+    }
+}
+
+/// A pod and its containers
+#[derive(Debug, Clone, PartialEq)]
+pub struct KubePod {
+    pub name: String,
+    pub phase: String,
+    pub containers: Vec<String>,
+}
+
+/// What the picker shows for one context and namespace
+#[derive(Debug, Clone, Default)]
+pub struct KubeListing {
+    pub contexts: Vec<String>,
+    pub current_context: Option<String>,
+    pub namespaces: Vec<String>,
+    pub pods: Vec<KubePod>,
+}
+
+fn context_args(context: Option<&str>) -> Vec<String> {
+    match context {
+        Some(context) if !context.is_empty() => vec!["--context".to_string(), context.to_string()],
+        _ => Vec::new(),
+    }
+}
+
+async fn kubectl(args: &[String]) -> Result<Vec<u8>> {
+    let output = tokio::process::Command::new("kubectl")
+        .args(args)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .context("Could not run `kubectl`; is it installed and on PATH?")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("kubectl failed: {}", stderr.trim()));
+    }
+    Ok(output.stdout)
+}
+
+/// Contexts, namespaces and pods for the picker; the namespace list falls
+/// back to the chosen one when the user may not list namespaces
+pub async fn browse(context: Option<String>, namespace: String) -> Result<KubeListing> {
+    let contexts = kubectl(&["config".into(), "get-contexts".into(), "--output=name".into()]).await?;
+    let current = kubectl(&["config".into(), "current-context".into()]).await.ok();
+
+    let mut args = context_args(context.as_deref());
+    args.extend(["get".into(), "namespaces".into(), "--output=json".into()]);
+    let namespaces = match kubectl(&args).await {
+        Ok(out) => parse_names(&serde_json::from_slice(&out)?),
+        Err(e) => {
+            log::debug!("Cannot list namespaces: {}", e);
+            vec![namespace.clone()]
+        }
+    };
+
+    let mut args = context_args(context.as_deref());
+    args.extend(["--namespace".into(), namespace, "get".into(), "pods".into(), "--output=json".into()]);
+    let pods = parse_pods(&serde_json::from_slice(&kubectl(&args).await?)?);
+
+    Ok(KubeListing {
+        contexts: String::from_utf8_lossy(&contexts).lines().map(str::to_string).collect(),
+        current_context: current.map(|c| String::from_utf8_lossy(&c).trim().to_string()),
+        namespaces,
+        pods,
+    })
+}
+
+/// `metadata.name` of every item in a `kubectl get -o json` list
+pub fn parse_names(list: &Value) -> Vec<String> {
+    items(list)
+        .filter_map(|item| item.pointer("/metadata/name")?.as_str().map(str::to_string))
+        .collect()
+}
+
+/// Pods from `kubectl get pods -o json`, running ones first
+pub fn parse_pods(list: &Value) -> Vec<KubePod> {
+    let mut pods: Vec<KubePod> = items(list)
+        .filter_map(|item| {
+            Some(KubePod {
+                name: item.pointer("/metadata/name")?.as_str()?.to_string(),
+                phase: item.pointer("/status/phase").and_then(Value::as_str).unwrap_or("Unknown").to_string(),
+                containers: item.pointer("/spec/containers")
+                    .and_then(Value::as_array)
+                    .map(|c| c.iter().filter_map(|c| c.get("name")?.as_str().map(str::to_string)).collect())
+                    .unwrap_or_default(),
+            })
+        })
+        .collect();
+    pods.sort_by_key(|p| p.phase != "Running");
+    pods
+}
+
+fn items(list: &Value) -> impl Iterator<Item = &Value> {
+    list.get("items").and_then(Value::as_array).into_iter().flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_pods() {
+        let list = json!({"items": [
+            {"metadata": {"name": "job-1"}, "status": {"phase": "Succeeded"},
+             "spec": {"containers": [{"name": "job"}]}},
+            {"metadata": {"name": "api-7f9c"}, "status": {"phase": "Running"},
+             "spec": {"containers": [{"name": "api"}, {"name": "envoy"}]}},
+        ]});
+        let pods = parse_pods(&list);
+        assert_eq!(pods[0].name, "api-7f9c");
+        assert_eq!(pods[0].containers, vec!["api", "envoy"]);
+        assert_eq!(parse_names(&list), vec!["job-1", "api-7f9c"]);
+    }
+
+    #[test]
+    fn test_exec_args() {
+        let target = KubeTarget {
+            context: Some("prod".into()),
+            namespace: "web".into(),
+            pod: "api-7f9c".into(),
+            container: Some("api".into()),
+            shell: String::new(),
+        };
+        assert_eq!(target.exec_args(true), vec![
+            "--context", "prod", "--namespace", "web", "exec", "-it", "api-7f9c",
+            "--container", "api", "--", "sh", "-c", DEFAULT_SHELL,
+        ]);
+        assert_eq!(target.label(), "api-7f9c/api (web)");
+    }
+}
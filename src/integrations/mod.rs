@@ -0,0 +1,10 @@
+//! Optional integrations with external tools
+//!
+//! Each integration drives the tool's own CLI, so it uses whatever
+//! credentials the user has already configured for it.
+
+pub mod cloud;
+pub mod docker;
+pub mod kubernetes;
+pub mod processes;
+pub mod services;
@@ -0,0 +1,147 @@
+//! Processes on a connected host
+//!
+//! A small process list in the spirit of htop, without a TUI: `ps` runs over
+//! the existing SSH connection every few seconds and signals are sent with
+//! `kill`, so nothing has to be installed on the host.
+
+/// One process per line without a header; `args` goes last as it has spaces.
+/// The `=` form works with both procps and BSD `ps`.
+pub const LIST_COMMAND: &str = "ps -eo pid=,user=,pcpu=,pmem=,rss=,etime=,args=";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Process {
+    pub pid: u32,
+    pub user: String,
+    /// Percent of one CPU
+    pub cpu: f32,
+    /// Percent of physical memory
+    pub mem: f32,
+    /// Resident size in KiB
+    pub rss_kb: u64,
+    /// As `ps` prints it, e.g. `3-04:12:55`
+    pub elapsed: String,
+    pub command: String,
+}
+
+impl Process {
+    /// Whether `query` appears in the command, user or pid; case-insensitive
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        query.is_empty()
+            || self.command.to_lowercase().contains(&query)
+            || self.user.to_lowercase().contains(&query)
+            || self.pid.to_string() == query
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProcessSort {
+    #[default]
+    Cpu,
+    Memory,
+    Pid,
+    Command,
+}
+
+impl ProcessSort {
+    /// Busiest first for usage columns, ascending otherwise
+    pub fn descending_by_default(&self) -> bool {
+        matches!(self, ProcessSort::Cpu | ProcessSort::Memory)
+    }
+}
+
+pub fn sort(processes: &mut [Process], by: ProcessSort, descending: bool) {
+    processes.sort_by(|a, b| {
+        let order = match by {
+            ProcessSort::Cpu => a.cpu.total_cmp(&b.cpu),
+            ProcessSort::Memory => a.rss_kb.cmp(&b.rss_kb),
+            ProcessSort::Pid => a.pid.cmp(&b.pid),
+            ProcessSort::Command => a.command.to_lowercase().cmp(&b.command.to_lowercase()),
+        };
+        // Ties stay in pid order so rows don't jump between refreshes
+        let order = if descending { order.reverse() } else { order };
+        order.then(a.pid.cmp(&b.pid))
+    });
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    /// Ask the process to exit
+    Term,
+    /// Cannot be caught; for processes that ignore TERM
+    Kill,
+}
+
+impl Signal {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Signal::Term => "TERM",
+            Signal::Kill => "KILL",
+        }
+    }
+}
+
+/// Remote command sending `signal` to `pid`
+pub fn kill_command(pid: u32, signal: Signal) -> String {
+    format!("kill -{} {}", signal.name(), pid)
+}
+
+/// Processes from [`LIST_COMMAND`] output, without the `ps` itself; lines
+/// that do not parse are skipped
+pub fn parse_ps(output: &str) -> Vec<Process> {
+    output
+        .lines()
+        .filter_map(parse_line)
+        .filter(|process| process.command != LIST_COMMAND)
+        .collect()
+}
+
+fn parse_line(line: &str) -> Option<Process> {
+    let mut rest = line.trim_start();
+    let mut fields = [""; 6];
+    for field in fields.iter_mut() {
+        let end = rest.find(char::is_whitespace)?;
+        *field = &rest[..end];
+        rest = rest[end..].trim_start();
+    }
+    let [pid, user, cpu, mem, rss, elapsed] = fields;
+    Some(Process {
+        pid: pid.parse().ok()?,
+        user: user.to_string(),
+        cpu: cpu.parse().ok()?,
+        mem: mem.parse().ok()?,
+        rss_kb: rss.parse().ok()?,
+        elapsed: elapsed.to_string(),
+        command: rest.trim_end().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_sort() {
+        let output = concat!(
+            "    1 root      0.0  0.1  11820     3-04:12:55 /sbin/init splash\n",
+            "  812 postgres 12.5  4.2 345000       01:02:03 postgres: writer process\n",
+            " 4242 deploy   97.0  1.0  81234          00:42 python3 worker.py --queue high\n",
+            " 4300 deploy    0.0  0.0   2048          00:00 ps -eo pid=,user=,pcpu=,pmem=,rss=,etime=,args=\n",
+            "garbage line\n",
+        );
+        let mut processes = parse_ps(output);
+        assert_eq!(processes.len(), 3);
+        assert_eq!(processes[0].command, "/sbin/init splash");
+        assert_eq!(processes[1].elapsed, "01:02:03");
+
+        sort(&mut processes, ProcessSort::Cpu, true);
+        assert_eq!(processes[0].pid, 4242);
+        sort(&mut processes, ProcessSort::Memory, true);
+        assert_eq!(processes[0].user, "postgres");
+
+        assert!(processes[0].matches("WRITER"));
+        assert!(processes[0].matches("812"));
+        assert!(!processes[0].matches("81"));
+        assert_eq!(kill_command(4242, Signal::Kill), "kill -KILL 4242");
+    }
+}
@@ -0,0 +1,44 @@
+//! Service shortcuts saved with a profile
+//!
+//! Each shortcut is a command like `systemctl status nginx` shown as a
+//! button next to the terminal. It runs on an exec channel of the session's
+//! connection, so the shell and whatever runs in it are left alone.
+
+use serde::{Deserialize, Serialize};
+
+/// Offered when adding a shortcut, as (label, command)
+pub const SUGGESTIONS: [(&str, &str); 6] = [
+    ("nginx status", "systemctl status nginx --no-pager"),
+    ("Restart nginx", "sudo -n systemctl restart nginx"),
+    ("Failed units", "systemctl --failed --no-pager"),
+    ("Compose ps", "docker compose ps"),
+    ("Disk", "df -h"),
+    ("Recent errors", "journalctl -p err -n 50 --no-pager"),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServiceAction {
+    pub label: String,
+    pub command: String,
+}
+
+impl ServiceAction {
+    pub fn new(label: &str, command: &str) -> Self {
+        Self { label: label.to_string(), command: command.to_string() }
+    }
+
+    /// Button text; the command when no label was given
+    pub fn title(&self) -> &str {
+        let label = self.label.trim();
+        if label.is_empty() {
+            self.command.trim()
+        } else {
+            label
+        }
+    }
+
+    /// Whether there is anything to run
+    pub fn is_runnable(&self) -> bool {
+        !self.command.trim().is_empty()
+    }
+}
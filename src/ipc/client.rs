@@ -0,0 +1,62 @@
+//! Blocking control socket client, used by `tabsshctl`
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Write};
+
+use super::protocol::{RpcRequest, RpcResponse};
+
+#[cfg(unix)]
+type Stream = std::os::unix::net::UnixStream;
+#[cfg(windows)]
+type Stream = std::fs::File;
+
+pub struct IpcClient {
+    reader: BufReader<Stream>,
+    writer: Stream,
+    next_id: u64,
+}
+
+impl IpcClient {
+    /// Connect to a running TabSSH instance
+    pub fn connect() -> Result<Self> {
+        let path = super::socket_path();
+
+        #[cfg(unix)]
+        let stream = Stream::connect(&path);
+        #[cfg(windows)]
+        let stream = std::fs::OpenOptions::new().read(true).write(true).open(&path);
+
+        let stream = stream.with_context(|| format!("TabSSH is not running ({})", path.display()))?;
+        let writer = stream.try_clone()?;
+
+        Ok(Self {
+            reader: BufReader::new(stream),
+            writer,
+            next_id: 1,
+        })
+    }
+
+    /// Send one request and wait for its result
+    pub fn call(&mut self, method: &str, params: Value) -> Result<Value> {
+        let request = RpcRequest::new(self.next_id, method, params);
+        self.next_id += 1;
+
+        let mut line = serde_json::to_string(&request)?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())?;
+        self.writer.flush()?;
+
+        let mut reply = String::new();
+        if self.reader.read_line(&mut reply)? == 0 {
+            return Err(anyhow!("Connection closed by TabSSH"));
+        }
+
+        let response: RpcResponse = serde_json::from_str(&reply)?;
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(anyhow!("{}", error)),
+            (Some(result), None) => Ok(result),
+            (None, None) => Ok(Value::Null),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+//! Local control API
+//!
+//! A JSON-RPC 2.0 endpoint on a per-user unix socket (named pipe on
+//! Windows) that lets scripts and `tabsshctl` list sessions, open
+//! connections, send input, start transfers and query status.
+
+mod client;
+mod protocol;
+mod server;
+
+pub use client::IpcClient;
+pub use protocol::{IpcRequest, IpcTransferDirection, RpcError, RpcRequest, RpcResponse};
+pub use server::{start, IpcCall, IpcHandle};
+
+use std::path::PathBuf;
+
+/// Location of the control socket for the current user
+#[cfg(unix)]
+pub fn socket_path() -> PathBuf {
+    dirs::runtime_dir()
+        .or_else(dirs::data_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("tabssh")
+        .join("tabssh.sock")
+}
+
+/// Location of the control pipe for the current user
+#[cfg(windows)]
+pub fn socket_path() -> PathBuf {
+    let user = std::env::var("USERNAME").unwrap_or_else(|_| "default".to_string());
+    PathBuf::from(format!(r"\\.\pipe\tabssh-{}", user))
+}
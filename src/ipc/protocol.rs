@@ -0,0 +1,202 @@
+//! JSON-RPC 2.0 messages for the control socket
+//!
+//! Each request and response is a single line of JSON.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Standard JSON-RPC error codes
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+/// Application error: the referenced session or profile does not exist
+pub const NOT_FOUND: i64 = -32001;
+/// Application error: the app is locked or the session takes no input
+pub const FORBIDDEN: i64 = -32002;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcRequest {
+    pub jsonrpc: String,
+    #[serde(default)]
+    pub id: Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+impl RpcRequest {
+    pub fn new(id: u64, method: &str, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id: json!(id),
+            method: method.to_string(),
+            params,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcResponse {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+impl RpcResponse {
+    pub fn success(id: Value, result: Value) -> Self {
+        Self { jsonrpc: "2.0".to_string(), id, result: Some(result), error: None }
+    }
+
+    pub fn failure(id: Value, error: RpcError) -> Self {
+        Self { jsonrpc: "2.0".to_string(), id, result: None, error: Some(error) }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(NOT_FOUND, message)
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(FORBIDDEN, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(INTERNAL_ERROR, message)
+    }
+}
+
+impl std::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (code {})", self.message, self.code)
+    }
+}
+
+/// Direction of a transfer started over IPC
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IpcTransferDirection {
+    Upload,
+    Download,
+}
+
+/// Typed form of the supported methods
+#[derive(Debug, Clone, PartialEq)]
+pub enum IpcRequest {
+    /// `session.list`
+    ListSessions,
+    /// `session.open` `{ "profile": name }`
+    OpenConnection { profile: String },
+    /// `session.send` `{ "session": id, "text": text }`
+    SendText { session: String, text: String },
+    /// `transfer.start` `{ "session", "direction", "local", "remote" }`
+    StartTransfer {
+        session: String,
+        direction: IpcTransferDirection,
+        local: String,
+        remote: String,
+    },
+    /// `status`
+    Status,
+}
+
+impl IpcRequest {
+    /// Map a method name and params onto a typed request
+    pub fn parse(method: &str, params: &Value) -> Result<Self, RpcError> {
+        let str_param = |name: &str| -> Result<String, RpcError> {
+            params.get(name)
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("missing string param '{}'", name)))
+        };
+
+        match method {
+            "session.list" => Ok(Self::ListSessions),
+            "session.open" => Ok(Self::OpenConnection { profile: str_param("profile")? }),
+            "session.send" => Ok(Self::SendText {
+                session: str_param("session")?,
+                text: str_param("text")?,
+            }),
+            "transfer.start" => {
+                let direction = params.get("direction")
+                    .cloned()
+                    .and_then(|d| serde_json::from_value(d).ok())
+                    .ok_or_else(|| RpcError::new(INVALID_PARAMS, "direction must be 'upload' or 'download'"))?;
+                Ok(Self::StartTransfer {
+                    session: str_param("session")?,
+                    direction,
+                    local: str_param("local")?,
+                    remote: str_param("remote")?,
+                })
+            }
+            "status" => Ok(Self::Status),
+            other => Err(RpcError::new(METHOD_NOT_FOUND, format!("unknown method '{}'", other))),
+        }
+    }
+}
+
+/// Parse one line of input into a request id and typed request.
+///
+/// On failure the id (if any could be recovered) is returned with the error
+/// so the caller can still answer with a proper error response.
+pub fn decode_line(line: &str) -> Result<(Value, IpcRequest), (Value, RpcError)> {
+    let request: RpcRequest = serde_json::from_str(line)
+        .map_err(|e| (Value::Null, RpcError::new(PARSE_ERROR, e.to_string())))?;
+
+    if request.jsonrpc != "2.0" {
+        return Err((request.id, RpcError::new(INVALID_REQUEST, "jsonrpc must be \"2.0\"")));
+    }
+
+    match IpcRequest::parse(&request.method, &request.params) {
+        Ok(parsed) => Ok((request.id, parsed)),
+        Err(e) => Err((request.id, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_send() {
+        let line = r#"{"jsonrpc":"2.0","id":7,"method":"session.send","params":{"session":"abc","text":"ls\n"}}"#;
+        let (id, request) = decode_line(line).unwrap();
+        assert_eq!(id, json!(7));
+        assert_eq!(request, IpcRequest::SendText { session: "abc".into(), text: "ls\n".into() });
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let (_, err) = decode_line("not json").unwrap_err();
+        assert_eq!(err.code, PARSE_ERROR);
+
+        let (id, err) = decode_line(r#"{"jsonrpc":"2.0","id":1,"method":"nope"}"#).unwrap_err();
+        assert_eq!(id, json!(1));
+        assert_eq!(err.code, METHOD_NOT_FOUND);
+
+        let (_, err) = decode_line(r#"{"jsonrpc":"2.0","id":2,"method":"session.open","params":{}}"#).unwrap_err();
+        assert_eq!(err.code, INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_decode_transfer() {
+        let line = r#"{"jsonrpc":"2.0","id":3,"method":"transfer.start","params":{"session":"s","direction":"upload","local":"/tmp/a","remote":"/srv/a"}}"#;
+        let (_, request) = decode_line(line).unwrap();
+        assert!(matches!(request, IpcRequest::StartTransfer { direction: IpcTransferDirection::Upload, .. }));
+    }
+}
@@ -0,0 +1,155 @@
+//! Control socket listener
+//!
+//! Connections are served on the shared runtime. Every decoded request is
+//! handed to the UI thread as an [`IpcCall`] and the connection waits for
+//! the UI to answer before reading the next line.
+
+use anyhow::Result;
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::runtime::Runtime;
+use tokio::sync::{mpsc, oneshot};
+
+use super::protocol::{decode_line, IpcRequest, RpcError, RpcResponse};
+
+/// A request waiting for the UI to answer it
+pub struct IpcCall {
+    pub request: IpcRequest,
+    reply: oneshot::Sender<Result<Value, RpcError>>,
+}
+
+impl IpcCall {
+    pub fn respond(self, result: Result<Value, RpcError>) {
+        // The client may have hung up; nothing to do then
+        let _ = self.reply.send(result);
+    }
+}
+
+/// UI side of the control socket
+pub struct IpcHandle {
+    calls: mpsc::UnboundedReceiver<IpcCall>,
+}
+
+impl IpcHandle {
+    /// Next pending call, if any (non-blocking)
+    pub fn try_recv(&mut self) -> Option<IpcCall> {
+        self.calls.try_recv().ok()
+    }
+}
+
+/// Start listening on the control socket
+pub fn start(runtime: &Runtime) -> Result<IpcHandle> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let _guard = runtime.enter();
+    listen(tx)?;
+    Ok(IpcHandle { calls: rx })
+}
+
+#[cfg(unix)]
+fn listen(tx: mpsc::UnboundedSender<IpcCall>) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    use tokio::net::UnixListener;
+
+    let path = super::socket_path();
+    // Bound inside a directory only we can enter, so there is no moment
+    // where another user could connect before the socket is chmod'ed.
+    // Fails when someone else owns the directory, which is what we want.
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+        std::fs::set_permissions(parent, std::fs::Permissions::from_mode(0o700))?;
+    }
+    // A stale socket from a previous run would make bind fail
+    if path.exists() {
+        std::fs::remove_file(&path)?;
+    }
+
+    let listener = UnixListener::bind(&path)?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    log::info!("IPC listening on {}", path.display());
+
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(serve(stream, tx.clone()));
+                }
+                Err(e) => {
+                    log::warn!("IPC accept failed: {}", e);
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(windows)]
+fn listen(tx: mpsc::UnboundedSender<IpcCall>) -> Result<()> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let name = super::socket_path().to_string_lossy().to_string();
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .reject_remote_clients(true)
+        .create(&name)?;
+    log::info!("IPC listening on {}", name);
+
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = server.connect().await {
+                log::warn!("IPC connect failed: {}", e);
+                break;
+            }
+            let connected = server;
+            server = match ServerOptions::new().create(&name) {
+                Ok(next) => next,
+                Err(e) => {
+                    log::warn!("IPC pipe re-create failed: {}", e);
+                    tokio::spawn(serve(connected, tx.clone()));
+                    break;
+                }
+            };
+            tokio::spawn(serve(connected, tx.clone()));
+        }
+    });
+
+    Ok(())
+}
+
+async fn serve<S>(stream: S, tx: mpsc::UnboundedSender<IpcCall>)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match decode_line(&line) {
+            Ok((id, request)) => {
+                let (reply, result) = oneshot::channel();
+                if tx.send(IpcCall { request, reply }).is_err() {
+                    break;
+                }
+                match result.await {
+                    Ok(Ok(value)) => RpcResponse::success(id, value),
+                    Ok(Err(e)) => RpcResponse::failure(id, e),
+                    Err(_) => RpcResponse::failure(id, RpcError::internal("request dropped")),
+                }
+            }
+            Err((id, e)) => RpcResponse::failure(id, e),
+        };
+
+        let Ok(mut out) = serde_json::to_string(&response) else {
+            break;
+        };
+        out.push('\n');
+        if writer.write_all(out.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
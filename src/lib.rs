@@ -11,6 +11,9 @@ pub mod platform;
 pub mod config;
 pub mod ui;
 pub mod utils;
+pub mod ipc;
+pub mod plugins;
+pub mod integrations;
 
 // Re-export commonly used types
 pub use ssh::{
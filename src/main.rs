@@ -5,7 +5,10 @@
 mod app;
 mod config;
 mod crypto;
+mod integrations;
+mod ipc;
 mod platform;
+mod plugins;
 mod sftp;
 mod ssh;
 mod storage;
@@ -14,12 +17,22 @@ mod ui;
 mod utils;
 
 use app::TabSshApp;
+use utils::startup::StartupProfiler;
 
 fn main() -> anyhow::Result<()> {
+    let profiler = StartupProfiler::new();
+
+    // Before anything reads or writes the data directory
+    platform::portable::init(std::env::args().skip(1));
+
     // Initialize logging
     utils::logging::init_logging("info");
+    utils::crash::install_panic_hook();
     
     log::info!("StartingTabSSHDesktopv{}",env!("CARGO_PKG_VERSION"));
+    if let Some(dir) = platform::portable::dir() {
+        log::info!("Portable mode: data in {}", dir.display());
+    }
     
     // Platform-specific initialization
     #[cfg(target_os = "linux")]
@@ -34,19 +47,24 @@ fn main() -> anyhow::Result<()> {
     #[cfg(any(target_os = "freebsd", target_os = "openbsd", target_os = "netbsd"))]
     platform::bsd::setup();
     
+    // Settings load after the window appears, so give it an alpha channel
+    // whenever the desktop supports one; opacity then applies live
+    let transparency = platform::transparency::support();
+
     // Run application
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([1280.0, 720.0])
             .with_min_inner_size([800.0, 600.0])
-            .with_title("TabSSH Desktop"),
+            .with_title("TabSSH Desktop")
+            .with_transparent(transparency.transparent),
         ..Default::default()
     };
     
     eframe::run_native(
         "TabSSH",
         native_options,
-        Box::new(|cc| Box::new(TabSshApp::new(cc))),
+        Box::new(move |cc| Box::new(TabSshApp::new(cc, profiler, transparency))),
     )
     .map_err(|e| anyhow::anyhow!("Failedtorunapplication:{}",e))
 }
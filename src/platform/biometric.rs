@@ -0,0 +1,96 @@
+//! Biometric authentication
+//!
+//! Touch ID through LocalAuthentication on macOS and Windows Hello through
+//! UserConsentVerifier on Windows. Both prompts block until the user
+//! answers, so call `authenticate` off the UI thread.
+
+use anyhow::Result;
+
+/// Whether biometrics are set up and usable on this machine
+pub fn available() -> bool {
+    imp::available()
+}
+
+/// What the platform calls it, for button labels
+pub fn name() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "Touch ID"
+    } else if cfg!(target_os = "windows") {
+        "Windows Hello"
+    } else {
+        "Biometrics"
+    }
+}
+
+/// Ask for a fingerprint or face; `reason` is shown in the system prompt.
+/// Ok(false) when the user cancelled or did not match.
+pub fn authenticate(reason: &str) -> Result<bool> {
+    imp::authenticate(reason)
+}
+
+#[cfg(target_os = "macos")]
+mod imp {
+    use anyhow::Result;
+    use block2::RcBlock;
+    use objc2::runtime::Bool;
+    use objc2_foundation::{NSError, NSString};
+    use objc2_local_authentication::{LAContext, LAPolicy};
+    use std::sync::mpsc;
+
+    pub fn available() -> bool {
+        let context = unsafe { LAContext::new() };
+        unsafe { context.canEvaluatePolicy_error(LAPolicy::DeviceOwnerAuthenticationWithBiometrics) }.is_ok()
+    }
+
+    pub fn authenticate(reason: &str) -> Result<bool> {
+        let (tx, rx) = mpsc::channel();
+        let reply = RcBlock::new(move |success: Bool, _error: *mut NSError| {
+            let _ = tx.send(success.as_bool());
+        });
+        // The context has to outlive the prompt
+        let context = unsafe { LAContext::new() };
+        unsafe {
+            context.evaluatePolicy_localizedReason_reply(
+                LAPolicy::DeviceOwnerAuthenticationWithBiometrics,
+                &NSString::from_str(reason),
+                &reply,
+            );
+        }
+        let verified = rx.recv()?;
+        drop(context);
+        Ok(verified)
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod imp {
+    use anyhow::Result;
+    use windows::core::HSTRING;
+    use windows::Security::Credentials::UI::{
+        UserConsentVerificationResult, UserConsentVerifier, UserConsentVerifierAvailability,
+    };
+
+    pub fn available() -> bool {
+        UserConsentVerifier::CheckAvailabilityAsync()
+            .and_then(|operation| operation.get())
+            .map_or(false, |availability| availability == UserConsentVerifierAvailability::Available)
+    }
+
+    pub fn authenticate(reason: &str) -> Result<bool> {
+        let result = UserConsentVerifier::RequestVerificationAsync(&HSTRING::from(reason))?.get()?;
+        Ok(result == UserConsentVerificationResult::Verified)
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod imp {
+    use anyhow::{bail, Result};
+
+    pub fn available() -> bool {
+        false
+    }
+
+    pub fn authenticate(_reason: &str) -> Result<bool> {
+        bail!("Biometric unlock is not available on this platform")
+    }
+}
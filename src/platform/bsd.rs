@@ -1,5 +1,16 @@
 //! BSD-specific functionality
 
+use anyhow::Result;
+use std::path::Path;
+use std::process::Command;
+
 pub fn setup() {
     log::info!("BSDplatforminitialization");
 }
+
+/// Open the directory containing `path` in the desktop file manager
+pub fn reveal_in_file_manager(path: &Path) -> Result<()> {
+    let dir = if path.is_dir() { path } else { path.parent().unwrap_or(path) };
+    Command::new("xdg-open").arg(dir).spawn()?;
+    Ok(())
+}
@@ -0,0 +1,53 @@
+//! System-wide hotkeys, delivered while TabSSH is in the background
+//!
+//! Registration goes through the OS (RegisterHotKey on Windows, Carbon on
+//! macOS, an X11 key grab on Linux and the BSDs). Wayland compositors do not
+//! let applications grab keys, so registering fails there and the caller
+//! should say so instead of silently doing nothing.
+
+use anyhow::{anyhow, Context, Result};
+use global_hotkey::hotkey::HotKey;
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use std::sync::mpsc;
+
+pub struct GlobalHotkey {
+    manager: GlobalHotKeyManager,
+    hotkey: HotKey,
+    presses: mpsc::Receiver<()>,
+}
+
+impl GlobalHotkey {
+    /// Register `spec`, e.g. "Ctrl+`" or "Alt+Space", and wake `ctx` on
+    /// every press so the UI notices even while it is hidden
+    pub fn register(spec: &str, ctx: egui::Context) -> Result<Self> {
+        let hotkey: HotKey = spec.replace(' ', "").parse()
+            .map_err(|e| anyhow!("Invalid hotkey '{}': {}", spec, e))?;
+        let manager = GlobalHotKeyManager::new().context("Global hotkeys are not available on this desktop")?;
+        manager.register(hotkey)
+            .with_context(|| format!("{} is already used by another application", spec))?;
+
+        let id = hotkey.id();
+        let (tx, presses) = mpsc::channel();
+        GlobalHotKeyEvent::set_event_handler(Some(move |event: GlobalHotKeyEvent| {
+            if event.id == id && event.state == HotKeyState::Pressed {
+                let _ = tx.send(());
+                ctx.request_repaint();
+            }
+        }));
+
+        log::info!("Registered global hotkey {}", spec);
+        Ok(Self { manager, hotkey, presses })
+    }
+
+    /// Whether the hotkey was pressed since the last call
+    pub fn pressed(&self) -> bool {
+        self.presses.try_iter().count() > 0
+    }
+}
+
+impl Drop for GlobalHotkey {
+    fn drop(&mut self) {
+        GlobalHotKeyEvent::set_event_handler(None::<fn(GlobalHotKeyEvent)>);
+        let _ = self.manager.unregister(self.hotkey);
+    }
+}
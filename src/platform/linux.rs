@@ -1,5 +1,43 @@
 //! Linux-specific functionality
 
+use anyhow::Result;
+use std::path::Path;
+use std::process::Command;
+
 pub fn setup() {
     log::info!("Linuxplatforminitialization");
 }
+
+/// Open the directory containing `path` in the desktop file manager
+pub fn reveal_in_file_manager(path: &Path) -> Result<()> {
+    let dir = if path.is_dir() { path } else { path.parent().unwrap_or(path) };
+    Command::new("xdg-open").arg(dir).spawn()?;
+    Ok(())
+}
+
+/// Kernel IPv4 routing table plus interface states, for change detection
+pub fn route_table() -> Option<String> {
+    let routes = std::fs::read_to_string("/proc/net/route").ok()?;
+    // Interface, destination, gateway and mask; the other columns are counters
+    let mut state: String = routes
+        .lines()
+        .skip(1)
+        .map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            format!("{} {} {} {};", fields.first().unwrap_or(&""), fields.get(1).unwrap_or(&""),
+                fields.get(2).unwrap_or(&""), fields.get(7).unwrap_or(&""))
+        })
+        .collect();
+    if let Ok(entries) = std::fs::read_dir("/sys/class/net") {
+        let mut links: Vec<String> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| {
+                let oper = std::fs::read_to_string(e.path().join("operstate")).unwrap_or_default();
+                format!("{}={}", e.file_name().to_string_lossy(), oper.trim())
+            })
+            .collect();
+        links.sort();
+        state.push_str(&links.join(","));
+    }
+    Some(state)
+}
@@ -1,5 +1,15 @@
 //! macOS-specific functionality
 
+use anyhow::Result;
+use std::path::Path;
+use std::process::Command;
+
 pub fn setup() {
     log::info!("macOSplatforminitialization");
 }
+
+/// Reveal `path` in Finder with the item selected
+pub fn reveal_in_file_manager(path: &Path) -> Result<()> {
+    Command::new("open").arg("-R").arg(path).spawn()?;
+    Ok(())
+}
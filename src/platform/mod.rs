@@ -12,6 +12,12 @@ pub mod linux;
 #[cfg(any(target_os = "freebsd", target_os = "openbsd", target_os = "netbsd"))]
 pub mod bsd;
 
+pub mod biometric;
+pub mod hotkey;
+pub mod network;
+pub mod portable;
+pub mod transparency;
+
 pub struct PlatformManager;
 
 impl PlatformManager {
@@ -35,13 +41,39 @@ impl PlatformManager {
         dirs::home_dir()
     }
     
+    /// The portable directory when running portable; see `portable`
     pub fn get_config_directory() -> Option<std::path::PathBuf> {
+        if let Some(dir) = portable::dir() {
+            return Some(dir.to_path_buf());
+        }
         dirs::config_dir().map(|p| p.join("tabssh"))
     }
     
+    /// The portable directory when running portable; see `portable`
     pub fn get_data_directory() -> Option<std::path::PathBuf> {
+        if let Some(dir) = portable::dir() {
+            return Some(dir.to_path_buf());
+        }
         dirs::data_dir().map(|p| p.join("tabssh"))
     }
+
+    /// Show a local file in the platform file manager (Finder/Explorer/xdg-open)
+    pub fn reveal_in_file_manager(path: &std::path::Path) -> anyhow::Result<()> {
+        #[cfg(target_os = "macos")]
+        return macos::reveal_in_file_manager(path);
+
+        #[cfg(target_os = "windows")]
+        return windows::reveal_in_file_manager(path);
+
+        #[cfg(target_os = "linux")]
+        return linux::reveal_in_file_manager(path);
+
+        #[cfg(any(target_os = "freebsd", target_os = "openbsd", target_os = "netbsd"))]
+        return bsd::reveal_in_file_manager(path);
+
+        #[allow(unreachable_code)]
+        Err(anyhow::anyhow!("Revealing files is not supported on this platform"))
+    }
 }
 
 impl Default for PlatformManager {
@@ -0,0 +1,96 @@
+//! Network change and sleep/resume detection
+//!
+//! TCP only notices a dead path after minutes of retransmits. Watching for a
+//! changed route or a suspend lets sessions be probed right away instead.
+
+use crate::utils::executor::Executor;
+use std::net::{IpAddr, UdpSocket};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::mpsc;
+
+/// How often the network state is sampled
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+/// Extra time between samples that means the machine was asleep
+const SLEEP_THRESHOLD: Duration = Duration::from_secs(10);
+
+/// Something that may have broken open connections
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetworkEvent {
+    /// Interfaces or the default route changed
+    Changed,
+    /// The machine resumed after sleeping this long
+    Resumed(Duration),
+}
+
+/// Background watcher; poll once per frame
+pub struct NetworkMonitor {
+    events: mpsc::UnboundedReceiver<NetworkEvent>,
+}
+
+impl NetworkMonitor {
+    pub fn start(executor: &Executor) -> Self {
+        let (tx, events) = mpsc::unbounded_channel();
+        executor.spawn_detached(async move {
+            let mut fingerprint = route_fingerprint();
+            let mut last_mono = Instant::now();
+            let mut last_wall = SystemTime::now();
+
+            loop {
+                tokio::time::sleep(POLL_INTERVAL).await;
+
+                // The monotonic clock stops during suspend on some platforms
+                // and the wall clock keeps going; on others both jump
+                let mono = last_mono.elapsed();
+                let wall = last_wall.elapsed().unwrap_or_default();
+                last_mono = Instant::now();
+                last_wall = SystemTime::now();
+                let gap = mono.max(wall);
+                if gap > POLL_INTERVAL + SLEEP_THRESHOLD {
+                    log::info!("Resumed after about {}s", gap.as_secs());
+                    if tx.send(NetworkEvent::Resumed(gap)).is_err() {
+                        break;
+                    }
+                }
+
+                let current = route_fingerprint();
+                if current != fingerprint {
+                    log::info!("Network changed: {:?} -> {:?}", fingerprint, current);
+                    fingerprint = current;
+                    if tx.send(NetworkEvent::Changed).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        Self { events }
+    }
+
+    /// Events since the last call
+    pub fn poll(&mut self) -> Vec<NetworkEvent> {
+        let mut events = Vec::new();
+        while let Ok(event) = self.events.try_recv() {
+            events.push(event);
+        }
+        events
+    }
+}
+
+/// Summary of the routing state that changes when the network does
+fn route_fingerprint() -> String {
+    #[cfg(target_os = "linux")]
+    if let Some(routes) = super::linux::route_table() {
+        return routes;
+    }
+
+    let v4 = source_address("192.0.2.1:9");
+    let v6 = source_address("[2001:db8::1]:9");
+    format!("{:?}/{:?}", v4, v6)
+}
+
+/// Local address the OS would use to reach `target`; no packet is sent
+fn source_address(target: &str) -> Option<IpAddr> {
+    let bind = if target.starts_with('[') { "[::]:0" } else { "0.0.0.0:0" };
+    let socket = UdpSocket::bind(bind).ok()?;
+    socket.connect(target).ok()?;
+    socket.local_addr().ok().map(|addr| addr.ip())
+}
@@ -0,0 +1,90 @@
+//! Portable mode
+//!
+//! For running from a USB stick: the database, settings, session logs,
+//! crash reports and plugins live in one directory instead of the OS data
+//! directories, and secrets go into the encrypted vault rather than the
+//! keychain of whatever machine the stick is plugged into.
+//!
+//! Portable mode is on when started with `--portable` (next to the
+//! executable) or `--portable=DIR`, or when a `portable.txt` marker file
+//! sits next to the executable. With the marker, a non-empty first line
+//! names the directory, relative to the executable.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const MARKER_FILE: &str = "portable.txt";
+/// Data directory name next to the executable when none is given
+const DEFAULT_DIR: &str = "tabssh-data";
+
+static PORTABLE_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// Decide on portable mode from the command line and the marker file; call
+/// once at startup, before anything touches the data directory
+pub fn init(args: impl IntoIterator<Item = String>) {
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf));
+    let marker = exe_dir
+        .as_ref()
+        .and_then(|dir| std::fs::read_to_string(dir.join(MARKER_FILE)).ok());
+    let dir = resolve(args, exe_dir.as_deref(), marker.as_deref());
+    if let Some(dir) = &dir {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            eprintln!("Could not create portable directory {}: {}", dir.display(), e);
+        }
+    }
+    let _ = PORTABLE_DIR.set(dir);
+}
+
+/// The portable data directory, when running portable
+pub fn dir() -> Option<&'static Path> {
+    PORTABLE_DIR.get().and_then(|dir| dir.as_deref())
+}
+
+pub fn is_portable() -> bool {
+    dir().is_some()
+}
+
+/// Where the data goes given the arguments, the executable's directory and
+/// the marker file's contents. The command line wins over the marker.
+fn resolve(args: impl IntoIterator<Item = String>, exe_dir: Option<&Path>, marker: Option<&str>) -> Option<PathBuf> {
+    let base = exe_dir.map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+    let from_args = args.into_iter().find_map(|arg| match arg.as_str() {
+        "--portable" => Some(None),
+        _ => arg.strip_prefix("--portable=").map(|dir| Some(dir.to_string())),
+    });
+    let named = match from_args {
+        Some(named) => named,
+        None => {
+            let marker = marker?;
+            marker.lines().next().map(str::trim).filter(|line| !line.is_empty()).map(str::to_string)
+        }
+    };
+    Some(match named {
+        Some(dir) => base.join(dir),
+        None => base.join(DEFAULT_DIR),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_resolve() {
+        let exe = Path::new("/media/usb/tabssh");
+        assert_eq!(resolve(args(&["tabssh"]), Some(exe), None), None);
+        assert_eq!(resolve(args(&["tabssh", "--portable"]), Some(exe), None), Some(exe.join("tabssh-data")));
+        assert_eq!(resolve(args(&["tabssh", "--portable=data"]), Some(exe), None), Some(exe.join("data")));
+        assert_eq!(resolve(args(&["tabssh", "--portable=/tmp/t"]), Some(exe), None), Some(PathBuf::from("/tmp/t")));
+
+        assert_eq!(resolve(args(&["tabssh"]), Some(exe), Some("")), Some(exe.join("tabssh-data")));
+        assert_eq!(resolve(args(&["tabssh"]), Some(exe), Some("  ../profile \n# notes")), Some(exe.join("../profile")));
+        assert_eq!(resolve(args(&["tabssh", "--portable=cli"]), Some(exe), Some("marker")), Some(exe.join("cli")));
+    }
+}
@@ -0,0 +1,105 @@
+//! Window transparency and blur-behind
+//!
+//! The window gets an alpha channel at creation whenever the desktop can
+//! composite it, so opacity can change live afterwards. Only the terminal
+//! background is drawn translucent; panels and dialogs stay opaque. Blur
+//! uses DWM on Windows and NSVisualEffectView on macOS. Elsewhere the
+//! window is only translucent, and opaque where nothing composites it.
+
+use anyhow::Result;
+
+/// What the desktop can do with a translucent window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransparencySupport {
+    pub transparent: bool,
+    pub blur: bool,
+}
+
+impl TransparencySupport {
+    /// Why opacity or blur settings have no effect, if they don't
+    pub fn limitation(&self) -> Option<&'static str> {
+        match (self.transparent, self.blur) {
+            (false, _) => Some("This desktop does not composite windows; the window stays opaque"),
+            (true, false) => Some("Background blur is not available on this desktop"),
+            (true, true) => None,
+        }
+    }
+}
+
+/// Probe the desktop; call before creating the window
+pub fn support() -> TransparencySupport {
+    if cfg!(any(target_os = "windows", target_os = "macos")) {
+        TransparencySupport { transparent: true, blur: true }
+    } else {
+        TransparencySupport { transparent: unix_compositing(), blur: false }
+    }
+}
+
+/// Wayland always composites. X11 only does with a compositing manager,
+/// which every mainstream desktop but a few lightweight ones runs.
+fn unix_compositing() -> bool {
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        return true;
+    }
+    let desktop = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default().to_lowercase();
+    const COMPOSITING: [&str; 7] = ["gnome", "kde", "cinnamon", "budgie", "pantheon", "unity", "deepin"];
+    desktop.split(':').any(|d| COMPOSITING.contains(&d))
+}
+
+/// Turn blur-behind on or off for the main window
+pub fn set_blur(frame: &eframe::Frame, enabled: bool) -> Result<()> {
+    #[cfg(target_os = "windows")]
+    {
+        if enabled {
+            window_vibrancy::apply_blur(frame, None)?;
+        } else {
+            window_vibrancy::clear_blur(frame)?;
+        }
+        return Ok(());
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        use window_vibrancy::NSVisualEffectMaterial;
+        if enabled {
+            window_vibrancy::apply_vibrancy(frame, NSVisualEffectMaterial::UnderWindowBackground, None, None)?;
+        } else {
+            window_vibrancy::clear_vibrancy(frame)?;
+        }
+        return Ok(());
+    }
+
+    #[allow(unreachable_code)]
+    {
+        let _ = frame;
+        if enabled {
+            anyhow::bail!("Background blur is not available on this desktop");
+        }
+        Ok(())
+    }
+}
+
+/// Alpha of the terminal background at `opacity` percent; opaque when the
+/// window cannot be translucent
+pub fn background_alpha(opacity: u8, support: TransparencySupport) -> u8 {
+    if !support.transparent {
+        return 255;
+    }
+    (opacity.clamp(10, 100) as u32 * 255 / 100) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_background_alpha() {
+        let supported = TransparencySupport { transparent: true, blur: false };
+        let unsupported = TransparencySupport { transparent: false, blur: false };
+        assert_eq!(background_alpha(100, supported), 255);
+        assert_eq!(background_alpha(50, supported), 127);
+        // Fully see-through would hide the terminal entirely
+        assert_eq!(background_alpha(0, supported), 25);
+        assert_eq!(background_alpha(50, unsupported), 255);
+    }
+}
@@ -1,5 +1,17 @@
 //! Windows-specific functionality
 
+use anyhow::Result;
+use std::path::Path;
+use std::process::Command;
+
 pub fn setup() {
     log::info!("Windowsplatforminitialization");
 }
+
+/// Reveal `path` in Explorer with the item selected
+pub fn reveal_in_file_manager(path: &Path) -> Result<()> {
+    let mut select = std::ffi::OsString::from("/select,");
+    select.push(path.as_os_str());
+    Command::new("explorer").arg(select).spawn()?;
+    Ok(())
+}
@@ -0,0 +1,249 @@
+//! Plugin manifest (`plugin.toml`)
+//!
+//! ```toml
+//! id = "com.example.inventory"
+//! name = "Inventory"
+//! version = "0.1.0"
+//! entry = "inventory.wasm"
+//!
+//! [permissions]
+//! filesystem = ["~/.config/inventory"]
+//! network = ["*.corp.example.com"]
+//!
+//! [[panels]]
+//! id = "hosts"
+//! title = "Inventory"
+//!
+//! [[context_actions]]
+//! id = "tag"
+//! label = "Tag host..."
+//! target = "connection"
+//!
+//! [[connection_types]]
+//! id = "inventory"
+//! name = "Inventory host"
+//! ```
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Parsed `plugin.toml`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginManifest {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub author: String,
+    /// WASM module, relative to the plugin directory
+    pub entry: String,
+    #[serde(default)]
+    pub permissions: PluginPermissions,
+    #[serde(default)]
+    pub panels: Vec<PanelContribution>,
+    #[serde(default)]
+    pub context_actions: Vec<ContextActionContribution>,
+    #[serde(default)]
+    pub connection_types: Vec<ConnectionTypeContribution>,
+}
+
+impl PluginManifest {
+    pub fn parse(text: &str) -> Result<Self> {
+        let manifest: Self = toml::from_str(text)?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.id.trim().is_empty() || self.id.contains(['/', '\\']) {
+            return Err(anyhow!("Invalid plugin id '{}'", self.id));
+        }
+        // The entry must stay inside the plugin directory
+        let entry = Path::new(&self.entry);
+        if entry.is_absolute() || entry.components().any(|c| matches!(c, std::path::Component::ParentDir)) {
+            return Err(anyhow!("Plugin entry must be a relative path inside the plugin folder"));
+        }
+        Ok(())
+    }
+}
+
+/// Sandbox rules declared by a plugin and shown to the user before enabling
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PluginPermissions {
+    /// Directories the plugin may read and write (`~` is expanded)
+    #[serde(default)]
+    pub filesystem: Vec<String>,
+    /// Hosts the plugin may direct connections to; `*.domain` matches subdomains
+    #[serde(default)]
+    pub network: Vec<String>,
+}
+
+impl PluginPermissions {
+    pub fn is_empty(&self) -> bool {
+        self.filesystem.is_empty() && self.network.is_empty()
+    }
+
+    /// Whether `path` lies inside one of the granted directories
+    pub fn allows_path(&self, path: &Path) -> bool {
+        self.resolve_path(path).is_some()
+    }
+
+    /// `path` with symlinks resolved, if that lies inside one of the granted
+    /// directories. File access must go to the returned path, so a link
+    /// inside a granted directory can't lead out of it.
+    pub fn resolve_path(&self, path: &Path) -> Option<PathBuf> {
+        let path = resolve(path)?;
+        self.filesystem
+            .iter()
+            .any(|root| {
+                let root = PathBuf::from(shellexpand::tilde(root).as_ref());
+                resolve(&root).is_some_and(|root| path.starts_with(root))
+            })
+            .then_some(path)
+    }
+
+    /// Whether connections to `host` are allowed
+    pub fn allows_host(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        self.network.iter().any(|pattern| {
+            let pattern = pattern.to_ascii_lowercase();
+            match pattern.strip_prefix("*.") {
+                Some(domain) => host.ends_with(&format!(".{}", domain)),
+                None => pattern == "*" || pattern == host,
+            }
+        })
+    }
+}
+
+/// Resolve `.` and `..` lexically so `granted/../secret` can't escape
+fn normalize(path: &Path) -> Option<PathBuf> {
+    use std::path::Component;
+
+    if !path.is_absolute() {
+        return None;
+    }
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                if !out.pop() {
+                    return None;
+                }
+            }
+            Component::CurDir => {}
+            other => out.push(other),
+        }
+    }
+    Some(out)
+}
+
+/// Normalize `path`, then follow symlinks in the part of it that exists.
+/// Components that don't exist yet, like a file about to be written, are
+/// kept as they are; a dangling link resolves to nothing.
+fn resolve(path: &Path) -> Option<PathBuf> {
+    let path = normalize(path)?;
+    let existing = path.ancestors().find(|p| p.symlink_metadata().is_ok())?;
+    let rest = path.strip_prefix(existing).ok()?;
+    Some(std::fs::canonicalize(existing).ok()?.join(rest))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PanelContribution {
+    pub id: String,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextTarget {
+    Connection,
+    File,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextActionContribution {
+    pub id: String,
+    pub label: String,
+    pub target: ContextTarget,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionTypeContribution {
+    pub id: String,
+    pub name: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+        id = "com.example.inventory"
+        name = "Inventory"
+        version = "0.1.0"
+        entry = "inventory.wasm"
+
+        [permissions]
+        filesystem = ["/srv/inventory"]
+        network = ["*.corp.example.com", "bastion.example.com"]
+
+        [[panels]]
+        id = "hosts"
+        title = "Inventory"
+
+        [[context_actions]]
+        id = "tag"
+        label = "Tag host..."
+        target = "connection"
+    "#;
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest = PluginManifest::parse(MANIFEST).unwrap();
+        assert_eq!(manifest.panels.len(), 1);
+        assert_eq!(manifest.context_actions[0].target, ContextTarget::Connection);
+        assert!(manifest.connection_types.is_empty());
+    }
+
+    #[test]
+    fn test_rejects_escaping_entry() {
+        let text = MANIFEST.replace("inventory.wasm", "../evil.wasm");
+        assert!(PluginManifest::parse(&text).is_err());
+    }
+
+    #[test]
+    fn test_permissions() {
+        let permissions = PluginManifest::parse(MANIFEST).unwrap().permissions;
+
+        assert!(permissions.allows_path(Path::new("/srv/inventory/hosts.json")));
+        assert!(!permissions.allows_path(Path::new("/srv/inventory/../secrets")));
+        assert!(!permissions.allows_path(Path::new("relative/path")));
+
+        assert!(permissions.allows_host("db1.corp.example.com"));
+        assert!(permissions.allows_host("BASTION.example.com"));
+        assert!(!permissions.allows_host("corp.example.com.evil.net"));
+        assert!(!permissions.allows_host("example.com"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_cannot_leave_granted_dir() {
+        let granted = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("secret"), "x").unwrap();
+        std::os::unix::fs::symlink(outside.path(), granted.path().join("link")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("gone"), granted.path().join("dangling")).unwrap();
+
+        let permissions = PluginPermissions {
+            filesystem: vec![granted.path().to_string_lossy().to_string()],
+            network: Vec::new(),
+        };
+        assert!(permissions.allows_path(&granted.path().join("new.json")));
+        assert!(!permissions.allows_path(&granted.path().join("link/secret")));
+        assert!(!permissions.allows_path(&granted.path().join("link/new.json")));
+        assert!(!permissions.allows_path(&granted.path().join("dangling")));
+    }
+}
@@ -0,0 +1,17 @@
+//! Plugin system
+//!
+//! Plugins live in their own folder under the plugin directory, each with a
+//! `plugin.toml` manifest and a WebAssembly entry module. They can add side
+//! panels, context-menu actions and connection types, and run sandboxed
+//! with only the filesystem and network access their manifest declares.
+
+mod manifest;
+mod registry;
+mod runtime;
+
+pub use manifest::{
+    ConnectionTypeContribution, ContextActionContribution, ContextTarget, PanelContribution,
+    PluginManifest, PluginPermissions,
+};
+pub use registry::{PluginEntry, PluginRegistry};
+pub use runtime::{ConnectionTarget, PanelContent, PanelItem, PluginInstance};
@@ -0,0 +1,178 @@
+//! Plugin discovery and enable/disable state
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::manifest::{
+    ConnectionTypeContribution, ContextActionContribution, ContextTarget, PanelContribution,
+    PluginManifest,
+};
+use super::runtime::PluginInstance;
+use crate::storage::database::Database;
+
+/// A plugin found on disk
+pub struct PluginEntry {
+    pub manifest: PluginManifest,
+    pub dir: PathBuf,
+    pub enabled: bool,
+    /// Set while the plugin is enabled and loaded successfully
+    pub instance: Option<PluginInstance>,
+    /// Last load or call error, shown in the plugin manager
+    pub error: Option<String>,
+}
+
+/// All installed plugins
+pub struct PluginRegistry {
+    dir: PathBuf,
+    pub plugins: Vec<PluginEntry>,
+}
+
+impl PluginRegistry {
+    /// Default plugin directory: `<data dir>/tabssh/plugins`, or `plugins`
+    /// in the portable directory
+    pub fn default_dir() -> PathBuf {
+        crate::platform::PlatformManager::get_data_directory()
+            .unwrap_or_else(|| PathBuf::from("tabssh"))
+            .join("plugins")
+    }
+
+    /// Scan `dir` for `*/plugin.toml` and load the enabled plugins
+    pub fn discover(dir: PathBuf, db: &Database) -> Self {
+        let enabled = load_enabled_state(db);
+        let mut plugins = Vec::new();
+
+        if let Ok(entries) = std::fs::read_dir(&dir) {
+            for entry in entries.flatten() {
+                let plugin_dir = entry.path();
+                let manifest_path = plugin_dir.join("plugin.toml");
+                if !manifest_path.is_file() {
+                    continue;
+                }
+
+                let manifest = match std::fs::read_to_string(&manifest_path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|text| PluginManifest::parse(&text))
+                {
+                    Ok(manifest) => manifest,
+                    Err(e) => {
+                        log::warn!("Skipping plugin in {}: {}", plugin_dir.display(), e);
+                        continue;
+                    }
+                };
+
+                // New plugins start disabled so their permissions are reviewed first
+                let is_enabled = enabled.get(&manifest.id).copied().unwrap_or(false);
+                let mut plugin = PluginEntry {
+                    manifest,
+                    dir: plugin_dir,
+                    enabled: is_enabled,
+                    instance: None,
+                    error: None,
+                };
+                if is_enabled {
+                    load(&mut plugin);
+                }
+                plugins.push(plugin);
+            }
+        }
+
+        plugins.sort_by(|a, b| a.manifest.name.to_lowercase().cmp(&b.manifest.name.to_lowercase()));
+        log::info!("Found {} plugin(s) in {}", plugins.len(), dir.display());
+
+        Self { dir, plugins }
+    }
+
+    pub fn dir(&self) -> &PathBuf {
+        &self.dir
+    }
+
+    /// Enable or disable a plugin and remember the choice
+    pub fn set_enabled(&mut self, db: &Database, id: &str, enabled: bool) -> Result<()> {
+        if let Some(plugin) = self.plugins.iter_mut().find(|p| p.manifest.id == id) {
+            plugin.enabled = enabled;
+            if enabled {
+                load(plugin);
+            } else {
+                plugin.instance = None;
+                plugin.error = None;
+            }
+        }
+        self.save_enabled_state(db)
+    }
+
+    fn save_enabled_state(&self, db: &Database) -> Result<()> {
+        let state: HashMap<_, _> = self.plugins.iter()
+            .map(|p| (p.manifest.id.clone(), p.enabled))
+            .collect();
+        let json = serde_json::to_string(&state)?;
+
+        db.connection().execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('plugin_state', ?1)",
+            [&json],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn instance_mut(&mut self, id: &str) -> Option<&mut PluginInstance> {
+        self.plugins.iter_mut()
+            .find(|p| p.manifest.id == id)
+            .and_then(|p| p.instance.as_mut())
+    }
+
+    fn active(&self) -> impl Iterator<Item = &PluginEntry> {
+        self.plugins.iter().filter(|p| p.instance.is_some())
+    }
+
+    /// Side panels from loaded plugins, as `(plugin id, panel)`
+    pub fn panels(&self) -> Vec<(String, PanelContribution)> {
+        self.active()
+            .flat_map(|p| p.manifest.panels.iter().map(move |panel| (p.manifest.id.clone(), panel.clone())))
+            .collect()
+    }
+
+    /// Context-menu actions for connections or files, as `(plugin id, action)`
+    pub fn context_actions(&self, target: ContextTarget) -> Vec<(String, ContextActionContribution)> {
+        self.active()
+            .flat_map(|p| {
+                p.manifest.context_actions.iter()
+                    .filter(move |a| a.target == target)
+                    .map(move |a| (p.manifest.id.clone(), a.clone()))
+            })
+            .collect()
+    }
+
+    /// Extra connection types, as `(plugin id, type)`
+    pub fn connection_types(&self) -> Vec<(String, ConnectionTypeContribution)> {
+        self.active()
+            .flat_map(|p| p.manifest.connection_types.iter().map(move |t| (p.manifest.id.clone(), t.clone())))
+            .collect()
+    }
+}
+
+fn load(plugin: &mut PluginEntry) {
+    match PluginInstance::load(&plugin.dir, &plugin.manifest) {
+        Ok(instance) => {
+            plugin.instance = Some(instance);
+            plugin.error = None;
+        }
+        Err(e) => {
+            log::warn!("Failed to load plugin '{}': {}", plugin.manifest.id, e);
+            plugin.instance = None;
+            plugin.error = Some(e.to_string());
+        }
+    }
+}
+
+fn load_enabled_state(db: &Database) -> HashMap<String, bool> {
+    db.connection()
+        .query_row(
+            "SELECT value FROM settings WHERE key = 'plugin_state'",
+            [],
+            |row| row.get::<_, String>(0),
+        )
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
@@ -0,0 +1,323 @@
+//! WASM plugin host
+//!
+//! Plugins are WebAssembly modules run in an interpreter with a fuel limit
+//! per call. They have no direct access to the filesystem or network; the
+//! only way out is through the `tabssh` host functions below, which check
+//! the manifest's permissions.
+//!
+//! ABI: strings cross the boundary as UTF-8 `(ptr, len)` pairs in the
+//! module's exported `memory`. The plugin exports `alloc(len) -> ptr` so the
+//! host can pass arguments in, and returns strings packed as
+//! `(ptr << 32) | len` in an `i64`.
+//!
+//! Exports used by the host (all optional except `memory` and `alloc`):
+//! - `render_panel(id_ptr, id_len) -> i64` - panel JSON, see [`PanelContent`]
+//! - `on_event(panel_ptr, panel_len, event_ptr, event_len)`
+//! - `on_action(action_ptr, action_len, ctx_ptr, ctx_len)`
+//! - `resolve_connection(type_ptr, type_len, input_ptr, input_len) -> i64` -
+//!   [`ConnectionTarget`] JSON
+//!
+//! Imports provided under the `tabssh` module:
+//! - `log(ptr, len)`
+//! - `read_file(path_ptr, path_len) -> i64` - contents, or 0 if denied/missing
+//! - `write_file(path_ptr, path_len, data_ptr, data_len) -> i32` - 0 on success
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use wasmi::{Caller, Config, Engine, Extern, Instance, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use super::manifest::{PluginManifest, PluginPermissions};
+
+/// Instruction budget for a single call into a plugin
+const FUEL_PER_CALL: u64 = 50_000_000;
+/// Largest file a plugin may read through `read_file`
+const MAX_READ_BYTES: u64 = 4 * 1024 * 1024;
+/// Linear memory a plugin may grow to
+const MAX_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+/// Largest string or buffer copied out of plugin memory at once
+const MAX_TRANSFER_BYTES: usize = 16 * 1024 * 1024;
+
+struct HostState {
+    plugin_id: String,
+    permissions: PluginPermissions,
+    limits: StoreLimits,
+}
+
+/// One element of a plugin side panel
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum PanelItem {
+    Heading { text: String },
+    Label { text: String },
+    Button { id: String, label: String },
+    Separator,
+}
+
+/// Content of a plugin side panel, re-requested after every event
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PanelContent {
+    pub items: Vec<PanelItem>,
+}
+
+/// Where a plugin-defined connection type resolves to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionTarget {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    pub username: String,
+}
+
+fn default_port() -> u16 {
+    22
+}
+
+/// A loaded, instantiated plugin
+pub struct PluginInstance {
+    store: Store<HostState>,
+    instance: Instance,
+    memory: Memory,
+    /// Total fuel added to the store so far
+    fuel_granted: u64,
+}
+
+impl PluginInstance {
+    /// Compile and instantiate the plugin's WASM entry
+    pub fn load(dir: &Path, manifest: &PluginManifest) -> Result<Self> {
+        let wasm = std::fs::read(dir.join(&manifest.entry))
+            .with_context(|| format!("Failed to read {}", manifest.entry))?;
+
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, &wasm[..])?;
+
+        let mut store = Store::new(&engine, HostState {
+            plugin_id: manifest.id.clone(),
+            permissions: manifest.permissions.clone(),
+            limits: StoreLimitsBuilder::new()
+                .memory_size(MAX_MEMORY_BYTES)
+                .memories(1)
+                .tables(1)
+                .instances(1)
+                .build(),
+        });
+        store.limiter(|state| &mut state.limits);
+        store.add_fuel(FUEL_PER_CALL).map_err(|e| anyhow!("{e}"))?;
+
+        let mut linker = Linker::<HostState>::new(&engine);
+        link_host_functions(&mut linker)?;
+
+        let instance = linker.instantiate(&mut store, &module)?.start(&mut store)?;
+        let memory = instance.get_memory(&store, "memory")
+            .ok_or_else(|| anyhow!("Plugin does not export memory"))?;
+
+        Ok(Self { store, instance, memory, fuel_granted: FUEL_PER_CALL })
+    }
+
+    pub fn render_panel(&mut self, panel_id: &str) -> Result<PanelContent> {
+        let json = self.call_returning_string("render_panel", &[panel_id])?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    pub fn on_event(&mut self, panel_id: &str, event_id: &str) -> Result<()> {
+        self.call_void("on_event", &[panel_id, event_id])
+    }
+
+    /// Run a context-menu action; `context` is JSON describing the selection
+    pub fn on_action(&mut self, action_id: &str, context: &str) -> Result<()> {
+        self.call_void("on_action", &[action_id, context])
+    }
+
+    /// Ask a plugin connection type where to connect. The target is checked
+    /// against the network permissions before it is returned.
+    pub fn resolve_connection(&mut self, type_id: &str, input: &str) -> Result<ConnectionTarget> {
+        let json = self.call_returning_string("resolve_connection", &[type_id, input])?;
+        let target: ConnectionTarget = serde_json::from_str(&json)?;
+
+        if !self.store.data().permissions.allows_host(&target.host) {
+            return Err(anyhow!(
+                "Plugin '{}' is not allowed to connect to {}",
+                self.store.data().plugin_id,
+                target.host
+            ));
+        }
+        Ok(target)
+    }
+
+    /// Top the store back up to a full per-call budget
+    fn refuel(&mut self) -> Result<()> {
+        let consumed = self.store.fuel_consumed().unwrap_or(0);
+        let remaining = self.fuel_granted.saturating_sub(consumed);
+        let top_up = FUEL_PER_CALL.saturating_sub(remaining);
+        if top_up > 0 {
+            self.store.add_fuel(top_up).map_err(|e| anyhow!("{e}"))?;
+            self.fuel_granted += top_up;
+        }
+        Ok(())
+    }
+
+    fn pass_strings(&mut self, args: &[&str]) -> Result<Vec<i32>> {
+        let alloc = self.instance.get_typed_func::<i32, i32>(&self.store, "alloc")?;
+        let mut params = Vec::with_capacity(args.len() * 2);
+        for arg in args {
+            let len = arg.len() as i32;
+            let ptr = alloc.call(&mut self.store, len)?;
+            self.memory.write(&mut self.store, ptr as usize, arg.as_bytes()).map_err(|e| anyhow!("{e}"))?;
+            params.push(ptr);
+            params.push(len);
+        }
+        Ok(params)
+    }
+
+    fn call_void(&mut self, name: &str, args: &[&str]) -> Result<()> {
+        let Some(func) = self.instance.get_func(&self.store, name) else {
+            return Ok(());
+        };
+        self.refuel()?;
+        let params: Vec<_> = self.pass_strings(args)?.into_iter().map(wasmi::Value::I32).collect();
+        func.call(&mut self.store, &params, &mut [])?;
+        Ok(())
+    }
+
+    fn call_returning_string(&mut self, name: &str, args: &[&str]) -> Result<String> {
+        let func = self.instance.get_func(&self.store, name)
+            .ok_or_else(|| anyhow!("Plugin does not export {}", name))?;
+        self.refuel()?;
+        let params: Vec<_> = self.pass_strings(args)?.into_iter().map(wasmi::Value::I32).collect();
+        let mut result = [wasmi::Value::I64(0)];
+        func.call(&mut self.store, &params, &mut result)?;
+
+        let packed = result[0].i64().ok_or_else(|| anyhow!("{} must return i64", name))?;
+        let (ptr, len) = unpack(packed);
+        let data = self.memory.data(&self.store);
+        let range = checked_range(ptr as i64, len as i64, data.len())
+            .ok_or_else(|| anyhow!("{} returned {} bytes at {:#x}, outside plugin memory", name, len, ptr))?;
+        Ok(String::from_utf8(data[range].to_vec())?)
+    }
+}
+
+fn unpack(packed: i64) -> (usize, usize) {
+    (((packed as u64) >> 32) as usize, (packed as u64 & 0xffff_ffff) as usize)
+}
+
+fn pack(ptr: i32, len: i32) -> i64 {
+    (((ptr as u32) as u64) << 32 | (len as u32) as u64) as i64
+}
+
+fn caller_memory(caller: &Caller<'_, HostState>) -> Option<Memory> {
+    caller.get_export("memory").and_then(Extern::into_memory)
+}
+
+/// The bytes `ptr..ptr + len` of a memory of `memory_size` bytes, if they
+/// lie inside it and are no more than `MAX_TRANSFER_BYTES`. Both numbers
+/// come from the plugin, so this is checked before anything is allocated.
+fn checked_range(ptr: i64, len: i64, memory_size: usize) -> Option<std::ops::Range<usize>> {
+    if ptr < 0 || len < 0 || len > MAX_TRANSFER_BYTES as i64 {
+        return None;
+    }
+    let end = ptr.checked_add(len)?;
+    if end > memory_size as i64 {
+        return None;
+    }
+    Some(ptr as usize..end as usize)
+}
+
+fn read_bytes(caller: &Caller<'_, HostState>, ptr: i32, len: i32) -> Option<Vec<u8>> {
+    let memory = caller_memory(caller)?;
+    let data = memory.data(caller);
+    let range = checked_range(ptr as i64, len as i64, data.len())?;
+    Some(data[range].to_vec())
+}
+
+fn read_str(caller: &Caller<'_, HostState>, ptr: i32, len: i32) -> Option<String> {
+    String::from_utf8(read_bytes(caller, ptr, len)?).ok()
+}
+
+fn link_host_functions(linker: &mut Linker<HostState>) -> Result<()> {
+    linker.func_wrap("tabssh", "log", |caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+        if let Some(message) = read_str(&caller, ptr, len) {
+            log::info!("[plugin {}] {}", caller.data().plugin_id, message);
+        }
+    })?;
+
+    linker.func_wrap("tabssh", "read_file", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> i64 {
+        let Some(path) = read_str(&caller, ptr, len) else {
+            return 0;
+        };
+        let Some(path) = caller.data().permissions.resolve_path(Path::new(&path)) else {
+            log::warn!("Plugin '{}' denied read of {}", caller.data().plugin_id, path);
+            return 0;
+        };
+        let too_big = std::fs::metadata(&path).map(|m| m.len() > MAX_READ_BYTES).unwrap_or(true);
+        let Ok(data) = (if too_big { Err(()) } else { std::fs::read(&path).map_err(|_| ()) }) else {
+            return 0;
+        };
+
+        // Copy the contents into plugin memory via its allocator
+        let Some(alloc) = caller.get_export("alloc").and_then(Extern::into_func) else {
+            return 0;
+        };
+        let Ok(alloc) = alloc.typed::<i32, i32>(&caller) else {
+            return 0;
+        };
+        let Ok(dest) = alloc.call(&mut caller, data.len() as i32) else {
+            return 0;
+        };
+        match caller_memory(&caller) {
+            Some(memory) if memory.write(&mut caller, dest as usize, &data).is_ok() => pack(dest, data.len() as i32),
+            _ => 0,
+        }
+    })?;
+
+    linker.func_wrap(
+        "tabssh",
+        "write_file",
+        |caller: Caller<'_, HostState>, path_ptr: i32, path_len: i32, data_ptr: i32, data_len: i32| -> i32 {
+            let Some(path) = read_str(&caller, path_ptr, path_len) else {
+                return -1;
+            };
+            let Some(path) = caller.data().permissions.resolve_path(Path::new(&path)) else {
+                log::warn!("Plugin '{}' denied write to {}", caller.data().plugin_id, path);
+                return -1;
+            };
+            let Some(data) = read_bytes(&caller, data_ptr, data_len) else {
+                return -1;
+            };
+            if std::fs::write(&path, data).is_ok() { 0 } else { -1 }
+        },
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_roundtrip() {
+        assert_eq!(unpack(pack(0x1000, 42)), (0x1000, 42));
+    }
+
+    #[test]
+    fn test_checked_range() {
+        assert_eq!(checked_range(16, 4, 64), Some(16..20));
+        assert_eq!(checked_range(60, 4, 64), Some(60..64));
+        assert_eq!(checked_range(61, 4, 64), None);
+        assert_eq!(checked_range(-1, 4, 64), None);
+        assert_eq!(checked_range(0, -4, 64), None);
+        assert_eq!(checked_range(0, MAX_TRANSFER_BYTES as i64 + 1, usize::MAX), None);
+        // An unpacked i64 return value can put both halves near u32::MAX
+        assert_eq!(checked_range(u32::MAX as i64, u32::MAX as i64, MAX_MEMORY_BYTES), None);
+    }
+
+    #[test]
+    fn test_panel_json() {
+        let json = r#"{"items":[{"type":"heading","text":"Hosts"},{"type":"button","id":"sync","label":"Sync"},{"type":"separator"}]}"#;
+        let panel: PanelContent = serde_json::from_str(json).unwrap();
+        assert_eq!(panel.items.len(), 3);
+        assert_eq!(panel.items[1], PanelItem::Button { id: "sync".into(), label: "Sync".into() });
+    }
+}
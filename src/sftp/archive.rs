@@ -0,0 +1,431 @@
+//! Remote archive operations (compress / extract)
+//!
+//! Archives are built on the server with `tar`/`zip`/`unzip` over an exec
+//! channel. When exec is unavailable or the tool is missing, the files are
+//! pulled down over SFTP, processed locally and pushed back.
+
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+use tokio::sync::mpsc;
+
+use super::client::SftpClient;
+use crate::ssh::SshConnection;
+use crate::utils::helpers::shell_quote;
+
+/// Supported archive formats
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveFormat {
+    TarGz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// File extension including the leading dot
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::TarGz => ".tar.gz",
+            ArchiveFormat::Zip => ".zip",
+        }
+    }
+
+    /// Detect the archive format from a file name
+    pub fn detect(name: &str) -> Option<Self> {
+        let lower = name.to_lowercase();
+        if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else if lower.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else {
+            None
+        }
+    }
+
+    /// Remote command that packs `names` (relative to `dir`) into `archive`
+    pub fn compress_command(&self, dir: &str, names: &[String], archive: &str) -> String {
+        let items: Vec<String> = names.iter().map(|n| shell_quote(n)).collect();
+        match self {
+            ArchiveFormat::TarGz => format!(
+                "cd {} && tar -czf {} -- {}",
+                shell_quote(dir),
+                shell_quote(archive),
+                items.join(" ")
+            ),
+            ArchiveFormat::Zip => format!(
+                "cd {} && zip -qr {} -- {}",
+                shell_quote(dir),
+                shell_quote(archive),
+                items.join(" ")
+            ),
+        }
+    }
+
+    /// Remote command that unpacks `archive` into `dir`
+    pub fn extract_command(&self, dir: &str, archive: &str) -> String {
+        match self {
+            ArchiveFormat::TarGz => format!(
+                "cd {} && tar -xzf {}",
+                shell_quote(dir),
+                shell_quote(archive)
+            ),
+            ArchiveFormat::Zip => format!(
+                "cd {} && unzip -q -o {}",
+                shell_quote(dir),
+                shell_quote(archive)
+            ),
+        }
+    }
+}
+
+/// Default archive name for a selection
+pub fn archive_name_for(names: &[String], format: ArchiveFormat) -> String {
+    let stem = match names {
+        [single] => single.trim_end_matches('/').to_string(),
+        _ => "archive".to_string(),
+    };
+    format!("{}{}", stem, format.extension())
+}
+
+/// Progress stage of an archive job
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArchiveStage {
+    RunningRemote,
+    Downloading,
+    ProcessingLocally,
+    Uploading,
+}
+
+impl std::fmt::Display for ArchiveStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArchiveStage::RunningRemote => write!(f, "Running on server"),
+            ArchiveStage::Downloading => write!(f, "Downloading"),
+            ArchiveStage::ProcessingLocally => write!(f, "Processing locally"),
+            ArchiveStage::Uploading => write!(f, "Uploading"),
+        }
+    }
+}
+
+/// Events emitted while an archive job runs
+#[derive(Debug, Clone)]
+pub enum ArchiveEvent {
+    Stage(ArchiveStage),
+    /// Bytes moved during a fallback transfer (done, total)
+    Progress(u64, u64),
+    /// The remote tool was unavailable; falling back to SFTP with the reason
+    FallingBack(String),
+    Finished,
+    Failed(String),
+}
+
+/// Compress remote entries in `dir` into a new archive next to them.
+/// Returns the archive file name.
+pub async fn compress_remote(
+    conn: &SshConnection,
+    sftp: &mut SftpClient,
+    dir: &str,
+    names: &[String],
+    format: ArchiveFormat,
+    events: &mpsc::UnboundedSender<ArchiveEvent>,
+) -> Result<String> {
+    let archive = archive_name_for(names, format);
+    let result = compress_inner(conn, sftp, dir, names, &archive, format, events).await;
+    report(events, &result);
+    result.map(|_| archive)
+}
+
+/// Extract a remote archive into the directory that contains it
+pub async fn extract_remote(
+    conn: &SshConnection,
+    sftp: &mut SftpClient,
+    dir: &str,
+    archive: &str,
+    events: &mpsc::UnboundedSender<ArchiveEvent>,
+) -> Result<()> {
+    let format = ArchiveFormat::detect(archive)
+        .ok_or_else(|| anyhow!("{} is not a supported archive", archive))?;
+    let result = extract_inner(conn, sftp, dir, archive, format, events).await;
+    report(events, &result);
+    result
+}
+
+fn report(events: &mpsc::UnboundedSender<ArchiveEvent>, result: &Result<()>) {
+    let _ = events.send(match result {
+        Ok(()) => ArchiveEvent::Finished,
+        Err(e) => ArchiveEvent::Failed(format!("{:#}", e)),
+    });
+}
+
+async fn compress_inner(
+    conn: &SshConnection,
+    sftp: &mut SftpClient,
+    dir: &str,
+    names: &[String],
+    archive: &str,
+    format: ArchiveFormat,
+    events: &mpsc::UnboundedSender<ArchiveEvent>,
+) -> Result<()> {
+    let _ = events.send(ArchiveEvent::Stage(ArchiveStage::RunningRemote));
+    match conn.exec(&format.compress_command(dir, names, archive)).await {
+        Ok(output) if !output.command_not_found() => {
+            output.into_result("Remote archiver")?;
+            return Ok(());
+        }
+        Ok(output) => {
+            let _ = events.send(ArchiveEvent::FallingBack(output.stderr.trim().to_string()));
+        }
+        Err(e) => {
+            let _ = events.send(ArchiveEvent::FallingBack(e.to_string()));
+        }
+    }
+
+    let work = scratch_dir()?;
+    let result = async {
+        let _ = events.send(ArchiveEvent::Stage(ArchiveStage::Downloading));
+        for name in names {
+            download_tree(sftp, &remote_join(dir, name), &work.join(name), events).await?;
+        }
+
+        let _ = events.send(ArchiveEvent::Stage(ArchiveStage::ProcessingLocally));
+        let local_archive = work.join(archive);
+        run_local(local_compress_command(format, &work, names, &local_archive)).await?;
+
+        let _ = events.send(ArchiveEvent::Stage(ArchiveStage::Uploading));
+        sftp.upload_file(&local_archive, Path::new(&remote_join(dir, archive)), Some(progress_forwarder(events)))
+            .await
+            .context("Failed to upload archive")
+    }
+    .await;
+
+    let _ = std::fs::remove_dir_all(&work);
+    result
+}
+
+async fn extract_inner(
+    conn: &SshConnection,
+    sftp: &mut SftpClient,
+    dir: &str,
+    archive: &str,
+    format: ArchiveFormat,
+    events: &mpsc::UnboundedSender<ArchiveEvent>,
+) -> Result<()> {
+    let _ = events.send(ArchiveEvent::Stage(ArchiveStage::RunningRemote));
+    match conn.exec(&format.extract_command(dir, archive)).await {
+        Ok(output) if !output.command_not_found() => {
+            output.into_result("Remote extractor")?;
+            return Ok(());
+        }
+        Ok(output) => {
+            let _ = events.send(ArchiveEvent::FallingBack(output.stderr.trim().to_string()));
+        }
+        Err(e) => {
+            let _ = events.send(ArchiveEvent::FallingBack(e.to_string()));
+        }
+    }
+
+    let work = scratch_dir()?;
+    let result = async {
+        let _ = events.send(ArchiveEvent::Stage(ArchiveStage::Downloading));
+        let local_archive = work.join(archive);
+        sftp.download_file(Path::new(&remote_join(dir, archive)), &local_archive, Some(progress_forwarder(events)))
+            .await
+            .context("Failed to download archive")?;
+
+        let _ = events.send(ArchiveEvent::Stage(ArchiveStage::ProcessingLocally));
+        let out_dir = work.join("extracted");
+        std::fs::create_dir_all(&out_dir)?;
+        run_local(local_extract_command(format, &local_archive, &out_dir)).await?;
+
+        let _ = events.send(ArchiveEvent::Stage(ArchiveStage::Uploading));
+        for entry in std::fs::read_dir(&out_dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            upload_tree(sftp, &entry.path(), &remote_join(dir, &name), events).await?;
+        }
+        Ok(())
+    }
+    .await;
+
+    let _ = std::fs::remove_dir_all(&work);
+    result
+}
+
+fn remote_join(dir: &str, name: &str) -> String {
+    if dir.ends_with('/') {
+        format!("{}{}", dir, name)
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+fn scratch_dir() -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("tabssh-archive-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+fn progress_forwarder(events: &mpsc::UnboundedSender<ArchiveEvent>) -> Box<dyn Fn(u64, u64) + Send> {
+    let events = events.clone();
+    Box::new(move |done, total| {
+        let _ = events.send(ArchiveEvent::Progress(done, total));
+    })
+}
+
+/// Recursively download a remote file or directory
+async fn download_tree(
+    sftp: &mut SftpClient,
+    remote: &str,
+    local: &Path,
+    events: &mpsc::UnboundedSender<ArchiveEvent>,
+) -> Result<()> {
+    let mut pending = vec![(remote.to_string(), local.to_path_buf())];
+    while let Some((remote, local)) = pending.pop() {
+        // lstat, so a link to a parent directory can't loop forever
+        let stat = sftp.lstat(Path::new(&remote)).await?;
+        if stat.is_symlink {
+            log::warn!("Skipping symlink {} while downloading", remote);
+        } else if stat.is_directory {
+            tokio::fs::create_dir_all(&local).await?;
+            for child in sftp.list_directory(Path::new(&remote)).await? {
+                if child.name == "." || child.name == ".." {
+                    continue;
+                }
+                pending.push((remote_join(&remote, &child.name), local.join(&child.name)));
+            }
+        } else {
+            sftp.download_file(Path::new(&remote), &local, Some(progress_forwarder(events))).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Recursively upload a local file or directory. Symlinks are skipped: an
+/// archive may hold a link to `/` or `~`, which must not send the user's
+/// own files to the server.
+async fn upload_tree(
+    sftp: &mut SftpClient,
+    local: &Path,
+    remote: &str,
+    events: &mpsc::UnboundedSender<ArchiveEvent>,
+) -> Result<()> {
+    let mut pending = vec![(local.to_path_buf(), remote.to_string())];
+    while let Some((local, remote)) = pending.pop() {
+        let metadata = std::fs::symlink_metadata(&local)?;
+        if metadata.file_type().is_symlink() {
+            log::warn!("Skipping symlink {:?} while uploading", local);
+        } else if metadata.is_dir() {
+            // The directory may already exist when extracting over an old copy
+            let _ = sftp.create_directory(Path::new(&remote)).await;
+            for entry in std::fs::read_dir(&local)? {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().to_string();
+                pending.push((entry.path(), remote_join(&remote, &name)));
+            }
+        } else {
+            sftp.upload_file(&local, Path::new(&remote), Some(progress_forwarder(events))).await?;
+        }
+    }
+    Ok(())
+}
+
+fn local_compress_command(format: ArchiveFormat, dir: &Path, names: &[String], archive: &Path) -> Command {
+    let mut cmd;
+    match format {
+        ArchiveFormat::TarGz => {
+            cmd = Command::new("tar");
+            cmd.arg("-czf").arg(archive).arg("-C").arg(dir).arg("--");
+        }
+        ArchiveFormat::Zip if cfg!(target_os = "windows") => {
+            // bsdtar ships with Windows 10+ and can write zip archives
+            cmd = Command::new("tar");
+            cmd.arg("-a").arg("-cf").arg(archive).arg("-C").arg(dir).arg("--");
+        }
+        ArchiveFormat::Zip => {
+            cmd = Command::new("zip");
+            cmd.current_dir(dir).arg("-qr").arg(archive).arg("--");
+        }
+    }
+    cmd.args(names);
+    cmd
+}
+
+fn local_extract_command(format: ArchiveFormat, archive: &Path, dir: &Path) -> Command {
+    let mut cmd;
+    match format {
+        ArchiveFormat::TarGz => {
+            cmd = Command::new("tar");
+            cmd.arg("-xzf").arg(archive).arg("-C").arg(dir);
+        }
+        ArchiveFormat::Zip if cfg!(target_os = "windows") => {
+            cmd = Command::new("tar");
+            cmd.arg("-xf").arg(archive).arg("-C").arg(dir);
+        }
+        ArchiveFormat::Zip => {
+            cmd = Command::new("unzip");
+            cmd.arg("-q").arg("-o").arg(archive).arg("-d").arg(dir);
+        }
+    }
+    cmd
+}
+
+async fn run_local(mut cmd: Command) -> Result<()> {
+    let program = format!("{:?}", cmd.as_std().get_program());
+    let output = cmd
+        .output()
+        .await
+        .with_context(|| format!("Failed to start local {}", program))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Local {} failed ({}): {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(ArchiveFormat::detect("backup.tar.gz"), Some(ArchiveFormat::TarGz));
+        assert_eq!(ArchiveFormat::detect("site.TGZ"), Some(ArchiveFormat::TarGz));
+        assert_eq!(ArchiveFormat::detect("logs.zip"), Some(ArchiveFormat::Zip));
+        assert_eq!(ArchiveFormat::detect("notes.txt"), None);
+    }
+
+    #[test]
+    fn test_compress_command_quotes_names() {
+        let cmd = ArchiveFormat::TarGz.compress_command(
+            "/var/www",
+            &["my site".to_string()],
+            "my site.tar.gz",
+        );
+        assert_eq!(cmd, "cd /var/www && tar -czf 'my site.tar.gz' -- 'my site'");
+
+        let cmd = ArchiveFormat::Zip.compress_command("/tmp", &["-rm".to_string()], "x.zip");
+        assert_eq!(cmd, "cd /tmp && zip -qr x.zip -- -rm");
+    }
+
+    #[test]
+    fn test_local_compress_ends_options() {
+        let cmd = local_compress_command(ArchiveFormat::Zip, Path::new("/tmp"), &["-T".to_string()], Path::new("x.zip"));
+        let args: Vec<_> = cmd.as_std().get_args().map(|a| a.to_string_lossy().to_string()).collect();
+        assert_eq!(args.last().map(String::as_str), Some("-T"));
+        assert!(args.iter().position(|a| a == "--") < args.iter().position(|a| a == "-T"));
+    }
+
+    #[test]
+    fn test_archive_name() {
+        assert_eq!(archive_name_for(&["logs".to_string()], ArchiveFormat::Zip), "logs.zip");
+        assert_eq!(
+            archive_name_for(&["a".to_string(), "b".to_string()], ArchiveFormat::TarGz),
+            "archive.tar.gz"
+        );
+    }
+}
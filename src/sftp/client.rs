@@ -2,10 +2,19 @@
 
 use anyhow::{anyhow, Context, Result};
 use russh::Channel;
-use russh_sftp::client::SftpSession;
+use russh_sftp::client::error::Error as SftpError;
+use russh_sftp::client::{RawSftpSession, SftpSession};
+use russh_sftp::protocol::{FileAttributes, StatusCode};
+use std::io::SeekFrom;
 use std::path::{Path, PathBuf};
 use tokio::fs::File;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use crate::utils::memory;
+
+/// Chunk size for reads and writes; each running transfer holds one
+const TRANSFER_BUFFER: usize = 32 * 1024;
+/// Entries per batch handed to the browser while a listing comes in
+const LISTING_BATCH: usize = 500;
 
 pub struct SftpClient {
     session_id: String,
@@ -13,6 +22,17 @@ pub struct SftpClient {
     current_path: PathBuf,
 }
 
+// By hand: the russh-sftp session has no Debug
+impl std::fmt::Debug for SftpClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SftpClient")
+            .field("session_id", &self.session_id)
+            .field("connected", &self.sftp.is_some())
+            .field("current_path", &self.current_path)
+            .finish()
+    }
+}
+
 impl SftpClient {
     pub fn new(session_id: String) -> Self {
         Self {
@@ -26,7 +46,7 @@ impl SftpClient {
     pub async fn connect(&mut self, channel: Channel<russh::client::Msg>) -> Result<()> {
         log::info!("SFTP: Connecting session {}", self.session_id);
         
-        let sftp = SftpSession::new(channel)
+        let sftp = SftpSession::new(channel.into_stream())
             .await
             .context("Failed to create SFTP session")?;
         
@@ -39,7 +59,7 @@ impl SftpClient {
     pub async fn disconnect(&mut self) -> Result<()> {
         log::info!("SFTP: Disconnecting session {}", self.session_id);
         
-        if let Some(mut sftp) = self.sftp.take() {
+        if let Some(sftp) = self.sftp.take() {
             sftp.close().await?;
         }
         
@@ -51,44 +71,69 @@ impl SftpClient {
         self.sftp.as_ref().ok_or_else(|| anyhow!("SFTP not connected"))
     }
 
-    /// Get mutable SFTP session
-    fn sftp_mut(&mut self) -> Result<&mut SftpSession> {
-        self.sftp.as_mut().ok_or_else(|| anyhow!("SFTP not connected"))
-    }
-
     /// List directory contents
     pub async fn list_directory(&mut self, path: &Path) -> Result<Vec<FileEntry>> {
         log::debug!("SFTP: Listing directory {:?}", path);
         
-        let sftp = self.sftp_mut()?;
-        let path_str = path.to_string_lossy();
-        
-        let dir = sftp.open_dir(&path_str).await?;
-        let entries = sftp.read_dir(&dir).await?;
-        
-        let mut files = Vec::new();
-        for entry in entries {
-            let attrs = entry.attrs();
-            
-            files.push(FileEntry {
-                name: entry.filename().to_string(),
-                path: path.join(entry.filename()),
-                size: attrs.size.unwrap_or(0),
-                is_directory: attrs.is_dir(),
-                permissions: attrs.permissions.unwrap_or(0),
-                modified: attrs.mtime
-                    .map(|t| chrono::DateTime::from_timestamp(t as i64, 0))
-                    .flatten()
-                    .unwrap_or_else(|| chrono::Utc::now()),
-            });
-        }
-        
-        sftp.close_dir(dir).await?;
+        let sftp = self.sftp()?;
+        let files: Vec<FileEntry> = sftp.read_dir(path.to_string_lossy())
+            .await?
+            .map(|entry| file_entry(path, entry.file_name(), &entry.metadata()))
+            .collect();
         
         log::debug!("SFTP: Found {} entries", files.len());
         Ok(files)
     }
 
+    /// List a directory in batches of at most `LISTING_BATCH`, each sent
+    /// as soon as the server's reply arrives, so huge directories show up
+    /// without one giant update. Stops early once `batches` is dropped;
+    /// returns the number of entries listed.
+    ///
+    /// Runs its own session on `channel`, which needs the SFTP subsystem
+    /// started: `SftpSession::read_dir` reads the whole directory before
+    /// returning any of it, and the session does not expose the handle.
+    pub async fn list_directory_batched(
+        channel: Channel<russh::client::Msg>,
+        path: &Path,
+        batches: tokio::sync::mpsc::Sender<Vec<FileEntry>>,
+    ) -> Result<usize> {
+        log::debug!("SFTP: Streaming directory {:?}", path);
+
+        let session = RawSftpSession::new(channel.into_stream());
+        session.init().await.context("Failed to start the SFTP session")?;
+        let handle = session.opendir(path.to_string_lossy()).await?.handle;
+        let mut listed = 0;
+        loop {
+            let files = match session.readdir(handle.as_str()).await {
+                Ok(name) => name.files,
+                Err(SftpError::Status(status)) if status.status_code == StatusCode::Eof => break,
+                Err(e) => {
+                    let _ = session.close(handle.as_str()).await;
+                    return Err(e.into());
+                }
+            };
+            let entries: Vec<FileEntry> = files.into_iter()
+                .filter(|file| file.filename != "." && file.filename != "..")
+                .map(|file| file_entry(path, file.filename, &file.attrs))
+                .collect();
+            listed += entries.len();
+            let mut entries = entries.into_iter().peekable();
+            let mut receiver_gone = false;
+            while entries.peek().is_some() {
+                if batches.send(entries.by_ref().take(LISTING_BATCH).collect()).await.is_err() {
+                    receiver_gone = true;
+                    break;
+                }
+            }
+            if receiver_gone {
+                break;
+            }
+        }
+        session.close(handle.as_str()).await?;
+        Ok(listed)
+    }
+
     /// Download file from remote to local
     pub async fn download_file(
         &mut self,
@@ -98,23 +143,22 @@ impl SftpClient {
     ) -> Result<()> {
         log::info!("SFTP: Downloading {:?} to {:?}", remote_path, local_path);
         
-        let sftp = self.sftp_mut()?;
-        let remote_str = remote_path.to_string_lossy();
+        let _lease = memory::lease_transfer(&self.session_id, TRANSFER_BUFFER);
+        let sftp = self.sftp()?;
         
         // Open remote file
-        let remote_file = sftp.open(&remote_str).await?;
-        let attrs = sftp.fstat(&remote_file).await?;
-        let total_size = attrs.size.unwrap_or(0);
+        let mut remote_file = sftp.open(remote_path.to_string_lossy()).await?;
+        let total_size = remote_file.metadata().await?.size.unwrap_or(0);
         
         // Create local file
         let mut local_file = File::create(local_path).await?;
         
         // Read and write in chunks
-        let mut buffer = vec![0u8; 32768]; // 32KB buffer
+        let mut buffer = vec![0u8; TRANSFER_BUFFER];
         let mut transferred = 0u64;
         
         loop {
-            let n = sftp.read(&remote_file, transferred, &mut buffer).await?;
+            let n = remote_file.read(&mut buffer).await?;
             if n == 0 {
                 break;
             }
@@ -127,7 +171,7 @@ impl SftpClient {
             }
         }
         
-        sftp.close(remote_file).await?;
+        remote_file.close().await?;
         local_file.flush().await?;
         
         log::info!("SFTP: Download complete ({} bytes)", transferred);
@@ -143,8 +187,8 @@ impl SftpClient {
     ) -> Result<()> {
         log::info!("SFTP: Uploading {:?} to {:?}", local_path, remote_path);
         
-        let sftp = self.sftp_mut()?;
-        let remote_str = remote_path.to_string_lossy();
+        let _lease = memory::lease_transfer(&self.session_id, TRANSFER_BUFFER);
+        let sftp = self.sftp()?;
         
         // Open local file
         let mut local_file = File::open(local_path).await?;
@@ -152,10 +196,10 @@ impl SftpClient {
         let total_size = metadata.len();
         
         // Create remote file
-        let remote_file = sftp.create(&remote_str).await?;
+        let mut remote_file = sftp.create(remote_path.to_string_lossy()).await?;
         
         // Read and write in chunks
-        let mut buffer = vec![0u8; 32768]; // 32KB buffer
+        let mut buffer = vec![0u8; TRANSFER_BUFFER];
         let mut transferred = 0u64;
         
         loop {
@@ -164,7 +208,7 @@ impl SftpClient {
                 break;
             }
             
-            sftp.write(&remote_file, transferred, &buffer[..n]).await?;
+            remote_file.write_all(&buffer[..n]).await?;
             transferred += n as u64;
             
             if let Some(ref callback) = progress_callback {
@@ -172,20 +216,80 @@ impl SftpClient {
             }
         }
         
-        sftp.close(remote_file).await?;
+        // Closing waits for the server to confirm the writes
+        remote_file.close().await?;
         
         log::info!("SFTP: Upload complete ({} bytes)", transferred);
         Ok(())
     }
 
+    /// Read up to `len` bytes of a remote file from `offset`; fewer at its end
+    pub async fn read_range(&mut self, remote_path: &Path, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let _lease = memory::lease_transfer(&self.session_id, len);
+        let sftp = self.sftp()?;
+        let mut remote_file = sftp.open(remote_path.to_string_lossy()).await?;
+        remote_file.seek(SeekFrom::Start(offset)).await?;
+
+        let mut buffer = Vec::with_capacity(len);
+        (&mut remote_file).take(len as u64).read_to_end(&mut buffer).await?;
+
+        remote_file.close().await?;
+        Ok(buffer)
+    }
+
+    /// Stream a remote file into `chunks`, e.g. to write it to another
+    /// server while it downloads. Returns the bytes read.
+    pub async fn read_chunks(&mut self, remote_path: &Path, chunks: tokio::sync::mpsc::Sender<Vec<u8>>) -> Result<u64> {
+        let _lease = memory::lease_transfer(&self.session_id, TRANSFER_BUFFER);
+        let sftp = self.sftp()?;
+        let mut remote_file = sftp.open(remote_path.to_string_lossy()).await?;
+
+        let mut buffer = vec![0u8; TRANSFER_BUFFER];
+        let mut transferred = 0u64;
+        loop {
+            let n = remote_file.read(&mut buffer).await?;
+            if n == 0 {
+                break;
+            }
+            transferred += n as u64;
+            if chunks.send(buffer[..n].to_vec()).await.is_err() {
+                break;
+            }
+        }
+
+        remote_file.close().await?;
+        Ok(transferred)
+    }
+
+    /// Write chunks to a new remote file until the sender is dropped.
+    /// Returns the bytes written.
+    pub async fn write_chunks(
+        &mut self,
+        remote_path: &Path,
+        mut chunks: tokio::sync::mpsc::Receiver<Vec<u8>>,
+        progress_callback: Option<Box<dyn Fn(u64) + Send>>,
+    ) -> Result<u64> {
+        let sftp = self.sftp()?;
+        let mut remote_file = sftp.create(remote_path.to_string_lossy()).await?;
+
+        let mut transferred = 0u64;
+        while let Some(chunk) = chunks.recv().await {
+            remote_file.write_all(&chunk).await?;
+            transferred += chunk.len() as u64;
+            if let Some(ref callback) = progress_callback {
+                callback(transferred);
+            }
+        }
+
+        remote_file.close().await?;
+        Ok(transferred)
+    }
+
     /// Create directory
     pub async fn create_directory(&mut self, path: &Path) -> Result<()> {
         log::info!("SFTP: Creating directory {:?}", path);
         
-        let sftp = self.sftp_mut()?;
-        let path_str = path.to_string_lossy();
-        
-        sftp.create_dir(&path_str).await?;
+        self.sftp()?.create_dir(path.to_string_lossy()).await?;
         
         log::info!("SFTP: Directory created");
         Ok(())
@@ -195,10 +299,7 @@ impl SftpClient {
     pub async fn delete_file(&mut self, path: &Path) -> Result<()> {
         log::info!("SFTP: Deleting file {:?}", path);
         
-        let sftp = self.sftp_mut()?;
-        let path_str = path.to_string_lossy();
-        
-        sftp.remove_file(&path_str).await?;
+        self.sftp()?.remove_file(path.to_string_lossy()).await?;
         
         log::info!("SFTP: File deleted");
         Ok(())
@@ -208,10 +309,7 @@ impl SftpClient {
     pub async fn delete_directory(&mut self, path: &Path) -> Result<()> {
         log::info!("SFTP: Deleting directory {:?}", path);
         
-        let sftp = self.sftp_mut()?;
-        let path_str = path.to_string_lossy();
-        
-        sftp.remove_dir(&path_str).await?;
+        self.sftp()?.remove_dir(path.to_string_lossy()).await?;
         
         log::info!("SFTP: Directory deleted");
         Ok(())
@@ -221,11 +319,7 @@ impl SftpClient {
     pub async fn rename(&mut self, old_path: &Path, new_path: &Path) -> Result<()> {
         log::info!("SFTP: Renaming {:?} to {:?}", old_path, new_path);
         
-        let sftp = self.sftp_mut()?;
-        let old_str = old_path.to_string_lossy();
-        let new_str = new_path.to_string_lossy();
-        
-        sftp.rename(&old_str, &new_str).await?;
+        self.sftp()?.rename(old_path.to_string_lossy(), new_path.to_string_lossy()).await?;
         
         log::info!("SFTP: Rename complete");
         Ok(())
@@ -235,10 +329,7 @@ impl SftpClient {
     pub async fn stat(&mut self, path: &Path) -> Result<FileEntry> {
         log::debug!("SFTP: Getting stats for {:?}", path);
         
-        let sftp = self.sftp_mut()?;
-        let path_str = path.to_string_lossy();
-        
-        let attrs = sftp.metadata(&path_str).await?;
+        let attrs = self.sftp()?.metadata(path.to_string_lossy()).await?;
         
         Ok(FileEntry {
             name: path.file_name()
@@ -248,22 +339,57 @@ impl SftpClient {
             path: path.to_path_buf(),
             size: attrs.size.unwrap_or(0),
             is_directory: attrs.is_dir(),
+            is_symlink: false,
+            permissions: attrs.permissions.unwrap_or(0),
+            modified: attrs.mtime
+                .and_then(|t| chrono::DateTime::from_timestamp(t as i64, 0))
+                .unwrap_or_else(chrono::Utc::now),
+        })
+    }
+
+    /// Like `stat`, but describes a symlink itself instead of its target
+    pub async fn lstat(&mut self, path: &Path) -> Result<FileEntry> {
+        let attrs = self.sftp()?.symlink_metadata(path.to_string_lossy()).await?;
+
+        Ok(FileEntry {
+            name: path.file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("")
+                .to_string(),
+            path: path.to_path_buf(),
+            size: attrs.size.unwrap_or(0),
+            is_directory: attrs.is_dir(),
+            is_symlink: attrs.is_symlink(),
             permissions: attrs.permissions.unwrap_or(0),
             modified: attrs.mtime
-                .map(|t| chrono::DateTime::from_timestamp(t as i64, 0))
-                .flatten()
-                .unwrap_or_else(|| chrono::Utc::now()),
+                .and_then(|t| chrono::DateTime::from_timestamp(t as i64, 0))
+                .unwrap_or_else(chrono::Utc::now),
         })
     }
 
+    /// Set several attributes in one SETSTAT; `None` fields are left unchanged
+    pub async fn set_attributes(&mut self, path: &Path, attributes: RemoteAttributes) -> Result<()> {
+        log::info!("SFTP: Setting attributes of {:?}: {:?}", path, attributes);
+
+        let mut attrs = FileAttributes::empty();
+        attrs.permissions = attributes.mode;
+        // SFTP sets both times together or neither
+        attrs.atime = attributes.mtime;
+        attrs.mtime = attributes.mtime;
+        attrs.uid = attributes.uid;
+        attrs.gid = attributes.gid;
+        self.sftp()?.set_metadata(path.to_string_lossy(), attrs).await?;
+
+        Ok(())
+    }
+
     /// Change permissions
     pub async fn chmod(&mut self, path: &Path, mode: u32) -> Result<()> {
         log::info!("SFTP: Changing permissions of {:?} to {:o}", path, mode);
         
-        let sftp = self.sftp_mut()?;
-        let path_str = path.to_string_lossy();
-        
-        sftp.set_permissions(&path_str, mode).await?;
+        let mut attrs = FileAttributes::empty();
+        attrs.permissions = Some(mode);
+        self.sftp()?.set_metadata(path.to_string_lossy(), attrs).await?;
         
         log::info!("SFTP: Permissions changed");
         Ok(())
@@ -278,12 +404,39 @@ impl SftpClient {
     }
 }
 
+fn file_entry(dir: &Path, name: String, attrs: &FileAttributes) -> FileEntry {
+    FileEntry {
+        path: dir.join(&name),
+        name,
+        size: attrs.size.unwrap_or(0),
+        is_directory: attrs.is_dir(),
+        is_symlink: attrs.is_symlink(),
+        permissions: attrs.permissions.unwrap_or(0),
+        modified: attrs.mtime
+            .and_then(|t| chrono::DateTime::from_timestamp(t as i64, 0))
+            .unwrap_or_else(chrono::Utc::now),
+    }
+}
+
+/// Attributes for `SftpClient::set_attributes`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RemoteAttributes {
+    pub mode: Option<u32>,
+    /// Seconds since the Unix epoch
+    pub mtime: Option<u32>,
+    /// Numeric owner and group; only root may change them
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+}
+
 #[derive(Debug, Clone)]
 pub struct FileEntry {
     pub name: String,
     pub path: PathBuf,
     pub size: u64,
     pub is_directory: bool,
+    /// Only set by `lstat` and listings; `stat` follows links
+    pub is_symlink: bool,
     pub permissions: u32,
     pub modified: chrono::DateTime<chrono::Utc>,
 }
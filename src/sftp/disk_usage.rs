@@ -0,0 +1,245 @@
+//! Remote disk usage analysis
+//!
+//! Uses `du` over an exec channel when possible and falls back to walking
+//! the tree over SFTP, which is slower but works on SFTP-only accounts.
+//! `folder_size` always walks, since it also counts entries.
+
+use anyhow::{bail, Result};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::client::SftpClient;
+use crate::ssh::SshConnection;
+use crate::utils::helpers::{format_file_size, shell_quote};
+
+/// Size of one direct child of the scanned directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiskUsageEntry {
+    pub name: String,
+    pub path: String,
+    pub bytes: u64,
+    pub is_directory: bool,
+}
+
+/// Result of scanning a remote directory
+#[derive(Debug, Clone)]
+pub struct DiskUsageReport {
+    pub path: String,
+    pub total_bytes: u64,
+    /// Children sorted largest first
+    pub entries: Vec<DiskUsageEntry>,
+    /// Whether the SFTP walk was used instead of `du`
+    pub via_sftp: bool,
+}
+
+/// Recursive totals for one folder, not counting the folder itself
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FolderSize {
+    pub bytes: u64,
+    pub files: u64,
+    pub folders: u64,
+}
+
+impl FolderSize {
+    /// Entry counts, e.g. "1,204 files, 1 folder"
+    pub fn describe_counts(&self) -> String {
+        let count = |n: u64, one: &str, many: &str| {
+            format!("{} {}", group_thousands(n), if n == 1 { one } else { many })
+        };
+        format!("{}, {}", count(self.files, "file", "files"), count(self.folders, "folder", "folders"))
+    }
+
+    /// Size and entry counts, for tooltips
+    pub fn describe(&self) -> String {
+        format!("{} in {}", format_file_size(self.bytes), self.describe_counts())
+    }
+}
+
+fn group_thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Walk `dir` over SFTP and add up its contents. Setting `cancel` stops the
+/// walk before the next directory is read.
+pub async fn folder_size(sftp: &mut SftpClient, dir: &str, cancel: &AtomicBool) -> Result<FolderSize> {
+    walk_tree(sftp, dir, Some(cancel)).await
+}
+
+/// `du` invocation for one level below `dir`, sizes in KiB, not crossing filesystems
+pub fn du_command(dir: &str) -> String {
+    format!("du -x -k --max-depth=1 {} 2>/dev/null", shell_quote(dir))
+}
+
+/// Parse `du -k` output into a report for `dir`.
+///
+/// `du` prints one `<kib>\t<path>` line per child plus a final line for
+/// `dir` itself, which becomes the total.
+pub fn parse_du_output(dir: &str, output: &str) -> DiskUsageReport {
+    let base = dir.trim_end_matches('/');
+    let mut total_bytes = 0;
+    let mut entries = Vec::new();
+
+    for line in output.lines() {
+        let Some((size, path)) = line.split_once('\t') else {
+            continue;
+        };
+        let Ok(kib) = size.trim().parse::<u64>() else {
+            continue;
+        };
+        let bytes = kib * 1024;
+        let path = path.trim_end_matches('/');
+
+        if path == base || (base.is_empty() && path == "/") {
+            total_bytes = bytes;
+            continue;
+        }
+
+        let name = path.rsplit('/').next().unwrap_or(path).to_string();
+        entries.push(DiskUsageEntry {
+            name,
+            path: path.to_string(),
+            bytes,
+            is_directory: true,
+        });
+    }
+
+    if total_bytes == 0 {
+        total_bytes = entries.iter().map(|e| e.bytes).sum();
+    }
+    entries.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+
+    DiskUsageReport {
+        path: dir.to_string(),
+        total_bytes,
+        entries,
+        via_sftp: false,
+    }
+}
+
+/// Scan `dir` on the server, preferring `du` and falling back to SFTP
+pub async fn scan_remote(
+    conn: Option<&SshConnection>,
+    sftp: &mut SftpClient,
+    dir: &str,
+) -> Result<DiskUsageReport> {
+    if let Some(conn) = conn {
+        match conn.exec(&du_command(dir)).await {
+            // du exits 1 when some subdirectories are unreadable but still
+            // prints everything it could size, so only require output
+            Ok(output) if !output.command_not_found() && !output.stdout.trim().is_empty() => {
+                return Ok(parse_du_output(dir, &output.stdout));
+            }
+            Ok(output) => {
+                log::info!("du unavailable on server ({:?}), walking over SFTP", output.exit_status);
+            }
+            Err(e) => {
+                log::info!("Exec unavailable ({}), walking over SFTP", e);
+            }
+        }
+    }
+
+    walk_sftp(sftp, dir).await
+}
+
+async fn walk_sftp(sftp: &mut SftpClient, dir: &str) -> Result<DiskUsageReport> {
+    let base = dir.trim_end_matches('/');
+    let mut entries = Vec::new();
+
+    for child in sftp.list_directory(Path::new(dir)).await? {
+        if child.name == "." || child.name == ".." {
+            continue;
+        }
+        let path = format!("{}/{}", base, child.name);
+        let bytes = if child.is_directory {
+            tree_size(sftp, &path).await.unwrap_or(0)
+        } else {
+            child.size
+        };
+        entries.push(DiskUsageEntry {
+            name: child.name.clone(),
+            path,
+            bytes,
+            is_directory: child.is_directory,
+        });
+    }
+
+    entries.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+    Ok(DiskUsageReport {
+        path: dir.to_string(),
+        total_bytes: entries.iter().map(|e| e.bytes).sum(),
+        entries,
+        via_sftp: true,
+    })
+}
+
+async fn tree_size(sftp: &mut SftpClient, dir: &str) -> Result<u64> {
+    Ok(walk_tree(sftp, dir, None).await?.bytes)
+}
+
+async fn walk_tree(sftp: &mut SftpClient, dir: &str, cancel: Option<&AtomicBool>) -> Result<FolderSize> {
+    let mut totals = FolderSize::default();
+    let mut pending = vec![dir.trim_end_matches('/').to_string()];
+
+    while let Some(current) = pending.pop() {
+        if cancel.is_some_and(|cancel| cancel.load(Ordering::Relaxed)) {
+            bail!("Cancelled");
+        }
+        // Unreadable directories are skipped, matching du's behaviour
+        let Ok(children) = sftp.list_directory(Path::new(&current)).await else {
+            continue;
+        };
+        for child in children {
+            if child.name == "." || child.name == ".." {
+                continue;
+            }
+            if child.is_directory {
+                totals.folders += 1;
+                pending.push(format!("{}/{}", current, child.name));
+            } else {
+                totals.files += 1;
+                totals.bytes += child.size;
+            }
+        }
+    }
+
+    Ok(totals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_du_output() {
+        let output = "12\t/var/log/apt\n4096\t/var/log/journal\n8\t/var/log/nginx\n4120\t/var/log\n";
+        let report = parse_du_output("/var/log", output);
+
+        assert_eq!(report.total_bytes, 4120 * 1024);
+        assert_eq!(report.entries.len(), 3);
+        assert_eq!(report.entries[0].name, "journal");
+        assert_eq!(report.entries[0].bytes, 4096 * 1024);
+        assert_eq!(report.entries[2].name, "nginx");
+    }
+
+    #[test]
+    fn test_folder_size_counts() {
+        let size = FolderSize { bytes: 0, files: 12_345, folders: 1 };
+        assert_eq!(size.describe_counts(), "12,345 files, 1 folder");
+        assert_eq!(FolderSize::default().describe_counts(), "0 files, 0 folders");
+    }
+
+    #[test]
+    fn test_parse_du_ignores_noise() {
+        let report = parse_du_output("/srv/", "du: cannot read directory\n20\t/srv/data\n20\t/srv\n");
+        assert_eq!(report.entries.len(), 1);
+        assert_eq!(report.total_bytes, 20 * 1024);
+    }
+}
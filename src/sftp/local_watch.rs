@@ -0,0 +1,134 @@
+//! Live refresh for the local pane
+//!
+//! Watches one local directory (not its subdirectories) with the platform
+//! file notification API. Changes are debounced: the pane refreshes once
+//! things have been quiet for a moment, or at least every couple of seconds
+//! while something keeps writing, such as a running download.
+
+use anyhow::{Context, Result};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Quiet time before a burst of changes triggers a refresh
+const QUIET: Duration = Duration::from_millis(300);
+/// Longest a refresh waits while changes keep coming
+const MAX_DELAY: Duration = Duration::from_secs(2);
+
+pub struct LocalWatcher {
+    path: PathBuf,
+    // Watching stops when this is dropped
+    _watcher: RecommendedWatcher,
+    changes: mpsc::Receiver<()>,
+    debounce: Debounce,
+}
+
+impl LocalWatcher {
+    /// Watch `path`; `on_change` runs on the watcher's thread for every
+    /// change, e.g. to wake up the UI so it calls `poll`
+    pub fn new(path: &Path, on_change: impl Fn() + Send + 'static) -> Result<Self> {
+        let (tx, changes) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            match event {
+                // Reading a file is not a change to the listing
+                Ok(event) if matches!(event.kind, EventKind::Access(_)) => {}
+                Ok(_) => {
+                    let _ = tx.send(());
+                    on_change();
+                }
+                Err(e) => log::debug!("Local watcher error: {}", e),
+            }
+        })
+        .context("Could not start watching local files")?;
+        watcher
+            .watch(path, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Could not watch {}", path.display()))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            _watcher: watcher,
+            changes,
+            debounce: Debounce::default(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the listing should be reloaded now; call once per frame
+    pub fn poll(&mut self) -> bool {
+        let now = Instant::now();
+        while self.changes.try_recv().is_ok() {
+            self.debounce.event(now);
+        }
+        self.debounce.due(now)
+    }
+
+    /// How soon `poll` may report a refresh, for scheduling a repaint
+    pub fn next_check(&self) -> Option<Duration> {
+        self.debounce.pending().then_some(QUIET)
+    }
+}
+
+/// Collapses bursts of change events into one refresh
+#[derive(Debug, Default)]
+struct Debounce {
+    first: Option<Instant>,
+    last: Option<Instant>,
+}
+
+impl Debounce {
+    fn event(&mut self, now: Instant) {
+        self.first.get_or_insert(now);
+        self.last = Some(now);
+    }
+
+    fn pending(&self) -> bool {
+        self.first.is_some()
+    }
+
+    /// True once per burst, when it has settled or run too long
+    fn due(&mut self, now: Instant) -> bool {
+        let (Some(first), Some(last)) = (self.first, self.last) else { return false };
+        if now.duration_since(last) >= QUIET || now.duration_since(first) >= MAX_DELAY {
+            self.first = None;
+            self.last = None;
+            return true;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debounce_waits_for_quiet() {
+        let start = Instant::now();
+        let mut debounce = Debounce::default();
+        assert!(!debounce.due(start));
+
+        debounce.event(start);
+        debounce.event(start + Duration::from_millis(100));
+        assert!(!debounce.due(start + Duration::from_millis(200)));
+        assert!(debounce.due(start + Duration::from_millis(400)));
+        // Reported once per burst
+        assert!(!debounce.due(start + Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn test_debounce_refreshes_during_long_bursts() {
+        let start = Instant::now();
+        let mut debounce = Debounce::default();
+        let mut refreshed = false;
+        for step in 0..30 {
+            let now = start + Duration::from_millis(step * 100);
+            debounce.event(now);
+            refreshed |= debounce.due(now);
+        }
+        assert!(refreshed);
+    }
+}
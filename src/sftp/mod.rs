@@ -2,10 +2,25 @@
 
 #![allow(dead_code)]
 
+mod archive;
 mod client;
+mod disk_usage;
+mod local_watch;
+mod relay;
+mod schedule;
+mod tail;
+
+pub use archive::{ArchiveEvent, ArchiveFormat, ArchiveStage, compress_remote, extract_remote};
+pub use disk_usage::{DiskUsageEntry, DiskUsageReport, FolderSize, folder_size, scan_remote};
+pub use local_watch::LocalWatcher;
+pub use relay::{RelayEndpoint, RelayEvent, RelayMethod, relay};
+pub use schedule::{PostAction, TransferOptions, TransferSchedule, run_post_actions};
+pub use tail::{TailEvent, tail_file};
 
 #[allow(unused_imports)]
 pub use client::{
+    SftpClient,
+    RemoteAttributes,
     TransferTask,
     read_local_directory,
     create_local_directory,
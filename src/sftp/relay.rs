@@ -0,0 +1,199 @@
+//! Remote-to-remote transfers between two SFTP sessions
+//!
+//! When the destination server can reach the source by itself (keys or an
+//! agent, no prompts), it pulls the files with `scp` and nothing passes
+//! through TabSSH. Otherwise each file streams through: chunks read from the
+//! source are written to the destination while the download continues, so
+//! nothing is staged on local disk.
+
+use anyhow::Result;
+use std::path::Path;
+use tokio::sync::mpsc;
+
+use super::client::{FileEntry, SftpClient};
+use crate::ssh::{ConnectionConfig, SshConnection};
+use crate::utils::helpers::shell_quote;
+
+/// Chunks in flight between the source read and the destination write
+const PIPELINE_DEPTH: usize = 16;
+
+/// One side of a relay
+pub struct RelayEndpoint<'a> {
+    /// Exec channel for the direct copy; `None` always streams
+    pub conn: Option<&'a SshConnection>,
+    pub sftp: &'a mut SftpClient,
+}
+
+/// How the files are moving
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RelayMethod {
+    /// The destination pulls straight from the source with `scp`
+    Direct,
+    /// Through this machine, downloading and uploading at once
+    Streamed,
+}
+
+impl std::fmt::Display for RelayMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RelayMethod::Direct => write!(f, "Server to server"),
+            RelayMethod::Streamed => write!(f, "Through this computer"),
+        }
+    }
+}
+
+/// Events emitted while a relay runs
+#[derive(Debug, Clone)]
+pub enum RelayEvent {
+    Method(RelayMethod),
+    /// Bytes written to the destination over all files (done, total)
+    Progress(u64, u64),
+    /// The direct copy failed; streaming instead, with the reason
+    FallingBack(String),
+    Finished,
+    Failed(String),
+}
+
+/// `scp` run on the destination to pull `sources` from the source host into `dest_dir`
+pub fn direct_copy_command(source: &ConnectionConfig, sources: &[String], dest_dir: &str, recursive: bool) -> String {
+    let mut command = String::from("scp -q -p -o BatchMode=yes -o ConnectTimeout=10");
+    if recursive {
+        command.push_str(" -r");
+    }
+    command.push_str(&format!(" -P {}", source.port));
+    for path in sources {
+        command.push(' ');
+        command.push_str(&shell_quote(&format!("{}@{}:{}", source.username, source.host, path)));
+    }
+    command.push(' ');
+    command.push_str(&shell_quote(dest_dir));
+    command
+}
+
+/// Copy `entries` from the source session into `dest_dir` on the destination
+pub async fn relay(
+    source: RelayEndpoint<'_>,
+    entries: &[FileEntry],
+    dest: RelayEndpoint<'_>,
+    dest_dir: &str,
+    events: &mpsc::UnboundedSender<RelayEvent>,
+) -> Result<()> {
+    let result = relay_inner(source, entries, dest, dest_dir, events).await;
+    let _ = events.send(match &result {
+        Ok(()) => RelayEvent::Finished,
+        Err(e) => RelayEvent::Failed(format!("{:#}", e)),
+    });
+    result
+}
+
+async fn relay_inner(
+    source: RelayEndpoint<'_>,
+    entries: &[FileEntry],
+    dest: RelayEndpoint<'_>,
+    dest_dir: &str,
+    events: &mpsc::UnboundedSender<RelayEvent>,
+) -> Result<()> {
+    if let (Some(from), Some(to)) = (source.conn, dest.conn) {
+        let _ = events.send(RelayEvent::Method(RelayMethod::Direct));
+        let sources: Vec<String> = entries.iter().map(|e| e.path.to_string_lossy().into_owned()).collect();
+        let recursive = entries.iter().any(|e| e.is_directory);
+        match to.exec(&direct_copy_command(from.config(), &sources, dest_dir, recursive)).await {
+            Ok(output) if output.success() => return Ok(()),
+            Ok(output) => {
+                let _ = events.send(RelayEvent::FallingBack(output.stderr.trim().to_string()));
+            }
+            Err(e) => {
+                let _ = events.send(RelayEvent::FallingBack(e.to_string()));
+            }
+        }
+    }
+
+    let _ = events.send(RelayEvent::Method(RelayMethod::Streamed));
+    let (dirs, files) = plan(source.sftp, entries, dest_dir).await?;
+    for dir in &dirs {
+        // Already existing is fine; a real problem shows up on the first write
+        let _ = dest.sftp.create_directory(Path::new(dir)).await;
+    }
+
+    let total: u64 = files.iter().map(|(_, _, size)| size).sum();
+    let mut done = 0u64;
+    let _ = events.send(RelayEvent::Progress(0, total));
+
+    for (from, to, _) in &files {
+        let (chunks_tx, chunks_rx) = mpsc::channel(PIPELINE_DEPTH);
+        let progress: Box<dyn Fn(u64) + Send> = {
+            let events = events.clone();
+            let before = done;
+            Box::new(move |written: u64| {
+                let _ = events.send(RelayEvent::Progress(before + written, total));
+            })
+        };
+        let (read, written) = tokio::join!(
+            source.sftp.read_chunks(Path::new(from), chunks_tx),
+            dest.sftp.write_chunks(Path::new(to), chunks_rx, Some(progress)),
+        );
+        read?;
+        done += written?;
+        log::info!("Relayed {} to {}", from, to);
+    }
+
+    Ok(())
+}
+
+/// Destination directories to create and (source, destination, size) per file
+async fn plan(
+    sftp: &mut SftpClient,
+    entries: &[FileEntry],
+    dest_dir: &str,
+) -> Result<(Vec<String>, Vec<(String, String, u64)>)> {
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    let mut pending: Vec<(String, String, bool, u64)> = entries.iter()
+        .map(|e| (e.path.to_string_lossy().into_owned(), join(dest_dir, &e.name), e.is_directory, e.size))
+        .collect();
+
+    while let Some((from, to, is_directory, size)) = pending.pop() {
+        if !is_directory {
+            files.push((from, to, size));
+            continue;
+        }
+        for child in sftp.list_directory(Path::new(&from)).await? {
+            if child.name == "." || child.name == ".." {
+                continue;
+            }
+            pending.push((join(&from, &child.name), join(&to, &child.name), child.is_directory, child.size));
+        }
+        dirs.push(to);
+    }
+
+    // Parents before children
+    dirs.sort();
+    files.reverse();
+    Ok((dirs, files))
+}
+
+fn join(dir: &str, name: &str) -> String {
+    format!("{}/{}", dir.trim_end_matches('/'), name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_direct_copy_command() {
+        let source = ConnectionConfig {
+            host: "db01".to_string(),
+            port: 2222,
+            username: "backup".to_string(),
+            ..Default::default()
+        };
+        let command = direct_copy_command(&source, &["/var/dumps/a b.sql".to_string()], "/srv/in/", false);
+        assert_eq!(
+            command,
+            "scp -q -p -o BatchMode=yes -o ConnectTimeout=10 -P 2222 'backup@db01:/var/dumps/a b.sql' /srv/in/"
+        );
+        assert!(direct_copy_command(&source, &[], "/tmp", true).contains(" -r "));
+        assert_eq!(join("/srv/in/", "x"), "/srv/in/x");
+    }
+}
@@ -0,0 +1,274 @@
+//! Transfer scheduling and post-transfer actions
+//!
+//! A queued transfer can be held until a wall-clock time or until the rest
+//! of the queue drains, and can carry follow-up steps that run once it
+//! completes successfully.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, NaiveTime};
+use std::path::Path;
+
+use super::client::{RemoteAttributes, SftpClient};
+use super::TransferDirection;
+use crate::ssh::SshConnection;
+use crate::utils::helpers::shell_quote;
+
+/// When a queued transfer may start
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransferSchedule {
+    /// Start as soon as it reaches the front of the queue
+    Immediately,
+    /// Hold until the given local time
+    At(DateTime<Local>),
+    /// Hold until no other transfer is running or waiting to run
+    AfterQueue,
+}
+
+impl Default for TransferSchedule {
+    fn default() -> Self {
+        Self::Immediately
+    }
+}
+
+impl TransferSchedule {
+    /// Whether the transfer may start now. `queue_busy` is true while any
+    /// other transfer is in progress or immediately runnable.
+    pub fn is_due(&self, now: DateTime<Local>, queue_busy: bool) -> bool {
+        match self {
+            Self::Immediately => true,
+            Self::At(at) => now >= *at,
+            Self::AfterQueue => !queue_busy,
+        }
+    }
+
+    /// Schedule for the next occurrence of an `HH:MM` time of day
+    pub fn at_time_of_day(input: &str, now: DateTime<Local>) -> Option<Self> {
+        let time = NaiveTime::parse_from_str(input.trim(), "%H:%M").ok()?;
+        let today = now.date_naive().and_time(time).and_local_timezone(Local).earliest()?;
+        let at = if today > now {
+            today
+        } else {
+            (now.date_naive() + chrono::Duration::days(1))
+                .and_time(time)
+                .and_local_timezone(Local)
+                .earliest()?
+        };
+        Some(Self::At(at))
+    }
+
+    /// Short label for the transfers panel
+    pub fn label(&self) -> String {
+        match self {
+            Self::Immediately => "Pending".to_string(),
+            Self::At(at) => format!("Scheduled {}", at.format("%H:%M")),
+            Self::AfterQueue => "After queue".to_string(),
+        }
+    }
+}
+
+/// Step run after a transfer completes
+#[derive(Debug, Clone, PartialEq)]
+pub enum PostAction {
+    /// Remove the source once the copy is complete
+    DeleteSource,
+    /// Copy the local mode bits and modification time to the uploaded file
+    PreserveAttributes,
+    /// Set the numeric owner and/or group of the uploaded file (root only)
+    Chown { uid: Option<u32>, gid: Option<u32> },
+    /// Set the mode of the uploaded file (uploads only)
+    Chmod(u32),
+    /// Run a command on the server; `{path}` expands to the remote path
+    RemoteCommand(String),
+    /// Show a desktop notification
+    Notify,
+}
+
+impl PostAction {
+    pub fn describe(&self) -> String {
+        match self {
+            Self::DeleteSource => "Delete source".to_string(),
+            Self::PreserveAttributes => "Preserve mode and time".to_string(),
+            Self::Chown { uid, gid } => {
+                let id = |id: &Option<u32>| id.map(|id| id.to_string()).unwrap_or_default();
+                format!("chown {}:{}", id(uid), id(gid))
+            }
+            Self::Chmod(mode) => format!("chmod {:o}", mode),
+            Self::RemoteCommand(cmd) => format!("Run: {}", cmd),
+            Self::Notify => "Notify".to_string(),
+        }
+    }
+}
+
+/// Scheduling and follow-up configuration for one queued transfer
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransferOptions {
+    pub schedule: TransferSchedule,
+    pub post_actions: Vec<PostAction>,
+}
+
+impl TransferOptions {
+    pub fn wants_notification(&self) -> bool {
+        self.post_actions.contains(&PostAction::Notify)
+    }
+
+    /// Options for an upload with no overrides, following the
+    /// `preserve_upload_attributes` setting
+    pub fn upload_default(preserve_attributes: bool) -> Self {
+        Self {
+            post_actions: if preserve_attributes { vec![PostAction::PreserveAttributes] } else { Vec::new() },
+            ..Self::default()
+        }
+    }
+}
+
+/// Expand `{path}` in a post-transfer command with the shell-quoted remote path
+pub fn expand_remote_command(template: &str, remote_path: &str) -> String {
+    template.replace("{path}", &shell_quote(remote_path))
+}
+
+/// Run the post-actions for a completed transfer.
+///
+/// `Notify` is left to the caller since it needs the UI's notification
+/// manager. Every other action is attempted even if an earlier one fails;
+/// the returned list holds one message per failure.
+pub async fn run_post_actions(
+    actions: &[PostAction],
+    direction: &TransferDirection,
+    local_path: &Path,
+    remote_path: &str,
+    conn: Option<&SshConnection>,
+    sftp: &mut SftpClient,
+) -> Vec<String> {
+    let mut failures = Vec::new();
+
+    for action in actions {
+        let result = match action {
+            PostAction::DeleteSource => delete_source(direction, local_path, remote_path, sftp).await,
+            PostAction::PreserveAttributes | PostAction::Chown { .. } if *direction != TransferDirection::Upload => {
+                Err(anyhow!("only applies to uploads"))
+            }
+            PostAction::PreserveAttributes => match std::fs::metadata(local_path) {
+                Ok(metadata) => sftp.set_attributes(Path::new(remote_path), local_attributes(&metadata)).await,
+                Err(e) => Err(e.into()),
+            },
+            PostAction::Chown { uid, gid } => {
+                let attributes = RemoteAttributes { uid: *uid, gid: *gid, ..RemoteAttributes::default() };
+                sftp.set_attributes(Path::new(remote_path), attributes).await
+            }
+            PostAction::Chmod(mode) => {
+                if *direction == TransferDirection::Upload {
+                    sftp.chmod(Path::new(remote_path), *mode).await
+                } else {
+                    Err(anyhow!("chmod only applies to uploads"))
+                }
+            }
+            PostAction::RemoteCommand(template) => match conn {
+                Some(conn) => conn
+                    .exec(&expand_remote_command(template, remote_path))
+                    .await
+                    .and_then(|output| output.into_result("Post-transfer command"))
+                    .map(|_| ()),
+                None => Err(anyhow!("No shell connection for remote command")),
+            },
+            PostAction::Notify => Ok(()),
+        };
+
+        if let Err(e) = result {
+            log::warn!("Post-transfer action '{}' failed: {}", action.describe(), e);
+            failures.push(format!("{}: {}", action.describe(), e));
+        }
+    }
+
+    failures
+}
+
+/// Mode bits (Unix only; Windows has none worth copying) and modification
+/// time of a local file
+fn local_attributes(metadata: &std::fs::Metadata) -> RemoteAttributes {
+    #[cfg(unix)]
+    let mode = {
+        use std::os::unix::fs::PermissionsExt;
+        Some(metadata.permissions().mode() & 0o7777)
+    };
+    #[cfg(not(unix))]
+    let mode = None;
+
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .and_then(|since| u32::try_from(since.as_secs()).ok());
+    RemoteAttributes { mode, mtime, ..RemoteAttributes::default() }
+}
+
+async fn delete_source(
+    direction: &TransferDirection,
+    local_path: &Path,
+    remote_path: &str,
+    sftp: &mut SftpClient,
+) -> Result<()> {
+    match direction {
+        TransferDirection::Upload => {
+            if local_path.is_dir() {
+                std::fs::remove_dir_all(local_path)?;
+            } else {
+                std::fs::remove_file(local_path)?;
+            }
+            Ok(())
+        }
+        TransferDirection::Download => sftp.delete_file(Path::new(remote_path)).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_schedule_is_due() {
+        let now = Local.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+
+        assert!(TransferSchedule::Immediately.is_due(now, true));
+        assert!(!TransferSchedule::AfterQueue.is_due(now, true));
+        assert!(TransferSchedule::AfterQueue.is_due(now, false));
+        assert!(!TransferSchedule::At(now + chrono::Duration::minutes(5)).is_due(now, false));
+        assert!(TransferSchedule::At(now).is_due(now, true));
+    }
+
+    #[test]
+    fn test_at_time_of_day_rolls_over() {
+        let now = Local.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+
+        match TransferSchedule::at_time_of_day("13:30", now) {
+            Some(TransferSchedule::At(at)) => assert_eq!(at.date_naive(), now.date_naive()),
+            other => panic!("unexpected {:?}", other),
+        }
+        match TransferSchedule::at_time_of_day("09:00", now) {
+            Some(TransferSchedule::At(at)) => assert!(at.date_naive() > now.date_naive()),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(TransferSchedule::at_time_of_day("25:00", now).is_none());
+    }
+
+    #[test]
+    fn test_local_attributes() {
+        let path = std::env::temp_dir().join(format!("tabssh-attrs-{}", std::process::id()));
+        std::fs::write(&path, b"x").unwrap();
+        let attributes = local_attributes(&std::fs::metadata(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(attributes.mtime.is_some());
+        assert_eq!(attributes.uid, None);
+        #[cfg(unix)]
+        assert!(attributes.mode.is_some_and(|mode| mode & 0o600 == 0o600));
+    }
+
+    #[test]
+    fn test_expand_remote_command() {
+        assert_eq!(
+            expand_remote_command("sha256sum {path}", "/srv/my file.iso"),
+            "sha256sum '/srv/my file.iso'"
+        );
+    }
+}
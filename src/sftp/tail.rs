@@ -0,0 +1,135 @@
+//! Streaming a remote file into the file viewer
+//!
+//! Reads the last `INITIAL_BYTES` of the file and then, while following,
+//! polls its size and sends whatever was appended, like `tail -f` over
+//! SFTP. A file that shrinks was truncated or rotated and is read again
+//! from the start. The viewer gets an SFTP channel of its own for this, so
+//! a file left open never holds up the session's browser.
+
+use anyhow::Result;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use super::client::SftpClient;
+
+/// How much of the end of a file is shown when it opens
+const INITIAL_BYTES: u64 = 1024 * 1024;
+const CHUNK: u64 = 64 * 1024;
+const POLL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TailEvent {
+    /// The first bytes were skipped to start near the end
+    Skipped(u64),
+    Text(String),
+    /// The file shrank; what was shown is stale and it starts over
+    Restarted,
+    /// Reading stopped, e.g. the file was removed or the session closed
+    Failed(String),
+}
+
+/// Stream `path` into `events` on the dedicated client `sftp` until the
+/// receiver is dropped. New content is only polled for while `follow` is set.
+pub async fn tail_file(
+    mut sftp: SftpClient,
+    path: &str,
+    follow: Arc<AtomicBool>,
+    events: mpsc::UnboundedSender<TailEvent>,
+) -> Result<()> {
+    let path = Path::new(path);
+    let mut offset = sftp.stat(path).await?.size.saturating_sub(INITIAL_BYTES);
+    if offset > 0 {
+        let _ = events.send(TailEvent::Skipped(offset));
+    }
+    let mut decoder = Utf8Decoder::default();
+    let mut first = true;
+
+    loop {
+        if first || follow.load(Ordering::Relaxed) {
+            first = false;
+            let size = sftp.stat(path).await?.size;
+            if size < offset {
+                offset = 0;
+                decoder = Utf8Decoder::default();
+                let _ = events.send(TailEvent::Restarted);
+            }
+            while offset < size {
+                let chunk = sftp.read_range(path, offset, CHUNK.min(size - offset) as usize).await?;
+                if chunk.is_empty() {
+                    break;
+                }
+                offset += chunk.len() as u64;
+                let text = decoder.push(&chunk);
+                if !text.is_empty() && events.send(TailEvent::Text(text)).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+        if events.is_closed() {
+            return Ok(());
+        }
+        tokio::time::sleep(POLL).await;
+    }
+}
+
+/// Decodes UTF-8 arriving in arbitrary chunks, holding back a character
+/// split across two reads; invalid bytes become U+FFFD
+#[derive(Debug, Default)]
+struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    fn push(&mut self, bytes: &[u8]) -> String {
+        self.pending.extend_from_slice(bytes);
+        let mut text = String::new();
+        loop {
+            match std::str::from_utf8(&self.pending) {
+                Ok(valid) => {
+                    text.push_str(valid);
+                    self.pending.clear();
+                    return text;
+                }
+                Err(e) => {
+                    let valid = e.valid_up_to();
+                    text.push_str(&String::from_utf8_lossy(&self.pending[..valid]));
+                    match e.error_len() {
+                        Some(len) => {
+                            text.push('\u{FFFD}');
+                            self.pending.drain(..valid + len);
+                        }
+                        // Incomplete character at the end; wait for the rest
+                        None => {
+                            self.pending.drain(..valid);
+                            return text;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decoder_joins_split_characters() {
+        let mut decoder = Utf8Decoder::default();
+        let bytes = "größe €".as_bytes();
+        let split = bytes.len() - 2;
+        assert_eq!(decoder.push(&bytes[..split]), "größe ");
+        assert_eq!(decoder.push(&bytes[split..]), "€");
+    }
+
+    #[test]
+    fn test_decoder_replaces_invalid_bytes() {
+        let mut decoder = Utf8Decoder::default();
+        assert_eq!(decoder.push(b"ok\xffdone"), "ok\u{FFFD}done");
+        assert!(decoder.pending.is_empty());
+    }
+}
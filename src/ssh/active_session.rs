@@ -2,14 +2,33 @@
 //!
 //! Bridges async SSH connections with the synchronous UI terminal view.
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use russh::client::{self, Handle};
 use russh_keys::key;
 use russh::{ChannelMsg, Disconnect};
+use std::future::Future;
 use std::sync::Arc;
-use tokio::sync::mpsc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
 use uuid::Uuid;
 
+use super::auth::{self, AuthProgress, Credentials};
+use super::session_info::ServerHello;
+use super::{ConnectionConfig, SshConnection};
+use crate::storage::database::TrustedCa;
+use crate::sftp::SftpClient;
+
+/// Longest the TCP connect and SSH handshake may take
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest a liveness probe may wait for the server's reply
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest a side command like `docker ps` may run
+const EXEC_TIMEOUT: Duration = Duration::from_secs(15);
+
 /// Messages from SSH session to UI
 #[derive(Debug)]
 pub enum SessionEvent {
@@ -17,6 +36,12 @@ pub enum SessionEvent {
     Data(Vec<u8>),
     Disconnected,
     Error(String),
+    /// The server answered a probe after this round trip
+    Alive(Duration),
+    /// Negotiated algorithms and server version, looked up once authenticated
+    Info(super::SessionInfo),
+    /// One step of trying the profile's authentication methods
+    Auth(AuthProgress),
 }
 
 /// Commands from UI to SSH session
@@ -24,17 +49,59 @@ pub enum SessionEvent {
 pub enum SessionCommand {
     SendData(Vec<u8>),
     Resize(u32, u32),
+    /// Exercise the connection without touching the shell
+    Keepalive,
+    /// Send keepalives on this interval from now on; `None` stops them
+    SetKeepalive(Option<Duration>),
+    /// Check the connection is still alive, ending the session if not
+    Probe,
+    /// Run a command on its own channel and reply with its stdout
+    Exec(String, oneshot::Sender<Result<String>>),
+    /// Open another shell channel on this connection, for a new tab
+    OpenShell(PtyOptions, oneshot::Sender<Result<ActiveSession>>),
+    /// Start an SFTP session on this connection, for a browser next to the shell
+    OpenSftp(oneshot::Sender<Result<SftpClient>>),
     Disconnect,
 }
 
+/// Terminal advertised when the shell's PTY is requested
+#[derive(Debug, Clone)]
+pub struct PtyOptions {
+    /// Value of `TERM` on the server
+    pub term: String,
+    pub cols: u32,
+    pub rows: u32,
+    /// Run this in the PTY instead of the login shell
+    pub command: Option<String>,
+}
+
+impl Default for PtyOptions {
+    fn default() -> Self {
+        Self {
+            term: "xterm-256color".to_string(),
+            cols: 80,
+            rows: 24,
+            command: None,
+        }
+    }
+}
+
 /// SSH client handler
 struct SessionHandler {
     host: String,
+    port: u16,
+    trusted_cas: Vec<TrustedCa>,
+    host_certificate: Option<Vec<u8>>,
 }
 
 impl SessionHandler {
-    fn new(host: &str) -> Self {
-        Self { host: host.to_string() }
+    fn new(config: &ConnectionConfig) -> Self {
+        Self {
+            host: config.host.clone(),
+            port: config.port,
+            trusted_cas: config.trusted_cas.clone(),
+            host_certificate: config.host_certificate.clone(),
+        }
     }
 }
 
@@ -43,15 +110,23 @@ impl client::Handler for SessionHandler {
     type Error = anyhow::Error;
 
     async fn check_server_key(
-        &mut self,
+        self,
         server_public_key: &key::PublicKey,
-    ) -> Result<bool, Self::Error> {
+    ) -> Result<(Self, bool), Self::Error> {
         log::info!("Server key for {}: {}", self.host, server_public_key.fingerprint());
-        Ok(true)
+        let trusted = super::connection::check_server_key(
+            &self.host,
+            self.port,
+            server_public_key,
+            self.host_certificate.as_deref(),
+            &self.trusted_cas,
+        ).await?;
+        Ok((self, trusted))
     }
 }
 
 /// Active SSH session that runs in background
+#[derive(Debug)]
 pub struct ActiveSession {
     pub id: Uuid,
     pub host: String,
@@ -59,35 +134,33 @@ pub struct ActiveSession {
     pub port: u16,
     event_rx: mpsc::Receiver<SessionEvent>,
     command_tx: mpsc::Sender<SessionCommand>,
+    task: JoinHandle<()>,
 }
 
 impl ActiveSession {
     /// Connect with password authentication
     pub async fn connect_password(
-        host: String,
-        port: u16,
-        username: String,
+        config: ConnectionConfig,
         password: String,
+        pty: PtyOptions,
     ) -> Result<Self> {
         let id = Uuid::new_v4();
         let (event_tx, event_rx) = mpsc::channel(256);
         let (command_tx, command_rx) = mpsc::channel(256);
 
-        let session_host = host.clone();
-        let session_user = username.clone();
+        let session_host = config.host.clone();
+        let session_user = config.username.clone();
+        let port = config.port;
 
-        tokio::spawn(async move {
-            if let Err(e) = run_session_password(
-                &host,
-                port,
-                &username,
+        let task = tokio::spawn(supervise(event_tx.clone(), async move {
+            run_session_password(
+                &config,
                 &password,
+                pty,
                 event_tx,
                 command_rx,
-            ).await {
-                log::error!("Session error: {}", e);
-            }
-        });
+            ).await
+        }));
 
         Ok(Self {
             id,
@@ -96,37 +169,68 @@ impl ActiveSession {
             port,
             event_rx,
             command_tx,
+            task,
         })
     }
 
     /// Connect with key authentication
     pub async fn connect_key(
-        host: String,
-        port: u16,
-        username: String,
+        config: ConnectionConfig,
         key_path: String,
         passphrase: Option<String>,
+        pty: PtyOptions,
     ) -> Result<Self> {
         let id = Uuid::new_v4();
         let (event_tx, event_rx) = mpsc::channel(256);
         let (command_tx, command_rx) = mpsc::channel(256);
 
-        let session_host = host.clone();
-        let session_user = username.clone();
+        let session_host = config.host.clone();
+        let session_user = config.username.clone();
+        let port = config.port;
 
-        tokio::spawn(async move {
-            if let Err(e) = run_session_key(
-                &host,
-                port,
-                &username,
+        let task = tokio::spawn(supervise(event_tx.clone(), async move {
+            run_session_key(
+                &config,
                 &key_path,
                 passphrase.as_deref(),
+                pty,
                 event_tx,
                 command_rx,
-            ).await {
-                log::error!("Session error: {}", e);
-            }
-        });
+            ).await
+        }));
+
+        Ok(Self {
+            id,
+            host: session_host,
+            username: session_user,
+            port,
+            event_rx,
+            command_tx,
+            task,
+        })
+    }
+
+    /// Connect trying each of `methods` in order until one is accepted;
+    /// every attempt is reported as a `SessionEvent::Auth`. With `via` the
+    /// connection is tunnelled through that bastion login, which the session
+    /// keeps open until it ends.
+    pub async fn connect(
+        config: ConnectionConfig,
+        methods: Vec<Credentials>,
+        pty: PtyOptions,
+        via: Option<Arc<SshConnection>>,
+    ) -> Result<Self> {
+        let id = Uuid::new_v4();
+        let (event_tx, event_rx) = mpsc::channel(256);
+        let (command_tx, command_rx) = mpsc::channel(256);
+
+        let session_host = config.host.clone();
+        let session_user = config.username.clone();
+        let port = config.port;
+
+        let task = tokio::spawn(supervise(event_tx.clone(), async move {
+            run_session_methods(&config, &methods, via.as_deref(), pty, event_tx, command_rx).await
+        }));
 
         Ok(Self {
             id,
@@ -135,14 +239,62 @@ impl ActiveSession {
             port,
             event_rx,
             command_tx,
+            task,
+        })
+    }
+
+    /// Run a local program as the session, e.g. `kubectl exec` into a pod
+    ///
+    /// Output and input go through pipes, so the program gets no terminal of
+    /// its own; `label` stands in for the host in the UI.
+    pub fn spawn_process(label: String, program: String, args: Vec<String>) -> Result<Self> {
+        let mut child = tokio::process::Command::new(&program)
+            .args(&args)
+            .env("TERM", "xterm-256color")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| anyhow!("Could not start {}: {}", program, e))?;
+        log::info!("Started {} for {}", program, label);
+
+        let (event_tx, event_rx) = mpsc::channel(256);
+        let (command_tx, command_rx) = mpsc::channel(256);
+        let stdout = child.stdout.take().ok_or_else(|| anyhow!("No stdout"))?;
+        let stderr = child.stderr.take().ok_or_else(|| anyhow!("No stderr"))?;
+        let stdin = child.stdin.take().ok_or_else(|| anyhow!("No stdin"))?;
+
+        let task = tokio::spawn(supervise(event_tx.clone(), async move {
+            run_process_session(child, stdin, stdout, stderr, event_tx, command_rx).await
+        }));
+
+        Ok(Self {
+            id: Uuid::new_v4(),
+            host: label,
+            username: String::new(),
+            port: 0,
+            event_rx,
+            command_tx,
+            task,
         })
     }
 
+    /// Whether the background task has exited
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
     /// Try to receive events (non-blocking)
     pub fn try_recv(&mut self) -> Option<SessionEvent> {
         self.event_rx.try_recv().ok()
     }
 
+    /// Sender for commands to this session, for callers outside the UI thread
+    pub fn command_sender(&self) -> mpsc::Sender<SessionCommand> {
+        self.command_tx.clone()
+    }
+
     /// Send data to the SSH session
     pub fn send_data(&self, data: Vec<u8>) {
         let _ = self.command_tx.try_send(SessionCommand::SendData(data));
@@ -153,92 +305,298 @@ impl ActiveSession {
         let _ = self.command_tx.try_send(SessionCommand::Resize(cols, rows));
     }
 
+    /// Send a no-op request over the connection
+    pub fn send_keepalive(&self) {
+        let _ = self.command_tx.try_send(SessionCommand::Keepalive);
+    }
+
+    /// Change how often keepalives go out while the session is idle
+    pub fn set_keepalive(&self, interval: Option<Duration>) {
+        let _ = self.command_tx.try_send(SessionCommand::SetKeepalive(interval));
+    }
+
+    /// Ask the server for a reply, e.g. after the network changed
+    pub fn probe(&self) {
+        let _ = self.command_tx.try_send(SessionCommand::Probe);
+    }
+
+    /// Run a command next to the shell, e.g. to list containers; resolves
+    /// to its stdout, or an error with its stderr if it failed
+    pub fn exec(&self, command: String) -> impl Future<Output = Result<String>> {
+        let command_tx = self.command_tx.clone();
+        async move {
+            let (reply, result) = oneshot::channel();
+            command_tx.send(SessionCommand::Exec(command, reply)).await
+                .map_err(|_| anyhow!("Session closed"))?;
+            result.await.map_err(|_| anyhow!("Session closed"))?
+        }
+    }
+
+    /// Another shell on this session's connection, without a new handshake
+    /// or login. Fails if the server refuses more channels (sshd's
+    /// `MaxSessions`), in which case the caller should connect separately.
+    pub fn open_shell(&self, pty: PtyOptions) -> impl Future<Output = Result<ActiveSession>> {
+        let command_tx = self.command_tx.clone();
+        let (host, username, port) = (self.host.clone(), self.username.clone(), self.port);
+        async move {
+            let (reply, result) = oneshot::channel();
+            command_tx.send(SessionCommand::OpenShell(pty, reply)).await
+                .map_err(|_| anyhow!("Session closed"))?;
+            let mut session = result.await.map_err(|_| anyhow!("Session closed"))??;
+            session.host = host;
+            session.username = username;
+            session.port = port;
+            Ok(session)
+        }
+    }
+
+    /// SFTP over this session's connection, without logging in again. The
+    /// connection stays up only as long as a shell on it does.
+    pub fn open_sftp(&self) -> impl Future<Output = Result<SftpClient>> {
+        let command_tx = self.command_tx.clone();
+        async move {
+            let (reply, result) = oneshot::channel();
+            command_tx.send(SessionCommand::OpenSftp(reply)).await
+                .map_err(|_| anyhow!("Session closed"))?;
+            result.await.map_err(|_| anyhow!("Session closed"))?
+        }
+    }
+
     /// Disconnect the session
     pub fn disconnect(&self) {
         let _ = self.command_tx.try_send(SessionCommand::Disconnect);
     }
 }
 
-async fn run_session_password(
+/// Run a session task so that every exit path ends with a final event:
+/// `Disconnected` on a clean close, `Error` otherwise.
+async fn supervise(event_tx: mpsc::Sender<SessionEvent>, session: impl Future<Output = Result<()>>) {
+    match session.await {
+        Ok(()) => {
+            let _ = event_tx.send(SessionEvent::Disconnected).await;
+        }
+        Err(e) => {
+            log::error!("Session error: {}", e);
+            let _ = event_tx.send(SessionEvent::Error(e.to_string())).await;
+        }
+    }
+}
+
+fn client_config() -> client::Config {
+    client::Config {
+        inactivity_timeout: Some(Duration::from_secs(300)),
+        ..Default::default()
+    }
+}
+
+async fn connect(addr: &str, handler: SessionHandler) -> Result<(Handle<SessionHandler>, ServerHello)> {
+    let config = client_config();
+    let handshake = async {
+        let (stream, hello) = ServerHello::tap(TcpStream::connect(addr).await?, &config.preferred);
+        let handle = client::connect_stream(Arc::new(config), stream, handler).await?;
+        Ok((handle, hello))
+    };
+    tokio::time::timeout(CONNECT_TIMEOUT, handshake)
+        .await
+        .map_err(|_| anyhow!("Timed out connecting to {}", addr))?
+}
+
+/// SSH handshake over a direct-tcpip channel of `bastion`, for a host
+/// behind a jump host
+async fn connect_via(
+    bastion: &SshConnection,
     host: &str,
     port: u16,
-    username: &str,
+    handler: SessionHandler,
+) -> Result<(Handle<SessionHandler>, ServerHello)> {
+    let config = client_config();
+    let handshake = async {
+        let tunnel = bastion.open_tunnel(host, port).await?;
+        let (stream, hello) = ServerHello::tap(tunnel.into_stream(), &config.preferred);
+        let handle = client::connect_stream(Arc::new(config), stream, handler).await?;
+        Ok((handle, hello))
+    };
+    tokio::time::timeout(CONNECT_TIMEOUT, handshake)
+        .await
+        .map_err(|_| anyhow!("Timed out connecting to {}:{} through {}", host, port, bastion.config().host))?
+}
+
+async fn run_session_password(
+    config: &ConnectionConfig,
     password: &str,
+    pty: PtyOptions,
     event_tx: mpsc::Sender<SessionEvent>,
     command_rx: mpsc::Receiver<SessionCommand>,
 ) -> Result<()> {
-    let config = client::Config {
-        inactivity_timeout: Some(std::time::Duration::from_secs(300)),
-        ..Default::default()
-    };
-
-    let addr = format!("{}:{}", host, port);
+    let addr = config.dial_address();
     log::info!("Connecting to {}", addr);
 
-    let handler = SessionHandler::new(host);
-    let mut handle = client::connect(Arc::new(config), &addr, handler).await?;
+    let handler = SessionHandler::new(config);
+    let (mut handle, hello) = connect(&addr, handler).await?;
 
-    log::info!("Authenticating as {}", username);
-    let authenticated = handle.authenticate_password(username, password).await?;
+    log::info!("Authenticating as {}", config.username);
+    let authenticated = handle.authenticate_password(&config.username, password).await?;
 
     if !authenticated {
-        let _ = event_tx.send(SessionEvent::Error("Authentication failed".to_string())).await;
-        return Err(anyhow::anyhow!("Authentication failed"));
+        return Err(anyhow!("Authentication failed"));
     }
+    report_session_info(&addr, &hello, &event_tx).await;
 
-    run_shell_session(handle, event_tx, command_rx).await
+    run_shell_session(handle, pty, event_tx, command_rx).await
 }
 
 async fn run_session_key(
-    host: &str,
-    port: u16,
-    username: &str,
+    config: &ConnectionConfig,
     key_path: &str,
     passphrase: Option<&str>,
+    pty: PtyOptions,
     event_tx: mpsc::Sender<SessionEvent>,
     command_rx: mpsc::Receiver<SessionCommand>,
 ) -> Result<()> {
-    let config = client::Config {
-        inactivity_timeout: Some(std::time::Duration::from_secs(300)),
-        ..Default::default()
-    };
-
-    let addr = format!("{}:{}", host, port);
+    let addr = config.dial_address();
     log::info!("Connecting to {}", addr);
 
-    let handler = SessionHandler::new(host);
-    let mut handle = client::connect(Arc::new(config), &addr, handler).await?;
+    let handler = SessionHandler::new(config);
+    let (mut handle, hello) = connect(&addr, handler).await?;
 
-    log::info!("Authenticating with key as {}", username);
+    log::info!("Authenticating with key as {}", config.username);
     let key_data = tokio::fs::read_to_string(key_path).await?;
     let key_pair = russh_keys::decode_secret_key(&key_data, passphrase)?;
 
-    let authenticated = handle.authenticate_publickey(username, Arc::new(key_pair)).await?;
+    let authenticated = handle.authenticate_publickey(&config.username, Arc::new(key_pair)).await?;
 
     if !authenticated {
-        let _ = event_tx.send(SessionEvent::Error("Key authentication failed".to_string())).await;
-        return Err(anyhow::anyhow!("Key authentication failed"));
+        return Err(anyhow!("Key authentication failed"));
     }
+    report_session_info(&addr, &hello, &event_tx).await;
 
-    run_shell_session(handle, event_tx, command_rx).await
+    run_shell_session(handle, pty, event_tx, command_rx).await
+}
+
+async fn run_session_methods(
+    config: &ConnectionConfig,
+    methods: &[Credentials],
+    via: Option<&SshConnection>,
+    pty: PtyOptions,
+    event_tx: mpsc::Sender<SessionEvent>,
+    command_rx: mpsc::Receiver<SessionCommand>,
+) -> Result<()> {
+    let addr = config.dial_address();
+    let handler = SessionHandler::new(config);
+    let (mut handle, hello) = match via {
+        Some(bastion) => {
+            log::info!("Connecting to {} through {}", addr, bastion.config().host);
+            connect_via(bastion, &config.host, config.port, handler).await?
+        }
+        None => {
+            log::info!("Connecting to {}", addr);
+            connect(&addr, handler).await?
+        }
+    };
+
+    log::info!("Authenticating as {} with {} method(s)", config.username, methods.len());
+    let progress_tx = event_tx.clone();
+    auth::authenticate(&mut handle, config, methods, |progress| {
+        let _ = progress_tx.try_send(SessionEvent::Auth(progress));
+    })
+    .await?;
+    report_session_info(&addr, &hello, &event_tx).await;
+
+    run_shell_session(handle, pty, event_tx, command_rx).await
+}
+
+/// Pass on the negotiated details; failures only lose the popover
+async fn report_session_info(addr: &str, hello: &ServerHello, event_tx: &mpsc::Sender<SessionEvent>) {
+    match hello.session_info() {
+        Ok(info) => {
+            let _ = event_tx.send(SessionEvent::Info(info)).await;
+        }
+        Err(e) => log::debug!("No session details for {}: {}", addr, e),
+    }
 }
 
 async fn run_shell_session(
     handle: Handle<SessionHandler>,
+    pty: PtyOptions,
     event_tx: mpsc::Sender<SessionEvent>,
-    mut command_rx: mpsc::Receiver<SessionCommand>,
+    command_rx: mpsc::Receiver<SessionCommand>,
 ) -> Result<()> {
     log::info!("Opening shell channel");
-    let mut channel = handle.channel_open_session().await?;
+    let channel = handle.channel_open_session().await?;
+    run_channel(Arc::new(handle), channel, pty, event_tx, command_rx).await
+}
 
-    channel.request_pty(false, "xterm-256color", 80, 24, 0, 0, &[]).await?;
-    channel.request_shell(false).await?;
+/// Next keepalive tick, or never while keepalives are off
+async fn next_tick(keepalive: &mut Option<tokio::time::Interval>) {
+    match keepalive {
+        Some(timer) => {
+            timer.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Start a shell on a channel opened on a connection other tabs may share
+async fn open_sftp(handle: &Handle<SessionHandler>) -> Result<SftpClient> {
+    let channel = handle.channel_open_session().await?;
+    channel.request_subsystem(true, "sftp").await?;
+    let mut sftp = SftpClient::new(Uuid::new_v4().to_string());
+    sftp.connect(channel).await?;
+    Ok(sftp)
+}
+
+fn spawn_shared_shell(
+    handle: Arc<Handle<SessionHandler>>,
+    channel: russh::Channel<client::Msg>,
+    pty: PtyOptions,
+) -> ActiveSession {
+    let (event_tx, event_rx) = mpsc::channel(256);
+    let (command_tx, command_rx) = mpsc::channel(256);
+    let task = tokio::spawn(supervise(event_tx.clone(), async move {
+        run_channel(handle, channel, pty, event_tx, command_rx).await
+    }));
+
+    // `open_shell` fills in the connection details
+    ActiveSession {
+        id: Uuid::new_v4(),
+        host: String::new(),
+        username: String::new(),
+        port: 0,
+        event_rx,
+        command_tx,
+        task,
+    }
+}
+
+/// Drive one shell channel. The connection is closed when the last
+/// channel sharing it ends.
+async fn run_channel(
+    handle: Arc<Handle<SessionHandler>>,
+    mut channel: russh::Channel<client::Msg>,
+    pty: PtyOptions,
+    event_tx: mpsc::Sender<SessionEvent>,
+    mut command_rx: mpsc::Receiver<SessionCommand>,
+) -> Result<()> {
+    log::debug!("Requesting {} PTY of {}x{}", pty.term, pty.cols, pty.rows);
+    channel.request_pty(false, &pty.term, pty.cols, pty.rows, 0, 0, &[]).await?;
+    match &pty.command {
+        Some(command) => channel.exec(false, command.as_str()).await?,
+        None => channel.request_shell(false).await?,
+    }
 
     let _ = event_tx.send(SessionEvent::Connected).await;
     log::info!("Shell session started");
 
+    let mut size = (pty.cols, pty.rows);
+    let mut keepalive: Option<tokio::time::Interval> = None;
+
     loop {
         tokio::select! {
+            _ = next_tick(&mut keepalive) => {
+                if let Err(e) = channel.window_change(size.0, size.1, 0, 0).await {
+                    log::warn!("Keepalive failed: {}", e);
+                }
+            }
             msg = channel.wait() => {
                 match msg {
                     Some(ChannelMsg::Data { data }) => {
@@ -248,7 +606,6 @@ async fn run_shell_session(
                     }
                     Some(ChannelMsg::Eof) | Some(ChannelMsg::Close) | None => {
                         log::info!("Channel closed");
-                        let _ = event_tx.send(SessionEvent::Disconnected).await;
                         break;
                     }
                     Some(ChannelMsg::ExitStatus { exit_status }) => {
@@ -265,10 +622,63 @@ async fn run_shell_session(
                         }
                     }
                     Some(SessionCommand::Resize(cols, rows)) => {
+                        size = (cols, rows);
                         if let Err(e) = channel.window_change(cols, rows, 0, 0).await {
                             log::warn!("Failed to resize: {}", e);
                         }
                     }
+                    Some(SessionCommand::Keepalive) => {
+                        // Re-sending the current size reaches the server but
+                        // raises no SIGWINCH, since the PTY size is unchanged
+                        if let Err(e) = channel.window_change(size.0, size.1, 0, 0).await {
+                            log::warn!("Keepalive failed: {}", e);
+                        }
+                    }
+                    Some(SessionCommand::SetKeepalive(interval)) => {
+                        keepalive = interval.filter(|i| !i.is_zero()).map(|period| {
+                            let mut timer = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+                            timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                            timer
+                        });
+                    }
+                    Some(SessionCommand::Probe) => {
+                        // Opening a channel needs a reply from the server, so a
+                        // dead path fails here rather than minutes later in TCP
+                        let started = std::time::Instant::now();
+                        match tokio::time::timeout(PROBE_TIMEOUT, handle.channel_open_session()).await {
+                            Ok(Ok(probe)) => {
+                                let _ = probe.close().await;
+                                let _ = event_tx.send(SessionEvent::Alive(started.elapsed())).await;
+                            }
+                            Ok(Err(e)) => return Err(anyhow!("Connection lost: {}", e)),
+                            Err(_) => return Err(anyhow!("Connection lost: no reply within {}s", PROBE_TIMEOUT.as_secs())),
+                        }
+                    }
+                    Some(SessionCommand::Exec(command, reply)) => {
+                        // Runs on its own channel and task, so a slow command
+                        // never holds up the shell's input and output
+                        let handle = handle.clone();
+                        tokio::spawn(async move {
+                            let output = tokio::time::timeout(EXEC_TIMEOUT, exec_output(&handle, &command))
+                                .await
+                                .unwrap_or_else(|_| Err(anyhow!("`{}` did not finish within {}s", command, EXEC_TIMEOUT.as_secs())));
+                            let _ = reply.send(output);
+                        });
+                    }
+                    Some(SessionCommand::OpenShell(pty, reply)) => {
+                        let opened = match tokio::time::timeout(PROBE_TIMEOUT, handle.channel_open_session()).await {
+                            Ok(Ok(shared)) => Ok(spawn_shared_shell(handle.clone(), shared, pty)),
+                            Ok(Err(e)) => Err(anyhow!("Server refused another session on this connection: {}", e)),
+                            Err(_) => Err(anyhow!("Server did not open another session within {}s", PROBE_TIMEOUT.as_secs())),
+                        };
+                        let _ = reply.send(opened);
+                    }
+                    Some(SessionCommand::OpenSftp(reply)) => {
+                        let opened = tokio::time::timeout(PROBE_TIMEOUT, open_sftp(&handle))
+                            .await
+                            .unwrap_or_else(|_| Err(anyhow!("Server did not start SFTP within {}s", PROBE_TIMEOUT.as_secs())));
+                        let _ = reply.send(opened);
+                    }
                     Some(SessionCommand::Disconnect) | None => {
                         log::info!("Disconnect requested");
                         break;
@@ -278,6 +688,113 @@ async fn run_shell_session(
         }
     }
 
-    let _ = handle.disconnect(Disconnect::ByApplication, "Session ended", "en").await;
+    let _ = channel.close().await;
+    // Tabs still sharing the connection keep it open
+    if let Ok(handle) = Arc::try_unwrap(handle) {
+        let _ = handle.disconnect(Disconnect::ByApplication, "Session ended", "en").await;
+    }
     Ok(())
 }
+
+/// Run `command` on a fresh channel and collect its output
+async fn exec_output(handle: &Handle<SessionHandler>, command: &str) -> Result<String> {
+    let mut channel = handle.channel_open_session().await?;
+    channel.exec(true, command).await?;
+
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    let mut exit_status = None;
+    while let Some(msg) = channel.wait().await {
+        match msg {
+            ChannelMsg::Data { data } => stdout.extend_from_slice(&data),
+            ChannelMsg::ExtendedData { data, ext: 1 } => stderr.extend_from_slice(&data),
+            ChannelMsg::ExitStatus { exit_status: status } => exit_status = Some(status),
+            ChannelMsg::Close => break,
+            _ => {}
+        }
+    }
+
+    match exit_status {
+        Some(0) | None => Ok(String::from_utf8_lossy(&stdout).into_owned()),
+        Some(status) => {
+            let stderr = String::from_utf8_lossy(&stderr);
+            Err(anyhow!("`{}` exited with {}: {}", command, status, stderr.trim()))
+        }
+    }
+}
+
+/// Pump a local process's pipes until it exits or the tab disconnects
+async fn run_process_session(
+    mut child: tokio::process::Child,
+    mut stdin: tokio::process::ChildStdin,
+    mut stdout: tokio::process::ChildStdout,
+    mut stderr: tokio::process::ChildStderr,
+    event_tx: mpsc::Sender<SessionEvent>,
+    mut command_rx: mpsc::Receiver<SessionCommand>,
+) -> Result<()> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let _ = event_tx.send(SessionEvent::Connected).await;
+    let mut out_buf = [0u8; 8192];
+    let mut err_buf = [0u8; 8192];
+    let mut stderr_open = true;
+
+    loop {
+        tokio::select! {
+            read = stdout.read(&mut out_buf) => {
+                match read? {
+                    0 => break,
+                    n => {
+                        if event_tx.send(SessionEvent::Data(out_buf[..n].to_vec())).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+            read = stderr.read(&mut err_buf), if stderr_open => {
+                match read? {
+                    0 => stderr_open = false,
+                    n => {
+                        let _ = event_tx.send(SessionEvent::Data(err_buf[..n].to_vec())).await;
+                    }
+                }
+            }
+            cmd = command_rx.recv() => {
+                match cmd {
+                    Some(SessionCommand::SendData(data)) => {
+                        stdin.write_all(&data).await?;
+                        stdin.flush().await?;
+                    }
+                    // No terminal to resize and no connection to exercise
+                    Some(SessionCommand::Resize(..))
+                    | Some(SessionCommand::Keepalive)
+                    | Some(SessionCommand::SetKeepalive(_)) => {}
+                    Some(SessionCommand::Exec(_, reply)) => {
+                        let _ = reply.send(Err(anyhow!("Not an SSH session")));
+                    }
+                    Some(SessionCommand::OpenShell(_, reply)) => {
+                        let _ = reply.send(Err(anyhow!("Not an SSH session")));
+                    }
+                    Some(SessionCommand::OpenSftp(reply)) => {
+                        let _ = reply.send(Err(anyhow!("Not an SSH session")));
+                    }
+                    Some(SessionCommand::Probe) => {
+                        let _ = event_tx.send(SessionEvent::Alive(Duration::ZERO)).await;
+                    }
+                    Some(SessionCommand::Disconnect) | None => {
+                        log::info!("Disconnect requested");
+                        let _ = child.kill().await;
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    let status = child.wait().await?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("Process exited with {}", status))
+    }
+}
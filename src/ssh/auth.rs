@@ -1,7 +1,27 @@
 //! SSH authentication handling
 
-use anyhow::Result;
-use std::path::PathBuf;
+use anyhow::{anyhow, Result};
+use super::bastion::{BastionId, MfaPrompt, MfaPrompter};
+use super::ConnectionConfig;
+use russh::client::{self, Handle, KeyboardInteractiveAuthResponse};
+use russh_keys::agent::client::AgentClient;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// Pipe of the OpenSSH agent service on Windows, when `SSH_AUTH_SOCK` is unset
+const WINDOWS_AGENT_PIPE: &str = r"\\.\pipe\openssh-ssh-agent";
+
+/// Where keyboard-interactive questions go that the saved password can't answer
+static CHALLENGE_PROMPTER: std::sync::Mutex<Option<MfaPrompter>> = std::sync::Mutex::new(None);
+
+/// Put the questions of every keyboard-interactive login from now on to
+/// the user through `prompter`
+pub fn set_challenge_prompter(prompter: MfaPrompter) {
+    if let Ok(mut current) = CHALLENGE_PROMPTER.lock() {
+        *current = Some(prompter);
+    }
+}
 
 /// Credentials for SSH authentication
 #[derive(Debug, Clone)]
@@ -46,31 +66,227 @@ impl Credentials {
     pub fn keyboard_interactive() -> Self {
         Self::KeyboardInteractive
     }
+
+    /// Stable name for remembering which method worked, e.g. `key:~/.ssh/id_rsa`
+    pub fn id(&self) -> String {
+        match self {
+            Self::Password { .. } => "password".to_string(),
+            Self::PublicKey { key_path, .. } => format!("key:{}", key_path.display()),
+            Self::Agent => "agent".to_string(),
+            Self::KeyboardInteractive => "keyboard-interactive".to_string(),
+        }
+    }
+
+    /// For the attempt log shown while connecting
+    pub fn label(&self) -> String {
+        match self {
+            Self::Password { .. } => "password".to_string(),
+            Self::PublicKey { key_path, .. } => format!("key {}", key_path.display()),
+            Self::Agent => "SSH agent".to_string(),
+            Self::KeyboardInteractive => "keyboard-interactive".to_string(),
+        }
+    }
 }
 
-/// Find default SSH keys in user's .ssh directory
-pub fn find_default_keys() -> Vec<PathBuf> {
-    let mut keys = Vec::new();
+/// Progress of `authenticate`, one step at a time
+#[derive(Debug, Clone)]
+pub enum AuthProgress {
+    Trying(String),
+    /// Method label and why it did not work
+    Failed(String, String),
+    /// `Credentials::id` of the method that worked
+    Succeeded(String),
+}
 
-    if let Some(home) = dirs::home_dir() {
-        let ssh_dir = home.join(".ssh");
+/// Move the method with `id` to the front, keeping the others in order
+pub fn prefer_method(methods: &mut Vec<Credentials>, id: &str) {
+    if let Some(index) = methods.iter().position(|m| m.id() == id) {
+        let method = methods.remove(index);
+        methods.insert(0, method);
+    }
+}
 
-        let key_names = [
-            "id_ed25519",
-            "id_ecdsa",
-            "id_rsa",
-            "id_dsa",
-        ];
+/// Try `methods` in order until the server accepts one, like OpenSSH does
+/// with several IdentityFiles. A method that fails, or can't even be tried
+/// (unreadable key, no agent), is reported and the next one is tried.
+/// A keyboard-interactive password prompt is answered with the password
+/// from the list, if there is one; other questions go to the user. Returns
+/// the index of the method that worked.
+pub async fn authenticate<H: client::Handler>(
+    handle: &mut Handle<H>,
+    config: &ConnectionConfig,
+    methods: &[Credentials],
+    mut report: impl FnMut(AuthProgress),
+) -> Result<usize> {
+    let username = config.username.as_str();
+    if methods.is_empty() {
+        return Err(anyhow!("No authentication method to try"));
+    }
+    let password = methods.iter().find_map(|m| match m {
+        Credentials::Password { password } => Some(password.as_str()),
+        _ => None,
+    });
+
+    for (index, method) in methods.iter().enumerate() {
+        let label = method.label();
+        report(AuthProgress::Trying(label.clone()));
+        let result = match method {
+            Credentials::Password { password } => handle
+                .authenticate_password(username, password)
+                .await
+                .map_err(anyhow::Error::from),
+            Credentials::PublicKey { key_path, passphrase } => {
+                match read_key(key_path, passphrase.as_deref()).await {
+                    Ok(key) => handle
+                        .authenticate_publickey(username, Arc::new(key))
+                        .await
+                        .map_err(anyhow::Error::from),
+                    Err(e) => Err(anyhow!("could not load the key: {}", e)),
+                }
+            }
+            Credentials::Agent => authenticate_agent(handle, username).await,
+            Credentials::KeyboardInteractive => {
+                let server = BastionId { user: username.to_string(), host: config.host.clone(), port: config.port };
+                authenticate_keyboard_interactive(handle, &server, password).await
+            }
+        };
+        match result {
+            Ok(true) => {
+                log::info!("Authenticated as {} with {}", username, label);
+                report(AuthProgress::Succeeded(method.id()));
+                return Ok(index);
+            }
+            Ok(false) => report(AuthProgress::Failed(label, "rejected by the server".to_string())),
+            Err(e) => report(AuthProgress::Failed(label, e.to_string())),
+        }
+    }
+    Err(anyhow!("Authentication failed: no method was accepted"))
+}
+
+/// Where the local agent listens
+#[derive(Debug, Clone, PartialEq)]
+enum AgentAddress {
+    /// Unix socket
+    Socket(PathBuf),
+    /// Windows named pipe
+    Pipe(String),
+}
+
+/// The agent `SSH_AUTH_SOCK` points at. On Windows it may name a pipe, e.g.
+/// the one `pageant --openssh-config` writes out; when it is unset the
+/// OpenSSH agent service's pipe is tried.
+fn agent_address(auth_sock: Option<String>, windows: bool) -> Option<AgentAddress> {
+    match auth_sock.filter(|path| !path.is_empty()) {
+        Some(path) if windows && (path.starts_with(r"\\.\pipe\") || path.starts_with("//./pipe/")) => {
+            Some(AgentAddress::Pipe(path))
+        }
+        Some(path) => Some(AgentAddress::Socket(PathBuf::from(path))),
+        None if windows => Some(AgentAddress::Pipe(WINDOWS_AGENT_PIPE.to_string())),
+        None => None,
+    }
+}
+
+/// Offer each key the local agent holds until the server accepts one
+async fn authenticate_agent<H: client::Handler>(handle: &mut Handle<H>, username: &str) -> Result<bool> {
+    let address = agent_address(std::env::var("SSH_AUTH_SOCK").ok(), cfg!(windows))
+        .ok_or_else(|| anyhow!("no SSH agent: SSH_AUTH_SOCK is not set"))?;
+    match address {
+        #[cfg(unix)]
+        AgentAddress::Socket(path) => {
+            let stream = tokio::net::UnixStream::connect(&path)
+                .await
+                .map_err(|e| anyhow!("no SSH agent at {}: {}", path.display(), e))?;
+            offer_agent_keys(handle, username, AgentClient::connect(stream)).await
+        }
+        #[cfg(windows)]
+        AgentAddress::Pipe(pipe) => {
+            let client = tokio::net::windows::named_pipe::ClientOptions::new()
+                .open(&pipe)
+                .map_err(|e| anyhow!("no SSH agent at {}: {}", pipe, e))?;
+            offer_agent_keys(handle, username, AgentClient::connect(client)).await
+        }
+        #[cfg(not(unix))]
+        AgentAddress::Socket(path) => Err(anyhow!("no SSH agent at {}: only named pipes are supported here", path.display())),
+        #[cfg(not(windows))]
+        AgentAddress::Pipe(pipe) => Err(anyhow!("no SSH agent at {}: named pipes exist only on Windows", pipe)),
+    }
+}
 
-        for name in key_names {
-            let key_path = ssh_dir.join(name);
-            if key_path.exists() {
-                keys.push(key_path);
+async fn offer_agent_keys<H, S>(handle: &mut Handle<H>, username: &str, mut agent: AgentClient<S>) -> Result<bool>
+where
+    H: client::Handler,
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    let keys = agent.request_identities().await?;
+    if keys.is_empty() {
+        return Err(anyhow!("the agent holds no keys"));
+    }
+    let count = keys.len();
+    for (i, key) in keys.into_iter().enumerate() {
+        let fingerprint = key.fingerprint();
+        let (returned, result) = handle.authenticate_future(username, key, agent).await;
+        agent = returned;
+        if result.map_err(|e| anyhow!("agent could not sign with {}: {:?}", fingerprint, e))? {
+            log::info!("Agent key {} of {} accepted: {}", i + 1, count, fingerprint);
+            return Ok(true);
+        }
+        log::debug!("Agent key {} not accepted for {}", fingerprint, username);
+    }
+    Ok(false)
+}
+
+/// Answer a lone hidden password prompt with `password`, once; a one-time
+/// code or any other question goes to the user
+async fn authenticate_keyboard_interactive<H: client::Handler>(
+    handle: &mut Handle<H>,
+    server: &BastionId,
+    mut password: Option<&str>,
+) -> Result<bool> {
+    let mut response = handle.authenticate_keyboard_interactive_start(&server.user, None).await?;
+    loop {
+        match response {
+            KeyboardInteractiveAuthResponse::Success => return Ok(true),
+            KeyboardInteractiveAuthResponse::Failure => return Ok(false),
+            KeyboardInteractiveAuthResponse::InfoRequest { name, instructions, prompts } => {
+                let questions: Vec<(String, bool)> = prompts.into_iter().map(|p| (p.prompt, p.echo)).collect();
+                let answers = if questions.is_empty() {
+                    Vec::new()
+                } else if let Some(saved) = password.filter(|_| is_password_prompt(&questions)) {
+                    // Asked again means the saved password was wrong
+                    password = None;
+                    vec![saved.to_string()]
+                } else {
+                    ask_user(server, name, instructions, questions).await?
+                };
+                response = handle.authenticate_keyboard_interactive_respond(answers).await?;
             }
         }
     }
+}
 
-    keys
+/// One hidden prompt asking for the password, as servers using PAM send
+fn is_password_prompt(questions: &[(String, bool)]) -> bool {
+    matches!(questions, [(text, false)] if text.to_lowercase().contains("password"))
+}
+
+async fn ask_user(server: &BastionId, name: String, instructions: String, questions: Vec<(String, bool)>) -> Result<Vec<String>> {
+    let prompter = CHALLENGE_PROMPTER.lock().ok().and_then(|prompter| prompter.clone())
+        .ok_or_else(|| anyhow!("the server asked questions and nobody can answer them here"))?;
+    let (prompt, answer) = MfaPrompt::new(server.clone(), name, instructions, questions);
+    prompter.send(prompt).map_err(|_| anyhow!("Nowhere to ask the questions of {}", server))?;
+    answer.await.ok().flatten().ok_or_else(|| anyhow!("Login to {} was cancelled", server))
+}
+
+/// Find the user's SSH keys: the standard names in ~/.ssh, then the
+/// IdentityFiles of ~/.ssh/config that exist
+pub fn find_default_keys() -> Vec<PathBuf> {
+    let Some(home) = dirs::home_dir() else { return Vec::new() };
+    let config = super::SshConfigParser::parse_default().ok();
+    super::key_health::candidates(&home.join(".ssh"), config.as_ref())
+        .into_iter()
+        .map(|(path, _)| path)
+        .filter(|path| path.exists())
+        .collect()
 }
 
 /// Read SSH key from file
@@ -85,3 +301,79 @@ pub fn is_key_encrypted(path: &std::path::Path) -> Result<bool> {
     let key_data = std::fs::read_to_string(path)?;
     Ok(key_data.contains("ENCRYPTED"))
 }
+
+/// Check that a private key file can be used: it exists, is readable, and
+/// is not open to other users (OpenSSH refuses such keys)
+pub fn check_key_file(path: &Path) -> Result<()> {
+    let meta = std::fs::metadata(path).map_err(|_| anyhow!("Key file not found"))?;
+    if !meta.is_file() {
+        return Err(anyhow!("Key path is not a file"));
+    }
+    std::fs::File::open(path).map_err(|_| anyhow!("Key file is not readable"))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = meta.permissions().mode() & 0o777;
+        if mode & 0o077 != 0 {
+            return Err(anyhow!("Key file permissions {:o} are too open; use 600", mode));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefer_method() {
+        let mut methods = vec![
+            Credentials::agent(),
+            Credentials::public_key("/keys/id_ed25519", None),
+            Credentials::public_key("/keys/id_work", None),
+            Credentials::password("secret"),
+        ];
+        prefer_method(&mut methods, "key:/keys/id_work");
+        let ids: Vec<String> = methods.iter().map(Credentials::id).collect();
+        assert_eq!(ids, ["key:/keys/id_work", "agent", "key:/keys/id_ed25519", "password"]);
+
+        // Gone from the profile since; order unchanged
+        prefer_method(&mut methods, "key:/keys/id_old");
+        assert_eq!(methods[0].id(), "key:/keys/id_work");
+    }
+
+    #[test]
+    fn test_is_password_prompt() {
+        let prompt = |text: &str, echo: bool| vec![(text.to_string(), echo)];
+        assert!(is_password_prompt(&prompt("Password: ", false)));
+        assert!(is_password_prompt(&prompt("alice@db-01's password:", false)));
+        assert!(!is_password_prompt(&prompt("Verification code: ", false)));
+        assert!(!is_password_prompt(&prompt("Password: ", true)));
+        let both = vec![("Password: ".to_string(), false), ("OTP: ".to_string(), false)];
+        assert!(!is_password_prompt(&both));
+    }
+
+    #[test]
+    fn test_agent_address() {
+        let sock = |path: &str| Some(path.to_string());
+
+        assert_eq!(agent_address(sock("/tmp/ssh-XXXX/agent.42"), false), Some(AgentAddress::Socket("/tmp/ssh-XXXX/agent.42".into())));
+        assert_eq!(agent_address(None, false), None);
+        assert_eq!(agent_address(sock(""), false), None);
+        // Only Windows has pipes; the same path is a socket elsewhere
+        assert_eq!(agent_address(sock(r"\\.\pipe\pageant.user"), false), Some(AgentAddress::Socket(r"\\.\pipe\pageant.user".into())));
+
+        assert_eq!(agent_address(sock(r"\\.\pipe\pageant.user"), true), Some(AgentAddress::Pipe(r"\\.\pipe\pageant.user".to_string())));
+        assert_eq!(agent_address(sock("//./pipe/openssh-ssh-agent"), true), Some(AgentAddress::Pipe("//./pipe/openssh-ssh-agent".to_string())));
+        // Sockets of Cygwin and MSYS agents are files, not pipes
+        assert_eq!(agent_address(sock("C:/Users/me/.ssh/agent.sock"), true), Some(AgentAddress::Socket("C:/Users/me/.ssh/agent.sock".into())));
+    }
+
+    #[test]
+    fn test_agent_address_falls_back_to_the_openssh_service() {
+        assert_eq!(agent_address(None, true), Some(AgentAddress::Pipe(WINDOWS_AGENT_PIPE.to_string())));
+        assert_eq!(agent_address(Some(String::new()), true), Some(AgentAddress::Pipe(WINDOWS_AGENT_PIPE.to_string())));
+    }
+}
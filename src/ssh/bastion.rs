@@ -0,0 +1,309 @@
+//! Bastions that ask for a second factor again and again
+//!
+//! Some bastions end a login after a while unless the user verifies again,
+//! with a one-time code or a push to their phone. SSH cannot re-authenticate
+//! a connection that is already open, so the hop is renewed by logging in to
+//! the bastion again shortly before the deadline. Bastions that tie the
+//! grant to the user keep the earlier connection, and with it every session
+//! tunnelled through it; new tunnels (tabs, SFTP, reconnects) use the newest
+//! login. The challenges of such a login are sent to the UI together with
+//! the bastion they belong to, so the dialog names the bastion and not the
+//! host the user is working on.
+
+use anyhow::{anyhow, Result};
+use russh::client::{self, Handle, KeyboardInteractiveAuthResponse};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+use super::{ConnectionConfig, Credentials, SshConnection};
+use crate::utils::executor::{Executor, Task};
+
+/// Renew this long before the bastion's deadline, leaving time to answer;
+/// short logins use a quarter of their length instead
+pub const REAUTH_LEAD: Duration = Duration::from_secs(2 * 60);
+
+/// When a login made at `logged_in` has to be renewed, for a bastion that
+/// ends logins after `every`. The lead never exceeds a quarter of the login,
+/// so a short one isn't renewed, and prompted for, the moment it is made.
+/// A zero interval means no deadline.
+fn renewal_due(logged_in: Instant, every: Option<Duration>) -> Option<Instant> {
+    let every = every.filter(|every| !every.is_zero())?;
+    Some(logged_in + every - REAUTH_LEAD.min(every / 4))
+}
+
+/// `user@host:port` of a bastion
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BastionId {
+    pub user: String,
+    pub host: String,
+    pub port: u16,
+}
+
+impl std::fmt::Display for BastionId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}:{}", self.user, self.host, self.port)
+    }
+}
+
+/// Questions a server asked during login, waiting for the user
+pub struct MfaPrompt {
+    /// Server asking; a bastion unless `jump_host` is false
+    pub bastion: BastionId,
+    /// Asked by a jump host to keep its login, rather than by the host itself
+    pub jump_host: bool,
+    /// Hosts reached through the bastion, to say what the login keeps open
+    pub leaf_hosts: Vec<String>,
+    pub name: String,
+    pub instructions: String,
+    /// Prompt text, and whether the answer may be shown while typed
+    pub prompts: Vec<(String, bool)>,
+    reply: oneshot::Sender<Option<Vec<String>>>,
+}
+
+impl MfaPrompt {
+    /// Questions for the user, and where the answers arrive; `None` if the
+    /// user cancelled
+    pub(super) fn new(
+        bastion: BastionId,
+        name: String,
+        instructions: String,
+        prompts: Vec<(String, bool)>,
+    ) -> (Self, oneshot::Receiver<Option<Vec<String>>>) {
+        let (reply, answer) = oneshot::channel();
+        let prompt = Self {
+            bastion,
+            jump_host: false,
+            leaf_hosts: Vec::new(),
+            name,
+            instructions,
+            prompts,
+            reply,
+        };
+        (prompt, answer)
+    }
+
+    /// One answer per prompt, in order
+    pub fn answer(self, answers: Vec<String>) {
+        let _ = self.reply.send(Some(answers));
+    }
+
+    pub fn cancel(self) {
+        let _ = self.reply.send(None);
+    }
+}
+
+/// Where bastion logins send their questions; the UI holds the receiver
+pub type MfaPrompter = mpsc::UnboundedSender<MfaPrompt>;
+
+/// Keyboard-interactive login with every question put to the user
+pub async fn answer_challenges<H: client::Handler>(
+    handle: &mut Handle<H>,
+    bastion: &BastionId,
+    leaf_hosts: &[String],
+    prompter: &MfaPrompter,
+) -> Result<bool> {
+    let mut response = handle.authenticate_keyboard_interactive_start(&bastion.user, None).await?;
+    loop {
+        match response {
+            KeyboardInteractiveAuthResponse::Success => return Ok(true),
+            KeyboardInteractiveAuthResponse::Failure => return Ok(false),
+            KeyboardInteractiveAuthResponse::InfoRequest { name, instructions, prompts } => {
+                // An empty round only checks in; it is answered without asking
+                let answers = if prompts.is_empty() {
+                    Vec::new()
+                } else {
+                    let questions = prompts.into_iter().map(|p| (p.prompt, p.echo)).collect();
+                    let (mut prompt, answer) = MfaPrompt::new(bastion.clone(), name, instructions, questions);
+                    prompt.jump_host = true;
+                    prompt.leaf_hosts = leaf_hosts.to_vec();
+                    prompter
+                        .send(prompt)
+                        .map_err(|_| anyhow!("Nowhere to ask for the verification of {}", bastion))?;
+                    answer
+                        .await
+                        .ok()
+                        .flatten()
+                        .ok_or_else(|| anyhow!("Verification for {} was cancelled", bastion))?
+                };
+                response = handle.authenticate_keyboard_interactive_respond(answers).await?;
+            }
+        }
+    }
+}
+
+/// A bastion and how to log in to it
+#[derive(Debug, Clone)]
+pub struct BastionHop {
+    pub id: BastionId,
+    pub config: ConnectionConfig,
+    /// Tried before the challenges; keyboard-interactive always goes to the user
+    pub methods: Vec<Credentials>,
+    /// The bastion ends logins after this long; `None` keeps one login
+    pub reauth_every: Option<Duration>,
+}
+
+/// What polling a keeper found
+#[derive(Debug, Clone, PartialEq)]
+pub enum BastionEvent {
+    LoggedIn(BastionId),
+    Renewed(BastionId),
+    /// The login failed; the previous one stays in use until it ends
+    Failed(BastionId, String),
+}
+
+/// Keeps one bastion logged in for the sessions tunnelled through it
+pub struct BastionKeeper {
+    hop: BastionHop,
+    leaf_hosts: Vec<String>,
+    prompter: MfaPrompter,
+    /// Newest login and when it happened
+    current: Option<(Arc<SshConnection>, Instant)>,
+    /// Earlier logins, dropped once no tunnel holds them
+    retired: Vec<Arc<SshConnection>>,
+    login: Option<Task<Result<SshConnection>>>,
+    /// The renewal of the current login failed or was cancelled; asking
+    /// again until the bastion ends it would only nag
+    renewal_failed: bool,
+    /// Why the first login failed; this keeper tunnels nothing after it
+    failure: Option<String>,
+}
+
+impl BastionKeeper {
+    pub fn new(hop: BastionHop, prompter: MfaPrompter) -> Self {
+        Self {
+            hop,
+            leaf_hosts: Vec::new(),
+            prompter,
+            current: None,
+            retired: Vec::new(),
+            login: None,
+            renewal_failed: false,
+            failure: None,
+        }
+    }
+
+    pub fn id(&self) -> &BastionId {
+        &self.hop.id
+    }
+
+    /// Name `host` in the bastion's prompts
+    pub fn add_leaf(&mut self, host: &str) {
+        if !self.leaf_hosts.iter().any(|h| h == host) {
+            self.leaf_hosts.push(host.to_string());
+        }
+    }
+
+    /// The newest login, to open tunnels on. Tunnels keep their clone, which
+    /// keeps that connection open after a renewal.
+    pub fn connection(&self) -> Option<Arc<SshConnection>> {
+        self.current.as_ref().map(|(connection, _)| connection.clone())
+    }
+
+    pub fn is_logging_in(&self) -> bool {
+        self.login.is_some()
+    }
+
+    /// Why the first login failed, if it did
+    pub fn failure(&self) -> Option<&str> {
+        self.failure.as_deref()
+    }
+
+    /// When the current login has to be renewed
+    pub fn renew_at(&self) -> Option<Instant> {
+        let (_, at) = self.current.as_ref()?;
+        renewal_due(*at, self.hop.reauth_every)
+    }
+
+    /// Start a login unless one is running
+    pub fn login(&mut self, executor: &Executor) {
+        if self.login.is_some() {
+            return;
+        }
+        let hop = self.hop.clone();
+        let leaf_hosts = self.leaf_hosts.clone();
+        let prompter = self.prompter.clone();
+        self.login = Some(executor.spawn(async move {
+            SshConnection::connect_bastion(hop.config, &hop.methods, &hop.id, &leaf_hosts, &prompter).await
+        }));
+    }
+
+    /// Collect a finished login and start the renewal once it is due. Call
+    /// every frame.
+    pub fn poll(&mut self, executor: &Executor) -> Option<BastionEvent> {
+        self.retired.retain(|connection| Arc::strong_count(connection) > 1);
+
+        if let Some(result) = self.login.as_mut().and_then(|login| login.poll()) {
+            self.login = None;
+            let id = self.hop.id.clone();
+            return Some(match result.and_then(|login| login) {
+                Ok(connection) => {
+                    let renewed = self.current.is_some();
+                    if let Some((previous, _)) = self.current.replace((Arc::new(connection), Instant::now())) {
+                        self.retired.push(previous);
+                    }
+                    self.renewal_failed = false;
+                    if renewed { BastionEvent::Renewed(id) } else { BastionEvent::LoggedIn(id) }
+                }
+                Err(e) => {
+                    self.renewal_failed = self.current.is_some();
+                    if self.current.is_none() {
+                        self.failure = Some(e.to_string());
+                    }
+                    BastionEvent::Failed(id, e.to_string())
+                }
+            });
+        }
+
+        let due = self.renew_at().is_some_and(|at| Instant::now() >= at);
+        if due && !self.renewal_failed {
+            log::info!("Renewing the login to {}", self.hop.id);
+            self.login(executor);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renewal_due_ahead_of_the_deadline() {
+        let logged_in = Instant::now();
+        assert_eq!(renewal_due(logged_in, None), None);
+        assert_eq!(
+            renewal_due(logged_in, Some(Duration::from_secs(30 * 60))),
+            Some(logged_in + Duration::from_secs(28 * 60))
+        );
+        // Short logins keep three quarters of their length
+        assert_eq!(
+            renewal_due(logged_in, Some(Duration::from_secs(60))),
+            Some(logged_in + Duration::from_secs(45))
+        );
+        assert_eq!(
+            renewal_due(logged_in, Some(REAUTH_LEAD)),
+            Some(logged_in + Duration::from_secs(90))
+        );
+        assert_eq!(renewal_due(logged_in, Some(Duration::ZERO)), None);
+    }
+
+    #[test]
+    fn test_keeper_renews_nothing_before_login() {
+        let (prompter, _prompts) = mpsc::unbounded_channel();
+        let hop = BastionHop {
+            id: BastionId { user: "ops".to_string(), host: "bastion.example.com".to_string(), port: 22 },
+            config: ConnectionConfig::default(),
+            methods: Vec::new(),
+            reauth_every: Some(Duration::from_secs(15 * 60)),
+        };
+        let mut keeper = BastionKeeper::new(hop, prompter);
+        keeper.add_leaf("db-01");
+        keeper.add_leaf("db-01");
+
+        assert_eq!(keeper.leaf_hosts, vec!["db-01".to_string()]);
+        assert!(keeper.connection().is_none());
+        assert!(keeper.renew_at().is_none());
+        assert!(!keeper.is_logging_in());
+        assert!(keeper.failure().is_none());
+    }
+}
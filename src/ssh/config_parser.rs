@@ -1,7 +1,7 @@
 //! SSH config file parser (~/.ssh/config)
 
 use anyhow::{anyhow, Result};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
 /// SSH config entry for a host
@@ -195,6 +195,92 @@ impl Default for SshConfigParser {
     }
 }
 
+/// Render host entries as `Host` blocks for ~/.ssh/config.
+/// Ports of 22 are left out, matching what `ssh` assumes. A name used by
+/// an earlier block gets a `-2`, `-3`... suffix, since `ssh` would only
+/// ever apply the first.
+pub fn write_config(hosts: &[HostConfig]) -> String {
+    let mut out = format!("# Exported from TabSSH on {}\n", chrono::Local::now().format("%Y-%m-%d %H:%M"));
+    let mut used = HashSet::new();
+
+    for host in hosts {
+        let mut name = host.host_pattern.clone();
+        let mut n = 1;
+        while !used.insert(name.to_lowercase()) {
+            n += 1;
+            name = format!("{}-{}", host.host_pattern, n);
+        }
+        out.push_str(&format!("\nHost {}\n", name));
+        if let Some(hostname) = &host.hostname {
+            out.push_str(&format!("    HostName {}\n", hostname));
+        }
+        if let Some(user) = &host.user {
+            out.push_str(&format!("    User {}\n", user));
+        }
+        if let Some(port) = host.port.filter(|port| *port != 22) {
+            out.push_str(&format!("    Port {}\n", port));
+        }
+        for identity in &host.identity_file {
+            out.push_str(&format!("    IdentityFile {}\n", quote_if_spaced(&collapse_tilde(identity))));
+        }
+        if let Some(jump) = &host.proxy_jump {
+            out.push_str(&format!("    ProxyJump {}\n", jump));
+        }
+        for (local_port, remote_host, remote_port) in &host.local_forward {
+            out.push_str(&format!("    LocalForward {} {}:{}\n", local_port, remote_host, remote_port));
+        }
+        for (remote_port, local_host, local_port) in &host.remote_forward {
+            out.push_str(&format!("    RemoteForward {} {}:{}\n", remote_port, local_host, local_port));
+        }
+        for port in &host.dynamic_forward {
+            out.push_str(&format!("    DynamicForward {}\n", port));
+        }
+        if host.compression == Some(true) {
+            out.push_str("    Compression yes\n");
+        }
+        if let Some(interval) = host.server_alive_interval.filter(|i| *i > 0) {
+            out.push_str(&format!("    ServerAliveInterval {}\n", interval));
+        }
+    }
+
+    out
+}
+
+/// A `Host` alias from a profile name: letters, digits and `-_.@+:` only,
+/// so no whitespace, pattern, quote or `=` characters. Runs of anything
+/// else become one `-`; may be empty.
+pub fn host_alias(name: &str) -> String {
+    let mut alias = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '@' | '+' | ':') {
+            alias.push(c);
+        } else if !alias.ends_with('-') {
+            alias.push('-');
+        }
+    }
+    alias.trim_matches('-').to_string()
+}
+
+fn quote_if_spaced(value: &str) -> String {
+    if value.contains(' ') {
+        format!("\"{}\"", value)
+    } else {
+        value.to_string()
+    }
+}
+
+/// Shorten paths under the home directory back to `~`
+fn collapse_tilde(path: &str) -> String {
+    if let Some(home) = dirs::home_dir() {
+        if let Some(rest) = path.strip_prefix(&*home.to_string_lossy()) {
+            if rest.starts_with('/') || rest.starts_with('\\') {
+                return format!("~{}", rest);
+            }
+        }
+    }
+    path.to_string()
+}
+
 /// Expand tilde in path
 fn expand_tilde(path: &str) -> String {
     if path.starts_with('~') {
@@ -298,6 +384,43 @@ Host example
         assert!(!wildcard_match("*.com","example.org"));
     }
 
+    #[test]
+    fn test_write_config_round_trip() {
+        let host = HostConfig {
+            host_pattern: host_alias(" Prod web 01 "),
+            hostname: Some("web01.example.com".to_string()),
+            port: Some(2222),
+            user: Some("deploy".to_string()),
+            identity_file: vec!["/keys/id_ed25519".to_string()],
+            proxy_jump: Some("admin@bastion:22".to_string()),
+            local_forward: vec![(5432, "db.internal".to_string(), 5432)],
+            ..Default::default()
+        };
+        let default_port = HostConfig {
+            host_pattern: "plain".to_string(),
+            hostname: Some("10.0.0.5".to_string()),
+            port: Some(22),
+            ..Default::default()
+        };
+
+        let text = write_config(&[host, default_port.clone(), default_port]);
+        assert!(text.contains("Host Prod-web-01\n"));
+        assert!(text.contains("Host plain\n"));
+        assert!(text.contains("Host plain-2\n"));
+        assert_eq!(host_alias("db = \"main\" #1"), "db-main-1");
+        assert_eq!(host_alias(" *? "), "");
+        assert!(!text.contains("Port 22\n"));
+
+        let mut parser = SshConfigParser::new();
+        parser.parse_content(&text).unwrap();
+        let parsed = parser.get_config("Prod-web-01").unwrap();
+        assert_eq!(parsed.port, Some(2222));
+        assert_eq!(parsed.identity_file, vec!["/keys/id_ed25519".to_string()]);
+        assert_eq!(parsed.proxy_jump.as_deref(), Some("admin@bastion:22"));
+        assert_eq!(parsed.local_forward, vec![(5432, "db.internal".to_string(), 5432)]);
+        assert_eq!(parser.get_config("plain").unwrap().port, None);
+    }
+
     #[test]
     fn test_parse_forwards() {
         assert_eq!(
@@ -2,12 +2,15 @@
 
 use anyhow::{anyhow, Result};
 use russh::client::{self, Handle};
-use russh_keys::key;
-use russh::{Channel, ChannelId, Disconnect};
+use russh_keys::{key, PublicKeyBase64};
+use russh::{Channel, ChannelId, ChannelMsg, Disconnect};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, oneshot, Mutex};
 
+use super::host_ca::verify_host_certificate;
 use super::{ConnectionConfig, Credentials};
+use crate::storage::database::TrustedCa;
+use crate::storage::Database;
 
 /// Host key information for verification
 #[derive(Debug, Clone)]
@@ -34,61 +37,176 @@ impl HostKeyInfo {
     }
 }
 
-/// Verify host key against known hosts in database
-pub async fn verify_host_key(
+/// The server offered a different key than the one on record; put to the
+/// user by [`verify_host_key`] as [`HostKeyQuestion::Changed`].
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Host key for {host}:{port} changed: expected {expected}, got {}", offered.fingerprint)]
+pub struct HostKeyMismatch {
+    pub host: String,
+    pub port: u16,
+    /// Fingerprint on record
+    pub expected: String,
+    /// Key the server presented
+    pub offered: HostKeyInfo,
+}
+
+/// A server key the user has to decide on before the handshake goes on
+#[derive(Debug, Clone)]
+pub enum HostKeyQuestion {
+    /// First connection to the host
+    Unknown(HostKeyInfo),
+    /// The key differs from the one on record
+    Changed(HostKeyMismatch),
+}
+
+impl HostKeyQuestion {
+    pub fn offered(&self) -> &HostKeyInfo {
+        match self {
+            Self::Unknown(info) => info,
+            Self::Changed(mismatch) => &mismatch.offered,
+        }
+    }
+}
+
+/// A host key question waiting for the user; dropping it rejects the key
+pub struct HostKeyPrompt {
+    pub question: HostKeyQuestion,
+    reply: oneshot::Sender<Option<bool>>,
+}
+
+impl HostKeyPrompt {
+    /// Trust the offered key; for a changed key `update_openssh` also
+    /// replaces it in `~/.ssh/known_hosts`
+    pub fn trust(self, update_openssh: bool) {
+        let _ = self.reply.send(Some(update_openssh));
+    }
+
+    pub fn reject(self) {
+        let _ = self.reply.send(None);
+    }
+}
+
+/// Where host key questions go; the UI holds the receiver
+pub type HostKeyPrompter = mpsc::UnboundedSender<HostKeyPrompt>;
+
+static HOST_KEY_PROMPTER: std::sync::Mutex<Option<HostKeyPrompter>> = std::sync::Mutex::new(None);
+
+/// Send the questions of every handshake from now on to `prompter`
+pub fn set_host_key_prompter(prompter: HostKeyPrompter) {
+    if let Ok(mut current) = HOST_KEY_PROMPTER.lock() {
+        *current = Some(prompter);
+    }
+}
+
+/// Whether the key the server presented is trusted, for both handlers.
+///
+/// A host that comes with a certificate must match it; one that doesn't is
+/// not let through on the plain key either. Without a certificate the key
+/// is checked against the known hosts.
+pub(super) async fn check_server_key(
     host: &str,
     port: u16,
     key: &key::PublicKey,
-    database: Option<&Database>,
+    certificate: Option<&[u8]>,
+    trusted_cas: &[TrustedCa],
 ) -> Result<bool> {
+    match certificate {
+        Some(certificate) => Ok(verify_host_certificate(host, port, key, certificate, trusted_cas)),
+        None => verify_host_key(host, port, key).await,
+    }
+}
+
+/// Check a server's key against the known hosts. A known key passes; an
+/// unknown or changed one is put to the user, and the handshake waits for
+/// the answer. Without anyone to ask, the key is rejected.
+pub async fn verify_host_key(host: &str, port: u16, key: &key::PublicKey) -> Result<bool> {
     let key_info = HostKeyInfo::from_public_key(host, port, key);
-    
-    // If no database, accept (for testing/initial connection)
-    let db = match database {
-        Some(d) => d,
-        None => {
-            log::warn!("No database available for host key verification");
-            return Ok(true);
-        }
-    };
-    
-    // Check if host is known
-    match db.get_known_host(&key_info.host, key_info.port)? {
-        Some(known_key) => {
-            // Host is known, verify fingerprint matches
-            if known_key.fingerprint == key_info.fingerprint {
+
+    // The database is not held across the wait for the user
+    let question = {
+        let db = Database::open()?;
+        match db.get_known_host(&key_info.host, key_info.port)? {
+            Some(known) if known.fingerprint == key_info.fingerprint => {
                 log::info!("Host key verified for {}:{}", host, port);
-                // Update last_seen timestamp
                 db.update_known_host_last_seen(&key_info.host, key_info.port)?;
-                Ok(true)
-            } else {
-                // MITM ATTACK DETECTED!
+                return Ok(true);
+            }
+            Some(known) => {
                 log::error!(
-                    "⚠️  HOST KEY MISMATCH for {}:{} - Possible MITM attack!",
-                    host, port
+                    "Host key mismatch for {}:{}: expected {}, got {}",
+                    host, port, known.fingerprint, key_info.fingerprint
                 );
-                log::error!("Expected: {}", known_key.fingerprint);
-                log::error!("Got:      {}", key_info.fingerprint);
-                Err(anyhow!(
-                    "Host key verification failed! Expected {}, got {}",
-                    known_key.fingerprint,
-                    key_info.fingerprint
-                ))
+                HostKeyQuestion::Changed(HostKeyMismatch {
+                    host: host.to_string(),
+                    port,
+                    expected: known.fingerprint,
+                    offered: key_info,
+                })
             }
+            None => {
+                log::info!("New host {}:{} with fingerprint {}", host, port, key_info.fingerprint);
+                HostKeyQuestion::Unknown(key_info)
+            }
+        }
+    };
+
+    let prompter = HOST_KEY_PROMPTER.lock().ok().and_then(|prompter| prompter.clone());
+    let Some(prompter) = prompter else {
+        log::warn!("Nowhere to ask about the key of {}:{}; rejecting it", host, port);
+        return Ok(false);
+    };
+    let (reply, answer) = oneshot::channel();
+    if prompter.send(HostKeyPrompt { question: question.clone(), reply }).is_err() {
+        return Ok(false);
+    }
+    let Some(update_openssh) = answer.await.ok().flatten() else {
+        log::warn!("Key of {}:{} rejected", host, port);
+        return Ok(false);
+    };
+
+    let db = Database::open()?;
+    match question {
+        HostKeyQuestion::Unknown(info) => {
+            db.add_known_host(&info.host, info.port, &info.key_type, &info.fingerprint, &info.key_data)?;
+        }
+        HostKeyQuestion::Changed(mismatch) => mismatch.accept(&db, update_openssh)?,
+    }
+    Ok(true)
+}
+
+/// Captured result of a remote command run over an exec channel
+#[derive(Debug, Clone)]
+pub struct ExecOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_status: Option<u32>,
+}
+
+impl ExecOutput {
+    /// Whether the command exited with status 0
+    pub fn success(&self) -> bool {
+        self.exit_status == Some(0)
+    }
+
+    /// Shell status 127 means the command itself was not found
+    pub fn command_not_found(&self) -> bool {
+        self.exit_status == Some(127)
+    }
+
+    /// Turn a non-zero exit into an error carrying the remote stderr
+    pub fn into_result(self, what: &str) -> Result<Self> {
+        if self.success() {
+            return Ok(self);
         }
-        None => {
-            // First time seeing this host - should prompt user
-            log::info!("New host {}:{} with fingerprint: {}", host, port, key_info.fingerprint);
-            // For now, auto-accept and store (in production, should show dialog)
-            db.add_known_host(
-                &key_info.host,
-                key_info.port,
-                &key_info.key_type,
-                &key_info.fingerprint,
-                &key_info.key_data,
-            )?;
-            log::info!("Added new host key to known_hosts");
-            Ok(true)
+        let status = self
+            .exit_status
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let detail = self.stderr.trim();
+        if detail.is_empty() {
+            Err(anyhow!("{} exited with status {}", what, status))
+        } else {
+            Err(anyhow!("{} exited with status {}: {}", what, status, detail))
         }
     }
 }
@@ -96,14 +214,20 @@ pub async fn verify_host_key(
 /// SSH client handler for russh callbacks
 pub struct SshClientHandler {
     host: String,
+    port: u16,
     server_public_key: Option<key::PublicKey>,
+    trusted_cas: Vec<TrustedCa>,
+    host_certificate: Option<Vec<u8>>,
 }
 
 impl SshClientHandler {
-    pub fn new(host: &str) -> Self {
+    pub fn new(config: &ConnectionConfig) -> Self {
         Self {
-            host: host.to_string(),
+            host: config.host.clone(),
+            port: config.port,
             server_public_key: None,
+            trusted_cas: config.trusted_cas.clone(),
+            host_certificate: config.host_certificate.clone(),
         }
     }
 }
@@ -113,16 +237,25 @@ impl client::Handler for SshClientHandler {
     type Error = anyhow::Error;
 
     async fn check_server_key(
-        &mut self,
+        mut self,
         server_public_key: &key::PublicKey,
-    ) -> Result<bool, Self::Error> {
+    ) -> Result<(Self, bool), Self::Error> {
         log::info!(
             "Server key for {}: {}",
             self.host,
             server_public_key.fingerprint()
         );
-        self.server_public_key = Some(server_public_key.clone());
-        Ok(true)
+        let trusted = check_server_key(
+            &self.host,
+            self.port,
+            server_public_key,
+            self.host_certificate.as_deref(),
+            &self.trusted_cas,
+        ).await?;
+        if trusted {
+            self.server_public_key = Some(server_public_key.clone());
+        }
+        Ok((self, trusted))
     }
 }
 
@@ -134,6 +267,28 @@ pub struct SshConnection {
 }
 
 impl SshConnection {
+    /// Knock if the profile asks for it, then open the TCP connection and
+    /// do the SSH handshake
+    async fn dial(config: &ConnectionConfig, ssh_config: client::Config) -> Result<Handle<SshClientHandler>> {
+        let mut target = config.clone();
+        target.knock(&config.knock).await?;
+
+        let addr = target.dial_address();
+        log::info!("Connecting to {}", addr);
+
+        let handler = SshClientHandler::new(config);
+        Ok(client::connect(Arc::new(ssh_config), &addr, handler).await?)
+    }
+
+    /// Reach the server and complete the SSH handshake without authenticating
+    pub async fn probe(config: &ConnectionConfig) -> Result<()> {
+        let handle = Self::dial(config, client::Config::default()).await?;
+        handle
+            .disconnect(Disconnect::ByApplication, "Connection test", "en")
+            .await?;
+        Ok(())
+    }
+
     /// Connect to an SSH server with password authentication
     pub async fn connect_password(
         config: ConnectionConfig,
@@ -144,11 +299,7 @@ impl SshConnection {
             ..Default::default()
         };
 
-        let addr = format!("{}:{}", config.host, config.port);
-        log::info!("Connecting to {}", addr);
-
-        let handler = SshClientHandler::new(&config.host);
-        let mut handle = client::connect(Arc::new(ssh_config), &addr, handler).await?;
+        let mut handle = Self::dial(&config, ssh_config).await?;
 
         log::info!("Connected, authenticating as {}", config.username);
 
@@ -180,11 +331,7 @@ impl SshConnection {
             ..Default::default()
         };
 
-        let addr = format!("{}:{}", config.host, config.port);
-        log::info!("Connecting to {}", addr);
-
-        let handler = SshClientHandler::new(&config.host);
-        let mut handle = client::connect(Arc::new(ssh_config), &addr, handler).await?;
+        let mut handle = Self::dial(&config, ssh_config).await?;
 
         log::info!("Connected, authenticating with key as {}", config.username);
 
@@ -212,6 +359,85 @@ impl SshConnection {
         })
     }
 
+    /// Connect trying `methods` in order; also returns the label of the
+    /// method that was accepted
+    pub async fn connect_methods(
+        config: ConnectionConfig,
+        methods: &[Credentials],
+    ) -> Result<(Self, String)> {
+        let ssh_config = client::Config {
+            inactivity_timeout: Some(std::time::Duration::from_secs(config.keepalive as u64)),
+            ..Default::default()
+        };
+
+        let mut handle = Self::dial(&config, ssh_config).await?;
+
+        let mut failures = Vec::new();
+        let index = super::auth::authenticate(&mut handle, &config, methods, |progress| {
+            if let super::AuthProgress::Failed(method, reason) = progress {
+                failures.push(format!("{}: {}", method, reason));
+            }
+        })
+        .await
+        .map_err(|e| anyhow!("{} ({})", e, failures.join("; ")))?;
+
+        Ok((
+            Self {
+                handle,
+                config,
+                channels: Arc::new(Mutex::new(Vec::new())),
+            },
+            methods[index].label(),
+        ))
+    }
+
+    /// Log in to a bastion: `methods` first, then keyboard-interactive with
+    /// each question put to the user through `prompter`. Bastions asking for
+    /// a second factor accept a key only partially and go on to the challenges.
+    pub async fn connect_bastion(
+        config: ConnectionConfig,
+        methods: &[Credentials],
+        bastion: &super::bastion::BastionId,
+        leaf_hosts: &[String],
+        prompter: &super::bastion::MfaPrompter,
+    ) -> Result<Self> {
+        let ssh_config = client::Config {
+            inactivity_timeout: Some(std::time::Duration::from_secs(config.keepalive as u64)),
+            ..Default::default()
+        };
+
+        let mut handle = Self::dial(&config, ssh_config).await?;
+
+        let methods: Vec<Credentials> = methods
+            .iter()
+            .filter(|m| !matches!(m, Credentials::KeyboardInteractive))
+            .cloned()
+            .collect();
+        let mut authenticated = !methods.is_empty()
+            && super::auth::authenticate(&mut handle, &config, &methods, |_| {}).await.is_ok();
+        if !authenticated {
+            authenticated = super::bastion::answer_challenges(&mut handle, bastion, leaf_hosts, prompter).await?;
+        }
+        if !authenticated {
+            return Err(anyhow!("{} did not accept the login", bastion));
+        }
+        log::info!("Logged in to bastion {}", bastion);
+
+        Ok(Self {
+            handle,
+            config,
+            channels: Arc::new(Mutex::new(Vec::new())),
+        })
+    }
+
+    /// Open a direct-tcpip channel to `host:port` from the server, for a
+    /// connection through this one as a jump host
+    pub async fn open_tunnel(&self, host: &str, port: u16) -> Result<Channel<client::Msg>> {
+        let channel = self.handle.channel_open_direct_tcpip(host, port as u32, "127.0.0.1", 0).await?;
+        self.channels.lock().await.push(channel.id());
+        Ok(channel)
+    }
+
     /// Open a shell channel
     pub async fn open_shell(&self) -> Result<Channel<client::Msg>> {
         let channel = self.handle.channel_open_session().await?;
@@ -222,6 +448,40 @@ impl SshConnection {
         Ok(channel)
     }
 
+    /// Open a channel with the SFTP subsystem started, ready for `SftpClient::connect`
+    pub async fn open_sftp(&self) -> Result<Channel<client::Msg>> {
+        let channel = self.handle.channel_open_session().await?;
+        channel.request_subsystem(true, "sftp").await?;
+        Ok(channel)
+    }
+
+    /// Run a command on an exec channel and collect its output
+    pub async fn exec(&self, command: &str) -> Result<ExecOutput> {
+        log::debug!("Exec on {}: {}", self.config.host, command);
+
+        let mut channel = self.handle.channel_open_session().await?;
+        channel.exec(true, command).await?;
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let mut exit_status = None;
+
+        while let Some(msg) = channel.wait().await {
+            match msg {
+                ChannelMsg::Data { data } => stdout.extend_from_slice(&data),
+                ChannelMsg::ExtendedData { data, ext: 1 } => stderr.extend_from_slice(&data),
+                ChannelMsg::ExitStatus { exit_status: status } => exit_status = Some(status),
+                _ => {}
+            }
+        }
+
+        Ok(ExecOutput {
+            stdout: String::from_utf8_lossy(&stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&stderr).into_owned(),
+            exit_status,
+        })
+    }
+
     /// Request a PTY on the channel
     pub async fn request_pty(
         channel: &Channel<client::Msg>,
@@ -281,50 +541,3 @@ impl SshConnection {
         &self.config
     }
 }
-
-/// Jump host support for ProxyJump
-pub async fn connect_through_jump_host(
-    jump_host: &str,
-    jump_port: u16,
-    jump_user: &str,
-    jump_creds: &Credentials,
-    target_host: &str,
-    target_port: u16,
-    target_user: &str,
-    target_creds: &Credentials,
-) -> Result<SshConnection> {
-    // Connect to jump host first
-    let jump_config = ConnectionConfig {
-        host: jump_host.to_string(),
-        port: jump_port,
-        username: jump_user.to_string(),
-        timeout: 30,
-        keepalive: 60,
-        compression: false,
-    };
-    
-    let jump_conn = match jump_creds {
-        Credentials::Password(pwd) => {
-            SshConnection::connect_password(jump_config, pwd).await?
-        }
-        Credentials::PublicKey(key_path, passphrase) => {
-            SshConnection::connect_key(jump_config, key_path, passphrase.as_deref()).await?
-        }
-        _ => return Err(anyhow!("Unsupportedcredentialtypeforjumphost")),
-    };
-    
-    // Open direct-tcpip channel through jump host to target
-    let mut channel = jump_conn.handle.channel_open_direct_tcpip(
-        target_host,
-        target_port as u32,
-        "127.0.0.1",
-        0,
-    ).await?;
-    
-    log::info!("Establishedtunnelthroughjumphostto{}:{}",target_host,target_port);
-    
-    // Now connect to target through the tunnel
-    // This would require wrapping the channel as a transport
-    // For now, return jump connection as placeholder
-    Ok(jump_conn)
-}
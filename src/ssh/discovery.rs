@@ -0,0 +1,236 @@
+//! LAN discovery of SSH hosts over multicast DNS
+//!
+//! Sends one `_ssh._tcp.local` PTR query from an ephemeral port (an mDNS
+//! "one-shot" query, RFC 6762 5.1), so responders answer by unicast and no
+//! system mDNS daemon has to be displaced from port 5353.
+
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+const MDNS_ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(224, 0, 0, 251)), 5353);
+const SERVICE: &str = "_ssh._tcp.local";
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_AAAA: u16 = 28;
+const TYPE_SRV: u16 = 33;
+
+/// An SSH server announced on the local network
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveredHost {
+    /// Service instance name, usually the machine's name
+    pub instance: String,
+    /// Target host name, e.g. `pi.local`
+    pub hostname: String,
+    pub port: u16,
+    pub addresses: Vec<IpAddr>,
+}
+
+impl DiscoveredHost {
+    /// Address to connect to: the first IPv4 address, else any, else the name
+    pub fn connect_host(&self) -> String {
+        self.addresses.iter()
+            .find(|a| a.is_ipv4())
+            .or_else(|| self.addresses.first())
+            .map(|a| a.to_string())
+            .unwrap_or_else(|| self.hostname.clone())
+    }
+}
+
+/// One resource record of interest
+#[derive(Debug, Clone, PartialEq)]
+enum Record {
+    Ptr { owner: String, target: String },
+    Srv { owner: String, port: u16, target: String },
+    Addr { owner: String, addr: IpAddr },
+}
+
+/// Browse for `listen` and return what answered
+pub async fn browse(listen: Duration) -> Result<Vec<DiscoveredHost>> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket.send_to(&build_query(), MDNS_ADDR).await?;
+
+    let mut records = Vec::new();
+    let mut buf = [0u8; 9000];
+    let deadline = tokio::time::Instant::now() + listen;
+    while let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        let (len, from) = received?;
+        match parse_response(&buf[..len]) {
+            Ok(mut parsed) => records.append(&mut parsed),
+            Err(e) => log::debug!("Ignoring mDNS packet from {}: {}", from, e),
+        }
+    }
+    Ok(collect_hosts(&records))
+}
+
+/// PTR query for the SSH service, asking for unicast replies
+fn build_query() -> Vec<u8> {
+    let mut packet = vec![0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+    for label in SERVICE.split('.') {
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+    packet.extend_from_slice(&TYPE_PTR.to_be_bytes());
+    packet.extend_from_slice(&0x8001u16.to_be_bytes()); // QU bit, class IN
+    packet
+}
+
+fn read_u16(packet: &[u8], pos: usize) -> Result<u16> {
+    let bytes = packet.get(pos..pos + 2).ok_or_else(|| anyhow!("Packet is truncated"))?;
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+/// Read a possibly compressed name at `pos`; returns it and the position after it
+fn read_name(packet: &[u8], mut pos: usize) -> Result<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    for _ in 0..64 {
+        let len = *packet.get(pos).ok_or_else(|| anyhow!("Packet is truncated"))? as usize;
+        if len == 0 {
+            return Ok((labels.join("."), end.unwrap_or(pos + 1)));
+        }
+        if len & 0xC0 == 0xC0 {
+            let pointer = (read_u16(packet, pos)? & 0x3FFF) as usize;
+            end.get_or_insert(pos + 2);
+            pos = pointer;
+            continue;
+        }
+        let label = packet.get(pos + 1..pos + 1 + len).ok_or_else(|| anyhow!("Packet is truncated"))?;
+        labels.push(String::from_utf8_lossy(label).into_owned());
+        pos += 1 + len;
+    }
+    Err(anyhow!("Name has too many labels or a pointer loop"))
+}
+
+/// Records from every section of a response
+fn parse_response(packet: &[u8]) -> Result<Vec<Record>> {
+    let questions = read_u16(packet, 4)?;
+    let count = read_u16(packet, 6)? as usize + read_u16(packet, 8)? as usize + read_u16(packet, 10)? as usize;
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = read_name(packet, pos)?.1 + 4;
+    }
+
+    let mut records = Vec::new();
+    for _ in 0..count {
+        let (owner, after) = read_name(packet, pos)?;
+        let rtype = read_u16(packet, after)?;
+        let rdlength = read_u16(packet, after + 8)? as usize;
+        let rdata = after + 10;
+        let data = packet.get(rdata..rdata + rdlength).ok_or_else(|| anyhow!("Packet is truncated"))?;
+        match rtype {
+            TYPE_PTR => records.push(Record::Ptr { owner, target: read_name(packet, rdata)?.0 }),
+            TYPE_SRV => records.push(Record::Srv {
+                owner,
+                port: read_u16(packet, rdata + 4)?,
+                target: read_name(packet, rdata + 6)?.0,
+            }),
+            TYPE_A if rdlength == 4 => records.push(Record::Addr {
+                owner,
+                addr: IpAddr::V4(Ipv4Addr::new(data[0], data[1], data[2], data[3])),
+            }),
+            TYPE_AAAA if rdlength == 16 => {
+                let octets: [u8; 16] = data.try_into()?;
+                records.push(Record::Addr { owner, addr: IpAddr::V6(Ipv6Addr::from(octets)) });
+            }
+            _ => {}
+        }
+        pos = rdata + rdlength;
+    }
+    Ok(records)
+}
+
+/// Join PTR, SRV and address records into hosts, by instance name
+fn collect_hosts(records: &[Record]) -> Vec<DiscoveredHost> {
+    let mut hosts = BTreeMap::new();
+    for record in records {
+        if let Record::Ptr { owner, target } = record {
+            if owner.eq_ignore_ascii_case(SERVICE) {
+                let instance = target.strip_suffix(&format!(".{}", SERVICE)).unwrap_or(target).to_string();
+                hosts.entry(target.to_lowercase()).or_insert(DiscoveredHost {
+                    instance,
+                    hostname: String::new(),
+                    port: 22,
+                    addresses: Vec::new(),
+                });
+            }
+        }
+    }
+    for record in records {
+        if let Record::Srv { owner, port, target } = record {
+            if let Some(host) = hosts.get_mut(&owner.to_lowercase()) {
+                host.hostname = target.clone();
+                host.port = *port;
+            }
+        }
+    }
+    for host in hosts.values_mut() {
+        for record in records {
+            if let Record::Addr { owner, addr } = record {
+                if owner.eq_ignore_ascii_case(&host.hostname) && !host.addresses.contains(addr) {
+                    host.addresses.push(*addr);
+                }
+            }
+        }
+    }
+    hosts.into_values().filter(|h| !h.hostname.is_empty()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(packet: &mut Vec<u8>, name: &str) {
+        for label in name.split('.') {
+            packet.push(label.len() as u8);
+            packet.extend_from_slice(label.as_bytes());
+        }
+        packet.push(0);
+    }
+
+    fn record(packet: &mut Vec<u8>, owner: &str, rtype: u16, rdata: &[u8]) {
+        name(packet, owner);
+        packet.extend_from_slice(&rtype.to_be_bytes());
+        packet.extend_from_slice(&[0x80, 0x01, 0, 0, 0x11, 0x94]);
+        packet.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        packet.extend_from_slice(rdata);
+    }
+
+    #[test]
+    fn test_parse_announcement() {
+        let mut packet = vec![0, 0, 0x84, 0, 0, 0, 0, 1, 0, 0, 0, 2];
+
+        // PTR whose target points back into the packet via compression
+        let instance_offset = packet.len() + SERVICE.len() + 2 + 10;
+        let mut ptr = vec![2];
+        ptr.extend_from_slice(b"pi");
+        ptr.extend_from_slice(&[0xC0, 12]);
+        record(&mut packet, SERVICE, TYPE_PTR, &ptr);
+        assert_eq!(read_name(&packet, instance_offset).unwrap().0, "pi._ssh._tcp.local");
+
+        let mut srv = vec![0, 0, 0, 0, 0x08, 0xAE];
+        name(&mut srv, "pi.local");
+        record(&mut packet, "pi._ssh._tcp.local", TYPE_SRV, &srv);
+        record(&mut packet, "pi.local", TYPE_A, &[192, 168, 1, 20]);
+
+        let hosts = collect_hosts(&parse_response(&packet).unwrap());
+        assert_eq!(hosts, vec![DiscoveredHost {
+            instance: "pi".to_string(),
+            hostname: "pi.local".to_string(),
+            port: 2222,
+            addresses: vec![IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20))],
+        }]);
+        assert_eq!(hosts[0].connect_host(), "192.168.1.20");
+    }
+
+    #[test]
+    fn test_rejects_pointer_loops() {
+        let packet = [0xC0, 0];
+        assert!(read_name(&packet, 0).is_err());
+    }
+}
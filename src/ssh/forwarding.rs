@@ -2,7 +2,7 @@
 
 use anyhow::{anyhow, Result};
 use russh::client::Handle;
-use std::net::SocketAddr;
+use std::net::{SocketAddr, TcpListener as StdTcpListener};
 use std::sync::Arc;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
@@ -26,6 +26,56 @@ pub struct PortForward {
     pub remote_host: String,
     pub remote_port: u16,
     pub active: bool,
+    /// Why the forward could not start, for the forwarding panel
+    pub error: Option<String>,
+}
+
+/// The listening side of a forward is taken, locally or on the server.
+///
+/// Returned (inside `anyhow::Error`) by the `start_*_forward` methods;
+/// downcast to offer `suggestion` instead.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{} port {port} is already in use", if *remote { "Server" } else { "Local" })]
+pub struct PortInUse {
+    pub port: u16,
+    /// On the server, for a remote forward
+    pub remote: bool,
+    /// A free local port nearby; never set for remote forwards
+    pub suggestion: Option<u16>,
+}
+
+/// Whether `port` can be bound on `addr` right now
+pub fn local_port_available(addr: &str, port: u16) -> bool {
+    StdTcpListener::bind((addr, port)).is_ok()
+}
+
+/// A free port close to `port` on `addr`, else any free one
+pub fn suggest_free_port(addr: &str, port: u16) -> Option<u16> {
+    (port.saturating_add(1)..=port.saturating_add(100))
+        .filter(|&candidate| candidate >= 1024)
+        .find(|&candidate| local_port_available(addr, candidate))
+        .or_else(|| {
+            StdTcpListener::bind((addr, 0))
+                .and_then(|listener| listener.local_addr())
+                .map(|bound| bound.port())
+                .ok()
+        })
+}
+
+/// Bind the listener of a local or dynamic forward, turning "address in
+/// use" into `PortInUse` with a suggestion
+async fn bind_listener(forward: &PortForward) -> Result<TcpListener> {
+    let listen_addr: SocketAddr = format!("{}:{}", forward.listen_addr, forward.listen_port).parse()?;
+    match TcpListener::bind(listen_addr).await {
+        Ok(listener) => Ok(listener),
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => Err(PortInUse {
+            port: forward.listen_port,
+            remote: false,
+            suggestion: suggest_free_port(&forward.listen_addr, forward.listen_port),
+        }
+        .into()),
+        Err(e) => Err(anyhow!("Could not listen on {}: {}", listen_addr, e)),
+    }
 }
 
 impl PortForward {
@@ -38,6 +88,7 @@ impl PortForward {
             remote_host,
             remote_port,
             active: false,
+            error: None,
         }
     }
 
@@ -50,6 +101,7 @@ impl PortForward {
             remote_host: local_host,
             remote_port: local_port,
             active: false,
+            error: None,
         }
     }
 
@@ -62,6 +114,7 @@ impl PortForward {
             remote_host: String::new(),
             remote_port: 0,
             active: false,
+            error: None,
         }
     }
 }
@@ -90,6 +143,38 @@ impl ForwardingManager {
         self.forwards.lock().await.clone()
     }
 
+    /// Record whether a forward is running, and why not
+    pub async fn set_status(&self, id: uuid::Uuid, active: bool, error: Option<String>) {
+        if let Some(forward) = self.forwards.lock().await.iter_mut().find(|f| f.id == id) {
+            forward.active = active;
+            forward.error = error;
+        }
+    }
+
+    /// Ask the server to listen for a remote forward. A refusal almost
+    /// always means the port is taken there, so it comes back as
+    /// `PortInUse`; accepted connections then arrive through the handler's
+    /// forwarded-tcpip channel callback.
+    pub async fn start_remote_forward<H>(
+        &self,
+        forward: &PortForward,
+        ssh_handle: &mut Handle<H>,
+    ) -> Result<()>
+    where
+        H: russh::client::Handler + Send + 'static,
+    {
+        let accepted = ssh_handle
+            .tcpip_forward(forward.listen_addr.clone(), forward.listen_port as u32)
+            .await?;
+        if !accepted {
+            log::warn!("Server refused remote forward on port {}", forward.listen_port);
+            return Err(PortInUse { port: forward.listen_port, remote: true, suggestion: None }.into());
+        }
+        log::info!("Remote forward: server :{} -> {}:{}",
+            forward.listen_port, forward.remote_host, forward.remote_port);
+        Ok(())
+    }
+
     pub async fn start_local_forward<H>(
         &self,
         forward: PortForward,
@@ -98,11 +183,10 @@ impl ForwardingManager {
     where
         H: russh::client::Handler + Send + 'static,
     {
-        let listen_addr: SocketAddr = format!("{}:{}",forward.listen_addr,forward.listen_port).parse()?;
-        let listener = TcpListener::bind(listen_addr).await?;
+        let listener = bind_listener(&forward).await?;
         
         log::info!("Localforward:{}->{}:{}",
-            listen_addr, forward.remote_host, forward.remote_port);
+            listener.local_addr()?, forward.remote_host, forward.remote_port);
 
         let remote_host = forward.remote_host.clone();
         let remote_port = forward.remote_port;
@@ -178,10 +262,9 @@ impl ForwardingManager {
     where
         H: russh::client::Handler + Send + 'static,
     {
-        let listen_addr: SocketAddr = format!("{}:{}",forward.listen_addr,forward.listen_port).parse()?;
-        let listener = TcpListener::bind(listen_addr).await?;
+        let listener = bind_listener(&forward).await?;
         
-        log::info!("Dynamicforward(SOCKS):{}",listen_addr);
+        log::info!("Dynamicforward(SOCKS):{}",listener.local_addr()?);
 
         tokio::spawn(async move {
             loop {
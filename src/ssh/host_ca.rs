@@ -0,0 +1,435 @@
+//! Host certificate authorities
+//!
+//! Fleets that sign their host keys only need the CA trusted once: any host
+//! whose certificate is signed by a trusted CA, names it as a principal and
+//! falls under the CA's host patterns is accepted without a per-host prompt,
+//! as with OpenSSH's `@cert-authority` lines.
+
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use russh_keys::key;
+use russh_keys::PublicKeyBase64;
+
+use crate::storage::database::TrustedCa;
+use crate::storage::Database;
+
+/// OpenSSH certificate type of host certificates
+const SSH_CERT_TYPE_HOST: u32 = 2;
+
+/// Parse an `@cert-authority <patterns> <key-type> <base64> [comment]` line,
+/// as found in known_hosts, into (patterns, key type, base64 key)
+pub fn parse_cert_authority_line(line: &str) -> Option<(Vec<String>, String, String)> {
+    let mut fields = line.trim().strip_prefix("@cert-authority")?.split_whitespace();
+    let patterns = fields.next()?.split(',').map(str::to_string).collect();
+    let key_type = fields.next()?.to_string();
+    let key = fields.next()?.to_string();
+    base64::engine::general_purpose::STANDARD.decode(&key).ok()?;
+    Some((patterns, key_type, key))
+}
+
+/// Decode a `<key-type>-cert-v01@openssh.com <base64> [comment]` line, as in
+/// a server's `ssh_host_*_key-cert.pub`, into the certificate blob
+pub fn parse_certificate_line(line: &str) -> Option<Vec<u8>> {
+    let mut fields = line.split_whitespace();
+    fields.next().filter(|kind| kind.ends_with("-cert-v01@openssh.com"))?;
+    base64::engine::general_purpose::STANDARD.decode(fields.next()?).ok()
+}
+
+/// Whether `host` matches a comma list of OpenSSH patterns (`*`, `?`, `!negation`)
+pub fn host_matches(patterns: &[String], host: &str) -> bool {
+    let host = host.to_lowercase();
+    let mut matched = false;
+    for pattern in patterns {
+        let pattern = pattern.to_lowercase();
+        if let Some(negated) = pattern.strip_prefix('!') {
+            if wildcard_match(negated, &host) {
+                return false;
+            }
+        } else if wildcard_match(&pattern, &host) {
+            matched = true;
+        }
+    }
+    matched
+}
+
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+/// Reader for the SSH wire encoding
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.data.get(self.pos..self.pos + 4).ok_or_else(|| anyhow!("Certificate is truncated"))?;
+        self.pos += 4;
+        Ok(u32::from_be_bytes(bytes.try_into()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        let bytes = self.data.get(self.pos..self.pos + 8).ok_or_else(|| anyhow!("Certificate is truncated"))?;
+        self.pos += 8;
+        Ok(u64::from_be_bytes(bytes.try_into()?))
+    }
+
+    fn string(&mut self) -> Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        let bytes = self.data.get(self.pos..self.pos + len).ok_or_else(|| anyhow!("Certificate is truncated"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+}
+
+fn put_string(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    out.extend_from_slice(bytes);
+}
+
+/// The public key fields of a key type, which sit between the nonce and serial
+fn public_key_field_count(cert_type: &str) -> Result<usize> {
+    Ok(match cert_type {
+        "ssh-ed25519-cert-v01@openssh.com" => 1,
+        "ssh-rsa-cert-v01@openssh.com" => 2,
+        t if t.starts_with("ecdsa-sha2-") => 2,
+        other => return Err(anyhow!("Unsupported certificate type {}", other)),
+    })
+}
+
+/// An OpenSSH host certificate
+#[derive(Debug, Clone)]
+pub struct HostCertificate {
+    pub cert_type: String,
+    pub serial: u64,
+    pub kind: u32,
+    pub key_id: String,
+    pub principals: Vec<String>,
+    pub valid_after: u64,
+    pub valid_before: u64,
+    /// Wire-encoded host key the certificate is for
+    pub public_key: Vec<u8>,
+    /// Wire-encoded public key of the signing CA
+    pub signature_key: Vec<u8>,
+    signature: Vec<u8>,
+    /// Length of the signed prefix of the blob
+    signed_len: usize,
+    blob: Vec<u8>,
+}
+
+impl HostCertificate {
+    /// Parse a certificate from its wire encoding
+    pub fn parse(blob: &[u8]) -> Result<Self> {
+        let mut r = Reader { data: blob, pos: 0 };
+        let cert_type = String::from_utf8(r.string()?.to_vec())?;
+        r.string()?; // nonce
+        let key_start = r.pos;
+        for _ in 0..public_key_field_count(&cert_type)? {
+            r.string()?;
+        }
+        let mut public_key = Vec::new();
+        put_string(&mut public_key, cert_type.trim_end_matches("-cert-v01@openssh.com").as_bytes());
+        public_key.extend_from_slice(&blob[key_start..r.pos]);
+        let serial = r.u64()?;
+        let kind = r.u32()?;
+        let key_id = String::from_utf8_lossy(r.string()?).into_owned();
+
+        let mut principals = Vec::new();
+        let mut list = Reader { data: r.string()?, pos: 0 };
+        while list.pos < list.data.len() {
+            principals.push(String::from_utf8_lossy(list.string()?).into_owned());
+        }
+
+        let valid_after = r.u64()?;
+        let valid_before = r.u64()?;
+        r.string()?; // critical options
+        r.string()?; // extensions
+        r.string()?; // reserved
+        let signature_key = r.string()?.to_vec();
+        let signed_len = r.pos;
+        let signature = r.string()?.to_vec();
+
+        Ok(Self {
+            cert_type,
+            serial,
+            kind,
+            key_id,
+            principals,
+            valid_after,
+            valid_before,
+            public_key,
+            signature_key,
+            signature,
+            signed_len,
+            blob: blob.to_vec(),
+        })
+    }
+
+    /// Check type, validity window and principals; not the signature
+    pub fn check_claims(&self, host: &str, now: u64) -> Result<()> {
+        if self.kind != SSH_CERT_TYPE_HOST {
+            return Err(anyhow!("Certificate is not a host certificate"));
+        }
+        if now < self.valid_after || now >= self.valid_before {
+            return Err(anyhow!("Host certificate is expired or not yet valid"));
+        }
+        // As in OpenSSH, a certificate without principals is valid for any host
+        if !self.principals.is_empty() && !self.principals.iter().any(|p| p.eq_ignore_ascii_case(host)) {
+            return Err(anyhow!("Host certificate does not name {}", host));
+        }
+        Ok(())
+    }
+
+    fn verify_signature(&self) -> Result<()> {
+        let mut r = Reader { data: &self.signature, pos: 0 };
+        let algorithm = String::from_utf8_lossy(r.string()?).into_owned();
+        let signature = r.string()?;
+        // Without OpenSSL russh-keys reads Ed25519 and P-256 keys only, so
+        // RSA CAs fail here
+        let ca = key::parse_public_key(&self.signature_key)
+            .map_err(|e| anyhow!("Unsupported CA key: {}", e))?;
+        if algorithm != ca.name() {
+            return Err(anyhow!("Host certificate signature uses {}, not the CA's {}", algorithm, ca.name()));
+        }
+        if !ca.verify_detached(&self.blob[..self.signed_len], signature) {
+            return Err(anyhow!("Host certificate signature is invalid"));
+        }
+        Ok(())
+    }
+
+    /// Accept the certificate for `host`, which presented the wire-encoded
+    /// key `presented`, if a trusted CA covering the host signed it; returns
+    /// the CA
+    pub fn verify<'a>(&self, host: &str, presented: &[u8], cas: &'a [TrustedCa], now: u64) -> Result<&'a TrustedCa> {
+        if self.public_key != presented {
+            return Err(anyhow!("Host certificate is for another key than the one {} presented", host));
+        }
+        self.check_claims(host, now)?;
+        let ca = cas.iter()
+            .filter(|ca| host_matches(&ca.host_patterns, host))
+            .find(|ca| {
+                base64::engine::general_purpose::STANDARD
+                    .decode(&ca.public_key)
+                    .map_or(false, |key| key == self.signature_key)
+            })
+            .ok_or_else(|| anyhow!("Host certificate is not signed by a trusted CA for {}", host))?;
+        self.verify_signature()?;
+        log::info!("Host {} accepted by CA '{}' (key id {}, serial {})", host, ca.name, self.key_id, self.serial);
+        Ok(ca)
+    }
+}
+
+/// Trust the CA from an `@cert-authority` line, named by its comment if any
+pub fn trust_cert_authority_line(db: &Database, line: &str) -> Result<()> {
+    let (patterns, key_type, key) = parse_cert_authority_line(line)
+        .ok_or_else(|| anyhow!("Expected '@cert-authority <hosts> <key-type> <key> [comment]'"))?;
+    let name = line.split_whitespace().skip(4).collect::<Vec<_>>().join(" ");
+    let name = if name.is_empty() { patterns.join(",") } else { name };
+    db.add_trusted_ca(&name, &key_type, &key, &patterns)
+}
+
+/// Whether `cert_blob` vouches for the key `host` presented, signed by one
+/// of the trusted `cas`.
+///
+/// A valid certificate from a covering CA is accepted without consulting or
+/// adding to the per-host known_hosts entries. russh 0.40 does not negotiate
+/// the `-cert-v01@openssh.com` host key algorithms, so the certificate comes
+/// with the connection settings rather than from the handshake.
+pub fn verify_host_certificate(
+    host: &str,
+    port: u16,
+    presented: &key::PublicKey,
+    cert_blob: &[u8],
+    cas: &[TrustedCa],
+) -> bool {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let verified = HostCertificate::parse(cert_blob)
+        .and_then(|cert| cert.verify(host, &presented.public_key_bytes(), cas, now).map(|_| ()));
+    match verified {
+        Ok(()) => true,
+        Err(e) => {
+            log::warn!("Host certificate for {}:{} rejected: {}", host, port, e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cert_blob(kind: u32, principals: &[&str], valid_before: u64) -> Vec<u8> {
+        let mut blob = Vec::new();
+        put_string(&mut blob, b"ssh-ed25519-cert-v01@openssh.com");
+        put_string(&mut blob, &[0; 32]);
+        put_string(&mut blob, &[1; 32]);
+        blob.extend_from_slice(&7u64.to_be_bytes());
+        blob.extend_from_slice(&kind.to_be_bytes());
+        put_string(&mut blob, b"web01-host");
+        let mut list = Vec::new();
+        for p in principals {
+            put_string(&mut list, p.as_bytes());
+        }
+        put_string(&mut blob, &list);
+        blob.extend_from_slice(&100u64.to_be_bytes());
+        blob.extend_from_slice(&valid_before.to_be_bytes());
+        put_string(&mut blob, b"");
+        put_string(&mut blob, b"");
+        put_string(&mut blob, b"");
+        put_string(&mut blob, b"ca-key");
+        put_string(&mut blob, b"signature");
+        blob
+    }
+
+    #[test]
+    fn test_parse_and_check_claims() {
+        let cert = HostCertificate::parse(&cert_blob(2, &["web01.example.com"], 1000)).unwrap();
+        assert_eq!(cert.serial, 7);
+        assert_eq!(cert.key_id, "web01-host");
+        assert_eq!(cert.signature_key, b"ca-key");
+        assert!(cert.check_claims("WEB01.example.com", 500).is_ok());
+        assert!(cert.check_claims("db01.example.com", 500).is_err());
+        assert!(cert.check_claims("web01.example.com", 1000).is_err());
+
+        let user_cert = HostCertificate::parse(&cert_blob(1, &[], 1000)).unwrap();
+        assert!(user_cert.check_claims("web01.example.com", 500).is_err());
+    }
+
+    /// An Ed25519 host certificate for `host_key`, signed by `ca`
+    fn signed_cert(ca: &key::KeyPair, host_key: &key::PublicKey, principals: &[&str]) -> Vec<u8> {
+        let host_key = host_key.public_key_bytes();
+        let mut fields = Reader { data: &host_key, pos: 0 };
+        fields.string().unwrap();
+
+        let mut blob = Vec::new();
+        put_string(&mut blob, b"ssh-ed25519-cert-v01@openssh.com");
+        put_string(&mut blob, &[7; 32]);
+        put_string(&mut blob, fields.string().unwrap());
+        blob.extend_from_slice(&1u64.to_be_bytes());
+        blob.extend_from_slice(&SSH_CERT_TYPE_HOST.to_be_bytes());
+        put_string(&mut blob, b"web01-host");
+        let mut list = Vec::new();
+        for p in principals {
+            put_string(&mut list, p.as_bytes());
+        }
+        put_string(&mut blob, &list);
+        blob.extend_from_slice(&0u64.to_be_bytes());
+        blob.extend_from_slice(&u64::MAX.to_be_bytes());
+        put_string(&mut blob, b"");
+        put_string(&mut blob, b"");
+        put_string(&mut blob, b"");
+        put_string(&mut blob, &ca.clone_public_key().unwrap().public_key_bytes());
+
+        let signature = ca.sign_detached(&blob).unwrap();
+        let mut signature_blob = Vec::new();
+        put_string(&mut signature_blob, b"ssh-ed25519");
+        put_string(&mut signature_blob, signature.as_ref());
+        put_string(&mut blob, &signature_blob);
+        blob
+    }
+
+    fn trusted(ca: &key::KeyPair, patterns: &[&str]) -> TrustedCa {
+        TrustedCa {
+            id: "ca".to_string(),
+            name: "fleet CA".to_string(),
+            key_type: "ssh-ed25519".to_string(),
+            public_key: base64::engine::general_purpose::STANDARD.encode(ca.clone_public_key().unwrap().public_key_bytes()),
+            host_patterns: patterns.iter().map(|p| p.to_string()).collect(),
+            added_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_signed_certificate() {
+        let ca = key::KeyPair::generate_ed25519().unwrap();
+        let host = key::KeyPair::generate_ed25519().unwrap().clone_public_key().unwrap();
+        let cert = signed_cert(&ca, &host, &["web01.example.com"]);
+        let cas = vec![trusted(&ca, &["*.example.com"])];
+
+        assert!(verify_host_certificate("web01.example.com", 22, &host, &cert, &cas));
+        // Not a principal, or not covered by the CA
+        assert!(!verify_host_certificate("db01.example.com", 22, &host, &cert, &cas));
+        assert!(!verify_host_certificate("web01.example.com", 22, &host, &cert, &[trusted(&ca, &["*.example.org"])]));
+
+        // Signed by a CA nobody trusts
+        let rogue = key::KeyPair::generate_ed25519().unwrap();
+        assert!(!verify_host_certificate("web01.example.com", 22, &host, &signed_cert(&rogue, &host, &[]), &cas));
+    }
+
+    #[test]
+    fn test_certificate_must_match_presented_key() {
+        let ca = key::KeyPair::generate_ed25519().unwrap();
+        let host = key::KeyPair::generate_ed25519().unwrap().clone_public_key().unwrap();
+        let other = key::KeyPair::generate_ed25519().unwrap().clone_public_key().unwrap();
+        let cas = vec![trusted(&ca, &["*"])];
+
+        // A certificate copied from another host does not vouch for this key
+        let cert = signed_cert(&ca, &other, &[]);
+        assert!(!verify_host_certificate("web01", 22, &host, &cert, &cas));
+        assert!(verify_host_certificate("web01", 22, &other, &cert, &cas));
+    }
+
+    #[test]
+    fn test_tampered_certificate() {
+        let ca = key::KeyPair::generate_ed25519().unwrap();
+        let host = key::KeyPair::generate_ed25519().unwrap().clone_public_key().unwrap();
+        let mut cert = signed_cert(&ca, &host, &["web01"]);
+        let at = cert.windows(5).rposition(|w| w == b"web01").unwrap();
+        cert[at + 4] = b'2';
+        assert!(!verify_host_certificate("web02", 22, &host, &cert, &[trusted(&ca, &["*"])]));
+    }
+
+    #[test]
+    fn test_host_patterns() {
+        let patterns = vec!["*.example.com".to_string(), "!bastion.example.com".to_string()];
+        assert!(host_matches(&patterns, "web01.example.com"));
+        assert!(!host_matches(&patterns, "bastion.example.com"));
+        assert!(!host_matches(&patterns, "example.org"));
+        assert!(host_matches(&["db?".to_string()], "db1"));
+    }
+
+    #[test]
+    fn test_parse_cert_authority_line() {
+        let (patterns, key_type, _) =
+            parse_cert_authority_line("@cert-authority *.example.com,10.0.* ssh-ed25519 AAAAC3NzaC1lZDI1NTE5 fleet CA").unwrap();
+        assert_eq!(patterns, vec!["*.example.com", "10.0.*"]);
+        assert_eq!(key_type, "ssh-ed25519");
+        assert!(parse_cert_authority_line("web01 ssh-ed25519 AAAA").is_none());
+    }
+
+    #[test]
+    fn test_parse_certificate_line() {
+        let blob = cert_blob(SSH_CERT_TYPE_HOST, &["web01"], u64::MAX);
+        let line = format!(
+            "ssh-ed25519-cert-v01@openssh.com {} root@web01",
+            base64::engine::general_purpose::STANDARD.encode(&blob)
+        );
+        assert_eq!(parse_certificate_line(&line), Some(blob));
+        assert!(parse_certificate_line("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5").is_none());
+        assert!(parse_certificate_line("ssh-ed25519-cert-v01@openssh.com not-base64!").is_none());
+    }
+}
@@ -0,0 +1,172 @@
+//! Watching an unreachable host until it is back
+//!
+//! A watch probes the SSH port every few seconds, knocking first if the
+//! profile does. A probe only succeeds once the server sends its
+//! identification line, so a host whose network is up before sshd does not
+//! count as back. Dropping the [`HostWatch`] stops the probes.
+
+use anyhow::{anyhow, Context, Result};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::watch;
+use super::knock::{self, Knock};
+use crate::utils::executor::{Executor, Task};
+
+/// Pause between two probes
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(5);
+/// A reboot taking longer than this needs someone to look at it
+pub const MAX_WATCH: Duration = Duration::from_secs(2 * 60 * 60);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(4);
+/// Servers may send a few lines before the identification
+const MAX_BANNER_LINES: usize = 16;
+
+/// The host to watch and what to do once it is back
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchTarget {
+    pub connection_id: String,
+    /// Profile name, for display
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    pub knock: Vec<Knock>,
+    /// Open the connection as soon as the host answers
+    pub auto_connect: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatchStatus {
+    /// Still down; why the last probe failed
+    Waiting { probes: u32, last_error: Option<String> },
+    /// The server answered with this identification line
+    Up { probes: u32, banner: String, after: Duration },
+    /// Stopped after [`MAX_WATCH`]
+    GaveUp { probes: u32 },
+}
+
+impl WatchStatus {
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, WatchStatus::Waiting { .. })
+    }
+}
+
+impl std::fmt::Display for WatchStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WatchStatus::Waiting { probes: 0, .. } => write!(f, "Probing..."),
+            WatchStatus::Waiting { probes, last_error } => {
+                write!(f, "Down after {} probe{}", probes, if *probes == 1 { "" } else { "s" })?;
+                match last_error {
+                    Some(error) => write!(f, ": {}", error),
+                    None => Ok(()),
+                }
+            }
+            WatchStatus::Up { after, .. } => write!(f, "Back after {}", crate::utils::helpers::format_idle(*after)),
+            WatchStatus::GaveUp { probes } => write!(f, "Gave up after {} probes", probes),
+        }
+    }
+}
+
+/// A running watch; the probes stop when it is dropped
+pub struct HostWatch {
+    pub target: WatchTarget,
+    pub started: Instant,
+    status: watch::Receiver<WatchStatus>,
+    /// Ends with the watch, which wakes the UI to announce it
+    _probes: Task<()>,
+}
+
+impl HostWatch {
+    pub fn start(executor: &Executor, target: WatchTarget) -> Self {
+        let (tx, rx) = watch::channel(WatchStatus::Waiting { probes: 0, last_error: None });
+        let started = Instant::now();
+        let host = target.host.clone();
+        let port = target.port;
+        let knocks = target.knock.clone();
+        let task = executor.spawn(async move {
+            let mut probes = 0;
+            loop {
+                // Probe the address knocked on, not whatever the name resolves to next
+                let mut target = host.clone();
+                if !knocks.is_empty() {
+                    if let Ok(addr) = knock::resolve(&host, port).await {
+                        let _ = knock::perform(addr.ip(), &knocks).await;
+                        target = addr.ip().to_string();
+                    }
+                }
+                let result = probe(&target, port).await;
+                probes += 1;
+                let status = match result {
+                    Ok(banner) => WatchStatus::Up { probes, banner, after: started.elapsed() },
+                    Err(_) if started.elapsed() >= MAX_WATCH => WatchStatus::GaveUp { probes },
+                    Err(e) => WatchStatus::Waiting { probes, last_error: Some(e.to_string()) },
+                };
+                let finished = status.is_finished();
+                if tx.send(status).is_err() || finished {
+                    return;
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(PROBE_INTERVAL) => {}
+                    _ = tx.closed() => return,
+                }
+            }
+        });
+        Self { target, started, status: rx, _probes: task }
+    }
+
+    pub fn status(&self) -> WatchStatus {
+        self.status.borrow().clone()
+    }
+
+    /// The status if it changed since the last call
+    pub fn poll(&mut self) -> Option<WatchStatus> {
+        match self.status.has_changed() {
+            Ok(true) => Some(self.status.borrow_and_update().clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Connect to the SSH port and read the server's identification line
+pub async fn probe(host: &str, port: u16) -> Result<String> {
+    tokio::time::timeout(PROBE_TIMEOUT, async {
+        let stream = TcpStream::connect((host, port))
+            .await
+            .with_context(|| format!("Could not connect to {}:{}", host, port))?;
+        let mut lines = BufReader::new(stream).lines();
+        for _ in 0..MAX_BANNER_LINES {
+            match lines.next_line().await? {
+                Some(line) if line.starts_with("SSH-") => return Ok(line.trim_end().to_string()),
+                Some(_) => continue,
+                None => break,
+            }
+        }
+        Err(anyhow!("{}:{} accepted the connection but sent no SSH banner", host, port))
+    })
+    .await
+    .map_err(|_| anyhow!("No answer from {}:{} within {} s", host, port, PROBE_TIMEOUT.as_secs()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_probe_waits_for_banner() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            // The first connection closes without a banner, like a port
+            // forwarded before sshd is up; the second is a real server
+            let (socket, _) = listener.accept().await.unwrap();
+            drop(socket);
+            let (mut socket, _) = listener.accept().await.unwrap();
+            socket.write_all(b"Booting\r\nSSH-2.0-OpenSSH_9.6\r\n").await.unwrap();
+        });
+
+        assert!(probe("127.0.0.1", port).await.is_err());
+        assert_eq!(probe("127.0.0.1", port).await.unwrap(), "SSH-2.0-OpenSSH_9.6");
+    }
+}
@@ -0,0 +1,137 @@
+//! Port knocking before the SSH dial
+//!
+//! Some hosts keep the SSH port closed until a sequence of connection
+//! attempts has hit the right ports in order, as knockd does. Each knock is
+//! one TCP connect attempt or one UDP datagram; no answer is expected.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+/// Pause after a knock when the sequence doesn't give one
+pub const DEFAULT_DELAY_MS: u64 = 100;
+/// A TCP knock only needs its SYN sent; a closed port may never answer
+const TCP_KNOCK_TIMEOUT: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KnockProtocol {
+    Tcp,
+    Udp,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Knock {
+    pub port: u16,
+    pub protocol: KnockProtocol,
+    /// Wait after this knock, before the next one or the SSH dial
+    pub delay_ms: u64,
+}
+
+impl std::fmt::Display for Knock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let protocol = match self.protocol {
+            KnockProtocol::Tcp => "tcp",
+            KnockProtocol::Udp => "udp",
+        };
+        write!(f, "{}/{}", self.port, protocol)?;
+        if self.delay_ms != DEFAULT_DELAY_MS {
+            write!(f, "@{}", self.delay_ms)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a sequence like `7000 8000/udp 9000@500`: ports separated by
+/// spaces or commas, TCP unless `/udp` is given, and an optional pause in
+/// milliseconds after the knock
+pub fn parse_sequence(spec: &str) -> Result<Vec<Knock>> {
+    spec.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let (knock, delay) = match part.split_once('@') {
+                Some((knock, delay)) => (knock, Some(delay)),
+                None => (part, None),
+            };
+            let (port, protocol) = match knock.split_once('/') {
+                Some((port, protocol)) => (port, protocol),
+                None => (knock, "tcp"),
+            };
+            let protocol = match protocol.to_ascii_lowercase().as_str() {
+                "tcp" => KnockProtocol::Tcp,
+                "udp" => KnockProtocol::Udp,
+                other => return Err(anyhow!("Unknown protocol '{}' in '{}'", other, part)),
+            };
+            let port = port.parse::<u16>().ok().filter(|port| *port != 0)
+                .ok_or_else(|| anyhow!("'{}' is not a port", port))?;
+            let delay_ms = match delay {
+                Some(delay) => delay.parse().map_err(|_| anyhow!("'{}' is not a delay in milliseconds", delay))?,
+                None => DEFAULT_DELAY_MS,
+            };
+            Ok(Knock { port, protocol, delay_ms })
+        })
+        .collect()
+}
+
+/// The sequence as `parse_sequence` reads it
+pub fn format_sequence(sequence: &[Knock]) -> String {
+    sequence.iter().map(Knock::to_string).collect::<Vec<_>>().join(" ")
+}
+
+/// Look `host` up once, so the knocks and the dial after them reach the
+/// same address even when the name has several
+pub async fn resolve(host: &str, port: u16) -> Result<SocketAddr> {
+    tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| anyhow!("Could not resolve {} to knock: {}", host, e))?
+        .next()
+        .ok_or_else(|| anyhow!("No address for {} to knock", host))
+}
+
+/// Knock on `ip` in order; returns a line per knock, which is also logged
+pub async fn perform(ip: IpAddr, sequence: &[Knock]) -> Result<Vec<String>> {
+    let mut lines = Vec::new();
+    for knock in sequence {
+        let target = SocketAddr::new(ip, knock.port);
+        match knock.protocol {
+            KnockProtocol::Tcp => {
+                // Refused or timed out both mean the SYN went out
+                let _ = tokio::time::timeout(TCP_KNOCK_TIMEOUT, tokio::net::TcpStream::connect(target)).await;
+            }
+            KnockProtocol::Udp => {
+                let local = if ip.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+                let socket = tokio::net::UdpSocket::bind(local).await?;
+                socket.send_to(&[0], target).await
+                    .map_err(|e| anyhow!("Knock on {} failed: {}", target, e))?;
+            }
+        }
+        let line = format!("Knocked {} on {}", knock, ip);
+        log::info!("{}", line);
+        lines.push(line);
+        tokio::time::sleep(Duration::from_millis(knock.delay_ms)).await;
+    }
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sequence() {
+        let sequence = parse_sequence("7000, 8000/UDP 9000@500").unwrap();
+        assert_eq!(sequence, vec![
+            Knock { port: 7000, protocol: KnockProtocol::Tcp, delay_ms: DEFAULT_DELAY_MS },
+            Knock { port: 8000, protocol: KnockProtocol::Udp, delay_ms: DEFAULT_DELAY_MS },
+            Knock { port: 9000, protocol: KnockProtocol::Tcp, delay_ms: 500 },
+        ]);
+        assert_eq!(format_sequence(&sequence), "7000/tcp 8000/udp 9000/tcp@500");
+        assert_eq!(parse_sequence(&format_sequence(&sequence)).unwrap(), sequence);
+
+        assert!(parse_sequence("").unwrap().is_empty());
+        assert!(parse_sequence("70000").is_err());
+        assert!(parse_sequence("7000/icmp").is_err());
+        assert!(parse_sequence("7000@soon").is_err());
+    }
+}
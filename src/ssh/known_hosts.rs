@@ -0,0 +1,224 @@
+//! Replacing a changed host key, in our database and in OpenSSH's known_hosts
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use super::connection::HostKeyMismatch;
+use crate::storage::Database;
+
+/// `~/.ssh/known_hosts`, if there is a home directory
+pub fn openssh_known_hosts_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".ssh").join("known_hosts"))
+}
+
+/// Host pattern as OpenSSH writes it: `host`, or `[host]:port` off port 22
+fn host_pattern(host: &str, port: u16) -> String {
+    if port == 22 {
+        host.to_string()
+    } else {
+        format!("[{}]:{}", host, port)
+    }
+}
+
+/// Whether a hashed name (`|1|salt|hash`, as `HashKnownHosts` writes it)
+/// stands for `pattern`: the hash is HMAC-SHA1 of the pattern keyed by the salt
+fn hashed_name_matches(name: &str, pattern: &str) -> bool {
+    let Some((salt, hash)) = name.strip_prefix("|1|").and_then(|rest| rest.split_once('|')) else {
+        return false;
+    };
+    let (Ok(salt), Ok(hash)) = (STANDARD.decode(salt), STANDARD.decode(hash)) else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha1>::new_from_slice(&salt) else {
+        return false;
+    };
+    mac.update(pattern.as_bytes());
+    mac.verify_slice(&hash).is_ok()
+}
+
+/// Drop `host` from every entry, plain or hashed, and append `new_line`.
+/// Returns the new content and how many entries were removed.
+pub fn rewrite_known_hosts(content: &str, host: &str, port: u16, new_line: &str) -> (String, usize) {
+    let pattern = host_pattern(host, port);
+    let mut removed = 0;
+    let mut out = String::with_capacity(content.len() + new_line.len() + 1);
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        let Some((hosts, rest)) = trimmed.split_once(char::is_whitespace) else {
+            out.push_str(line);
+            out.push('\n');
+            continue;
+        };
+        if trimmed.starts_with('#') || hosts.starts_with('@') {
+            out.push_str(line);
+            out.push('\n');
+            continue;
+        }
+
+        let names: Vec<&str> = hosts.split(',').collect();
+        let kept: Vec<&str> = names
+            .iter()
+            .copied()
+            .filter(|name| *name != pattern && !hashed_name_matches(name, &pattern))
+            .collect();
+        if kept.len() == names.len() {
+            out.push_str(line);
+            out.push('\n');
+        } else {
+            removed += 1;
+            if !kept.is_empty() {
+                out.push_str(&format!("{} {}\n", kept.join(","), rest));
+            }
+        }
+    }
+
+    out.push_str(new_line);
+    out.push('\n');
+    (out, removed)
+}
+
+fn update_openssh_file(path: &Path, mismatch: &HostKeyMismatch) -> Result<usize> {
+    // Only a missing file counts as empty; rewriting one we could not read
+    // would throw away every other host in it
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Could not read {}", path.display())),
+    };
+    let offered = &mismatch.offered;
+    let new_line = format!(
+        "{} {} {}",
+        host_pattern(&mismatch.host, mismatch.port),
+        offered.key_type,
+        String::from_utf8_lossy(&offered.key_data)
+    );
+    let (updated, removed) = rewrite_known_hosts(&content, &mismatch.host, mismatch.port, &new_line);
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // Write beside the file and rename, so a crash can't truncate it
+    let tmp = path.with_extension("tabssh-tmp");
+    let written = (|| {
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(updated.as_bytes())?;
+        if let Ok(metadata) = std::fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.sync_all()?;
+        std::fs::rename(&tmp, path)
+    })();
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e).with_context(|| format!("Could not write {}", path.display()));
+    }
+    Ok(removed)
+}
+
+impl HostKeyMismatch {
+    /// Trust the offered key from now on. The change is recorded in the
+    /// database; with `update_openssh`, `~/.ssh/known_hosts` is updated too.
+    pub fn accept(&self, db: &Database, update_openssh: bool) -> Result<()> {
+        let offered = &self.offered;
+        db.replace_known_host(&self.host, self.port, &offered.key_type, &offered.fingerprint, &offered.key_data)?;
+
+        if update_openssh {
+            if let Some(path) = openssh_known_hosts_path() {
+                let removed = update_openssh_file(&path, self)?;
+                log::info!("Updated {} ({} old entr{} removed)", path.display(), removed, if removed == 1 { "y" } else { "ies" });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ssh::HostKeyInfo;
+
+    #[test]
+    fn test_rewrite_replaces_plain_entries() {
+        let content = "\
+web01 ssh-ed25519 AAAAold
+[web01]:2222 ssh-ed25519 AAAAother
+db01,web01 ssh-rsa AAAArsa
+|1|salt=|hash= ssh-ed25519 AAAAhashed
+# web01 comment
+";
+        let (out, removed) = rewrite_known_hosts(content, "web01", 22, "web01 ssh-ed25519 AAAAnew");
+        assert_eq!(removed, 2);
+        assert_eq!(out, "\
+[web01]:2222 ssh-ed25519 AAAAother
+db01 ssh-rsa AAAArsa
+|1|salt=|hash= ssh-ed25519 AAAAhashed
+# web01 comment
+web01 ssh-ed25519 AAAAnew
+");
+    }
+
+    fn hashed(salt: &[u8], pattern: &str) -> String {
+        let mut mac = Hmac::<Sha1>::new_from_slice(salt).unwrap();
+        mac.update(pattern.as_bytes());
+        format!("|1|{}|{}", STANDARD.encode(salt), STANDARD.encode(mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn test_rewrite_replaces_hashed_entries() {
+        let stale = hashed(b"0123456789abcdefghij", "web01");
+        let other_port = hashed(b"0123456789abcdefghij", "[web01]:2222");
+        let content = format!("{} ssh-ed25519 AAAAold\n{} ssh-ed25519 AAAAother\n", stale, other_port);
+
+        let (out, removed) = rewrite_known_hosts(&content, "web01", 22, "web01 ssh-ed25519 AAAAnew");
+        assert_eq!(removed, 1);
+        assert_eq!(out, format!("{} ssh-ed25519 AAAAother\nweb01 ssh-ed25519 AAAAnew\n", other_port));
+    }
+
+    #[test]
+    fn test_rewrite_non_default_port() {
+        let (out, removed) = rewrite_known_hosts("[web01]:2222 ssh-ed25519 AAAAold\n", "web01", 2222, "[web01]:2222 ssh-ed25519 AAAAnew");
+        assert_eq!(removed, 1);
+        assert_eq!(out, "[web01]:2222 ssh-ed25519 AAAAnew\n");
+    }
+
+    fn mismatch() -> HostKeyMismatch {
+        HostKeyMismatch {
+            host: "web01".to_string(),
+            port: 22,
+            expected: "SHA256:old".to_string(),
+            offered: HostKeyInfo {
+                host: "web01".to_string(),
+                port: 22,
+                key_type: "ssh-ed25519".to_string(),
+                fingerprint: "SHA256:new".to_string(),
+                key_data: b"AAAAnew".to_vec(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_update_creates_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".ssh").join("known_hosts");
+        assert_eq!(update_openssh_file(&path, &mismatch()).unwrap(), 0);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "web01 ssh-ed25519 AAAAnew\n");
+    }
+
+    #[test]
+    fn test_update_keeps_unreadable_file() {
+        let dir = tempfile::tempdir().unwrap();
+        // A directory in the file's place can't be read as text
+        let path = dir.path().join("known_hosts");
+        std::fs::create_dir(&path).unwrap();
+        std::fs::write(path.join("keep"), "db01 ssh-ed25519 AAAA\n").unwrap();
+
+        assert!(update_openssh_file(&path, &mismatch()).is_err());
+        assert!(path.join("keep").exists());
+        assert!(!dir.path().join("known_hosts.tabssh-tmp").exists());
+    }
+}
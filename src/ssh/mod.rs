@@ -4,20 +4,34 @@
 
 mod active_session;
 mod auth;
+pub mod bastion;
 mod connection;
 mod config_parser;
+pub mod discovery;
 mod forwarding;
+pub mod host_ca;
+pub mod host_watch;
+pub mod knock;
+pub mod key_health;
+mod known_hosts;
+mod session_info;
 mod session_manager;
 
-pub use active_session::{ActiveSession, SessionEvent};
+pub use active_session::{ActiveSession, PtyOptions, SessionCommand, SessionEvent};
 #[allow(unused_imports)]
-pub use auth::{Credentials, find_default_keys};
+pub use auth::{set_challenge_prompter, AuthProgress, Credentials, check_key_file, find_default_keys, prefer_method};
 #[allow(unused_imports)]
-pub use connection::SshConnection;
+pub use connection::{set_host_key_prompter, SshConnection, ExecOutput, HostKeyInfo, HostKeyMismatch, HostKeyPrompt, HostKeyQuestion};
 pub use config_parser::{SshConfigParser, HostConfig};
-pub use forwarding::{ForwardingManager, PortForward, ForwardType};
+pub use forwarding::{local_port_available, suggest_free_port, ForwardingManager, PortForward, PortInUse, ForwardType};
+pub use known_hosts::openssh_known_hosts_path;
+pub use session_info::SessionInfo;
 pub use session_manager::SessionManager;
 
+use anyhow::Result;
+use std::net::SocketAddr;
+use crate::storage::database::TrustedCa;
+
 /// SSH authentication type
 #[derive(Debug, Clone, PartialEq)]
 pub enum AuthType {
@@ -36,6 +50,14 @@ pub struct ConnectionConfig {
     pub timeout: u32,
     pub keepalive: u32,
     pub compression: bool,
+    /// Port knocks sent before the dial
+    pub knock: Vec<knock::Knock>,
+    /// Address the knocks went to, dialed instead of looking `host` up again
+    pub resolved: Option<SocketAddr>,
+    /// CAs trusted to vouch for host keys
+    pub trusted_cas: Vec<TrustedCa>,
+    /// Certificate for the server's host key, checked against `trusted_cas`
+    pub host_certificate: Option<Vec<u8>>,
 }
 
 impl Default for ConnectionConfig {
@@ -48,6 +70,10 @@ impl Default for ConnectionConfig {
             timeout: 30,
             keepalive: 60,
             compression: false,
+            knock: Vec::new(),
+            resolved: None,
+            trusted_cas: Vec::new(),
+            host_certificate: None,
         }
     }
 }
@@ -85,4 +111,35 @@ impl ConnectionConfig {
         self.compression = compression;
         self
     }
+
+    pub fn with_knock(mut self, knock: Vec<knock::Knock>) -> Self {
+        self.knock = knock;
+        self
+    }
+
+    pub fn with_host_certificate(mut self, certificate: Vec<u8>, trusted_cas: Vec<TrustedCa>) -> Self {
+        self.host_certificate = Some(certificate);
+        self.trusted_cas = trusted_cas;
+        self
+    }
+
+    /// Send `sequence` to the host, and dial the address it was sent to.
+    /// Nothing is looked up without knocks.
+    pub async fn knock(&mut self, sequence: &[knock::Knock]) -> Result<Vec<String>> {
+        if sequence.is_empty() {
+            return Ok(Vec::new());
+        }
+        let addr = knock::resolve(&self.host, self.port).await?;
+        let lines = knock::perform(addr.ip(), sequence).await?;
+        self.resolved = Some(addr);
+        Ok(lines)
+    }
+
+    /// What to open the TCP connection to
+    pub fn dial_address(&self) -> String {
+        match self.resolved {
+            Some(addr) => addr.to_string(),
+            None => format!("{}:{}", self.host, self.port),
+        }
+    }
 }
@@ -0,0 +1,290 @@
+//! Negotiated connection details for the session info popover
+//!
+//! russh does not expose the algorithms it negotiated, so they are recovered
+//! the way the protocol defines them: the server's version string and
+//! KEXINIT arrive in the clear, and for each category the first client
+//! preference the server also supports wins (RFC 4253, 7.1). The server's
+//! side is copied off the session's own stream as it is read, and the
+//! client's side is the session's configuration.
+
+use anyhow::{anyhow, Result};
+use russh::Preferred;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// Most bytes kept from the start of a session, enough for any KEXINIT
+const HELLO_MAX: usize = 64 * 1024;
+const SSH_MSG_KEXINIT: u8 = 20;
+/// Ciphers that authenticate themselves, making the MAC unused
+const AEAD_CIPHERS: [&str; 3] = ["chacha20-poly1305@openssh.com", "aes256-gcm@openssh.com", "aes128-gcm@openssh.com"];
+
+/// Algorithm lists from the server's KEXINIT
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ServerAlgorithms {
+    pub kex: Vec<String>,
+    pub host_key: Vec<String>,
+    pub cipher_c2s: Vec<String>,
+    pub cipher_s2c: Vec<String>,
+    pub mac_c2s: Vec<String>,
+    pub mac_s2c: Vec<String>,
+    pub compression_c2s: Vec<String>,
+    pub compression_s2c: Vec<String>,
+}
+
+/// What a session runs with, for audits and interop debugging
+#[derive(Debug, Clone)]
+pub struct SessionInfo {
+    pub server_version: String,
+    pub kex: String,
+    pub host_key: String,
+    pub cipher: String,
+    pub mac: String,
+    pub compression: String,
+    pub connected_at: chrono::DateTime<chrono::Local>,
+}
+
+/// Parse a KEXINIT payload (message number included)
+pub fn parse_kexinit(payload: &[u8]) -> Result<ServerAlgorithms> {
+    if payload.first() != Some(&SSH_MSG_KEXINIT) || payload.len() < 17 {
+        return Err(anyhow!("Expected KEXINIT from server"));
+    }
+    let mut pos = 17; // message number and cookie
+    let mut lists = Vec::with_capacity(8);
+    for _ in 0..8 {
+        let len_bytes = payload.get(pos..pos + 4).ok_or_else(|| anyhow!("KEXINIT is truncated"))?;
+        let len = u32::from_be_bytes(len_bytes.try_into()?) as usize;
+        pos += 4;
+        let list = payload.get(pos..pos + len).ok_or_else(|| anyhow!("KEXINIT is truncated"))?;
+        pos += len;
+        lists.push(
+            String::from_utf8_lossy(list)
+                .split(',')
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect::<Vec<_>>(),
+        );
+    }
+    let mut lists = lists.into_iter();
+    let mut next = || lists.next().unwrap_or_default();
+    Ok(ServerAlgorithms {
+        kex: next(),
+        host_key: next(),
+        cipher_c2s: next(),
+        cipher_s2c: next(),
+        mac_c2s: next(),
+        mac_s2c: next(),
+        compression_c2s: next(),
+        compression_s2c: next(),
+    })
+}
+
+/// First client preference the server supports
+pub fn negotiate(client: &[&str], server: &[String]) -> Option<String> {
+    client.iter().find(|c| server.iter().any(|s| s == *c)).map(|c| c.to_string())
+}
+
+/// Copy of what the server sent first on a session's own stream: its
+/// version string and KEXINIT, which travel in the clear
+#[derive(Clone)]
+pub(super) struct ServerHello {
+    bytes: Arc<Mutex<Option<Vec<u8>>>>,
+    preferred: Arc<Preferred>,
+}
+
+impl ServerHello {
+    /// Wrap `stream` so the hello is kept; `preferred` is the session's own
+    /// client configuration
+    pub(super) fn tap<S>(stream: S, preferred: &Preferred) -> (HelloTap<S>, Self) {
+        let hello = Self {
+            bytes: Arc::new(Mutex::new(Some(Vec::new()))),
+            preferred: Arc::new(Preferred {
+                kex: preferred.kex,
+                key: preferred.key,
+                cipher: preferred.cipher,
+                mac: preferred.mac,
+                compression: preferred.compression,
+            }),
+        };
+        (HelloTap { inner: stream, hello: hello.clone(), done: false }, hello)
+    }
+
+    /// Negotiate the session's details; the kept bytes are released
+    pub(super) fn session_info(&self) -> Result<SessionInfo> {
+        let bytes = self
+            .bytes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+            .ok_or_else(|| anyhow!("Session details were already read"))?;
+        let (server_version, server) = parse_hello(&bytes)?;
+        Ok(negotiate_all(&self.preferred, server_version, &server))
+    }
+}
+
+/// Stream that copies the first bytes read into a `ServerHello`
+pub(super) struct HelloTap<S> {
+    inner: S,
+    hello: ServerHello,
+    /// Set once the copy is full or has been taken, to stop locking
+    done: bool,
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for HelloTap<S> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let before = buf.filled().len();
+        let result = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if !this.done {
+            let mut bytes = this.hello.bytes.lock().unwrap_or_else(|e| e.into_inner());
+            match bytes.as_mut() {
+                Some(bytes) => {
+                    let read = &buf.filled()[before..];
+                    let room = HELLO_MAX - bytes.len();
+                    bytes.extend_from_slice(&read[..read.len().min(room)]);
+                    this.done = bytes.len() >= HELLO_MAX;
+                }
+                None => this.done = true,
+            }
+        }
+        result
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for HelloTap<S> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+/// Split the server's version string and algorithm lists from the start of its stream
+pub fn parse_hello(bytes: &[u8]) -> Result<(String, ServerAlgorithms)> {
+    // Servers may send other lines before the version string
+    let mut rest = bytes;
+    let version = loop {
+        let end = rest.iter().position(|&b| b == b'\n').ok_or_else(|| anyhow!("No version string from server"))?;
+        let line = String::from_utf8_lossy(&rest[..end]).trim_end_matches('\r').to_string();
+        rest = &rest[end + 1..];
+        if line.starts_with("SSH-") {
+            break line;
+        }
+    };
+
+    let len_bytes = rest.get(..4).ok_or_else(|| anyhow!("KEXINIT is truncated"))?;
+    let packet_len = u32::from_be_bytes(len_bytes.try_into()?) as usize;
+    if !(5..=HELLO_MAX).contains(&packet_len) {
+        return Err(anyhow!("Unexpected packet length {}", packet_len));
+    }
+    let packet = rest.get(4..4 + packet_len).ok_or_else(|| anyhow!("KEXINIT is truncated"))?;
+    let padding = packet[0] as usize;
+    let payload = packet.get(1..packet_len.saturating_sub(padding)).ok_or_else(|| anyhow!("Bad padding"))?;
+    Ok((version, parse_kexinit(payload)?))
+}
+
+/// Negotiate each category the way the session did
+fn negotiate_all(preferred: &Preferred, server_version: String, server: &ServerAlgorithms) -> SessionInfo {
+    let kex: Vec<&str> = preferred.kex.iter().map(|n| n.as_ref()).collect();
+    let key: Vec<&str> = preferred.key.iter().map(|n| n.as_ref()).collect();
+    let cipher: Vec<&str> = preferred.cipher.iter().map(|n| n.as_ref()).collect();
+    let mac: Vec<&str> = preferred.mac.iter().map(|n| n.as_ref()).collect();
+    let compression = preferred.compression;
+
+    let pair = |c2s: Option<String>, s2c: Option<String>| {
+        let c2s = c2s.unwrap_or_else(|| "none in common".to_string());
+        let s2c = s2c.unwrap_or_else(|| "none in common".to_string());
+        if c2s == s2c { c2s } else { format!("{} / {}", c2s, s2c) }
+    };
+    let cipher_c2s = negotiate(&cipher, &server.cipher_c2s);
+    let mac_name = match &cipher_c2s {
+        Some(c) if AEAD_CIPHERS.contains(&c.as_str()) => "implicit (AEAD cipher)".to_string(),
+        _ => pair(negotiate(&mac, &server.mac_c2s), negotiate(&mac, &server.mac_s2c)),
+    };
+
+    SessionInfo {
+        server_version,
+        kex: negotiate(&kex, &server.kex).unwrap_or_else(|| "none in common".to_string()),
+        host_key: negotiate(&key, &server.host_key).unwrap_or_else(|| "none in common".to_string()),
+        cipher: pair(cipher_c2s, negotiate(&cipher, &server.cipher_s2c)),
+        mac: mac_name,
+        compression: pair(
+            negotiate(compression, &server.compression_c2s),
+            negotiate(compression, &server.compression_s2c),
+        ),
+        connected_at: chrono::Local::now(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kexinit(lists: [&str; 10]) -> Vec<u8> {
+        let mut payload = vec![SSH_MSG_KEXINIT];
+        payload.extend_from_slice(&[0; 16]);
+        for list in lists {
+            payload.extend_from_slice(&(list.len() as u32).to_be_bytes());
+            payload.extend_from_slice(list.as_bytes());
+        }
+        payload.extend_from_slice(&[0, 0, 0, 0, 0]);
+        payload
+    }
+
+    #[test]
+    fn test_parse_kexinit_and_negotiate() {
+        let payload = kexinit([
+            "curve25519-sha256,diffie-hellman-group14-sha256",
+            "ssh-ed25519,rsa-sha2-512",
+            "aes256-ctr,chacha20-poly1305@openssh.com",
+            "aes256-ctr",
+            "hmac-sha2-256",
+            "hmac-sha2-256",
+            "none,zlib@openssh.com",
+            "none",
+            "",
+            "",
+        ]);
+        let server = parse_kexinit(&payload).unwrap();
+        assert_eq!(server.host_key, vec!["ssh-ed25519", "rsa-sha2-512"]);
+        assert_eq!(server.compression_c2s, vec!["none", "zlib@openssh.com"]);
+
+        // The client's order decides, not the server's
+        assert_eq!(
+            negotiate(&["chacha20-poly1305@openssh.com", "aes256-ctr"], &server.cipher_c2s),
+            Some("chacha20-poly1305@openssh.com".to_string())
+        );
+        assert_eq!(negotiate(&["3des-cbc"], &server.cipher_s2c), None);
+    }
+
+    #[test]
+    fn test_rejects_other_messages() {
+        assert!(parse_kexinit(&[21]).is_err());
+        assert!(parse_kexinit(&kexinit(["", "", "", "", "", "", "", "", "", ""])[..30]).is_err());
+    }
+
+    #[test]
+    fn test_parse_hello() {
+        let payload = kexinit(["curve25519-sha256", "ssh-ed25519", "aes256-ctr", "aes256-ctr", "hmac-sha2-256", "hmac-sha2-256", "none", "none", "", ""]);
+        let padding = 4;
+        let mut hello = b"Welcome\r\nSSH-2.0-OpenSSH_9.6\r\n".to_vec();
+        hello.extend_from_slice(&((payload.len() + padding + 1) as u32).to_be_bytes());
+        hello.push(padding as u8);
+        hello.extend_from_slice(&payload);
+        hello.extend_from_slice(&[0; 4]);
+
+        let (version, server) = parse_hello(&hello).unwrap();
+        assert_eq!(version, "SSH-2.0-OpenSSH_9.6");
+        assert_eq!(server.kex, vec!["curve25519-sha256"]);
+        assert!(parse_hello(&hello[..hello.len() - 8]).is_err());
+        assert!(parse_hello(b"SSH-2.0-OpenSSH_9.6").is_err());
+    }
+}
@@ -1,13 +1,15 @@
 //! SSH session manager - handles multiple SSH connections
 
 use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
-use tokio::runtime::Runtime;
+use std::time::Duration;
 use tokio::sync::Mutex;
 use uuid::Uuid;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 
 use super::connection::SshConnection;
+use crate::utils::executor::Executor;
 use super::ConnectionConfig;
 
 /// Session state
@@ -40,22 +42,22 @@ impl Session {
 
 /// Manages multiple SSH sessions
 pub struct SessionManager {
-    runtime: Arc<Runtime>,
+    executor: Executor,
     sessions: Arc<Mutex<HashMap<Uuid, Session>>>,
 }
 
 impl SessionManager {
     /// Create a new session manager
-    pub fn new(runtime: Arc<Runtime>) -> Self {
+    pub fn new(executor: Executor) -> Self {
         Self {
-            runtime,
+            executor,
             sessions: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
-    /// Get the async runtime
-    pub fn runtime(&self) -> Arc<Runtime> {
-        self.runtime.clone()
+    /// Get the app executor
+    pub fn executor(&self) -> &Executor {
+        &self.executor
     }
 
     /// Connect with password authentication
@@ -70,7 +72,7 @@ impl SessionManager {
         let session_id = session.id;
         self.sessions.lock().await.insert(session_id, session);
 
-        match SshConnection::connect_password(config.clone(), password).await {
+        match with_timeout(&config, SshConnection::connect_password(config.clone(), password)).await {
             Ok(conn) => {
                 let mut sessions = self.sessions.lock().await;
                 if let Some(session) = sessions.get_mut(&session_id) {
@@ -102,7 +104,7 @@ impl SessionManager {
         let session_id = session.id;
         self.sessions.lock().await.insert(session_id, session);
 
-        match SshConnection::connect_key(config.clone(), key_path, passphrase).await {
+        match with_timeout(&config, SshConnection::connect_key(config.clone(), key_path, passphrase)).await {
             Ok(conn) => {
                 let mut sessions = self.sessions.lock().await;
                 if let Some(session) = sessions.get_mut(&session_id) {
@@ -121,6 +123,25 @@ impl SessionManager {
         }
     }
 
+    /// Drop sessions that have ended so they don't pile up; returns their ids.
+    ///
+    /// Safe to call every frame: skips the pass if the map is busy.
+    pub fn reap(&self) -> Vec<Uuid> {
+        let Ok(mut sessions) = self.sessions.try_lock() else {
+            return Vec::new();
+        };
+
+        let finished: Vec<Uuid> = sessions.values()
+            .filter(|s| matches!(s.state, SessionState::Disconnected | SessionState::Error(_)))
+            .map(|s| s.id)
+            .collect();
+        for id in &finished {
+            sessions.remove(id);
+            log::debug!("Reaped session {}", id);
+        }
+        finished
+    }
+
     /// Disconnect a session
     pub async fn disconnect(&self, session_id: Uuid) -> Result<()> {
         let mut sessions = self.sessions.lock().await;
@@ -157,3 +178,11 @@ impl SessionManager {
         sessions.len()
     }
 }
+
+/// Bound a connect attempt by the profile's timeout, so no session stays Connecting forever
+async fn with_timeout<T>(config: &ConnectionConfig, connect: impl Future<Output = Result<T>>) -> Result<T> {
+    let limit = Duration::from_secs(config.timeout.max(1) as u64);
+    tokio::time::timeout(limit, connect)
+        .await
+        .map_err(|_| anyhow!("Timed out connecting to {}:{}", config.host, config.port))?
+}
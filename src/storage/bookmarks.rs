@@ -0,0 +1,83 @@
+//! Remote path bookmarks
+//!
+//! Frequently used directories on a host (`/var/log`, `/etc/nginx`), kept
+//! per connection profile and offered next to the SFTP path bar.
+
+use anyhow::Result;
+use super::database::Database;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathBookmark {
+    pub connection_id: String,
+    pub path: String,
+}
+
+impl PathBookmark {
+    /// Bookmarks of one connection, by path
+    pub fn for_connection(db: &Database, connection_id: &str) -> Result<Vec<String>> {
+        let mut stmt = db.connection().prepare(
+            "SELECT path FROM path_bookmarks WHERE connection_id = ?1 ORDER BY path",
+        )?;
+        let paths = stmt
+            .query_map([connection_id], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(paths)
+    }
+
+    /// Every bookmark, for searching across hosts
+    pub fn load_all(db: &Database) -> Result<Vec<Self>> {
+        let mut stmt = db.connection().prepare(
+            "SELECT connection_id, path FROM path_bookmarks ORDER BY connection_id, path",
+        )?;
+        let bookmarks = stmt
+            .query_map([], |row| Ok(Self { connection_id: row.get(0)?, path: row.get(1)? }))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(bookmarks)
+    }
+
+    /// Bookmark `path`; adding it twice keeps one
+    pub fn add(db: &Database, connection_id: &str, path: &str) -> Result<()> {
+        db.connection().execute(
+            "INSERT OR IGNORE INTO path_bookmarks (connection_id, path, created_at) VALUES (?1, ?2, ?3)",
+            rusqlite::params![connection_id, normalize(path), chrono::Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    pub fn remove(db: &Database, connection_id: &str, path: &str) -> Result<()> {
+        db.connection().execute(
+            "DELETE FROM path_bookmarks WHERE connection_id = ?1 AND path = ?2",
+            rusqlite::params![connection_id, normalize(path)],
+        )?;
+        Ok(())
+    }
+
+    /// Drop the bookmarks of a deleted profile
+    pub fn forget_connection(db: &Database, connection_id: &str) -> Result<()> {
+        db.connection().execute("DELETE FROM path_bookmarks WHERE connection_id = ?1", [connection_id])?;
+        Ok(())
+    }
+}
+
+/// One spelling per directory: no trailing slash except for the root
+pub fn normalize(path: &str) -> String {
+    let trimmed = path.trim().trim_end_matches('/');
+    if trimmed.is_empty() && path.trim().starts_with('/') {
+        "/".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("/var/log/"), "/var/log");
+        assert_eq!(normalize(" /etc/nginx "), "/etc/nginx");
+        assert_eq!(normalize("///"), "/");
+        assert_eq!(normalize("~/projects/"), "~/projects");
+    }
+}
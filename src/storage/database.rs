@@ -4,6 +4,7 @@
 
 use anyhow::Result;
 use rusqlite::Connection;
+use crate::config::themes::Theme;
 use std::path::PathBuf;
 
 /// Database wrapper for SQLite
@@ -30,12 +31,20 @@ impl Database {
         Ok(db)
     }
 
+    /// A fresh database that lives in memory, for tests
+    #[cfg(test)]
+    pub(crate) fn open_in_memory() -> Result<Self> {
+        let db = Self { conn: Connection::open_in_memory()? };
+        db.initialize()?;
+        Ok(db)
+    }
+
     /// Get the database file path
     fn database_path() -> Result<PathBuf> {
-        let data_dir = dirs::data_dir()
+        let data_dir = crate::platform::PlatformManager::get_data_directory()
             .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
 
-        Ok(data_dir.join("tabssh").join("tabssh.db"))
+        Ok(data_dir.join("tabssh.db"))
     }
 
     /// Initialize database schema
@@ -99,9 +108,124 @@ impl Database {
                 key TEXT PRIMARY KEY,
                 value TEXT NOT NULL
             );
+
+            -- Finished file transfers
+            CREATE TABLE IF NOT EXISTS transfer_history (
+                id TEXT PRIMARY KEY,
+                connection_id TEXT,
+                host TEXT NOT NULL,
+                direction TEXT NOT NULL,
+                source TEXT NOT NULL,
+                destination TEXT NOT NULL,
+                size INTEGER NOT NULL DEFAULT 0,
+                duration_ms INTEGER NOT NULL DEFAULT 0,
+                success INTEGER NOT NULL,
+                error TEXT,
+                finished_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_transfer_history_finished
+                ON transfer_history(finished_at);
+
+            -- Finished shell sessions, for the local usage statistics
+            CREATE TABLE IF NOT EXISTS session_history (
+                id TEXT PRIMARY KEY,
+                connection_id TEXT,
+                host TEXT NOT NULL,
+                started_at TEXT NOT NULL,
+                duration_secs INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE INDEX IF NOT EXISTS idx_session_history_started
+                ON session_history(started_at);
+
+            -- Host keys replaced by the user after a mismatch
+            CREATE TABLE IF NOT EXISTS host_key_changes (
+                id TEXT PRIMARY KEY,
+                host TEXT NOT NULL,
+                port INTEGER NOT NULL,
+                old_fingerprint TEXT NOT NULL,
+                new_fingerprint TEXT NOT NULL,
+                changed_at TEXT NOT NULL
+            );
+
+            -- Connection folders; profiles reference them by group_name path
+            CREATE TABLE IF NOT EXISTS folders (
+                path TEXT PRIMARY KEY,
+                defaults TEXT NOT NULL DEFAULT '{}'
+            );
+
+            -- Remote directories bookmarked per connection profile
+            CREATE TABLE IF NOT EXISTS path_bookmarks (
+                connection_id TEXT NOT NULL,
+                path TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                PRIMARY KEY (connection_id, path)
+            );
+
+            -- Profiles opened together in one layout
+            CREATE TABLE IF NOT EXISTS session_groups (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                profile_ids TEXT NOT NULL DEFAULT '[]',
+                layout TEXT NOT NULL DEFAULT 'grid',
+                broadcast INTEGER NOT NULL DEFAULT 0
+            );
+
+            -- Host certificate authorities (@cert-authority)
+            CREATE TABLE IF NOT EXISTS trusted_host_cas (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                key_type TEXT NOT NULL,
+                public_key TEXT NOT NULL UNIQUE,
+                host_patterns TEXT NOT NULL,
+                added_at TEXT NOT NULL
+            );
             "#,
         )?;
 
+        // Profile fields added after the first release
+        for (column, decl) in [
+            ("key_path", "TEXT"),
+            ("is_favorite", "INTEGER NOT NULL DEFAULT 0"),
+            ("terminal_type", "TEXT"),
+            ("initial_command", "TEXT"),
+            ("encoding", "TEXT NOT NULL DEFAULT 'UTF-8'"),
+            ("tcp_keepalive", "INTEGER NOT NULL DEFAULT 1"),
+            ("x11_forwarding", "INTEGER NOT NULL DEFAULT 0"),
+            ("agent_forwarding", "INTEGER NOT NULL DEFAULT 0"),
+            ("local_forwards", "TEXT NOT NULL DEFAULT '[]'"),
+            ("remote_forwards", "TEXT NOT NULL DEFAULT '[]'"),
+            ("jump_host", "TEXT"),
+            ("notes", "TEXT NOT NULL DEFAULT ''"),
+            ("tags", "TEXT NOT NULL DEFAULT '[]'"),
+            ("kubernetes", "TEXT"),
+            ("auto_sftp", "TEXT NOT NULL DEFAULT 'off'"),
+            ("critical", "INTEGER NOT NULL DEFAULT 0"),
+            ("confirm_commands", "INTEGER NOT NULL DEFAULT 0"),
+            ("identity_files", "TEXT NOT NULL DEFAULT '[]'"),
+            ("auth_order", "TEXT NOT NULL DEFAULT '[]'"),
+            ("last_auth", "TEXT"),
+            ("service_actions", "TEXT NOT NULL DEFAULT '[]'"),
+            ("knock_sequence", "TEXT NOT NULL DEFAULT '[]'"),
+            ("scrollback_lines", "INTEGER"),
+            ("log_retention", "TEXT"),
+            ("host_certificate", "TEXT"),
+        ] {
+            self.add_column_if_missing("connections", column, decl)?;
+        }
+
+        Ok(())
+    }
+
+    /// Add a column to an existing table, for schema upgrades
+    fn add_column_if_missing(&self, table: &str, column: &str, decl: &str) -> Result<()> {
+        let mut stmt = self.conn.prepare(&format!("PRAGMA table_info({})", table))?;
+        let exists = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .filter_map(|name| name.ok())
+            .any(|name| name == column);
+        if !exists {
+            self.conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl))?;
+        }
         Ok(())
     }
 
@@ -224,4 +348,392 @@ impl Database {
 
         Ok(hosts)
     }
+
+    /// Every host name in saved connections and known_hosts
+    pub fn all_host_names(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("SELECT host FROM connections UNION SELECT host FROM known_hosts")?;
+        let hosts = stmt.query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(hosts)
+    }
+
+    /// Replace a known host's key after the user confirmed a legitimate change,
+    /// recording the old and new fingerprints
+    pub fn replace_known_host(
+        &self,
+        host: &str,
+        port: u16,
+        key_type: &str,
+        fingerprint: &str,
+        public_key: &[u8],
+    ) -> Result<()> {
+        let old = self.get_known_host(host, port)?;
+        let now = chrono::Local::now().to_rfc3339();
+
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "DELETE FROM known_hosts WHERE host = ?1 AND port = ?2",
+            rusqlite::params![host, port as i64],
+        )?;
+        tx.execute(
+            "INSERT INTO known_hosts (id, host, port, key_type, fingerprint, public_key, first_seen, last_seen)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)",
+            rusqlite::params![uuid::Uuid::new_v4().to_string(), host, port as i64, key_type, fingerprint, public_key, &now],
+        )?;
+        tx.execute(
+            "INSERT INTO host_key_changes (id, host, port, old_fingerprint, new_fingerprint, changed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                uuid::Uuid::new_v4().to_string(),
+                host,
+                port as i64,
+                old.map(|k| k.fingerprint).unwrap_or_default(),
+                fingerprint,
+                &now,
+            ],
+        )?;
+        tx.commit()?;
+
+        log::warn!("Replaced host key for {}:{} ({})", host, port, fingerprint);
+        Ok(())
+    }
+
+    /// Key replacements for a host, newest first
+    pub fn list_host_key_changes(&self, host: &str, port: u16) -> Result<Vec<HostKeyChange>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT old_fingerprint, new_fingerprint, changed_at FROM host_key_changes
+             WHERE host = ?1 AND port = ?2 ORDER BY changed_at DESC"
+        )?;
+        let changes = stmt.query_map(rusqlite::params![host, port as i64], |row| {
+            Ok(HostKeyChange {
+                old_fingerprint: row.get(0)?,
+                new_fingerprint: row.get(1)?,
+                changed_at: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+        Ok(changes)
+    }
+}
+
+/// A host CA whose certificates are trusted for matching hosts
+#[derive(Debug, Clone)]
+pub struct TrustedCa {
+    pub id: String,
+    pub name: String,
+    pub key_type: String,
+    /// Base64 wire encoding of the CA public key
+    pub public_key: String,
+    /// OpenSSH host patterns the CA may vouch for
+    pub host_patterns: Vec<String>,
+    pub added_at: String,
+}
+
+impl Database {
+    /// Trust a host CA for hosts matching `host_patterns`
+    pub fn add_trusted_ca(
+        &self,
+        name: &str,
+        key_type: &str,
+        public_key: &str,
+        host_patterns: &[String],
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO trusted_host_cas (id, name, key_type, public_key, host_patterns, added_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                uuid::Uuid::new_v4().to_string(),
+                name,
+                key_type,
+                public_key,
+                host_patterns.join(","),
+                chrono::Local::now().to_rfc3339(),
+            ],
+        )?;
+
+        log::info!("Trusted host CA '{}' for {}", name, host_patterns.join(","));
+        Ok(())
+    }
+
+    /// All trusted host CAs
+    pub fn list_trusted_cas(&self) -> Result<Vec<TrustedCa>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, key_type, public_key, host_patterns, added_at
+             FROM trusted_host_cas ORDER BY name"
+        )?;
+        let cas = stmt.query_map([], |row| {
+            Ok(TrustedCa {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                key_type: row.get(2)?,
+                public_key: row.get(3)?,
+                host_patterns: row.get::<_, String>(4)?.split(',').map(str::to_string).collect(),
+                added_at: row.get(5)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+        Ok(cas)
+    }
+
+    /// Stop trusting a host CA
+    pub fn remove_trusted_ca(&self, id: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM trusted_host_cas WHERE id = ?1", [id])?;
+        log::info!("Removed trusted host CA {}", id);
+        Ok(())
+    }
+}
+
+/// A recorded host key replacement
+#[derive(Debug, Clone)]
+pub struct HostKeyChange {
+    pub old_fingerprint: String,
+    pub new_fingerprint: String,
+    pub changed_at: String,
+}
+
+/// Theme made in the theme editor, as stored in the themes table
+#[derive(Debug, Clone)]
+pub struct CustomTheme {
+    pub id: String,
+    pub theme: Theme,
+}
+
+/// Finished transfer, as recorded in the history
+#[derive(Debug, Clone)]
+pub struct TransferRecord {
+    pub id: String,
+    pub connection_id: Option<String>,
+    pub host: String,
+    /// "upload" or "download"
+    pub direction: String,
+    pub source: String,
+    pub destination: String,
+    pub size: u64,
+    pub duration_ms: u64,
+    pub success: bool,
+    pub error: Option<String>,
+    /// RFC 3339, in UTC
+    pub finished_at: String,
+}
+
+impl TransferRecord {
+    /// Average speed in bytes per second
+    pub fn bytes_per_second(&self) -> u64 {
+        if self.duration_ms == 0 {
+            return self.size;
+        }
+        self.size * 1000 / self.duration_ms
+    }
+}
+
+/// Finished shell session, as recorded for the usage statistics
+#[derive(Debug, Clone)]
+pub struct SessionRecord {
+    pub id: String,
+    pub connection_id: Option<String>,
+    pub host: String,
+    pub started_at: String,
+    pub duration_secs: u64,
+}
+
+/// Filter for [`Database::list_transfer_history`]
+#[derive(Debug, Clone, Default)]
+pub struct TransferHistoryFilter {
+    /// Substring match on host
+    pub host: Option<String>,
+    /// Only transfers finished at or after this RFC 3339 timestamp
+    pub since: Option<String>,
+    /// Only transfers finished before this RFC 3339 timestamp
+    pub until: Option<String>,
+    pub limit: Option<usize>,
+}
+
+impl Database {
+    // ========== Transfer History Methods ==========
+
+    /// Record a finished transfer
+    pub fn add_transfer_record(&self, record: &TransferRecord) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO transfer_history
+             (id, connection_id, host, direction, source, destination, size, duration_ms, success, error, finished_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            rusqlite::params![
+                record.id,
+                record.connection_id,
+                record.host,
+                record.direction,
+                record.source,
+                record.destination,
+                record.size as i64,
+                record.duration_ms as i64,
+                record.success,
+                record.error,
+                record.finished_at,
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// List recorded transfers, newest first
+    pub fn list_transfer_history(&self, filter: &TransferHistoryFilter) -> Result<Vec<TransferRecord>> {
+        let host = filter.host.as_ref().map(|h| format!("%{}%", h));
+        let limit = filter.limit.map(|l| l as i64).unwrap_or(-1);
+
+        let mut stmt = self.conn.prepare(
+            "SELECT id, connection_id, host, direction, source, destination, size, duration_ms, success, error, finished_at
+             FROM transfer_history
+             WHERE (?1 IS NULL OR host LIKE ?1)
+               AND (?2 IS NULL OR julianday(finished_at) >= julianday(?2))
+               AND (?3 IS NULL OR julianday(finished_at) < julianday(?3))
+             ORDER BY julianday(finished_at) DESC
+             LIMIT ?4"
+        )?;
+
+        let records = stmt.query_map(
+            rusqlite::params![host, filter.since, filter.until, limit],
+            |row| {
+                Ok(TransferRecord {
+                    id: row.get(0)?,
+                    connection_id: row.get(1)?,
+                    host: row.get(2)?,
+                    direction: row.get(3)?,
+                    source: row.get(4)?,
+                    destination: row.get(5)?,
+                    size: row.get::<_, i64>(6)? as u64,
+                    duration_ms: row.get::<_, i64>(7)? as u64,
+                    success: row.get(8)?,
+                    error: row.get(9)?,
+                    finished_at: row.get(10)?,
+                })
+            },
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(records)
+    }
+
+    /// Remove one history entry
+    pub fn delete_transfer_record(&self, id: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM transfer_history WHERE id = ?1", rusqlite::params![id])?;
+        Ok(())
+    }
+
+    /// Remove all history entries
+    pub fn clear_transfer_history(&self) -> Result<()> {
+        self.conn.execute("DELETE FROM transfer_history", [])?;
+        log::info!("Cleared transfer history");
+        Ok(())
+    }
+
+    // ========== Session History Methods ==========
+
+    /// Record a finished shell session
+    pub fn add_session_record(&self, record: &SessionRecord) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO session_history (id, connection_id, host, started_at, duration_secs)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![
+                record.id,
+                record.connection_id,
+                record.host,
+                record.started_at,
+                record.duration_secs as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Remove all recorded sessions
+    pub fn clear_session_history(&self) -> Result<()> {
+        self.conn.execute("DELETE FROM session_history", [])?;
+        log::info!("Cleared session history");
+        Ok(())
+    }
+
+    // ========== Custom Theme Methods ==========
+
+    /// Insert or update a custom theme; `colors` holds the theme as JSON
+    pub fn save_custom_theme(&self, id: &str, theme: &Theme) -> Result<()> {
+        // Sync uses created_at as the modification time
+        self.conn.execute(
+            "INSERT OR REPLACE INTO themes (id, name, is_builtin, colors, created_at)
+             VALUES (?1, ?2, 0, ?3, ?4)",
+            rusqlite::params![id, theme.name, serde_json::to_string(theme)?, chrono::Local::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Custom themes by name; rows that no longer parse are skipped
+    pub fn list_custom_themes(&self) -> Result<Vec<CustomTheme>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, colors FROM themes WHERE is_builtin = 0 ORDER BY name"
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+        })?;
+
+        let mut themes = Vec::new();
+        for row in rows {
+            let (id, name, colors) = row?;
+            match serde_json::from_str::<Theme>(&colors) {
+                Ok(mut theme) => {
+                    theme.name = name;
+                    themes.push(CustomTheme { id, theme });
+                }
+                Err(e) => log::warn!("Skipping unreadable theme '{}': {}", name, e),
+            }
+        }
+        Ok(themes)
+    }
+
+    pub fn delete_custom_theme(&self, id: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM themes WHERE id = ?1 AND is_builtin = 0", rusqlite::params![id])?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_history() {
+        let db = Database::open_in_memory().unwrap();
+        let record = |id: &str, host: &str| TransferRecord {
+            id: id.to_string(),
+            connection_id: None,
+            host: host.to_string(),
+            direction: "upload".to_string(),
+            source: "/tmp/report.pdf".to_string(),
+            destination: "/srv/report.pdf".to_string(),
+            size: 4096,
+            duration_ms: 2000,
+            success: true,
+            error: None,
+            finished_at: chrono::Utc::now().to_rfc3339(),
+        };
+        db.add_transfer_record(&record("a", "history-test.example.com")).unwrap();
+        db.add_transfer_record(&record("b", "other.example.com")).unwrap();
+
+        let filter = TransferHistoryFilter {
+            host: Some("history-test".to_string()),
+            ..Default::default()
+        };
+        let records = db.list_transfer_history(&filter).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].id, "a");
+        assert_eq!(records[0].bytes_per_second(), 2048);
+
+        // Stored in UTC, filtered by instant whatever the caller's offset
+        let an_hour_ago = chrono::Utc::now() - chrono::Duration::hours(1);
+        let filter = TransferHistoryFilter {
+            since: Some(an_hour_ago.with_timezone(&chrono::FixedOffset::east_opt(5 * 3600).unwrap()).to_rfc3339()),
+            ..Default::default()
+        };
+        assert_eq!(db.list_transfer_history(&filter).unwrap().len(), 2);
+
+        db.delete_transfer_record("a").unwrap();
+        assert!(db.list_transfer_history(&filter).unwrap().is_empty());
+    }
 }
@@ -0,0 +1,220 @@
+//! Connection folders - nested groups with inherited defaults
+//!
+//! A profile's group is a `/`-separated folder path ("Prod/EU/Web"). Folders
+//! are stored separately so they can exist while empty and carry defaults
+//! that every profile below them inherits; the nearest folder wins. Besides
+//! terminal settings, a folder can carry the user, port, key and bastion,
+//! so e.g. a "Prod-EU" folder defines its jump host once.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use super::database::Database;
+
+/// Settings a folder hands down to its profiles; `None` inherits from above
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FolderDefaults {
+    pub username: Option<String>,
+    pub port: Option<u16>,
+    /// Private key for profiles using public key auth
+    pub key_path: Option<String>,
+    pub jump_host: Option<FolderJumpHost>,
+    pub terminal_type: Option<String>,
+    pub encoding: Option<String>,
+    pub keepalive_interval: Option<u16>,
+    pub initial_command: Option<String>,
+}
+
+impl FolderDefaults {
+    /// Fill unset fields from `parent`
+    fn inherit(&mut self, parent: &FolderDefaults) {
+        self.username = self.username.take().or_else(|| parent.username.clone());
+        self.port = self.port.or(parent.port);
+        self.key_path = self.key_path.take().or_else(|| parent.key_path.clone());
+        self.jump_host = self.jump_host.take().or_else(|| parent.jump_host.clone());
+        self.terminal_type = self.terminal_type.take().or_else(|| parent.terminal_type.clone());
+        self.encoding = self.encoding.take().or_else(|| parent.encoding.clone());
+        self.keepalive_interval = self.keepalive_interval.or(parent.keepalive_interval);
+        self.initial_command = self.initial_command.take().or_else(|| parent.initial_command.clone());
+    }
+}
+
+/// Bastion shared by a folder's profiles
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FolderJumpHost {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Folder {
+    pub path: String,
+    pub defaults: FolderDefaults,
+}
+
+impl Folder {
+    pub fn load_all(db: &Database) -> Result<Vec<Self>> {
+        let mut stmt = db.connection().prepare("SELECT path, defaults FROM folders ORDER BY path")?;
+        let folders = stmt.query_map([], |row| {
+            let defaults: String = row.get(1)?;
+            Ok(Folder {
+                path: row.get(0)?,
+                defaults: serde_json::from_str(&defaults).unwrap_or_default(),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+        Ok(folders)
+    }
+
+    pub fn save(&self, db: &Database) -> Result<()> {
+        db.connection().execute(
+            "INSERT OR REPLACE INTO folders (path, defaults) VALUES (?1, ?2)",
+            rusqlite::params![&self.path, serde_json::to_string(&self.defaults)?],
+        )?;
+        Ok(())
+    }
+
+    /// Rename a folder and everything below it, moving member profiles along
+    pub fn rename(db: &Database, from: &str, to: &str) -> Result<()> {
+        let tx = db.connection().unchecked_transaction()?;
+        // Compare exact prefixes rather than LIKE, which would treat `_` and
+        // `%` in folder names as wildcards; lengths are in characters on both
+        // sides so non-ASCII names cut in the right place
+        let below = format!("{}/", from);
+        tx.execute(
+            "UPDATE folders SET path = ?1 || substr(path, length(?2) + 1)
+             WHERE path = ?2 OR substr(path, 1, length(?3)) = ?3",
+            rusqlite::params![to, from, &below],
+        )?;
+        tx.execute(
+            "UPDATE connections SET group_name = ?1 || substr(group_name, length(?2) + 1)
+             WHERE group_name = ?2 OR substr(group_name, 1, length(?3)) = ?3",
+            rusqlite::params![to, from, &below],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Delete a folder and its subfolders; member profiles move to the parent
+    pub fn delete(db: &Database, path: &str) -> Result<()> {
+        let tx = db.connection().unchecked_transaction()?;
+        let below = format!("{}/", path);
+        tx.execute(
+            "DELETE FROM folders WHERE path = ?1 OR substr(path, 1, length(?2)) = ?2",
+            rusqlite::params![path, &below],
+        )?;
+        tx.execute(
+            "UPDATE connections SET group_name = ?1
+             WHERE group_name = ?2 OR substr(group_name, 1, length(?3)) = ?3",
+            rusqlite::params![parent(path), path, &below],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+/// Parent folder path, `None` at the top level
+pub fn parent(path: &str) -> Option<&str> {
+    path.rsplit_once('/').map(|(parent, _)| parent)
+}
+
+/// Last component of a folder path
+pub fn leaf_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Whether `group` is `folder` or lies below it
+pub fn contains(folder: &str, group: &str) -> bool {
+    group == folder || group.strip_prefix(folder).map_or(false, |rest| rest.starts_with('/'))
+}
+
+/// Every folder path implied by `paths`, ancestors included, sorted
+pub fn with_ancestors<'a>(paths: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut all = Vec::new();
+    for path in paths {
+        let mut current = Some(path);
+        while let Some(p) = current {
+            all.push(p.to_string());
+            current = parent(p);
+        }
+    }
+    all.sort();
+    all.dedup();
+    all
+}
+
+/// Defaults for a profile in `group`, merged from the root down
+pub fn resolve_defaults(folders: &[Folder], group: &str) -> FolderDefaults {
+    let mut resolved = FolderDefaults::default();
+    let mut current = Some(group);
+    while let Some(path) = current {
+        if let Some(folder) = folders.iter().find(|f| f.path == path) {
+            resolved.inherit(&folder.defaults);
+        }
+        current = parent(path);
+    }
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths() {
+        assert_eq!(parent("Prod/EU/Web"), Some("Prod/EU"));
+        assert_eq!(parent("Prod"), None);
+        assert_eq!(leaf_name("Prod/EU"), "EU");
+        assert!(contains("Prod", "Prod/EU"));
+        assert!(!contains("Prod", "Production"));
+        assert_eq!(with_ancestors(["Prod/EU/Web", "Dev"]), vec!["Dev", "Prod", "Prod/EU", "Prod/EU/Web"]);
+    }
+
+    #[test]
+    fn test_nearest_folder_wins() {
+        let folders = vec![
+            Folder {
+                path: "Prod".to_string(),
+                defaults: FolderDefaults {
+                    terminal_type: Some("xterm".to_string()),
+                    keepalive_interval: Some(60),
+                    ..Default::default()
+                },
+            },
+            Folder {
+                path: "Prod/EU".to_string(),
+                defaults: FolderDefaults {
+                    keepalive_interval: Some(15),
+                    username: Some("deploy".to_string()),
+                    jump_host: Some(FolderJumpHost {
+                        host: "bastion-eu.example.com".to_string(),
+                        port: 22,
+                        username: None,
+                    }),
+                    ..Default::default()
+                },
+            },
+        ];
+        let resolved = resolve_defaults(&folders, "Prod/EU/Web");
+        assert_eq!(resolved.keepalive_interval, Some(15));
+        assert_eq!(resolved.username.as_deref(), Some("deploy"));
+        assert_eq!(resolved.jump_host.map(|j| j.host).as_deref(), Some("bastion-eu.example.com"));
+        assert_eq!(resolve_defaults(&folders, "Prod").username, None);
+        assert_eq!(resolved.terminal_type.as_deref(), Some("xterm"));
+        assert_eq!(resolve_defaults(&folders, "Dev"), FolderDefaults::default());
+    }
+
+    #[test]
+    fn test_rename_matches_exact_prefix() {
+        let db = Database::open_in_memory().unwrap();
+        for path in ["Prod_EU", "Prod_EU/Web", "ProdXEU/Web", "Über", "Über/Db", "Dev_A/x", "DevXA/y"] {
+            Folder { path: path.to_string(), defaults: FolderDefaults::default() }.save(&db).unwrap();
+        }
+        Folder::rename(&db, "Prod_EU", "EU").unwrap();
+        Folder::rename(&db, "Über", "Uber").unwrap();
+        Folder::delete(&db, "Dev_A").unwrap();
+        let paths: Vec<String> = Folder::load_all(&db).unwrap().into_iter().map(|f| f.path).collect();
+        assert_eq!(paths, vec!["DevXA/y", "EU", "EU/Web", "ProdXEU/Web", "Uber", "Uber/Db"]);
+    }
+}
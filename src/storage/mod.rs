@@ -1,5 +1,14 @@
 //! Storage module - database and persistence
 
+pub mod bookmarks;
 pub mod database;
+pub mod folders;
+pub mod retention;
+pub mod session_groups;
+pub mod settings;
+pub mod settings_profile;
+pub mod stats;
+pub mod sync;
+pub mod workspace;
 
 pub use database::Database;
@@ -0,0 +1,319 @@
+//! Log retention and storage usage
+//!
+//! Session logs and traffic captures pile up in the data directory. A
+//! retention policy keeps them in check: at most so many files, none older
+//! than so many days, and older logs gzipped. A profile may bring its own
+//! policy for the logs of its host, which are named `<host>-<time>.log`;
+//! all other logs follow the policy in settings.
+
+use anyhow::{anyhow, Context, Result};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use super::database::Database;
+use crate::crypto::audit::SESSION_LOG_DIR;
+use crate::platform::PlatformManager;
+
+/// Directories of the data directory holding logs, with their captions
+pub const LOG_DIRS: [(&str, &str); 2] = [(SESSION_LOG_DIR, "Session logs"), ("captures", "Traffic captures")];
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Limits on kept logs; `None` leaves that limit off
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogRetention {
+    /// Keep only the newest this many
+    pub max_files: Option<u32>,
+    /// Delete logs last written longer ago than this
+    pub max_age_days: Option<u32>,
+    /// Gzip logs last written longer ago than this
+    pub compress_after_days: Option<u32>,
+}
+
+impl LogRetention {
+    /// Values filled in when a limit is first switched on
+    pub const SUGGESTED: LogRetention = LogRetention {
+        max_files: Some(200),
+        max_age_days: Some(90),
+        compress_after_days: Some(7),
+    };
+
+    pub fn keeps_everything(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// What applying a policy did
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RetentionOutcome {
+    pub deleted: usize,
+    pub compressed: usize,
+    pub freed_bytes: u64,
+}
+
+impl RetentionOutcome {
+    fn add(&mut self, other: RetentionOutcome) {
+        self.deleted += other.deleted;
+        self.compressed += other.compressed;
+        self.freed_bytes += other.freed_bytes;
+    }
+}
+
+impl std::fmt::Display for RetentionOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.deleted == 0 && self.compressed == 0 {
+            return write!(f, "Nothing to clean up");
+        }
+        write!(
+            f,
+            "Deleted {} and compressed {} logs, freeing {}",
+            self.deleted,
+            self.compressed,
+            crate::utils::helpers::format_file_size(self.freed_bytes)
+        )
+    }
+}
+
+/// A profile's own policy, for logs of its host
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfilePolicy {
+    pub host: String,
+    pub retention: LogRetention,
+}
+
+/// Policies of the profiles that have one
+pub fn profile_policies(db: &Database) -> Result<Vec<ProfilePolicy>> {
+    let conn = db.connection();
+    let mut stmt = conn.prepare("SELECT host, log_retention FROM connections WHERE log_retention IS NOT NULL")?;
+    let policies = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .filter_map(|row| row.ok())
+        .filter_map(|(host, json)| {
+            let retention = serde_json::from_str(&json).ok()?;
+            Some(ProfilePolicy { host, retention })
+        })
+        .collect();
+    Ok(policies)
+}
+
+/// Apply `default` and the profiles' policies to the logs in the data directory
+pub fn enforce(db: &Database, default: &LogRetention) -> Result<RetentionOutcome> {
+    let data_dir = PlatformManager::get_data_directory().ok_or_else(|| anyhow!("No data directory"))?;
+    let outcome = enforce_in(&data_dir, default, &profile_policies(db)?, SystemTime::now());
+    if outcome.deleted > 0 || outcome.compressed > 0 {
+        log::info!("Log retention: {}", outcome);
+    }
+    Ok(outcome)
+}
+
+/// Apply the policies to the logs under `data_dir` as of `now`
+pub fn enforce_in(data_dir: &Path, default: &LogRetention, profiles: &[ProfilePolicy], now: SystemTime) -> RetentionOutcome {
+    // Longest host first, so `web-prod-` logs are not taken for host `web`
+    let mut profiles: Vec<&ProfilePolicy> = profiles.iter().collect();
+    profiles.sort_by_key(|policy| std::cmp::Reverse(policy.host.len()));
+
+    let mut outcome = RetentionOutcome::default();
+    for (dir, _) in LOG_DIRS {
+        let mut groups: Vec<Vec<LogFile>> = (0..=profiles.len()).map(|_| Vec::new()).collect();
+        for file in list_logs(&data_dir.join(dir)) {
+            let name = file.path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            let group = profiles
+                .iter()
+                .position(|policy| name.starts_with(&format!("{}-", policy.host)))
+                .unwrap_or(profiles.len());
+            groups[group].push(file);
+        }
+        for (i, files) in groups.into_iter().enumerate() {
+            let policy = profiles.get(i).map_or(default, |policy| &policy.retention);
+            outcome.add(apply(files, policy, now));
+        }
+    }
+    outcome
+}
+
+struct LogFile {
+    path: PathBuf,
+    modified: SystemTime,
+    size: u64,
+}
+
+fn list_logs(dir: &Path) -> Vec<LogFile> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.ends_with(".log") || name.ends_with(".log.gz")
+        })
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok().filter(|m| m.is_file())?;
+            Some(LogFile { path: entry.path(), modified: metadata.modified().ok()?, size: metadata.len() })
+        })
+        .collect()
+}
+
+/// Newest first: delete what is over the count or too old, gzip the rest
+/// once old enough. A file that cannot be handled is logged and skipped.
+fn apply(mut files: Vec<LogFile>, policy: &LogRetention, now: SystemTime) -> RetentionOutcome {
+    let mut outcome = RetentionOutcome::default();
+    files.sort_by(|a, b| b.modified.cmp(&a.modified));
+    let older_than = |file: &LogFile, days: Option<u32>| {
+        days.map_or(false, |days| now.duration_since(file.modified).unwrap_or_default() > DAY * days)
+    };
+
+    for (i, file) in files.iter().enumerate() {
+        let over_count = policy.max_files.map_or(false, |max| i >= max as usize);
+        if over_count || older_than(file, policy.max_age_days) {
+            match std::fs::remove_file(&file.path) {
+                Ok(()) => {
+                    outcome.deleted += 1;
+                    outcome.freed_bytes += file.size;
+                }
+                Err(e) => log::warn!("Could not delete {}: {}", file.path.display(), e),
+            }
+        } else if older_than(file, policy.compress_after_days) && file.path.extension().map_or(false, |ext| ext == "log") {
+            match compress(file) {
+                Ok(size) => {
+                    outcome.compressed += 1;
+                    outcome.freed_bytes += file.size.saturating_sub(size);
+                }
+                Err(e) => log::warn!("Could not compress {}: {:#}", file.path.display(), e),
+            }
+        }
+    }
+    outcome
+}
+
+/// Replace a log with `<name>.gz`, keeping its modification time so age
+/// limits still count from when it was written; returns the new size
+fn compress(file: &LogFile) -> Result<u64> {
+    let mut target = file.path.clone().into_os_string();
+    target.push(".gz");
+    let target = PathBuf::from(target);
+
+    let mut input = File::open(&file.path)?;
+    let output = File::create(&target).with_context(|| format!("Could not create {}", target.display()))?;
+    let mut encoder = GzEncoder::new(output, Compression::default());
+    let result = std::io::copy(&mut input, &mut encoder).and_then(|_| encoder.finish());
+    let output = match result {
+        Ok(output) => output,
+        Err(e) => {
+            std::fs::remove_file(&target).ok();
+            return Err(e.into());
+        }
+    };
+    output.set_modified(file.modified)?;
+    let size = output.metadata()?.len();
+    std::fs::remove_file(&file.path)?;
+    Ok(size)
+}
+
+/// One line of the storage breakdown
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageCategory {
+    pub label: &'static str,
+    pub path: PathBuf,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// What the data directory holds, largest first
+pub fn storage_usage(data_dir: &Path) -> Vec<StorageCategory> {
+    let mut categories = Vec::new();
+    let mut database = StorageCategory { label: "Database", path: data_dir.join("tabssh.db"), files: 0, bytes: 0 };
+    let mut other = StorageCategory { label: "Other", path: data_dir.to_path_buf(), files: 0, bytes: 0 };
+    let known: Vec<(&str, &'static str)> = LOG_DIRS.into_iter().chain([("crashes", "Crash reports")]).collect();
+
+    let Ok(entries) = std::fs::read_dir(data_dir) else { return categories };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let (files, bytes) = tree_size(&entry.path());
+        if name.starts_with("tabssh.db") {
+            database.files += files;
+            database.bytes += bytes;
+        } else if let Some((_, label)) = known.iter().find(|(dir, _)| *dir == name) {
+            categories.push(StorageCategory { label, path: entry.path(), files, bytes });
+        } else {
+            other.files += files;
+            other.bytes += bytes;
+        }
+    }
+    categories.extend([database, other].into_iter().filter(|category| category.files > 0));
+    categories.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+    categories
+}
+
+/// Files under `path` and their total size; `path` itself if it is a file
+fn tree_size(path: &Path) -> (usize, u64) {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else { return (0, 0) };
+    if !metadata.is_dir() {
+        return (1, metadata.len());
+    }
+    let Ok(entries) = std::fs::read_dir(path) else { return (0, 0) };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| tree_size(&entry.path()))
+        .fold((0, 0), |(files, bytes), (f, b)| (files + f, bytes + b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enforce_policies() {
+        let data_dir = std::env::temp_dir().join(format!("tabssh-retention-{}", std::process::id()));
+        let logs = data_dir.join(SESSION_LOG_DIR);
+        std::fs::create_dir_all(&logs).unwrap();
+        std::fs::write(data_dir.join("tabssh.db"), vec![0u8; 4096]).unwrap();
+
+        let now = SystemTime::now();
+        let write_log = |name: &str, days_old: u32| {
+            let path = logs.join(name);
+            std::fs::write(&path, "output line\n".repeat(200)).unwrap();
+            File::options().write(true).open(&path).unwrap().set_modified(now - DAY * days_old).unwrap();
+        };
+        write_log("db1-20240101.log", 1);
+        write_log("db1-20231201.log", 30);
+        write_log("web-20240102.log", 2);
+        write_log("web-20231101.log", 60);
+        write_log("web-prod-20231001.log", 100);
+        write_log("cache-20231001.log", 100);
+        std::fs::write(logs.join("notes.txt"), "not a log").unwrap();
+
+        let default = LogRetention { max_files: None, max_age_days: Some(45), compress_after_days: Some(7) };
+        let profiles = [
+            ProfilePolicy { host: "db1".to_string(), retention: LogRetention { max_files: Some(1), ..Default::default() } },
+            ProfilePolicy { host: "web".to_string(), retention: LogRetention::default() },
+            ProfilePolicy { host: "web-prod".to_string(), retention: LogRetention { max_age_days: Some(50), ..Default::default() } },
+        ];
+        let outcome = enforce_in(&data_dir, &default, &profiles, now);
+
+        let mut left: Vec<String> = std::fs::read_dir(&logs)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        left.sort();
+        // db1 keeps one file, web keeps everything, web-prod is not taken
+        // for web, and cache falls to the default
+        assert_eq!(left, vec!["db1-20240101.log", "notes.txt", "web-20231101.log", "web-20240102.log"]);
+        assert_eq!(outcome.deleted, 3);
+        assert_eq!(outcome.compressed, 0);
+
+        write_log("other-20240101.log", 10);
+        let outcome = enforce_in(&data_dir, &default, &profiles, now);
+        assert_eq!(outcome.compressed, 1);
+        assert!(outcome.freed_bytes > 0);
+        let gz = std::fs::metadata(logs.join("other-20240101.log.gz")).unwrap();
+        assert!(now.duration_since(gz.modified().unwrap()).unwrap() > DAY * 9);
+
+        let usage = storage_usage(&data_dir);
+        std::fs::remove_dir_all(&data_dir).ok();
+        assert_eq!(usage.iter().map(|c| c.label).collect::<Vec<_>>(), vec!["Session logs", "Database"]);
+        assert_eq!(usage[0].files, 5);
+    }
+}
@@ -0,0 +1,163 @@
+//! Session groups - profiles opened together in one pane layout
+//!
+//! A group such as "web-cluster" names a set of saved profiles, how their
+//! terminals are arranged and whether typed input starts out broadcast to
+//! all of them, so the whole set opens with one action.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use super::database::Database;
+
+/// How a group's terminals are arranged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PaneLayout {
+    /// As close to square as possible
+    #[default]
+    Grid,
+    /// Side by side
+    Columns,
+    /// Stacked
+    Rows,
+    /// One tab per profile, no splits
+    Tabs,
+}
+
+impl PaneLayout {
+    pub const ALL: [PaneLayout; 4] = [PaneLayout::Grid, PaneLayout::Columns, PaneLayout::Rows, PaneLayout::Tabs];
+
+    /// Columns and rows of the split for `panes` terminals
+    pub fn cells(self, panes: usize) -> (usize, usize) {
+        let panes = panes.max(1);
+        match self {
+            PaneLayout::Grid => {
+                let cols = (panes as f64).sqrt().ceil() as usize;
+                (cols, panes.div_ceil(cols))
+            }
+            PaneLayout::Columns => (panes, 1),
+            PaneLayout::Rows => (1, panes),
+            PaneLayout::Tabs => (1, 1),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            PaneLayout::Grid => "grid",
+            PaneLayout::Columns => "columns",
+            PaneLayout::Rows => "rows",
+            PaneLayout::Tabs => "tabs",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        Self::ALL.into_iter().find(|layout| layout.as_str() == value).unwrap_or_default()
+    }
+}
+
+impl std::fmt::Display for PaneLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PaneLayout::Grid => write!(f, "Grid"),
+            PaneLayout::Columns => write!(f, "Side by side"),
+            PaneLayout::Rows => write!(f, "Stacked"),
+            PaneLayout::Tabs => write!(f, "Separate tabs"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionGroup {
+    pub id: String,
+    pub name: String,
+    /// Profiles in pane order
+    pub profile_ids: Vec<String>,
+    pub layout: PaneLayout,
+    /// Input typed in one pane goes to all of them from the start
+    pub broadcast: bool,
+}
+
+impl SessionGroup {
+    pub fn new(name: &str) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            profile_ids: Vec::new(),
+            layout: PaneLayout::default(),
+            broadcast: false,
+        }
+    }
+
+    pub fn load_all(db: &Database) -> Result<Vec<Self>> {
+        let mut stmt = db.connection().prepare(
+            "SELECT id, name, profile_ids, layout, broadcast FROM session_groups ORDER BY name COLLATE NOCASE",
+        )?;
+        let groups = stmt.query_map([], |row| {
+            let profile_ids: String = row.get(2)?;
+            let layout: String = row.get(3)?;
+            Ok(SessionGroup {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                profile_ids: serde_json::from_str(&profile_ids).unwrap_or_default(),
+                layout: PaneLayout::parse(&layout),
+                broadcast: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+        Ok(groups)
+    }
+
+    pub fn save(&self, db: &Database) -> Result<()> {
+        db.connection().execute(
+            "INSERT OR REPLACE INTO session_groups (id, name, profile_ids, layout, broadcast)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![
+                &self.id,
+                &self.name,
+                serde_json::to_string(&self.profile_ids)?,
+                self.layout.as_str(),
+                self.broadcast,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete(db: &Database, id: &str) -> Result<()> {
+        db.connection().execute("DELETE FROM session_groups WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    /// Drop a deleted profile from every group
+    pub fn forget_profile(db: &Database, profile_id: &str) -> Result<()> {
+        for mut group in Self::load_all(db)? {
+            let before = group.profile_ids.len();
+            group.profile_ids.retain(|id| id != profile_id);
+            if group.profile_ids.len() != before {
+                group.save(db)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_cells() {
+        assert_eq!(PaneLayout::Grid.cells(4), (2, 2));
+        assert_eq!(PaneLayout::Grid.cells(5), (3, 2));
+        assert_eq!(PaneLayout::Grid.cells(1), (1, 1));
+        assert_eq!(PaneLayout::Columns.cells(3), (3, 1));
+        assert_eq!(PaneLayout::Rows.cells(3), (1, 3));
+        assert_eq!(PaneLayout::Tabs.cells(3), (1, 1));
+    }
+
+    #[test]
+    fn test_layout_names_round_trip() {
+        for layout in PaneLayout::ALL {
+            assert_eq!(PaneLayout::parse(layout.as_str()), layout);
+        }
+        assert_eq!(PaneLayout::parse("unknown"), PaneLayout::Grid);
+    }
+}
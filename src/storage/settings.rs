@@ -2,76 +2,311 @@
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use super::database::Database;
+use crate::config::keymap::Keymap;
+use crate::utils::custom_actions::CustomAction;
+use crate::terminal::prediction::PredictionMode;
+use super::retention::LogRetention;
+use crate::terminal::status::{self, StatusSegmentConfig};
+use crate::terminal::triggers::OutputTrigger;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Settings {
     // General
     pub default_shell: String,
     pub auto_connect_on_startup: bool,
     pub restore_previous_sessions: bool,
+    /// Look for new releases once a day
+    #[serde(default = "default_true")]
+    pub check_for_updates: bool,
+    /// Save a redacted report after a crash and offer it on the next start
+    #[serde(default)]
+    pub crash_reports: bool,
     
     // Terminal
     pub font_family: String,
     pub font_size: f32,
     pub scrollback_lines: usize,
+    /// Memory each terminal's scrollback may take, in MiB; the oldest lines
+    /// go first when either this or the line limit is reached
+    #[serde(default = "default_scrollback_memory_mb")]
+    pub scrollback_memory_mb: usize,
     pub cursor_style: CursorStyle,
     pub cursor_blink: bool,
     pub bell_style: BellStyle,
+    /// Reply to ENQ (Ctrl+E) from the host; empty sends nothing
+    #[serde(default)]
+    pub answerback: String,
+    /// Send modified keys as `CSI code ; mod u` even if the application did not ask
+    #[serde(default)]
+    pub csi_u_keys: bool,
+    /// Alt+key sends ESC then the key; off on macOS so Option can compose
+    #[serde(default = "crate::terminal::input::default_alt_sends_escape")]
+    pub alt_sends_escape: bool,
+    /// Draw typed characters before the server echoes them
+    #[serde(default)]
+    pub echo_prediction: PredictionMode,
+    /// New tabs ignore window titles set by the remote
+    #[serde(default)]
+    pub lock_tab_titles: bool,
+    /// Print how long a command took after it ran `long_command_seconds` or
+    /// more; needs shell integration on the host
+    #[serde(default)]
+    pub annotate_long_commands: bool,
+    #[serde(default = "default_long_command_seconds")]
+    pub long_command_seconds: u32,
+    /// Segments of the status bar under each terminal, in display order
+    #[serde(default = "status::default_layout")]
+    pub status_bar: Vec<StatusSegmentConfig>,
     
     // Theme
     pub selected_theme: String,
+    /// Terminal background opacity in percent; the rest of the window stays opaque
+    #[serde(default = "default_window_opacity")]
+    pub window_opacity: u8,
+    /// Blur what shows through a translucent terminal background
+    #[serde(default)]
+    pub background_blur: bool,
     
     // Connection
     pub default_port: u16,
     pub connection_timeout: u32,
     pub keepalive_interval: u32,
     pub compression: bool,
+    /// Uploads keep the local mode bits and modification time unless a
+    /// transfer's options say otherwise
+    #[serde(default)]
+    pub preserve_upload_attributes: bool,
+    /// Refresh the SFTP browser's local pane when files change
+    #[serde(default = "default_true")]
+    pub watch_local_directory: bool,
     
     // Security
     pub auto_lock_timeout: u32,
     pub remember_passwords: bool,
     pub strict_host_key_checking: bool,
+    /// Offer sharing a read-only view of a terminal with TabSSH on the LAN
+    #[serde(default)]
+    pub allow_session_sharing: bool,
+    /// Shares listen on every interface instead of loopback only. The view
+    /// is sent unencrypted, so this is off unless asked for.
+    #[serde(default)]
+    pub share_on_lan: bool,
+    /// Unlock with Touch ID / Windows Hello instead of the master password
+    #[serde(default)]
+    pub biometric_unlock: bool,
+    /// Key exports always ask for the master password, never biometrics
+    #[serde(default = "default_true")]
+    pub require_password_for_key_export: bool,
+    /// Hide the content of terminals left idle and unfocused
+    #[serde(default)]
+    pub privacy_cover: PrivacyCover,
+    /// Minutes without focus or input before the cover goes up
+    #[serde(default = "default_privacy_idle_minutes")]
+    pub privacy_idle_minutes: u32,
     
     // Advanced
     pub log_level: String,
+    /// Limits on session logs and traffic captures; profiles may set their own
+    #[serde(default)]
+    pub log_retention: LogRetention,
+
+    // Custom actions
+    #[serde(default)]
+    pub custom_actions: Vec<CustomAction>,
+
+    /// Output patterns to highlight or act on
+    #[serde(default)]
+    pub output_triggers: Vec<OutputTrigger>,
+
+    /// Drop-down terminal summoned with a global hotkey
+    #[serde(default)]
+    pub quake: QuakeSettings,
+
+    /// Window shortcuts
+    #[serde(default)]
+    pub keymap: Keymap,
+}
+
+/// Drop-down terminal summoned from anywhere with a global hotkey
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuakeSettings {
+    pub enabled: bool,
+    /// e.g. "Ctrl+`" or "Alt+Space"
+    pub hotkey: String,
+    /// Saved connection to open in it; a local shell when `None`
+    pub profile_id: Option<String>,
+    /// Share of the screen height, in percent
+    pub height_percent: u8,
+}
+
+impl Default for QuakeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hotkey: "Ctrl+`".to_string(),
+            profile_id: None,
+            height_percent: 40,
+        }
+    }
+}
+
+/// What a settings change means for open sessions, as sent on the
+/// settings-changed bus so they update without reconnecting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsChange {
+    /// Font, cursor, theme or window opacity
+    Appearance,
+    /// New scrollback limit in lines
+    Scrollback(usize),
+    /// New scrollback memory cap in MiB
+    ScrollbackMemory(usize),
+    /// New keepalive interval in seconds; 0 turns keepalives off
+    Keepalive(u32),
+    /// Answerback, key encoding or echo prediction
+    Input,
+    OutputTriggers,
+    StatusBar,
+    /// Whether and after how long command durations are printed
+    CommandAnnotations,
+    /// Whether terminals may be shared on the LAN
+    Sharing,
+    /// How and after how long idle terminals are covered
+    PrivacyCover,
+}
+
+impl SettingsChange {
+    /// Every kind of change, for applying `settings` from scratch
+    pub fn all(settings: &Settings) -> Vec<SettingsChange> {
+        vec![
+            SettingsChange::Appearance,
+            SettingsChange::Scrollback(settings.scrollback_lines),
+            SettingsChange::ScrollbackMemory(settings.scrollback_memory_mb),
+            SettingsChange::Keepalive(settings.keepalive_interval),
+            SettingsChange::Input,
+            SettingsChange::OutputTriggers,
+            SettingsChange::StatusBar,
+            SettingsChange::CommandAnnotations,
+            SettingsChange::Sharing,
+            SettingsChange::PrivacyCover,
+        ]
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum CursorStyle {
     Block,
     Beam,
     Underline,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum BellStyle {
     None,
     Visual,
     Audio,
 }
 
+/// What covers a terminal that was left idle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PrivacyCover {
+    #[default]
+    Off,
+    /// Veil thick enough that only the shape of the output shows
+    Blur,
+    /// Plain background
+    Blank,
+}
+
+impl PrivacyCover {
+    pub const ALL: [PrivacyCover; 3] = [PrivacyCover::Off, PrivacyCover::Blur, PrivacyCover::Blank];
+
+    /// How long until a terminal unfocused for `idle` gets covered, given
+    /// `after` from `Settings::privacy_idle`; `None` once it is due
+    pub fn wait(idle: Duration, after: Duration) -> Option<Duration> {
+        after.checked_sub(idle).filter(|left| !left.is_zero())
+    }
+}
+
+impl std::fmt::Display for PrivacyCover {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PrivacyCover::Off => write!(f, "Off"),
+            PrivacyCover::Blur => write!(f, "Blur"),
+            PrivacyCover::Blank => write!(f, "Blank"),
+        }
+    }
+}
+
+fn default_window_opacity() -> u8 {
+    100
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_scrollback_memory_mb() -> usize {
+    crate::utils::memory::DEFAULT_SCROLLBACK_CAP / (1024 * 1024)
+}
+
+fn default_long_command_seconds() -> u32 {
+    10
+}
+
+fn default_privacy_idle_minutes() -> u32 {
+    10
+}
+
 impl Default for Settings {
     fn default() -> Self {
         Self {
             default_shell: "/bin/bash".to_string(),
             auto_connect_on_startup: false,
             restore_previous_sessions: true,
+            check_for_updates: true,
+            crash_reports: false,
             font_family: "monospace".to_string(),
             font_size: 14.0,
             scrollback_lines: 10000,
+            scrollback_memory_mb: default_scrollback_memory_mb(),
             cursor_style: CursorStyle::Block,
             cursor_blink: true,
             bell_style: BellStyle::Visual,
+            answerback: String::new(),
+            csi_u_keys: false,
+            alt_sends_escape: crate::terminal::input::default_alt_sends_escape(),
+            echo_prediction: PredictionMode::default(),
+            lock_tab_titles: false,
+            annotate_long_commands: false,
+            long_command_seconds: default_long_command_seconds(),
+            status_bar: status::default_layout(),
             selected_theme: "Default Dark".to_string(),
+            window_opacity: default_window_opacity(),
+            background_blur: false,
             default_port: 22,
             connection_timeout: 30,
             keepalive_interval: 60,
             compression: false,
+            preserve_upload_attributes: false,
+            watch_local_directory: true,
             auto_lock_timeout: 0,
             remember_passwords: false,
             strict_host_key_checking: true,
+            allow_session_sharing: false,
+            share_on_lan: false,
+            biometric_unlock: false,
+            require_password_for_key_export: true,
+            privacy_cover: PrivacyCover::Off,
+            privacy_idle_minutes: default_privacy_idle_minutes(),
             log_level: "info".to_string(),
+            log_retention: LogRetention::default(),
+            custom_actions: Vec::new(),
+            output_triggers: Vec::new(),
+            quake: QuakeSettings::default(),
+            keymap: Keymap::default(),
         }
     }
 }
@@ -97,7 +332,109 @@ impl Settings {
             "INSERT OR REPLACE INTO settings (key, value) VALUES ('app_settings', ?1)",
             [&json],
         )?;
+        // Used by sync to pick the newer side of a conflict
+        db.connection().execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('app_settings_modified', ?1)",
+            [chrono::Local::now().to_rfc3339()],
+        )?;
         
         Ok(())
     }
+
+    /// The changes from `self` to `new` that open sessions have to apply
+    pub fn changes(&self, new: &Settings) -> Vec<SettingsChange> {
+        let mut changes = Vec::new();
+        if self.font_family != new.font_family
+            || self.font_size != new.font_size
+            || self.cursor_style != new.cursor_style
+            || self.cursor_blink != new.cursor_blink
+            || self.selected_theme != new.selected_theme
+            || self.window_opacity != new.window_opacity
+            || self.background_blur != new.background_blur
+        {
+            changes.push(SettingsChange::Appearance);
+        }
+        if self.scrollback_lines != new.scrollback_lines {
+            changes.push(SettingsChange::Scrollback(new.scrollback_lines));
+        }
+        if self.scrollback_memory_mb != new.scrollback_memory_mb {
+            changes.push(SettingsChange::ScrollbackMemory(new.scrollback_memory_mb));
+        }
+        if self.keepalive_interval != new.keepalive_interval {
+            changes.push(SettingsChange::Keepalive(new.keepalive_interval));
+        }
+        if self.answerback != new.answerback
+            || self.csi_u_keys != new.csi_u_keys
+            || self.alt_sends_escape != new.alt_sends_escape
+            || self.echo_prediction != new.echo_prediction
+        {
+            changes.push(SettingsChange::Input);
+        }
+        if self.output_triggers != new.output_triggers {
+            changes.push(SettingsChange::OutputTriggers);
+        }
+        if self.status_bar != new.status_bar {
+            changes.push(SettingsChange::StatusBar);
+        }
+        if self.annotate_long_commands != new.annotate_long_commands
+            || self.long_command_seconds != new.long_command_seconds
+        {
+            changes.push(SettingsChange::CommandAnnotations);
+        }
+        if self.allow_session_sharing != new.allow_session_sharing || self.share_on_lan != new.share_on_lan {
+            changes.push(SettingsChange::Sharing);
+        }
+        if self.privacy_cover != new.privacy_cover || self.privacy_idle_minutes != new.privacy_idle_minutes {
+            changes.push(SettingsChange::PrivacyCover);
+        }
+        changes
+    }
+
+    /// How long a terminal may sit unfocused before the privacy cover goes
+    /// up; `None` while it is off. Never under a minute, whatever the file says.
+    pub fn privacy_idle(&self) -> Option<Duration> {
+        (self.privacy_cover != PrivacyCover::Off)
+            .then(|| Duration::from_secs(self.privacy_idle_minutes.max(1) as u64 * 60))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes() {
+        let old = Settings::default();
+        assert!(old.changes(&old.clone()).is_empty());
+
+        let mut new = old.clone();
+        new.font_size = 16.0;
+        new.selected_theme = "Solarized Light".to_string();
+        new.scrollback_lines = 500;
+        new.log_level = "debug".to_string();
+        assert_eq!(
+            old.changes(&new),
+            vec![SettingsChange::Appearance, SettingsChange::Scrollback(500)]
+        );
+    }
+
+    #[test]
+    fn test_privacy_idle() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.privacy_idle(), None);
+
+        settings.privacy_cover = PrivacyCover::Blur;
+        assert_eq!(settings.privacy_idle(), Some(Duration::from_secs(600)));
+        settings.privacy_idle_minutes = 0;
+        assert_eq!(settings.privacy_idle(), Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_privacy_wait() {
+        let after = Duration::from_secs(600);
+        assert_eq!(PrivacyCover::wait(Duration::ZERO, after), Some(after));
+        assert_eq!(PrivacyCover::wait(Duration::from_secs(590), after), Some(Duration::from_secs(10)));
+        assert_eq!(PrivacyCover::wait(after, after), None);
+        assert_eq!(PrivacyCover::wait(Duration::from_secs(3600), after), None);
+    }
 }
@@ -0,0 +1,165 @@
+//! Settings profiles - all settings and keybindings in one file
+//!
+//! A profile is exported as JSON or TOML, picked by the file extension, and
+//! imported on another machine. Profiles can also be saved by name in the
+//! config directory and switched between from the settings screen.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use super::settings::Settings;
+use crate::platform::PlatformManager;
+
+const PROFILE_DIR: &str = "settings-profiles";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfileFormat {
+    Json,
+    Toml,
+}
+
+impl ProfileFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ProfileFormat::Json => "json",
+            ProfileFormat::Toml => "toml",
+        }
+    }
+
+    /// From the extension; anything but `.toml` is read as JSON
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => ProfileFormat::Toml,
+            _ => ProfileFormat::Json,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsProfile {
+    pub name: String,
+    /// TabSSH version that wrote it
+    #[serde(default)]
+    pub app_version: String,
+    #[serde(default)]
+    pub exported_at: String,
+    pub settings: Settings,
+}
+
+impl SettingsProfile {
+    pub fn new(name: &str, settings: Settings) -> Self {
+        Self {
+            name: name.to_string(),
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            exported_at: chrono::Local::now().to_rfc3339(),
+            settings,
+        }
+    }
+
+    pub fn to_text(&self, format: ProfileFormat) -> Result<String> {
+        Ok(match format {
+            ProfileFormat::Json => serde_json::to_string_pretty(self)?,
+            ProfileFormat::Toml => toml::to_string_pretty(self)?,
+        })
+    }
+
+    pub fn parse(text: &str, format: ProfileFormat) -> Result<Self> {
+        let profile = match format {
+            ProfileFormat::Json => serde_json::from_str(text).context("Not a TabSSH settings profile")?,
+            ProfileFormat::Toml => toml::from_str(text).context("Not a TabSSH settings profile")?,
+        };
+        Ok(profile)
+    }
+
+    pub fn export(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_text(ProfileFormat::from_path(path))?)
+            .with_context(|| format!("Could not write {}", path.display()))
+    }
+
+    pub fn import(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()))?;
+        Self::parse(&text, ProfileFormat::from_path(path))
+    }
+
+    /// The profile's settings on this machine: what only makes sense where
+    /// it was exported, like the connection the drop-down terminal opens or
+    /// biometric unlock, stays as `current` has it
+    pub fn applied_to(&self, current: &Settings) -> Settings {
+        let mut settings = self.settings.clone();
+        settings.biometric_unlock = current.biometric_unlock;
+        settings.quake.profile_id = current.quake.profile_id.clone();
+        settings
+    }
+
+    /// Names of the profiles saved on this machine
+    pub fn list_saved() -> Vec<String> {
+        let Some(dir) = profile_dir() else { return Vec::new() };
+        let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().map_or(false, |ext| ext == "toml"))
+            .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+            .collect();
+        names.sort_by_key(|name| name.to_lowercase());
+        names
+    }
+
+    pub fn save_named(&self) -> Result<()> {
+        let path = saved_path(&self.name)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        self.export(&path)
+    }
+
+    pub fn load_named(name: &str) -> Result<Self> {
+        Self::import(&saved_path(name)?)
+    }
+
+    pub fn delete_named(name: &str) -> Result<()> {
+        std::fs::remove_file(saved_path(name)?)?;
+        Ok(())
+    }
+}
+
+fn profile_dir() -> Option<PathBuf> {
+    PlatformManager::get_config_directory().map(|dir| dir.join(PROFILE_DIR))
+}
+
+fn saved_path(name: &str) -> Result<PathBuf> {
+    let name = name.trim();
+    if name.is_empty() || name.contains(['/', '\\', ':']) || name.starts_with('.') {
+        return Err(anyhow!("'{}' can't be used as a profile name", name));
+    }
+    profile_dir()
+        .map(|dir| dir.join(format!("{}.toml", name)))
+        .ok_or_else(|| anyhow!("No config directory"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut settings = Settings::default();
+        settings.font_size = 17.0;
+        settings.keymap.set("find", "Ctrl+Shift+F");
+        settings.quake.profile_id = Some("other-machine".to_string());
+        let profile = SettingsProfile::new("work", settings.clone());
+
+        for format in [ProfileFormat::Json, ProfileFormat::Toml] {
+            let text = profile.to_text(format).unwrap();
+            let parsed = SettingsProfile::parse(&text, format).unwrap();
+            assert_eq!(parsed.name, "work");
+            assert_eq!(parsed.settings, settings);
+        }
+
+        let here = SettingsProfile::parse(&profile.to_text(ProfileFormat::Toml).unwrap(), ProfileFormat::Toml)
+            .unwrap()
+            .applied_to(&Settings::default());
+        assert_eq!(here.quake.profile_id, None);
+        assert_eq!(here.keymap.binding("find"), Some("Ctrl+Shift+F"));
+    }
+}
@@ -0,0 +1,131 @@
+//! Local usage statistics
+//!
+//! Sessions, time connected, data transferred and the busiest hosts, worked
+//! out from the session and transfer history tables for the stats page.
+//! Nothing here is sent anywhere.
+
+use anyhow::Result;
+use super::database::Database;
+
+/// How many hosts the "most used" list shows
+const TOP_HOSTS: usize = 10;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageStats {
+    pub sessions: u64,
+    pub connected_secs: u64,
+    pub uploaded_bytes: u64,
+    pub downloaded_bytes: u64,
+    pub transfers: u64,
+    pub failed_transfers: u64,
+    /// Busiest hosts by time connected
+    pub top_hosts: Vec<HostUsage>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostUsage {
+    pub host: String,
+    pub sessions: u64,
+    pub connected_secs: u64,
+}
+
+impl UsageStats {
+    /// Totals since the RFC 3339 timestamp `since`, or over all history.
+    /// Timestamps are compared as instants, since they may carry different
+    /// UTC offsets.
+    pub fn load(db: &Database, since: Option<&str>) -> Result<Self> {
+        let conn = db.connection();
+        let (sessions, connected_secs) = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(duration_secs), 0) FROM session_history
+             WHERE ?1 IS NULL OR julianday(started_at) >= julianday(?1)",
+            [since],
+            |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64)),
+        )?;
+
+        let (transfers, failed_transfers, uploaded_bytes, downloaded_bytes) = conn.query_row(
+            "SELECT COUNT(*),
+                    COALESCE(SUM(success = 0), 0),
+                    COALESCE(SUM(CASE WHEN success AND direction = 'upload' THEN size END), 0),
+                    COALESCE(SUM(CASE WHEN success AND direction = 'download' THEN size END), 0)
+             FROM transfer_history
+             WHERE ?1 IS NULL OR julianday(finished_at) >= julianday(?1)",
+            [since],
+            |row| Ok((
+                row.get::<_, i64>(0)? as u64,
+                row.get::<_, i64>(1)? as u64,
+                row.get::<_, i64>(2)? as u64,
+                row.get::<_, i64>(3)? as u64,
+            )),
+        )?;
+
+        let mut stmt = conn.prepare(
+            "SELECT host, COUNT(*), COALESCE(SUM(duration_secs), 0) FROM session_history
+             WHERE ?1 IS NULL OR julianday(started_at) >= julianday(?1)
+             GROUP BY host
+             ORDER BY SUM(duration_secs) DESC, COUNT(*) DESC
+             LIMIT ?2",
+        )?;
+        let top_hosts = stmt
+            .query_map(rusqlite::params![since, TOP_HOSTS as i64], |row| {
+                Ok(HostUsage {
+                    host: row.get(0)?,
+                    sessions: row.get::<_, i64>(1)? as u64,
+                    connected_secs: row.get::<_, i64>(2)? as u64,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            sessions,
+            connected_secs,
+            uploaded_bytes,
+            downloaded_bytes,
+            transfers,
+            failed_transfers,
+            top_hosts,
+        })
+    }
+}
+
+/// Time connected for display: minutes below an hour, then hours
+pub fn format_connected(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{} s", secs),
+        60..=3599 => format!("{} min", secs / 60),
+        _ => format!("{:.1} h", secs as f64 / 3600.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::database::SessionRecord;
+
+    #[test]
+    fn test_format_connected() {
+        assert_eq!(format_connected(42), "42 s");
+        assert_eq!(format_connected(25 * 60 + 10), "25 min");
+        assert_eq!(format_connected(5400), "1.5 h");
+        assert_eq!(format_connected(100 * 3600), "100.0 h");
+    }
+
+    #[test]
+    fn test_since_compares_instants() {
+        let db = Database::open_in_memory().unwrap();
+        db.add_session_record(&SessionRecord {
+            id: "a".to_string(),
+            connection_id: None,
+            host: "stats-test.example.com".to_string(),
+            started_at: "2024-03-01T09:30:00+00:00".to_string(),
+            duration_secs: 600,
+        }).unwrap();
+
+        // 10:00 at +02:00 is 08:00 UTC, before the session; as text it sorts after
+        let stats = UsageStats::load(&db, Some("2024-03-01T10:00:00+02:00")).unwrap();
+        assert_eq!(stats.sessions, 1);
+        assert_eq!(stats.top_hosts.len(), 1);
+
+        let stats = UsageStats::load(&db, Some("2024-03-01T10:00:00+00:00")).unwrap();
+        assert_eq!(stats.sessions, 0);
+    }
+}
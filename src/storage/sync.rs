@@ -0,0 +1,750 @@
+//! Settings sync across machines
+//!
+//! Connections, custom themes and app settings are bundled into a single
+//! JSON document kept on a user-provided target (SFTP path, git repository
+//! or WebDAV URL). SSH keys and passwords never leave the machine.
+//!
+//! Syncing is a three-way merge per item against the snapshot from the last
+//! successful sync: an item changed on only one side wins, and an item
+//! changed on both sides is a conflict settled by [`ConflictStrategy`].
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use super::database::Database;
+use crate::crypto::KeychainManager;
+use crate::sftp::SftpClient;
+use crate::ssh::{host_ca, ConnectionConfig, Credentials, SshConnection};
+
+/// File name of the bundle on every target
+pub const BUNDLE_FILE: &str = "tabssh-sync.json";
+const BUNDLE_VERSION: u32 = 1;
+/// Keychain service holding the WebDAV password, keyed by URL
+pub const KEYCHAIN_SERVICE: &str = "tabssh-sync";
+
+/// Connection columns that travel with the bundle. Usage counters and the
+/// auth method that last worked describe this machine and stay out of it.
+const SYNCED_CONNECTION_COLUMNS: &[&str] = &[
+    "id", "name", "host", "port", "username", "auth_type", "key_id", "group_name",
+    "timeout", "keepalive", "compression", "created_at", "updated_at", "key_path",
+    "is_favorite", "terminal_type", "initial_command", "encoding", "tcp_keepalive",
+    "x11_forwarding", "agent_forwarding", "local_forwards", "remote_forwards",
+    "jump_host", "notes", "tags", "kubernetes", "auto_sftp", "critical", "confirm_commands",
+    "identity_files", "auth_order", "service_actions", "knock_sequence", "scrollback_lines",
+    "log_retention", "host_certificate",
+];
+
+/// Where the sync bundle is kept
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum SyncTarget {
+    /// Directory on a saved connection, reached over SFTP
+    Sftp { connection_id: String, directory: String },
+    /// Git remote; a working clone is kept in the data directory
+    Git { url: String, branch: String },
+    /// WebDAV collection URL; the password is kept in the OS keychain
+    WebDav { url: String, username: String },
+}
+
+impl std::fmt::Display for SyncTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncTarget::Sftp { directory, .. } => write!(f, "SFTP {}", directory),
+            SyncTarget::Git { url, branch } => write!(f, "git {} ({})", url, branch),
+            SyncTarget::WebDav { url, .. } => write!(f, "WebDAV {}", url),
+        }
+    }
+}
+
+/// How to settle an item changed on both machines since the last sync
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ConflictStrategy {
+    /// Keep whichever side was modified most recently
+    PreferNewer,
+    PreferLocal,
+    PreferRemote,
+}
+
+impl Default for ConflictStrategy {
+    fn default() -> Self {
+        Self::PreferNewer
+    }
+}
+
+impl std::fmt::Display for ConflictStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConflictStrategy::PreferNewer => write!(f, "Keep newest"),
+            ConflictStrategy::PreferLocal => write!(f, "Keep this machine's"),
+            ConflictStrategy::PreferRemote => write!(f, "Keep remote"),
+        }
+    }
+}
+
+/// Per-machine sync configuration; never synced itself
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncConfig {
+    pub target: Option<SyncTarget>,
+    #[serde(default)]
+    pub strategy: ConflictStrategy,
+}
+
+impl SyncConfig {
+    pub fn load(db: &Database) -> Self {
+        db.connection()
+            .query_row("SELECT value FROM settings WHERE key = 'sync_config'", [], |row| row.get::<_, String>(0))
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, db: &Database) -> Result<()> {
+        db.connection().execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('sync_config', ?1)",
+            [serde_json::to_string(self)?],
+        )?;
+        Ok(())
+    }
+}
+
+/// Store the WebDAV password for `url` in the OS keychain
+pub fn store_webdav_password(url: &str, password: &str) -> Result<()> {
+    KeychainManager::new().store_password(KEYCHAIN_SERVICE, url, password)
+}
+
+/// One synced record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncItem {
+    /// RFC 3339 time of the last change, when known
+    pub modified: Option<String>,
+    pub data: Value,
+}
+
+impl SyncItem {
+    fn fingerprint(&self) -> String {
+        fnv1a(&self.data.to_string())
+    }
+
+    /// `modified` as an instant; machines write it with their own offsets,
+    /// so the strings do not compare. Unknown counts as oldest.
+    fn modified_at(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        let modified = self.modified.as_deref()?;
+        chrono::DateTime::parse_from_rfc3339(modified).ok().map(|t| t.with_timezone(&chrono::Utc))
+    }
+}
+
+/// The synced document
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncBundle {
+    pub version: u32,
+    pub exported_at: String,
+    pub device: String,
+    /// Keyed by `connection:<id>`, `theme:<id>` and `settings`
+    pub items: BTreeMap<String, SyncItem>,
+}
+
+/// What a sync run did
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    pub pulled: usize,
+    pub pushed: usize,
+    pub deleted: usize,
+    /// Keys of items changed on both sides, with the side that was kept
+    pub conflicts: Vec<(String, &'static str)>,
+}
+
+impl SyncReport {
+    pub fn summary(&self) -> String {
+        let mut text = format!("{} pulled, {} pushed, {} deleted", self.pulled, self.pushed, self.deleted);
+        if !self.conflicts.is_empty() {
+            text.push_str(&format!(", {} conflict(s) resolved", self.conflicts.len()));
+        }
+        text
+    }
+}
+
+/// 64-bit FNV-1a, stable across builds so stored fingerprints stay valid
+fn fnv1a(text: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in text.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// Merge local and remote items against the fingerprints from the last sync.
+///
+/// Returns the merged item set and a report; `pulled` counts items taken
+/// from remote, `pushed` items taken from local.
+pub fn merge(
+    local: &BTreeMap<String, SyncItem>,
+    remote: &BTreeMap<String, SyncItem>,
+    base: &HashMap<String, String>,
+    strategy: ConflictStrategy,
+) -> (BTreeMap<String, SyncItem>, SyncReport) {
+    let mut merged = BTreeMap::new();
+    let mut report = SyncReport::default();
+
+    let keys: BTreeSet<_> = local.keys().chain(remote.keys()).cloned().collect();
+    for key in keys {
+        let l = local.get(&key);
+        let r = remote.get(&key);
+        let lf = l.map(SyncItem::fingerprint);
+        let rf = r.map(SyncItem::fingerprint);
+        let bf = base.get(&key).cloned();
+
+        let take_remote = if lf == rf {
+            false
+        } else if lf == bf {
+            true
+        } else if rf == bf {
+            false
+        } else {
+            let remote_wins = match strategy {
+                ConflictStrategy::PreferLocal => false,
+                ConflictStrategy::PreferRemote => true,
+                // A deletion loses to an edit; otherwise compare timestamps
+                ConflictStrategy::PreferNewer => match (l, r) {
+                    (None, _) => true,
+                    (_, None) => false,
+                    (Some(l), Some(r)) => r.modified_at() > l.modified_at(),
+                },
+            };
+            report.conflicts.push((key.clone(), if remote_wins { "remote" } else { "local" }));
+            remote_wins
+        };
+
+        if lf != rf {
+            if take_remote {
+                report.pulled += 1;
+                if r.is_none() {
+                    report.deleted += 1;
+                }
+            } else {
+                report.pushed += 1;
+            }
+        }
+
+        if let Some(item) = if take_remote { r } else { l } {
+            merged.insert(key, item.clone());
+        }
+    }
+
+    (merged, report)
+}
+
+// ========== Local snapshot ==========
+
+/// Collect the syncable records from the local database
+pub fn collect_local(db: &Database) -> Result<BTreeMap<String, SyncItem>> {
+    let conn = db.connection();
+    let mut items = BTreeMap::new();
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM connections",
+        SYNCED_CONNECTION_COLUMNS.join(", ")
+    ))?;
+    let rows = stmt.query_map([], |row| {
+        let mut data = serde_json::Map::new();
+        for (i, column) in SYNCED_CONNECTION_COLUMNS.iter().enumerate() {
+            data.insert(column.to_string(), sql_to_json(row.get(i)?));
+        }
+        Ok(data)
+    })?;
+    for row in rows {
+        let data = row?;
+        let id = data.get("id").and_then(Value::as_str).unwrap_or_default().to_string();
+        let modified = data.get("updated_at").and_then(Value::as_str).map(str::to_string);
+        items.insert(format!("connection:{}", id), SyncItem { modified, data: Value::Object(data) });
+    }
+
+    let mut stmt = conn.prepare("SELECT id, name, colors, created_at FROM themes WHERE is_builtin = 0")?;
+    let rows = stmt.query_map([], |row| {
+        let id: String = row.get(0)?;
+        let created_at: String = row.get(3)?;
+        let data = serde_json::json!({
+            "id": id,
+            "name": row.get::<_, String>(1)?,
+            "colors": row.get::<_, String>(2)?,
+            "created_at": created_at.clone(),
+        });
+        Ok((id, created_at, data))
+    })?;
+    for row in rows {
+        let (id, created_at, data) = row?;
+        items.insert(format!("theme:{}", id), SyncItem { modified: Some(created_at), data });
+    }
+
+    if let Ok(json) = conn.query_row(
+        "SELECT value FROM settings WHERE key = 'app_settings'",
+        [],
+        |row| row.get::<_, String>(0),
+    ) {
+        let modified = conn.query_row(
+            "SELECT value FROM settings WHERE key = 'app_settings_modified'",
+            [],
+            |row| row.get::<_, String>(0),
+        ).ok();
+        items.insert("settings".to_string(), SyncItem { modified, data: serde_json::from_str(&json)? });
+    }
+
+    Ok(items)
+}
+
+/// Write merged items back, touching only what differs from `local`
+pub fn apply_local(
+    db: &Database,
+    local: &BTreeMap<String, SyncItem>,
+    merged: &BTreeMap<String, SyncItem>,
+) -> Result<()> {
+    let conn = db.connection();
+
+    for (key, item) in merged {
+        if local.get(key) == Some(item) {
+            continue;
+        }
+        let d = &item.data;
+        let s = |name: &str| d.get(name).and_then(Value::as_str).map(str::to_string);
+
+        if key.starts_with("connection:") {
+            upsert_connection(conn, d)?;
+        } else if key.starts_with("theme:") {
+            conn.execute(
+                "INSERT OR REPLACE INTO themes (id, name, is_builtin, colors, created_at)
+                 VALUES (?1, ?2, 0, ?3, ?4)",
+                rusqlite::params![s("id"), s("name"), s("colors"), s("created_at")],
+            )?;
+        } else if key == "settings" {
+            conn.execute(
+                "INSERT OR REPLACE INTO settings (key, value) VALUES ('app_settings', ?1)",
+                [d.to_string()],
+            )?;
+        }
+    }
+
+    for key in local.keys().filter(|k| !merged.contains_key(*k)) {
+        if let Some(id) = key.strip_prefix("connection:") {
+            conn.execute("DELETE FROM connections WHERE id = ?1", [id])?;
+        } else if let Some(id) = key.strip_prefix("theme:") {
+            conn.execute("DELETE FROM themes WHERE id = ?1 AND is_builtin = 0", [id])?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Insert or update a synced connection without touching the columns that
+/// stay on this machine, such as the usage counters
+fn upsert_connection(conn: &rusqlite::Connection, data: &Value) -> Result<()> {
+    let columns: Vec<&str> = SYNCED_CONNECTION_COLUMNS
+        .iter()
+        .copied()
+        .filter(|column| data.get(*column).is_some())
+        .collect();
+    if !columns.contains(&"id") {
+        return Err(anyhow!("Synced connection has no id"));
+    }
+    let placeholders: Vec<String> = (1..=columns.len()).map(|i| format!("?{}", i)).collect();
+    let updates: Vec<String> = columns
+        .iter()
+        .filter(|column| **column != "id")
+        .map(|column| format!("{0} = excluded.{0}", column))
+        .collect();
+    let sql = format!(
+        "INSERT INTO connections ({}) VALUES ({}) ON CONFLICT(id) DO UPDATE SET {}",
+        columns.join(", "),
+        placeholders.join(", "),
+        updates.join(", ")
+    );
+    conn.execute(&sql, rusqlite::params_from_iter(columns.iter().map(|column| json_to_sql(&data[*column]))))?;
+    Ok(())
+}
+
+fn sql_to_json(value: rusqlite::types::Value) -> Value {
+    use rusqlite::types::Value as Sql;
+    match value {
+        Sql::Null => Value::Null,
+        Sql::Integer(n) => Value::from(n),
+        Sql::Real(f) => Value::from(f),
+        Sql::Text(text) => Value::String(text),
+        Sql::Blob(_) => Value::Null,
+    }
+}
+
+fn json_to_sql(value: &Value) -> rusqlite::types::Value {
+    use rusqlite::types::Value as Sql;
+    match value {
+        Value::Bool(b) => Sql::Integer(*b as i64),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => Sql::Integer(i),
+            None => Sql::Real(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(text) => Sql::Text(text.clone()),
+        Value::Null | Value::Array(_) | Value::Object(_) => Sql::Null,
+    }
+}
+
+fn load_base(db: &Database) -> HashMap<String, String> {
+    db.connection()
+        .query_row("SELECT value FROM settings WHERE key = 'sync_base'", [], |row| row.get::<_, String>(0))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_base(db: &Database, merged: &BTreeMap<String, SyncItem>) -> Result<()> {
+    let base: HashMap<_, _> = merged.iter().map(|(k, v)| (k.clone(), v.fingerprint())).collect();
+    db.connection().execute(
+        "INSERT OR REPLACE INTO settings (key, value) VALUES ('sync_base', ?1)",
+        [serde_json::to_string(&base)?],
+    )?;
+    db.connection().execute(
+        "INSERT OR REPLACE INTO settings (key, value) VALUES ('sync_last_run', ?1)",
+        [chrono::Local::now().to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+/// Time of the last successful sync, if any
+pub fn last_sync(db: &Database) -> Option<String> {
+    db.connection()
+        .query_row("SELECT value FROM settings WHERE key = 'sync_last_run'", [], |row| row.get(0))
+        .ok()
+}
+
+// ========== Transports ==========
+
+/// Credentials and connections a transport may need
+pub struct SyncContext<'a> {
+    /// Open connection for [`SyncTarget::Sftp`]
+    pub ssh: Option<&'a SshConnection>,
+    /// Password for [`SyncTarget::WebDav`]
+    pub webdav_password: Option<String>,
+}
+
+impl<'a> SyncContext<'a> {
+    /// Build a context for `target`, reading the WebDAV password from the keychain
+    pub fn for_target(target: &SyncTarget, ssh: Option<&'a SshConnection>) -> Self {
+        let webdav_password = match target {
+            SyncTarget::WebDav { url, .. } => KeychainManager::new().get_password(KEYCHAIN_SERVICE, url).ok(),
+            _ => None,
+        };
+        Self { ssh, webdav_password }
+    }
+}
+
+async fn fetch(target: &SyncTarget, ctx: &SyncContext<'_>) -> Result<Option<Vec<u8>>> {
+    match target {
+        SyncTarget::Sftp { directory, .. } => {
+            let ssh = ctx.ssh.ok_or_else(|| anyhow!("SFTP sync needs an open connection"))?;
+            let mut sftp = open_sftp(ssh).await?;
+            let remote = Path::new(directory).join(BUNDLE_FILE);
+            if sftp.stat(&remote).await.is_err() {
+                return Ok(None);
+            }
+            let temp = temp_file("fetch");
+            sftp.download_file(&remote, &temp, None).await?;
+            let data = tokio::fs::read(&temp).await?;
+            let _ = tokio::fs::remove_file(&temp).await;
+            Ok(Some(data))
+        }
+        SyncTarget::Git { url, branch } => {
+            let dir = git_checkout(url, branch).await?;
+            match tokio::fs::read(dir.join(BUNDLE_FILE)).await {
+                Ok(data) => Ok(Some(data)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        }
+        SyncTarget::WebDav { url, username } => {
+            let url = webdav_file_url(url);
+            let auth = basic_auth(username, ctx.webdav_password.as_deref().unwrap_or(""));
+            tokio::task::spawn_blocking(move || {
+                match ureq::get(&url).set("Authorization", &auth).call() {
+                    Ok(response) => {
+                        let mut data = Vec::new();
+                        std::io::Read::read_to_end(&mut response.into_reader(), &mut data)?;
+                        Ok(Some(data))
+                    }
+                    Err(ureq::Error::Status(404, _)) => Ok(None),
+                    Err(e) => Err(anyhow!("WebDAV fetch failed: {}", e)),
+                }
+            })
+            .await?
+        }
+    }
+}
+
+async fn push(target: &SyncTarget, ctx: &SyncContext<'_>, data: Vec<u8>) -> Result<()> {
+    match target {
+        SyncTarget::Sftp { directory, .. } => {
+            let ssh = ctx.ssh.ok_or_else(|| anyhow!("SFTP sync needs an open connection"))?;
+            let mut sftp = open_sftp(ssh).await?;
+            let temp = temp_file("push");
+            tokio::fs::write(&temp, &data).await?;
+            let result = sftp.upload_file(&temp, &Path::new(directory).join(BUNDLE_FILE), None).await;
+            let _ = tokio::fs::remove_file(&temp).await;
+            result
+        }
+        SyncTarget::Git { url, branch } => {
+            let dir = git_checkout(url, branch).await?;
+            tokio::fs::write(dir.join(BUNDLE_FILE), &data).await?;
+            git(&dir, &["add", BUNDLE_FILE]).await?;
+            // Nothing staged means nothing changed since the last push
+            if git(&dir, &["diff", "--cached", "--quiet"]).await.is_ok() {
+                return Ok(());
+            }
+            let message = format!("TabSSH sync from {}", device_name());
+            git(&dir, &["commit", "-m", &message]).await?;
+            git(&dir, &["push", "origin", branch]).await
+        }
+        SyncTarget::WebDav { url, username } => {
+            let url = webdav_file_url(url);
+            let auth = basic_auth(username, ctx.webdav_password.as_deref().unwrap_or(""));
+            tokio::task::spawn_blocking(move || {
+                ureq::put(&url)
+                    .set("Authorization", &auth)
+                    .set("Content-Type", "application/json")
+                    .send_bytes(&data)
+                    .map(|_| ())
+                    .map_err(|e| anyhow!("WebDAV upload failed: {}", e))
+            })
+            .await?
+        }
+    }
+}
+
+async fn open_sftp(ssh: &SshConnection) -> Result<SftpClient> {
+    let mut sftp = SftpClient::new(uuid::Uuid::new_v4().to_string());
+    sftp.connect(ssh.open_sftp().await?).await?;
+    Ok(sftp)
+}
+
+fn temp_file(purpose: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("tabssh-sync-{}-{}.json", purpose, uuid::Uuid::new_v4()))
+}
+
+fn webdav_file_url(url: &str) -> String {
+    format!("{}/{}", url.trim_end_matches('/'), BUNDLE_FILE)
+}
+
+fn basic_auth(username: &str, password: &str) -> String {
+    use base64::Engine;
+    let token = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+    format!("Basic {}", token)
+}
+
+fn git_dir() -> PathBuf {
+    crate::platform::PlatformManager::get_data_directory()
+        .unwrap_or_else(|| std::env::temp_dir().join("tabssh"))
+        .join("sync-git")
+}
+
+async fn git(dir: &Path, args: &[&str]) -> Result<()> {
+    let output = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .await
+        .context("git is not installed")?;
+    if !output.status.success() {
+        return Err(anyhow!("git {} failed: {}", args[0], String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+/// Clone the sync repository, or bring the existing clone up to date
+async fn git_checkout(url: &str, branch: &str) -> Result<PathBuf> {
+    let dir = git_dir();
+    if !dir.join(".git").exists() {
+        tokio::fs::create_dir_all(&dir).await?;
+        let output = tokio::process::Command::new("git")
+            .args(["clone", "--branch", branch, url])
+            .arg(&dir)
+            .output()
+            .await
+            .context("git is not installed")?;
+        if !output.status.success() {
+            return Err(anyhow!("git clone failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+        }
+    } else {
+        git(&dir, &["pull", "--rebase", "origin", branch]).await?;
+    }
+    Ok(dir)
+}
+
+fn device_name() -> String {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Log in to the saved connection of an SFTP target. Nobody is there to
+/// type a password, so only the agent and the profile's key are tried.
+pub async fn connect_target(connection_id: &str) -> Result<SshConnection> {
+    let (config, key_path) = {
+        let db = Database::open()?;
+        let (host, port, username, key_path, certificate): (String, i64, String, Option<String>, Option<String>) =
+            db.connection()
+                .query_row(
+                    "SELECT host, port, username, key_path, host_certificate FROM connections WHERE id = ?1",
+                    [connection_id],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+                )
+                .map_err(|_| anyhow!("The sync connection no longer exists"))?;
+        let config = ConnectionConfig::new(host, username).with_port(port as u16);
+        let config = match certificate.as_deref().and_then(host_ca::parse_certificate_line) {
+            Some(certificate) => config.with_host_certificate(certificate, db.list_trusted_cas()?),
+            None => config,
+        };
+        (config, key_path)
+    };
+
+    let mut methods = vec![Credentials::agent()];
+    methods.extend(key_path.map(|path| Credentials::public_key(shellexpand::tilde(&path).into_owned(), None)));
+    Ok(SshConnection::connect_methods(config, &methods).await?.0)
+}
+
+/// Pull, merge, apply locally and push the merged bundle back.
+///
+/// The database is opened for each step rather than held over the
+/// network calls, so a sync can run on the executor.
+pub async fn run_sync(
+    target: &SyncTarget,
+    strategy: ConflictStrategy,
+    ctx: &SyncContext<'_>,
+) -> Result<SyncReport> {
+    let (local, base) = {
+        let db = Database::open()?;
+        (collect_local(&db)?, load_base(&db))
+    };
+    let remote = match fetch(target, ctx).await? {
+        Some(data) => {
+            let bundle: SyncBundle = serde_json::from_slice(&data).context("Remote sync file is corrupt")?;
+            if bundle.version > BUNDLE_VERSION {
+                return Err(anyhow!("Remote sync file was written by a newer TabSSH"));
+            }
+            bundle.items
+        }
+        None => BTreeMap::new(),
+    };
+
+    let (merged, report) = merge(&local, &remote, &base, strategy);
+
+    apply_local(&Database::open()?, &local, &merged)?;
+
+    if merged != remote {
+        let bundle = SyncBundle {
+            version: BUNDLE_VERSION,
+            exported_at: chrono::Local::now().to_rfc3339(),
+            device: device_name(),
+            items: merged.clone(),
+        };
+        push(target, ctx, serde_json::to_vec_pretty(&bundle)?).await?;
+    }
+
+    save_base(&Database::open()?, &merged)?;
+    log::info!("Sync with {} complete: {}", target, report.summary());
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(value: &str, modified: &str) -> SyncItem {
+        SyncItem { modified: Some(modified.to_string()), data: Value::String(value.to_string()) }
+    }
+
+    fn base_of(items: &[(&str, &SyncItem)]) -> HashMap<String, String> {
+        items.iter().map(|(k, v)| (k.to_string(), v.fingerprint())).collect()
+    }
+
+    #[test]
+    fn test_one_sided_changes() {
+        let old = item("a", "2024-01-01");
+        let base = base_of(&[("x", &old), ("y", &old)]);
+
+        let local = BTreeMap::from([("x".to_string(), item("local", "2024-02-01")), ("y".to_string(), old.clone())]);
+        let remote = BTreeMap::from([("x".to_string(), old.clone())]);
+
+        let (merged, report) = merge(&local, &remote, &base, ConflictStrategy::PreferNewer);
+
+        // x edited locally, y deleted remotely
+        assert_eq!(merged["x"].data, Value::String("local".into()));
+        assert!(!merged.contains_key("y"));
+        assert_eq!(report.deleted, 1);
+        assert!(report.conflicts.is_empty());
+    }
+
+    #[test]
+    fn test_conflict_strategies() {
+        let base = base_of(&[("x", &item("a", "2024-01-01T00:00:00Z"))]);
+        let local = BTreeMap::from([("x".to_string(), item("local", "2024-03-01T00:00:00Z"))]);
+        let remote = BTreeMap::from([("x".to_string(), item("remote", "2024-02-01T00:00:00Z"))]);
+
+        let (merged, report) = merge(&local, &remote, &base, ConflictStrategy::PreferNewer);
+        assert_eq!(merged["x"].data, Value::String("local".into()));
+        assert_eq!(report.conflicts, vec![("x".to_string(), "local")]);
+
+        let (merged, _) = merge(&local, &remote, &base, ConflictStrategy::PreferRemote);
+        assert_eq!(merged["x"].data, Value::String("remote".into()));
+    }
+
+    #[test]
+    fn test_newer_compares_instants_not_strings() {
+        let base = base_of(&[("x", &item("a", "2024-03-01T00:00:00Z"))]);
+        // 10:00 at +02:00 is 08:00 UTC, an hour before the remote edit
+        let local = BTreeMap::from([("x".to_string(), item("local", "2024-03-01T10:00:00+02:00"))]);
+        let remote = BTreeMap::from([("x".to_string(), item("remote", "2024-03-01T09:00:00Z"))]);
+
+        let (merged, report) = merge(&local, &remote, &base, ConflictStrategy::PreferNewer);
+        assert_eq!(merged["x"].data, Value::String("remote".into()));
+        assert_eq!(report.conflicts, vec![("x".to_string(), "remote")]);
+    }
+
+    #[test]
+    fn test_new_items_both_sides() {
+        let local = BTreeMap::from([("l".to_string(), item("1", "t"))]);
+        let remote = BTreeMap::from([("r".to_string(), item("2", "t"))]);
+
+        let (merged, report) = merge(&local, &remote, &HashMap::new(), ConflictStrategy::PreferNewer);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(report.pulled, 1);
+        assert_eq!(report.pushed, 1);
+    }
+
+    #[test]
+    fn test_pull_keeps_local_only_columns() {
+        let db = Database::open_in_memory().unwrap();
+        db.connection().execute(
+            "INSERT INTO connections (id, name, host, port, username, auth_type, connection_count,
+                                      last_connected, created_at, updated_at)
+             VALUES ('c1', 'web', 'web.example.com', 22, 'deploy', 'password', 5,
+                     '2024-03-01T10:00:00Z', '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z')",
+            [],
+        ).unwrap();
+
+        let local = collect_local(&db).unwrap();
+        let mut merged = local.clone();
+        let item = merged.get_mut("connection:c1").unwrap();
+        item.data["name"] = Value::String("web (prod)".into());
+        item.data["updated_at"] = Value::String("2024-02-01T00:00:00Z".into());
+        apply_local(&db, &local, &merged).unwrap();
+
+        let (name, count, last): (String, i64, Option<String>) = db.connection().query_row(
+            "SELECT name, connection_count, last_connected FROM connections WHERE id = 'c1'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).unwrap();
+        assert_eq!(name, "web (prod)");
+        assert_eq!(count, 5);
+        assert_eq!(last.as_deref(), Some("2024-03-01T10:00:00Z"));
+        assert_eq!(collect_local(&db).unwrap()["connection:c1"], merged["connection:c1"]);
+    }
+}
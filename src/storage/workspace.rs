@@ -0,0 +1,53 @@
+//! Workspace persistence - the tabs open at exit, reopened on next start
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use super::database::Database;
+use crate::terminal::scratchpad::ScratchpadFormat;
+
+/// A terminal tab to reopen
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceTab {
+    pub connection_id: String,
+    pub title: String,
+    /// The tab's scratchpad text
+    #[serde(default)]
+    pub scratchpad: String,
+    #[serde(default)]
+    pub scratchpad_format: ScratchpadFormat,
+}
+
+/// Tabs open when the app was last closed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Workspace {
+    pub tabs: Vec<WorkspaceTab>,
+    pub active_tab: usize,
+}
+
+impl Workspace {
+    pub fn load(db: &Database) -> Result<Option<Self>> {
+        match db.connection().query_row(
+            "SELECT value FROM settings WHERE key = 'workspace'",
+            [],
+            |row| row.get::<_, String>(0),
+        ) {
+            Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, db: &Database) -> Result<()> {
+        let json = serde_json::to_string(self)?;
+        db.connection().execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('workspace', ?1)",
+            [&json],
+        )?;
+        Ok(())
+    }
+
+    pub fn clear(db: &Database) -> Result<()> {
+        db.connection().execute("DELETE FROM settings WHERE key = 'workspace'", [])?;
+        Ok(())
+    }
+}
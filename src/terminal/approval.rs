@@ -0,0 +1,157 @@
+//! Command approval for critical hosts
+//!
+//! Profiles can ask for every command to be confirmed before it runs. The
+//! terminal holds back any input that `submits` a line and shows the command
+//! line captured since the last shell-integration `B` mark; without shell
+//! integration the input is still held, only the command is unknown. `risk`
+//! picks out the commands that deserve a louder warning, like `rm -rf` or
+//! writing to a raw disk.
+
+/// Whether input would run the command line: a carriage return or line feed
+/// anywhere in it (Enter, Ctrl+M, Ctrl+J, Alt+Enter, pasted lines), or Enter
+/// with modifiers as `CSI 13 ; m u` or modifyOtherKeys' `CSI 27 ; m ; 13 ~`
+pub fn submits(data: &[u8]) -> bool {
+    if data.iter().any(|&b| b == b'\r' || b == b'\n') {
+        return true;
+    }
+    data.windows(2)
+        .enumerate()
+        .filter(|(_, pair)| *pair == b"\x1b[")
+        .any(|(start, _)| {
+            let rest = &data[start + 2..];
+            let Some(end) = rest.iter().position(|b| !(b.is_ascii_digit() || *b == b';' || *b == b':')) else {
+                return false;
+            };
+            // Only digits and separators so far, so this is ASCII
+            let params: Vec<&str> = std::str::from_utf8(&rest[..end]).unwrap_or_default().split(';').collect();
+            // Kitty adds alternate keys after a colon
+            let code = |i: usize| params.get(i).and_then(|p| p.split(':').next());
+            match rest[end] {
+                b'u' => code(0) == Some("13"),
+                b'~' => code(0) == Some("27") && code(2) == Some("13"),
+                _ => false,
+            }
+        })
+}
+
+/// Why `command` looks destructive, if it does
+pub fn risk(command: &str) -> Option<&'static str> {
+    let lower = command.to_lowercase();
+    if lower.replace(' ', "").contains(":(){") {
+        return Some("Fork bomb");
+    }
+    if lower.contains("drop table") || lower.contains("drop database") || lower.contains("truncate table") {
+        return Some("Deletes database data");
+    }
+    command
+        .split(|c| matches!(c, ';' | '&' | '|' | '\n'))
+        .find_map(segment_risk)
+}
+
+/// Risk of one simple command, without pipes or separators
+fn segment_risk(segment: &str) -> Option<&'static str> {
+    if [">/dev/sd", "> /dev/sd", ">/dev/nvme", "> /dev/nvme"].iter().any(|device| segment.contains(device)) {
+        return Some("Overwrites a disk device");
+    }
+
+    // Look past privilege wrappers and `VAR=value` prefixes to the program
+    let mut words = segment
+        .split_whitespace()
+        .skip_while(|word| matches!(*word, "sudo" | "doas" | "exec" | "nohup" | "time") || is_assignment(word));
+    let program = words.next()?;
+    let program = program.rsplit('/').next().unwrap_or(program);
+    let args: Vec<&str> = words.collect();
+    let short = |flag: char| args.iter().any(|arg| arg.starts_with('-') && !arg.starts_with("--") && arg.contains(flag));
+    let long = |flag: &str| args.contains(&flag);
+
+    match program {
+        "rm" if (short('r') || short('R') || long("--recursive")) && (short('f') || long("--force")) => {
+            Some("Deletes recursively without asking")
+        }
+        "dd" if args.iter().any(|arg| arg.starts_with("of=/dev/")) => Some("Writes straight to a device"),
+        "chmod" | "chown" | "chgrp" if short('R') || long("--recursive") => Some("Changes permissions recursively"),
+        "shutdown" | "reboot" | "halt" | "poweroff" => Some("Stops or restarts the host"),
+        "init" | "telinit" if matches!(args.first(), Some(&"0") | Some(&"6")) => Some("Stops or restarts the host"),
+        "systemctl" if matches!(args.first(), Some(&"reboot") | Some(&"poweroff") | Some(&"halt")) => {
+            Some("Stops or restarts the host")
+        }
+        "wipefs" | "fdisk" | "parted" | "shred" => Some("Erases or repartitions a disk"),
+        _ if program.starts_with("mkfs") => Some("Formats a filesystem"),
+        _ => None,
+    }
+}
+
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terminal::input::{encode_key, InputModes, KeyboardOptions, ModifiedKeyEncoding};
+    use egui::{Key, Modifiers};
+
+    #[test]
+    fn test_every_enter_variant_submits() {
+        let auto = KeyboardOptions { encoding: ModifiedKeyEncoding::Auto, alt_sends_escape: true };
+        let csi_u = KeyboardOptions { encoding: ModifiedKeyEncoding::CsiU, ..auto };
+        let legacy = InputModes::default();
+        let level1 = InputModes { modify_other_keys: 1, ..Default::default() };
+        let ctrl = Modifiers::CTRL;
+
+        let submitting = [
+            (Key::Enter, Modifiers::NONE, legacy, auto),
+            (Key::Enter, Modifiers::SHIFT, legacy, auto),
+            (Key::Enter, Modifiers::ALT, legacy, auto),
+            (Key::M, ctrl, legacy, auto),
+            (Key::J, ctrl, legacy, auto),
+            (Key::Enter, ctrl, level1, auto),
+            (Key::Enter, Modifiers::SHIFT, legacy, csi_u),
+        ];
+        for (key, modifiers, modes, options) in submitting {
+            let data = encode_key(key, &modifiers, &modes, &options).unwrap();
+            assert!(submits(&data), "{:?} {:?} sent {:?}", key, modifiers, data);
+        }
+        assert!(submits(b"\x1b[13;2:3u"));
+        assert!(submits(b"rm -rf /tmp/x\n"));
+    }
+
+    #[test]
+    fn test_other_input_does_not_submit() {
+        let auto = KeyboardOptions { encoding: ModifiedKeyEncoding::Auto, alt_sends_escape: true };
+        let level2 = InputModes { modify_other_keys: 2, ..Default::default() };
+        for (key, modifiers) in [(Key::C, Modifiers::CTRL), (Key::ArrowUp, Modifiers::NONE), (Key::Tab, Modifiers::NONE), (Key::F5, Modifiers::NONE)] {
+            let data = encode_key(key, &modifiers, &level2, &auto).unwrap();
+            assert!(!submits(&data), "{:?} sent {:?}", key, data);
+        }
+        assert!(!submits(b"ls -la"));
+        assert!(!submits(b"\x1b[1;5C"));
+        assert!(!submits(b"\x1b[27;5;99~"));
+    }
+
+    #[test]
+    fn test_risky_commands() {
+        assert!(risk("rm -rf /var/lib/app").is_some());
+        assert!(risk("sudo rm -r -f build").is_some());
+        assert!(risk("cd /srv && /bin/rm --recursive --force data").is_some());
+        assert!(risk("LC_ALL=C sudo mkfs.ext4 /dev/sdb1").is_some());
+        assert!(risk("dd if=image.iso of=/dev/sda bs=4M").is_some());
+        assert!(risk("cat zeros > /dev/sda").is_some());
+        assert!(risk("sudo systemctl reboot").is_some());
+        assert!(risk("psql -c 'DROP TABLE users'").is_some());
+        assert!(risk(":(){ :|:& };:").is_some());
+    }
+
+    #[test]
+    fn test_harmless_commands() {
+        assert_eq!(risk("ls -la"), None);
+        assert_eq!(risk("rm old.log"), None);
+        assert_eq!(risk("rm -r build"), None);
+        assert_eq!(risk("dd if=/dev/zero of=test.img"), None);
+        assert_eq!(risk("systemctl status nginx"), None);
+        assert_eq!(risk("grep -rf patterns.txt ."), None);
+        assert_eq!(risk(""), None);
+    }
+}
@@ -0,0 +1,299 @@
+//! Terminal throughput benchmark
+//!
+//! A large block of output is fed through the parser in channel-sized
+//! chunks, with a frame drawn every few chunks the way a busy tab would.
+//! The output is either a raw recording (e.g. from `script`) or a built-in
+//! corpus mixing what terminals commonly receive: build logs, colored `ls`,
+//! full-screen redraws, wide characters and long wrapped lines.
+
+use anyhow::{anyhow, Context, Result};
+use std::path::Path;
+use std::time::Duration;
+
+/// A frame slower than this misses 60 Hz
+pub const FRAME_BUDGET: Duration = Duration::from_micros(16_667);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchmarkOptions {
+    /// Size of the built-in corpus, and what a short recording is repeated up to
+    pub corpus_bytes: usize,
+    pub cols: u16,
+    pub rows: u16,
+    pub scrollback: usize,
+    /// Bytes handed to the parser at a time, about one channel read
+    pub chunk_bytes: usize,
+    /// Chunks parsed between two frames
+    pub chunks_per_frame: usize,
+}
+
+impl Default for BenchmarkOptions {
+    fn default() -> Self {
+        Self {
+            corpus_bytes: 32 * 1024 * 1024,
+            cols: 160,
+            rows: 48,
+            scrollback: 10_000,
+            chunk_bytes: 32 * 1024,
+            chunks_per_frame: 4,
+        }
+    }
+}
+
+/// Frame times, nearest-rank percentiles
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FrameStats {
+    pub count: usize,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+    /// Frames over [`FRAME_BUDGET`]
+    pub over_budget: usize,
+}
+
+impl FrameStats {
+    pub fn from_samples(samples: &[Duration]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let rank = |p: f64| sorted[((p * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len()) - 1];
+        Self {
+            count: sorted.len(),
+            mean: sorted.iter().sum::<Duration>() / sorted.len() as u32,
+            p50: rank(0.50),
+            p95: rank(0.95),
+            p99: rank(0.99),
+            max: sorted[sorted.len() - 1],
+            over_budget: sorted.iter().filter(|t| **t > FRAME_BUDGET).count(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchmarkReport {
+    pub bytes: usize,
+    /// Time spent in the parser alone
+    pub parse_time: Duration,
+    /// Parsing and drawing together
+    pub total_time: Duration,
+    pub frames: FrameStats,
+}
+
+impl BenchmarkReport {
+    /// Parser throughput in MB/s (10^6 bytes)
+    pub fn parse_mb_per_sec(&self) -> f64 {
+        mb_per_sec(self.bytes, self.parse_time)
+    }
+
+    /// Throughput including drawing, what a tab sustains
+    pub fn total_mb_per_sec(&self) -> f64 {
+        mb_per_sec(self.bytes, self.total_time)
+    }
+}
+
+impl std::fmt::Display for BenchmarkReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        writeln!(
+            f,
+            "{:.1} MB in {:.2} s: {:.1} MB/s parsing, {:.1} MB/s with drawing",
+            self.bytes as f64 / 1e6,
+            self.total_time.as_secs_f64(),
+            self.parse_mb_per_sec(),
+            self.total_mb_per_sec()
+        )?;
+        write!(
+            f,
+            "{} frames: mean {:.2} ms, p50 {:.2} ms, p95 {:.2} ms, p99 {:.2} ms, max {:.2} ms, {} over budget",
+            self.frames.count,
+            ms(self.frames.mean),
+            ms(self.frames.p50),
+            ms(self.frames.p95),
+            ms(self.frames.p99),
+            ms(self.frames.max),
+            self.frames.over_budget
+        )
+    }
+}
+
+fn mb_per_sec(bytes: usize, time: Duration) -> f64 {
+    if time.is_zero() {
+        return 0.0;
+    }
+    bytes as f64 / 1e6 / time.as_secs_f64()
+}
+
+/// A raw recording, repeated until it is at least `min_bytes` long
+pub fn load_corpus(path: &Path, min_bytes: usize) -> Result<Vec<u8>> {
+    let recording = std::fs::read(path).with_context(|| format!("Could not read {}", path.display()))?;
+    if recording.is_empty() {
+        return Err(anyhow!("{} is empty", path.display()));
+    }
+    let mut corpus = recording.clone();
+    while corpus.len() < min_bytes {
+        corpus.extend_from_slice(&recording);
+    }
+    Ok(corpus)
+}
+
+/// The built-in corpus, the same for the same size
+pub fn corpus(bytes: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes + 8192);
+    let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+    let mut segment = 0;
+    while out.len() < bytes {
+        match segment % 6 {
+            0 => build_log(&mut out, &mut rng),
+            1 => colored_listing(&mut out, &mut rng),
+            2 => screen_redraw(&mut out, &mut rng),
+            3 => wide_text(&mut out, &mut rng),
+            4 => long_lines(&mut out, &mut rng),
+            _ => progress_bar(&mut out),
+        }
+        segment += 1;
+    }
+    out.truncate(bytes);
+    out
+}
+
+struct Rng(u64);
+
+impl Rng {
+    /// xorshift64; only needs to be repeatable
+    fn below(&mut self, n: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % n
+    }
+}
+
+const WORDS: [&str; 12] = [
+    "request", "handler", "cache", "worker", "session", "upstream",
+    "timeout", "buffer", "index", "replica", "config", "socket",
+];
+
+fn build_log(out: &mut Vec<u8>, rng: &mut Rng) {
+    for i in 0..40 {
+        let word = WORDS[rng.below(WORDS.len() as u64) as usize];
+        match rng.below(8) {
+            0 => out.extend_from_slice(format!(
+                "\x1b[1m\x1b[33mwarning\x1b[0m\x1b[1m: unused variable: `{}`\x1b[0m\r\n  \x1b[1m\x1b[38;5;12m-->\x1b[0m src/{}.rs:{}:9\r\n",
+                word, word, i + 10
+            ).as_bytes()),
+            1 => out.extend_from_slice(format!(
+                "\x1b[38;2;255;85;85merror\x1b[0m: {} failed after {} ms\r\n",
+                word, rng.below(5000)
+            ).as_bytes()),
+            _ => out.extend_from_slice(format!(
+                "\x1b[1m\x1b[32m   Compiling\x1b[0m {}-{} v0.{}.{}\r\n",
+                word, i, rng.below(20), rng.below(10)
+            ).as_bytes()),
+        }
+    }
+}
+
+fn colored_listing(out: &mut Vec<u8>, rng: &mut Rng) {
+    for _ in 0..12 {
+        for _ in 0..6 {
+            let word = WORDS[rng.below(WORDS.len() as u64) as usize];
+            let (color, suffix) = match rng.below(4) {
+                0 => ("01;34", "/"),
+                1 => ("01;32", ".sh"),
+                2 => ("01;31", ".tar.gz"),
+                _ => ("00", ".txt"),
+            };
+            out.extend_from_slice(format!("\x1b[{}m{}{}\x1b[0m  ", color, word, suffix).as_bytes());
+        }
+        out.extend_from_slice(b"\r\n");
+    }
+}
+
+/// What `top` or an editor sends: cursor moves, erases and reverse video
+fn screen_redraw(out: &mut Vec<u8>, rng: &mut Rng) {
+    out.extend_from_slice(b"\x1b[?1049h\x1b[H\x1b[2J\x1b[7m  PID USER      %CPU %MEM COMMAND\x1b[K\x1b[0m");
+    for row in 2..=40 {
+        out.extend_from_slice(format!(
+            "\x1b[{};1H{:>5} deploy   {:>4}.{} {:>4}.{} {}\x1b[K",
+            row,
+            rng.below(60000),
+            rng.below(100),
+            rng.below(10),
+            rng.below(20),
+            rng.below(10),
+            WORDS[rng.below(WORDS.len() as u64) as usize]
+        ).as_bytes());
+    }
+    out.extend_from_slice(b"\x1b[?1049l");
+}
+
+fn wide_text(out: &mut Vec<u8>, rng: &mut Rng) {
+    out.extend_from_slice("\u{250C}\u{2500}\u{2500}\u{2500}\u{252C}\u{2500}\u{2500}\u{2500}\u{2510}\r\n".as_bytes());
+    for _ in 0..20 {
+        let text = match rng.below(3) {
+            0 => "\u{65E5}\u{672C}\u{8A9E}\u{306E}\u{30C6}\u{30AD}\u{30B9}\u{30C8}",
+            1 => "\u{2714} passed \u{1F680} deployed",
+            _ => "caf\u{E9} na\u{EF}ve \u{FC}ber",
+        };
+        out.extend_from_slice(format!("\u{2502} {} \u{2502}\r\n", text).as_bytes());
+    }
+    out.extend_from_slice("\u{2514}\u{2500}\u{2500}\u{2500}\u{2534}\u{2500}\u{2500}\u{2500}\u{2518}\r\n".as_bytes());
+}
+
+fn long_lines(out: &mut Vec<u8>, rng: &mut Rng) {
+    for _ in 0..4 {
+        for _ in 0..60 {
+            out.extend_from_slice(WORDS[rng.below(WORDS.len() as u64) as usize].as_bytes());
+            out.push(b' ');
+        }
+        out.extend_from_slice(b"\r\n");
+    }
+}
+
+fn progress_bar(out: &mut Vec<u8>) {
+    for percent in (0..=100).step_by(5) {
+        let done = percent / 5;
+        out.extend_from_slice(format!(
+            "\r[{}{}] {:>3}%",
+            "#".repeat(done),
+            " ".repeat(20 - done),
+            percent
+        ).as_bytes());
+    }
+    out.extend_from_slice(b"\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corpus_and_stats() {
+        let corpus = corpus(256 * 1024);
+        assert_eq!(corpus.len(), 256 * 1024);
+        assert_eq!(corpus, super::corpus(256 * 1024));
+        assert!(corpus.windows(8).any(|w| w == b"\x1b[?1049h"));
+        assert!(String::from_utf8_lossy(&corpus).contains('\u{65E5}'));
+
+        let samples: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        let stats = FrameStats::from_samples(&samples);
+        assert_eq!(stats.count, 100);
+        assert_eq!(stats.p50, Duration::from_millis(50));
+        assert_eq!(stats.p95, Duration::from_millis(95));
+        assert_eq!(stats.max, Duration::from_millis(100));
+        assert_eq!(stats.over_budget, 84);
+        assert_eq!(FrameStats::from_samples(&[]), FrameStats::default());
+
+        let report = BenchmarkReport {
+            bytes: 50_000_000,
+            parse_time: Duration::from_millis(250),
+            total_time: Duration::from_millis(500),
+            frames: stats,
+        };
+        assert_eq!(report.parse_mb_per_sec(), 200.0);
+        assert!(report.to_string().starts_with("50.0 MB in 0.50 s: 200.0 MB/s parsing, 100.0 MB/s with drawing"));
+    }
+}
@@ -2,6 +2,15 @@
 
 use super::cell::{Cell, CellAttributes};
 use super::{Color, TerminalSize};
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+
+/// Most old lines evicted per new line while over a lowered scrollback
+/// limit, so lowering it never stalls a frame on one big eviction
+const EVICT_BATCH: usize = 64;
+
+/// When a line first received output
+pub type LineStamp = Option<DateTime<Local>>;
 
 /// Terminal buffer with scrollback support
 pub struct TerminalBuffer {
@@ -9,11 +18,25 @@ pub struct TerminalBuffer {
     screen: Vec<Vec<Cell>>,
 
     /// Scrollback buffer (previous lines)
-    scrollback: Vec<Vec<Cell>>,
+    scrollback: VecDeque<Vec<Cell>>,
+
+    /// Time each screen and scrollback line was first written, in step with the rows
+    screen_stamps: Vec<LineStamp>,
+    scrollback_stamps: VecDeque<LineStamp>,
 
-    /// Maximum scrollback lines
+    /// Maximum scrollback lines. After it is lowered the scrollback may hold
+    /// more for a while; the excess goes as new lines arrive.
     max_scrollback: usize,
 
+    /// Memory the scrollback may take, evicted the same way as lines
+    max_scrollback_bytes: usize,
+    /// What the scrollback lines take now, as counted by `line_bytes`
+    scrollback_bytes: usize,
+
+    /// Lines dropped off the top of a full scrollback, so callers can keep
+    /// stable line numbers across trimming
+    trimmed_lines: usize,
+
     /// Terminal dimensions
     size: TerminalSize,
 
@@ -41,6 +64,7 @@ pub struct TerminalBuffer {
     /// Alternate screen buffer
     alternate_screen: Option<Vec<Vec<Cell>>>,
     alternate_cursor: Option<(usize, usize)>,
+    alternate_stamps: Option<Vec<LineStamp>>,
 
     /// Origin mode (DECOM)
     origin_mode: bool,
@@ -59,8 +83,13 @@ impl TerminalBuffer {
 
         Self {
             screen,
-            scrollback: Vec::new(),
+            scrollback: VecDeque::new(),
+            screen_stamps: vec![None; rows as usize],
+            scrollback_stamps: VecDeque::new(),
             max_scrollback,
+            max_scrollback_bytes: usize::MAX,
+            scrollback_bytes: 0,
+            trimmed_lines: 0,
             size,
             cursor_x: 0,
             cursor_y: 0,
@@ -73,6 +102,7 @@ impl TerminalBuffer {
             scroll_bottom: rows as usize - 1,
             alternate_screen: None,
             alternate_cursor: None,
+            alternate_stamps: None,
             origin_mode: false,
             auto_wrap: true,
             insert_mode: false,
@@ -109,6 +139,155 @@ impl TerminalBuffer {
         self.scrollback.get(index)
     }
 
+    /// Number of lines in scrollback and on screen together
+    pub fn total_lines(&self) -> usize {
+        self.scrollback.len() + self.screen.len()
+    }
+
+    /// Get a line by absolute index, counting from the oldest scrollback line
+    pub fn get_line(&self, index: usize) -> Option<&Vec<Cell>> {
+        if index < self.scrollback.len() {
+            self.scrollback.get(index)
+        } else {
+            self.screen.get(index - self.scrollback.len())
+        }
+    }
+
+    pub fn max_scrollback(&self) -> usize {
+        self.max_scrollback
+    }
+
+    /// Change the scrollback limit, keeping the most recent lines. Raising
+    /// it keeps everything; lowering it evicts the oldest lines bit by bit
+    /// as output arrives instead of rebuilding the scrollback at once.
+    pub fn set_max_scrollback(&mut self, lines: usize) {
+        self.max_scrollback = lines;
+    }
+
+    /// Cap the memory the scrollback takes; like the line limit, lowering it
+    /// evicts as output arrives
+    pub fn set_max_scrollback_bytes(&mut self, bytes: usize) {
+        self.max_scrollback_bytes = bytes;
+    }
+
+    pub fn max_scrollback_bytes(&self) -> usize {
+        self.max_scrollback_bytes
+    }
+
+    /// Bytes held by the scrollback lines
+    pub fn scrollback_bytes(&self) -> usize {
+        self.scrollback_bytes
+    }
+
+    /// Bytes held by the screen, the alternate screen and the scrollback,
+    /// including slots the scrollback has reserved but not filled
+    pub fn memory_usage(&self) -> usize {
+        let rows = |rows: &[Vec<Cell>]| rows.iter().map(line_bytes).sum::<usize>();
+        let spare = self.scrollback.capacity() - self.scrollback.len();
+        self.scrollback_bytes
+            + spare * line_bytes(&Vec::new())
+            + rows(&self.screen)
+            + self.alternate_screen.as_deref().map_or(0, rows)
+    }
+
+    /// Drop the oldest lines beyond either limit, at most `EVICT_BATCH` at a time
+    fn evict_scrollback(&mut self) {
+        for _ in 0..EVICT_BATCH {
+            if self.scrollback.len() <= self.max_scrollback && self.scrollback_bytes <= self.max_scrollback_bytes {
+                break;
+            }
+            let Some(row) = self.scrollback.pop_front() else { break };
+            self.scrollback_bytes -= line_bytes(&row);
+            self.scrollback_stamps.pop_front();
+            self.trimmed_lines += 1;
+        }
+        // Give back what a lowered limit left reserved
+        if self.scrollback.capacity() > 4 * self.scrollback.len() + 1024 {
+            self.scrollback.shrink_to(2 * self.scrollback.len());
+            self.scrollback_stamps.shrink_to(2 * self.scrollback.len());
+        }
+    }
+
+    /// Drop all scrollback lines; the screen stays as it is
+    pub fn clear_scrollback(&mut self) {
+        self.trimmed_lines += self.scrollback.len();
+        self.scrollback = VecDeque::new();
+        self.scrollback_stamps = VecDeque::new();
+        self.scrollback_bytes = 0;
+    }
+
+    /// Lines trimmed from the scrollback since the buffer was created
+    pub fn trimmed_lines(&self) -> usize {
+        self.trimmed_lines
+    }
+
+    /// Plain text of one line, without trailing blanks
+    pub fn line_text(&self, index: usize) -> String {
+        self.get_line(index)
+            .map(|row| row.iter().map(|c| if c.character == '\0' { ' ' } else { c.character }).collect::<String>())
+            .map(|text| text.trim_end().to_string())
+            .unwrap_or_default()
+    }
+
+    /// When a line (absolute index, as in `get_line`) first received output
+    pub fn line_stamp(&self, index: usize) -> LineStamp {
+        if index < self.scrollback_stamps.len() {
+            self.scrollback_stamps[index]
+        } else {
+            self.screen_stamps.get(index - self.scrollback_stamps.len()).copied().flatten()
+        }
+    }
+
+    /// Like `text_range`, with each logical line prefixed by the time it was written
+    pub fn text_range_with_stamps(&self, start: (usize, usize), end: (usize, usize)) -> String {
+        let first = start.0.min(end.0);
+        let mut index = first;
+        let mut lines = Vec::new();
+        for line in self.text_range(start, end).split('\n') {
+            let stamp = match self.line_stamp(index) {
+                Some(time) => time.format("%H:%M:%S%.3f").to_string(),
+                None => " ".repeat(12),
+            };
+            lines.push(format!("[{}] {}", stamp, line));
+            // A logical line spans every row soft-wrapped into it
+            while self.get_line(index).and_then(|row| row.last()).map_or(false, |cell| cell.wrapped) {
+                index += 1;
+            }
+            index += 1;
+        }
+        lines.join("\n")
+    }
+
+    /// Text between two (line, column) positions, inclusive, for copying.
+    ///
+    /// Soft-wrapped rows are joined into their logical line instead of
+    /// getting a newline, and trailing blanks of each logical line are dropped.
+    pub fn text_range(&self, start: (usize, usize), end: (usize, usize)) -> String {
+        let (start, end) = if start <= end { (start, end) } else { (end, start) };
+        let mut text = String::new();
+        let mut line = String::new();
+
+        for index in start.0..=end.0.min(self.total_lines().saturating_sub(1)) {
+            let Some(row) = self.get_line(index) else { break };
+            let from = if index == start.0 { start.1 } else { 0 };
+            let to = if index == end.0 { end.1 + 1 } else { row.len() };
+            for cell in row.iter().take(to).skip(from) {
+                line.push(if cell.character == '\0' { ' ' } else { cell.character });
+            }
+
+            let wrapped = index != end.0 && row.last().map_or(false, |cell| cell.wrapped);
+            if !wrapped {
+                text.push_str(line.trim_end());
+                if index != end.0 {
+                    text.push('\n');
+                }
+                line.clear();
+            }
+        }
+
+        text
+    }
+
     /// Write a character at the current cursor position
     pub fn write_char(&mut self, c: char) {
         if c == '\n' {
@@ -137,6 +316,9 @@ impl TerminalBuffer {
 
         if self.cursor_x >= self.size.cols as usize {
             if self.auto_wrap {
+                if let Some(last) = self.screen.get_mut(self.cursor_y).and_then(|row| row.last_mut()) {
+                    last.wrapped = true;
+                }
                 self.cursor_x = 0;
                 self.newline();
             } else {
@@ -148,12 +330,17 @@ impl TerminalBuffer {
             self.insert_blank(1);
         }
 
+        if let Some(stamp @ None) = self.screen_stamps.get_mut(self.cursor_y) {
+            *stamp = Some(Local::now());
+        }
+
         if let Some(row) = self.screen.get_mut(self.cursor_y) {
             if let Some(cell) = row.get_mut(self.cursor_x) {
                 cell.character = c;
                 cell.fg = self.current_fg;
                 cell.bg = self.current_bg;
                 cell.attrs = self.current_attrs;
+                cell.wrapped = false;
             }
         }
 
@@ -215,17 +402,17 @@ impl TerminalBuffer {
         for _ in 0..n {
             if self.scroll_top == 0 {
                 if let Some(row) = self.screen.get(0).cloned() {
-                    self.scrollback.push(row);
-
-                    while self.scrollback.len() > self.max_scrollback {
-                        self.scrollback.remove(0);
-                    }
+                    self.scrollback_bytes += line_bytes(&row);
+                    self.scrollback.push_back(row);
+                    self.scrollback_stamps.push_back(self.screen_stamps[0]);
+                    self.evict_scrollback();
                 }
             }
 
             for y in self.scroll_top..self.scroll_bottom {
                 if y + 1 < self.screen.len() {
                     self.screen[y] = self.screen[y + 1].clone();
+                    self.screen_stamps[y] = self.screen_stamps[y + 1];
                 }
             }
 
@@ -233,6 +420,7 @@ impl TerminalBuffer {
                 self.screen[self.scroll_bottom] = (0..self.size.cols as usize)
                     .map(|_| Cell::default())
                     .collect();
+                self.screen_stamps[self.scroll_bottom] = None;
             }
         }
     }
@@ -243,6 +431,7 @@ impl TerminalBuffer {
             for y in (self.scroll_top + 1..=self.scroll_bottom).rev() {
                 if y > 0 && y < self.screen.len() {
                     self.screen[y] = self.screen[y - 1].clone();
+                    self.screen_stamps[y] = self.screen_stamps[y - 1];
                 }
             }
 
@@ -250,6 +439,7 @@ impl TerminalBuffer {
                 self.screen[self.scroll_top] = (0..self.size.cols as usize)
                     .map(|_| Cell::default())
                     .collect();
+                self.screen_stamps[self.scroll_top] = None;
             }
         }
     }
@@ -260,6 +450,7 @@ impl TerminalBuffer {
             self.size.cols as usize,
             self.size.rows as usize,
         );
+        self.screen_stamps = vec![None; self.size.rows as usize];
     }
 
     /// Clear from cursor to end of screen
@@ -271,6 +462,7 @@ impl TerminalBuffer {
                 for cell in row.iter_mut() {
                     cell.clear();
                 }
+                self.screen_stamps[y] = None;
             }
         }
     }
@@ -282,6 +474,7 @@ impl TerminalBuffer {
                 for cell in row.iter_mut() {
                     cell.clear();
                 }
+                self.screen_stamps[y] = None;
             }
         }
 
@@ -294,6 +487,8 @@ impl TerminalBuffer {
             for cell in row.iter_mut() {
                 cell.clear();
             }
+            // A redrawn line (progress bars, prompts) gets the time of its new content
+            self.screen_stamps[self.cursor_y] = None;
         }
     }
 
@@ -348,6 +543,8 @@ impl TerminalBuffer {
                     self.cursor_y,
                     (0..self.size.cols as usize).map(|_| Cell::default()).collect(),
                 );
+                self.screen_stamps.remove(self.scroll_bottom);
+                self.screen_stamps.insert(self.cursor_y, None);
             }
         }
     }
@@ -361,6 +558,8 @@ impl TerminalBuffer {
                     self.scroll_bottom,
                     (0..self.size.cols as usize).map(|_| Cell::default()).collect(),
                 );
+                self.screen_stamps.remove(self.cursor_y);
+                self.screen_stamps.insert(self.scroll_bottom, None);
             }
         }
     }
@@ -406,16 +605,25 @@ impl TerminalBuffer {
         if self.alternate_screen.is_none() {
             self.alternate_screen = Some(self.screen.clone());
             self.alternate_cursor = Some((self.cursor_x, self.cursor_y));
+            self.alternate_stamps = Some(self.screen_stamps.clone());
             self.clear();
             self.cursor_x = 0;
             self.cursor_y = 0;
         }
     }
 
+    /// Whether a full-screen program switched to the alternate screen
+    pub fn is_alternate_screen(&self) -> bool {
+        self.alternate_screen.is_some()
+    }
+
     /// Switch back to main screen buffer
     pub fn switch_to_main(&mut self) {
         if let Some(main_screen) = self.alternate_screen.take() {
             self.screen = main_screen;
+            if let Some(stamps) = self.alternate_stamps.take() {
+                self.screen_stamps = stamps;
+            }
             if let Some((x, y)) = self.alternate_cursor.take() {
                 self.cursor_x = x;
                 self.cursor_y = y;
@@ -465,6 +673,7 @@ impl TerminalBuffer {
         }
 
         self.screen = new_screen;
+        self.screen_stamps.resize(new_rows, None);
         self.size = TerminalSize { cols, rows };
 
         self.cursor_x = self.cursor_x.min(new_cols.saturating_sub(1));
@@ -476,6 +685,19 @@ impl TerminalBuffer {
         }
     }
 
+    /// Write a line of local, dimmed text at the cursor, e.g. a note between
+    /// commands, leaving the current attributes as they were
+    pub fn write_annotation(&mut self, text: &str) {
+        let attrs = self.current_attrs;
+        if self.cursor_x > 0 {
+            self.write_str("\r\n");
+        }
+        self.current_attrs = CellAttributes { dim: true, italic: true, ..CellAttributes::default() };
+        self.write_str(text);
+        self.current_attrs = attrs;
+        self.write_str("\r\n");
+    }
+
     /// Get current attributes
     pub fn current_attrs(&self) -> CellAttributes {
         self.current_attrs
@@ -500,8 +722,137 @@ impl TerminalBuffer {
     }
 }
 
+/// Memory one line takes: its cells, the row itself and its stamp
+fn line_bytes(row: &Vec<Cell>) -> usize {
+    row.capacity() * std::mem::size_of::<Cell>()
+        + std::mem::size_of::<Vec<Cell>>()
+        + std::mem::size_of::<LineStamp>()
+}
+
 impl Default for TerminalBuffer {
     fn default() -> Self {
         Self::new(80, 24, 10000)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_joins_wrapped_rows() {
+        let mut buffer = TerminalBuffer::new(10, 4, 100);
+        buffer.write_str("0123456789abcdef\r\nnext   ");
+
+        let text = buffer.text_range((0, 0), (1, 9));
+        assert_eq!(text, "0123456789abcdef");
+        let text = buffer.text_range((0, 0), (2, 9));
+        assert_eq!(text, "0123456789abcdef\nnext");
+    }
+
+    #[test]
+    fn test_copy_keeps_hard_newlines() {
+        let mut buffer = TerminalBuffer::new(10, 4, 100);
+        buffer.write_str("one   \r\ntwo");
+
+        assert_eq!(buffer.text_range((0, 0), (1, 9)), "one\ntwo");
+        // Rewriting a wrapped row's last cell clears its wrap mark
+        buffer.write_str("\r\n0123456789x");
+        buffer.set_cursor(9, 2);
+        buffer.write_char('!');
+        assert_eq!(buffer.text_range((2, 0), (3, 9)), "012345678!\nx");
+    }
+
+    #[test]
+    fn test_line_stamps_follow_scrolling() {
+        let mut buffer = TerminalBuffer::new(10, 2, 100);
+        buffer.write_str("one\r\n0123456789ab\r\nthree");
+
+        // "one" scrolled into scrollback with its stamp
+        assert_eq!(buffer.scrollback_len(), 2);
+        assert!(buffer.line_stamp(0).is_some());
+        // A cleared line is stamped again by its next output
+        buffer.clear_line();
+        assert!(buffer.line_stamp(3).is_none());
+
+        let copied = buffer.text_range_with_stamps((0, 0), (2, 9));
+        let lines: Vec<&str> = copied.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with('[') && lines[0].ends_with("] one"));
+        assert!(lines[1].ends_with("] 0123456789ab"));
+    }
+
+    #[test]
+    fn test_lowering_scrollback_evicts_lazily() {
+        let mut buffer = TerminalBuffer::new(10, 1, 1000);
+        for i in 0..500 {
+            buffer.write_str(&format!("{}\r\n", i));
+        }
+        assert_eq!(buffer.scrollback_len(), 500);
+
+        buffer.set_max_scrollback(10);
+        // Nothing is dropped until output arrives
+        assert_eq!(buffer.scrollback_len(), 500);
+        buffer.write_str("500\r\n");
+        assert_eq!(buffer.scrollback_len(), 501 - EVICT_BATCH);
+        for i in 501..520 {
+            buffer.write_str(&format!("{}\r\n", i));
+        }
+
+        // The most recent lines survive, with line numbers kept stable
+        assert_eq!(buffer.scrollback_len(), 10);
+        assert_eq!(buffer.line_text(9), "519");
+        assert_eq!(buffer.trimmed_lines(), 510);
+
+        buffer.set_max_scrollback(1000);
+        buffer.write_str("520\r\n");
+        assert_eq!(buffer.scrollback_len(), 11);
+    }
+
+    #[test]
+    fn test_week_long_session_stays_under_memory_cap() {
+        const CAP: usize = 1024 * 1024;
+        let mut buffer = TerminalBuffer::new(80, 4, 100_000);
+        buffer.set_max_scrollback_bytes(CAP);
+        let screen_allowance = |buffer: &TerminalBuffer| {
+            let size = buffer.size();
+            2 * size.rows as usize * line_bytes(&vec![Cell::default(); size.cols as usize])
+        };
+
+        // A line every five seconds for a week, with the window resized
+        // every day and a full-screen program now and then
+        let mut peak = 0;
+        for line in 0..7 * 24 * 720 {
+            if line % (24 * 720) == 0 {
+                let cols = if (line / (24 * 720)) % 2 == 0 { 200 } else { 80 };
+                buffer.resize(cols, 4);
+            }
+            if line % 5000 == 0 {
+                buffer.switch_to_alternate();
+                buffer.write_str("\u{2502} top - load average: 0.42");
+                buffer.switch_to_main();
+            }
+            buffer.write_str(&format!("{} GET /api/v1/items?page={} 200 {}ms\r\n", line, line % 97, line % 13));
+            if line % 720 == 0 {
+                assert!(buffer.scrollback_bytes() <= CAP, "scrollback over its cap at line {}", line);
+                assert!(buffer.memory_usage() <= CAP + screen_allowance(&buffer) + CAP / 2);
+                peak = peak.max(buffer.memory_usage());
+            }
+        }
+
+        assert!(buffer.trimmed_lines() > 100_000);
+        assert!(buffer.scrollback_len() < 100_000);
+        assert!(peak <= 2 * CAP);
+        // The newest output is all still there
+        let newest = (0..buffer.total_lines()).rev().map(|i| buffer.line_text(i)).find(|text| !text.is_empty());
+        assert_eq!(newest.unwrap(), format!("{} GET /api/v1/items?page={} 200 {}ms", 120_959, 120_959 % 97, 120_959 % 13));
+
+        // Lowering the cap applies as output arrives and frees the reserve
+        buffer.set_max_scrollback_bytes(CAP / 8);
+        for line in 0..200 {
+            buffer.write_str(&format!("{}\r\n", line));
+        }
+        assert!(buffer.scrollback_bytes() <= CAP / 8);
+        assert!(buffer.memory_usage() <= CAP / 8 + screen_allowance(&buffer) + CAP / 16);
+    }
+}
@@ -0,0 +1,184 @@
+//! Traffic capture for debugging rendering problems
+//!
+//! Records what a session's channel carries to a text file: each read from
+//! the host on one line, escaped, optionally followed by the escape
+//! sequences in it by name. Typed input is redacted to how many bytes were
+//! sent, but the host's echo of it is output like any other and is kept,
+//! so a password typed with echo on ends up in the file. Clipboard contents
+//! (OSC 52) are left out.
+//! Replies the terminal sends by itself, like device attributes, are kept
+//! since they often explain a bug.
+
+use super::escapes::{self, EscapeScanner, Token};
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Size cap offered by default
+pub const DEFAULT_MAX_BYTES: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptureOptions {
+    /// List the escape sequences of each read under it
+    pub annotate: bool,
+    /// Stop writing once the file reaches this size
+    pub max_bytes: u64,
+}
+
+impl Default for CaptureOptions {
+    fn default() -> Self {
+        Self { annotate: true, max_bytes: DEFAULT_MAX_BYTES }
+    }
+}
+
+pub struct TrafficCapture {
+    path: PathBuf,
+    out: Option<BufWriter<File>>,
+    options: CaptureOptions,
+    scanner: EscapeScanner,
+    started: Instant,
+    written: u64,
+}
+
+impl TrafficCapture {
+    /// Create the file and write a header naming the session
+    pub fn start(path: &Path, session: &str, options: CaptureOptions) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = File::create(path).with_context(|| format!("Could not create {}", path.display()))?;
+        let mut capture = Self {
+            path: path.to_path_buf(),
+            out: Some(BufWriter::new(file)),
+            options,
+            scanner: EscapeScanner::new(),
+            started: Instant::now(),
+            written: 0,
+        };
+        capture.write(&format!(
+            "# TabSSH traffic capture of {}, started {}\n\
+             # < from the host, > typed (redacted), = sent by the terminal itself\n",
+            session,
+            chrono::Local::now().to_rfc3339()
+        ));
+        Ok(capture)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Bytes written so far
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// Whether writing has stopped, at the size cap or on a write error
+    pub fn is_full(&self) -> bool {
+        self.out.is_none()
+    }
+
+    /// A read from the host
+    pub fn record_output(&mut self, data: &[u8]) {
+        if self.is_full() {
+            return;
+        }
+        let mut line = String::new();
+        let mut notes = Vec::new();
+        for token in self.scanner.feed(data) {
+            match token {
+                Token::Text(text) => line.push_str(&escapes::escape_bytes(&text)),
+                Token::Sequence(sequence) if sequence.osc_number() == Some(52) => {
+                    line.push_str(&format!("\\e]52;<{} bytes redacted>\\e\\\\", sequence.raw.len()));
+                    notes.push("OSC 52 clipboard (contents redacted)".to_string());
+                }
+                Token::Sequence(sequence) => {
+                    line.push_str(&escapes::escape_bytes(&sequence.raw));
+                    notes.push(sequence.to_string());
+                }
+            }
+        }
+        let mut entry = format!("{} < {}\n", self.stamp(), line);
+        if self.options.annotate {
+            for note in notes {
+                entry.push_str(&format!("           {}\n", note));
+            }
+        }
+        self.write(&entry);
+    }
+
+    /// Something the user typed; only its length is kept
+    pub fn record_input(&mut self, data: &[u8]) {
+        let entry = format!("{} > <{} bytes typed>\n", self.stamp(), data.len());
+        self.write(&entry);
+    }
+
+    /// A reply the terminal sent on its own, e.g. to a device status query
+    pub fn record_reply(&mut self, data: &[u8]) {
+        let entry = format!("{} = {}\n", self.stamp(), escapes::escape_bytes(data));
+        self.write(&entry);
+    }
+
+    /// Flush and close the file
+    pub fn finish(mut self) -> Result<PathBuf> {
+        if let Some(mut out) = self.out.take() {
+            out.write_all(b"# end of capture\n")?;
+            out.flush()?;
+        }
+        Ok(self.path)
+    }
+
+    fn stamp(&self) -> String {
+        format!("{:>10.3}", self.started.elapsed().as_secs_f64())
+    }
+
+    fn write(&mut self, text: &str) {
+        let Some(out) = &mut self.out else { return };
+        let full = self.written + text.len() as u64 > self.options.max_bytes;
+        let result = if full {
+            writeln!(out, "# capture stopped: reached the {} byte cap", self.options.max_bytes).and_then(|_| out.flush())
+        } else {
+            out.write_all(text.as_bytes())
+        };
+        match result {
+            Ok(()) if !full => self.written += text.len() as u64,
+            Ok(()) => self.out = None,
+            Err(e) => {
+                log::warn!("Traffic capture to {} stopped: {}", self.path.display(), e);
+                self.out = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_redacts_and_caps() {
+        let path = std::env::temp_dir().join(format!("tabssh-capture-{}.log", std::process::id()));
+        let mut capture = TrafficCapture::start(&path, "me@host", CaptureOptions { annotate: true, max_bytes: 600 }).unwrap();
+        capture.record_input(b"hunter2\r");
+        capture.record_output(b"\x1b[?2004hok\x1b]52;c;c2VjcmV0\x07");
+        capture.record_reply(b"\x1b[?62;22c");
+        for _ in 0..20 {
+            capture.record_output(b"filler filler filler filler\r\n");
+        }
+        assert!(capture.is_full());
+        capture.finish().unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert!(!text.contains("hunter2"));
+        assert!(!text.contains("c2VjcmV0"));
+        assert!(text.contains("> <8 bytes typed>"));
+        assert!(text.contains("< \\e[?2004hok\\e]52;<"));
+        assert!(text.contains("\\e[?2004h  DECSET (bracketed paste)"));
+        assert!(text.contains("= \\e[?62;22c"));
+        assert!(text.contains("# capture stopped"));
+        assert!(text.len() < 700);
+    }
+}
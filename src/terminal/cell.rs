@@ -28,6 +28,8 @@ pub struct Cell {
     pub fg: Color,
     pub bg: Color,
     pub attrs: CellAttributes,
+    /// Set on the last cell of a row that auto-wrapped into the next one
+    pub wrapped: bool,
 }
 
 impl Default for Cell {
@@ -37,6 +39,7 @@ impl Default for Cell {
             fg: Color::WHITE,
             bg: Color::BLACK,
             attrs: CellAttributes::default(),
+            wrapped: false,
         }
     }
 }
@@ -55,12 +58,14 @@ impl Cell {
             fg,
             bg,
             attrs: CellAttributes::default(),
+            wrapped: false,
         }
     }
 
     pub fn clear(&mut self) {
         self.character = ' ';
         self.attrs = CellAttributes::default();
+        self.wrapped = false;
     }
 
     pub fn is_empty(&self) -> bool {
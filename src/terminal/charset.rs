@@ -0,0 +1,122 @@
+//! Character set conversion for servers that do not speak UTF-8
+//!
+//! The terminal parser works on UTF-8. For a profile with a legacy charset,
+//! incoming bytes are decoded to UTF-8 before parsing and outgoing input is
+//! encoded back. Decoding is streaming, so a multi-byte character split
+//! across two reads is still decoded correctly.
+
+use encoding_rs::{Decoder, Encoding, UTF_8};
+
+/// Charsets offered in the connection editor
+pub const SUPPORTED_CHARSETS: [&str; 4] = ["UTF-8", "ISO-8859-1", "GBK", "Big5"];
+
+/// Converts session data between the server's charset and UTF-8
+pub struct SessionCharset {
+    encoding: &'static Encoding,
+    decoder: Decoder,
+}
+
+impl Default for SessionCharset {
+    fn default() -> Self {
+        Self::utf8()
+    }
+}
+
+impl SessionCharset {
+    pub fn utf8() -> Self {
+        Self {
+            encoding: UTF_8,
+            decoder: UTF_8.new_decoder_without_bom_handling(),
+        }
+    }
+
+    /// Look up a charset by its WHATWG label, e.g. "GBK" or "ISO-8859-1".
+    ///
+    /// As in browsers, ISO-8859-1 is treated as its superset windows-1252.
+    pub fn from_label(label: &str) -> Option<Self> {
+        let encoding = Encoding::for_label(label.trim().as_bytes())?;
+        Some(Self {
+            encoding,
+            decoder: encoding.new_decoder_without_bom_handling(),
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.encoding.name()
+    }
+
+    pub fn is_utf8(&self) -> bool {
+        self.encoding == UTF_8
+    }
+
+    /// Decode bytes from the server into UTF-8 for the parser
+    pub fn decode(&mut self, data: &[u8]) -> Vec<u8> {
+        if self.is_utf8() {
+            return data.to_vec();
+        }
+        let mut out = String::with_capacity(
+            self.decoder.max_utf8_buffer_length(data.len()).unwrap_or(data.len() * 3),
+        );
+        let (_, _, _) = self.decoder.decode_to_string(data, &mut out, false);
+        out.into_bytes()
+    }
+
+    /// Encode UTF-8 input from the keyboard or a paste for the server.
+    ///
+    /// Characters the charset cannot represent are sent as `?`.
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        if self.is_utf8() {
+            return data.to_vec();
+        }
+        let text = String::from_utf8_lossy(data);
+        let mut encoder = self.encoding.new_encoder();
+        let mut out = Vec::with_capacity(
+            encoder.max_buffer_length_from_utf8_without_replacement(text.len()).unwrap_or(text.len() * 2),
+        );
+        let mut rest: &str = &text;
+        loop {
+            let (result, read) = encoder.encode_from_utf8_to_vec_without_replacement(rest, &mut out, true);
+            rest = &rest[read..];
+            match result {
+                encoding_rs::EncoderResult::InputEmpty => break,
+                encoding_rs::EncoderResult::Unmappable(_) => out.push(b'?'),
+                encoding_rs::EncoderResult::OutputFull => {
+                    out.reserve(rest.len() * 2 + 4);
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supported_labels() {
+        for label in SUPPORTED_CHARSETS {
+            assert!(SessionCharset::from_label(label).is_some(), "{}", label);
+        }
+        assert!(SessionCharset::from_label("klingon").is_none());
+    }
+
+    #[test]
+    fn test_gbk_round_trip_with_split_reads() {
+        let mut charset = SessionCharset::from_label("GBK").unwrap();
+        let encoded = charset.encode("\x1b[1m中文\x1b[0m".as_bytes());
+        assert_eq!(&encoded[..4], b"\x1b[1m");
+
+        // Split in the middle of the first two-byte character
+        let mut decoded = charset.decode(&encoded[..5]);
+        decoded.extend(charset.decode(&encoded[5..]));
+        assert_eq!(String::from_utf8(decoded).unwrap(), "\x1b[1m中文\x1b[0m");
+    }
+
+    #[test]
+    fn test_latin1_and_unmappable() {
+        let mut charset = SessionCharset::from_label("ISO-8859-1").unwrap();
+        assert_eq!(charset.decode(&[b'c', b'a', b'f', 0xE9]), "café".as_bytes());
+        assert_eq!(charset.encode("é中".as_bytes()), vec![0xE9, b'?']);
+    }
+}
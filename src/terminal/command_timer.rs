@@ -0,0 +1,137 @@
+//! Command timing from shell-integration marks
+//!
+//! Shells set up for integration (iTerm2, VS Code, WezTerm and others use
+//! the same marks) emit OSC 133 around each prompt and command:
+//! `A` prompt start, `B` command line start, `C` output start and
+//! `D;<exit>` command finished. The time from `C` to `D` is how long the
+//! command ran.
+
+use crate::utils::helpers::format_idle;
+use std::time::{Duration, Instant};
+
+/// An OSC 133 mark
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellMark {
+    PromptStart,
+    CommandStart,
+    OutputStart,
+    /// Exit code if the shell reported one
+    CommandFinished(Option<i32>),
+}
+
+impl ShellMark {
+    /// Parse the parameters of an OSC sequence; `None` unless it is OSC 133
+    pub fn parse(params: &[&[u8]]) -> Option<Self> {
+        if params.first() != Some(&&b"133"[..]) {
+            return None;
+        }
+        // Options like `aid=...` may follow the kind; they are not needed here
+        match *params.get(1)? {
+            b"A" => Some(ShellMark::PromptStart),
+            b"B" => Some(ShellMark::CommandStart),
+            b"C" => Some(ShellMark::OutputStart),
+            b"D" => {
+                let exit_code = params.get(2)
+                    .and_then(|code| std::str::from_utf8(code).ok())
+                    .and_then(|code| code.parse().ok());
+                Some(ShellMark::CommandFinished(exit_code))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A command that ran to completion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FinishedCommand {
+    pub duration: Duration,
+    pub exit_code: Option<i32>,
+}
+
+/// Follows the marks of one session
+#[derive(Debug, Default)]
+pub struct CommandTimer {
+    /// When the running command started producing output
+    running_since: Option<Instant>,
+    last: Option<FinishedCommand>,
+}
+
+impl CommandTimer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a mark; returns the command it finished, if any
+    pub fn on_mark(&mut self, mark: ShellMark, now: Instant) -> Option<FinishedCommand> {
+        match mark {
+            ShellMark::OutputStart => {
+                self.running_since = Some(now);
+                None
+            }
+            ShellMark::CommandFinished(exit_code) => {
+                // `D` right after a prompt (Ctrl+C on an empty line) ran nothing
+                let started = self.running_since.take()?;
+                let finished = FinishedCommand { duration: now.duration_since(started), exit_code };
+                self.last = Some(finished);
+                Some(finished)
+            }
+            ShellMark::PromptStart | ShellMark::CommandStart => None,
+        }
+    }
+
+    /// How long the current command has been running
+    pub fn running_for(&self) -> Option<Duration> {
+        self.running_since.map(|started| started.elapsed())
+    }
+
+    pub fn last(&self) -> Option<FinishedCommand> {
+        self.last
+    }
+
+    /// Whether the shell sent any marks yet; without them nothing is timed
+    pub fn is_active(&self) -> bool {
+        self.running_since.is_some() || self.last.is_some()
+    }
+
+    /// Forget everything, e.g. on reconnect
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Command duration with sub-second precision for short commands
+pub fn format_command_duration(duration: Duration) -> String {
+    if duration < Duration::from_secs(60) {
+        format!("{:.1}s", duration.as_secs_f64())
+    } else {
+        format_idle(duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_marks() {
+        assert_eq!(ShellMark::parse(&[b"133", b"A"]), Some(ShellMark::PromptStart));
+        assert_eq!(ShellMark::parse(&[b"133", b"C"]), Some(ShellMark::OutputStart));
+        assert_eq!(ShellMark::parse(&[b"133", b"D", b"2"]), Some(ShellMark::CommandFinished(Some(2))));
+        assert_eq!(ShellMark::parse(&[b"133", b"D"]), Some(ShellMark::CommandFinished(None)));
+        assert_eq!(ShellMark::parse(&[b"0", b"title"]), None);
+    }
+
+    #[test]
+    fn test_timer() {
+        let start = Instant::now();
+        let mut timer = CommandTimer::new();
+        assert_eq!(timer.on_mark(ShellMark::CommandFinished(Some(130)), start), None);
+        assert!(!timer.is_active());
+
+        timer.on_mark(ShellMark::OutputStart, start);
+        let finished = timer.on_mark(ShellMark::CommandFinished(Some(0)), start + Duration::from_millis(2500));
+        assert_eq!(finished, Some(FinishedCommand { duration: Duration::from_millis(2500), exit_code: Some(0) }));
+        assert_eq!(timer.last(), finished);
+        assert_eq!(format_command_duration(finished.unwrap().duration), "2.5s");
+    }
+}
@@ -0,0 +1,481 @@
+//! Escape sequences in the output stream, split out and named
+//!
+//! A diagnostic view of what the host sends: the scanner cuts the byte
+//! stream into text and sequences, keeping a sequence that is split across
+//! reads until the rest arrives. It does not act on anything; the terminal
+//! parser does that.
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// An unterminated sequence longer than this is given up on
+const MAX_PENDING: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceKind {
+    /// A C0 control other than CR, LF and tab
+    Control,
+    /// `ESC` followed by one final byte, maybe after intermediates
+    Esc,
+    /// Control Sequence Introducer, `ESC [`
+    Csi,
+    /// Operating System Command, `ESC ]`
+    Osc,
+    /// DCS, APC, PM or SOS: a string up to `ESC \`
+    Str,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sequence {
+    pub kind: SequenceKind,
+    /// The whole sequence as received
+    pub raw: Vec<u8>,
+    /// Mnemonic like `SGR` or `DECSET`; None when not recognised
+    pub name: Option<&'static str>,
+    /// What the parameters mean, when worth saying, e.g. `alternate screen`
+    pub detail: String,
+    /// Cut off by a byte that can't appear in it, or never terminated
+    pub malformed: bool,
+}
+
+impl Sequence {
+    fn new(kind: SequenceKind, raw: &[u8], malformed: bool) -> Self {
+        let (name, detail) = if malformed { (None, String::new()) } else { describe(kind, raw) };
+        Self { kind, raw: raw.to_vec(), name, detail, malformed }
+    }
+
+    /// OSC number, e.g. 52 for the clipboard
+    pub fn osc_number(&self) -> Option<u32> {
+        if self.kind != SequenceKind::Osc {
+            return None;
+        }
+        let body = &self.raw[2..];
+        let end = body.iter().position(|b| !b.is_ascii_digit()).unwrap_or(body.len());
+        std::str::from_utf8(&body[..end]).ok()?.parse().ok()
+    }
+}
+
+impl std::fmt::Display for Sequence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", escape_bytes(&self.raw))?;
+        match self.name {
+            Some(name) => write!(f, "  {}", name)?,
+            None if self.malformed => write!(f, "  malformed")?,
+            None => write!(f, "  unknown")?,
+        }
+        if !self.detail.is_empty() {
+            write!(f, " ({})", self.detail)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Text(Vec<u8>),
+    Sequence(Sequence),
+}
+
+/// Cuts output into tokens as it arrives
+#[derive(Debug, Default)]
+pub struct EscapeScanner {
+    /// Start of a sequence whose end has not arrived yet
+    pending: Vec<u8>,
+}
+
+impl EscapeScanner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, data: &[u8]) -> Vec<Token> {
+        let mut buf = std::mem::take(&mut self.pending);
+        buf.extend_from_slice(data);
+
+        let mut tokens = Vec::new();
+        let mut text_start = None;
+        let mut i = 0;
+        while i < buf.len() {
+            let b = buf[i];
+            let is_control = (b < 0x20 && !matches!(b, b'\r' | b'\n' | b'\t')) || b == 0x7f;
+            if !is_control {
+                text_start.get_or_insert(i);
+                i += 1;
+                continue;
+            }
+            if let Some(start) = text_start.take() {
+                tokens.push(Token::Text(buf[start..i].to_vec()));
+            }
+            if b != 0x1b {
+                tokens.push(Token::Sequence(Sequence::new(SequenceKind::Control, &buf[i..=i], false)));
+                i += 1;
+                continue;
+            }
+            match scan_escape(&buf[i..]) {
+                Some((kind, len, malformed)) => {
+                    tokens.push(Token::Sequence(Sequence::new(kind, &buf[i..i + len], malformed)));
+                    i += len;
+                }
+                None if buf.len() - i > MAX_PENDING => {
+                    tokens.push(Token::Sequence(Sequence::new(SequenceKind::Str, &buf[i..], true)));
+                    i = buf.len();
+                }
+                None => {
+                    self.pending = buf[i..].to_vec();
+                    buf.truncate(i);
+                    break;
+                }
+            }
+        }
+        if let Some(start) = text_start {
+            tokens.push(Token::Text(buf[start..].to_vec()));
+        }
+        tokens
+    }
+}
+
+/// The latest sequences of a session for the inspector, with totals
+#[derive(Debug)]
+pub struct SequenceLog {
+    scanner: EscapeScanner,
+    entries: VecDeque<(Instant, Sequence)>,
+    capacity: usize,
+    /// Sequences seen since the log was created or cleared
+    pub total: u64,
+    /// Of those, sequences without a name here
+    pub unknown: u64,
+    pub malformed: u64,
+    /// Bytes of text between them
+    pub text_bytes: u64,
+}
+
+impl SequenceLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            scanner: EscapeScanner::new(),
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+            total: 0,
+            unknown: 0,
+            malformed: 0,
+            text_bytes: 0,
+        }
+    }
+
+    pub fn feed(&mut self, data: &[u8]) {
+        let now = Instant::now();
+        for token in self.scanner.feed(data) {
+            match token {
+                Token::Text(text) => self.text_bytes += text.len() as u64,
+                Token::Sequence(sequence) => {
+                    self.total += 1;
+                    if sequence.malformed {
+                        self.malformed += 1;
+                    } else if sequence.name.is_none() {
+                        self.unknown += 1;
+                    }
+                    if self.entries.len() == self.capacity {
+                        self.entries.pop_front();
+                    }
+                    self.entries.push_back((now, sequence));
+                }
+            }
+        }
+    }
+
+    /// Oldest first
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &(Instant, Sequence)> + ExactSizeIterator {
+        self.entries.iter()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new(self.capacity);
+    }
+}
+
+/// Kind and length of the sequence at the start of `buf`, which begins with
+/// ESC, and whether it was cut short; None while it is incomplete
+fn scan_escape(buf: &[u8]) -> Option<(SequenceKind, usize, bool)> {
+    let next = *buf.get(1)?;
+    match next {
+        b'[' => {
+            for (i, &b) in buf.iter().enumerate().skip(2) {
+                match b {
+                    0x20..=0x3f => {}
+                    0x40..=0x7e => return Some((SequenceKind::Csi, i + 1, false)),
+                    _ => return Some((SequenceKind::Csi, i, true)),
+                }
+            }
+            None
+        }
+        b']' | b'P' | b'_' | b'^' | b'X' => {
+            let kind = if next == b']' { SequenceKind::Osc } else { SequenceKind::Str };
+            let mut i = 2;
+            while i < buf.len() {
+                match buf[i] {
+                    // OSC may also end with BEL
+                    0x07 if kind == SequenceKind::Osc => return Some((kind, i + 1, false)),
+                    0x1b => {
+                        let after = *buf.get(i + 1)?;
+                        return Some(if after == b'\\' { (kind, i + 2, false) } else { (kind, i, true) });
+                    }
+                    _ => i += 1,
+                }
+            }
+            None
+        }
+        0x20..=0x2f => {
+            let end = buf.iter().skip(1).position(|b| !(0x20..=0x2f).contains(b))? + 1;
+            let malformed = !(0x30..=0x7e).contains(&buf[end]);
+            Some((SequenceKind::Esc, if malformed { end } else { end + 1 }, malformed))
+        }
+        0x30..=0x7e => Some((SequenceKind::Esc, 2, false)),
+        _ => Some((SequenceKind::Esc, 1, true)),
+    }
+}
+
+/// Name and a short reading of a complete sequence
+fn describe(kind: SequenceKind, raw: &[u8]) -> (Option<&'static str>, String) {
+    match kind {
+        SequenceKind::Control => (control_name(raw[0]), String::new()),
+        SequenceKind::Esc => (esc_name(&raw[1..]), String::new()),
+        SequenceKind::Csi => describe_csi(&raw[2..]),
+        SequenceKind::Osc => describe_osc(raw),
+        SequenceKind::Str => (
+            match raw[1] {
+                b'P' => Some("DCS"),
+                b'_' => Some("APC"),
+                b'^' => Some("PM"),
+                _ => Some("SOS"),
+            },
+            String::new(),
+        ),
+    }
+}
+
+fn control_name(b: u8) -> Option<&'static str> {
+    Some(match b {
+        0x00 => "NUL",
+        0x05 => "ENQ",
+        0x07 => "BEL",
+        0x08 => "BS",
+        0x0b => "VT",
+        0x0c => "FF",
+        0x0e => "SO",
+        0x0f => "SI",
+        0x7f => "DEL",
+        _ => return None,
+    })
+}
+
+fn esc_name(rest: &[u8]) -> Option<&'static str> {
+    Some(match rest {
+        b"7" => "DECSC",
+        b"8" => "DECRC",
+        b"D" => "IND",
+        b"E" => "NEL",
+        b"H" => "HTS",
+        b"M" => "RI",
+        b"c" => "RIS",
+        b"=" => "DECKPAM",
+        b">" => "DECKPNM",
+        b"\\" => "ST",
+        b"#8" => "DECALN",
+        [b'(' | b')' | b'*' | b'+', _] => "SCS",
+        [b'%', _] => "DOCS",
+        _ => return None,
+    })
+}
+
+fn describe_csi(body: &[u8]) -> (Option<&'static str>, String) {
+    let Some((&last, body)) = body.split_last() else {
+        return (None, String::new());
+    };
+    let private = body.first().copied().filter(|b| matches!(b, b'?' | b'>' | b'<' | b'='));
+    let params_end = body.iter().position(|b| (0x20..=0x2f).contains(b)).unwrap_or(body.len());
+    let intermediates = &body[params_end..];
+    let params = std::str::from_utf8(&body[usize::from(private.is_some())..params_end]).unwrap_or("");
+
+    let name = match (private, intermediates, last) {
+        (None, b"", b'A') => "CUU",
+        (None, b"", b'B') => "CUD",
+        (None, b"", b'C') => "CUF",
+        (None, b"", b'D') => "CUB",
+        (None, b"", b'E') => "CNL",
+        (None, b"", b'F') => "CPL",
+        (None, b"", b'G') => "CHA",
+        (None, b"", b'H') => "CUP",
+        (None, b"", b'I') => "CHT",
+        (None, b"", b'J') => "ED",
+        (Some(b'?'), b"", b'J') => "DECSED",
+        (None, b"", b'K') => "EL",
+        (Some(b'?'), b"", b'K') => "DECSEL",
+        (None, b"", b'L') => "IL",
+        (None, b"", b'M') => "DL",
+        (None, b"", b'P') => "DCH",
+        (None, b"", b'@') => "ICH",
+        (None, b"", b'S') => "SU",
+        (None, b"", b'T') => "SD",
+        (None, b"", b'X') => "ECH",
+        (None, b"", b'Z') => "CBT",
+        (None, b"", b'b') => "REP",
+        (None, b"", b'c') => "DA",
+        (Some(b'>'), b"", b'c') => "DA2",
+        (None, b"", b'd') => "VPA",
+        (None, b"", b'f') => "HVP",
+        (None, b"", b'g') => "TBC",
+        (None, b"", b'h') => "SM",
+        (Some(b'?'), b"", b'h') => "DECSET",
+        (None, b"", b'l') => "RM",
+        (Some(b'?'), b"", b'l') => "DECRST",
+        (None, b"", b'm') => "SGR",
+        (None, b"", b'n') => "DSR",
+        (Some(b'?'), b"", b'n') => "DECDSR",
+        (None, b"", b'r') => "DECSTBM",
+        (None, b"", b's') => "SCOSC",
+        (None, b"", b't') => "XTWINOPS",
+        (None, b"", b'u') => "SCORC",
+        (Some(b'?'), b"", b'u') => "kitty keyboard query",
+        (Some(b'>'), b"", b'u') => "kitty keyboard push",
+        (Some(b'<'), b"", b'u') => "kitty keyboard pop",
+        (None, b" ", b'q') => "DECSCUSR",
+        (None, b"!", b'p') => "DECSTR",
+        (Some(b'?'), b"$", b'p') => "DECRQM",
+        (Some(b'>'), b"", b'q') => "XTVERSION",
+        _ => return (None, String::new()),
+    };
+
+    let detail = match name {
+        "DECSET" | "DECRST" => params
+            .split(';')
+            .map(|mode| dec_mode_name(mode).map_or_else(|| format!("mode {}", mode), str::to_string))
+            .collect::<Vec<_>>()
+            .join(", "),
+        "SGR" if params.is_empty() || params == "0" => "reset".to_string(),
+        _ => String::new(),
+    };
+    (Some(name), detail)
+}
+
+fn dec_mode_name(mode: &str) -> Option<&'static str> {
+    Some(match mode {
+        "1" => "application cursor keys",
+        "3" => "132 columns",
+        "5" => "reverse video",
+        "6" => "origin mode",
+        "7" => "autowrap",
+        "12" => "cursor blink",
+        "25" => "cursor visible",
+        "47" | "1047" => "alternate screen",
+        "1048" => "save cursor",
+        "1049" => "alternate screen with saved cursor",
+        "1000" => "mouse clicks",
+        "1002" => "mouse drags",
+        "1003" => "all mouse motion",
+        "1004" => "focus events",
+        "1005" => "UTF-8 mouse",
+        "1006" => "SGR mouse",
+        "1015" => "urxvt mouse",
+        "2004" => "bracketed paste",
+        "2026" => "synchronized output",
+        _ => return None,
+    })
+}
+
+fn describe_osc(raw: &[u8]) -> (Option<&'static str>, String) {
+    let body = &raw[2..];
+    let end = body.iter().position(|b| !b.is_ascii_digit()).unwrap_or(body.len());
+    let name = match &body[..end] {
+        b"0" => "icon name and title",
+        b"1" => "icon name",
+        b"2" => "window title",
+        b"4" => "set palette color",
+        b"7" => "working directory",
+        b"8" => "hyperlink",
+        b"9" => "notification",
+        b"10" => "foreground color",
+        b"11" => "background color",
+        b"12" => "cursor color",
+        b"52" => "clipboard",
+        b"104" => "reset palette",
+        b"133" => "shell integration mark",
+        b"777" => "notification",
+        b"1337" => "iTerm2 extension",
+        _ => return (None, String::new()),
+    };
+    (Some(name), String::new())
+}
+
+/// Bytes as a readable string: printable ASCII as is, ESC as `\e`, common
+/// controls by their escapes and everything else as `\xNN`
+pub fn escape_bytes(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    for &b in bytes {
+        match b {
+            0x1b => out.push_str("\\e"),
+            b'\r' => out.push_str("\\r"),
+            b'\n' => out.push_str("\\n"),
+            b'\t' => out.push_str("\\t"),
+            b'\\' => out.push_str("\\\\"),
+            0x20..=0x7e => out.push(b as char),
+            _ => out.push_str(&format!("\\x{:02x}", b)),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequences(tokens: &[Token]) -> Vec<String> {
+        tokens
+            .iter()
+            .filter_map(|token| match token {
+                Token::Sequence(sequence) => Some(sequence.to_string()),
+                Token::Text(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_scanner() {
+        let mut scanner = EscapeScanner::new();
+        let tokens = scanner.feed(b"\x1b[1;32mok\x1b[0m\r\n\x1b[?1049h\x1b]0;vim\x07\x1b[");
+        assert_eq!(tokens[1], Token::Text(b"ok".to_vec()));
+        assert_eq!(sequences(&tokens), vec![
+            "\\e[1;32m  SGR",
+            "\\e[0m  SGR (reset)",
+            "\\e[?1049h  DECSET (alternate screen with saved cursor)",
+            "\\e]0;vim\\x07  icon name and title",
+        ]);
+
+        // The CSI started above completes with the next read
+        let tokens = scanner.feed(b"2J\x1b(B\x07\x1b[5y\x1b]52;c;aGk=\x1b\\");
+        assert_eq!(sequences(&tokens), vec![
+            "\\e[2J  ED",
+            "\\e(B  SCS",
+            "\\x07  BEL",
+            "\\e[5y  unknown",
+            "\\e]52;c;aGk=\\e\\\\  clipboard",
+        ]);
+        match &tokens[4] {
+            Token::Sequence(sequence) => assert_eq!(sequence.osc_number(), Some(52)),
+            other => panic!("{:?}", other),
+        }
+
+        let tokens = scanner.feed(b"\x1b[12\nx");
+        assert_eq!(sequences(&tokens), vec!["\\e[12  malformed"]);
+        assert_eq!(tokens[1], Token::Text(b"\nx".to_vec()));
+    }
+
+    #[test]
+    fn test_sequence_log() {
+        let mut log = SequenceLog::new(2);
+        log.feed(b"\x1b[H\x1b[2Jhello\x1b[5y\x1b");
+        log.feed(b"[?25l");
+        assert_eq!((log.total, log.unknown, log.malformed, log.text_bytes), (4, 1, 0, 5));
+        let kept: Vec<String> = log.entries().map(|(_, sequence)| sequence.to_string()).collect();
+        assert_eq!(kept, vec!["\\e[5y  unknown", "\\e[?25l  DECRST (cursor visible)"]);
+    }
+}
@@ -0,0 +1,443 @@
+//! Export terminal output as HTML or PDF with its colors and attributes
+//!
+//! Rows come in as resolved cells, so the same code serves the screen, the
+//! whole scrollback or a range of it. The PDF writer is deliberately small:
+//! one monospace font family from the PDF base set, no embedding, text
+//! outside Latin-1 mapped to ASCII look-alikes.
+
+use crate::config::themes::Theme;
+
+/// Output file format
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Html,
+    Pdf,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 2] = [ExportFormat::Html, ExportFormat::Pdf];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Html => "html",
+            ExportFormat::Pdf => "pdf",
+        }
+    }
+}
+
+impl std::fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportFormat::Html => write!(f, "HTML"),
+            ExportFormat::Pdf => write!(f, "PDF"),
+        }
+    }
+}
+
+/// Color scheme of the exported document
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportScheme {
+    /// Colors as on screen
+    Theme,
+    /// Dark text on white, with colors too light for paper darkened
+    Printer,
+}
+
+impl std::fmt::Display for ExportScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportScheme::Theme => write!(f, "Terminal theme"),
+            ExportScheme::Printer => write!(f, "Printer-friendly"),
+        }
+    }
+}
+
+/// One character with its colors; `None` means the theme default
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExportCell {
+    pub c: char,
+    pub fg: Option<[u8; 3]>,
+    pub bg: Option<[u8; 3]>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub dim: bool,
+    pub inverse: bool,
+}
+
+impl ExportCell {
+    pub fn plain(c: char) -> Self {
+        Self {
+            c,
+            fg: None,
+            bg: None,
+            bold: false,
+            italic: false,
+            underline: false,
+            dim: false,
+            inverse: false,
+        }
+    }
+}
+
+/// Default colors the cells are drawn against
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExportPalette {
+    pub background: [u8; 3],
+    pub foreground: [u8; 3],
+}
+
+impl ExportPalette {
+    pub fn from_theme(theme: &Theme) -> Self {
+        let rgb = |hex: &str, fallback: [u8; 3]| {
+            Theme::parse_color(hex).map(|(r, g, b)| [r, g, b]).unwrap_or(fallback)
+        };
+        Self {
+            background: rgb(&theme.background, [0x1e, 0x1e, 0x1e]),
+            foreground: rgb(&theme.foreground, [0xd4, 0xd4, 0xd4]),
+        }
+    }
+
+    fn for_scheme(&self, scheme: ExportScheme) -> Self {
+        match scheme {
+            ExportScheme::Theme => *self,
+            ExportScheme::Printer => Self {
+                background: [255, 255, 255],
+                foreground: [0, 0, 0],
+            },
+        }
+    }
+}
+
+impl Default for ExportPalette {
+    fn default() -> Self {
+        Self::from_theme(&Theme::default_dark())
+    }
+}
+
+/// Resolved look of a run of cells
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SpanStyle {
+    fg: [u8; 3],
+    /// `None` leaves the page background showing
+    bg: Option<[u8; 3]>,
+    bold: bool,
+    italic: bool,
+    underline: bool,
+}
+
+struct Span {
+    style: SpanStyle,
+    text: String,
+}
+
+fn blend(a: [u8; 3], b: [u8; 3], t: f32) -> [u8; 3] {
+    let mix = |x: u8, y: u8| (x as f32 + (y as f32 - x as f32) * t).round() as u8;
+    [mix(a[0], b[0]), mix(a[1], b[1]), mix(a[2], b[2])]
+}
+
+fn luminance(c: [u8; 3]) -> f32 {
+    (0.299 * c[0] as f32 + 0.587 * c[1] as f32 + 0.114 * c[2] as f32) / 255.0
+}
+
+fn resolve(cell: &ExportCell, palette: &ExportPalette, scheme: ExportScheme) -> SpanStyle {
+    let mut fg = cell.fg;
+    let mut bg = cell.bg;
+    if scheme == ExportScheme::Printer {
+        // Light text was readable on the dark screen, not on paper
+        fg = fg.map(|c| if luminance(c) > 0.55 { blend(c, [0, 0, 0], 0.5) } else { c });
+        bg = bg.map(|c| blend(c, [255, 255, 255], 0.7));
+    }
+    let palette = palette.for_scheme(scheme);
+    let (mut fg, bg) = if cell.inverse {
+        (bg.unwrap_or(palette.background), Some(fg.unwrap_or(palette.foreground)))
+    } else {
+        (fg.unwrap_or(palette.foreground), bg)
+    };
+    if cell.dim {
+        fg = blend(fg, bg.unwrap_or(palette.background), 0.4);
+    }
+    SpanStyle {
+        fg,
+        bg,
+        bold: cell.bold,
+        italic: cell.italic,
+        underline: cell.underline,
+    }
+}
+
+/// Runs of equal style, without trailing blanks that have no background
+fn spans(row: &[ExportCell], palette: &ExportPalette, scheme: ExportScheme) -> Vec<Span> {
+    let end = row.iter()
+        .rposition(|cell| !(cell.c == ' ' || cell.c == '\0') || cell.bg.is_some() || cell.inverse)
+        .map_or(0, |i| i + 1);
+    let mut spans: Vec<Span> = Vec::new();
+    for cell in &row[..end] {
+        let style = resolve(cell, palette, scheme);
+        let c = if cell.c == '\0' { ' ' } else { cell.c };
+        match spans.last_mut() {
+            Some(span) if span.style == style => span.text.push(c),
+            _ => spans.push(Span { style, text: c.to_string() }),
+        }
+    }
+    spans
+}
+
+fn hex(c: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", c[0], c[1], c[2])
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Standalone HTML page with the rows in a `<pre>` block
+pub fn to_html(rows: &[Vec<ExportCell>], palette: &ExportPalette, scheme: ExportScheme, title: &str) -> String {
+    let page = palette.for_scheme(scheme);
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str(&format!("<title>{}</title>\n", escape_html(title)));
+    out.push_str(&format!(
+        "<style>pre.terminal {{ background: {}; color: {}; padding: 12px; \
+         font-family: Menlo, Consolas, 'DejaVu Sans Mono', monospace; font-size: 13px; line-height: 1.25; }}</style>\n",
+        hex(page.background),
+        hex(page.foreground),
+    ));
+    out.push_str("</head>\n<body>\n<pre class=\"terminal\">");
+
+    for (i, row) in rows.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        for span in spans(row, palette, scheme) {
+            let mut css = Vec::new();
+            if span.style.fg != page.foreground {
+                css.push(format!("color: {}", hex(span.style.fg)));
+            }
+            if let Some(bg) = span.style.bg {
+                css.push(format!("background: {}", hex(bg)));
+            }
+            if span.style.bold {
+                css.push("font-weight: bold".to_string());
+            }
+            if span.style.italic {
+                css.push("font-style: italic".to_string());
+            }
+            if span.style.underline {
+                css.push("text-decoration: underline".to_string());
+            }
+            if css.is_empty() {
+                out.push_str(&escape_html(&span.text));
+            } else {
+                out.push_str(&format!("<span style=\"{}\">{}</span>", css.join("; "), escape_html(&span.text)));
+            }
+        }
+    }
+
+    out.push_str("</pre>\n</body>\n</html>\n");
+    out
+}
+
+// A4 portrait, in points
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 36.0;
+const MAX_FONT_SIZE: f32 = 9.0;
+/// Advance of every Courier glyph, per point of font size
+const COURIER_ADVANCE: f32 = 0.6;
+
+/// Byte for a character in WinAnsiEncoding; box drawing degrades to ASCII
+fn pdf_byte(c: char) -> u8 {
+    match c {
+        ' '..='~' => c as u8,
+        '\u{a0}'..='\u{ff}' => c as u32 as u8,
+        '─' | '━' | '═' | '╌' => b'-',
+        '│' | '┃' | '║' | '╎' => b'|',
+        '┌' | '┐' | '└' | '┘' | '├' | '┤' | '┬' | '┴' | '┼'
+        | '╔' | '╗' | '╚' | '╝' | '╠' | '╣' | '╦' | '╩' | '╬'
+        | '╭' | '╮' | '╯' | '╰' => b'+',
+        '•' | '●' => b'*',
+        '→' => b'>',
+        '←' => b'<',
+        _ => b'?',
+    }
+}
+
+fn pdf_string(text: &str) -> Vec<u8> {
+    let mut out = vec![b'('];
+    for c in text.chars() {
+        let b = pdf_byte(c);
+        if matches!(b, b'(' | b')' | b'\\') {
+            out.push(b'\\');
+        }
+        out.push(b);
+    }
+    out.push(b')');
+    out
+}
+
+fn pdf_rgb(c: [u8; 3]) -> String {
+    format!("{:.3} {:.3} {:.3}", c[0] as f32 / 255.0, c[1] as f32 / 255.0, c[2] as f32 / 255.0)
+}
+
+/// Content stream of one page
+fn pdf_page(rows: &[Vec<ExportCell>], palette: &ExportPalette, scheme: ExportScheme, font_size: f32) -> Vec<u8> {
+    let page = palette.for_scheme(scheme);
+    let line_height = font_size * 1.25;
+    let advance = font_size * COURIER_ADVANCE;
+    let mut out = Vec::new();
+
+    if scheme == ExportScheme::Theme {
+        out.extend(format!("{} rg 0 0 {} {} re f\n", pdf_rgb(page.background), PAGE_WIDTH, PAGE_HEIGHT).into_bytes());
+    }
+
+    for (i, row) in rows.iter().enumerate() {
+        let baseline = PAGE_HEIGHT - MARGIN - font_size - i as f32 * line_height;
+        let mut x = MARGIN;
+        for span in spans(row, palette, scheme) {
+            let width = span.text.chars().count() as f32 * advance;
+            if let Some(bg) = span.style.bg {
+                out.extend(format!(
+                    "{} rg {:.2} {:.2} {:.2} {:.2} re f\n",
+                    pdf_rgb(bg), x, baseline - font_size * 0.25, width, line_height,
+                ).into_bytes());
+            }
+            let font = match (span.style.bold, span.style.italic) {
+                (false, false) => "F1",
+                (true, false) => "F2",
+                (false, true) => "F3",
+                (true, true) => "F4",
+            };
+            out.extend(format!(
+                "BT /{} {} Tf {} rg {:.2} {:.2} Td ",
+                font, font_size, pdf_rgb(span.style.fg), x, baseline,
+            ).into_bytes());
+            out.extend(pdf_string(&span.text));
+            out.extend(b" Tj ET\n");
+            if span.style.underline {
+                let y = baseline - font_size * 0.15;
+                out.extend(format!(
+                    "{} RG 0.5 w {:.2} {:.2} m {:.2} {:.2} l S\n",
+                    pdf_rgb(span.style.fg), x, y, x + width, y,
+                ).into_bytes());
+            }
+            x += width;
+        }
+    }
+    out
+}
+
+/// PDF document on A4 pages; the font shrinks so the widest row fits
+pub fn to_pdf(rows: &[Vec<ExportCell>], palette: &ExportPalette, scheme: ExportScheme, title: &str) -> Vec<u8> {
+    let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0).max(80);
+    let font_size = ((PAGE_WIDTH - 2.0 * MARGIN) / (columns as f32 * COURIER_ADVANCE)).min(MAX_FONT_SIZE);
+    let per_page = (((PAGE_HEIGHT - 2.0 * MARGIN) / (font_size * 1.25)) as usize).max(1);
+    let pages: Vec<&[Vec<ExportCell>]> = if rows.is_empty() {
+        vec![rows]
+    } else {
+        rows.chunks(per_page).collect()
+    };
+
+    // 1 catalog, 2 page tree, 3 info, 4-7 fonts, then a page and its content per page
+    let first_page = 8;
+    let mut objects: Vec<Vec<u8>> = Vec::new();
+    objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+    let kids: Vec<String> = (0..pages.len()).map(|i| format!("{} 0 R", first_page + 2 * i)).collect();
+    objects.push(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len()).into_bytes());
+    let mut info = b"<< /Producer (TabSSH) /Title ".to_vec();
+    info.extend(pdf_string(title));
+    info.extend(b" >>");
+    objects.push(info);
+    for font in ["Courier", "Courier-Bold", "Courier-Oblique", "Courier-BoldOblique"] {
+        objects.push(format!(
+            "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+            font,
+        ).into_bytes());
+    }
+    for (i, rows) in pages.iter().enumerate() {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+             /Resources << /Font << /F1 4 0 R /F2 5 0 R /F3 6 0 R /F4 7 0 R >> >> /Contents {} 0 R >>",
+            PAGE_WIDTH, PAGE_HEIGHT, first_page + 2 * i + 1,
+        ).into_bytes());
+        let content = pdf_page(rows, palette, scheme, font_size);
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend(content);
+        stream.extend(b"\nendstream");
+        objects.push(stream);
+    }
+
+    let mut out = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.extend(format!("{} 0 obj\n", i + 1).into_bytes());
+        out.extend(object);
+        out.extend(b"\nendobj\n");
+    }
+    let xref = out.len();
+    out.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).into_bytes());
+    for offset in offsets {
+        out.extend(format!("{:010} 00000 n \n", offset).into_bytes());
+    }
+    out.extend(format!(
+        "trailer\n<< /Size {} /Root 1 0 R /Info 3 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1, xref,
+    ).into_bytes());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(text: &str) -> Vec<ExportCell> {
+        text.chars().map(ExportCell::plain).collect()
+    }
+
+    #[test]
+    fn test_html_keeps_colors_and_escapes() {
+        let mut line = row("a<b  ");
+        line[2].fg = Some([255, 0, 0]);
+        line[2].bold = true;
+        let html = to_html(&[line, row("x & y")], &ExportPalette::default(), ExportScheme::Theme, "web01");
+
+        assert!(html.contains("<title>web01</title>"));
+        assert!(html.contains("a&lt;<span style=\"color: #ff0000; font-weight: bold\">b</span>\nx &amp; y</pre>"));
+    }
+
+    #[test]
+    fn test_printer_scheme_darkens_light_text() {
+        let mut cell = ExportCell::plain('x');
+        cell.fg = Some([229, 229, 229]);
+        let style = resolve(&cell, &ExportPalette::default(), ExportScheme::Printer);
+        assert!(luminance(style.fg) < 0.55);
+        assert_eq!(resolve(&ExportCell::plain('x'), &ExportPalette::default(), ExportScheme::Printer).fg, [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_pdf_structure() {
+        let rows: Vec<_> = (0..200).map(|i| row(&format!("line {} (ok) ─", i))).collect();
+        let pdf = to_pdf(&rows, &ExportPalette::default(), ExportScheme::Printer, "log");
+        let text = String::from_utf8_lossy(&pdf);
+
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.ends_with("%%EOF\n"));
+        assert!(text.contains("/Count 3"));
+        assert!(text.contains("(line 0 \\(ok\\) -) Tj"));
+
+        // startxref points at the xref table
+        let start: usize = text.rsplit("startxref\n").next().unwrap().lines().next().unwrap().parse().unwrap();
+        assert!(text[start..].starts_with("xref"));
+    }
+}
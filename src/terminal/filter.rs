@@ -0,0 +1,133 @@
+//! Output filtering ("grep mode")
+//!
+//! Picks the lines of the scrollback and screen that match a pattern, plus
+//! optional context around them, without touching the buffer itself.
+
+use anyhow::{Context, Result};
+use regex::{Regex, RegexBuilder};
+
+/// How to match lines
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FilterOptions {
+    pub pattern: String,
+    pub regex: bool,
+    pub case_sensitive: bool,
+    /// Show the lines that do not match instead
+    pub invert: bool,
+    /// Lines shown before and after each match
+    pub context: usize,
+}
+
+/// A row of the filtered view
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilteredLine {
+    /// Buffer line by absolute index; `matched` is false for context lines
+    Line { index: usize, matched: bool },
+    /// Lines left out between two groups
+    Gap,
+}
+
+/// Compiled filter
+pub struct LineFilter {
+    regex: Regex,
+    invert: bool,
+    context: usize,
+}
+
+impl LineFilter {
+    pub fn new(options: &FilterOptions) -> Result<Self> {
+        let pattern = if options.regex {
+            options.pattern.clone()
+        } else {
+            regex::escape(&options.pattern)
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(!options.case_sensitive)
+            .build()
+            .context("Invalid pattern")?;
+        Ok(Self { regex, invert: options.invert, context: options.context })
+    }
+
+    pub fn is_match(&self, line: &str) -> bool {
+        self.regex.is_match(line) != self.invert
+    }
+
+    /// Rows to show for the given lines, in order, like `grep -C`
+    pub fn apply<S: AsRef<str>>(&self, lines: &[S]) -> Vec<FilteredLine> {
+        let mut rows = Vec::new();
+        // First line not yet emitted
+        let mut next = 0;
+
+        for (index, line) in lines.iter().enumerate() {
+            if !self.is_match(line.as_ref()) {
+                continue;
+            }
+            let start = index.saturating_sub(self.context).max(next);
+            if start > next && !rows.is_empty() {
+                rows.push(FilteredLine::Gap);
+            }
+            for before in start..index {
+                rows.push(FilteredLine::Line { index: before, matched: false });
+            }
+            rows.push(FilteredLine::Line { index, matched: true });
+            next = index + 1;
+
+            // Trailing context is emitted lazily so overlapping groups merge
+            let end = (index + 1 + self.context).min(lines.len());
+            for after in next..end {
+                if self.is_match(lines[after].as_ref()) {
+                    break;
+                }
+                rows.push(FilteredLine::Line { index: after, matched: false });
+                next = after + 1;
+            }
+        }
+
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines() -> Vec<&'static str> {
+        vec!["boot", "ERROR disk", "retry", "ok", "ok", "ok", "error again", "done"]
+    }
+
+    #[test]
+    fn test_context_and_gaps() {
+        let filter = LineFilter::new(&FilterOptions {
+            pattern: "error".to_string(),
+            context: 1,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let shown: Vec<_> = filter.apply(&lines()).into_iter().map(|row| match row {
+            FilteredLine::Line { index, matched } => format!("{}{}", index, if matched { "*" } else { "" }),
+            FilteredLine::Gap => "--".to_string(),
+        }).collect();
+        assert_eq!(shown, vec!["0", "1*", "2", "--", "5", "6*", "7"]);
+    }
+
+    #[test]
+    fn test_plain_text_and_invert() {
+        let literal = LineFilter::new(&FilterOptions { pattern: "a.b".to_string(), ..Default::default() }).unwrap();
+        assert!(literal.is_match("x a.b y"));
+        assert!(!literal.is_match("axb"));
+
+        let inverted = LineFilter::new(&FilterOptions {
+            pattern: "ok".to_string(),
+            case_sensitive: true,
+            invert: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let rows = inverted.apply(&lines());
+        assert_eq!(rows.len(), 6);
+        assert_eq!(rows[3], FilteredLine::Gap);
+
+        assert!(LineFilter::new(&FilterOptions { pattern: "(".to_string(), regex: true, ..Default::default() }).is_err());
+    }
+}
@@ -0,0 +1,108 @@
+//! Guess whether a session has a foreground job running
+//!
+//! With shell integration the OSC 133 marks say so: a command runs from
+//! its `C` mark until `D`. Without them the guess goes by the screen: a
+//! full-screen program on the alternate screen, or output that kept coming
+//! well after the user last typed. Closing such a tab asks first.
+
+use crate::utils::helpers::format_idle;
+use std::time::Duration;
+
+/// Output this recent counts as a running job when there are no marks
+pub const RECENT_OUTPUT: Duration = Duration::from_secs(5);
+/// Output this soon after a keystroke is taken to be its echo
+const ECHO_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ForegroundJob {
+    /// Reported by shell integration, with the command line when known
+    Command { command: Option<String>, running_for: Duration },
+    /// vim, top, less and the like
+    FullScreen,
+    /// No marks, but output arrived this long ago
+    RecentOutput(Duration),
+}
+
+impl std::fmt::Display for ForegroundJob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ForegroundJob::Command { command: Some(command), running_for } => {
+                write!(f, "`{}` running for {}", command, format_idle(*running_for))
+            }
+            ForegroundJob::Command { command: None, running_for } => {
+                write!(f, "a command running for {}", format_idle(*running_for))
+            }
+            ForegroundJob::FullScreen => write!(f, "a full-screen program"),
+            ForegroundJob::RecentOutput(ago) if ago.as_secs() == 0 => write!(f, "output right now"),
+            ForegroundJob::RecentOutput(ago) => write!(f, "output {}s ago", ago.as_secs()),
+        }
+    }
+}
+
+/// What a terminal knows about its session right now
+#[derive(Debug, Clone, Default)]
+pub struct Activity {
+    /// Whether the shell sends OSC 133 marks
+    pub shell_integration: bool,
+    /// Command line and how long it has run, while a command runs
+    pub running: Option<(Option<String>, Duration)>,
+    pub alternate_screen: bool,
+    /// Time since the last output and the last keystroke
+    pub output_ago: Option<Duration>,
+    pub input_ago: Option<Duration>,
+}
+
+pub fn guess(activity: &Activity) -> Option<ForegroundJob> {
+    if let Some((command, running_for)) = &activity.running {
+        return Some(ForegroundJob::Command { command: command.clone(), running_for: *running_for });
+    }
+    if activity.alternate_screen {
+        return Some(ForegroundJob::FullScreen);
+    }
+    if activity.shell_integration {
+        // Back at the prompt, as far as the marks tell
+        return None;
+    }
+
+    let output_ago = activity.output_ago.filter(|ago| *ago < RECENT_OUTPUT)?;
+    let echo = activity
+        .input_ago
+        .is_some_and(|input_ago| input_ago >= output_ago && input_ago - output_ago <= ECHO_DELAY);
+    (!echo).then_some(ForegroundJob::RecentOutput(output_ago))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(s: f32) -> Option<Duration> {
+        Some(Duration::from_secs_f32(s))
+    }
+
+    #[test]
+    fn test_guess() {
+        let running = Activity {
+            shell_integration: true,
+            running: Some((Some("make".to_string()), Duration::from_secs(90))),
+            ..Default::default()
+        };
+        assert_eq!(guess(&running).unwrap().to_string(), "`make` running for 1m 30s");
+
+        let at_prompt = Activity { shell_integration: true, output_ago: secs(0.1), ..Default::default() };
+        assert_eq!(guess(&at_prompt), None);
+
+        let vim = Activity { alternate_screen: true, output_ago: secs(60.0), ..Default::default() };
+        assert_eq!(guess(&vim), Some(ForegroundJob::FullScreen));
+
+        // Output keeps coming long after Enter
+        let tail = Activity { output_ago: secs(0.2), input_ago: secs(30.0), ..Default::default() };
+        assert!(matches!(guess(&tail), Some(ForegroundJob::RecentOutput(_))));
+
+        // Only the echo of what was typed
+        let typing = Activity { output_ago: secs(1.0), input_ago: secs(1.1), ..Default::default() };
+        assert_eq!(guess(&typing), None);
+
+        let quiet = Activity { output_ago: secs(10.0), ..Default::default() };
+        assert_eq!(guess(&quiet), None);
+    }
+}
@@ -0,0 +1,418 @@
+//! Keyboard input encoding
+//!
+//! Turns egui key events into the bytes a terminal application expects,
+//! following the modes it has switched on:
+//!
+//! - DECCKM (`CSI ? 1 h`) - cursor keys send `SS3` instead of `CSI`
+//! - DECKPAM (`ESC =`) - application keypad (tracked only, see [`InputModes`])
+//! - modifyOtherKeys (`CSI > 4 ; n m`) - modified keys send `CSI 27 ; m ; code ~`
+//!
+//! Modified cursor and function keys always use xterm's `CSI 1 ; m X` form,
+//! where `m` is 1 + Shift(1) + Alt(2) + Ctrl(4).
+//!
+//! egui reports a printable key twice: as `Event::Key` and as `Event::Text`
+//! carrying the character the platform produced. Text is the source of truth
+//! for characters, so [`encode_key`] returns `None` for Alt+letter and
+//! [`encode_text`] adds the ESC prefix instead. When a modified key goes out
+//! in an extended form (modifyOtherKeys level 2 or CSI-u), [`KeyEvents`]
+//! drops the text that follows it. On macOS the Option key
+//! composes characters (Option+e then e gives "é"), so prefixing is off by
+//! default there and the composed text is sent unchanged.
+
+use egui::{Key, Modifiers};
+
+/// Input modes requested by the remote application
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InputModes {
+    /// DECCKM application cursor keys
+    pub application_cursor: bool,
+    /// DECKPAM application keypad. egui does not say which keys came from
+    /// the numeric keypad, so keypad keys are still sent as their
+    /// main-keyboard equivalents; the mode is tracked for completeness.
+    pub application_keypad: bool,
+    /// xterm modifyOtherKeys level (0 = off, 1 = ambiguous keys only, 2 = all)
+    pub modify_other_keys: u8,
+    /// Mode 1004: report focus changes as `CSI I` / `CSI O`
+    pub focus_reporting: bool,
+}
+
+/// Sequence reporting a focus change, for applications that enabled mode 1004
+pub fn focus_report(focused: bool) -> &'static [u8] {
+    if focused {
+        b"\x1b[I"
+    } else {
+        b"\x1b[O"
+    }
+}
+
+/// How modified keys that have no legacy encoding are sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ModifiedKeyEncoding {
+    /// Follow the application's modifyOtherKeys request
+    #[default]
+    Auto,
+    /// Always send `CSI code ; m u` (fixterms / CSI-u)
+    CsiU,
+}
+
+/// User preferences for keyboard encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyboardOptions {
+    pub encoding: ModifiedKeyEncoding,
+    /// Alt+key sends ESC followed by the key, as in xterm's metaSendsEscape
+    pub alt_sends_escape: bool,
+}
+
+impl Default for KeyboardOptions {
+    fn default() -> Self {
+        Self {
+            encoding: ModifiedKeyEncoding::Auto,
+            alt_sends_escape: default_alt_sends_escape(),
+        }
+    }
+}
+
+/// Alt sends ESC everywhere except macOS, where Option composes characters
+pub fn default_alt_sends_escape() -> bool {
+    !cfg!(target_os = "macos")
+}
+
+/// Encode text from an `egui::Event::Text`, given the modifiers held
+pub fn encode_text(text: &str, modifiers: &Modifiers, options: &KeyboardOptions) -> Vec<u8> {
+    if options.alt_sends_escape && modifiers.alt && !modifiers.ctrl {
+        let mut bytes = Vec::with_capacity(text.len() + 1);
+        bytes.push(0x1b);
+        bytes.extend_from_slice(text.as_bytes());
+        return bytes;
+    }
+    text.as_bytes().to_vec()
+}
+
+/// xterm modifier parameter, or `None` when no modifier is held
+fn modifier_param(modifiers: &Modifiers) -> Option<u8> {
+    let mut value = 0;
+    if modifiers.shift {
+        value |= 1;
+    }
+    if modifiers.alt {
+        value |= 2;
+    }
+    if modifiers.ctrl {
+        value |= 4;
+    }
+    (value != 0).then_some(value + 1)
+}
+
+/// Final byte of a cursor or SS3-style key
+fn cursor_final(key: Key) -> Option<u8> {
+    Some(match key {
+        Key::ArrowUp => b'A',
+        Key::ArrowDown => b'B',
+        Key::ArrowRight => b'C',
+        Key::ArrowLeft => b'D',
+        Key::Home => b'H',
+        Key::End => b'F',
+        Key::F1 => b'P',
+        Key::F2 => b'Q',
+        Key::F3 => b'R',
+        Key::F4 => b'S',
+        _ => return None,
+    })
+}
+
+/// Number of a `CSI n ~` key
+fn tilde_code(key: Key) -> Option<u8> {
+    Some(match key {
+        Key::Insert => 2,
+        Key::Delete => 3,
+        Key::PageUp => 5,
+        Key::PageDown => 6,
+        Key::F5 => 15,
+        Key::F6 => 17,
+        Key::F7 => 18,
+        Key::F8 => 19,
+        Key::F9 => 20,
+        Key::F10 => 21,
+        Key::F11 => 23,
+        Key::F12 => 24,
+        _ => return None,
+    })
+}
+
+/// Unicode code point of a printable key, for modifyOtherKeys and CSI-u
+fn key_codepoint(key: Key) -> Option<u32> {
+    let name = key.name();
+    if name.len() == 1 {
+        return name.chars().next().map(|c| c.to_ascii_lowercase() as u32);
+    }
+    Some(match key {
+        Key::Enter => 13,
+        Key::Tab => 9,
+        Key::Backspace => 127,
+        Key::Escape => 27,
+        Key::Space => 32,
+        Key::Minus => b'-' as u32,
+        Key::Plus => b'+' as u32,
+        Key::Equals => b'=' as u32,
+        Key::Comma => b',' as u32,
+        Key::Period => b'.' as u32,
+        Key::Colon => b':' as u32,
+        Key::Semicolon => b';' as u32,
+        Key::Backslash => b'\\' as u32,
+        Key::OpenBracket => b'[' as u32,
+        Key::CloseBracket => b']' as u32,
+        Key::Backtick => b'`' as u32,
+        _ => return None,
+    })
+}
+
+/// Keys that move around without changing anything: cursor keys, Home,
+/// End and the page keys. Synchronized panes repeat these to each other.
+pub fn is_navigation_key(key: Key) -> bool {
+    matches!(
+        key,
+        Key::ArrowUp | Key::ArrowDown | Key::ArrowLeft | Key::ArrowRight
+            | Key::Home | Key::End | Key::PageUp | Key::PageDown
+    )
+}
+
+/// Encode a key press, or `None` if the key produces no input.
+///
+/// Plain printable characters arrive as `egui::Event::Text` and are not
+/// handled here.
+pub fn encode_key(
+    key: Key,
+    modifiers: &Modifiers,
+    modes: &InputModes,
+    options: &KeyboardOptions,
+) -> Option<Vec<u8>> {
+    let modifier = modifier_param(modifiers);
+
+    // Cursor keys, Home/End and F1-F4
+    if let Some(final_byte) = cursor_final(key) {
+        let is_function = matches!(key, Key::F1 | Key::F2 | Key::F3 | Key::F4);
+        return Some(match modifier {
+            Some(m) => format!("\x1b[1;{}{}", m, final_byte as char).into_bytes(),
+            None if is_function || modes.application_cursor => vec![0x1b, b'O', final_byte],
+            None => vec![0x1b, b'[', final_byte],
+        });
+    }
+
+    if let Some(code) = tilde_code(key) {
+        return Some(match modifier {
+            Some(m) => format!("\x1b[{};{}~", code, m).into_bytes(),
+            None => format!("\x1b[{}~", code).into_bytes(),
+        });
+    }
+
+    let modifier = match modifier {
+        Some(m) => m,
+        None => {
+            return match key {
+                Key::Enter => Some(vec![0x0D]),
+                Key::Tab => Some(vec![0x09]),
+                Key::Backspace => Some(vec![0x7F]),
+                Key::Escape => Some(vec![0x1B]),
+                _ => None,
+            };
+        }
+    };
+
+    let codepoint = key_codepoint(key)?;
+    let shift_only = modifier == 2;
+    let ctrl = modifiers.ctrl;
+
+    // Extended encodings, when asked for
+    let extended = match options.encoding {
+        ModifiedKeyEncoding::CsiU => Some(format!("\x1b[{};{}u", codepoint, modifier)),
+        ModifiedKeyEncoding::Auto => {
+            let ambiguous = (ctrl && legacy_control(key).is_none())
+                || matches!(key, Key::Enter | Key::Tab | Key::Backspace | Key::Escape);
+            match modes.modify_other_keys {
+                2 => Some(format!("\x1b[27;{};{}~", modifier, codepoint)),
+                1 if ambiguous => Some(format!("\x1b[27;{};{}~", modifier, codepoint)),
+                _ => None,
+            }
+        }
+    };
+    if let Some(sequence) = extended {
+        // Shift on a printable key is just the shifted character
+        if !(shift_only && key_codepoint(key).map_or(false, |c| c > 32 && c < 127)) {
+            return Some(sequence.into_bytes());
+        }
+    }
+
+    // Legacy encodings
+    if ctrl {
+        if let Some(byte) = legacy_control(key) {
+            return Some(if modifiers.alt && options.alt_sends_escape { vec![0x1b, byte] } else { vec![byte] });
+        }
+    }
+    let bytes = match key {
+        Key::Tab if modifiers.shift => b"\x1b[Z".to_vec(),
+        Key::Enter => vec![0x0D],
+        Key::Tab => vec![0x09],
+        Key::Backspace if ctrl => vec![0x08],
+        Key::Backspace => vec![0x7F],
+        Key::Escape => vec![0x1B],
+        _ => return None,
+    };
+    if modifiers.alt && options.alt_sends_escape {
+        let mut prefixed = vec![0x1b];
+        prefixed.extend(bytes);
+        return Some(prefixed);
+    }
+    Some(bytes)
+}
+
+/// Whether egui also reports this key press as `Event::Text`. Ctrl and
+/// Command chords produce no text.
+fn text_follows(key: Key, modifiers: &Modifiers) -> bool {
+    !modifiers.ctrl && !modifiers.command && key_codepoint(key).map_or(false, |c| (32..127).contains(&c))
+}
+
+/// Encodes one frame's key and text events in the order egui reports them
+#[derive(Debug, Default)]
+pub struct KeyEvents {
+    /// The last key was sent encoded, so its text must not be sent again
+    skip_text: bool,
+}
+
+impl KeyEvents {
+    /// Bytes for an `Event::Key` press, as [`encode_key`]
+    pub fn key(
+        &mut self,
+        key: Key,
+        modifiers: &Modifiers,
+        modes: &InputModes,
+        options: &KeyboardOptions,
+    ) -> Option<Vec<u8>> {
+        let data = encode_key(key, modifiers, modes, options);
+        self.skip_text = data.is_some() && text_follows(key, modifiers);
+        data
+    }
+
+    /// Bytes for an `Event::Text`, or `None` when its key was already sent
+    pub fn text(&mut self, text: &str, modifiers: &Modifiers, options: &KeyboardOptions) -> Option<Vec<u8>> {
+        if std::mem::take(&mut self.skip_text) {
+            return None;
+        }
+        Some(encode_text(text, modifiers, options))
+    }
+}
+
+/// C0 control byte for Ctrl+key, where one exists
+fn legacy_control(key: Key) -> Option<u8> {
+    let name = key.name();
+    let c = name.chars().next()?;
+    if name.len() == 1 && c.is_ascii_alphabetic() {
+        return Some(c.to_ascii_uppercase() as u8 - b'A' + 1);
+    }
+    match key {
+        Key::Space => Some(0x00),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctrl() -> Modifiers {
+        Modifiers { ctrl: true, ..Default::default() }
+    }
+
+    fn encode(key: Key, modifiers: Modifiers, modes: InputModes) -> Vec<u8> {
+        let options = KeyboardOptions { encoding: ModifiedKeyEncoding::Auto, alt_sends_escape: true };
+        encode_key(key, &modifiers, &modes, &options).unwrap()
+    }
+
+    #[test]
+    fn test_cursor_keys_follow_decckm() {
+        let normal = InputModes::default();
+        let app = InputModes { application_cursor: true, ..Default::default() };
+        assert_eq!(encode(Key::ArrowUp, Modifiers::NONE, normal), b"\x1b[A");
+        assert_eq!(encode(Key::ArrowUp, Modifiers::NONE, app), b"\x1bOA");
+        assert_eq!(encode(Key::Home, Modifiers::NONE, app), b"\x1bOH");
+    }
+
+    #[test]
+    fn test_modified_special_keys() {
+        let modes = InputModes::default();
+        assert_eq!(encode(Key::ArrowRight, ctrl(), modes), b"\x1b[1;5C");
+        assert_eq!(encode(Key::ArrowLeft, Modifiers::SHIFT, modes), b"\x1b[1;2D");
+        assert_eq!(encode(Key::Delete, ctrl(), modes), b"\x1b[3;5~");
+        assert_eq!(encode(Key::F5, Modifiers::NONE, modes), b"\x1b[15~");
+        assert_eq!(encode(Key::Tab, Modifiers::SHIFT, modes), b"\x1b[Z");
+    }
+
+    #[test]
+    fn test_ctrl_letters() {
+        let modes = InputModes::default();
+        assert_eq!(encode(Key::C, ctrl(), modes), vec![0x03]);
+        assert_eq!(encode(Key::A, ctrl(), modes), vec![0x01]);
+    }
+
+    #[test]
+    fn test_modify_other_keys() {
+        let level2 = InputModes { modify_other_keys: 2, ..Default::default() };
+        assert_eq!(encode(Key::C, ctrl(), level2), b"\x1b[27;5;99~");
+
+        let level1 = InputModes { modify_other_keys: 1, ..Default::default() };
+        // Ctrl+C has a control byte, so level 1 keeps the legacy form
+        assert_eq!(encode(Key::C, ctrl(), level1), vec![0x03]);
+        assert_eq!(encode(Key::Enter, ctrl(), level1), b"\x1b[27;5;13~");
+        assert_eq!(encode(Key::Equals, ctrl(), level2), b"\x1b[27;5;61~");
+        assert_eq!(encode(Key::Comma, ctrl(), level2), b"\x1b[27;5;44~");
+    }
+
+    #[test]
+    fn test_csi_u_option() {
+        let options = KeyboardOptions { encoding: ModifiedKeyEncoding::CsiU, alt_sends_escape: true };
+        let bytes = encode_key(Key::Enter, &Modifiers::SHIFT, &InputModes::default(), &options);
+        assert_eq!(bytes.unwrap(), b"\x1b[13;2u");
+    }
+
+    #[test]
+    fn test_alt_sends_escape() {
+        let on = KeyboardOptions { encoding: ModifiedKeyEncoding::Auto, alt_sends_escape: true };
+        let off = KeyboardOptions { alt_sends_escape: false, ..on };
+
+        // Letters come through the text event
+        assert_eq!(encode_key(Key::B, &Modifiers::ALT, &InputModes::default(), &on), None);
+        assert_eq!(encode_text("b", &Modifiers::ALT, &on), b"\x1bb");
+        assert_eq!(encode_text("\u{e5}", &Modifiers::ALT, &off), "\u{e5}".as_bytes());
+
+        assert_eq!(encode(Key::Backspace, Modifiers::ALT, InputModes::default()), b"\x1b\x7f");
+        assert_eq!(encode_key(Key::Backspace, &Modifiers::ALT, &InputModes::default(), &off).unwrap(), vec![0x7f]);
+    }
+
+    /// What a key press and the text egui reports with it send together
+    fn typed(key: Key, text: &str, modifiers: Modifiers, modes: InputModes, options: KeyboardOptions) -> Vec<u8> {
+        let mut events = KeyEvents::default();
+        let mut bytes = events.key(key, &modifiers, &modes, &options).unwrap_or_default();
+        bytes.extend(events.text(text, &modifiers, &options).unwrap_or_default());
+        bytes
+    }
+
+    #[test]
+    fn test_chords_sent_once_with_modify_other_keys() {
+        let options = KeyboardOptions { encoding: ModifiedKeyEncoding::Auto, alt_sends_escape: true };
+        let level2 = InputModes { modify_other_keys: 2, ..Default::default() };
+        assert_eq!(typed(Key::B, "b", Modifiers::ALT, level2, options), b"\x1b[27;3;98~");
+        assert_eq!(typed(Key::Space, " ", Modifiers::SHIFT, level2, options), b"\x1b[27;2;32~");
+        // Shift on a letter is only the shifted character
+        assert_eq!(typed(Key::B, "B", Modifiers::SHIFT, level2, options), b"B");
+        // Without the mode, Alt comes through the text with an ESC prefix
+        assert_eq!(typed(Key::B, "b", Modifiers::ALT, InputModes::default(), options), b"\x1bb");
+    }
+
+    #[test]
+    fn test_chords_sent_once_with_csi_u() {
+        let options = KeyboardOptions { encoding: ModifiedKeyEncoding::CsiU, alt_sends_escape: true };
+        let modes = InputModes::default();
+        assert_eq!(typed(Key::B, "b", Modifiers::ALT, modes, options), b"\x1b[98;3u");
+        assert_eq!(typed(Key::Comma, "<", Modifiers::ALT | Modifiers::SHIFT, modes, options), b"\x1b[44;4u");
+        assert_eq!(typed(Key::B, "B", Modifiers::SHIFT, modes, options), b"B");
+    }
+}
@@ -1,6 +1,21 @@
 //! Terminal emulation
 
+pub mod approval;
+pub mod benchmark;
+pub mod capture;
+pub mod charset;
+pub mod command_timer;
 pub mod emulator;
+pub mod escapes;
+pub mod export;
+pub mod filter;
+pub mod foreground;
+pub mod input;
+pub mod prediction;
+pub mod scratchpad;
+pub mod share;
+pub mod status;
+pub mod triggers;
 pub mod vt;
 
 pub use emulator::TerminalEmulator;
@@ -1,9 +1,24 @@
 //! Terminal parser using vte crate for ANSI escape sequence handling
 
 use super::buffer::TerminalBuffer;
+use super::command_timer::{format_command_duration, CommandTimer, ShellMark};
+use super::input::InputModes;
 use super::Color;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
 use vte::{Params, Perform};
 
+/// End of a synchronized update (DECRST 2026)
+const SYNC_END: &[u8] = b"\x1b[?2026l";
+/// Longest an application may hold back rendering with mode 2026
+const SYNC_TIMEOUT: Duration = Duration::from_millis(150);
+/// Most bytes buffered during one synchronized update
+const SYNC_MAX_BYTES: usize = 2 * 1024 * 1024;
+/// Kinds of unhandled sequence counted one by one; the rest are lumped
+/// together, since the server picks the kinds
+const MAX_UNHANDLED_KINDS: usize = 64;
+const OTHER_UNHANDLED: &str = "other";
+
 /// Standard ANSI colors
 const ANSI_COLORS: [Color; 8] = [
     Color::rgb(0, 0, 0),
@@ -32,6 +47,57 @@ const ANSI_BRIGHT_COLORS: [Color; 8] = [
 pub struct TerminalParser {
     buffer: TerminalBuffer,
     parser: vte::Parser,
+    input_modes: InputModes,
+    /// Sent in reply to ENQ
+    answerback: String,
+    /// Replies to the host waiting to be sent
+    responses: Vec<u8>,
+    /// Set by the performer when the application begins a synchronized update
+    sync_requested: bool,
+    /// Output held back during a synchronized update, and when it began
+    sync_pending: Option<(Instant, Vec<u8>)>,
+    /// Command durations from shell-integration marks
+    command_timer: CommandTimer,
+    /// Note the duration after commands that ran at least this long
+    annotate_after: Option<Duration>,
+    /// Latest OSC 0/2 title, until taken
+    title: Option<String>,
+    /// Where the command line begins (line counted with trimmed scrollback,
+    /// column) from the last `B` mark, until output starts
+    command_start: Option<(usize, usize)>,
+    /// Command line of the command running since the `C` mark
+    running_command: Option<String>,
+    /// Sequences received but not acted on, e.g. `CSI c` or `OSC 52`, with counts
+    unhandled: Unhandled,
+    /// Bumped whenever the buffer may have changed
+    generation: u64,
+}
+
+/// Counts of sequences received but not acted on, by kind
+#[derive(Default)]
+struct Unhandled {
+    counts: BTreeMap<String, u64>,
+    /// Reused to name each sequence, so known kinds cost no allocation
+    key: String,
+}
+
+impl Unhandled {
+    fn count(&mut self, what: std::fmt::Arguments) {
+        use std::fmt::Write;
+        self.key.clear();
+        let _ = self.key.write_fmt(what);
+        let key = if self.counts.contains_key(&self.key) || self.counts.len() < MAX_UNHANDLED_KINDS {
+            self.key.as_str()
+        } else {
+            OTHER_UNHANDLED
+        };
+        match self.counts.get_mut(key) {
+            Some(count) => *count += 1,
+            None => {
+                self.counts.insert(key.to_string(), 1);
+            }
+        }
+    }
 }
 
 impl TerminalParser {
@@ -39,18 +105,112 @@ impl TerminalParser {
         Self {
             buffer: TerminalBuffer::new(cols, rows, scrollback),
             parser: vte::Parser::new(),
+            input_modes: InputModes::default(),
+            answerback: String::new(),
+            responses: Vec::new(),
+            sync_requested: false,
+            sync_pending: None,
+            command_timer: CommandTimer::new(),
+            annotate_after: None,
+            title: None,
+            command_start: None,
+            running_command: None,
+            unhandled: Unhandled::default(),
+            generation: 0,
         }
     }
 
-    /// Process input bytes
+    /// Process input bytes.
+    ///
+    /// While the application has a synchronized update open (mode 2026),
+    /// bytes are held back and applied in one go once it ends, so a frame
+    /// never shows a half-drawn screen.
     pub fn process(&mut self, data: &[u8]) {
-        let mut performer = TerminalPerformer {
-            buffer: &mut self.buffer,
-        };
+        if let Some((_, pending)) = &mut self.sync_pending {
+            // Only the new bytes (plus a marker split across reads) need scanning
+            let scan_from = pending.len().saturating_sub(SYNC_END.len() - 1);
+            pending.extend_from_slice(data);
+            let ended = pending[scan_from..].windows(SYNC_END.len()).any(|w| w == SYNC_END);
+            if ended || pending.len() > SYNC_MAX_BYTES {
+                self.flush_sync();
+            }
+            return;
+        }
+        self.apply(data);
+    }
+
+    /// Apply held-back output if the synchronized update has timed out; call once per frame
+    pub fn tick(&mut self) {
+        if matches!(&self.sync_pending, Some((started, _)) if started.elapsed() >= SYNC_TIMEOUT) {
+            log::debug!("Synchronized update timed out");
+            self.flush_sync();
+        }
+    }
+
+    /// Whether a synchronized update is holding back output
+    pub fn is_synchronized(&self) -> bool {
+        self.sync_pending.is_some()
+    }
+
+    fn flush_sync(&mut self) {
+        if let Some((_, pending)) = self.sync_pending.take() {
+            self.apply(&pending);
+        }
+    }
 
-        for byte in data {
+    /// Run `data` through the parser. Synchronized updates that begin and
+    /// end within `data` are applied in the same pass; one still open at
+    /// the end holds back the rest of `data`, copied once.
+    fn apply(&mut self, data: &[u8]) {
+        self.generation = self.generation.wrapping_add(1);
+        let mut start = 0;
+        // Start of the next end marker at or after `start`, once searched for
+        let mut next_end: Option<usize> = None;
+        while start < data.len() {
+            start += self.advance(&data[start..]);
+            let Some((_, pending)) = &mut self.sync_pending else {
+                continue;
+            };
+            if next_end.map_or(true, |end| end < start) {
+                next_end = data[start..]
+                    .windows(SYNC_END.len())
+                    .position(|w| w == SYNC_END)
+                    .map(|at| start + at);
+            }
+            if next_end.is_none() && data.len() - start <= SYNC_MAX_BYTES {
+                pending.extend_from_slice(&data[start..]);
+                return;
+            }
+            // The update is complete already (or too large to hold): go on
+            self.sync_pending = None;
+        }
+    }
+
+    /// Run bytes through the parser until a synchronized update begins;
+    /// returns how many were used
+    fn advance(&mut self, data: &[u8]) -> usize {
+        for (i, byte) in data.iter().enumerate() {
+            let mut performer = TerminalPerformer {
+                buffer: &mut self.buffer,
+                input_modes: &mut self.input_modes,
+                answerback: &self.answerback,
+                responses: &mut self.responses,
+                sync_requested: &mut self.sync_requested,
+                command_timer: &mut self.command_timer,
+                annotate_after: self.annotate_after,
+                title: &mut self.title,
+                command_start: &mut self.command_start,
+                running_command: &mut self.running_command,
+                unhandled: &mut self.unhandled,
+            };
             self.parser.advance(&mut performer, *byte);
+
+            if std::mem::take(&mut self.sync_requested) {
+                self.sync_pending = Some((Instant::now(), Vec::new()));
+                return i + 1;
+            }
         }
+        data.len()
     }
 
     /// Get the underlying buffer
@@ -60,11 +220,77 @@ impl TerminalParser {
 
     /// Get mutable access to the buffer
     pub fn buffer_mut(&mut self) -> &mut TerminalBuffer {
+        self.generation = self.generation.wrapping_add(1);
         &mut self.buffer
     }
 
+    /// Changes whenever the buffer may have changed, so views can keep
+    /// results derived from it until then
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Set the answerback message sent when the host sends ENQ
+    pub fn set_answerback(&mut self, answerback: String) {
+        self.answerback = answerback;
+    }
+
+    /// Take replies generated while processing (answerback, reports)
+    pub fn take_responses(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.responses)
+    }
+
+    /// Title the remote set since the last call; empty when it cleared it
+    pub fn take_title(&mut self) -> Option<String> {
+        self.title.take()
+    }
+
+    /// Running and last command, if the shell sends integration marks
+    pub fn command_timer(&self) -> &CommandTimer {
+        &self.command_timer
+    }
+
+    /// Forget command timings, e.g. when a new session starts
+    pub fn reset_command_timer(&mut self) {
+        self.command_timer.reset();
+        self.command_start = None;
+        self.running_command = None;
+    }
+
+    /// Command line of the running command, with shell integration
+    pub fn running_command(&self) -> Option<&str> {
+        self.running_command.as_deref()
+    }
+
+    /// The command line typed at a shell prompt so far; `None` outside a
+    /// prompt or without shell integration
+    pub fn command_line(&self) -> Option<String> {
+        command_text(&self.buffer, self.command_start?)
+    }
+
+    /// Print a dimmed "took 12.3s" line after commands that ran at least
+    /// `min` long; `None` turns it off
+    pub fn set_command_annotation(&mut self, min: Option<Duration>) {
+        self.annotate_after = min;
+    }
+
+    /// Sequences the emulator ignored so far, by kind, with how often each came
+    pub fn unhandled(&self) -> &BTreeMap<String, u64> {
+        &self.unhandled.counts
+    }
+
+    pub fn clear_unhandled(&mut self) {
+        self.unhandled.counts.clear();
+    }
+
+    /// Keyboard modes requested by the application
+    pub fn input_modes(&self) -> InputModes {
+        self.input_modes
+    }
+
     /// Resize the terminal
     pub fn resize(&mut self, cols: u16, rows: u16) {
+        self.generation = self.generation.wrapping_add(1);
         self.buffer.resize(cols, rows);
     }
 }
@@ -72,6 +298,27 @@ impl TerminalParser {
 /// VTE performer that applies escape sequences to the buffer
 struct TerminalPerformer<'a> {
     buffer: &'a mut TerminalBuffer,
+    input_modes: &'a mut InputModes,
+    answerback: &'a str,
+    responses: &'a mut Vec<u8>,
+    sync_requested: &'a mut bool,
+    command_timer: &'a mut CommandTimer,
+    annotate_after: Option<Duration>,
+    title: &'a mut Option<String>,
+    command_start: &'a mut Option<(usize, usize)>,
+    running_command: &'a mut Option<String>,
+    unhandled: &'a mut Unhandled,
+}
+
+/// Text from where the command line began through the cursor row
+fn command_text(buffer: &TerminalBuffer, (line, col): (usize, usize)) -> Option<String> {
+    let start = line.checked_sub(buffer.trimmed_lines())?;
+    let (_, y) = buffer.cursor_position();
+    let end = buffer.scrollback_len() + y;
+    let cols = buffer.size().cols as usize;
+    // Through the end of the cursor row, in case the cursor was moved back
+    let text = buffer.text_range((start, col), (end, cols.saturating_sub(1)));
+    Some(text.trim().to_string())
 }
 
 impl<'a> Perform for TerminalPerformer<'a> {
@@ -81,6 +328,7 @@ impl<'a> Perform for TerminalPerformer<'a> {
 
     fn execute(&mut self, byte: u8) {
         match byte {
+            0x05 => self.responses.extend_from_slice(self.answerback.as_bytes()),
             0x07 => {}
             0x08 => {
                 let (x, _) = self.buffer.cursor_position();
@@ -97,17 +345,58 @@ impl<'a> Perform for TerminalPerformer<'a> {
             0x0d => {
                 self.buffer.write_char('\r');
             }
-            _ => {}
+            _ => self.skip(format_args!("C0 0x{:02x}", byte)),
         }
     }
 
-    fn hook(&mut self, _params: &Params, _intermediates: &[u8], _ignore: bool, _c: char) {}
+    fn hook(&mut self, _params: &Params, intermediates: &[u8], _ignore: bool, c: char) {
+        self.skip(format_args!("DCS {}{}", String::from_utf8_lossy(intermediates), c));
+    }
 
     fn put(&mut self, _byte: u8) {}
 
     fn unhook(&mut self) {}
 
-    fn osc_dispatch(&mut self, _params: &[&[u8]], _bell_terminated: bool) {}
+    fn osc_dispatch(&mut self, params: &[&[u8]], _bell_terminated: bool) {
+        // OSC 0 sets icon name and title, OSC 2 the title; a title may contain ';'
+        if let [b"0" | b"2", title @ ..] = params {
+            let title = title.iter()
+                .map(|part| String::from_utf8_lossy(part))
+                .collect::<Vec<_>>()
+                .join(";");
+            *self.title = Some(title.chars().filter(|c| !c.is_control()).collect());
+            return;
+        }
+        let Some(mark) = ShellMark::parse(params) else {
+            // Numbers only, so text the server puts there can't name kinds
+            match params.first().filter(|n| (1..=5).contains(&n.len()) && n.iter().all(u8::is_ascii_digit)) {
+                Some(number) => self.skip(format_args!("OSC {}", String::from_utf8_lossy(number))),
+                None => self.skip(format_args!("OSC ?")),
+            }
+            return;
+        };
+        *self.running_command = match mark {
+            ShellMark::OutputStart => self.command_start
+                .and_then(|start| command_text(self.buffer, start))
+                .filter(|command| !command.is_empty()),
+            _ => None,
+        };
+        *self.command_start = match mark {
+            ShellMark::CommandStart => {
+                let (x, y) = self.buffer.cursor_position();
+                Some((self.buffer.trimmed_lines() + self.buffer.scrollback_len() + y, x))
+            }
+            _ => None,
+        };
+        let Some(finished) = self.command_timer.on_mark(mark, Instant::now()) else { return };
+        if self.annotate_after.is_some_and(|min| finished.duration >= min) {
+            let exit = match finished.exit_code {
+                Some(code) if code != 0 => format!(", exit {}", code),
+                _ => String::new(),
+            };
+            self.buffer.write_annotation(&format!("took {}{}", format_command_duration(finished.duration), exit));
+        }
+    }
 
     fn csi_dispatch(&mut self, params: &Params, intermediates: &[u8], _ignore: bool, c: char) {
         let params: Vec<u16> = params.iter().map(|p| p[0]).collect();
@@ -201,6 +490,12 @@ impl<'a> Perform for TerminalPerformer<'a> {
                 let (x, _) = self.buffer.cursor_position();
                 self.buffer.set_cursor(x, row);
             }
+            'm' if intermediates.contains(&b'>') => {
+                // XTMODKEYS: only modifyOtherKeys (resource 4) is supported
+                if param(0, 0) == 4 {
+                    self.input_modes.modify_other_keys = param(1, 0).min(2) as u8;
+                }
+            }
             'm' => {
                 self.handle_sgr(&params);
             }
@@ -215,13 +510,25 @@ impl<'a> Perform for TerminalPerformer<'a> {
             'u' => {
                 self.buffer.restore_cursor();
             }
+            'p' if intermediates == b"?$" => {
+                // DECRQM: 1 = set, 2 = reset, 0 = not recognized
+                let mode = param(0, 0);
+                let state = match mode {
+                    1 => if self.input_modes.application_cursor { 1 } else { 2 },
+                    1004 => if self.input_modes.focus_reporting { 1 } else { 2 },
+                    // Held-back output is applied after the update ends
+                    2026 => 2,
+                    _ => 0,
+                };
+                self.responses.extend_from_slice(format!("\x1b[?{};{}$y", mode, state).as_bytes());
+            }
             'h' => {
                 self.handle_mode(intermediates, &params, true);
             }
             'l' => {
                 self.handle_mode(intermediates, &params, false);
             }
-            _ => {}
+            _ => self.skip(format_args!("CSI {}{}", String::from_utf8_lossy(intermediates), c)),
         }
     }
 
@@ -231,17 +538,25 @@ impl<'a> Perform for TerminalPerformer<'a> {
             ([], b'8') => self.buffer.restore_cursor(),
             ([], b'D') => self.buffer.scroll_up(1),
             ([], b'M') => self.buffer.scroll_down(1),
+            ([], b'=') => self.input_modes.application_keypad = true,
+            ([], b'>') => self.input_modes.application_keypad = false,
             ([], b'c') => {
                 self.buffer.clear();
                 self.buffer.reset_attrs();
                 self.buffer.set_cursor(0, 0);
+                *self.input_modes = InputModes::default();
             }
-            _ => {}
+            _ => self.skip(format_args!("ESC {}{}", String::from_utf8_lossy(intermediates), byte as char)),
         }
     }
 }
 
 impl<'a> TerminalPerformer<'a> {
+    /// Count a sequence that was received but not acted on
+    fn skip(&mut self, what: std::fmt::Arguments) {
+        self.unhandled.count(what);
+    }
+
     /// Handle SGR (Select Graphic Rendition) sequences
     fn handle_sgr(&mut self, params: &[u16]) {
         if params.is_empty() {
@@ -377,7 +692,7 @@ impl<'a> TerminalPerformer<'a> {
                     let color_idx = (params[i] - 100) as usize;
                     self.buffer.set_bg(ANSI_BRIGHT_COLORS[color_idx]);
                 }
-                _ => {}
+                other => self.skip(format_args!("SGR {}", other)),
             }
             i += 1;
         }
@@ -410,10 +725,12 @@ impl<'a> TerminalPerformer<'a> {
         for param in params {
             if is_dec {
                 match *param {
-                    1 => {}
+                    1 => self.input_modes.application_cursor = enable,
                     6 => self.buffer.set_origin_mode(enable),
                     7 => self.buffer.set_auto_wrap(enable),
                     25 => {}
+                    1004 => self.input_modes.focus_reporting = enable,
+                    2026 => *self.sync_requested = enable,
                     47 | 1047 => {
                         if enable {
                             self.buffer.switch_to_alternate();
@@ -431,15 +748,103 @@ impl<'a> TerminalPerformer<'a> {
                             self.buffer.restore_cursor();
                         }
                     }
-                    _ => {}
+                    other => self.skip(format_args!("DECSET ?{}", other)),
                 }
             } else {
                 match *param {
                     4 => self.buffer.set_insert_mode(enable),
-                    _ => {}
+                    other => self.skip(format_args!("SM {}", other)),
                 }
             }
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYNC_BEGIN: &[u8] = b"\x1b[?2026h";
+
+    fn screen_text(parser: &TerminalParser) -> String {
+        parser.buffer().line_text(0)
+    }
+
+    #[test]
+    fn test_unhandled_kinds_are_bounded() {
+        let mut parser = TerminalParser::new(40, 4, 100);
+        parser.process(b"\x1b]52;c;aGk=\x07\x1b]52;c;aGk=\x07");
+        parser.process(b"\x1b]secret text\x07");
+        assert_eq!(parser.unhandled().get("OSC 52"), Some(&2));
+        assert_eq!(parser.unhandled().get("OSC ?"), Some(&1));
+
+        for n in 0..200 {
+            parser.process(format!("\x1b]{};x\x07", 1000 + n).as_bytes());
+        }
+        assert_eq!(parser.unhandled().len(), MAX_UNHANDLED_KINDS + 1);
+        assert!(parser.unhandled()[OTHER_UNHANDLED] > 0);
+    }
+
+    #[test]
+    fn test_synchronized_update_is_held_until_it_ends() {
+        let mut parser = TerminalParser::new(40, 4, 100);
+        parser.process(b"a");
+        parser.process(SYNC_BEGIN);
+        parser.process(b"bc");
+        assert!(parser.is_synchronized());
+        assert_eq!(screen_text(&parser), "a");
+
+        parser.process(b"d\x1b[?20");
+        assert_eq!(screen_text(&parser), "a");
+        parser.process(b"26le");
+        assert!(!parser.is_synchronized());
+        assert_eq!(screen_text(&parser), "abcde");
+    }
+
+    #[test]
+    fn test_nested_and_repeated_updates_in_one_read() {
+        let mut parser = TerminalParser::new(40, 4, 100);
+        let mut data = Vec::new();
+        for c in [b'a', b'b', b'c'] {
+            data.extend_from_slice(SYNC_BEGIN);
+            data.extend_from_slice(SYNC_BEGIN);
+            data.push(c);
+            data.extend_from_slice(SYNC_END);
+        }
+        parser.process(&data);
+        assert!(!parser.is_synchronized());
+        assert_eq!(screen_text(&parser), "abc");
+
+        // Many updates in one read are applied without nesting calls
+        let block = [SYNC_BEGIN, b"x", SYNC_END].concat();
+        parser.process(&block.repeat(100_000));
+        assert!(!parser.is_synchronized());
+    }
+
+    #[test]
+    fn test_unterminated_update_after_complete_one() {
+        let mut parser = TerminalParser::new(40, 4, 100);
+        let data = [SYNC_BEGIN, b"a", SYNC_END, SYNC_BEGIN, b"b"].concat();
+        parser.process(&data);
+        assert!(parser.is_synchronized());
+        assert_eq!(screen_text(&parser), "a");
+
+        parser.process(SYNC_END);
+        assert_eq!(screen_text(&parser), "ab");
+    }
+
+    #[test]
+    fn test_update_times_out() {
+        let mut parser = TerminalParser::new(40, 4, 100);
+        parser.process(&[SYNC_BEGIN, b"late"].concat());
+        parser.tick();
+        assert_eq!(screen_text(&parser), "");
+
+        if let Some((started, _)) = &mut parser.sync_pending {
+            *started -= SYNC_TIMEOUT;
+        }
+        parser.tick();
+        assert!(!parser.is_synchronized());
+        assert_eq!(screen_text(&parser), "late");
+    }
+}
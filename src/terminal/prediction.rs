@@ -0,0 +1,195 @@
+//! Local echo prediction for high-latency links
+//!
+//! Like mosh, typed printable characters are drawn at the cursor right away
+//! and dropped once the server echoes them back. Predictions are only shown
+//! after the server has confirmed one since the last Enter, control key or
+//! other output, so input at a prompt that does not echo (passwords) is
+//! never displayed.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Round trip above which `Adaptive` starts predicting
+pub const ADAPTIVE_THRESHOLD: Duration = Duration::from_millis(60);
+
+/// Unconfirmed predictions are given up after this long
+const EXPIRY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PredictionMode {
+    Off,
+    /// Only while the measured round trip is slow
+    #[default]
+    Adaptive,
+    Always,
+}
+
+impl PredictionMode {
+    pub const ALL: [PredictionMode; 3] = [PredictionMode::Off, PredictionMode::Adaptive, PredictionMode::Always];
+}
+
+impl std::fmt::Display for PredictionMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PredictionMode::Off => write!(f, "Off"),
+            PredictionMode::Adaptive => write!(f, "On slow connections"),
+            PredictionMode::Always => write!(f, "Always"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct EchoPredictor {
+    mode: PredictionMode,
+    /// Smoothed round trip time, from probes and echo delays
+    srtt: Option<Duration>,
+    /// Typed characters not yet echoed, oldest first
+    pending: Vec<(char, Instant)>,
+    /// The server echoed a prediction since the last reset
+    confirmed: bool,
+}
+
+impl EchoPredictor {
+    pub fn new(mode: PredictionMode) -> Self {
+        Self { mode, srtt: None, pending: Vec::new(), confirmed: false }
+    }
+
+    pub fn set_mode(&mut self, mode: PredictionMode) {
+        self.mode = mode;
+        if mode == PredictionMode::Off {
+            self.reset();
+        }
+    }
+
+    /// Feed a measured round trip
+    pub fn observe_rtt(&mut self, rtt: Duration) {
+        self.srtt = Some(match self.srtt {
+            Some(srtt) => (srtt * 7 + rtt) / 8,
+            None => rtt,
+        });
+    }
+
+    fn enabled(&self) -> bool {
+        match self.mode {
+            PredictionMode::Off => false,
+            PredictionMode::Always => true,
+            PredictionMode::Adaptive => self.srtt.map_or(false, |srtt| srtt >= ADAPTIVE_THRESHOLD),
+        }
+    }
+
+    /// Drop all predictions and wait for a confirmed echo before showing more
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.confirmed = false;
+    }
+
+    /// Record bytes sent to the server. Typing is tracked even while
+    /// predictions are hidden, since echo delays are how `Adaptive` measures.
+    pub fn on_input(&mut self, data: &[u8]) {
+        if self.mode == PredictionMode::Off {
+            return;
+        }
+        let Ok(text) = std::str::from_utf8(data) else {
+            self.reset();
+            return;
+        };
+        for c in text.chars() {
+            match c {
+                '\x7f' | '\x08' if !self.pending.is_empty() => {
+                    self.pending.pop();
+                }
+                c if c.is_control() => {
+                    // Enter, arrows, Ctrl keys: the application decides what happens
+                    self.reset();
+                    return;
+                }
+                c => self.pending.push((c, Instant::now())),
+            }
+        }
+    }
+
+    /// Match server output against the predictions. Output that is not
+    /// the echo of a prediction, such as a new prompt, needs another
+    /// confirmed echo before predictions are shown again.
+    pub fn on_output(&mut self, data: &[u8]) {
+        let text = String::from_utf8_lossy(data);
+        for c in text.chars() {
+            match self.pending.first() {
+                Some(&(expected, typed_at)) if c == expected => {
+                    self.pending.remove(0);
+                    self.confirmed = true;
+                    self.observe_rtt(typed_at.elapsed());
+                }
+                _ => {
+                    // Redraws, completions, prompts or no echo at all: stop guessing
+                    self.reset();
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Characters to draw at the cursor, if any should be shown
+    pub fn display(&mut self) -> Option<String> {
+        if self.pending.first().map_or(false, |(_, at)| at.elapsed() >= EXPIRY) {
+            self.reset();
+        }
+        if !self.enabled() || !self.confirmed || self.pending.is_empty() {
+            return None;
+        }
+        Some(self.pending.iter().map(|(c, _)| *c).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shown_only_after_first_echo() {
+        let mut predictor = EchoPredictor::new(PredictionMode::Always);
+        predictor.on_input(b"ls");
+        assert_eq!(predictor.display(), None);
+
+        predictor.on_output(b"l");
+        assert_eq!(predictor.display().as_deref(), Some("s"));
+
+        predictor.on_input(b" -la\x7f");
+        assert_eq!(predictor.display().as_deref(), Some("s -l"));
+
+        predictor.on_input(b"\r");
+        predictor.on_input(b"secret");
+        assert_eq!(predictor.display(), None);
+    }
+
+    #[test]
+    fn test_mismatch_and_adaptive_mode() {
+        let mut predictor = EchoPredictor::new(PredictionMode::Always);
+        predictor.on_input(b"ab");
+        predictor.on_output(b"a");
+        predictor.on_output(b"\x1b[K");
+        assert_eq!(predictor.display(), None);
+
+        let mut adaptive = EchoPredictor::new(PredictionMode::Adaptive);
+        adaptive.observe_rtt(Duration::from_millis(10));
+        adaptive.on_input(b"x");
+        adaptive.on_output(b"x");
+        adaptive.on_input(b"y");
+        assert_eq!(adaptive.display(), None);
+
+        adaptive.observe_rtt(Duration::from_millis(600));
+        assert_eq!(adaptive.display().as_deref(), Some("y"));
+    }
+
+    #[test]
+    fn test_no_echo_prompt_after_confirmed_echo() {
+        let mut predictor = EchoPredictor::new(PredictionMode::Always);
+        predictor.on_input(b"y");
+        predictor.on_output(b"y");
+
+        // The program asks for a password without the user pressing Enter
+        predictor.on_output(b"\r\nPassword: ");
+        predictor.on_input(b"hunter2");
+        assert_eq!(predictor.display(), None);
+    }
+}
@@ -1,7 +1,12 @@
 //! Terminal renderer for egui
 
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use eframe::egui::{self, Color32, FontId, Pos2, Rect, Stroke, Vec2};
+use super::benchmark::{BenchmarkOptions, BenchmarkReport, FrameStats};
 use super::buffer::TerminalBuffer;
+use super::parser::TerminalParser;
+use super::triggers::TriggerSet;
 use super::Color;
 
 /// Terminal renderer configuration
@@ -11,8 +16,13 @@ pub struct RendererConfig {
     pub cursor_style: CursorStyle,
     pub cursor_blink: bool,
     pub show_scrollbar: bool,
+    /// Show the time each line was written in a gutter
+    pub show_timestamps: bool,
 }
 
+/// Gutter width in characters: "HH:MM:SS" and a space
+pub const GUTTER_CHARS: f32 = 9.0;
+
 impl Default for RendererConfig {
     fn default() -> Self {
         Self {
@@ -21,6 +31,7 @@ impl Default for RendererConfig {
             cursor_style: CursorStyle::Block,
             cursor_blink: true,
             show_scrollbar: true,
+            show_timestamps: false,
         }
     }
 }
@@ -33,21 +44,46 @@ pub enum CursorStyle {
     Beam,
 }
 
+/// A place in the output: line counted with the scrollback trimmed so far,
+/// so it keeps pointing at the same text as old lines are dropped, and column
+pub type OutputPoint = (usize, usize);
+
 /// Terminal renderer
 pub struct TerminalRenderer {
     config: RendererConfig,
+    /// Top line shown, counted like `OutputPoint` lines; `None` follows the
+    /// output. Kept as an anchor so a tab that is not shown still opens
+    /// where it was left, however much arrives meanwhile.
+    scroll_anchor: Option<usize>,
+    /// Offset into the buffer worked out from the anchor on the last frame
     scroll_offset: usize,
+    /// Lines trimmed from the scrollback as of the last frame
+    trimmed: usize,
+    /// Largest offset on the last frame, where the output is followed
+    max_scroll: usize,
+    /// Lines the user scrolled by since the last `take_scroll_delta`,
+    /// negative towards older output
+    scroll_delta: isize,
+    /// Start and end of the selected text, if any
+    selection: Option<(OutputPoint, OutputPoint)>,
     char_width: f32,
     char_height: f32,
+    triggers: Option<Arc<TriggerSet>>,
 }
 
 impl TerminalRenderer {
     pub fn new(config: RendererConfig) -> Self {
         Self {
             config,
+            scroll_anchor: None,
             scroll_offset: 0,
+            trimmed: 0,
+            max_scroll: 0,
+            scroll_delta: 0,
+            selection: None,
             char_width: 0.0,
             char_height: 0.0,
+            triggers: None,
         }
     }
 
@@ -61,35 +97,88 @@ impl TerminalRenderer {
         self.char_height = self.config.font_size * 1.2;
     }
 
+    /// Show or hide the timestamp gutter
+    pub fn set_show_timestamps(&mut self, show: bool) {
+        self.config.show_timestamps = show;
+    }
+
+    /// Highlight output matching these triggers
+    pub fn set_triggers(&mut self, triggers: Option<Arc<TriggerSet>>) {
+        self.triggers = triggers;
+    }
+
+    /// Change font and cursor; the character size follows on the next frame
+    pub fn set_appearance(&mut self, font_family: &str, font_size: f32, cursor_style: CursorStyle, cursor_blink: bool) {
+        self.config.font_family = font_family.to_string();
+        self.config.font_size = font_size;
+        self.config.cursor_style = cursor_style;
+        self.config.cursor_blink = cursor_blink;
+    }
+
+    fn gutter_width(&self) -> f32 {
+        if self.config.show_timestamps {
+            GUTTER_CHARS * self.char_width
+        } else {
+            0.0
+        }
+    }
+
     /// Render the terminal buffer
     pub fn render(&mut self, ui: &mut egui::Ui, buffer: &TerminalBuffer) {
         self.calculate_char_size(ui);
 
         let available = ui.available_size();
+        let gutter = self.gutter_width();
         let visible_rows = (available.y / self.char_height) as usize;
-        let visible_cols = (available.x / self.char_width) as usize;
+        let visible_cols = ((available.x - gutter) / self.char_width) as usize;
 
         let total_rows = buffer.scrollback_len() + buffer.size().rows as usize;
 
         let max_scroll = total_rows.saturating_sub(visible_rows);
-        self.scroll_offset = self.scroll_offset.min(max_scroll);
+        self.max_scroll = max_scroll;
+        self.trimmed = buffer.trimmed_lines();
+        self.scroll_offset = match self.scroll_anchor {
+            Some(line) => line.saturating_sub(self.trimmed).min(max_scroll),
+            None => max_scroll,
+        };
 
         let (response, painter) = ui.allocate_painter(available, egui::Sense::click_and_drag());
         let rect = response.rect;
 
         painter.rect_filled(rect, 0.0, Color32::from_rgb(30, 30, 30));
 
-        ui.input(|i| {
-            let scroll = i.raw_scroll_delta.y;
-            if scroll != 0.0 {
-                let scroll_lines = (scroll / 20.0).abs() as usize;
-                if scroll > 0.0 {
-                    self.scroll_offset = self.scroll_offset.saturating_sub(scroll_lines);
-                } else {
-                    self.scroll_offset = (self.scroll_offset + scroll_lines).min(max_scroll);
-                }
+        let scroll = ui.input(|i| i.raw_scroll_delta.y);
+        if scroll != 0.0 {
+            let scroll_lines = (scroll / 20.0).abs() as usize;
+            let offset = if scroll > 0.0 {
+                self.scroll_offset.saturating_sub(scroll_lines)
+            } else {
+                (self.scroll_offset + scroll_lines).min(max_scroll)
+            };
+            self.scroll_delta += offset as isize - self.scroll_offset as isize;
+            self.scroll_offset = offset;
+            // Back at the bottom means following the output again
+            self.scroll_anchor = (offset < max_scroll).then_some(offset + self.trimmed);
+        }
+
+        let (char_width, char_height, first_line) = (self.char_width, self.char_height, self.trimmed + self.scroll_offset);
+        let point_at = |pos: Pos2| -> OutputPoint {
+            let row = ((pos.y - rect.top()) / char_height).max(0.0) as usize;
+            let col = ((pos.x - rect.left() - gutter) / char_width).max(0.0) as usize;
+            (first_line + row, col.min(visible_cols.saturating_sub(1)))
+        };
+        if response.drag_started() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                self.selection = Some((point_at(pos), point_at(pos)));
             }
-        });
+        } else if response.dragged() {
+            if let (Some(pos), Some((_, end))) = (response.interact_pointer_pos(), self.selection.as_mut()) {
+                *end = point_at(pos);
+            }
+        } else if response.clicked() {
+            self.selection = None;
+        }
+        let selected = self.selection.map(|(a, b)| if a <= b { (a, b) } else { (b, a) });
 
         let font_id = FontId::monospace(self.config.font_size);
 
@@ -104,8 +193,55 @@ impl TerminalRenderer {
                 buffer.get_row(screen_row)
             };
 
+            if gutter > 0.0 {
+                if let Some(time) = buffer.line_stamp(absolute_row) {
+                    painter.text(
+                        Pos2::new(rect.left(), y),
+                        egui::Align2::LEFT_TOP,
+                        time.format("%H:%M:%S"),
+                        font_id.clone(),
+                        Color32::from_rgb(110, 110, 110),
+                    );
+                }
+            }
+
+            if let Some(((start_line, start_col), (end_line, end_col))) = selected {
+                let line = self.trimmed + absolute_row;
+                if (start_line..=end_line).contains(&line) {
+                    let from = if line == start_line { start_col } else { 0 };
+                    let to = if line == end_line { end_col + 1 } else { visible_cols };
+                    painter.rect_filled(
+                        Rect::from_min_max(
+                            Pos2::new(rect.left() + gutter + from as f32 * self.char_width, y),
+                            Pos2::new(rect.left() + gutter + to as f32 * self.char_width, y + self.char_height),
+                        ),
+                        0.0,
+                        Color32::from_rgba_unmultiplied(80, 120, 200, 90),
+                    );
+                }
+            }
+
             if let Some(cells) = cells {
-                let mut x = rect.left();
+                let mut x = rect.left() + gutter;
+
+                if let Some(triggers) = &self.triggers {
+                    let text: String = cells.iter().map(|c| c.character).collect();
+                    for span in triggers.highlights(&text) {
+                        let end = span.end.min(visible_cols);
+                        if span.start >= end {
+                            continue;
+                        }
+                        let [r, g, b] = span.color;
+                        painter.rect_filled(
+                            Rect::from_min_size(
+                                Pos2::new(x + span.start as f32 * self.char_width, y),
+                                Vec2::new((end - span.start) as f32 * self.char_width, self.char_height),
+                            ),
+                            0.0,
+                            Color32::from_rgb(r, g, b),
+                        );
+                    }
+                }
 
                 for (_col_idx, cell) in cells.iter().enumerate().take(visible_cols) {
                     if cell.bg != Color::BLACK {
@@ -186,7 +322,7 @@ impl TerminalRenderer {
             && cursor_screen_row < self.scroll_offset + visible_rows
         {
             let cursor_display_row = cursor_screen_row - self.scroll_offset;
-            let cursor_px_x = rect.left() + (cursor_x as f32 * self.char_width);
+            let cursor_px_x = rect.left() + gutter + (cursor_x as f32 * self.char_width);
             let cursor_px_y = rect.top() + (cursor_display_row as f32 * self.char_height);
 
             let should_show = if self.config.cursor_blink {
@@ -244,6 +380,20 @@ impl TerminalRenderer {
             }
         }
 
+        // Without the gutter, hovering the first columns reveals a line's time
+        if gutter == 0.0 {
+            if let Some(pos) = response.hover_pos() {
+                if pos.x < rect.left() + 2.0 * self.char_width {
+                    let row = self.scroll_offset + ((pos.y - rect.top()) / self.char_height) as usize;
+                    if let Some(time) = buffer.line_stamp(row) {
+                        egui::show_tooltip_at_pointer(ui.ctx(), egui::Id::new("line_stamp"), |ui| {
+                            ui.label(time.format("%Y-%m-%d %H:%M:%S%.3f").to_string());
+                        });
+                    }
+                }
+            }
+        }
+
         if self.config.show_scrollbar && total_rows > visible_rows {
             let scrollbar_width = 8.0;
             let scrollbar_x = rect.right() - scrollbar_width - 2.0;
@@ -264,31 +414,139 @@ impl TerminalRenderer {
         ui.ctx().request_repaint();
     }
 
-    /// Scroll to bottom of buffer
-    pub fn scroll_to_bottom(&mut self, buffer: &TerminalBuffer) {
-        let total_rows = buffer.scrollback_len() + buffer.size().rows as usize;
-        let visible_rows = 24;
-        self.scroll_offset = total_rows.saturating_sub(visible_rows);
+    /// Follow the output again
+    pub fn scroll_to_bottom(&mut self) {
+        self.scroll_anchor = None;
     }
 
-    /// Get current scroll offset
+    /// Index of the top line shown, as of the last frame
     pub fn scroll_offset(&self) -> usize {
         self.scroll_offset
     }
 
-    /// Set scroll offset
+    /// Show the buffer from this line index on; it stays on the same text
+    /// while output arrives
     pub fn set_scroll_offset(&mut self, offset: usize) {
         self.scroll_offset = offset;
+        self.scroll_anchor = Some(offset + self.trimmed);
+    }
+
+    /// Scroll by `lines`, negative towards older output, as if the user had
+    pub fn scroll_by(&mut self, lines: isize) {
+        let offset = self.scroll_offset.saturating_add_signed(lines).min(self.max_scroll);
+        self.scroll_offset = offset;
+        self.scroll_anchor = (offset < self.max_scroll).then_some(offset + self.trimmed);
+    }
+
+    /// How far the user scrolled since the last call, for panes scrolling
+    /// in lockstep
+    pub fn take_scroll_delta(&mut self) -> isize {
+        std::mem::take(&mut self.scroll_delta)
+    }
+
+    pub fn is_following_output(&self) -> bool {
+        self.scroll_anchor.is_none()
+    }
+
+    /// Selected text, joined like `TerminalBuffer::text_range`; `None` once
+    /// the selection scrolled out of the kept history
+    pub fn selected_text(&self, buffer: &TerminalBuffer) -> Option<String> {
+        let (start, end) = self.selection?;
+        let trimmed = buffer.trimmed_lines();
+        let start = (start.0.checked_sub(trimmed)?, start.1);
+        let end = (end.0.checked_sub(trimmed)?, end.1);
+        Some(buffer.text_range(start, end))
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selection = None;
     }
 
     /// Calculate terminal size in characters for given pixel dimensions
     pub fn calculate_size(&self, width: f32, height: f32) -> (u16, u16) {
-        let cols = (width / self.char_width.max(1.0)) as u16;
+        let cols = ((width - self.gutter_width()) / self.char_width.max(1.0)) as u16;
         let rows = (height / self.char_height.max(1.0)) as u16;
         (cols.max(1), rows.max(1))
     }
 }
 
+/// Draw the screen of `buffer` shrunk into `rect` for the tab overview.
+/// Each row is one run of text in the colour of its first visible cell and
+/// backgrounds are left out, so dozens of thumbnails stay cheap.
+pub fn paint_thumbnail(painter: &egui::Painter, rect: Rect, buffer: &TerminalBuffer) {
+    let size = buffer.size();
+    let (cols, rows) = (size.cols.max(1) as f32, size.rows.max(1) as f32);
+    let row_height = rect.height() / rows;
+    // Monospace glyphs are about 0.6 of the font size wide
+    let font_size = (rect.width() / cols / 0.6).min(row_height);
+    if font_size < 1.0 {
+        return;
+    }
+    let font_id = FontId::monospace(font_size);
+
+    for row in 0..size.rows as usize {
+        let Some(cells) = buffer.get_row(row) else { continue };
+        let text: String = cells.iter().map(|cell| cell.character).collect();
+        let text = text.trim_end();
+        if text.is_empty() {
+            continue;
+        }
+        let color = cells.iter()
+            .find(|cell| !cell.is_empty())
+            .map_or(Color32::LIGHT_GRAY, |cell| color_to_egui(cell.fg));
+        painter.text(
+            Pos2::new(rect.left(), rect.top() + row as f32 * row_height),
+            egui::Align2::LEFT_TOP,
+            text,
+            font_id.clone(),
+            color,
+        );
+    }
+}
+
+/// Parse `corpus` and draw it in a headless egui context, a frame every
+/// `chunks_per_frame` chunks; drawing includes tessellation but not the GPU
+pub fn run_benchmark(corpus: &[u8], options: &BenchmarkOptions) -> BenchmarkReport {
+    let mut parser = TerminalParser::new(options.cols, options.rows, options.scrollback);
+    let mut renderer = TerminalRenderer::new(RendererConfig { cursor_blink: false, ..Default::default() });
+    let ctx = egui::Context::default();
+    // Room for the grid at the default font size
+    let screen = Rect::from_min_size(Pos2::ZERO, Vec2::new(options.cols as f32 * 9.0, options.rows as f32 * 17.0 + 16.0));
+    let mut frame = |parser: &TerminalParser| {
+        let started = Instant::now();
+        let input = egui::RawInput { screen_rect: Some(screen), ..Default::default() };
+        let output = ctx.run(input, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| renderer.render(ui, parser.buffer()));
+        });
+        ctx.tessellate(output.shapes, output.pixels_per_point);
+        started.elapsed()
+    };
+    // Font atlas setup is a one-off, not part of any frame
+    frame(&parser);
+
+    let started = Instant::now();
+    let mut parse_time = Duration::ZERO;
+    let mut frame_times = Vec::new();
+    for (i, chunk) in corpus.chunks(options.chunk_bytes.max(1)).enumerate() {
+        let parse_started = Instant::now();
+        parser.process(chunk);
+        parse_time += parse_started.elapsed();
+        // Replies to queries in the output would go to the host
+        parser.take_responses();
+        if (i + 1) % options.chunks_per_frame.max(1) == 0 {
+            frame_times.push(frame(&parser));
+        }
+    }
+    frame_times.push(frame(&parser));
+
+    BenchmarkReport {
+        bytes: corpus.len(),
+        parse_time,
+        total_time: started.elapsed(),
+        frames: FrameStats::from_samples(&frame_times),
+    }
+}
+
 fn color_to_egui(color: Color) -> Color32 {
     Color32::from_rgb(color.r, color.g, color.b)
 }
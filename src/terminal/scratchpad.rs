@@ -0,0 +1,88 @@
+//! Per-tab scratchpad for commands and findings during an incident
+//!
+//! The text itself lives with the tab and is saved with the workspace; this
+//! module only knows how to write it out together with the session log, so
+//! the notes and the output they refer to end up in one file.
+
+use serde::{Deserialize, Serialize};
+
+/// How the scratchpad is shown and exported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ScratchpadFormat {
+    #[default]
+    Plain,
+    Markdown,
+}
+
+impl ScratchpadFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ScratchpadFormat::Plain => "txt",
+            ScratchpadFormat::Markdown => "md",
+        }
+    }
+}
+
+/// The scratchpad followed by the session log. `title` names the session,
+/// e.g. `root@db01`, and `exported_at` is shown under it.
+pub fn export(title: &str, exported_at: &str, notes: &str, format: ScratchpadFormat, log: &[String]) -> String {
+    let notes = notes.trim_end();
+    let mut log = log.to_vec();
+    while log.last().map_or(false, |line| line.trim().is_empty()) {
+        log.pop();
+    }
+
+    let mut out = String::new();
+    match format {
+        ScratchpadFormat::Markdown => {
+            out.push_str(&format!("# {}\n\n_Exported {}_\n\n## Scratchpad\n\n", title, exported_at));
+            out.push_str(if notes.is_empty() { "_Empty_" } else { notes });
+            // A fence longer than any backtick run in the log keeps it one block
+            let fence = "`".repeat(longest_backtick_run(&log).max(2) + 1);
+            out.push_str(&format!("\n\n## Session log\n\n{}text\n", fence));
+            for line in &log {
+                out.push_str(line);
+                out.push('\n');
+            }
+            out.push_str(&fence);
+            out.push('\n');
+        }
+        ScratchpadFormat::Plain => {
+            out.push_str(&format!("{}\nExported {}\n\n==== Scratchpad ====\n\n", title, exported_at));
+            out.push_str(notes);
+            out.push_str("\n\n==== Session log ====\n\n");
+            for line in &log {
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+fn longest_backtick_run(lines: &[String]) -> usize {
+    lines
+        .iter()
+        .flat_map(|line| line.split(|c| c != '`'))
+        .map(str::len)
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export() {
+        let log: Vec<String> = ["$ uptime", " 10:02 up 3 days", "```", "", ""].iter().map(|s| s.to_string()).collect();
+
+        let md = export("root@db01", "2024-05-01 10:03", "- disk full?\n", ScratchpadFormat::Markdown, &log);
+        assert!(md.starts_with("# root@db01\n\n_Exported 2024-05-01 10:03_\n\n## Scratchpad\n\n- disk full?\n\n"));
+        assert!(md.ends_with("````text\n$ uptime\n 10:02 up 3 days\n```\n````\n"));
+
+        let plain = export("root@db01", "now", "", ScratchpadFormat::Plain, &log);
+        assert!(plain.contains("==== Scratchpad ====\n\n\n\n==== Session log ====\n\n$ uptime\n"));
+        assert!(plain.ends_with("```\n"));
+    }
+}
@@ -0,0 +1,434 @@
+//! Live read-only view of a terminal, on this machine or over the LAN
+//!
+//! The sharing side listens on TCP and streams the rendered screen as
+//! newline-delimited JSON: one full frame once the viewer presents the
+//! session's token, then only the lines that changed. Nothing a viewer sends
+//! after the token is read, so a view can never type into the session.
+//! The stream is not encrypted, so shares listen on loopback unless LAN
+//! sharing is turned on.
+
+use super::export::ExportCell;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::runtime::Runtime;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// Longest a viewer may take to present the token
+const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
+/// Most a viewer may send before presenting the token; a hello is under 100
+const MAX_HELLO_BYTES: u64 = 512;
+
+/// Run of characters with the same look; `None` colors are the viewer's defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ShareSpan {
+    pub text: String,
+    pub fg: Option<[u8; 3]>,
+    pub bg: Option<[u8; 3]>,
+    #[serde(default)]
+    pub bold: bool,
+    #[serde(default)]
+    pub italic: bool,
+    #[serde(default)]
+    pub underline: bool,
+    #[serde(default)]
+    pub dim: bool,
+    #[serde(default)]
+    pub inverse: bool,
+}
+
+impl ShareSpan {
+    fn same_style(&self, other: &ShareSpan) -> bool {
+        (self.fg, self.bg, self.bold, self.italic, self.underline, self.dim, self.inverse)
+            == (other.fg, other.bg, other.bold, other.italic, other.underline, other.dim, other.inverse)
+    }
+}
+
+pub type ShareLine = Vec<ShareSpan>;
+
+/// What the viewer shows: the visible screen of the shared terminal
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ShareFrame {
+    pub title: String,
+    pub cols: u16,
+    pub rows: u16,
+    /// Column and row of the cursor
+    pub cursor: (u16, u16),
+    pub lines: Vec<ShareLine>,
+}
+
+/// Merge cells with the same look into spans
+pub fn line_from_cells(cells: &[ExportCell]) -> ShareLine {
+    let mut line: ShareLine = Vec::new();
+    for cell in cells {
+        let span = ShareSpan {
+            text: String::new(),
+            fg: cell.fg,
+            bg: cell.bg,
+            bold: cell.bold,
+            italic: cell.italic,
+            underline: cell.underline,
+            dim: cell.dim,
+            inverse: cell.inverse,
+        };
+        match line.last_mut() {
+            Some(last) if last.same_style(&span) => last.text.push(cell.c),
+            _ => line.push(ShareSpan { text: cell.c.to_string(), ..span }),
+        }
+    }
+    line
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    /// First line from the viewer
+    Hello { token: String },
+    Denied { reason: String },
+    Full { frame: ShareFrame },
+    /// Changed lines by index; size changes always send a full frame
+    Delta {
+        cursor: (u16, u16),
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        lines: Vec<(usize, ShareLine)>,
+    },
+}
+
+/// What to send to go from `prev` to `next`, if anything changed
+fn diff(prev: &ShareFrame, next: &ShareFrame) -> Option<Message> {
+    if prev == next {
+        return None;
+    }
+    if (prev.cols, prev.rows) != (next.cols, next.rows) || prev.lines.len() != next.lines.len() {
+        return Some(Message::Full { frame: next.clone() });
+    }
+    let lines = next.lines.iter()
+        .zip(&prev.lines)
+        .enumerate()
+        .filter(|(_, (new, old))| new != old)
+        .map(|(index, (new, _))| (index, new.clone()))
+        .collect();
+    Some(Message::Delta {
+        cursor: next.cursor,
+        title: (prev.title != next.title).then(|| next.title.clone()),
+        lines,
+    })
+}
+
+/// Update the viewer's frame from a message
+fn apply(frame: &mut ShareFrame, message: Message) {
+    match message {
+        Message::Full { frame: full } => *frame = full,
+        Message::Delta { cursor, title, lines } => {
+            frame.cursor = cursor;
+            if let Some(title) = title {
+                frame.title = title;
+            }
+            for (index, line) in lines {
+                if let Some(slot) = frame.lines.get_mut(index) {
+                    *slot = line;
+                }
+            }
+        }
+        Message::Hello { .. } | Message::Denied { .. } => {}
+    }
+}
+
+/// Compare without leaking how much of the token matched
+fn tokens_match(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+async fn send(writer: &mut (impl AsyncWrite + Unpin), message: &Message) -> Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await?;
+    Ok(())
+}
+
+/// Address other machines on the LAN reach this one at. Connecting a UDP
+/// socket sends nothing; it only picks the outgoing interface.
+pub fn lan_address() -> IpAddr {
+    std::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .and_then(|socket| {
+            socket.connect((Ipv4Addr::new(192, 0, 2, 1), 9))?;
+            socket.local_addr()
+        })
+        .map(|addr| addr.ip())
+        .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST))
+}
+
+/// Sharing side; dropping it ends the share and disconnects all viewers
+pub struct ShareServer {
+    /// Listening on every interface rather than loopback only
+    lan: bool,
+    port: u16,
+    token: String,
+    frames: watch::Sender<Arc<ShareFrame>>,
+    viewers: Arc<AtomicUsize>,
+    listener: JoinHandle<()>,
+}
+
+impl ShareServer {
+    /// Listen on all interfaces when `lan` is set, else on loopback only;
+    /// port 0 picks a free one
+    pub fn start(runtime: &Runtime, lan: bool, port: u16, frame: ShareFrame) -> Result<Self> {
+        let _guard = runtime.enter();
+        let ip = if lan { Ipv4Addr::UNSPECIFIED } else { Ipv4Addr::LOCALHOST };
+        let std_listener = std::net::TcpListener::bind((ip, port))
+            .with_context(|| format!("Could not listen on port {}", port))?;
+        std_listener.set_nonblocking(true)?;
+        let listener = TcpListener::from_std(std_listener)?;
+        let port = listener.local_addr()?.port();
+
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        let (frames, _) = watch::channel(Arc::new(frame));
+        let viewers = Arc::new(AtomicUsize::new(0));
+
+        let accept = {
+            let token = token.clone();
+            let template = frames.subscribe();
+            let viewers = viewers.clone();
+            runtime.spawn(async move {
+                loop {
+                    let (stream, peer) = match listener.accept().await {
+                        Ok(accepted) => accepted,
+                        Err(e) => {
+                            log::warn!("Session share stopped accepting: {}", e);
+                            break;
+                        }
+                    };
+                    let token = token.clone();
+                    let frames = template.clone();
+                    let viewers = viewers.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = serve_viewer(stream, &token, frames, &viewers).await {
+                            log::info!("Viewer {} left: {:#}", peer, e);
+                        }
+                    });
+                }
+            })
+        };
+        log::info!("Sharing session on {}:{}", ip, port);
+
+        Ok(Self { lan, port, token, frames, viewers, listener: accept })
+    }
+
+    /// Where viewers connect, as `ip:port`
+    pub fn address(&self) -> SocketAddr {
+        let ip = if self.lan { lan_address() } else { IpAddr::V4(Ipv4Addr::LOCALHOST) };
+        SocketAddr::new(ip, self.port)
+    }
+
+    /// Whether viewers on other machines can connect
+    pub fn is_lan(&self) -> bool {
+        self.lan
+    }
+
+    /// Secret a viewer must present
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// Viewers connected right now
+    pub fn viewers(&self) -> usize {
+        self.viewers.load(Ordering::Relaxed)
+    }
+
+    /// Send the current screen; viewers get the lines that changed
+    pub fn publish(&self, frame: ShareFrame) {
+        self.frames.send_if_modified(|current| {
+            if **current == frame {
+                return false;
+            }
+            *current = Arc::new(frame);
+            true
+        });
+    }
+}
+
+impl Drop for ShareServer {
+    fn drop(&mut self) {
+        // Viewer tasks end once the frame sender is gone
+        self.listener.abort();
+        log::info!("Stopped sharing session on port {}", self.port);
+    }
+}
+
+/// Counts a viewer for as long as it is connected
+struct ViewerGuard<'a>(&'a AtomicUsize);
+
+impl Drop for ViewerGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+async fn serve_viewer(
+    stream: TcpStream,
+    token: &str,
+    mut frames: watch::Receiver<Arc<ShareFrame>>,
+    viewers: &AtomicUsize,
+) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut hello = String::new();
+    let read = tokio::time::timeout(
+        HELLO_TIMEOUT,
+        BufReader::new(reader.take(MAX_HELLO_BYTES)).read_line(&mut hello),
+    )
+    .await
+    .map_err(|_| anyhow!("no token within {} s", HELLO_TIMEOUT.as_secs()))??;
+    if read == 0 {
+        bail!("hung up before sending the token");
+    }
+    if !hello.ends_with('\n') {
+        send(&mut writer, &Message::Denied { reason: "Token line too long".to_string() }).await?;
+        bail!("sent more than {} bytes without a token", MAX_HELLO_BYTES);
+    }
+    match serde_json::from_str(&hello) {
+        Ok(Message::Hello { token: offered }) if tokens_match(&offered, token) => {}
+        _ => {
+            send(&mut writer, &Message::Denied { reason: "Wrong token".to_string() }).await?;
+            bail!("wrong token");
+        }
+    }
+
+    viewers.fetch_add(1, Ordering::Relaxed);
+    let _guard = ViewerGuard(viewers);
+    let mut sent = frames.borrow_and_update().clone();
+    send(&mut writer, &Message::Full { frame: (*sent).clone() }).await?;
+    while frames.changed().await.is_ok() {
+        let next = frames.borrow_and_update().clone();
+        if let Some(message) = diff(&sent, &next) {
+            send(&mut writer, &message).await?;
+        }
+        sent = next;
+    }
+    Ok(())
+}
+
+/// Viewer side, as the UI sees it
+#[derive(Debug, Clone)]
+pub enum ViewerState {
+    Connecting,
+    Live(Arc<ShareFrame>),
+    /// Disconnected, with the reason
+    Ended(String),
+}
+
+/// Connect to a shared session; the state updates as frames arrive.
+/// Dropping the receiver disconnects.
+pub fn watch(runtime: &Runtime, address: String, token: String, ctx: egui::Context) -> watch::Receiver<ViewerState> {
+    let (tx, rx) = watch::channel(ViewerState::Connecting);
+    runtime.spawn(async move {
+        let reason = match view(&address, &token, &tx, &ctx).await {
+            Ok(()) => "The session is no longer shared".to_string(),
+            Err(e) => format!("{:#}", e),
+        };
+        let _ = tx.send(ViewerState::Ended(reason));
+        ctx.request_repaint();
+    });
+    rx
+}
+
+async fn view(address: &str, token: &str, tx: &watch::Sender<ViewerState>, ctx: &egui::Context) -> Result<()> {
+    let stream = TcpStream::connect(address).await.with_context(|| format!("Could not connect to {}", address))?;
+    let (reader, mut writer) = stream.into_split();
+    send(&mut writer, &Message::Hello { token: token.trim().to_string() }).await?;
+
+    let mut frame = ShareFrame::default();
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        match serde_json::from_str::<Message>(&line)? {
+            Message::Denied { reason } => bail!(reason),
+            message => apply(&mut frame, message),
+        }
+        if tx.send(ViewerState::Live(Arc::new(frame.clone()))).is_err() {
+            // The viewer was closed
+            return Ok(());
+        }
+        ctx.request_repaint();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(lines: &[&str]) -> ShareFrame {
+        ShareFrame {
+            title: "web-01".to_string(),
+            cols: 10,
+            rows: lines.len() as u16,
+            cursor: (0, 0),
+            lines: lines.iter()
+                .map(|text| line_from_cells(&text.chars().map(ExportCell::plain).collect::<Vec<_>>()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_diff_sends_changed_lines() {
+        let before = frame(&["$ ls", "a  b", ""]);
+        let mut after = frame(&["$ ls", "a  b  c", "$ "]);
+        after.cursor = (2, 2);
+
+        let Some(Message::Delta { cursor, title, lines }) = diff(&before, &after) else {
+            panic!("expected a delta");
+        };
+        assert_eq!(cursor, (2, 2));
+        assert_eq!(title, None);
+        assert_eq!(lines.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [1, 2]);
+
+        let mut viewed = before.clone();
+        apply(&mut viewed, Message::Delta { cursor, title, lines });
+        assert_eq!(viewed, after);
+        assert_eq!(diff(&after, &after), None);
+    }
+
+    #[test]
+    fn test_line_from_cells_merges_spans() {
+        let mut cells: Vec<ExportCell> = "ok err".chars().map(ExportCell::plain).collect();
+        for cell in &mut cells[3..] {
+            cell.fg = Some([255, 0, 0]);
+        }
+        let line = line_from_cells(&cells);
+        assert_eq!(line.len(), 2);
+        assert_eq!(line[0].text, "ok ");
+        assert_eq!(line[1].text, "err");
+    }
+
+    #[test]
+    fn test_share_listens_on_loopback_and_caps_hello() {
+        let runtime = Runtime::new().unwrap();
+        let share = ShareServer::start(&runtime, false, 0, frame(&["$ "])).unwrap();
+        assert!(!share.is_lan());
+        assert!(share.address().ip().is_loopback());
+
+        let reply = runtime.block_on(async {
+            let mut stream = TcpStream::connect(share.address()).await.unwrap();
+            // No newline, so without the cap this would be read forever
+            stream.write_all(&[b'x'; 4096]).await.unwrap();
+            let mut reply = String::new();
+            BufReader::new(stream).read_line(&mut reply).await.unwrap();
+            reply
+        });
+        assert!(matches!(serde_json::from_str(&reply), Ok(Message::Denied { .. })), "{}", reply);
+        assert_eq!(share.viewers(), 0);
+    }
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("abc123", "abc123"));
+        assert!(!tokens_match("abc124", "abc123"));
+        assert!(!tokens_match("abc", "abc123"));
+    }
+}
@@ -0,0 +1,177 @@
+//! Terminal status bar layout
+//!
+//! Which segments the status bar under each terminal shows, in which order,
+//! and what clicking each one does. Stored with the settings.
+
+use serde::{Deserialize, Serialize};
+
+/// A piece of information in the status bar
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StatusSegment {
+    /// Connected, connecting, error...
+    State,
+    UserHost,
+    /// Grid size in columns and rows
+    Size,
+    Scrollback,
+    /// Round trip of the last liveness probe
+    Latency,
+    /// Bytes received and sent this session
+    Bytes,
+    Clock,
+    /// Time since the session connected
+    Uptime,
+    /// Duration of the running or last command, from shell-integration marks
+    LastCommand,
+}
+
+impl StatusSegment {
+    pub const ALL: [StatusSegment; 9] = [
+        StatusSegment::State,
+        StatusSegment::UserHost,
+        StatusSegment::Size,
+        StatusSegment::Scrollback,
+        StatusSegment::Latency,
+        StatusSegment::Bytes,
+        StatusSegment::Clock,
+        StatusSegment::Uptime,
+        StatusSegment::LastCommand,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            StatusSegment::State => "Connection state",
+            StatusSegment::UserHost => "user@host",
+            StatusSegment::Size => "Terminal size",
+            StatusSegment::Scrollback => "Scrollback lines",
+            StatusSegment::Latency => "Latency",
+            StatusSegment::Bytes => "Bytes in/out",
+            StatusSegment::Clock => "Clock",
+            StatusSegment::Uptime => "Session uptime",
+            StatusSegment::LastCommand => "Command duration",
+        }
+    }
+}
+
+/// What clicking a segment does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SegmentAction {
+    #[default]
+    Nothing,
+    /// Copy the segment's text to the clipboard
+    Copy,
+    /// Ask the server for a reply and update the latency
+    Probe,
+    /// Reconnect with the credentials of the current session
+    Reconnect,
+    /// Start the byte counters over
+    ResetCounters,
+}
+
+impl SegmentAction {
+    pub const ALL: [SegmentAction; 5] = [
+        SegmentAction::Nothing,
+        SegmentAction::Copy,
+        SegmentAction::Probe,
+        SegmentAction::Reconnect,
+        SegmentAction::ResetCounters,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SegmentAction::Nothing => "Nothing",
+            SegmentAction::Copy => "Copy text",
+            SegmentAction::Probe => "Probe connection",
+            SegmentAction::Reconnect => "Reconnect",
+            SegmentAction::ResetCounters => "Reset byte counters",
+        }
+    }
+}
+
+/// One segment's place in the status bar
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusSegmentConfig {
+    pub segment: StatusSegment,
+    pub visible: bool,
+    #[serde(default)]
+    pub on_click: SegmentAction,
+}
+
+/// The layout before any customization
+pub fn default_layout() -> Vec<StatusSegmentConfig> {
+    StatusSegment::ALL
+        .into_iter()
+        .map(|segment| StatusSegmentConfig {
+            segment,
+            visible: matches!(
+                segment,
+                StatusSegment::State
+                    | StatusSegment::UserHost
+                    | StatusSegment::Size
+                    | StatusSegment::Scrollback
+                    | StatusSegment::Uptime
+                    | StatusSegment::LastCommand
+            ),
+            on_click: match segment {
+                StatusSegment::State => SegmentAction::Probe,
+                StatusSegment::UserHost => SegmentAction::Copy,
+                _ => SegmentAction::Nothing,
+            },
+        })
+        .collect()
+}
+
+/// Repair a stored layout: drop duplicates and append segments it lacks,
+/// e.g. ones added in a later version, hidden at the end
+pub fn normalize(layout: &[StatusSegmentConfig]) -> Vec<StatusSegmentConfig> {
+    let mut normalized: Vec<StatusSegmentConfig> = Vec::with_capacity(StatusSegment::ALL.len());
+    for config in layout {
+        if !normalized.iter().any(|c| c.segment == config.segment) {
+            normalized.push(*config);
+        }
+    }
+    for segment in StatusSegment::ALL {
+        if !normalized.iter().any(|c| c.segment == segment) {
+            normalized.push(StatusSegmentConfig { segment, visible: false, on_click: SegmentAction::Nothing });
+        }
+    }
+    normalized
+}
+
+/// Byte count in the short form the status bar has room for
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let clock = StatusSegmentConfig { segment: StatusSegment::Clock, visible: true, on_click: SegmentAction::Nothing };
+        let layout = normalize(&[clock, clock]);
+
+        assert_eq!(layout.len(), StatusSegment::ALL.len());
+        assert_eq!(layout[0], clock);
+        assert!(layout[1..].iter().all(|c| !c.visible && c.segment != StatusSegment::Clock));
+        assert_eq!(normalize(&default_layout()), default_layout());
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(5 * 1024 * 1024), "5.0 MB");
+    }
+}
@@ -0,0 +1,195 @@
+//! Output highlighting and triggers
+//!
+//! Users define patterns that color matching output, raise a notification
+//! or flag the tab. All patterns are compiled into one `RegexSet`, so a line
+//! that matches nothing costs a single scan however many triggers exist.
+
+use regex::{Regex, RegexBuilder, RegexSet, RegexSetBuilder};
+use serde::{Deserialize, Serialize};
+
+/// A user-defined output pattern and what it does
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputTrigger {
+    pub id: String,
+    pub name: String,
+    pub pattern: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// Background for matching text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub highlight: Option<[u8; 3]>,
+    /// Raise a notification for each matching line
+    #[serde(default)]
+    pub notify: bool,
+    /// Flag the tab until it is next viewed
+    #[serde(default)]
+    pub mark_tab: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl OutputTrigger {
+    pub fn new(name: &str, pattern: &str) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            pattern: pattern.to_string(),
+            enabled: true,
+            case_sensitive: false,
+            highlight: None,
+            notify: false,
+            mark_tab: false,
+        }
+    }
+
+    /// Error message if the pattern does not compile
+    pub fn validate(&self) -> Option<String> {
+        builder(self).build().err().map(|e| e.to_string())
+    }
+}
+
+fn builder(trigger: &OutputTrigger) -> RegexBuilder {
+    let mut builder = RegexBuilder::new(&trigger.pattern);
+    builder.case_insensitive(!trigger.case_sensitive);
+    builder
+}
+
+/// Starter triggers offered when the user has none
+pub fn example_triggers() -> Vec<OutputTrigger> {
+    let mut error = OutputTrigger::new("Errors", r"\b(ERROR|FATAL|panic(ked)?)\b");
+    error.case_sensitive = true;
+    error.highlight = Some([127, 29, 29]);
+    error.mark_tab = true;
+
+    let mut warning = OutputTrigger::new("Warnings", r"\bWARN(ING)?\b");
+    warning.case_sensitive = true;
+    warning.highlight = Some([113, 63, 18]);
+
+    let mut ip = OutputTrigger::new("IPv4 addresses", r"\b(\d{1,3}\.){3}\d{1,3}\b");
+    ip.highlight = Some([30, 58, 138]);
+
+    vec![error, warning, ip]
+}
+
+/// Colored span of a line, in character columns
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Highlight {
+    pub start: usize,
+    pub end: usize,
+    pub color: [u8; 3],
+}
+
+/// A line that matched a notifying or tab-marking trigger
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriggerHit {
+    pub trigger: String,
+    pub line: String,
+    pub notify: bool,
+    pub mark_tab: bool,
+}
+
+/// Enabled triggers compiled for matching
+pub struct TriggerSet {
+    set: RegexSet,
+    regexes: Vec<Regex>,
+    triggers: Vec<OutputTrigger>,
+}
+
+impl TriggerSet {
+    /// Compile the enabled triggers; ones with invalid patterns are skipped
+    pub fn new(triggers: &[OutputTrigger]) -> Self {
+        let mut regexes = Vec::new();
+        let mut kept = Vec::new();
+        for trigger in triggers.iter().filter(|t| t.enabled && !t.pattern.is_empty()) {
+            match builder(trigger).build() {
+                Ok(regex) => {
+                    regexes.push(regex);
+                    kept.push(trigger.clone());
+                }
+                Err(e) => log::warn!("Skipping trigger '{}': {}", trigger.name, e),
+            }
+        }
+        // Case sensitivity is per pattern, so it goes inline for the set
+        let set = RegexSetBuilder::new(kept.iter().map(|t| {
+            if t.case_sensitive { t.pattern.clone() } else { format!("(?i:{})", t.pattern) }
+        }))
+        .build()
+        .unwrap_or_else(|_| RegexSet::empty());
+
+        Self { set, regexes, triggers: kept }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triggers.is_empty()
+    }
+
+    /// Colored spans for a line of output
+    pub fn highlights(&self, line: &str) -> Vec<Highlight> {
+        let mut spans = Vec::new();
+        for index in self.set.matches(line).iter() {
+            let Some(color) = self.triggers[index].highlight else { continue };
+            for m in self.regexes[index].find_iter(line) {
+                if m.is_empty() {
+                    continue;
+                }
+                spans.push(Highlight {
+                    start: line[..m.start()].chars().count(),
+                    end: line[..m.end()].chars().count(),
+                    color,
+                });
+            }
+        }
+        spans
+    }
+
+    /// Notifications and tab marks for a completed line
+    pub fn hits(&self, line: &str) -> Vec<TriggerHit> {
+        self.set
+            .matches(line)
+            .iter()
+            .map(|index| &self.triggers[index])
+            .filter(|t| t.notify || t.mark_tab)
+            .map(|t| TriggerHit {
+                trigger: t.name.clone(),
+                line: line.trim().to_string(),
+                notify: t.notify,
+                mark_tab: t.mark_tab,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlights_use_char_columns() {
+        let mut trigger = OutputTrigger::new("err", "error");
+        trigger.highlight = Some([255, 0, 0]);
+        let set = TriggerSet::new(&[trigger]);
+
+        // Case-insensitive by default; "é" is two bytes but one column
+        assert_eq!(set.highlights("é ERROR"), vec![Highlight { start: 2, end: 7, color: [255, 0, 0] }]);
+        assert!(set.highlights("all good").is_empty());
+    }
+
+    #[test]
+    fn test_hits_and_invalid_patterns() {
+        let mut panic = OutputTrigger::new("panics", "panicked at");
+        panic.case_sensitive = true;
+        panic.notify = true;
+        let broken = OutputTrigger::new("broken", "(unclosed");
+        assert!(broken.validate().is_some());
+
+        let set = TriggerSet::new(&[panic, broken]);
+        let hits = set.hits("thread 'main' panicked at src/main.rs:2:5  ");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].line, "thread 'main' panicked at src/main.rs:2:5");
+        assert!(set.hits("PANICKED AT").is_empty());
+    }
+}
@@ -1,26 +1,159 @@
 //! Main application state
 
-use crate::ssh::SessionManager;
-use crate::storage::database::Database;
-use crate::storage::settings::Settings;
+use crate::ipc::{IpcHandle, IpcRequest, IpcTransferDirection, RpcError};
+use crate::platform::network::{NetworkEvent, NetworkMonitor};
+use crate::plugins::PluginRegistry;
+use crate::ssh::{set_challenge_prompter, set_host_key_prompter, HostKeyPrompt, SessionCommand, SessionManager, SshConfigParser, SshConnection};
+use crate::ssh::bastion::{BastionEvent, BastionHop, BastionId, BastionKeeper, MfaPrompt, MfaPrompter};
+use crate::ssh::host_watch::{HostWatch, WatchStatus, WatchTarget};
+use crate::storage::database::{Database, SessionRecord, TransferRecord};
+use crate::storage::retention;
+use crate::storage::stats::UsageStats;
+use crate::storage::sync::{self, SyncConfig, SyncContext, SyncReport, SyncTarget};
+use crate::storage::settings::{Settings, SettingsChange};
+use crate::storage::workspace::{Workspace, WorkspaceTab};
+use crate::terminal::foreground::ForegroundJob;
+use crate::terminal::scratchpad::ScratchpadFormat;
+use crate::terminal::triggers::TriggerHit;
 use crate::config::themes::ThemeManager;
+use crate::sftp::{tail_file, TailEvent};
+use crate::ui::components::TabBarAction;
+use crate::ui::screens::FileViewerScreen;
 use crate::ui::notifications::NotificationManager;
-use anyhow::Result;
+use crate::ui::tab::{Tab as TabChrome, TabStatus};
+use crate::ui::tab_manager::TabManager;
+use crate::utils::executor::{Executor, Task};
+use crate::utils::profile_share::SharedProfile;
+use crate::utils::startup::StartupProfiler;
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+/// How long exit waits for sessions to send their SSH disconnect
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(1);
+
+/// Settings events a slow subscriber may fall behind by; it then only
+/// misses intermediate states, since each event carries the full settings
+const SETTINGS_EVENT_CAPACITY: usize = 16;
 
 pub struct AppState {
+    /// The one runtime all async work runs on
+    pub executor: Executor,
     pub db: Database,
     pub settings: Settings,
     pub theme_manager: ThemeManager,
     pub session_manager: SessionManager,
     pub notification_manager: NotificationManager,
+    pub plugins: PluginRegistry,
+    /// ~/.ssh/config, parsed once at startup
+    pub ssh_config: SshConfigParser,
     pub active_tab: usize,
     pub tabs: Vec<Tab>,
+    /// What the tab bar shows of each tab: names, colors and flags. Same
+    /// ids and order as `tabs`.
+    pub tab_chrome: TabManager,
+    /// Open remote file viewers, keyed by tab id; closing the tab stops the read
+    pub file_viewers: HashMap<String, FileViewerScreen>,
+    /// Tab bar requests waiting for the terminal view of their session
+    terminal_requests: Vec<(String, TerminalRequest)>,
+    ipc: Option<IpcHandle>,
+    /// Input channels of live shell sessions, keyed by session id
+    session_inputs: HashMap<String, mpsc::Sender<SessionCommand>>,
+    /// When each live shell session started, for the session history
+    session_starts: HashMap<String, (Instant, chrono::DateTime<chrono::Utc>)>,
+    /// What seems to be running in each session, keyed by session id
+    foreground_jobs: HashMap<String, ForegroundJob>,
+    /// Terminal views report what happens in their session here
+    view_reporter: ViewReporter,
+    view_reports: mpsc::UnboundedReceiver<(String, ViewEvent)>,
+    /// Connections requested over IPC, waiting for the connect flow
+    pub pending_opens: Vec<PendingOpen>,
+    /// Session ids of tabs waiting for a local shell to start
+    pub pending_local_shells: Vec<String>,
+    /// Transfers requested over IPC, waiting for their SFTP tab
+    ipc_transfers: Vec<IpcTransfer>,
+    /// Watches for network changes and sleep/resume
+    network: NetworkMonitor,
+    /// Unreachable hosts probed until they answer
+    watches: Vec<HostWatch>,
+    /// Jump hosts kept logged in for the sessions through them
+    bastions: HashMap<BastionId, BastionKeeper>,
+    /// Questions of bastion logins, handed to the dialog one at a time
+    mfa_prompter: MfaPrompter,
+    mfa_prompts: mpsc::UnboundedReceiver<MfaPrompt>,
+    /// Unknown and changed server keys, handed to the dialog one at a time
+    host_key_prompts: mpsc::UnboundedReceiver<HostKeyPrompt>,
+    /// Settings-changed bus; open views subscribe to apply changes live
+    settings_events: broadcast::Sender<SettingsEvent>,
+    /// Sync started from the settings screen, until it finishes
+    sync_task: Option<Task<Result<SyncReport>>>,
+}
+
+/// What a terminal view tells the app about its session
+#[derive(Debug, Clone)]
+pub enum ViewEvent {
+    /// What seems to run in the session now
+    Job(Option<ForegroundJob>),
+    /// Output matched a notifying or tab-marking trigger
+    Trigger(TriggerHit),
+    /// The tab's scratchpad after an edit settled
+    Scratchpad(String, ScratchpadFormat),
+}
+
+/// Where terminal views send `ViewEvent`s under their session id; see
+/// `AppState::view_reporter`
+pub type ViewReporter = mpsc::UnboundedSender<(String, ViewEvent)>;
+
+/// Sent on the settings-changed bus after settings were saved
+#[derive(Debug, Clone)]
+pub struct SettingsEvent {
+    pub settings: Arc<Settings>,
+    pub changes: Vec<SettingsChange>,
+}
+
+/// Connection requested over IPC
+#[derive(Debug, Clone)]
+pub struct PendingOpen {
+    pub session_id: String,
+    pub connection_id: String,
+}
+
+/// Transfer queued for an SFTP session's browser, requested over IPC or
+/// run again from the history
+#[derive(Debug, Clone)]
+pub struct IpcTransfer {
+    pub id: String,
+    pub session_id: String,
+    pub direction: IpcTransferDirection,
+    pub local: String,
+    pub remote: String,
+}
+
+/// Something the tab bar or the control socket asked of a terminal,
+/// carried out by its view
+#[derive(Debug, Clone, PartialEq)]
+pub enum TerminalRequest {
+    ClearScrollback,
+    /// Start the session log if `true`, stop it if `false`
+    SetLogging(bool),
+    SetReadOnly(bool),
+    /// Input from the control socket; it passes the same read-only and
+    /// command confirmation checks as typing
+    SendText(String),
 }
 
 pub struct Tab {
     pub id: String,
     pub title: String,
     pub tab_type: TabType,
+    /// Saved connection the tab was opened from, if any
+    pub connection_id: Option<String>,
+    /// Scratchpad notes, saved with the workspace
+    pub scratchpad: String,
+    pub scratchpad_format: ScratchpadFormat,
 }
 
 pub enum TabType {
@@ -29,49 +162,414 @@ pub enum TabType {
     Settings,
     Forwarding,
     ConnectionList,
+    Plugins,
+    /// Stored secrets audit
+    Security,
+    /// Read-only view of a remote file: session_id, path
+    FileViewer(String, String),
+    /// Local usage statistics
+    UsageStats,
+    /// Finished transfers of every session
+    TransferHistory,
+    /// Memory held by open sessions
+    Diagnostics,
 }
 
 impl AppState {
-    pub fn new() -> Result<Self> {
-        let db = Database::open()?;
-        let settings = Settings::load(&db)?;
-        let theme_manager = ThemeManager::new();
-        let runtime = std::sync::Arc::new(tokio::runtime::Runtime::new()?);
-        let session_manager = SessionManager::new(runtime);
+    /// Open the database and load everything the UI needs. Slow on a cold
+    /// disk, so the app runs it off the UI thread behind a skeleton screen.
+    pub fn load(profiler: &StartupProfiler) -> Result<Self> {
+        let db = profiler.phase("database", Database::open)?;
+        let settings = profiler.phase("settings", || Settings::load(&db))?;
+        crate::utils::crash::set_enabled(settings.crash_reports);
+        match db.all_host_names() {
+            Ok(hosts) => crate::utils::crash::set_known_hosts(hosts),
+            Err(e) => log::warn!("Could not load host names for crash reports: {}", e),
+        }
+        let theme_manager = profiler.phase("themes", || {
+            let mut themes = ThemeManager::new();
+            themes.load_custom_themes(&db);
+            themes
+        });
+        let ssh_config = profiler.phase("ssh config", || {
+            SshConfigParser::parse_default().unwrap_or_else(|e| {
+                log::warn!("Could not parse ~/.ssh/config: {}", e);
+                SshConfigParser::new()
+            })
+        });
+        let executor = profiler.phase("runtime", Executor::new)?;
+        let ipc = profiler.phase("ipc", || match crate::ipc::start(executor.runtime()) {
+            Ok(handle) => Some(handle),
+            Err(e) => {
+                log::warn!("Control socket unavailable: {}", e);
+                None
+            }
+        });
+        let session_manager = SessionManager::new(executor.clone());
+        let network = NetworkMonitor::start(&executor);
         let notification_manager = NotificationManager::new();
+        let (mfa_prompter, mfa_prompts) = mpsc::unbounded_channel();
+        let (host_key_prompter, host_key_prompts) = mpsc::unbounded_channel();
+        let (view_reporter, view_reports) = mpsc::unbounded_channel();
+        set_host_key_prompter(host_key_prompter);
+        set_challenge_prompter(mfa_prompter.clone());
+        let plugins = profiler.phase("plugins", || PluginRegistry::discover(PluginRegistry::default_dir(), &db));
         
-        Ok(Self {
+        let mut state = Self {
+            executor,
             db,
             settings,
             theme_manager,
             session_manager,
             notification_manager,
+            plugins,
+            ssh_config,
             active_tab: 0,
             tabs: Vec::new(),
-        })
+            tab_chrome: TabManager::new(),
+            file_viewers: HashMap::new(),
+            terminal_requests: Vec::new(),
+            ipc,
+            session_inputs: HashMap::new(),
+            session_starts: HashMap::new(),
+            foreground_jobs: HashMap::new(),
+            view_reporter,
+            view_reports,
+            pending_opens: Vec::new(),
+            pending_local_shells: Vec::new(),
+            ipc_transfers: Vec::new(),
+            network,
+            watches: Vec::new(),
+            bastions: HashMap::new(),
+            mfa_prompter,
+            mfa_prompts,
+            host_key_prompts,
+            settings_events: broadcast::channel(SETTINGS_EVENT_CAPACITY).0,
+            sync_task: None,
+        };
+
+        if state.settings.restore_previous_sessions {
+            profiler.phase("workspace", || state.restore_workspace());
+        }
+        profiler.phase("log retention", || {
+            if let Err(e) = retention::enforce(&state.db, &state.settings.log_retention) {
+                log::warn!("Could not apply log retention: {:#}", e);
+            }
+        });
+        Ok(state)
+    }
+
+    /// Reopen the terminal tabs saved at last exit; they connect through `pending_opens`
+    fn restore_workspace(&mut self) {
+        let workspace = match Workspace::load(&self.db) {
+            Ok(Some(workspace)) => workspace,
+            Ok(None) => return,
+            Err(e) => {
+                log::warn!("Could not read saved workspace: {}", e);
+                return;
+            }
+        };
+
+        for saved in workspace.tabs {
+            self.open_connection_tab(saved.connection_id, saved.title);
+            if let Some(tab) = self.tabs.last_mut() {
+                tab.scratchpad = saved.scratchpad;
+                tab.scratchpad_format = saved.scratchpad_format;
+            }
+        }
+        self.active_tab = workspace.active_tab.min(self.tabs.len().saturating_sub(1));
+    }
+
+    fn open_connection_tab(&mut self, connection_id: String, title: String) -> String {
+        let session_id = uuid::Uuid::new_v4().to_string();
+        self.add_terminal_tab(session_id.clone(), title);
+        if let Some(tab) = self.tabs.last_mut() {
+            tab.connection_id = Some(connection_id.clone());
+        }
+        self.pending_opens.push(PendingOpen { session_id: session_id.clone(), connection_id });
+        session_id
+    }
+
+    /// Tab for the drop-down window: the chosen profile, or a local shell.
+    /// The main window keeps its active tab. Returns the tab id.
+    pub fn open_quake_tab(&mut self, profile_id: Option<String>) -> String {
+        let active = self.active_tab;
+        match profile_id {
+            Some(connection_id) => {
+                let title: String = self.db.connection()
+                    .query_row("SELECT name FROM connections WHERE id = ?1", [&connection_id], |row| row.get(0))
+                    .unwrap_or_else(|_| "Drop-down".to_string());
+                self.open_connection_tab(connection_id, title);
+            }
+            None => {
+                let session_id = uuid::Uuid::new_v4().to_string();
+                self.add_terminal_tab(session_id.clone(), "Local shell".to_string());
+                self.pending_local_shells.push(session_id);
+            }
+        }
+        self.active_tab = active;
+        self.tabs.last().map(|tab| tab.id.clone()).unwrap_or_default()
+    }
+
+    /// What would be cut off by quitting now, or `None` if nothing is running
+    pub fn exit_warning(&self) -> Option<String> {
+        let sessions = self.session_inputs.values().filter(|s| !s.is_closed()).count();
+        let transfers = self.ipc_transfers.len();
+        let mut parts = Vec::new();
+        if sessions > 0 {
+            parts.push(format!("{} active session{}", sessions, if sessions == 1 { "" } else { "s" }));
+        }
+        if transfers > 0 {
+            parts.push(format!("{} pending transfer{}", transfers, if transfers == 1 { "" } else { "s" }));
+        }
+        (!parts.is_empty()).then(|| parts.join(" and "))
+    }
+
+    /// For a terminal view to report on its session with
+    /// `TerminalView::report_to`
+    pub fn view_reporter(&self) -> ViewReporter {
+        self.view_reporter.clone()
+    }
+
+    /// Take in what terminal views reported since last frame
+    pub fn poll_view_reports(&mut self) {
+        while let Ok((session_id, event)) = self.view_reports.try_recv() {
+            match event {
+                // Kept while the tab is open, whether or not the session is
+                ViewEvent::Scratchpad(text, format) => self.set_scratchpad(&session_id, text, format),
+                // A report can arrive after its session was unregistered
+                _ if !self.session_inputs.contains_key(&session_id) => {}
+                ViewEvent::Job(job) => self.set_foreground_job(&session_id, job),
+                ViewEvent::Trigger(hit) => self.apply_trigger_hit(&session_id, hit),
+            }
+        }
+    }
+
+    /// Notify, or flag the session's tab unless it is the one on screen
+    fn apply_trigger_hit(&mut self, session_id: &str, hit: TriggerHit) {
+        let Some(index) = self.tabs.iter()
+            .position(|tab| matches!(&tab.tab_type, TabType::Terminal(id) if id == session_id))
+        else {
+            return;
+        };
+        if hit.notify {
+            self.notification_manager.info(format!("{} in {}: {}", hit.trigger, self.tabs[index].title, hit.line.trim()));
+        }
+        if hit.mark_tab && index != self.active_tab {
+            let chrome = uuid::Uuid::parse_str(&self.tabs[index].id).ok().and_then(|id| self.tab_chrome.get_tab_mut(id));
+            if let Some(chrome) = chrome {
+                chrome.set_alert(hit.trigger);
+            }
+        }
+    }
+
+    /// Keep what `TerminalView::foreground_job` reports for a session
+    fn set_foreground_job(&mut self, session_id: &str, job: Option<ForegroundJob>) {
+        match job {
+            Some(job) => self.foreground_jobs.insert(session_id.to_string(), job),
+            None => self.foreground_jobs.remove(session_id),
+        };
+    }
+
+    /// "title: what runs" for each terminal tab that seems busy
+    pub fn busy_tabs(&self) -> Vec<String> {
+        (0..self.tabs.len()).filter_map(|index| self.busy_tab(index)).collect()
+    }
+
+    /// Why closing the tab at `index` should be confirmed, if it should
+    pub fn busy_tab(&self, index: usize) -> Option<String> {
+        let job = self.tab_job(index)?;
+        Some(format!("{}: {}", self.tabs[index].title, job))
+    }
+
+    /// What seems to run in the live session of the tab at `index`
+    pub fn tab_job(&self, index: usize) -> Option<String> {
+        let TabType::Terminal(session_id) = &self.tabs.get(index)?.tab_type else { return None };
+        if self.session_inputs.get(session_id).map_or(true, |input| input.is_closed()) {
+            return None;
+        }
+        Some(self.foreground_jobs.get(session_id)?.to_string())
+    }
+
+    /// Close sessions cleanly and persist state before the window goes away
+    pub fn shutdown(&mut self) {
+        log::info!("Shutting down");
+
+        if self.settings.restore_previous_sessions {
+            self.save_workspace();
+        } else if let Err(e) = Workspace::clear(&self.db) {
+            log::warn!("Could not clear saved workspace: {}", e);
+        }
+
+        if let Err(e) = self.save_settings() {
+            log::warn!("Could not save settings: {}", e);
+        }
+
+        for (session_id, (started, started_at)) in std::mem::take(&mut self.session_starts) {
+            self.record_session(&session_id, started.elapsed(), started_at);
+        }
+        for sender in self.session_inputs.values() {
+            let _ = sender.try_send(SessionCommand::Disconnect);
+        }
+        // Session tasks drop their receiver once the SSH disconnect is sent
+        let deadline = Instant::now() + SHUTDOWN_GRACE;
+        while Instant::now() < deadline && self.session_inputs.values().any(|s| !s.is_closed()) {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        self.session_inputs.clear();
+
+        log::logger().flush();
     }
     
+    /// Store the open terminal tabs, with their scratchpads
+    fn save_workspace(&self) {
+        let workspace = Workspace {
+            tabs: self.tabs.iter()
+                .filter(|tab| matches!(tab.tab_type, TabType::Terminal(_)))
+                .filter_map(|tab| Some(WorkspaceTab {
+                    connection_id: tab.connection_id.clone()?,
+                    title: tab.title.clone(),
+                    scratchpad: tab.scratchpad.clone(),
+                    scratchpad_format: tab.scratchpad_format,
+                }))
+                .collect(),
+            active_tab: self.active_tab,
+        };
+        if let Err(e) = workspace.save(&self.db) {
+            log::warn!("Could not save workspace: {}", e);
+        }
+    }
+
+    /// Keep the scratchpad of a session's tab; saved right away so notes
+    /// survive a crash
+    fn set_scratchpad(&mut self, session_id: &str, text: String, format: ScratchpadFormat) {
+        let Some(tab) = self.tabs.iter_mut()
+            .find(|tab| matches!(&tab.tab_type, TabType::Terminal(id) if id == session_id))
+        else {
+            return;
+        };
+        tab.scratchpad = text;
+        tab.scratchpad_format = format;
+        if self.settings.restore_previous_sessions {
+            self.save_workspace();
+        }
+    }
+
     pub fn add_terminal_tab(&mut self, session_id: String, title: String) {
+        let id = uuid::Uuid::new_v4();
+        self.tab_chrome.add_tab(TabChrome::for_app_tab(id, &title));
         self.tabs.push(Tab {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: id.to_string(),
             title,
             tab_type: TabType::Terminal(session_id),
+            connection_id: None,
+            scratchpad: String::new(),
+            scratchpad_format: ScratchpadFormat::Plain,
         });
         self.active_tab = self.tabs.len() - 1;
     }
     
-    pub fn add_sftp_tab(&mut self, session_id: String, title: String) {
+    /// Show the tab of a page like the usage statistics, opening it if needed
+    pub fn open_tool_tab(&mut self, tab_type: TabType, title: &str) {
+        let same = |tab: &Tab| std::mem::discriminant(&tab.tab_type) == std::mem::discriminant(&tab_type);
+        if let Some(index) = self.tabs.iter().position(same) {
+            self.active_tab = index;
+            return;
+        }
+        let id = uuid::Uuid::new_v4();
+        self.tab_chrome.add_tab(TabChrome::for_app_tab(id, title));
         self.tabs.push(Tab {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: id.to_string(),
+            title: title.to_string(),
+            tab_type,
+            connection_id: None,
+            scratchpad: String::new(),
+            scratchpad_format: ScratchpadFormat::Plain,
+        });
+        self.active_tab = self.tabs.len() - 1;
+    }
+
+    /// Usage figures since the RFC 3339 timestamp `since`, or `None` after
+    /// telling the user why they could not be read
+    pub fn usage_stats(&mut self, since: Option<&str>) -> Option<UsageStats> {
+        match UsageStats::load(&self.db, since) {
+            Ok(stats) => Some(stats),
+            Err(e) => {
+                self.notification_manager.error(format!("Could not read usage statistics: {}", e));
+                None
+            }
+        }
+    }
+
+    /// SFTP browser tab; `connection_id` is the saved connection it browses
+    pub fn add_sftp_tab(&mut self, session_id: String, title: String, connection_id: Option<String>) {
+        let id = uuid::Uuid::new_v4();
+        self.tab_chrome.add_tab(TabChrome::for_app_tab(id, &title));
+        self.tabs.push(Tab {
+            id: id.to_string(),
             title,
             tab_type: TabType::Sftp(session_id),
+            connection_id,
+            scratchpad: String::new(),
+            scratchpad_format: ScratchpadFormat::Plain,
         });
         self.active_tab = self.tabs.len() - 1;
     }
     
+    /// Show `path` from a live session in a viewer tab. It reads on an SFTP
+    /// channel of its own, so following a file never holds up the
+    /// session's browser.
+    pub fn open_file_viewer(&mut self, session_id: &str, path: &str) {
+        let Some(input) = self.session_inputs.get(session_id).filter(|input| !input.is_closed()).cloned() else {
+            self.notification_manager.error(format!("Cannot open {}: the session has closed", path));
+            return;
+        };
+        let connection_name = self.tabs.iter()
+            .find(|tab| matches!(&tab.tab_type, TabType::Terminal(id) | TabType::Sftp(id) if id == session_id))
+            .map_or_else(|| session_id.to_string(), |tab| tab.title.clone());
+        let mut screen = FileViewerScreen::new(connection_name, path.to_string());
+        let (events, stream) = mpsc::unbounded_channel();
+        screen.set_stream(stream);
+
+        let follow = screen.follow_flag();
+        let remote_path = path.to_string();
+        self.executor.spawn_detached(async move {
+            let (reply, client) = oneshot::channel();
+            let result = match input.send(SessionCommand::OpenSftp(reply)).await {
+                Ok(()) => match client.await {
+                    Ok(Ok(sftp)) => tail_file(sftp, &remote_path, follow, events.clone()).await,
+                    Ok(Err(e)) => Err(e),
+                    Err(_) => Err(anyhow::anyhow!("Session closed")),
+                },
+                Err(_) => Err(anyhow::anyhow!("Session closed")),
+            };
+            if let Err(e) = result {
+                let _ = events.send(TailEvent::Failed(e.to_string()));
+            }
+        });
+
+        let title = path.rsplit('/').next().filter(|name| !name.is_empty()).unwrap_or(path).to_string();
+        let id = uuid::Uuid::new_v4();
+        self.tab_chrome.add_tab(TabChrome::for_app_tab(id, &title));
+        self.file_viewers.insert(id.to_string(), screen);
+        self.tabs.push(Tab {
+            id: id.to_string(),
+            title,
+            tab_type: TabType::FileViewer(session_id.to_string(), path.to_string()),
+            connection_id: None,
+            scratchpad: String::new(),
+            scratchpad_format: ScratchpadFormat::Plain,
+        });
+        self.active_tab = self.tabs.len() - 1;
+    }
+
     pub fn close_tab(&mut self, index: usize) {
         if index < self.tabs.len() {
-            self.tabs.remove(index);
+            let tab = self.tabs.remove(index);
+            self.file_viewers.remove(&tab.id);
+            if let Ok(id) = uuid::Uuid::parse_str(&tab.id) {
+                self.tab_chrome.close_tab(id);
+            }
             if self.active_tab >= self.tabs.len() && !self.tabs.is_empty(){
                 self.active_tab = self.tabs.len() - 1;
             }
@@ -94,8 +592,521 @@ impl AppState {
         }
     }
     
+    /// Bring the tab bar up to date with the tabs: active tab and default titles
+    pub fn sync_tab_chrome(&mut self) {
+        for tab in &self.tabs {
+            if let Some(chrome) = uuid::Uuid::parse_str(&tab.id).ok().and_then(|id| self.tab_chrome.get_tab_mut(id)) {
+                chrome.set_title(tab.title.clone());
+            }
+        }
+        if let Some(id) = self.tabs.get(self.active_tab).and_then(|tab| uuid::Uuid::parse_str(&tab.id).ok()) {
+            self.tab_chrome.set_active_tab(id);
+        }
+    }
+
+    fn tab_index(&self, id: uuid::Uuid) -> Option<usize> {
+        let id = id.to_string();
+        self.tabs.iter().position(|tab| tab.id == id)
+    }
+
+    /// Carry out an action from the tab bar. Closing right away skips the
+    /// busy check, so the window asks first and calls `close_tab` itself.
+    pub fn apply_tab_bar_action(&mut self, action: TabBarAction) {
+        if let TabBarAction::Close(id) = action {
+            if let Some(index) = self.tab_index(id) {
+                self.close_tab(index);
+            }
+            return;
+        }
+        let Some(action) = self.tab_chrome.apply_tab_bar_action(action) else {
+            // Selecting a tab changes the active one
+            if let Some(index) = self.tab_chrome.active_tab_id().and_then(|id| self.tab_index(id)) {
+                self.active_tab = index;
+            }
+            return;
+        };
+
+        let id = match &action {
+            TabBarAction::Duplicate(id)
+            | TabBarAction::Disconnect(id)
+            | TabBarAction::Reconnect(id)
+            | TabBarAction::ClearScrollback(id)
+            | TabBarAction::SetLogging(id, _)
+            | TabBarAction::SetReadOnly(id, _) => *id,
+            _ => return,
+        };
+        let Some(index) = self.tab_index(id) else { return };
+        let session_id = match &self.tabs[index].tab_type {
+            TabType::Terminal(session_id) | TabType::Sftp(session_id) => session_id.clone(),
+            _ => return,
+        };
+
+        match action {
+            TabBarAction::Duplicate(_) => self.duplicate_tab(index),
+            TabBarAction::Disconnect(_) => {
+                if let Some(sender) = self.session_inputs.get(&session_id) {
+                    let _ = sender.try_send(SessionCommand::Disconnect);
+                }
+                self.set_tab_status(id, TabStatus::Disconnected);
+            }
+            TabBarAction::Reconnect(_) => {
+                match self.tabs[index].connection_id.clone() {
+                    Some(connection_id) => self.pending_opens.push(PendingOpen { session_id, connection_id }),
+                    None => self.pending_local_shells.push(session_id),
+                }
+                self.set_tab_status(id, TabStatus::Connecting);
+            }
+            TabBarAction::ClearScrollback(_) => {
+                self.terminal_requests.push((session_id, TerminalRequest::ClearScrollback));
+            }
+            TabBarAction::SetLogging(_, logging) => {
+                if let Some(tab) = self.tab_chrome.get_tab_mut(id) {
+                    tab.set_logging(logging);
+                }
+                self.terminal_requests.push((session_id, TerminalRequest::SetLogging(logging)));
+            }
+            TabBarAction::SetReadOnly(_, read_only) => {
+                self.terminal_requests.push((session_id, TerminalRequest::SetReadOnly(read_only)));
+            }
+            _ => {}
+        }
+    }
+
+    /// Open another tab on the same connection, or another local shell,
+    /// right after the original; it keeps the name and color
+    fn duplicate_tab(&mut self, index: usize) {
+        let original = &self.tabs[index];
+        if !matches!(original.tab_type, TabType::Terminal(_)) {
+            return;
+        }
+        let copy = uuid::Uuid::parse_str(&original.id).ok().and_then(|id| self.tab_chrome.duplicate_tab(id));
+        let Some(copy) = copy else { return };
+
+        let session_id = uuid::Uuid::new_v4().to_string();
+        let connection_id = original.connection_id.clone();
+        self.tabs.insert(index + 1, Tab {
+            id: copy.to_string(),
+            title: original.title.clone(),
+            tab_type: TabType::Terminal(session_id.clone()),
+            connection_id: connection_id.clone(),
+            scratchpad: String::new(),
+            scratchpad_format: ScratchpadFormat::Plain,
+        });
+        self.active_tab = index + 1;
+        match connection_id {
+            Some(connection_id) => self.pending_opens.push(PendingOpen { session_id, connection_id }),
+            None => self.pending_local_shells.push(session_id),
+        }
+    }
+
+    fn set_tab_status(&mut self, id: uuid::Uuid, status: TabStatus) {
+        if let Some(tab) = self.tab_chrome.get_tab_mut(id) {
+            tab.set_status(status);
+        }
+    }
+
+    /// Tab bar requests for the terminal view of a session
+    pub fn take_terminal_requests(&mut self, session_id: &str) -> Vec<TerminalRequest> {
+        let (taken, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.terminal_requests)
+            .into_iter()
+            .partition(|(id, _)| id == session_id);
+        self.terminal_requests = kept;
+        taken.into_iter().map(|(_, request)| request).collect()
+    }
+
     pub fn save_settings(&self) -> Result<()> {
         self.settings.save(&self.db)?;
         Ok(())
     }
+
+    /// Save new settings and apply them to open sessions: keepalive timers
+    /// change here, views pick up the rest from `subscribe_settings`
+    pub fn update_settings(&mut self, settings: Settings) -> Result<()> {
+        let changes = self.settings.changes(&settings);
+        crate::utils::crash::set_enabled(settings.crash_reports);
+        self.settings = settings;
+        self.save_settings()?;
+        if changes.is_empty() {
+            return Ok(());
+        }
+        log::debug!("Applying settings changes {:?}", changes);
+
+        if changes.iter().any(|c| matches!(c, SettingsChange::Keepalive(_))) {
+            for sender in self.session_inputs.values() {
+                let _ = sender.try_send(SessionCommand::SetKeepalive(self.keepalive_interval()));
+            }
+        }
+        // No subscribers is fine: nothing is open to update
+        let _ = self.settings_events.send(SettingsEvent {
+            settings: Arc::new(self.settings.clone()),
+            changes,
+        });
+        Ok(())
+    }
+
+    /// Settings changes from now on; poll with `try_recv` each frame
+    pub fn subscribe_settings(&self) -> broadcast::Receiver<SettingsEvent> {
+        self.settings_events.subscribe()
+    }
+
+    fn keepalive_interval(&self) -> Option<Duration> {
+        match self.settings.keepalive_interval {
+            0 => None,
+            secs => Some(Duration::from_secs(secs as u64)),
+        }
+    }
+
+    /// Probe every live session after a network change or resume, so dead
+    /// ones fail now rather than after the TCP timeout. Returns the events
+    /// for views that track staleness themselves.
+    pub fn poll_network(&mut self) -> Vec<NetworkEvent> {
+        let events = self.network.poll();
+        if !events.is_empty() {
+            log::info!("{:?}: probing {} session(s)", events, self.session_inputs.len());
+            for sender in self.session_inputs.values() {
+                let _ = sender.try_send(SessionCommand::Probe);
+            }
+        }
+        events
+    }
+
+    /// Probe the profile named `name` until its SSH server answers, then
+    /// notify; `auto_connect` also opens a tab for it
+    pub fn watch_host(&mut self, name: &str, auto_connect: bool) -> Result<()> {
+        if let Some(watch) = self.watches.iter_mut().find(|watch| watch.target.name == name) {
+            watch.target.auto_connect = auto_connect;
+            return Ok(());
+        }
+        let (connection_id, host, port, knock): (String, String, u16, String) = self.db.connection()
+            .query_row(
+                "SELECT id, host, port, knock_sequence FROM connections WHERE name = ?1",
+                [name],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .with_context(|| format!("No connection profile named '{}'", name))?;
+        let target = WatchTarget {
+            connection_id,
+            name: name.to_string(),
+            host,
+            port,
+            knock: serde_json::from_str(&knock).unwrap_or_default(),
+            auto_connect,
+        };
+        log::info!("Watching {}:{} until it answers", target.host, target.port);
+        self.watches.push(HostWatch::start(&self.executor, target));
+        Ok(())
+    }
+
+    pub fn stop_watching(&mut self, name: &str) {
+        self.watches.retain(|watch| watch.target.name != name);
+    }
+
+    /// Names of the watched profiles with their status
+    pub fn watch_statuses(&self) -> Vec<(String, WatchStatus)> {
+        self.watches.iter().map(|watch| (watch.target.name.clone(), watch.status())).collect()
+    }
+
+    /// Announce hosts that are back or given up on, and open the ones set to connect
+    pub fn poll_watches(&mut self) {
+        let mut index = 0;
+        while index < self.watches.len() {
+            match self.watches[index].poll() {
+                Some(status @ WatchStatus::Up { .. }) => {
+                    let target = self.watches.remove(index).target;
+                    self.notification_manager.success(format!("{} is reachable again. {}", target.name, status));
+                    if target.auto_connect {
+                        self.open_connection_tab(target.connection_id, target.name);
+                    }
+                }
+                Some(status @ WatchStatus::GaveUp { .. }) => {
+                    let target = self.watches.remove(index).target;
+                    self.notification_manager.warning(format!("Stopped watching {}. {}", target.name, status));
+                }
+                _ => index += 1,
+            }
+        }
+    }
+
+    /// The keeper of `hop`, logging in first if it is new. Connections through
+    /// the bastion open their tunnel on `BastionKeeper::connection`.
+    pub fn bastion(&mut self, hop: BastionHop, leaf_host: &str) -> &mut BastionKeeper {
+        let executor = self.executor.clone();
+        let prompter = self.mfa_prompter.clone();
+        let keeper = self.bastions.entry(hop.id.clone()).or_insert_with(|| {
+            let mut keeper = BastionKeeper::new(hop, prompter);
+            keeper.login(&executor);
+            keeper
+        });
+        keeper.add_leaf(leaf_host);
+        keeper
+    }
+
+    /// The bastion login to tunnel a connection to `leaf_host` through, for
+    /// `TerminalViewScreen::connect_through`. `Ok(None)` while the first
+    /// login runs. Once it has failed the error is returned and the keeper
+    /// dropped, so the next connect logs in afresh.
+    pub fn bastion_tunnel(&mut self, hop: BastionHop, leaf_host: &str) -> Result<Option<Arc<SshConnection>>> {
+        if let Some(e) = self.bastions.get(&hop.id).and_then(|keeper| keeper.failure()) {
+            let error = anyhow::anyhow!("Login to {} failed: {}", hop.id, e);
+            self.bastions.remove(&hop.id);
+            return Err(error);
+        }
+        Ok(self.bastion(hop, leaf_host).connection())
+    }
+
+    /// Renew bastion logins that are due and announce the outcome. Returns
+    /// how soon to poll again.
+    pub fn poll_bastions(&mut self) -> Option<Duration> {
+        let mut wait: Option<Duration> = None;
+        for keeper in self.bastions.values_mut() {
+            match keeper.poll(&self.executor) {
+                Some(BastionEvent::Renewed(id)) => self.notification_manager.success(format!("Verified again with {}", id)),
+                Some(BastionEvent::Failed(id, e)) => self.notification_manager.warning(format!("Login to {} failed: {}", id, e)),
+                Some(BastionEvent::LoggedIn(_)) | None => {}
+            }
+            // Questions arrive while a login runs and nothing else wakes the UI
+            let next = if keeper.is_logging_in() {
+                Some(Duration::from_millis(250))
+            } else {
+                keeper.renew_at().map(|at| at.saturating_duration_since(Instant::now()))
+            };
+            wait = match (wait, next) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+        }
+        wait
+    }
+
+    /// Sync with the configured target, for `SettingsAction::SyncNow`; the
+    /// outcome comes from `poll_sync`. Does nothing while a sync runs.
+    pub fn sync_now(&mut self) {
+        if self.is_syncing() {
+            return;
+        }
+        let config = SyncConfig::load(&self.db);
+        self.sync_task = Some(self.executor.spawn(async move {
+            let target = config.target.ok_or_else(|| anyhow::anyhow!("No sync target is set up"))?;
+            let ssh = match &target {
+                SyncTarget::Sftp { connection_id, .. } => Some(sync::connect_target(connection_id).await?),
+                _ => None,
+            };
+            let ctx = SyncContext::for_target(&target, ssh.as_ref());
+            let report = sync::run_sync(&target, config.strategy, &ctx).await;
+            if let Some(ssh) = ssh {
+                let _ = ssh.close().await;
+            }
+            report
+        }));
+    }
+
+    pub fn is_syncing(&self) -> bool {
+        self.sync_task.as_ref().map_or(false, |task| !task.is_done())
+    }
+
+    /// Announce a finished sync; returns its summary or error for the
+    /// settings screen's `sync_status`. Call every frame.
+    pub fn poll_sync(&mut self) -> Option<std::result::Result<String, String>> {
+        let result = self.sync_task.as_mut()?.poll()?;
+        self.sync_task = None;
+        match result.and_then(|report| report) {
+            Ok(report) => {
+                let summary = report.summary();
+                // Pulled settings and themes take effect without a restart
+                if report.pulled > 0 {
+                    match Settings::load(&self.db) {
+                        Ok(settings) => {
+                            if let Err(e) = self.update_settings(settings) {
+                                log::warn!("Could not apply synced settings: {:#}", e);
+                            }
+                        }
+                        Err(e) => log::warn!("Could not load synced settings: {:#}", e),
+                    }
+                    self.theme_manager.load_custom_themes(&self.db);
+                }
+                self.notification_manager.success(format!("Sync complete: {}", summary));
+                Some(Ok(summary))
+            }
+            Err(e) => {
+                self.notification_manager.warning(format!("Sync failed: {:#}", e));
+                Some(Err(format!("{:#}", e)))
+            }
+        }
+    }
+
+    /// The next bastion question for the dialog
+    pub fn next_mfa_prompt(&mut self) -> Option<MfaPrompt> {
+        self.mfa_prompts.try_recv().ok()
+    }
+
+    /// The next server key for the host key dialog
+    pub fn next_host_key_prompt(&mut self) -> Option<HostKeyPrompt> {
+        self.host_key_prompts.try_recv().ok()
+    }
+
+    /// Make a shell session reachable from `session.send`
+    pub fn register_session_input(&mut self, session_id: String, sender: mpsc::Sender<SessionCommand>) {
+        let _ = sender.try_send(SessionCommand::SetKeepalive(self.keepalive_interval()));
+        self.session_starts.insert(session_id.clone(), (Instant::now(), chrono::Utc::now()));
+        self.session_inputs.insert(session_id, sender);
+    }
+
+    pub fn unregister_session_input(&mut self, session_id: &str) {
+        self.session_inputs.remove(session_id);
+        self.foreground_jobs.remove(session_id);
+        if let Some((started, started_at)) = self.session_starts.remove(session_id) {
+            self.record_session(session_id, started.elapsed(), started_at);
+        }
+    }
+
+    /// Add a finished session to the history behind the usage statistics
+    fn record_session(&self, session_id: &str, duration: Duration, started_at: chrono::DateTime<chrono::Utc>) {
+        let tab = self.tabs.iter()
+            .find(|tab| matches!(&tab.tab_type, TabType::Terminal(id) if id == session_id));
+        let connection_id = tab.and_then(|tab| tab.connection_id.clone());
+        let host = connection_id.as_ref()
+            .and_then(|id| {
+                self.db.connection()
+                    .query_row("SELECT host FROM connections WHERE id = ?1", [id], |row| row.get(0))
+                    .ok()
+            })
+            .or_else(|| tab.map(|tab| tab.title.clone()))
+            .unwrap_or_else(|| "local".to_string());
+
+        let record = SessionRecord {
+            id: session_id.to_string(),
+            connection_id,
+            host,
+            started_at: started_at.to_rfc3339(),
+            duration_secs: duration.as_secs(),
+        };
+        if let Err(e) = self.db.add_session_record(&record) {
+            log::warn!("Could not record session: {}", e);
+        }
+    }
+
+    /// Queue a finished transfer again on an open SFTP session of the same
+    /// saved connection
+    pub fn rerun_transfer(&mut self, record: &TransferRecord) {
+        let session = self.tabs.iter()
+            .filter(|tab| record.connection_id.is_some() && tab.connection_id == record.connection_id)
+            .find_map(|tab| match &tab.tab_type {
+                TabType::Sftp(id) => Some(id.clone()),
+                _ => None,
+            });
+        let Some(session_id) = session else {
+            self.notification_manager.warning(format!("Open an SFTP session to {} to run this transfer again", record.host));
+            return;
+        };
+        let (direction, local, remote) = match record.direction.as_str() {
+            "upload" => (IpcTransferDirection::Upload, &record.source, &record.destination),
+            _ => (IpcTransferDirection::Download, &record.destination, &record.source),
+        };
+        self.ipc_transfers.push(IpcTransfer {
+            id: uuid::Uuid::new_v4().to_string(),
+            session_id,
+            direction,
+            local: local.clone(),
+            remote: remote.clone(),
+        });
+        self.notification_manager.info(format!("Queued {} again", record.source));
+    }
+
+    /// Put a profile's `tabssh://` share link on the clipboard
+    pub fn copy_share_link(&mut self, ctx: &egui::Context, profile: &SharedProfile) {
+        match profile.to_link() {
+            Ok(link) => {
+                ctx.output_mut(|o| o.copied_text = link);
+                self.notification_manager.info(format!("Copied a share link for {}", profile.name));
+            }
+            Err(e) => self.notification_manager.error(format!("Could not create a share link: {}", e)),
+        }
+    }
+
+    /// Transfers queued for an SFTP session, over IPC or from the history
+    pub fn take_ipc_transfers(&mut self, session_id: &str) -> Vec<IpcTransfer> {
+        let (taken, kept) = std::mem::take(&mut self.ipc_transfers)
+            .into_iter()
+            .partition(|t| t.session_id == session_id);
+        self.ipc_transfers = kept;
+        taken
+    }
+
+    /// Answer pending control socket requests; call once per frame.
+    /// While the app is `locked` nothing may be typed into a session.
+    pub fn poll_ipc(&mut self, locked: bool) {
+        while let Some(call) = self.ipc.as_mut().and_then(|ipc| ipc.try_recv()) {
+            let result = self.handle_ipc(&call.request, locked);
+            call.respond(result);
+        }
+    }
+
+    fn handle_ipc(&mut self, request: &IpcRequest, locked: bool) -> std::result::Result<Value, RpcError> {
+        match request {
+            IpcRequest::ListSessions => {
+                let sessions: Vec<_> = self.tabs.iter()
+                    .filter_map(|tab| match &tab.tab_type {
+                        TabType::Terminal(id) => Some(json!({ "session": id, "title": tab.title, "kind": "terminal" })),
+                        TabType::Sftp(id) => Some(json!({ "session": id, "title": tab.title, "kind": "sftp" })),
+                        _ => None,
+                    })
+                    .collect();
+                Ok(json!(sessions))
+            }
+            IpcRequest::OpenConnection { profile } => {
+                let connection_id: String = self.db.connection()
+                    .query_row(
+                        "SELECT id FROM connections WHERE name = ?1",
+                        [profile],
+                        |row| row.get(0),
+                    )
+                    .map_err(|_| RpcError::not_found(format!("no connection profile named '{}'", profile)))?;
+
+                let session_id = self.open_connection_tab(connection_id, profile.clone());
+                Ok(json!({ "session": session_id }))
+            }
+            IpcRequest::SendText { session, text } => {
+                if locked {
+                    return Err(RpcError::forbidden("TabSSH is locked"));
+                }
+                let tab = self.tabs.iter()
+                    .find(|tab| matches!(&tab.tab_type, TabType::Terminal(id) if id == session))
+                    .ok_or_else(|| RpcError::not_found(format!("no shell session '{}'", session)))?;
+                let read_only = uuid::Uuid::parse_str(&tab.id).ok()
+                    .and_then(|id| self.tab_chrome.get_tab(id))
+                    .is_some_and(|chrome| chrome.is_read_only());
+                if read_only {
+                    return Err(RpcError::forbidden(format!("session '{}' is read-only", session)));
+                }
+                // The view sends it through the same checks as typing, so a
+                // command for a critical host still waits for confirmation
+                self.terminal_requests.push((session.clone(), TerminalRequest::SendText(text.clone())));
+                Ok(json!({ "queued": text.len() }))
+            }
+            IpcRequest::StartTransfer { session, direction, local, remote } => {
+                let has_sftp = self.tabs.iter()
+                    .any(|tab| matches!(&tab.tab_type, TabType::Sftp(id) if id == session));
+                if !has_sftp {
+                    return Err(RpcError::not_found(format!("no SFTP session '{}'", session)));
+                }
+
+                let id = uuid::Uuid::new_v4().to_string();
+                self.ipc_transfers.push(IpcTransfer {
+                    id: id.clone(),
+                    session_id: session.clone(),
+                    direction: *direction,
+                    local: local.clone(),
+                    remote: remote.clone(),
+                });
+                Ok(json!({ "transfer": id }))
+            }
+            IpcRequest::Status => Ok(json!({
+                "version": env!("CARGO_PKG_VERSION"),
+                "tabs": self.tabs.len(),
+                "active_tab": self.tabs.get(self.active_tab).map(|t| t.title.clone()),
+                "shell_sessions": self.session_inputs.len(),
+                "queued_transfers": self.ipc_transfers.len(),
+            })),
+        }
+    }
 }
@@ -4,6 +4,8 @@
 #![allow(dead_code)]
 
 use eframe::egui::{self, Color32, RichText, Rounding, Stroke, Vec2};
+use super::tab::{TabColor, TabStatus};
+use super::tab_manager::TabManager;
 
 /// Color palette for the application
 pub mod colors {
@@ -383,3 +385,171 @@ pub fn with_tooltip<R>(ui: &mut egui::Ui, _tooltip: &str, add_contents: impl FnO
     let response = add_contents(ui);
     response
 }
+
+/// Browser-style tab strip with a right-click menu per tab
+#[derive(Default)]
+pub struct TabBar {
+    /// Tab being renamed and the name typed so far
+    renaming: Option<(uuid::Uuid, String)>,
+}
+
+/// Actions from the tab bar
+#[derive(Debug, Clone)]
+pub enum TabBarAction {
+    Select(uuid::Uuid),
+    Close(uuid::Uuid),
+    /// New name, or `None` to restore the default title
+    Rename(uuid::Uuid, Option<String>),
+    SetColor(uuid::Uuid, Option<TabColor>),
+    /// Ignore titles from the remote if `true`
+    LockTitle(uuid::Uuid, bool),
+    Duplicate(uuid::Uuid),
+    Disconnect(uuid::Uuid),
+    Reconnect(uuid::Uuid),
+    ClearScrollback(uuid::Uuid),
+    /// Start logging if `true`, stop if `false`
+    SetLogging(uuid::Uuid, bool),
+    /// Block typed input if `true`
+    SetReadOnly(uuid::Uuid, bool),
+}
+
+impl TabBar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Render the tabs; the caller applies the returned action
+    pub fn render(&mut self, ui: &mut egui::Ui, tabs: &TabManager) -> Option<TabBarAction> {
+        let mut action = None;
+
+        ui.horizontal(|ui| {
+            for tab in tabs.tabs() {
+                let id = tab.id();
+                let active = tabs.active_tab_id() == Some(id);
+
+                if let Some((renaming_id, name)) = &mut self.renaming {
+                    if *renaming_id == id {
+                        let response = ui.add(egui::TextEdit::singleline(name)
+                            .text_color(colors::TEXT_PRIMARY)
+                            .desired_width(140.0));
+                        response.request_focus();
+                        if response.lost_focus() {
+                            if !ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                                action = Some(TabBarAction::Rename(id, Some(name.clone())));
+                            }
+                            self.renaming = None;
+                        }
+                        continue;
+                    }
+                }
+
+                let accent = tab.color().map(|c| {
+                    let (r, g, b) = c.rgb();
+                    Color32::from_rgb(r, g, b)
+                });
+                let text_color = if active { colors::TEXT_PRIMARY } else { colors::TEXT_SECONDARY };
+                let mut label = if tab.is_logging() {
+                    format!("\u{25CF} {}", tab.title())
+                } else {
+                    tab.title().to_string()
+                };
+                if tab.alert().is_some() {
+                    label = format!("\u{2691} {}", label);
+                }
+                if tab.is_read_only() {
+                    label = format!("\u{1F441} {}", label);
+                }
+                if tab.blocked_title().is_some() {
+                    label = format!("\u{1F512} {}", label);
+                }
+
+                let mut response = ui.add(
+                    egui::Button::new(RichText::new(label).color(text_color).size(13.0))
+                        .fill(if active { colors::BG_TERTIARY } else { colors::BG_SECONDARY })
+                        .stroke(Stroke::new(if accent.is_some() { 2.0 } else { 1.0 }, accent.unwrap_or(colors::BORDER)))
+                        .rounding(Rounding { nw: 6.0, ne: 6.0, sw: 0.0, se: 0.0 })
+                );
+                if response.clicked() {
+                    action = Some(TabBarAction::Select(id));
+                }
+                if response.middle_clicked() {
+                    action = Some(TabBarAction::Close(id));
+                }
+                if let Some(blocked) = tab.blocked_title() {
+                    response = response.on_hover_text(format!("Title is locked; the remote tried to set \"{}\"", blocked));
+                }
+
+                response.context_menu(|ui| {
+                    if ui.button("Rename...").clicked() {
+                        self.renaming = Some((id, tab.title().to_string()));
+                        ui.close_menu();
+                    }
+                    if tab.custom_title().is_some() && ui.button("Reset Name").clicked() {
+                        action = Some(TabBarAction::Rename(id, None));
+                        ui.close_menu();
+                    }
+                    let mut locked = tab.is_title_locked();
+                    if ui.checkbox(&mut locked, "Lock Title").on_hover_text("Ignore titles set by the remote").clicked() {
+                        action = Some(TabBarAction::LockTitle(id, locked));
+                        ui.close_menu();
+                    }
+                    ui.menu_button("Color", |ui| {
+                        for color in TabColor::ALL {
+                            let (r, g, b) = color.rgb();
+                            let text = RichText::new(format!("\u{25A0} {}", color)).color(Color32::from_rgb(r, g, b));
+                            if ui.selectable_label(tab.color() == Some(color), text).clicked() {
+                                action = Some(TabBarAction::SetColor(id, Some(color)));
+                                ui.close_menu();
+                            }
+                        }
+                        if ui.button("None").clicked() {
+                            action = Some(TabBarAction::SetColor(id, None));
+                            ui.close_menu();
+                        }
+                    });
+                    ui.separator();
+                    if ui.button("Duplicate").clicked() {
+                        action = Some(TabBarAction::Duplicate(id));
+                        ui.close_menu();
+                    }
+                    match tab.status() {
+                        TabStatus::Connected | TabStatus::Connecting => {
+                            if ui.button("Disconnect").clicked() {
+                                action = Some(TabBarAction::Disconnect(id));
+                                ui.close_menu();
+                            }
+                        }
+                        TabStatus::Disconnected | TabStatus::Error(_) => {
+                            if ui.button("Reconnect").clicked() {
+                                action = Some(TabBarAction::Reconnect(id));
+                                ui.close_menu();
+                            }
+                        }
+                    }
+                    ui.separator();
+                    if ui.button("Clear Scrollback").clicked() {
+                        action = Some(TabBarAction::ClearScrollback(id));
+                        ui.close_menu();
+                    }
+                    let mut read_only = tab.is_read_only();
+                    if ui.checkbox(&mut read_only, "Read-only").on_hover_text("Show output but send nothing typed").clicked() {
+                        action = Some(TabBarAction::SetReadOnly(id, read_only));
+                        ui.close_menu();
+                    }
+                    let logging_label = if tab.is_logging() { "Stop Logging" } else { "Start Logging..." };
+                    if ui.button(logging_label).clicked() {
+                        action = Some(TabBarAction::SetLogging(id, !tab.is_logging()));
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button("Close Tab").clicked() {
+                        action = Some(TabBarAction::Close(id));
+                        ui.close_menu();
+                    }
+                });
+            }
+        });
+
+        action
+    }
+}
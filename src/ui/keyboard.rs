@@ -1,81 +1,55 @@
 //! Keyboard shortcut handling
 
-use egui::{Context, Key, Modifiers};
+use egui::{Context, Event, InputState, Key, Modifiers};
+use crate::config::keymap::{self, Chord, Keymap};
 
 pub struct KeyboardHandler;
 
 impl KeyboardHandler {
-    pub fn handle_shortcuts(ctx: &Context) -> Option<KeyboardAction> {
+    /// The action bound in `keymap` to a shortcut pressed this frame
+    pub fn handle_shortcuts(ctx: &Context, keymap: &Keymap) -> Option<KeyboardAction> {
         ctx.input(|i| {
-            // Ctrl+T - New tab
-            if i.modifiers.ctrl && i.key_pressed(Key::T) {
-                return Some(KeyboardAction::NewTab);
-            }
-            
-            // Ctrl+W - Close tab
-            if i.modifiers.ctrl && i.key_pressed(Key::W) {
-                return Some(KeyboardAction::CloseTab);
-            }
-            
-            // Ctrl+Tab - Next tab
-            if i.modifiers.ctrl && i.key_pressed(Key::Tab) {
-                return Some(KeyboardAction::NextTab);
-            }
-            
-            // Ctrl+Shift+Tab - Previous tab
-            if i.modifiers.ctrl && i.modifiers.shift && i.key_pressed(Key::Tab) {
-                return Some(KeyboardAction::PreviousTab);
-            }
-            
-            // Ctrl+N - New connection
-            if i.modifiers.ctrl && i.key_pressed(Key::N) {
-                return Some(KeyboardAction::NewConnection);
-            }
-            
-            // Ctrl+, - Settings
-            if i.modifiers.ctrl && i.key_pressed(Key::Comma) {
-                return Some(KeyboardAction::OpenSettings);
-            }
-            
-            // Ctrl+Q - Quit
-            if i.modifiers.ctrl && i.key_pressed(Key::Q) {
-                return Some(KeyboardAction::Quit);
-            }
-            
-            // Ctrl+F - Find
-            if i.modifiers.ctrl && i.key_pressed(Key::F) {
-                return Some(KeyboardAction::Find);
-            }
-            
-            // Ctrl++ - Increase font
-            if i.modifiers.ctrl && i.key_pressed(Key::Plus) {
-                return Some(KeyboardAction::IncreaseFontSize);
-            }
-            
-            // Ctrl+- - Decrease font
-            if i.modifiers.ctrl && i.key_pressed(Key::Minus) {
-                return Some(KeyboardAction::DecreaseFontSize);
-            }
-            
-            // Ctrl+0 - Reset font
-            if i.modifiers.ctrl && i.key_pressed(Key::Num0) {
-                return Some(KeyboardAction::ResetFontSize);
-            }
-            
-            // Alt+1-9 - Switch to tab N
-            if i.modifiers.alt {
-                for n in 1..=9 {
-                    if i.key_pressed(Key::from_name(&n.to_string()).unwrap_or(Key::Num0)) {
-                        return Some(KeyboardAction::SwitchToTab(n - 1));
-                    }
-                }
-            }
-            
-            None
+            keymap::ACTIONS.iter().find_map(|(id, _)| {
+                let chord = keymap::parse(keymap.binding(id)?)?;
+                if pressed(i, &chord) { KeyboardAction::from_id(id) } else { None }
+            })
         })
     }
 }
 
+/// Whether `chord` was pressed with exactly its modifiers, so Ctrl+Tab
+/// doesn't also fire for Ctrl+Shift+Tab. Cmd only exists on macOS.
+///
+/// Symbol keys often need Shift to type (Plus is Shift+= on a US layout),
+/// so Shift is ignored for them, and the physical key counts too: with Shift
+/// held, `]` reports as `}`, which egui has no key for.
+fn pressed(i: &InputState, chord: &Chord) -> bool {
+    let Some(key) = Key::from_name(&chord.key) else { return false };
+    if chord.command && !cfg!(target_os = "macos") {
+        return false;
+    }
+    let symbol = is_symbol(key);
+    let held = |m: &Modifiers| {
+        m.ctrl == chord.ctrl
+            && m.alt == chord.alt
+            && m.mac_cmd == chord.command
+            && (symbol || m.shift == chord.shift)
+    };
+    i.events.iter().any(|event| matches!(
+        event,
+        Event::Key { key: logical, physical_key, pressed: true, modifiers, .. }
+            if (*logical == key || (symbol && *physical_key == Some(key))) && held(modifiers)
+    ))
+}
+
+fn is_symbol(key: Key) -> bool {
+    matches!(
+        key,
+        Key::Plus | Key::Minus | Key::Equals | Key::Comma | Key::Period | Key::Colon
+            | Key::Semicolon | Key::Backslash | Key::OpenBracket | Key::CloseBracket | Key::Backtick
+    )
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum KeyboardAction {
     NewTab,
@@ -90,4 +64,31 @@ pub enum KeyboardAction {
     IncreaseFontSize,
     DecreaseFontSize,
     ResetFontSize,
+    TabOverview,
+    UsageStats,
+}
+
+impl KeyboardAction {
+    /// The action for a `keymap::ACTIONS` id
+    pub fn from_id(id: &str) -> Option<Self> {
+        Some(match id {
+            "new_tab" => KeyboardAction::NewTab,
+            "close_tab" => KeyboardAction::CloseTab,
+            "next_tab" => KeyboardAction::NextTab,
+            "previous_tab" => KeyboardAction::PreviousTab,
+            "new_connection" => KeyboardAction::NewConnection,
+            "open_settings" => KeyboardAction::OpenSettings,
+            "quit" => KeyboardAction::Quit,
+            "find" => KeyboardAction::Find,
+            "font_larger" => KeyboardAction::IncreaseFontSize,
+            "font_smaller" => KeyboardAction::DecreaseFontSize,
+            "font_reset" => KeyboardAction::ResetFontSize,
+            "tab_overview" => KeyboardAction::TabOverview,
+            "usage_stats" => KeyboardAction::UsageStats,
+            _ => {
+                let n: usize = id.strip_prefix("tab_")?.parse().ok()?;
+                KeyboardAction::SwitchToTab(n.checked_sub(1)?)
+            }
+        })
+    }
 }
@@ -0,0 +1,334 @@
+//! Auto-lock screen
+//!
+//! Covers the window after `auto_lock_timeout` minutes without input. It
+//! unlocks with the master password or, where enabled, Touch ID / Windows
+//! Hello; a failed or cancelled biometric prompt leaves the password field.
+//! The same prompt confirms key exports, which can be limited to the password.
+//! A portable copy started without a master password asks for one first,
+//! since its vault can't hold secrets until there is one.
+
+use crate::crypto::{master_password, vault};
+use crate::platform::{biometric, portable};
+use crate::storage::settings::Settings;
+use crate::storage::Database;
+use anyhow::Result;
+use egui::RichText;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// What the user is unlocking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnlockPurpose {
+    /// The locked window
+    Window,
+    /// Exporting a private key
+    KeyExport,
+    /// Nothing yet: choosing the master password a portable vault needs
+    SetUp,
+}
+
+pub struct AppLock {
+    last_activity: Instant,
+    /// Open prompt, if any
+    prompt: Option<UnlockPurpose>,
+    password: String,
+    /// Repeat of `password` while choosing one
+    confirm: String,
+    error: Option<String>,
+    /// Answer of a running biometric prompt
+    biometric: Option<mpsc::Receiver<Result<bool>>>,
+    /// Offer biometrics once by themselves when a prompt opens
+    biometric_offered: bool,
+    /// Checked once at startup; the Windows check is slow
+    biometric_available: bool,
+    export_approved: bool,
+}
+
+impl AppLock {
+    pub fn new() -> Self {
+        Self {
+            last_activity: Instant::now(),
+            prompt: None,
+            password: String::new(),
+            confirm: String::new(),
+            error: None,
+            biometric: None,
+            biometric_offered: false,
+            // Biometrics can't open the portable vault
+            biometric_available: biometric::available() && !portable::is_portable(),
+            export_approved: false,
+        }
+    }
+
+    pub fn is_locked(&self) -> bool {
+        matches!(self.prompt, Some(UnlockPurpose::Window | UnlockPurpose::SetUp))
+    }
+
+    /// At the start of a portable copy: unlock the vault, or choose the
+    /// master password for it if there is none yet
+    pub fn open_vault(&mut self, db: &Database, settings: &Settings) {
+        if master_password::is_set(db) {
+            self.lock(db, settings);
+        } else {
+            self.open(UnlockPurpose::SetUp);
+        }
+    }
+
+    /// Lock now, e.g. from a menu; ignored when nothing could unlock it
+    pub fn lock(&mut self, db: &Database, settings: &Settings) {
+        if master_password::is_set(db) || self.biometric_allowed(settings, UnlockPurpose::Window) {
+            self.open(UnlockPurpose::Window);
+        }
+    }
+
+    /// Ask the user to confirm a key export; see `take_export_approval`
+    pub fn request_key_export(&mut self) {
+        if self.prompt.is_none() {
+            self.open(UnlockPurpose::KeyExport);
+        }
+    }
+
+    /// Whether the user confirmed the requested key export
+    pub fn take_export_approval(&mut self) -> bool {
+        std::mem::take(&mut self.export_approved)
+    }
+
+    fn open(&mut self, purpose: UnlockPurpose) {
+        self.prompt = Some(purpose);
+        self.password.clear();
+        self.confirm.clear();
+        self.error = None;
+        self.biometric_offered = false;
+    }
+
+    fn biometric_allowed(&self, settings: &Settings, purpose: UnlockPurpose) -> bool {
+        biometric_allowed(settings, purpose, self.biometric_available)
+    }
+
+    /// Track input, lock when idle too long and draw any open prompt.
+    /// Returns true while the window is locked; the caller then skips the
+    /// rest of the frame.
+    pub fn update(&mut self, ctx: &egui::Context, db: &Database, settings: &Settings) -> bool {
+        if self.prompt.is_none() {
+            if ctx.input(|i| !i.events.is_empty() || i.pointer.is_moving()) {
+                self.last_activity = Instant::now();
+            } else if idle_expired(settings.auto_lock_timeout, self.last_activity.elapsed()) {
+                log::info!("Locking after {} minutes without input", settings.auto_lock_timeout);
+                self.lock(db, settings);
+                // Nothing can unlock it; don't check again every frame
+                self.last_activity = Instant::now();
+            }
+            if settings.auto_lock_timeout > 0 {
+                ctx.request_repaint_after(Duration::from_secs(30));
+            }
+        }
+
+        let Some(purpose) = self.prompt else { return false };
+        self.poll_biometric(purpose);
+        match purpose {
+            UnlockPurpose::SetUp => {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.add_space(ui.available_height() / 3.0);
+                        ui.heading("\u{1F511} Choose a master password");
+                        ui.add_space(12.0);
+                        self.render_setup(ui, db);
+                    });
+                });
+            }
+            UnlockPurpose::Window => {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.add_space(ui.available_height() / 3.0);
+                        ui.heading("\u{1F512} TabSSH is locked");
+                        ui.add_space(12.0);
+                        self.render_prompt(ui, db, settings, purpose);
+                    });
+                });
+            }
+            UnlockPurpose::KeyExport => {
+                let mut open = true;
+                egui::Window::new("Confirm Key Export")
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+                    .open(&mut open)
+                    .show(ctx, |ui| {
+                        ui.label("Exporting a private key needs your confirmation.");
+                        ui.add_space(8.0);
+                        self.render_prompt(ui, db, settings, purpose);
+                    });
+                if !open {
+                    self.prompt = None;
+                }
+            }
+        }
+        self.is_locked()
+    }
+
+    fn render_prompt(&mut self, ui: &mut egui::Ui, db: &Database, settings: &Settings, purpose: UnlockPurpose) {
+        let use_biometric = self.biometric_allowed(settings, purpose);
+        if use_biometric && !std::mem::replace(&mut self.biometric_offered, true) {
+            self.start_biometric(ui.ctx(), purpose);
+        }
+
+        if master_password::is_set(db) {
+            let response = ui.add(egui::TextEdit::singleline(&mut self.password)
+                .password(true)
+                .hint_text("Master password")
+                .desired_width(220.0));
+            if self.biometric.is_none() && !response.has_focus() {
+                response.request_focus();
+            }
+            let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("Unlock").clicked() || submitted {
+                if master_password::verify(db, &self.password) {
+                    if portable::is_portable() && !vault::is_unlocked() {
+                        if let Err(e) = vault::unlock(&self.password) {
+                            log::warn!("Could not open the vault: {:#}", e);
+                        }
+                    }
+                    self.unlock(purpose);
+                } else {
+                    self.password.clear();
+                    self.error = Some("Wrong master password".to_string());
+                }
+            }
+        } else if purpose == UnlockPurpose::KeyExport && !use_biometric {
+            // Nothing to confirm with; exporting needs a master password
+            ui.label("Set a master password in Settings > Security to export keys.");
+        }
+
+        if use_biometric {
+            let label = format!("Unlock with {}", biometric::name());
+            if ui.add_enabled(self.biometric.is_none(), egui::Button::new(label)).clicked() {
+                self.start_biometric(ui.ctx(), purpose);
+            }
+        }
+        if let Some(error) = &self.error {
+            ui.label(RichText::new(error).color(crate::ui::components::colors::ERROR));
+        }
+    }
+
+    /// Password and repeat for a portable copy's first start
+    fn render_setup(&mut self, ui: &mut egui::Ui, db: &Database) {
+        ui.label("This portable copy keeps saved passwords in an encrypted vault beside it, \
+                  opened with a master password at every start.");
+        ui.add_space(8.0);
+        let response = ui.add(egui::TextEdit::singleline(&mut self.password)
+            .password(true)
+            .hint_text("Master password")
+            .desired_width(220.0));
+        // Focus once; biometrics play no part here, so their flag is free
+        if !std::mem::replace(&mut self.biometric_offered, true) {
+            response.request_focus();
+        }
+        ui.add(egui::TextEdit::singleline(&mut self.confirm)
+            .password(true)
+            .hint_text("Repeat")
+            .desired_width(220.0));
+
+        let matches = !self.password.is_empty() && self.password == self.confirm;
+        if ui.add_enabled(matches, egui::Button::new("Set master password")).clicked() {
+            match master_password::set(db, &self.password) {
+                Ok(()) => self.unlock(UnlockPurpose::SetUp),
+                Err(e) => self.error = Some(format!("{:#}", e)),
+            }
+        }
+        if ui.button("Not now")
+            .on_hover_text("Passwords are not remembered until a master password is set in Settings > Security")
+            .clicked()
+        {
+            self.unlock(UnlockPurpose::SetUp);
+        }
+        if let Some(error) = &self.error {
+            ui.label(RichText::new(error).color(crate::ui::components::colors::ERROR));
+        }
+    }
+
+    /// Show the system prompt on a thread; it blocks until answered
+    fn start_biometric(&mut self, ctx: &egui::Context, purpose: UnlockPurpose) {
+        let reason = match purpose {
+            UnlockPurpose::Window | UnlockPurpose::SetUp => "unlock TabSSH",
+            UnlockPurpose::KeyExport => "export a private key",
+        };
+        let (tx, rx) = mpsc::channel();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let _ = tx.send(biometric::authenticate(reason));
+            ctx.request_repaint();
+        });
+        self.biometric = Some(rx);
+        self.error = None;
+    }
+
+    fn poll_biometric(&mut self, purpose: UnlockPurpose) {
+        let Some(rx) = &self.biometric else { return };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => Ok(false),
+        };
+        self.biometric = None;
+        match result {
+            Ok(true) => self.unlock(purpose),
+            Ok(false) => self.error = Some(format!("{} did not confirm; enter your master password", biometric::name())),
+            Err(e) => {
+                log::warn!("Biometric unlock failed: {:#}", e);
+                self.error = Some(format!("{:#}", e));
+            }
+        }
+    }
+
+    fn unlock(&mut self, purpose: UnlockPurpose) {
+        self.prompt = None;
+        self.password.clear();
+        self.confirm.clear();
+        self.error = None;
+        self.last_activity = Instant::now();
+        if purpose == UnlockPurpose::KeyExport {
+            self.export_approved = true;
+        }
+    }
+}
+
+impl Default for AppLock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether biometrics may answer a prompt for `purpose`
+fn biometric_allowed(settings: &Settings, purpose: UnlockPurpose, available: bool) -> bool {
+    available
+        && settings.biometric_unlock
+        && !(purpose == UnlockPurpose::KeyExport && settings.require_password_for_key_export)
+}
+
+/// Whether `idle` exceeds an auto-lock timeout in minutes; 0 never locks
+fn idle_expired(timeout_minutes: u32, idle: Duration) -> bool {
+    timeout_minutes > 0 && idle >= Duration::from_secs(timeout_minutes as u64 * 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_biometric_allowed() {
+        let mut settings = Settings { biometric_unlock: true, ..Settings::default() };
+        assert!(biometric_allowed(&settings, UnlockPurpose::Window, true));
+        assert!(!biometric_allowed(&settings, UnlockPurpose::Window, false));
+        assert!(!biometric_allowed(&settings, UnlockPurpose::KeyExport, true));
+
+        settings.require_password_for_key_export = false;
+        assert!(biometric_allowed(&settings, UnlockPurpose::KeyExport, true));
+    }
+
+    #[test]
+    fn test_idle_expired() {
+        assert!(!idle_expired(0, Duration::from_secs(86_400)));
+        assert!(!idle_expired(5, Duration::from_secs(299)));
+        assert!(idle_expired(5, Duration::from_secs(300)));
+    }
+}
@@ -0,0 +1,258 @@
+//! Minimal Markdown rendering for profile notes
+//!
+//! Covers what runbook notes use: headings, lists, quotes, fenced code,
+//! rules, and inline bold, italic, code and links. Anything else is shown
+//! as plain text.
+
+use eframe::egui::{self, RichText};
+use crate::ui::components::{colors, spacing};
+
+/// A block-level element
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    Heading(u8, String),
+    Paragraph(String),
+    Bullet(String),
+    Numbered(String, String),
+    Quote(String),
+    Code(String),
+    Rule,
+}
+
+/// An inline run of text
+#[derive(Debug, Clone, PartialEq)]
+pub enum Span {
+    Text(String),
+    Bold(String),
+    Italic(String),
+    Code(String),
+    Link(String, String),
+}
+
+/// Split Markdown into blocks; consecutive text lines form one paragraph
+pub fn parse_blocks(text: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut code: Option<Vec<&str>> = None;
+
+    let flush = |paragraph: &mut Vec<&str>, blocks: &mut Vec<Block>| {
+        if !paragraph.is_empty() {
+            blocks.push(Block::Paragraph(paragraph.join(" ")));
+            paragraph.clear();
+        }
+    };
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if let Some(lines) = code.as_mut() {
+            if trimmed.starts_with("```") {
+                blocks.push(Block::Code(lines.join("\n")));
+                code = None;
+            } else {
+                lines.push(line);
+            }
+            continue;
+        }
+
+        if trimmed.starts_with("```") {
+            flush(&mut paragraph, &mut blocks);
+            code = Some(Vec::new());
+        } else if trimmed.is_empty() {
+            flush(&mut paragraph, &mut blocks);
+        } else if let Some((level, heading)) = heading(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            blocks.push(Block::Heading(level, heading.to_string()));
+        } else if trimmed.len() >= 3 && (trimmed.chars().all(|c| c == '-') || trimmed.chars().all(|c| c == '*')) {
+            flush(&mut paragraph, &mut blocks);
+            blocks.push(Block::Rule);
+        } else if let Some(item) = trimmed.strip_prefix("- ").or_else(|| trimmed.strip_prefix("* ")) {
+            flush(&mut paragraph, &mut blocks);
+            blocks.push(Block::Bullet(item.to_string()));
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            flush(&mut paragraph, &mut blocks);
+            blocks.push(Block::Quote(quote.trim_start().to_string()));
+        } else if let Some((number, item)) = numbered(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            blocks.push(Block::Numbered(number.to_string(), item.to_string()));
+        } else {
+            paragraph.push(trimmed);
+        }
+    }
+
+    flush(&mut paragraph, &mut blocks);
+    if let Some(lines) = code {
+        // Unclosed fence: show what there is
+        blocks.push(Block::Code(lines.join("\n")));
+    }
+    blocks
+}
+
+fn heading(line: &str) -> Option<(u8, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let rest = line[level..].strip_prefix(' ')?;
+    (1..=6).contains(&level).then_some((level as u8, rest.trim()))
+}
+
+fn numbered(line: &str) -> Option<(&str, &str)> {
+    let (number, rest) = line.split_once(". ")?;
+    (!number.is_empty() && number.chars().all(|c| c.is_ascii_digit())).then_some((number, rest))
+}
+
+/// Split a line into inline spans
+pub fn parse_spans(text: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut plain = String::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        let found = match c {
+            '`' => delimited(rest, "`").map(|(inner, len)| (Span::Code(inner.to_string()), len)),
+            '*' if rest.starts_with("**") => delimited(rest, "**").map(|(inner, len)| (Span::Bold(inner.to_string()), len)),
+            // Not inside snake_case words
+            '*' | '_' if !plain.ends_with(|ch: char| ch.is_alphanumeric()) => delimited(rest, &c.to_string()).map(|(inner, len)| (Span::Italic(inner.to_string()), len)),
+            '[' => link(rest),
+            _ => None,
+        };
+        match found {
+            Some((span, len)) => {
+                if !plain.is_empty() {
+                    spans.push(Span::Text(std::mem::take(&mut plain)));
+                }
+                spans.push(span);
+                rest = &rest[len..];
+            }
+            None => {
+                plain.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    if !plain.is_empty() {
+        spans.push(Span::Text(plain));
+    }
+    spans
+}
+
+/// Text between a delimiter pair at the start of `text`, and the bytes consumed
+fn delimited<'a>(text: &'a str, delim: &str) -> Option<(&'a str, usize)> {
+    let body = &text[delim.len()..];
+    let end = body.find(delim)?;
+    (end > 0 && !body.starts_with(' ')).then(|| (&body[..end], delim.len() * 2 + end))
+}
+
+/// `[label](url)` at the start of `text`
+fn link(text: &str) -> Option<(Span, usize)> {
+    let close = text.find("](")?;
+    let end = text[close..].find(')')? + close;
+    let label = &text[1..close];
+    let url = &text[close + 2..end];
+    (!url.is_empty() && !label.contains('[')).then(|| (Span::Link(label.to_string(), url.to_string()), end + 1))
+}
+
+/// Render Markdown into the UI
+pub fn render(ui: &mut egui::Ui, text: &str) {
+    for block in parse_blocks(text) {
+        match block {
+            Block::Heading(level, text) => {
+                let size = match level {
+                    1 => 18.0,
+                    2 => 16.0,
+                    _ => 14.0,
+                };
+                ui.add_space(spacing::XS);
+                ui.label(RichText::new(text).size(size).strong().color(colors::TEXT_PRIMARY));
+            }
+            Block::Paragraph(text) => render_spans(ui, &text),
+            Block::Bullet(text) => {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(RichText::new("\u{2022}").color(colors::TEXT_MUTED));
+                    render_inline(ui, &text);
+                });
+            }
+            Block::Numbered(number, text) => {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(RichText::new(format!("{}.", number)).color(colors::TEXT_MUTED));
+                    render_inline(ui, &text);
+                });
+            }
+            Block::Quote(text) => {
+                egui::Frame::none()
+                    .stroke(egui::Stroke::new(1.0, colors::BORDER))
+                    .inner_margin(egui::Margin::symmetric(spacing::SM, spacing::XS))
+                    .show(ui, |ui| render_spans(ui, &text));
+            }
+            Block::Code(code) => {
+                egui::Frame::none()
+                    .fill(colors::BG_TERTIARY)
+                    .inner_margin(spacing::SM)
+                    .rounding(4.0)
+                    .show(ui, |ui| {
+                        ui.set_width(ui.available_width());
+                        ui.label(RichText::new(code).monospace().color(colors::TEXT_PRIMARY));
+                    });
+            }
+            Block::Rule => {
+                ui.separator();
+            }
+        }
+    }
+}
+
+fn render_spans(ui: &mut egui::Ui, text: &str) {
+    ui.horizontal_wrapped(|ui| render_inline(ui, text));
+}
+
+fn render_inline(ui: &mut egui::Ui, text: &str) {
+    ui.spacing_mut().item_spacing.x = 0.0;
+    for span in parse_spans(text) {
+        match span {
+            Span::Text(text) => {
+                ui.label(RichText::new(text).color(colors::TEXT_SECONDARY));
+            }
+            Span::Bold(text) => {
+                ui.label(RichText::new(text).strong().color(colors::TEXT_PRIMARY));
+            }
+            Span::Italic(text) => {
+                ui.label(RichText::new(text).italics().color(colors::TEXT_SECONDARY));
+            }
+            Span::Code(text) => {
+                ui.label(RichText::new(text).monospace().background_color(colors::BG_TERTIARY));
+            }
+            Span::Link(label, url) => {
+                ui.hyperlink_to(label, url);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks() {
+        let notes = "# Runbook\nRestart with\nsystemd.\n\n- call **ops**\n1. drain\n```\nsystemctl restart api\n```\n---";
+        assert_eq!(parse_blocks(notes), vec![
+            Block::Heading(1, "Runbook".into()),
+            Block::Paragraph("Restart with systemd.".into()),
+            Block::Bullet("call **ops**".into()),
+            Block::Numbered("1".into(), "drain".into()),
+            Block::Code("systemctl restart api".into()),
+            Block::Rule,
+        ]);
+    }
+
+    #[test]
+    fn test_spans() {
+        assert_eq!(parse_spans("Page **on-call** via [PD](https://pd.example) or `pager`"), vec![
+            Span::Text("Page ".into()),
+            Span::Bold("on-call".into()),
+            Span::Text(" via ".into()),
+            Span::Link("PD".into(), "https://pd.example".into()),
+            Span::Text(" or ".into()),
+            Span::Code("pager".into()),
+        ]);
+        // Unmatched delimiters stay literal
+        assert_eq!(parse_spans("2 * 3"), vec![Span::Text("2 * 3".into())]);
+    }
+}
@@ -4,11 +4,19 @@ pub mod app_state;
 pub mod components;
 // pub mod dialogs;  // TODO: Create dialogs module
 pub mod keyboard;
+pub mod lock;
+pub mod markdown;
 pub mod notifications;
+pub mod overview;
+pub mod quake;
 pub mod screens;
 pub mod search;
+pub mod tab;
+pub mod tab_manager;
 
 pub use app_state::AppState;
 pub use keyboard::{KeyboardHandler, KeyboardAction};
 pub use notifications::NotificationManager;
 pub use search::SearchWidget;
+pub use tab::{Tab, TabColor, TabStatus};
+pub use tab_manager::TabManager;
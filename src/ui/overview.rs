@@ -0,0 +1,260 @@
+//! Tab overview - every open tab as a live thumbnail in a grid
+//!
+//! Meant for running dozens of sessions at once. The grid is laid out by
+//! rows and only the rows on screen are painted, so thirty thumbnails cost
+//! little more than the ten that fit. Typing filters the tabs by title or
+//! what runs in them; arrows move the selection, and Enter or a click
+//! switches to the tab and closes the overview.
+
+use egui::{Color32, Key, Painter, Rect, RichText, Sense, Stroke, Ui};
+use std::time::Duration;
+use super::components::{colors, spacing};
+
+/// Thumbnails redraw at least this often while the overview is open
+const REFRESH: Duration = Duration::from_millis(500);
+/// Narrowest a thumbnail gets before a column is dropped
+const MIN_TILE_WIDTH: f32 = 240.0;
+/// Thumbnail height to width, about that of an 80x24 terminal
+const THUMBNAIL_ASPECT: f32 = 0.55;
+const CAPTION_HEIGHT: f32 = 36.0;
+
+/// One tab as the overview shows it
+#[derive(Debug, Clone)]
+pub struct OverviewTile {
+    pub title: String,
+    /// Second caption line, e.g. what runs in the session
+    pub detail: Option<String>,
+    pub active: bool,
+}
+
+impl OverviewTile {
+    /// Whether every word of `query` is in the title or the detail
+    fn matches(&self, query: &str) -> bool {
+        let title = self.title.to_lowercase();
+        let detail = self.detail.as_deref().unwrap_or_default().to_lowercase();
+        query
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .all(|term| title.contains(&term) || detail.contains(&term))
+    }
+}
+
+pub struct TabOverview {
+    open: bool,
+    query: String,
+    /// Position in the filtered tiles
+    selected: usize,
+    /// Focus the search field on the next frame
+    focus_search: bool,
+}
+
+impl TabOverview {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            query: String::new(),
+            selected: 0,
+            focus_search: false,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Open with the active tab selected, or close
+    pub fn toggle(&mut self, active: usize) {
+        self.open = !self.open;
+        if self.open {
+            self.query.clear();
+            self.selected = active;
+            self.focus_search = true;
+        }
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    /// Draw the grid into `ui`; `paint` draws the thumbnail of the tab at an
+    /// index of `tiles` into a rect. Returns the tab to switch to.
+    pub fn show(&mut self, ui: &mut Ui, tiles: &[OverviewTile], mut paint: impl FnMut(usize, &Painter, Rect)) -> Option<usize> {
+        let shown: Vec<usize> = (0..tiles.len()).filter(|&i| tiles[i].matches(&self.query)).collect();
+        let mut chosen = None;
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("Tabs").strong().color(colors::TEXT_PRIMARY));
+            let search = ui.add(egui::TextEdit::singleline(&mut self.query)
+                .hint_text("Filter by title or command")
+                .desired_width(280.0));
+            if std::mem::take(&mut self.focus_search) {
+                search.request_focus();
+            }
+            if search.changed() {
+                self.selected = 0;
+            }
+            ui.label(RichText::new(format!("{} of {}", shown.len(), tiles.len())).color(colors::TEXT_SECONDARY));
+        });
+        ui.add_space(spacing::SM);
+
+        let width = ui.available_width();
+        let columns = columns_for(width);
+        let tile_width = (width - spacing::MD * (columns - 1) as f32) / columns as f32;
+        let thumbnail_height = tile_width * THUMBNAIL_ASPECT;
+
+        let (left, right, up, down, enter, escape) = ui.input(|i| (
+            i.key_pressed(Key::ArrowLeft),
+            i.key_pressed(Key::ArrowRight),
+            i.key_pressed(Key::ArrowUp),
+            i.key_pressed(Key::ArrowDown),
+            i.key_pressed(Key::Enter),
+            i.key_pressed(Key::Escape),
+        ));
+        if escape {
+            self.open = false;
+            return None;
+        }
+        let last = shown.len().saturating_sub(1);
+        if left {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        if right {
+            self.selected += 1;
+        }
+        if up {
+            self.selected = self.selected.saturating_sub(columns);
+        }
+        if down && self.selected + columns <= last {
+            self.selected += columns;
+        }
+        self.selected = self.selected.min(last);
+        if enter {
+            chosen = shown.get(self.selected).copied();
+        }
+
+        if shown.is_empty() {
+            ui.add_space(spacing::XL);
+            ui.vertical_centered(|ui| {
+                ui.label(RichText::new("No tab matches").color(colors::TEXT_MUTED));
+            });
+        }
+
+        let rows = shown.len().div_ceil(columns);
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show_rows(ui, thumbnail_height + CAPTION_HEIGHT, rows, |ui, visible| {
+                for row in visible {
+                    ui.horizontal(|ui| {
+                        ui.spacing_mut().item_spacing.x = spacing::MD;
+                        for position in row * columns..((row + 1) * columns).min(shown.len()) {
+                            let index = shown[position];
+                            let tile = &tiles[index];
+                            let (rect, response) = ui.allocate_exact_size(
+                                egui::vec2(tile_width, thumbnail_height + CAPTION_HEIGHT),
+                                Sense::click(),
+                            );
+                            if (up || down) && position == self.selected {
+                                response.scroll_to_me(None);
+                            }
+                            let stroke = if position == self.selected {
+                                Stroke::new(2.0, colors::BORDER_FOCUS)
+                            } else if response.hovered() {
+                                Stroke::new(1.0, colors::TEXT_SECONDARY)
+                            } else {
+                                Stroke::new(1.0, colors::BORDER)
+                            };
+                            paint_tile(ui, rect, tile, stroke, thumbnail_height, |painter, thumbnail| paint(index, painter, thumbnail));
+                            if response.clicked() {
+                                chosen = Some(index);
+                            }
+                        }
+                    });
+                }
+            });
+
+        if chosen.is_some() {
+            self.open = false;
+        }
+        ui.ctx().request_repaint_after(REFRESH);
+        chosen
+    }
+}
+
+impl Default for TabOverview {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Columns of at least `MIN_TILE_WIDTH` that fit in `width`
+fn columns_for(width: f32) -> usize {
+    ((width + spacing::MD) / (MIN_TILE_WIDTH + spacing::MD)).floor().max(1.0) as usize
+}
+
+/// Thumbnail with the title and detail under it
+fn paint_tile(
+    ui: &Ui,
+    rect: Rect,
+    tile: &OverviewTile,
+    stroke: Stroke,
+    thumbnail_height: f32,
+    paint: impl FnOnce(&Painter, Rect),
+) {
+    if !ui.is_rect_visible(rect) {
+        return;
+    }
+    let thumbnail = Rect::from_min_size(rect.min, egui::vec2(rect.width(), thumbnail_height));
+    let painter = ui.painter_at(thumbnail);
+    painter.rect_filled(thumbnail, 4.0, Color32::from_rgb(30, 30, 30));
+    paint(&painter, thumbnail.shrink(spacing::XS));
+    ui.painter().rect_stroke(thumbnail, 4.0, stroke);
+
+    let caption = ui.painter_at(Rect::from_min_max(egui::pos2(rect.left(), thumbnail.bottom()), rect.max));
+    let mut title = egui::pos2(rect.left() + 2.0, thumbnail.bottom() + spacing::XS);
+    if tile.active {
+        caption.circle_filled(egui::pos2(title.x + 3.0, title.y + 7.0), 3.0, colors::PRIMARY);
+        title.x += 10.0;
+    }
+    caption.text(
+        title,
+        egui::Align2::LEFT_TOP,
+        &tile.title,
+        egui::FontId::proportional(13.0),
+        colors::TEXT_PRIMARY,
+    );
+    if let Some(detail) = &tile.detail {
+        caption.text(
+            egui::pos2(rect.left() + 2.0, title.y + 16.0),
+            egui::Align2::LEFT_TOP,
+            detail,
+            egui::FontId::proportional(11.0),
+            colors::TEXT_SECONDARY,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(title: &str, detail: Option<&str>) -> OverviewTile {
+        OverviewTile { title: title.to_string(), detail: detail.map(str::to_string), active: false }
+    }
+
+    #[test]
+    fn test_filter_matches_every_word() {
+        let web = tile("deploy@web-01", Some("tail -f /var/log/nginx/error.log"));
+        assert!(web.matches(""));
+        assert!(web.matches("WEB nginx"));
+        assert!(!web.matches("web postgres"));
+        assert!(tile("db-02", None).matches("db"));
+        assert!(!tile("db-02", None).matches("vim"));
+    }
+
+    #[test]
+    fn test_columns_for() {
+        assert_eq!(columns_for(100.0), 1);
+        assert_eq!(columns_for(MIN_TILE_WIDTH * 4.0 + spacing::MD * 3.0), 4);
+        assert_eq!(columns_for(MIN_TILE_WIDTH * 4.0 + spacing::MD * 2.0), 3);
+    }
+}
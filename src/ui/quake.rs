@@ -0,0 +1,155 @@
+//! Quake-style drop-down window
+//!
+//! A borderless, always-on-top window that slides down from the top of the
+//! screen when the global hotkey is pressed and slides back up on the next
+//! press. It shows one tab of its own, opened the first time it is summoned.
+
+use crate::platform::hotkey::GlobalHotkey;
+use crate::storage::settings::QuakeSettings;
+use std::time::{Duration, Instant};
+
+const SLIDE_DURATION: Duration = Duration::from_millis(160);
+
+/// Screen size to assume when the platform does not report the monitor
+const FALLBACK_SCREEN: egui::Vec2 = egui::vec2(1280.0, 800.0);
+
+pub struct QuakeWindow {
+    hotkey: Option<GlobalHotkey>,
+    /// Settings the hotkey was registered for
+    applied: Option<QuakeSettings>,
+    /// Why the hotkey could not be registered
+    pub error: Option<String>,
+    visible: bool,
+    /// Start of the current slide and whether it slides in
+    slide: Option<(Instant, bool)>,
+    /// Take keyboard focus once the window exists
+    focus_pending: bool,
+    /// Tab shown in the drop-down
+    pub tab_id: Option<String>,
+}
+
+impl QuakeWindow {
+    pub fn new() -> Self {
+        Self {
+            hotkey: None,
+            applied: None,
+            error: None,
+            visible: false,
+            slide: None,
+            focus_pending: false,
+            tab_id: None,
+        }
+    }
+
+    /// Register or drop the hotkey to match `settings`; cheap when unchanged
+    pub fn apply(&mut self, settings: &QuakeSettings, ctx: &egui::Context) {
+        if self.applied.as_ref() == Some(settings) {
+            return;
+        }
+        self.applied = Some(settings.clone());
+        self.hotkey = None;
+        self.error = None;
+        if !settings.enabled {
+            self.visible = false;
+            return;
+        }
+        match GlobalHotkey::register(&settings.hotkey, ctx.clone()) {
+            Ok(hotkey) => self.hotkey = Some(hotkey),
+            Err(e) => {
+                log::warn!("Drop-down terminal unavailable: {:#}", e);
+                self.error = Some(format!("{:#}", e));
+            }
+        }
+    }
+
+    /// Toggle on a hotkey press. Returns true when summoned without a tab,
+    /// so the caller opens one and sets `tab_id`.
+    pub fn poll(&mut self) -> bool {
+        if !self.hotkey.as_ref().is_some_and(|h| h.pressed()) {
+            return false;
+        }
+        self.visible = !self.visible;
+        self.slide = Some((Instant::now(), self.visible));
+        self.focus_pending = self.visible;
+        self.visible && self.tab_id.is_none()
+    }
+
+    /// Hide without waiting for the hotkey, e.g. when its tab was closed
+    pub fn hide(&mut self) {
+        if self.visible {
+            self.visible = false;
+            self.slide = Some((Instant::now(), false));
+        }
+    }
+
+    /// Draw the window while it is down or sliding
+    pub fn show(&mut self, ctx: &egui::Context, settings: &QuakeSettings, add_contents: impl FnOnce(&mut egui::Ui)) {
+        let fraction = match self.slide {
+            Some((started, sliding_in)) => {
+                let t = (started.elapsed().as_secs_f32() / SLIDE_DURATION.as_secs_f32()).min(1.0);
+                if t >= 1.0 {
+                    self.slide = None;
+                } else {
+                    ctx.request_repaint();
+                }
+                slide_fraction(t, sliding_in)
+            }
+            None if self.visible => 1.0,
+            None => return,
+        };
+        if fraction <= 0.0 {
+            return;
+        }
+
+        let screen = ctx.input(|i| i.viewport().monitor_size).unwrap_or(FALLBACK_SCREEN);
+        let height = screen.y * settings.height_percent.clamp(10, 100) as f32 / 100.0;
+        let id = egui::ViewportId::from_hash_of("quake");
+        let mut close = false;
+        ctx.show_viewport_immediate(
+            id,
+            egui::ViewportBuilder::default()
+                .with_title("TabSSH")
+                .with_decorations(false)
+                .with_always_on_top()
+                .with_position([0.0, 0.0])
+                .with_inner_size([screen.x, (height * fraction).max(1.0)]),
+            |ctx, _class| {
+                egui::CentralPanel::default().show(ctx, add_contents);
+                close = ctx.input(|i| i.viewport().close_requested());
+            },
+        );
+
+        if std::mem::take(&mut self.focus_pending) {
+            ctx.send_viewport_cmd_to(id, egui::ViewportCommand::Focus);
+        }
+        if close {
+            self.hide();
+        }
+    }
+}
+
+impl Default for QuakeWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Visible share of the window at `t` (0..=1) into a slide, eased out
+fn slide_fraction(t: f32, sliding_in: bool) -> f32 {
+    let eased = 1.0 - (1.0 - t.clamp(0.0, 1.0)).powi(3);
+    if sliding_in { eased } else { 1.0 - eased }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slide_fraction() {
+        assert_eq!(slide_fraction(0.0, true), 0.0);
+        assert_eq!(slide_fraction(1.0, true), 1.0);
+        assert_eq!(slide_fraction(1.0, false), 0.0);
+        // Eased out: most of the distance is covered early
+        assert!(slide_fraction(0.5, true) > 0.8);
+    }
+}
@@ -0,0 +1,224 @@
+//! Cloud Browser Screen - running instances from AWS, Google Cloud and Azure
+
+#![allow(dead_code)]
+
+use eframe::egui::{self, RichText};
+use egui_extras::{Column, TableBuilder};
+use crate::integrations::cloud::{CloudInstance, CloudProvider, CloudQuery};
+use crate::ui::components::{colors, spacing, primary_button, secondary_button, text_input, empty_state};
+
+/// Cloud instance browser state
+pub struct CloudBrowserScreen {
+    provider: CloudProvider,
+    scope: String,
+    region: String,
+    filter: String,
+    instances: Option<Vec<CloudInstance>>,
+    /// Query the current instances came from, for tunnel commands
+    listed: Option<CloudQuery>,
+    loading: bool,
+    error: Option<String>,
+}
+
+impl CloudBrowserScreen {
+    pub fn new() -> Self {
+        Self {
+            provider: CloudProvider::Aws,
+            scope: String::new(),
+            region: String::new(),
+            filter: String::new(),
+            instances: None,
+            listed: None,
+            loading: false,
+            error: None,
+        }
+    }
+
+    fn query(&self) -> CloudQuery {
+        CloudQuery {
+            provider: self.provider,
+            scope: self.scope.trim().to_string(),
+            region: self.region.trim().to_string(),
+        }
+    }
+
+    /// Mark a listing as in progress
+    pub fn set_loading(&mut self) {
+        self.loading = true;
+        self.error = None;
+    }
+
+    pub fn set_instances(&mut self, instances: Vec<CloudInstance>) {
+        self.listed = Some(self.query());
+        self.instances = Some(instances);
+        self.loading = false;
+    }
+
+    pub fn set_error(&mut self, error: String) {
+        self.error = Some(error);
+        self.loading = false;
+    }
+
+    /// Render the cloud browser
+    pub fn render(&mut self, ui: &mut egui::Ui) -> Option<CloudBrowserAction> {
+        let mut action = None;
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("Cloud instances")
+                .color(colors::TEXT_PRIMARY)
+                .strong()
+                .size(16.0));
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if secondary_button(ui, "Close").clicked() {
+                    action = Some(CloudBrowserAction::Close);
+                }
+            });
+        });
+        ui.add_space(spacing::SM);
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("cloud_provider")
+                .selected_text(self.provider.to_string())
+                .show_ui(ui, |ui| {
+                    for provider in CloudProvider::ALL {
+                        ui.selectable_value(&mut self.provider, provider, provider.to_string());
+                    }
+                });
+            ui.label(RichText::new(self.provider.scope_label()).color(colors::TEXT_SECONDARY));
+            text_input(ui, &mut self.scope, "CLI default");
+            if self.provider == CloudProvider::Aws {
+                ui.label(RichText::new("Region").color(colors::TEXT_SECONDARY));
+                text_input(ui, &mut self.region, "CLI default");
+            }
+            let list = ui.add_enabled_ui(!self.loading, |ui| primary_button(ui, "List running")).inner;
+            if list.clicked() {
+                action = Some(CloudBrowserAction::List(self.query()));
+            }
+        });
+        ui.label(RichText::new(format!(
+            "Uses the `{}` CLI and its configured credentials",
+            self.provider.cli()
+        )).color(colors::TEXT_MUTED).size(11.0));
+
+        ui.add_space(spacing::SM);
+        ui.separator();
+
+        if let Some(error) = &self.error {
+            ui.label(RichText::new(error).color(colors::ERROR));
+        }
+        if self.loading {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(RichText::new(format!("Asking {}...", self.provider)).color(colors::TEXT_MUTED));
+            });
+            return action;
+        }
+
+        let (Some(instances), Some(listed)) = (&self.instances, &self.listed) else {
+            empty_state(ui, "\u{2601}", "No Instances Listed", "Pick a provider and list its running instances");
+            return action;
+        };
+        if instances.is_empty() {
+            empty_state(ui, "\u{2601}", "Nothing Running", "No running instances in this account");
+            return action;
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("Filter").color(colors::TEXT_SECONDARY));
+            text_input(ui, &mut self.filter, "Name, address or tag");
+        });
+        let filter = self.filter.to_lowercase();
+        let rows: Vec<&CloudInstance> = instances.iter()
+            .filter(|i| filter.is_empty() || matches_filter(i, &filter))
+            .collect();
+
+        TableBuilder::new(ui)
+            .striped(true)
+            .resizable(true)
+            .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+            .column(Column::initial(180.0).at_least(80.0).clip(true))
+            .column(Column::initial(120.0).at_least(80.0))
+            .column(Column::initial(120.0).at_least(80.0))
+            .column(Column::initial(120.0).at_least(60.0).clip(true))
+            .column(Column::remainder().clip(true))
+            .header(24.0, |mut header| {
+                for title in ["Name", "Public IP", "Private IP", "Location", "Tags"] {
+                    header.col(|ui| {
+                        ui.label(RichText::new(title).color(colors::TEXT_SECONDARY).strong());
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(22.0, rows.len(), |mut row| {
+                    let instance = rows[row.index()];
+                    let cells = [
+                        instance.name.clone(),
+                        instance.public_ip.clone().unwrap_or_default(),
+                        instance.private_ip.clone().unwrap_or_default(),
+                        instance.location.clone(),
+                        format_tags(&instance.tags),
+                    ];
+                    for cell in cells {
+                        row.col(|ui| {
+                            let response = ui.add(egui::Label::new(
+                                RichText::new(cell).color(colors::TEXT_PRIMARY),
+                            ).sense(egui::Sense::click()));
+                            if response.double_clicked() {
+                                if let Some(address) = instance.address() {
+                                    action = Some(CloudBrowserAction::Connect(address.to_string()));
+                                }
+                            }
+                            response.context_menu(|ui| {
+                                if let Some(address) = instance.address() {
+                                    if ui.button(format!("Connect to {}", address)).clicked() {
+                                        action = Some(CloudBrowserAction::Connect(address.to_string()));
+                                        ui.close_menu();
+                                    }
+                                }
+                                if let Some(command) = instance.tunnel_command(listed) {
+                                    let label = match instance.provider {
+                                        CloudProvider::Aws => "Connect via EC2 Instance Connect",
+                                        _ => "Connect via IAP tunnel",
+                                    };
+                                    if ui.button(label).clicked() {
+                                        action = Some(CloudBrowserAction::OpenTunnel(command));
+                                        ui.close_menu();
+                                    }
+                                }
+                                if ui.button("Save as connection").clicked() {
+                                    action = Some(CloudBrowserAction::SaveProfile(instance.clone()));
+                                    ui.close_menu();
+                                }
+                            });
+                        });
+                    }
+                });
+            });
+
+        action
+    }
+}
+
+fn matches_filter(instance: &CloudInstance, filter: &str) -> bool {
+    instance.name.to_lowercase().contains(filter)
+        || instance.address().is_some_and(|a| a.contains(filter))
+        || instance.tags.iter().any(|(k, v)| k.to_lowercase().contains(filter) || v.to_lowercase().contains(filter))
+}
+
+fn format_tags(tags: &[(String, String)]) -> String {
+    tags.iter()
+        .map(|(k, v)| if v.is_empty() { k.clone() } else { format!("{}={}", k, v) })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Actions from the cloud browser
+pub enum CloudBrowserAction {
+    List(CloudQuery),
+    /// Quick connect to an instance address
+    Connect(String),
+    /// Run the provider's tunnel command in a local terminal
+    OpenTunnel(Vec<String>),
+    SaveProfile(CloudInstance),
+    Close,
+}
@@ -5,11 +5,27 @@
 use eframe::egui::{self, RichText};
 use crate::ui::components::{colors, spacing, primary_button, secondary_button, danger_button,
     labeled_input, labeled_number, labeled_toggle, labeled_dropdown, section_header, card, form_row};
-use super::connection_manager::{ConnectionProfile, AuthType};
+use super::connection_manager::{ConnectionProfile, AuthType, AutoSftp, ProfileForward, ProfileJumpHost};
+use super::settings_screen::retention_limits;
+use crate::storage::retention::LogRetention;
+use crate::storage::folders::{resolve_defaults, Folder, FolderDefaults};
+use crate::terminal::charset::SUPPORTED_CHARSETS;
+use crate::ssh::{check_key_file, Credentials, SshConnection};
+use crate::ssh::{host_ca, knock};
+use crate::storage::Database;
+use crate::integrations::kubernetes::{self, KubeListing, KubeTarget};
+use crate::integrations::services::{self, ServiceAction};
+use crate::utils::executor::{Executor, Task};
+use crate::utils::helpers::is_valid_hostname;
+use crate::utils::profile_share::{SharedForward, SharedJumpHost, SharedProfile};
+use crate::utils::undo::UndoHistory;
+use std::path::Path;
+use std::time::Duration;
 
 /// Authentication method for the form
-#[derive(Clone, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub enum FormAuthMethod {
+    #[default]
     Password,
     PublicKey,
     KeyboardInteractive,
@@ -41,6 +57,14 @@ pub struct ConnectionEditorScreen {
     pub private_key_path: String,
     pub passphrase: String,
     pub save_password: bool,
+    /// Keys offered after `private_key_path`
+    pub identity_files: Vec<String>,
+    /// Try several methods in turn instead of just `auth_method`
+    pub auth_fallback: bool,
+    /// Every method, in the order tried, and whether it is tried at all
+    pub auth_order: Vec<(AuthType, bool)>,
+    /// What authenticated last time, shown as a hint
+    last_auth: Option<String>,
 
     // Advanced SSH options
     pub compression: bool,
@@ -52,6 +76,9 @@ pub struct ConnectionEditorScreen {
     pub terminal_type: String,
     pub initial_command: String,
     pub encoding: String,
+    pub auto_sftp: AutoSftp,
+    pub critical: bool,
+    pub confirm_commands: bool,
 
     // Forwarding
     pub enable_x11_forwarding: bool,
@@ -64,18 +91,133 @@ pub struct ConnectionEditorScreen {
     pub jump_host: String,
     pub jump_port: u16,
     pub jump_username: String,
+    /// Minutes between the bastion's MFA checks; 0 when it has none
+    pub jump_reauth_minutes: u16,
+
+    /// Port knock sequence as typed, e.g. `7000 8000/udp 9000@500`
+    pub knock_sequence: String,
+    /// The server's host certificate line, for hosts signed by a trusted CA
+    pub host_certificate: String,
+
+    // Buttons next to the terminal
+    pub service_actions: Vec<ServiceAction>,
+
+    // Scrollback and logs, overriding settings
+    pub override_scrollback: bool,
+    pub scrollback_lines: usize,
+    pub override_log_retention: bool,
+    pub log_retention: LogRetention,
 
     // Organization
     pub group: String,
     pub is_favorite: bool,
     pub notes: String,
+    /// Comma-separated tags
+    pub tags: String,
+    /// Saved folders, for the defaults the chosen group hands down
+    pub folders: Vec<Folder>,
+
+    // Kubernetes pod instead of SSH
+    pub use_kubernetes: bool,
+    pub kube: KubeTarget,
+    kube_listing: Option<KubeListing>,
+    kube_task: Option<Task<anyhow::Result<KubeListing>>>,
+    kube_error: Option<String>,
 
     // Edit mode
     pub editing_id: Option<String>,
     pub is_dirty: bool,
+    /// Form contents when opened or last saved
+    baseline: FormState,
+    history: UndoHistory<FormState>,
+    /// Asking whether to save before leaving
+    confirm_discard: bool,
+
+    // Validation and connection test
+    show_errors: bool,
+    test_task: Option<Task<anyhow::Result<String>>>,
+    test_result: Option<Result<String, String>>,
+}
+
+/// Editable contents of the form, for change tracking and undo. No
+/// `Debug`: it holds the password and passphrase.
+#[derive(Clone, Default, PartialEq)]
+struct FormState {
+    name: String,
+    host: String,
+    port: u16,
+    username: String,
+    auth_method: FormAuthMethod,
+    password: String,
+    private_key_path: String,
+    passphrase: String,
+    save_password: bool,
+    identity_files: Vec<String>,
+    auth_fallback: bool,
+    auth_order: Vec<(AuthType, bool)>,
+    compression: bool,
+    keepalive_interval: u16,
+    connection_timeout: u16,
+    tcp_keepalive: bool,
+    terminal_type: String,
+    initial_command: String,
+    encoding: String,
+    auto_sftp: AutoSftp,
+    critical: bool,
+    confirm_commands: bool,
+    enable_x11_forwarding: bool,
+    enable_agent_forwarding: bool,
+    local_forwards: Vec<PortForward>,
+    remote_forwards: Vec<PortForward>,
+    use_jump_host: bool,
+    jump_host: String,
+    jump_port: u16,
+    jump_username: String,
+    jump_reauth_minutes: u16,
+    knock_sequence: String,
+    host_certificate: String,
+    service_actions: Vec<ServiceAction>,
+    override_scrollback: bool,
+    scrollback_lines: usize,
+    override_log_retention: bool,
+    log_retention: LogRetention,
+    group: String,
+    is_favorite: bool,
+    notes: String,
+    tags: String,
+    use_kubernetes: bool,
+    kube: KubeTarget,
+}
+
+impl FormState {
+    /// Copy for the undo history. Secrets are left out so old ones are not
+    /// kept around, and undo leaves them as last typed.
+    fn without_secrets(mut self) -> Self {
+        self.password.clear();
+        self.passphrase.clear();
+        self
+    }
+}
+
+/// Problems with the form, per field
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FormErrors {
+    pub host: Option<String>,
+    pub username: Option<String>,
+    pub private_key: Option<String>,
+    pub jump_host: Option<String>,
+    pub knock: Option<String>,
+    pub host_certificate: Option<String>,
+    pub kubernetes: Option<String>,
+}
+
+impl FormErrors {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PortForward {
     pub local_port: u16,
     pub remote_host: String,
@@ -83,6 +225,28 @@ pub struct PortForward {
     pub enabled: bool,
 }
 
+impl From<&ProfileForward> for PortForward {
+    fn from(fwd: &ProfileForward) -> Self {
+        Self {
+            local_port: fwd.local_port,
+            remote_host: fwd.remote_host.clone(),
+            remote_port: fwd.remote_port,
+            enabled: fwd.enabled,
+        }
+    }
+}
+
+impl From<&PortForward> for ProfileForward {
+    fn from(fwd: &PortForward) -> Self {
+        Self {
+            local_port: fwd.local_port,
+            remote_host: fwd.remote_host.clone(),
+            remote_port: fwd.remote_port,
+            enabled: fwd.enabled,
+        }
+    }
+}
+
 impl Default for ConnectionEditorScreen {
     fn default() -> Self {
         Self::new()
@@ -91,7 +255,7 @@ impl Default for ConnectionEditorScreen {
 
 impl ConnectionEditorScreen {
     pub fn new() -> Self {
-        Self {
+        let mut editor = Self {
             name: String::new(),
             host: String::new(),
             port: 22,
@@ -102,6 +266,10 @@ impl ConnectionEditorScreen {
             private_key_path: String::new(),
             passphrase: String::new(),
             save_password: false,
+            identity_files: Vec::new(),
+            auth_fallback: false,
+            auth_order: AuthType::DEFAULT_ORDER.iter().map(|method| (method.clone(), true)).collect(),
+            last_auth: None,
 
             compression: false,
             keepalive_interval: 30,
@@ -111,6 +279,9 @@ impl ConnectionEditorScreen {
             terminal_type: String::from("xterm-256color"),
             initial_command: String::new(),
             encoding: String::from("UTF-8"),
+            auto_sftp: AutoSftp::Off,
+            critical: false,
+            confirm_commands: false,
 
             enable_x11_forwarding: false,
             enable_agent_forwarding: false,
@@ -121,13 +292,165 @@ impl ConnectionEditorScreen {
             jump_host: String::new(),
             jump_port: 22,
             jump_username: String::new(),
+            jump_reauth_minutes: 0,
+
+            knock_sequence: String::new(),
+            host_certificate: String::new(),
+
+            service_actions: Vec::new(),
+
+            override_scrollback: false,
+            scrollback_lines: 10000,
+            override_log_retention: false,
+            log_retention: LogRetention::SUGGESTED,
 
             group: String::new(),
             is_favorite: false,
             notes: String::new(),
+            tags: String::new(),
+            folders: Vec::new(),
+
+            use_kubernetes: false,
+            kube: KubeTarget { namespace: "default".to_string(), ..Default::default() },
+            kube_listing: None,
+            kube_task: None,
+            kube_error: None,
 
             editing_id: None,
             is_dirty: false,
+            baseline: FormState::default(),
+            history: UndoHistory::new(FormState::default()),
+            confirm_discard: false,
+
+            show_errors: false,
+            test_task: None,
+            test_result: None,
+        };
+        editor.mark_saved();
+        editor
+    }
+
+    /// Everything the user can edit
+    fn capture(&self) -> FormState {
+        FormState {
+            name: self.name.clone(),
+            host: self.host.clone(),
+            port: self.port,
+            username: self.username.clone(),
+            auth_method: self.auth_method.clone(),
+            password: self.password.clone(),
+            private_key_path: self.private_key_path.clone(),
+            passphrase: self.passphrase.clone(),
+            save_password: self.save_password,
+            identity_files: self.identity_files.clone(),
+            auth_fallback: self.auth_fallback,
+            auth_order: self.auth_order.clone(),
+            compression: self.compression,
+            keepalive_interval: self.keepalive_interval,
+            connection_timeout: self.connection_timeout,
+            tcp_keepalive: self.tcp_keepalive,
+            terminal_type: self.terminal_type.clone(),
+            initial_command: self.initial_command.clone(),
+            encoding: self.encoding.clone(),
+            auto_sftp: self.auto_sftp,
+            critical: self.critical,
+            confirm_commands: self.confirm_commands,
+            enable_x11_forwarding: self.enable_x11_forwarding,
+            enable_agent_forwarding: self.enable_agent_forwarding,
+            local_forwards: self.local_forwards.clone(),
+            remote_forwards: self.remote_forwards.clone(),
+            use_jump_host: self.use_jump_host,
+            jump_host: self.jump_host.clone(),
+            jump_port: self.jump_port,
+            jump_username: self.jump_username.clone(),
+            jump_reauth_minutes: self.jump_reauth_minutes,
+            knock_sequence: self.knock_sequence.clone(),
+            host_certificate: self.host_certificate.clone(),
+            service_actions: self.service_actions.clone(),
+            override_scrollback: self.override_scrollback,
+            scrollback_lines: self.scrollback_lines,
+            override_log_retention: self.override_log_retention,
+            log_retention: self.log_retention,
+            group: self.group.clone(),
+            is_favorite: self.is_favorite,
+            notes: self.notes.clone(),
+            tags: self.tags.clone(),
+            use_kubernetes: self.use_kubernetes,
+            kube: self.kube.clone(),
+        }
+    }
+
+    /// Go back to a state from the undo history; password and passphrase stay
+    fn restore(&mut self, state: FormState) {
+        self.name = state.name;
+        self.host = state.host;
+        self.port = state.port;
+        self.username = state.username;
+        self.auth_method = state.auth_method;
+        self.private_key_path = state.private_key_path;
+        self.save_password = state.save_password;
+        self.identity_files = state.identity_files;
+        self.auth_fallback = state.auth_fallback;
+        self.auth_order = state.auth_order;
+        self.compression = state.compression;
+        self.keepalive_interval = state.keepalive_interval;
+        self.connection_timeout = state.connection_timeout;
+        self.tcp_keepalive = state.tcp_keepalive;
+        self.terminal_type = state.terminal_type;
+        self.initial_command = state.initial_command;
+        self.encoding = state.encoding;
+        self.auto_sftp = state.auto_sftp;
+        self.critical = state.critical;
+        self.confirm_commands = state.confirm_commands;
+        self.enable_x11_forwarding = state.enable_x11_forwarding;
+        self.enable_agent_forwarding = state.enable_agent_forwarding;
+        self.local_forwards = state.local_forwards;
+        self.remote_forwards = state.remote_forwards;
+        self.use_jump_host = state.use_jump_host;
+        self.jump_host = state.jump_host;
+        self.jump_port = state.jump_port;
+        self.jump_username = state.jump_username;
+        self.jump_reauth_minutes = state.jump_reauth_minutes;
+        self.knock_sequence = state.knock_sequence;
+        self.host_certificate = state.host_certificate;
+        self.service_actions = state.service_actions;
+        self.override_scrollback = state.override_scrollback;
+        self.scrollback_lines = state.scrollback_lines;
+        self.override_log_retention = state.override_log_retention;
+        self.log_retention = state.log_retention;
+        self.group = state.group;
+        self.is_favorite = state.is_favorite;
+        self.notes = state.notes;
+        self.tags = state.tags;
+        self.use_kubernetes = state.use_kubernetes;
+        self.kube = state.kube;
+    }
+
+    /// Treat the current contents as saved, e.g. after the owner stored them
+    pub fn mark_saved(&mut self) {
+        self.baseline = self.capture();
+        self.history.reset(self.baseline.clone().without_secrets());
+        self.is_dirty = false;
+    }
+
+    /// Call before navigating away or closing the window. Returns true when
+    /// the editor can go right away; otherwise the user is asked first and
+    /// the answer comes back as a `Save` or `Cancel` action.
+    pub fn request_close(&mut self) -> bool {
+        self.is_dirty = self.capture() != self.baseline;
+        if self.is_dirty {
+            self.confirm_discard = true;
+        }
+        !self.is_dirty
+    }
+
+    /// Validated save, or the field errors shown instead
+    fn try_save(&mut self) -> Option<ConnectionEditorAction> {
+        if self.validate().is_empty() {
+            Some(ConnectionEditorAction::Save(self.to_profile()))
+        } else {
+            self.show_errors = true;
+            None
         }
     }
 
@@ -141,16 +464,82 @@ impl ConnectionEditorScreen {
             AuthType::Password => FormAuthMethod::Password,
             AuthType::PublicKey => FormAuthMethod::PublicKey,
             AuthType::KeyboardInteractive => FormAuthMethod::KeyboardInteractive,
+            AuthType::Agent => FormAuthMethod::Agent,
         };
+        editor.private_key_path = profile.key_path.clone().unwrap_or_default();
+        editor.identity_files = profile.identity_files.clone();
+        if !profile.auth_order.is_empty() {
+            editor.auth_fallback = true;
+            editor.auth_order = profile.auth_order.iter().map(|method| (method.clone(), true)).collect();
+            for method in AuthType::DEFAULT_ORDER {
+                if !profile.auth_order.contains(&method) {
+                    editor.auth_order.push((method, false));
+                }
+            }
+        }
+        editor.last_auth = profile.last_auth.clone();
+        editor.compression = profile.compression;
+        editor.keepalive_interval = profile.keepalive_interval;
+        editor.connection_timeout = profile.connection_timeout;
+        editor.tcp_keepalive = profile.tcp_keepalive;
+        if let Some(term) = &profile.terminal_type {
+            editor.terminal_type = term.clone();
+        }
+        editor.initial_command = profile.initial_command.clone().unwrap_or_default();
+        editor.encoding = profile.encoding.clone();
+        editor.auto_sftp = profile.auto_sftp;
+        editor.critical = profile.critical;
+        editor.confirm_commands = profile.confirm_commands;
+        editor.enable_x11_forwarding = profile.x11_forwarding;
+        editor.enable_agent_forwarding = profile.agent_forwarding;
+        editor.local_forwards = profile.local_forwards.iter().map(PortForward::from).collect();
+        editor.remote_forwards = profile.remote_forwards.iter().map(PortForward::from).collect();
+        if let Some(jump) = &profile.jump_host {
+            editor.use_jump_host = true;
+            editor.jump_host = jump.host.clone();
+            editor.jump_port = jump.port;
+            editor.jump_username = jump.username.clone().unwrap_or_default();
+            editor.jump_reauth_minutes = jump.reauth_minutes.unwrap_or(0).min(u16::MAX as u32) as u16;
+        }
+        editor.knock_sequence = knock::format_sequence(&profile.knock_sequence);
+        editor.host_certificate = profile.host_certificate.clone().unwrap_or_default();
+        editor.service_actions = profile.service_actions.clone();
+        if let Some(lines) = profile.scrollback_lines {
+            editor.override_scrollback = true;
+            editor.scrollback_lines = lines;
+        }
+        if let Some(retention) = profile.log_retention {
+            editor.override_log_retention = true;
+            editor.log_retention = retention;
+        }
         editor.group = profile.group.clone().unwrap_or_default();
         editor.is_favorite = profile.is_favorite;
+        editor.notes = profile.notes.clone();
+        editor.tags = profile.tags.join(", ");
+        if let Some(target) = &profile.kubernetes {
+            editor.use_kubernetes = true;
+            editor.kube = target.clone();
+        }
         editor.editing_id = Some(profile.id.clone());
+        editor.mark_saved();
         editor
     }
 
     /// Render the connection editor form
     pub fn render(&mut self, ui: &mut egui::Ui) -> Option<ConnectionEditorAction> {
         let mut action = None;
+        self.poll_test();
+        self.poll_kubernetes();
+        if let Some(state) = self.history.handle_keys(ui) {
+            self.restore(state);
+        }
+        self.is_dirty = self.capture() != self.baseline;
+        let errors = if self.show_errors { self.validate() } else { FormErrors::default() };
+
+        let save_shortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::S);
+        if ui.input_mut(|i| i.consume_shortcut(&save_shortcut)) {
+            action = self.try_save();
+        }
 
         egui::ScrollArea::vertical().show(ui, |ui| {
             let is_new = self.editing_id.is_none();
@@ -158,20 +547,50 @@ impl ConnectionEditorScreen {
 
             ui.horizontal(|ui| {
                 ui.heading(RichText::new(title).color(colors::TEXT_PRIMARY).size(20.0));
+                if self.is_dirty {
+                    ui.label(RichText::new("\u{25CF} Unsaved changes").color(colors::WARNING).size(12.0));
+                }
 
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    if danger_button(ui, "Cancel").clicked() {
+                    if danger_button(ui, "Cancel").clicked() && self.request_close() {
                         action = Some(ConnectionEditorAction::Cancel);
                     }
 
                     ui.add_space(spacing::SM);
 
-                    if primary_button(ui, if is_new { "Create" } else { "Save" }).clicked() {
-                        action = Some(ConnectionEditorAction::Save(self.to_profile()));
+                    if primary_button(ui, if is_new { "Create" } else { "Save" })
+                        .on_hover_text("Ctrl+S")
+                        .clicked()
+                    {
+                        action = self.try_save();
+                    }
+
+                    ui.add_space(spacing::SM);
+
+                    let testing = self.test_task.is_some();
+                    if ui.add_enabled(!testing, egui::Button::new("Test connection")).clicked() {
+                        if self.validate().is_empty() {
+                            action = Some(ConnectionEditorAction::TestConnection);
+                        } else {
+                            self.show_errors = true;
+                        }
                     }
                 });
             });
 
+            if self.test_task.is_some() {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(RichText::new("Testing connection...").color(colors::TEXT_SECONDARY));
+                });
+            } else if let Some(result) = &self.test_result {
+                let (color, text) = match result {
+                    Ok(msg) => (colors::SUCCESS, format!("✓ {}", msg)),
+                    Err(err) => (colors::DANGER, format!("✗ {}", err)),
+                };
+                ui.label(RichText::new(text).color(color));
+            }
+
             ui.add_space(spacing::LG);
 
             // Basic Settings Section
@@ -183,90 +602,90 @@ impl ConnectionEditorScreen {
                 });
 
                 form_row(ui, |ui| {
-                    labeled_input(ui, "Host", &mut self.host, "example.com or 192.168.1.1");
+                    labeled_toggle(ui, "Shell in a Kubernetes pod (kubectl exec)", &mut self.use_kubernetes);
                 });
 
-                form_row(ui, |ui| {
-                    labeled_number(ui, "Port", &mut self.port, 1, 65535);
-                });
+                if self.use_kubernetes {
+                    if self.render_kubernetes(ui, &errors.kubernetes) {
+                        action = Some(ConnectionEditorAction::BrowseKubernetes);
+                    }
+                } else {
+                    form_row(ui, |ui| {
+                        labeled_input(ui, "Host", &mut self.host, "example.com or 192.168.1.1");
+                    });
+                    field_error(ui, &errors.host);
 
-                form_row(ui, |ui| {
-                    labeled_input(ui, "Username", &mut self.username, "root");
-                });
-            });
+                    form_row(ui, |ui| {
+                        labeled_number(ui, "Port", &mut self.port, 1, 65535);
+                    });
 
-            // Authentication Section
-            section_header(ui, "Authentication");
+                    let inherited = self.inherited();
+                    let user_hint = inherited.username
+                        .map(|u| format!("{} (from folder)", u))
+                        .unwrap_or_else(|| "root".to_string());
+                    form_row(ui, |ui| {
+                        labeled_input(ui, "Username", &mut self.username, &user_hint);
+                    });
+                    if let Some(jump) = inherited.jump_host.filter(|_| !self.use_jump_host) {
+                        ui.label(RichText::new(format!("Jump host {}:{} from folder", jump.host, jump.port))
+                            .color(colors::TEXT_MUTED)
+                            .size(11.0));
+                    }
+                    field_error(ui, &errors.username);
+                }
+            });
 
-            card(ui, |ui| {
-                form_row(ui, |ui| {
-                    let auth_methods = [
-                        FormAuthMethod::Password,
-                        FormAuthMethod::PublicKey,
-                        FormAuthMethod::KeyboardInteractive,
-                        FormAuthMethod::Agent,
-                    ];
-                    labeled_dropdown(ui, "Method", "auth_method", &mut self.auth_method, &auth_methods);
-                });
+            if !self.use_kubernetes {
+                // Authentication Section
+                section_header(ui, "Authentication");
 
-                match self.auth_method {
-                    FormAuthMethod::Password => {
-                        form_row(ui, |ui| {
-                            ui.horizontal(|ui| {
-                                ui.label(RichText::new("Password").color(colors::TEXT_PRIMARY));
-                                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                                    let input = egui::TextEdit::singleline(&mut self.password)
-                                        .hint_text(RichText::new("Enter password").color(colors::TEXT_MUTED))
-                                        .text_color(colors::TEXT_PRIMARY)
-                                        .password(true)
-                                        .desired_width(200.0)
-                                        .margin(egui::Margin::symmetric(8.0, 6.0));
-                                    ui.add(input);
-                                });
-                            });
-                        });
+                card(ui, |ui| {
+                    form_row(ui, |ui| {
+                        labeled_toggle(ui, "Try several methods in order", &mut self.auth_fallback);
+                    });
 
-                        form_row(ui, |ui| {
-                            labeled_toggle(ui, "Save password in keychain", &mut self.save_password);
-                        });
+                    if self.auth_fallback {
+                        self.render_auth_order(ui);
+                        if self.method_enabled(&AuthType::Password) || self.method_enabled(&AuthType::KeyboardInteractive) {
+                            self.render_password_fields(ui);
+                        }
+                        if self.method_enabled(&AuthType::PublicKey) {
+                            self.render_key_fields(ui, &errors);
+                        }
+                        if let Some(last) = &self.last_auth {
+                            ui.label(RichText::new(format!("Worked last time: {}; tried first", last.replace("key:", "key ")))
+                                .color(colors::TEXT_SECONDARY)
+                                .size(12.0));
+                        }
+                        return;
                     }
-                    FormAuthMethod::PublicKey => {
-                        form_row(ui, |ui| {
-                            ui.horizontal(|ui| {
-                                labeled_input(ui, "Private Key", &mut self.private_key_path, "~/.ssh/id_ed25519");
-                                if secondary_button(ui, "Browse...").clicked() {
-                                    // TODO: File picker
-                                }
-                            });
-                        });
 
-                        form_row(ui, |ui| {
-                            ui.horizontal(|ui| {
-                                ui.label(RichText::new("Passphrase").color(colors::TEXT_PRIMARY));
-                                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                                    let input = egui::TextEdit::singleline(&mut self.passphrase)
-                                        .hint_text(RichText::new("Optional").color(colors::TEXT_MUTED))
-                                        .text_color(colors::TEXT_PRIMARY)
-                                        .password(true)
-                                        .desired_width(200.0)
-                                        .margin(egui::Margin::symmetric(8.0, 6.0));
-                                    ui.add(input);
-                                });
-                            });
-                        });
-                    }
-                    FormAuthMethod::KeyboardInteractive => {
-                        ui.label(RichText::new("You will be prompted for authentication during connection.")
-                            .color(colors::TEXT_SECONDARY)
-                            .size(12.0));
-                    }
-                    FormAuthMethod::Agent => {
-                        ui.label(RichText::new("SSH Agent will be used for authentication. Make sure your agent is running and has the appropriate key loaded.")
-                            .color(colors::TEXT_SECONDARY)
-                            .size(12.0));
+                    form_row(ui, |ui| {
+                        let auth_methods = [
+                            FormAuthMethod::Password,
+                            FormAuthMethod::PublicKey,
+                            FormAuthMethod::KeyboardInteractive,
+                            FormAuthMethod::Agent,
+                        ];
+                        labeled_dropdown(ui, "Method", "auth_method", &mut self.auth_method, &auth_methods);
+                    });
+
+                    match self.auth_method {
+                        FormAuthMethod::Password => self.render_password_fields(ui),
+                        FormAuthMethod::PublicKey => self.render_key_fields(ui, &errors),
+                        FormAuthMethod::KeyboardInteractive => {
+                            ui.label(RichText::new("You will be prompted for authentication during connection.")
+                                .color(colors::TEXT_SECONDARY)
+                                .size(12.0));
+                        }
+                        FormAuthMethod::Agent => {
+                            ui.label(RichText::new("SSH Agent will be used for authentication. Make sure your agent is running and has the appropriate key loaded.")
+                                .color(colors::TEXT_SECONDARY)
+                                .size(12.0));
+                        }
                     }
-                }
-            });
+                });
+            }
 
             // Terminal Settings Section
             section_header(ui, "Terminal");
@@ -297,7 +716,7 @@ impl ConnectionEditorScreen {
                 });
 
                 form_row(ui, |ui| {
-                    let encodings = ["UTF-8", "ISO-8859-1", "GBK", "Big5"];
+                    let encodings = SUPPORTED_CHARSETS;
                     ui.horizontal(|ui| {
                         ui.label(RichText::new("Character Encoding").color(colors::TEXT_PRIMARY));
                         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
@@ -314,106 +733,226 @@ impl ConnectionEditorScreen {
                         });
                     });
                 });
-            });
 
-            // Advanced SSH Options Section
-            section_header(ui, "Advanced SSH Options");
+                if !self.use_kubernetes {
+                    form_row(ui, |ui| {
+                        labeled_dropdown(ui, "Open SFTP Browser on Connect", "auto_sftp", &mut self.auto_sftp, &AutoSftp::ALL);
+                    });
+                }
 
-            card(ui, |ui| {
                 form_row(ui, |ui| {
-                    labeled_toggle(ui, "Enable compression", &mut self.compression);
+                    labeled_toggle(ui, "Critical host", &mut self.critical);
                 });
 
-                form_row(ui, |ui| {
-                    labeled_toggle(ui, "TCP keep-alive", &mut self.tcp_keepalive);
-                });
+                if self.critical {
+                    form_row(ui, |ui| {
+                        labeled_toggle(ui, "Confirm each command before it runs", &mut self.confirm_commands)
+                            .on_hover_text("Needs shell integration (OSC 133) on the host to see the command line");
+                    });
+                }
+            });
 
-                form_row(ui, |ui| {
-                    labeled_number(ui, "Keep-alive interval (seconds)", &mut self.keepalive_interval, 0, 600);
-                });
+            if !self.use_kubernetes {
+                // Advanced SSH Options Section
+                section_header(ui, "Advanced SSH Options");
 
-                form_row(ui, |ui| {
-                    labeled_number(ui, "Connection timeout (seconds)", &mut self.connection_timeout, 5, 300);
-                });
-            });
+                card(ui, |ui| {
+                    form_row(ui, |ui| {
+                        labeled_toggle(ui, "Enable compression", &mut self.compression);
+                    });
 
-            // Forwarding Section
-            section_header(ui, "Forwarding");
+                    form_row(ui, |ui| {
+                        labeled_toggle(ui, "TCP keep-alive", &mut self.tcp_keepalive);
+                    });
 
-            card(ui, |ui| {
-                form_row(ui, |ui| {
-                    labeled_toggle(ui, "Enable X11 forwarding", &mut self.enable_x11_forwarding);
-                });
+                    form_row(ui, |ui| {
+                        labeled_number(ui, "Keep-alive interval (seconds)", &mut self.keepalive_interval, 0, 600);
+                    });
 
-                form_row(ui, |ui| {
-                    labeled_toggle(ui, "Enable agent forwarding", &mut self.enable_agent_forwarding);
+                    form_row(ui, |ui| {
+                        labeled_number(ui, "Connection timeout (seconds)", &mut self.connection_timeout, 5, 300);
+                    });
+
+                    form_row(ui, |ui| {
+                        labeled_input(ui, "Port knock sequence", &mut self.knock_sequence, "7000 8000/udp 9000@500");
+                    });
+                    field_error(ui, &errors.knock);
+                    if !self.knock_sequence.trim().is_empty() {
+                        ui.label(RichText::new("Knocked in order before connecting; /udp for UDP, @ms to pause after a knock")
+                            .color(colors::TEXT_MUTED)
+                            .size(11.0));
+                        let can_test = self.test_task.is_none()
+                            && knock::parse_sequence(&self.knock_sequence).is_ok()
+                            && !self.host.trim().is_empty();
+                        if ui.add_enabled(can_test, egui::Button::new("Test knock")).clicked() {
+                            action = Some(ConnectionEditorAction::TestKnock);
+                        }
+                    }
+
+                    form_row(ui, |ui| {
+                        labeled_input(ui, "Host certificate", &mut self.host_certificate, "ssh-ed25519-cert-v01@openssh.com AAAA...");
+                    });
+                    field_error(ui, &errors.host_certificate);
+                    ui.label(RichText::new("The server's ssh_host_*_key-cert.pub; checked against the trusted CAs in settings")
+                        .color(colors::TEXT_MUTED)
+                        .size(11.0));
                 });
 
-                ui.add_space(spacing::SM);
-                ui.label(RichText::new("Port Forwarding").color(colors::TEXT_SECONDARY).size(13.0));
-                ui.add_space(spacing::XS);
+                // Forwarding Section
+                section_header(ui, "Forwarding");
 
-                ui.horizontal(|ui| {
-                    if secondary_button(ui, "+ Local Forward").clicked() {
-                        self.local_forwards.push(PortForward {
-                            local_port: 8080,
-                            remote_host: "localhost".to_string(),
-                            remote_port: 80,
-                            enabled: true,
+                card(ui, |ui| {
+                    form_row(ui, |ui| {
+                        labeled_toggle(ui, "Enable X11 forwarding", &mut self.enable_x11_forwarding);
+                    });
+
+                    form_row(ui, |ui| {
+                        labeled_toggle(ui, "Enable agent forwarding", &mut self.enable_agent_forwarding);
+                    });
+
+                    ui.add_space(spacing::SM);
+                    ui.label(RichText::new("Port Forwarding").color(colors::TEXT_SECONDARY).size(13.0));
+                    ui.add_space(spacing::XS);
+
+                    ui.horizontal(|ui| {
+                        if secondary_button(ui, "+ Local Forward").clicked() {
+                            self.local_forwards.push(PortForward {
+                                local_port: 8080,
+                                remote_host: "localhost".to_string(),
+                                remote_port: 80,
+                                enabled: true,
+                            });
+                        }
+
+                        if secondary_button(ui, "+ Remote Forward").clicked() {
+                            self.remote_forwards.push(PortForward {
+                                local_port: 8080,
+                                remote_host: "localhost".to_string(),
+                                remote_port: 80,
+                                enabled: true,
+                            });
+                        }
+                    });
+
+                    // Display existing forwards
+                    for (i, fwd) in self.local_forwards.clone().iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new(format!("L: {}:{}:{}", fwd.local_port, fwd.remote_host, fwd.remote_port))
+                                .color(colors::TEXT_SECONDARY));
+                            if ui.small_button("x").clicked() {
+                                self.local_forwards.remove(i);
+                            }
                         });
                     }
 
-                    if secondary_button(ui, "+ Remote Forward").clicked() {
-                        self.remote_forwards.push(PortForward {
-                            local_port: 8080,
-                            remote_host: "localhost".to_string(),
-                            remote_port: 80,
-                            enabled: true,
+                    for (i, fwd) in self.remote_forwards.clone().iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new(format!("R: {}:{}:{}", fwd.local_port, fwd.remote_host, fwd.remote_port))
+                                .color(colors::TEXT_SECONDARY));
+                            if ui.small_button("x").clicked() {
+                                self.remote_forwards.remove(i);
+                            }
                         });
                     }
                 });
 
-                // Display existing forwards
-                for (i, fwd) in self.local_forwards.clone().iter().enumerate() {
-                    ui.horizontal(|ui| {
-                        ui.label(RichText::new(format!("L: {}:{}:{}", fwd.local_port, fwd.remote_host, fwd.remote_port))
-                            .color(colors::TEXT_SECONDARY));
-                        if ui.small_button("x").clicked() {
-                            self.local_forwards.remove(i);
-                        }
+                // Jump Host Section
+                section_header(ui, "Jump Host / Proxy");
+
+                card(ui, |ui| {
+                    form_row(ui, |ui| {
+                        labeled_toggle(ui, "Use jump host (ProxyJump)", &mut self.use_jump_host);
                     });
-                }
 
-                for (i, fwd) in self.remote_forwards.clone().iter().enumerate() {
+                    if self.use_jump_host {
+                        form_row(ui, |ui| {
+                            labeled_input(ui, "Jump Host", &mut self.jump_host, "bastion.example.com");
+                        });
+                        field_error(ui, &errors.jump_host);
+
+                        form_row(ui, |ui| {
+                            labeled_number(ui, "Jump Port", &mut self.jump_port, 1, 65535);
+                        });
+
+                        form_row(ui, |ui| {
+                            labeled_input(ui, "Jump Username", &mut self.jump_username, "Same as connection if empty");
+                        });
+
+                        form_row(ui, |ui| {
+                            labeled_number(ui, "Verify again every (min)", &mut self.jump_reauth_minutes, 0, 1440);
+                        });
+                        ui.label(RichText::new("For bastions that ask for a second factor periodically; 0 if it never does. \
+                            The login is renewed two minutes early and the prompt names the bastion.")
+                            .color(colors::TEXT_MUTED)
+                            .size(11.0));
+                    }
+                });
+
+                // Service Shortcuts Section
+                section_header(ui, "Service Shortcuts");
+
+                card(ui, |ui| {
+                    ui.label(RichText::new("Commands shown as buttons next to the terminal. They run beside the shell and their output opens in a pane.")
+                        .color(colors::TEXT_SECONDARY)
+                        .size(12.0));
+                    ui.add_space(spacing::XS);
+
+                    let mut remove = None;
+                    for (i, action) in self.service_actions.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(&mut action.label)
+                                .hint_text("Label")
+                                .desired_width(120.0));
+                            ui.add(egui::TextEdit::singleline(&mut action.command)
+                                .hint_text("systemctl status nginx")
+                                .font(egui::TextStyle::Monospace)
+                                .desired_width(ui.available_width() - 30.0));
+                            if ui.small_button("x").clicked() {
+                                remove = Some(i);
+                            }
+                        });
+                    }
+                    if let Some(i) = remove {
+                        self.service_actions.remove(i);
+                    }
+
                     ui.horizontal(|ui| {
-                        ui.label(RichText::new(format!("R: {}:{}:{}", fwd.local_port, fwd.remote_host, fwd.remote_port))
-                            .color(colors::TEXT_SECONDARY));
-                        if ui.small_button("x").clicked() {
-                            self.remote_forwards.remove(i);
+                        if secondary_button(ui, "+ Shortcut").clicked() {
+                            self.service_actions.push(ServiceAction::new("", ""));
                         }
+                        ui.menu_button("Suggestions", |ui| {
+                            for (label, command) in services::SUGGESTIONS {
+                                if ui.button(label).on_hover_text(command).clicked() {
+                                    self.service_actions.push(ServiceAction::new(label, command));
+                                    ui.close_menu();
+                                }
+                            }
+                        });
                     });
-                }
-            });
+                });
+            }
 
-            // Jump Host Section
-            section_header(ui, "Jump Host / Proxy");
+            // Scrollback & Logs Section
+            section_header(ui, "Scrollback & Logs");
 
             card(ui, |ui| {
-                form_row(ui, |ui| {
-                    labeled_toggle(ui, "Use jump host (ProxyJump)", &mut self.use_jump_host);
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.override_scrollback, "Scrollback lines");
+                    let mut lines = self.scrollback_lines as i32;
+                    let drag = egui::DragValue::new(&mut lines).speed(100).clamp_range(1000..=1_000_000);
+                    if ui.add_enabled(self.override_scrollback, drag).changed() {
+                        self.scrollback_lines = lines as usize;
+                    }
                 });
+                ui.label(RichText::new("Unchecked, the length from settings is used")
+                    .color(colors::TEXT_MUTED)
+                    .size(11.0));
 
-                if self.use_jump_host {
-                    form_row(ui, |ui| {
-                        labeled_input(ui, "Jump Host", &mut self.jump_host, "bastion.example.com");
-                    });
-
-                    form_row(ui, |ui| {
-                        labeled_number(ui, "Jump Port", &mut self.jump_port, 1, 65535);
-                    });
-
-                    form_row(ui, |ui| {
-                        labeled_input(ui, "Jump Username", &mut self.jump_username, "Same as connection if empty");
+                ui.add_space(spacing::SM);
+                ui.checkbox(&mut self.override_log_retention, "Own retention for this host's session logs and captures");
+                if self.override_log_retention {
+                    ui.indent("log_retention", |ui| {
+                        retention_limits(ui, &mut self.log_retention);
                     });
                 }
             });
@@ -426,6 +965,10 @@ impl ConnectionEditorScreen {
                     labeled_input(ui, "Group", &mut self.group, "Production, Development, etc.");
                 });
 
+                form_row(ui, |ui| {
+                    labeled_input(ui, "Tags", &mut self.tags, "eu, k8s, db");
+                });
+
                 form_row(ui, |ui| {
                     labeled_toggle(ui, "Add to favorites", &mut self.is_favorite);
                 });
@@ -445,36 +988,584 @@ impl ConnectionEditorScreen {
             ui.add_space(spacing::XXL);
         });
 
+        let state = self.capture().without_secrets();
+        self.history.record(&state);
+
+        if self.confirm_discard {
+            if let Some(choice) = self.render_discard_prompt(ui.ctx()) {
+                action = Some(choice);
+            }
+        }
+
+        action
+    }
+
+    /// Save / discard / keep editing, shown when leaving with unsaved changes
+    fn render_discard_prompt(&mut self, ctx: &egui::Context) -> Option<ConnectionEditorAction> {
+        let mut action = None;
+        let mut open = true;
+        egui::Window::new("Unsaved Changes")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let name = if self.name.is_empty() { "this connection" } else { self.name.as_str() };
+                ui.label(RichText::new(format!("Save changes to {}?", name)).color(colors::TEXT_PRIMARY));
+                ui.add_space(spacing::SM);
+                ui.horizontal(|ui| {
+                    if primary_button(ui, "Save").clicked() {
+                        self.confirm_discard = false;
+                        action = self.try_save();
+                    }
+                    if danger_button(ui, "Discard").clicked() {
+                        self.confirm_discard = false;
+                        action = Some(ConnectionEditorAction::Cancel);
+                    }
+                    if secondary_button(ui, "Keep editing").clicked() {
+                        self.confirm_discard = false;
+                    }
+                });
+            });
+        if !open {
+            self.confirm_discard = false;
+        }
         action
     }
 
+    fn render_password_fields(&mut self, ui: &mut egui::Ui) {
+        form_row(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new("Password").color(colors::TEXT_PRIMARY));
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let input = egui::TextEdit::singleline(&mut self.password)
+                        .hint_text(RichText::new("Enter password").color(colors::TEXT_MUTED))
+                        .text_color(colors::TEXT_PRIMARY)
+                        .password(true)
+                        .desired_width(200.0)
+                        .margin(egui::Margin::symmetric(8.0, 6.0));
+                    ui.add(input);
+                });
+            });
+        });
+
+        form_row(ui, |ui| {
+            labeled_toggle(ui, "Save password in keychain", &mut self.save_password);
+        });
+    }
+
+    fn render_key_fields(&mut self, ui: &mut egui::Ui, errors: &FormErrors) {
+        form_row(ui, |ui| {
+            ui.horizontal(|ui| {
+                labeled_input(ui, "Private Key", &mut self.private_key_path, "~/.ssh/id_ed25519");
+                if secondary_button(ui, "Browse...").clicked() {
+                    // TODO: File picker
+                }
+            });
+        });
+        field_error(ui, &errors.private_key);
+
+        // More keys, offered in turn like OpenSSH's IdentityFile lines
+        let mut remove = None;
+        for (index, path) in self.identity_files.iter_mut().enumerate() {
+            form_row(ui, |ui| {
+                ui.horizontal(|ui| {
+                    labeled_input(ui, &format!("Key {}", index + 2), path, "~/.ssh/id_rsa");
+                    if secondary_button(ui, "Remove").clicked() {
+                        remove = Some(index);
+                    }
+                });
+            });
+        }
+        if let Some(index) = remove {
+            self.identity_files.remove(index);
+        }
+        if secondary_button(ui, "+ Add another key").clicked() {
+            self.identity_files.push(String::new());
+        }
+
+        form_row(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new("Passphrase").color(colors::TEXT_PRIMARY));
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let input = egui::TextEdit::singleline(&mut self.passphrase)
+                        .hint_text(RichText::new("Optional").color(colors::TEXT_MUTED))
+                        .text_color(colors::TEXT_PRIMARY)
+                        .password(true)
+                        .desired_width(200.0)
+                        .margin(egui::Margin::symmetric(8.0, 6.0));
+                    ui.add(input);
+                });
+            });
+        });
+    }
+
+    /// Methods with a checkbox each and arrows to reorder them
+    fn render_auth_order(&mut self, ui: &mut egui::Ui) {
+        ui.label(RichText::new("Tried from the top until the server accepts one")
+            .color(colors::TEXT_SECONDARY)
+            .size(12.0));
+        let mut swap = None;
+        let count = self.auth_order.len();
+        for (index, (method, enabled)) in self.auth_order.iter_mut().enumerate() {
+            form_row(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.checkbox(enabled, method.to_string());
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.add_enabled(index + 1 < count, egui::Button::new("\u{25BC}").small()).clicked() {
+                            swap = Some(index);
+                        }
+                        if ui.add_enabled(index > 0, egui::Button::new("\u{25B2}").small()).clicked() {
+                            swap = Some(index - 1);
+                        }
+                    });
+                });
+            });
+        }
+        if let Some(index) = swap {
+            self.auth_order.swap(index, index + 1);
+        }
+    }
+
+    fn method_enabled(&self, method: &AuthType) -> bool {
+        self.auth_order.iter().any(|(m, enabled)| m == method && *enabled)
+    }
+
+    /// Whether the profile authenticates with keys at all
+    fn uses_keys(&self) -> bool {
+        if self.auth_fallback {
+            self.method_enabled(&AuthType::PublicKey)
+        } else {
+            self.auth_method == FormAuthMethod::PublicKey
+        }
+    }
+
+    /// Defaults inherited from the group's folders
+    pub fn inherited(&self) -> FolderDefaults {
+        resolve_defaults(&self.folders, self.group.trim())
+    }
+
+    /// Check required fields, host syntax and the key file
+    pub fn validate(&self) -> FormErrors {
+        let mut errors = FormErrors::default();
+        if self.use_kubernetes {
+            if self.kube.namespace.trim().is_empty() || self.kube.pod.trim().is_empty() {
+                errors.kubernetes = Some("Choose a namespace and pod".to_string());
+            }
+            return errors;
+        }
+        let inherited = self.inherited();
+
+        let host = self.host.trim();
+        if host.is_empty() {
+            errors.host = Some("Host is required".to_string());
+        } else if !is_valid_hostname(host) {
+            errors.host = Some("Not a valid hostname or IP address".to_string());
+        }
+
+        if self.username.trim().is_empty() && inherited.username.is_none() {
+            errors.username = Some("Username is required".to_string());
+        }
+
+        if self.auth_fallback && !self.auth_order.iter().any(|(_, enabled)| *enabled) {
+            errors.private_key = Some("Choose at least one authentication method".to_string());
+        } else if self.uses_keys() {
+            let path = self.private_key_path.trim();
+            let path = if path.is_empty() { inherited.key_path.as_deref().unwrap_or("") } else { path };
+            if path.is_empty() {
+                errors.private_key = Some("Choose a private key".to_string());
+            } else if let Err(e) = check_key_file(Path::new(shellexpand::tilde(path).as_ref())) {
+                errors.private_key = Some(e.to_string());
+            }
+        }
+
+        if self.use_jump_host {
+            let jump = self.jump_host.trim();
+            if jump.is_empty() {
+                errors.jump_host = Some("Jump host is required".to_string());
+            } else if !is_valid_hostname(jump) {
+                errors.jump_host = Some("Not a valid hostname or IP address".to_string());
+            }
+        }
+
+        if let Err(e) = knock::parse_sequence(&self.knock_sequence) {
+            errors.knock = Some(e.to_string());
+        }
+
+        let certificate = self.host_certificate.trim();
+        if !certificate.is_empty() && host_ca::parse_certificate_line(certificate).is_none() {
+            errors.host_certificate = Some("Expected a '<type>-cert-v01@openssh.com <base64>' line".to_string());
+        }
+
+        errors
+    }
+
+    /// Start a TCP + handshake + authentication dry run; the result shows in the form
+    pub fn test_connection(&mut self, executor: &Executor) {
+        if self.use_kubernetes {
+            let target = self.kube.clone();
+            self.test_result = None;
+            self.test_task = Some(executor.spawn(async move {
+                let listing = kubernetes::browse(target.context.clone(), target.namespace.clone()).await?;
+                match listing.pods.iter().find(|p| p.name == target.pod) {
+                    Some(pod) if pod.phase == "Running" => Ok(format!("Pod {} is running", pod.name)),
+                    Some(pod) => Err(anyhow::anyhow!("Pod {} is {}", pod.name, pod.phase)),
+                    None => Err(anyhow::anyhow!("No pod {} in {}", target.pod, target.namespace)),
+                }
+            }));
+            return;
+        }
+        let effective = self.to_profile().with_folder_defaults(&self.folders);
+        let trusted_cas = Database::open().and_then(|db| db.list_trusted_cas()).unwrap_or_default();
+        let config = effective.connection_config(trusted_cas);
+        let limit = Duration::from_secs(self.connection_timeout.max(1) as u64);
+        let auth = self.auth_method.clone();
+        let password = self.password.clone();
+        let key_path = shellexpand::tilde(effective.key_path.as_deref().unwrap_or("")).into_owned();
+        let passphrase = Some(self.passphrase.clone()).filter(|p| !p.is_empty());
+        let methods = self.auth_fallback.then(|| {
+            effective.auth_methods(Some(password.as_str()).filter(|p| !p.is_empty()), passphrase.as_deref())
+        });
+
+        self.test_result = None;
+        self.test_task = Some(executor.spawn(async move {
+            let attempt = async {
+                if let Some(methods) = methods {
+                    let (connection, method) = SshConnection::connect_methods(config.clone(), &methods).await?;
+                    connection.close().await?;
+                    return Ok(format!("Connected and authenticated as {} with {}", config.username, method));
+                }
+                match auth {
+                    FormAuthMethod::Password => {
+                        SshConnection::connect_password(config.clone(), &password).await?.close().await?;
+                        Ok(format!("Connected and authenticated as {}", config.username))
+                    }
+                    FormAuthMethod::PublicKey => {
+                        SshConnection::connect_key(config.clone(), &key_path, passphrase.as_deref())
+                            .await?
+                            .close()
+                            .await?;
+                        Ok(format!("Connected and authenticated as {}", config.username))
+                    }
+                    FormAuthMethod::Agent => {
+                        SshConnection::connect_methods(config.clone(), &[Credentials::Agent]).await?.0.close().await?;
+                        Ok(format!("Connected and authenticated as {} with the SSH agent", config.username))
+                    }
+                    FormAuthMethod::KeyboardInteractive => {
+                        SshConnection::probe(&config).await?;
+                        Ok("Server reachable; authentication will run when you connect".to_string())
+                    }
+                }
+            };
+            tokio::time::timeout(limit, attempt)
+                .await
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("Timed out after {}s", limit.as_secs())))
+        }));
+    }
+
+    /// Send only the knock sequence, to check it without connecting
+    pub fn test_knock(&mut self, executor: &Executor) {
+        let host = self.host.trim().to_string();
+        let port = self.port;
+        let sequence = knock::parse_sequence(&self.knock_sequence).unwrap_or_default();
+        self.test_result = None;
+        self.test_task = Some(executor.spawn(async move {
+            let addr = knock::resolve(&host, port).await?;
+            let lines = knock::perform(addr.ip(), &sequence).await?;
+            Ok(format!("{}; now try connecting", lines.join(", ")))
+        }));
+    }
+
+    fn poll_test(&mut self) {
+        if let Some(result) = self.test_task.as_mut().and_then(|task| task.poll()) {
+            self.test_task = None;
+            self.test_result = Some(result.and_then(|r| r).map_err(|e| e.to_string()));
+        }
+    }
+
+    /// List contexts, namespaces and pods for the picker
+    pub fn browse_kubernetes(&mut self, executor: &Executor) {
+        let context = self.kube.context.clone();
+        let namespace = self.kube.namespace.trim().to_string();
+        self.kube_error = None;
+        self.kube_task = Some(executor.spawn(kubernetes::browse(context, namespace)));
+    }
+
+    fn poll_kubernetes(&mut self) {
+        if let Some(result) = self.kube_task.as_mut().and_then(|task| task.poll()) {
+            self.kube_task = None;
+            match result.and_then(|r| r) {
+                Ok(listing) => self.kube_listing = Some(listing),
+                Err(e) => self.kube_error = Some(e.to_string()),
+            }
+        }
+    }
+
+    /// Context, namespace, pod and container pickers; true asks for a refresh
+    fn render_kubernetes(&mut self, ui: &mut egui::Ui, error: &Option<String>) -> bool {
+        let mut refresh = false;
+        let listing = self.kube_listing.clone().unwrap_or_default();
+
+        form_row(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new("Context").color(colors::TEXT_PRIMARY));
+                let current = listing.current_context.as_deref().map(|c| format!("Current ({})", c))
+                    .unwrap_or_else(|| "Current".to_string());
+                let selected = self.kube.context.clone().unwrap_or_else(|| current.clone());
+                egui::ComboBox::from_id_source("kube_context")
+                    .selected_text(selected)
+                    .width(200.0)
+                    .show_ui(ui, |ui| {
+                        refresh |= ui.selectable_value(&mut self.kube.context, None, current).clicked();
+                        for context in &listing.contexts {
+                            refresh |= ui.selectable_value(&mut self.kube.context, Some(context.clone()), context).clicked();
+                        }
+                    });
+                if self.kube_task.is_some() {
+                    ui.spinner();
+                } else if secondary_button(ui, "Refresh").clicked() {
+                    refresh = true;
+                }
+            });
+        });
+
+        // Until the cluster has been listed, names are typed in
+        if listing.pods.is_empty() {
+            form_row(ui, |ui| {
+                labeled_input(ui, "Namespace", &mut self.kube.namespace, "default");
+            });
+            form_row(ui, |ui| {
+                labeled_input(ui, "Pod", &mut self.kube.pod, "Press Refresh to list pods");
+            });
+        } else {
+            self.render_kube_pickers(ui, &listing, &mut refresh);
+        }
+
+        form_row(ui, |ui| {
+            labeled_input(ui, "Shell", &mut self.kube.shell, "bash, falling back to sh");
+        });
+
+        if let Some(e) = &self.kube_error {
+            ui.label(RichText::new(e).color(colors::DANGER).size(12.0));
+        }
+        field_error(ui, error);
+        refresh
+    }
+
+    fn render_kube_pickers(&mut self, ui: &mut egui::Ui, listing: &KubeListing, refresh: &mut bool) {
+        form_row(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new("Namespace").color(colors::TEXT_PRIMARY));
+                egui::ComboBox::from_id_source("kube_namespace")
+                    .selected_text(self.kube.namespace.clone())
+                    .width(200.0)
+                    .show_ui(ui, |ui| {
+                        for namespace in &listing.namespaces {
+                            *refresh |= ui.selectable_value(&mut self.kube.namespace, namespace.clone(), namespace).clicked();
+                        }
+                    });
+            });
+        });
+
+        form_row(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new("Pod").color(colors::TEXT_PRIMARY));
+                egui::ComboBox::from_id_source("kube_pod")
+                    .selected_text(self.kube.pod.clone())
+                    .width(260.0)
+                    .show_ui(ui, |ui| {
+                        for pod in &listing.pods {
+                            let label = format!("{}  ({})", pod.name, pod.phase);
+                            if ui.selectable_label(self.kube.pod == pod.name, label).clicked() {
+                                self.kube.pod = pod.name.clone();
+                                self.kube.container = None;
+                            }
+                        }
+                    });
+            });
+        });
+
+        let containers = listing.pods.iter()
+            .find(|p| p.name == self.kube.pod)
+            .map(|p| p.containers.clone())
+            .unwrap_or_default();
+        if containers.len() > 1 {
+            form_row(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("Container").color(colors::TEXT_PRIMARY));
+                    egui::ComboBox::from_id_source("kube_container")
+                        .selected_text(self.kube.container.clone().unwrap_or_else(|| "Default".to_string()))
+                        .width(200.0)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.kube.container, None, "Default");
+                            for container in &containers {
+                                ui.selectable_value(&mut self.kube.container, Some(container.clone()), container);
+                            }
+                        });
+                });
+            });
+        }
+    }
+
     /// Convert form state to a ConnectionProfile
     pub fn to_profile(&self) -> ConnectionProfile {
         ConnectionProfile {
             id: self.editing_id.clone().unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
-            name: if self.name.is_empty() {
-                format!("{}@{}", self.username, self.host)
-            } else {
+            name: if !self.name.is_empty() {
                 self.name.clone()
+            } else if self.use_kubernetes {
+                self.kube.label()
+            } else {
+                format!("{}@{}", self.username, self.host)
             },
-            host: self.host.clone(),
+            host: if self.use_kubernetes { self.kube.pod.clone() } else { self.host.clone() },
             port: self.port,
             username: self.username.clone(),
-            auth_type: match self.auth_method {
-                FormAuthMethod::Password => AuthType::Password,
-                FormAuthMethod::PublicKey => AuthType::PublicKey,
-                FormAuthMethod::KeyboardInteractive => AuthType::KeyboardInteractive,
-                FormAuthMethod::Agent => AuthType::PublicKey, // Agent uses public key auth
+            // With fallback on, the first method stands for the profile in lists
+            auth_type: match self.auth_order.iter().find(|(_, enabled)| *enabled) {
+                Some((method, _)) if self.auth_fallback => method.clone(),
+                _ => match self.auth_method {
+                    FormAuthMethod::Password => AuthType::Password,
+                    FormAuthMethod::PublicKey => AuthType::PublicKey,
+                    FormAuthMethod::KeyboardInteractive => AuthType::KeyboardInteractive,
+                    FormAuthMethod::Agent => AuthType::Agent,
+                },
             },
             group: if self.group.is_empty() { None } else { Some(self.group.clone()) },
             last_connected: None,
+            connection_count: 0,
             is_favorite: self.is_favorite,
+            terminal_type: Some(self.terminal_type.clone()).filter(|t| !t.is_empty()),
+            key_path: (self.uses_keys() && !self.private_key_path.trim().is_empty())
+                .then(|| self.private_key_path.trim().to_string()),
+            identity_files: if self.uses_keys() {
+                self.identity_files.iter().map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect()
+            } else {
+                Vec::new()
+            },
+            auth_order: if self.auth_fallback {
+                self.auth_order.iter().filter(|(_, enabled)| *enabled).map(|(method, _)| method.clone()).collect()
+            } else {
+                Vec::new()
+            },
+            last_auth: self.last_auth.clone(),
+            compression: self.compression,
+            keepalive_interval: self.keepalive_interval,
+            connection_timeout: self.connection_timeout,
+            tcp_keepalive: self.tcp_keepalive,
+            initial_command: Some(self.initial_command.clone()).filter(|c| !c.trim().is_empty()),
+            encoding: self.encoding.clone(),
+            x11_forwarding: self.enable_x11_forwarding,
+            agent_forwarding: self.enable_agent_forwarding,
+            local_forwards: self.local_forwards.iter().map(ProfileForward::from).collect(),
+            remote_forwards: self.remote_forwards.iter().map(ProfileForward::from).collect(),
+            jump_host: self.use_jump_host.then(|| ProfileJumpHost {
+                host: self.jump_host.trim().to_string(),
+                port: self.jump_port,
+                username: Some(self.jump_username.clone()).filter(|u| !u.is_empty()),
+                reauth_minutes: (self.jump_reauth_minutes > 0).then_some(self.jump_reauth_minutes as u32),
+            }),
+            notes: self.notes.clone(),
+            tags: self.tags.split(',')
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+                .collect(),
+            kubernetes: self.use_kubernetes.then(|| self.kube.clone()),
+            auto_sftp: if self.use_kubernetes { AutoSftp::Off } else { self.auto_sftp },
+            critical: self.critical,
+            confirm_commands: self.critical && self.confirm_commands,
+            service_actions: self.service_actions.iter().filter(|a| a.is_runnable()).cloned().collect(),
+            knock_sequence: knock::parse_sequence(&self.knock_sequence).unwrap_or_default(),
+            host_certificate: Some(self.host_certificate.trim().to_string()).filter(|c| !c.is_empty()),
+            scrollback_lines: self.override_scrollback.then_some(self.scrollback_lines),
+            log_retention: self.override_log_retention.then_some(self.log_retention),
+        }
+    }
+
+    /// Build a shareable copy of this profile, leaving out every secret
+    pub fn to_shared_profile(&self) -> SharedProfile {
+        let profile = self.to_profile();
+        let forward = |kind: &str, fwd: &PortForward| SharedForward {
+            kind: kind.to_string(),
+            local_port: fwd.local_port,
+            remote_host: fwd.remote_host.clone(),
+            remote_port: fwd.remote_port,
+        };
+
+        SharedProfile {
+            name: profile.name,
+            host: profile.host,
+            port: profile.port,
+            username: profile.username,
+            auth_method: self.auth_method.to_string(),
+            group: profile.group,
+            jump_host: self.use_jump_host.then(|| SharedJumpHost {
+                host: self.jump_host.clone(),
+                port: self.jump_port,
+                username: (!self.jump_username.is_empty()).then(|| self.jump_username.clone()),
+            }),
+            forwards: self.local_forwards.iter().map(|f| forward("local", f))
+                .chain(self.remote_forwards.iter().map(|f| forward("remote", f)))
+                .collect(),
         }
     }
+
+    /// Open an imported profile as a new connection; credentials are left for the user
+    pub fn from_shared_profile(shared: &SharedProfile) -> Self {
+        let mut editor = Self::new();
+        editor.name = shared.name.clone();
+        editor.host = shared.host.clone();
+        editor.port = shared.port;
+        editor.username = shared.username.clone();
+        editor.auth_method = match shared.auth_method.as_str() {
+            "Public Key" => FormAuthMethod::PublicKey,
+            "Keyboard Interactive" => FormAuthMethod::KeyboardInteractive,
+            "SSH Agent" => FormAuthMethod::Agent,
+            _ => FormAuthMethod::Password,
+        };
+        editor.group = shared.group.clone().unwrap_or_default();
+
+        if let Some(jump) = &shared.jump_host {
+            editor.use_jump_host = true;
+            editor.jump_host = jump.host.clone();
+            editor.jump_port = jump.port;
+            editor.jump_username = jump.username.clone().unwrap_or_default();
+        }
+
+        for fwd in &shared.forwards {
+            let forward = PortForward {
+                local_port: fwd.local_port,
+                remote_host: fwd.remote_host.clone(),
+                remote_port: fwd.remote_port,
+                enabled: true,
+            };
+            if fwd.kind == "remote" {
+                editor.remote_forwards.push(forward);
+            } else {
+                editor.local_forwards.push(forward);
+            }
+        }
+
+        editor.is_dirty = true;
+        editor
+    }
+}
+
+/// Inline error under a form field
+fn field_error(ui: &mut egui::Ui, error: &Option<String>) {
+    if let Some(error) = error {
+        ui.label(RichText::new(error).color(colors::DANGER).size(12.0));
+    }
 }
 
 /// Actions from the connection editor
 pub enum ConnectionEditorAction {
     Save(ConnectionProfile),
+    /// Run [`ConnectionEditorScreen::test_connection`] on the app executor
+    TestConnection,
+    /// Run [`ConnectionEditorScreen::test_knock`] on the app executor
+    TestKnock,
+    /// Run [`ConnectionEditorScreen::browse_kubernetes`] on the app executor
+    BrowseKubernetes,
     Cancel,
 }
@@ -1,10 +1,24 @@
 //! Connection list screen
 
 use egui::{Context, Ui};
+use crate::ssh::host_watch::WatchStatus;
+use std::time::Duration;
+
+/// Row shown in the connection list
+#[derive(Debug, Clone)]
+pub struct ConnectionSummary {
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+}
 
 pub struct ConnectionListScreen {
     search_query: String,
     selected_connection: Option<String>,
+    pub connections: Vec<ConnectionSummary>,
+    /// Profiles being watched by name, from `AppState::watch_statuses`
+    pub watching: Vec<(String, WatchStatus)>,
 }
 
 impl ConnectionListScreen {
@@ -12,6 +26,11 @@ impl ConnectionListScreen {
         Self {
             search_query: String::new(),
             selected_connection: None,
+            #[cfg(feature = "demo-data")]
+            connections: sample_connections(),
+            #[cfg(not(feature = "demo-data"))]
+            connections: Vec::new(),
+            watching: Vec::new(),
         }
     }
     
@@ -35,6 +54,26 @@ impl ConnectionListScreen {
         });
         
         ui.separator();
+
+        if !self.watching.is_empty() {
+            // Probe results come in every few seconds
+            ui.ctx().request_repaint_after(Duration::from_secs(1));
+        }
+        
+        if self.connections.is_empty() {
+            ui.vertical_centered(|ui| {
+                ui.add_space(24.0);
+                ui.label("No saved connections yet");
+                ui.add_space(8.0);
+                if ui.button("➕ New Connection").clicked() {
+                    action = Some(ConnectionAction::New);
+                }
+                if ui.button("📥 Import SSH Config").clicked() {
+                    action = Some(ConnectionAction::ImportConfig);
+                }
+            });
+            return action;
+        }
         
         // Connection groups/categories
         ui.collapsing("Recent", |ui| {
@@ -48,36 +87,75 @@ impl ConnectionListScreen {
         action
     }
     
-    fn render_connection_list(&mut self, ui: &mut Ui, action: &mut Option<ConnectionAction>, _recent_only: bool) {
-        let connections = vec![
-            ("Production Server", "prod.example.com", "22", "admin"),
-            ("Dev Server", "dev.example.com", "22", "user"),
-            ("Database Server", "db.example.com", "22", "dbadmin"),
-        ];
+    fn render_connection_list(&mut self, ui: &mut Ui, action: &mut Option<ConnectionAction>, recent_only: bool) {
+        let query = self.search_query.to_lowercase();
+        let matching: Vec<usize> = (0..self.connections.len())
+            .filter(|&i| {
+                let c = &self.connections[i];
+                query.is_empty()
+                    || c.name.to_lowercase().contains(&query)
+                    || c.host.to_lowercase().contains(&query)
+            })
+            .collect();
         
-        for (name, host, port, user) in connections {
-            ui.horizontal(|ui| {
-                let is_selected = self.selected_connection.as_deref() == Some(name);
-                
-                if ui.selectable_label(is_selected, format!("🖥{}",name)).clicked(){
-                    self.selected_connection = Some(name.to_string());
+        // Only the rows on screen are laid out
+        let row_height = ui.spacing().interact_size.y;
+        egui::ScrollArea::vertical()
+            .id_source(if recent_only { "recent_connections" } else { "all_connections" })
+            .max_height(400.0)
+            .show_rows(ui, row_height, matching.len(), |ui, visible| {
+                for &index in &matching[visible] {
+                    self.render_connection_row(ui, action, index);
                 }
-                
-                ui.label(format!("{}@{}:{}",user,host,port));
-                
-                if ui.small_button("🔌").clicked() {
-                    *action = Some(ConnectionAction::Connect(name.to_string()));
-                }
-                
-                if ui.small_button("✏").clicked() {
-                    *action = Some(ConnectionAction::Edit(name.to_string()));
+            });
+    }
+    
+    fn render_connection_row(&mut self, ui: &mut Ui, action: &mut Option<ConnectionAction>, index: usize) {
+        let ConnectionSummary { name, host, port, username: user } = self.connections[index].clone();
+        ui.horizontal(|ui| {
+            let is_selected = self.selected_connection.as_deref() == Some(name.as_str());
+            
+            if ui.selectable_label(is_selected, format!("🖥{}",name)).clicked(){
+                self.selected_connection = Some(name.clone());
+            }
+            
+            ui.label(format!("{}@{}:{}",user,host,port));
+            
+            if ui.small_button("🔌").clicked() {
+                *action = Some(ConnectionAction::Connect(name.clone()));
+            }
+            
+            match self.watching.iter().find(|(watched, _)| *watched == name) {
+                Some((_, status)) => {
+                    ui.label(egui::RichText::new(format!("👁 {}", status)).weak());
+                    if ui.small_button("⏹").on_hover_text("Stop watching").clicked() {
+                        *action = Some(ConnectionAction::StopWatching(name.clone()));
+                    }
                 }
-                
-                if ui.small_button("🗑").clicked() {
-                    *action = Some(ConnectionAction::Delete(name.to_string()));
+                None => {
+                    ui.menu_button("👁", |ui| {
+                        if ui.button("Notify when reachable").clicked() {
+                            *action = Some(ConnectionAction::Watch { name: name.clone(), auto_connect: false });
+                            ui.close_menu();
+                        }
+                        if ui.button("Connect when reachable").clicked() {
+                            *action = Some(ConnectionAction::Watch { name: name.clone(), auto_connect: true });
+                            ui.close_menu();
+                        }
+                    })
+                    .response
+                    .on_hover_text("Watch host: probe it until it answers, e.g. during a reboot");
                 }
-            });
-        }
+            }
+            
+            if ui.small_button("✏").clicked() {
+                *action = Some(ConnectionAction::Edit(name.clone()));
+            }
+            
+            if ui.small_button("🗑").clicked() {
+                *action = Some(ConnectionAction::Delete(name.clone()));
+            }
+        });
     }
 }
 
@@ -94,4 +172,25 @@ pub enum ConnectionAction {
     Edit(String),
     Delete(String),
     ImportConfig,
+    /// `AppState::watch_host` the named profile
+    Watch { name: String, auto_connect: bool },
+    StopWatching(String),
+}
+
+/// Fake hosts for working on the UI without a database
+#[cfg(feature = "demo-data")]
+fn sample_connections() -> Vec<ConnectionSummary> {
+    [
+        ("Production Server", "prod.example.com", 22, "admin"),
+        ("Dev Server", "dev.example.com", 22, "user"),
+        ("Database Server", "db.example.com", 22, "dbadmin"),
+    ]
+    .into_iter()
+    .map(|(name, host, port, username)| ConnectionSummary {
+        name: name.to_string(),
+        host: host.to_string(),
+        port,
+        username: username.to_string(),
+    })
+    .collect()
 }
@@ -1,7 +1,32 @@
 //! Connection Manager Screen - displays list of saved connections
 
 use eframe::egui::{self, RichText, Vec2};
+use egui_extras::{Column, TableBuilder};
 use crate::ui::components::{colors, spacing, primary_button, secondary_button, icon_button, empty_state};
+use crate::plugins::ContextActionContribution;
+use crate::utils::custom_actions::{ActionContext, CustomAction};
+use crate::utils::helpers::parse_quick_connect;
+use crate::utils::history_import::{suggest_profiles, HistoryTarget};
+use crate::utils::profile_share::{SharedForward, SharedJumpHost, SharedProfile};
+use crate::ssh::config_parser::{host_alias, write_config, HostConfig};
+use crate::ssh::discovery::DiscoveredHost;
+use crate::ssh::{prefer_method, ConnectionConfig, Credentials};
+use crate::ssh::bastion::{BastionHop, BastionId};
+use crate::ssh::host_ca;
+use crate::ssh::knock::Knock;
+use crate::integrations::cloud::CloudInstance;
+use crate::integrations::kubernetes::KubeTarget;
+use crate::integrations::services::ServiceAction;
+use crate::storage::Database;
+use crate::storage::database::TrustedCa;
+use crate::storage::folders::{self, Folder, FolderDefaults, FolderJumpHost};
+use crate::storage::bookmarks::PathBookmark;
+use crate::storage::retention::LogRetention;
+use crate::storage::session_groups::{PaneLayout, SessionGroup};
+use std::collections::HashSet;
+use anyhow::Result;
+use rusqlite::Row;
+use serde::{Deserialize, Serialize};
 
 /// Connection profile for display
 #[derive(Clone)]
@@ -13,15 +38,144 @@ pub struct ConnectionProfile {
     pub username: String,
     pub auth_type: AuthType,
     pub group: Option<String>,
+    /// RFC 3339 time of the last successful connect
     pub last_connected: Option<String>,
+    /// Successful connects so far
+    pub connection_count: u32,
     pub is_favorite: bool,
+    /// `TERM` to request instead of the default from settings
+    pub terminal_type: Option<String>,
+    /// Private key for public key auth (never the key itself)
+    pub key_path: Option<String>,
+    /// More keys to offer after `key_path`, like several OpenSSH IdentityFiles
+    pub identity_files: Vec<String>,
+    /// Methods to try in turn; empty tries only `auth_type`
+    pub auth_order: Vec<AuthType>,
+    /// `Credentials::id` of the method that worked last, tried first
+    pub last_auth: Option<String>,
+    pub compression: bool,
+    pub keepalive_interval: u16,
+    pub connection_timeout: u16,
+    pub tcp_keepalive: bool,
+    pub initial_command: Option<String>,
+    pub encoding: String,
+    pub x11_forwarding: bool,
+    pub agent_forwarding: bool,
+    pub local_forwards: Vec<ProfileForward>,
+    pub remote_forwards: Vec<ProfileForward>,
+    pub jump_host: Option<ProfileJumpHost>,
+    /// Ports to knock on before connecting, for hosts behind knockd
+    pub knock_sequence: Vec<Knock>,
+    /// The server's `ssh_host_*_key-cert.pub` line, checked against the
+    /// trusted CAs instead of known_hosts
+    pub host_certificate: Option<String>,
+    pub notes: String,
+    /// Free-form labels for filtering, e.g. "eu", "k8s"
+    pub tags: Vec<String>,
+    /// Open a shell in this pod with kubectl instead of connecting over SSH
+    pub kubernetes: Option<KubeTarget>,
+    /// Open the SFTP browser whenever the terminal connects
+    pub auto_sftp: AutoSftp,
+    /// Production or otherwise sensitive host
+    pub critical: bool,
+    /// On a critical host, confirm every command line before it is sent
+    pub confirm_commands: bool,
+    /// Commands shown as buttons next to the terminal, e.g. `systemctl status nginx`
+    pub service_actions: Vec<ServiceAction>,
+    /// Scrollback length instead of the one in settings
+    pub scrollback_lines: Option<usize>,
+    /// Limits on this host's session logs and captures instead of the ones in settings
+    pub log_retention: Option<LogRetention>,
 }
 
-#[derive(Clone, PartialEq)]
+/// Port forward saved with a profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileForward {
+    pub local_port: u16,
+    pub remote_host: String,
+    pub remote_port: u16,
+    pub enabled: bool,
+}
+
+/// Jump host saved with a profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileJumpHost {
+    pub host: String,
+    pub port: u16,
+    /// Same as the connection's user when `None`
+    pub username: Option<String>,
+    /// The bastion ends logins after this many minutes unless verified
+    /// again; the login is renewed ahead of time
+    #[serde(default)]
+    pub reauth_minutes: Option<u32>,
+}
+
+impl ProfileJumpHost {
+    /// How to log in to the jump host for a connection as `user`, for
+    /// `AppState::bastion`
+    pub fn hop(&self, user: &str, methods: Vec<Credentials>) -> BastionHop {
+        let user = self.username.clone().unwrap_or_else(|| user.to_string());
+        BastionHop {
+            id: BastionId { user: user.clone(), host: self.host.clone(), port: self.port },
+            config: ConnectionConfig {
+                host: self.host.clone(),
+                port: self.port,
+                username: user,
+                ..Default::default()
+            },
+            methods,
+            reauth_every: self.reauth_minutes.map(|minutes| std::time::Duration::from_secs(minutes as u64 * 60)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum AuthType {
     Password,
     PublicKey,
     KeyboardInteractive,
+    Agent,
+}
+
+/// Where the SFTP browser opens when a profile's terminal connects
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum AutoSftp {
+    #[default]
+    Off,
+    /// Split pane next to the terminal
+    Split,
+    /// Tab of its own, linked to the terminal's
+    Tab,
+}
+
+impl AutoSftp {
+    pub const ALL: [AutoSftp; 3] = [AutoSftp::Off, AutoSftp::Split, AutoSftp::Tab];
+
+    fn as_db_str(&self) -> &'static str {
+        match self {
+            AutoSftp::Off => "off",
+            AutoSftp::Split => "split",
+            AutoSftp::Tab => "tab",
+        }
+    }
+
+    fn from_db_str(s: &str) -> Self {
+        match s {
+            "split" => AutoSftp::Split,
+            "tab" => AutoSftp::Tab,
+            _ => AutoSftp::Off,
+        }
+    }
+}
+
+impl std::fmt::Display for AutoSftp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AutoSftp::Off => write!(f, "Don't open"),
+            AutoSftp::Split => write!(f, "Beside the terminal"),
+            AutoSftp::Tab => write!(f, "In a new tab"),
+        }
+    }
 }
 
 impl std::fmt::Display for AuthType {
@@ -30,6 +184,7 @@ impl std::fmt::Display for AuthType {
             AuthType::Password => write!(f, "Password"),
             AuthType::PublicKey => write!(f, "Public Key"),
             AuthType::KeyboardInteractive => write!(f, "Keyboard Interactive"),
+            AuthType::Agent => write!(f, "SSH Agent"),
         }
     }
 }
@@ -45,7 +200,603 @@ impl Default for ConnectionProfile {
             auth_type: AuthType::Password,
             group: None,
             last_connected: None,
+            connection_count: 0,
             is_favorite: false,
+            terminal_type: None,
+            key_path: None,
+            identity_files: Vec::new(),
+            auth_order: Vec::new(),
+            last_auth: None,
+            compression: false,
+            keepalive_interval: 30,
+            connection_timeout: 30,
+            tcp_keepalive: true,
+            initial_command: None,
+            encoding: String::from("UTF-8"),
+            x11_forwarding: false,
+            agent_forwarding: false,
+            local_forwards: Vec::new(),
+            remote_forwards: Vec::new(),
+            jump_host: None,
+            knock_sequence: Vec::new(),
+            host_certificate: None,
+            scrollback_lines: None,
+            log_retention: None,
+            notes: String::new(),
+            tags: Vec::new(),
+            kubernetes: None,
+            auto_sftp: AutoSftp::Off,
+            critical: false,
+            confirm_commands: false,
+            service_actions: Vec::new(),
+        }
+    }
+}
+
+const PROFILE_COLUMNS: &str = "id, name, host, port, username, auth_type, group_name, last_connected,
+    is_favorite, terminal_type, key_path, compression, keepalive, timeout, tcp_keepalive,
+    initial_command, encoding, x11_forwarding, agent_forwarding, local_forwards, remote_forwards,
+    jump_host, notes, connection_count, tags, kubernetes, auto_sftp, critical, confirm_commands,
+    identity_files, auth_order, last_auth, service_actions, knock_sequence, scrollback_lines,
+    log_retention, host_certificate";
+
+impl AuthType {
+    /// Fallback order offered for new profiles, the same as OpenSSH's
+    pub const DEFAULT_ORDER: [AuthType; 4] = [
+        AuthType::Agent,
+        AuthType::PublicKey,
+        AuthType::Password,
+        AuthType::KeyboardInteractive,
+    ];
+
+    fn as_db_str(&self) -> &'static str {
+        match self {
+            AuthType::Password => "password",
+            AuthType::PublicKey => "publickey",
+            AuthType::KeyboardInteractive => "keyboard-interactive",
+            AuthType::Agent => "agent",
+        }
+    }
+
+    fn from_db_str(s: &str) -> Self {
+        match s {
+            "publickey" => AuthType::PublicKey,
+            "keyboard-interactive" => AuthType::KeyboardInteractive,
+            "agent" => AuthType::Agent,
+            _ => AuthType::Password,
+        }
+    }
+}
+
+impl ConnectionProfile {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let json = |index: usize| -> rusqlite::Result<Option<String>> { row.get(index) };
+
+        Ok(Self {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            host: row.get(2)?,
+            port: row.get::<_, i64>(3)? as u16,
+            username: row.get(4)?,
+            auth_type: AuthType::from_db_str(&row.get::<_, String>(5)?),
+            group: row.get(6)?,
+            last_connected: row.get(7)?,
+            is_favorite: row.get::<_, i64>(8)? != 0,
+            terminal_type: row.get(9)?,
+            key_path: row.get(10)?,
+            compression: row.get::<_, i64>(11)? != 0,
+            keepalive_interval: row.get::<_, i64>(12)? as u16,
+            connection_timeout: row.get::<_, i64>(13)? as u16,
+            tcp_keepalive: row.get::<_, i64>(14)? != 0,
+            initial_command: row.get(15)?,
+            encoding: row.get(16)?,
+            x11_forwarding: row.get::<_, i64>(17)? != 0,
+            agent_forwarding: row.get::<_, i64>(18)? != 0,
+            local_forwards: json(19)?.and_then(|j| serde_json::from_str(&j).ok()).unwrap_or_default(),
+            remote_forwards: json(20)?.and_then(|j| serde_json::from_str(&j).ok()).unwrap_or_default(),
+            jump_host: json(21)?.and_then(|j| serde_json::from_str(&j).ok()),
+            notes: row.get(22)?,
+            connection_count: row.get::<_, i64>(23)? as u32,
+            tags: json(24)?.and_then(|j| serde_json::from_str(&j).ok()).unwrap_or_default(),
+            kubernetes: json(25)?.and_then(|j| serde_json::from_str(&j).ok()),
+            auto_sftp: AutoSftp::from_db_str(&row.get::<_, String>(26)?),
+            critical: row.get::<_, i64>(27)? != 0,
+            confirm_commands: row.get::<_, i64>(28)? != 0,
+            identity_files: json(29)?.and_then(|j| serde_json::from_str(&j).ok()).unwrap_or_default(),
+            auth_order: json(30)?
+                .and_then(|j| serde_json::from_str::<Vec<String>>(&j).ok())
+                .map(|order| order.iter().map(|s| AuthType::from_db_str(s)).collect())
+                .unwrap_or_default(),
+            last_auth: row.get(31)?,
+            service_actions: json(32)?.and_then(|j| serde_json::from_str(&j).ok()).unwrap_or_default(),
+            knock_sequence: json(33)?.and_then(|j| serde_json::from_str(&j).ok()).unwrap_or_default(),
+            scrollback_lines: row.get::<_, Option<i64>>(34)?.map(|lines| lines as usize),
+            log_retention: json(35)?.and_then(|j| serde_json::from_str(&j).ok()),
+            host_certificate: row.get(36)?,
+        })
+    }
+
+    /// Insert or update in the database; usage stats and creation time are kept on update
+    pub fn save(&self, db: &Database) -> Result<()> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let jump_host = self.jump_host.as_ref().map(serde_json::to_string).transpose()?;
+        let kubernetes = self.kubernetes.as_ref().map(serde_json::to_string).transpose()?;
+        let log_retention = self.log_retention.as_ref().map(serde_json::to_string).transpose()?;
+
+        db.connection().execute(
+            "INSERT INTO connections
+             (id, name, host, port, username, auth_type, group_name, is_favorite, terminal_type,
+              key_path, compression, keepalive, timeout, tcp_keepalive, initial_command, encoding,
+              x11_forwarding, agent_forwarding, local_forwards, remote_forwards, jump_host, notes,
+              tags, kubernetes, auto_sftp, critical, confirm_commands, identity_files, auth_order,
+              service_actions, knock_sequence, scrollback_lines, log_retention, host_certificate,
+              created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+                     ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?29, ?30, ?31, ?32, ?33, ?34,
+                     ?35, ?28, ?28)
+             ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name, host = excluded.host, port = excluded.port,
+                username = excluded.username, auth_type = excluded.auth_type,
+                group_name = excluded.group_name, is_favorite = excluded.is_favorite,
+                terminal_type = excluded.terminal_type, key_path = excluded.key_path,
+                compression = excluded.compression, keepalive = excluded.keepalive,
+                timeout = excluded.timeout, tcp_keepalive = excluded.tcp_keepalive,
+                initial_command = excluded.initial_command, encoding = excluded.encoding,
+                x11_forwarding = excluded.x11_forwarding, agent_forwarding = excluded.agent_forwarding,
+                local_forwards = excluded.local_forwards, remote_forwards = excluded.remote_forwards,
+                jump_host = excluded.jump_host, notes = excluded.notes, tags = excluded.tags,
+                kubernetes = excluded.kubernetes, auto_sftp = excluded.auto_sftp,
+                critical = excluded.critical, confirm_commands = excluded.confirm_commands,
+                identity_files = excluded.identity_files, auth_order = excluded.auth_order,
+                service_actions = excluded.service_actions, knock_sequence = excluded.knock_sequence,
+                scrollback_lines = excluded.scrollback_lines, log_retention = excluded.log_retention,
+                host_certificate = excluded.host_certificate, updated_at = excluded.updated_at",
+            rusqlite::params![
+                &self.id,
+                &self.name,
+                &self.host,
+                self.port as i64,
+                &self.username,
+                self.auth_type.as_db_str(),
+                &self.group,
+                self.is_favorite as i64,
+                &self.terminal_type,
+                &self.key_path,
+                self.compression as i64,
+                self.keepalive_interval as i64,
+                self.connection_timeout as i64,
+                self.tcp_keepalive as i64,
+                &self.initial_command,
+                &self.encoding,
+                self.x11_forwarding as i64,
+                self.agent_forwarding as i64,
+                serde_json::to_string(&self.local_forwards)?,
+                serde_json::to_string(&self.remote_forwards)?,
+                jump_host,
+                &self.notes,
+                serde_json::to_string(&self.tags)?,
+                kubernetes,
+                self.auto_sftp.as_db_str(),
+                self.critical as i64,
+                self.confirm_commands as i64,
+                &now,
+                serde_json::to_string(&self.identity_files)?,
+                serde_json::to_string(&self.auth_order.iter().map(AuthType::as_db_str).collect::<Vec<_>>())?,
+                serde_json::to_string(&self.service_actions)?,
+                serde_json::to_string(&self.knock_sequence)?,
+                self.scrollback_lines.map(|lines| lines as i64),
+                log_retention,
+                &self.host_certificate,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// All saved profiles, by name
+    pub fn load_all(db: &Database) -> Result<Vec<Self>> {
+        let conn = db.connection();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM connections ORDER BY name COLLATE NOCASE",
+            PROFILE_COLUMNS
+        ))?;
+        let profiles = stmt.query_map([], Self::from_row)?.collect::<Result<Vec<_>, _>>()?;
+        Ok(profiles)
+    }
+
+    pub fn load(db: &Database, id: &str) -> Result<Option<Self>> {
+        match db.connection().query_row(
+            &format!("SELECT {} FROM connections WHERE id = ?1", PROFILE_COLUMNS),
+            [id],
+            Self::from_row,
+        ) {
+            Ok(profile) => Ok(Some(profile)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn delete(id: &str, db: &Database) -> Result<()> {
+        db.connection().execute("DELETE FROM connections WHERE id = ?1", [id])?;
+        SessionGroup::forget_profile(db, id)?;
+        PathBookmark::forget_connection(db, id)?;
+        Ok(())
+    }
+
+    pub fn set_favorite(id: &str, favorite: bool, db: &Database) -> Result<()> {
+        db.connection().execute(
+            "UPDATE connections SET is_favorite = ?2, updated_at = ?3 WHERE id = ?1",
+            rusqlite::params![id, favorite as i64, chrono::Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Save notes edited next to a session without touching other fields
+    pub fn save_notes(id: &str, notes: &str, db: &Database) -> Result<()> {
+        db.connection().execute(
+            "UPDATE connections SET notes = ?2, updated_at = ?3 WHERE id = ?1",
+            rusqlite::params![id, notes, chrono::Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Remember which method authenticated, to try it first next time
+    pub fn record_auth(id: &str, method: &str, db: &Database) -> Result<()> {
+        db.connection().execute(
+            "UPDATE connections SET last_auth = ?2 WHERE id = ?1",
+            rusqlite::params![id, method],
+        )?;
+        Ok(())
+    }
+
+    /// Everything to try when connecting, in order: each method of
+    /// `auth_order` (or just `auth_type`), every key for public key auth,
+    /// and what worked last time moved to the front. Password and
+    /// keyboard-interactive need `password`; without it the password step
+    /// is left out.
+    pub fn auth_methods(&self, password: Option<&str>, passphrase: Option<&str>) -> Vec<Credentials> {
+        let order = if self.auth_order.is_empty() {
+            std::slice::from_ref(&self.auth_type)
+        } else {
+            self.auth_order.as_slice()
+        };
+        let mut methods = Vec::new();
+        for auth_type in order {
+            match auth_type {
+                AuthType::Agent => methods.push(Credentials::agent()),
+                AuthType::PublicKey => {
+                    for key in self.key_path.iter().chain(&self.identity_files) {
+                        let path = shellexpand::tilde(key.trim()).into_owned();
+                        methods.push(Credentials::public_key(path, passphrase.map(str::to_string)));
+                    }
+                }
+                AuthType::Password => methods.extend(password.map(Credentials::password)),
+                AuthType::KeyboardInteractive => methods.push(Credentials::keyboard_interactive()),
+            }
+        }
+        if let Some(last) = &self.last_auth {
+            prefer_method(&mut methods, last);
+        }
+        methods
+    }
+
+    /// Settings for dialing this profile. With a host certificate the
+    /// server's key is checked against `trusted_cas` instead of known_hosts.
+    pub fn connection_config(&self, trusted_cas: Vec<TrustedCa>) -> ConnectionConfig {
+        let config = ConnectionConfig::new(self.host.trim(), self.username.trim())
+            .with_port(self.port)
+            .with_timeout(self.connection_timeout as u32)
+            .with_compression(self.compression)
+            .with_knock(self.knock_sequence.clone());
+        match self.host_certificate.as_deref().and_then(host_ca::parse_certificate_line) {
+            Some(certificate) => config.with_host_certificate(certificate, trusted_cas),
+            None => config,
+        }
+    }
+
+    /// Note a successful connect; returns the new last-connected time
+    pub fn record_connected(id: &str, db: &Database) -> Result<String> {
+        let now = chrono::Utc::now().to_rfc3339();
+        db.connection().execute(
+            "UPDATE connections SET last_connected = ?2, connection_count = connection_count + 1
+             WHERE id = ?1",
+            rusqlite::params![id, &now],
+        )?;
+        Ok(now)
+    }
+
+    /// Copy with unset fields filled from the folder defaults, for connecting.
+    /// Fields still at the built-in default count as unset, as does an empty
+    /// username.
+    pub fn with_folder_defaults(&self, folders: &[Folder]) -> Self {
+        let mut profile = self.clone();
+        let Some(group) = &self.group else {
+            return profile;
+        };
+        let defaults = folders::resolve_defaults(folders, group);
+        let base = Self::default();
+        if profile.username.trim().is_empty() {
+            profile.username = defaults.username.unwrap_or_default();
+        }
+        if profile.port == base.port {
+            profile.port = defaults.port.unwrap_or(profile.port);
+        }
+        if profile.auth_type == AuthType::PublicKey && profile.key_path.is_none() {
+            profile.key_path = defaults.key_path;
+        }
+        if profile.jump_host.is_none() {
+            profile.jump_host = defaults.jump_host.map(|jump| ProfileJumpHost {
+                host: jump.host,
+                port: jump.port,
+                username: jump.username,
+                reauth_minutes: None,
+            });
+        }
+        if profile.terminal_type.is_none() {
+            profile.terminal_type = defaults.terminal_type;
+        }
+        if profile.initial_command.is_none() {
+            profile.initial_command = defaults.initial_command;
+        }
+        if profile.encoding == base.encoding {
+            profile.encoding = defaults.encoding.unwrap_or(profile.encoding);
+        }
+        if profile.keepalive_interval == base.keepalive_interval {
+            profile.keepalive_interval = defaults.keepalive_interval.unwrap_or(profile.keepalive_interval);
+        }
+        profile
+    }
+
+    /// New profile for a host announced over mDNS
+    pub fn from_discovered(host: &DiscoveredHost) -> Self {
+        Self {
+            name: host.instance.clone(),
+            // The .local name survives DHCP address changes
+            host: host.hostname.trim_end_matches('.').to_string(),
+            port: host.port,
+            group: Some("LAN".to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// New profile for a cloud instance, tagged with its provider tags
+    pub fn from_cloud(instance: &CloudInstance) -> Self {
+        Self {
+            name: instance.name.clone(),
+            host: instance.address().unwrap_or_default().to_string(),
+            group: Some(instance.provider.to_string()),
+            auth_type: AuthType::PublicKey,
+            tags: instance.tags.iter()
+                .map(|(k, v)| if v.is_empty() { k.clone() } else { format!("{}={}", k, v) })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// New profile for a target seen in shell history
+    pub fn from_history(target: &HistoryTarget) -> Self {
+        Self {
+            name: target.host.clone(),
+            host: target.host.clone(),
+            port: target.port,
+            username: target.user.clone().unwrap_or_else(|| {
+                // ssh(1) falls back to the local user name
+                std::env::var("USER").or_else(|_| std::env::var("USERNAME")).unwrap_or_default()
+            }),
+            auth_type: AuthType::PublicKey,
+            ..Default::default()
+        }
+    }
+
+    /// Where the profile connects to, for cards and lists
+    pub fn address_display(&self) -> String {
+        match &self.kubernetes {
+            Some(target) => format!("kubectl {}", target.label()),
+            None => format!("{}@{}:{}", self.username, self.host, self.port),
+        }
+    }
+
+    /// OpenSSH `Host` entry for the profile with its folder defaults
+    /// applied; None for kubectl profiles. Only enabled forwards are
+    /// written, and no secrets.
+    pub fn to_host_config(&self, folders: &[Folder]) -> Option<HostConfig> {
+        if self.kubernetes.is_some() {
+            return None;
+        }
+        let profile = self.with_folder_defaults(folders);
+        let forwards = |list: &[ProfileForward]| {
+            list.iter()
+                .filter(|f| f.enabled)
+                .map(|f| (f.local_port, f.remote_host.clone(), f.remote_port))
+                .collect()
+        };
+        let alias = Some(host_alias(&profile.name))
+            .filter(|alias| !alias.is_empty())
+            .unwrap_or_else(|| host_alias(&profile.host));
+        Some(HostConfig {
+            host_pattern: if alias.is_empty() { "host".to_string() } else { alias },
+            hostname: Some(profile.host.clone()),
+            port: Some(profile.port),
+            user: Some(profile.username.clone()).filter(|u| !u.is_empty()),
+            identity_file: match (&profile.auth_type, &profile.key_path) {
+                (AuthType::PublicKey, Some(path)) => vec![path.clone()],
+                _ => Vec::new(),
+            },
+            proxy_jump: profile.jump_host.as_ref().map(|jump| {
+                let user = jump.username.as_deref().map(|u| format!("{}@", u)).unwrap_or_default();
+                format!("{}{}:{}", user, jump.host, jump.port)
+            }),
+            local_forward: forwards(&profile.local_forwards),
+            remote_forward: forwards(&profile.remote_forwards),
+            compression: Some(profile.compression),
+            server_alive_interval: Some(profile.keepalive_interval as u32),
+            ..Default::default()
+        })
+    }
+
+    /// Copy to share with others, without any secrets or key paths
+    pub fn to_shared(&self) -> SharedProfile {
+        let forward = |kind: &str, fwd: &ProfileForward| SharedForward {
+            kind: kind.to_string(),
+            local_port: fwd.local_port,
+            remote_host: fwd.remote_host.clone(),
+            remote_port: fwd.remote_port,
+        };
+        SharedProfile {
+            name: self.name.clone(),
+            host: self.host.clone(),
+            port: self.port,
+            username: self.username.clone(),
+            auth_method: self.auth_type.to_string(),
+            group: self.group.clone(),
+            jump_host: self.jump_host.as_ref().map(|jump| SharedJumpHost {
+                host: jump.host.clone(),
+                port: jump.port,
+                username: jump.username.clone(),
+            }),
+            forwards: self.local_forwards.iter().map(|f| forward("local", f))
+                .chain(self.remote_forwards.iter().map(|f| forward("remote", f)))
+                .collect(),
+        }
+    }
+
+    /// Last connect in local time, for display
+    pub fn last_connected_display(&self) -> Option<String> {
+        let last = self.last_connected.as_ref()?;
+        Some(match chrono::DateTime::parse_from_rfc3339(last) {
+            Ok(time) => time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string(),
+            // Older profiles stored a preformatted string
+            Err(_) => last.clone(),
+        })
+    }
+}
+
+/// Order of the connection list
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ConnectionSort {
+    #[default]
+    Name,
+    MostRecent,
+    MostUsed,
+}
+
+impl ConnectionSort {
+    pub const ALL: [ConnectionSort; 3] = [Self::Name, Self::MostRecent, Self::MostUsed];
+
+    fn compare(&self, a: &ConnectionProfile, b: &ConnectionProfile) -> std::cmp::Ordering {
+        let by_name = || a.name.to_lowercase().cmp(&b.name.to_lowercase());
+        match self {
+            Self::Name => by_name(),
+            // RFC 3339 UTC strings sort chronologically; never-connected go last
+            Self::MostRecent => b.last_connected.cmp(&a.last_connected).then_with(by_name),
+            Self::MostUsed => b.connection_count.cmp(&a.connection_count).then_with(by_name),
+        }
+    }
+}
+
+impl std::fmt::Display for ConnectionSort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Name => write!(f, "Name"),
+            Self::MostRecent => write!(f, "Most recent"),
+            Self::MostUsed => write!(f, "Most used"),
+        }
+    }
+}
+
+/// Height of a connection card's contents, fixed so the list can skip
+/// laying out cards that are scrolled away
+const CARD_INNER_HEIGHT: f32 = 60.0;
+const CARD_ROW_HEIGHT: f32 = CARD_INNER_HEIGHT + 2.0 * spacing::MD + spacing::SM;
+
+/// What the filtered connection list was built from
+#[derive(Debug, Clone, PartialEq)]
+struct ListKey {
+    query: String,
+    group: Option<String>,
+    layout: ConnectionLayout,
+    sort: ConnectionSort,
+    table_sort: (TableColumn, bool),
+}
+
+/// A connection in the filtered list with its text formatted once
+struct ListRow {
+    /// Index into `ConnectionManagerScreen::connections`
+    index: usize,
+    /// Address and last-connected line for cards; the cells in
+    /// `TableColumn::ALL` order for the table
+    text: Vec<String>,
+}
+
+/// How the connection list is laid out
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ConnectionLayout {
+    #[default]
+    Cards,
+    /// Dense rows for large inventories
+    Table,
+}
+
+/// Sortable column of the table layout
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TableColumn {
+    Name,
+    Host,
+    User,
+    Group,
+    Tags,
+    LastConnected,
+    Auth,
+}
+
+impl TableColumn {
+    pub const ALL: [TableColumn; 7] = [
+        Self::Name, Self::Host, Self::User, Self::Group, Self::Tags, Self::LastConnected, Self::Auth,
+    ];
+
+    fn title(&self) -> &'static str {
+        match self {
+            Self::Name => "Name",
+            Self::Host => "Host",
+            Self::User => "User",
+            Self::Group => "Group",
+            Self::Tags => "Tags",
+            Self::LastConnected => "Last connected",
+            Self::Auth => "Auth",
+        }
+    }
+
+    fn initial_width(&self) -> f32 {
+        match self {
+            Self::Name | Self::Host => 180.0,
+            Self::LastConnected | Self::Tags => 130.0,
+            _ => 100.0,
+        }
+    }
+
+    fn compare(&self, a: &ConnectionProfile, b: &ConnectionProfile) -> std::cmp::Ordering {
+        let text = |s: &str| s.to_lowercase();
+        match self {
+            Self::Name => text(&a.name).cmp(&text(&b.name)),
+            Self::Host => text(&a.host).cmp(&text(&b.host)).then(a.port.cmp(&b.port)),
+            Self::User => a.username.cmp(&b.username),
+            Self::Group => a.group.cmp(&b.group),
+            Self::Tags => a.tags.cmp(&b.tags),
+            Self::LastConnected => a.last_connected.cmp(&b.last_connected),
+            Self::Auth => a.auth_type.to_string().cmp(&b.auth_type.to_string()),
+        }
+    }
+
+    fn cell(&self, conn: &ConnectionProfile) -> String {
+        match self {
+            Self::Name => conn.name.clone(),
+            Self::Host if conn.kubernetes.is_some() => conn.address_display(),
+            Self::Host if conn.port == 22 => conn.host.clone(),
+            Self::Host => format!("{}:{}", conn.host, conn.port),
+            Self::User => conn.username.clone(),
+            Self::Group => conn.group.clone().unwrap_or_default(),
+            Self::Tags => conn.tags.join(", "),
+            Self::LastConnected => conn.last_connected_display().unwrap_or_else(|| "Never".to_string()),
+            Self::Auth => conn.auth_type.to_string(),
         }
     }
 }
@@ -54,9 +805,45 @@ impl Default for ConnectionProfile {
 pub struct ConnectionManagerScreen {
     pub connections: Vec<ConnectionProfile>,
     pub search_query: String,
+    /// `user@host:port` typed into the quick-connect box
+    pub quick_connect: String,
     pub selected_connection_id: Option<String>,
     pub selected_group: Option<String>,
     pub groups: Vec<String>,
+    pub sort: ConnectionSort,
+    pub custom_actions: Vec<CustomAction>,
+    /// Connection actions contributed by enabled plugins, as `(plugin id, action)`
+    pub plugin_actions: Vec<(String, ContextActionContribution)>,
+    /// Targets found in shell history, with whether each is ticked for creation
+    pub history_suggestions: Vec<(HistoryTarget, bool)>,
+    /// LAN discovery panel: `None` when closed, empty while nothing answered
+    pub discovered: Option<Vec<DiscoveredHost>>,
+    /// Discovery is running
+    pub discovering: bool,
+    pub layout: ConnectionLayout,
+    /// Table sort column and whether it is ascending
+    pub table_sort: (TableColumn, bool),
+    /// Saved folders with their defaults
+    pub folders: Vec<Folder>,
+    /// Folder paths collapsed in the sidebar
+    collapsed: HashSet<String>,
+    /// Profile being dragged onto a folder
+    dragging: Option<String>,
+    /// Folder being created: parent path and name typed so far
+    new_folder: Option<(Option<String>, String)>,
+    /// Folder whose defaults are being edited
+    editing_folder: Option<Folder>,
+    /// Jump host of the edited folder as typed, `user@host:port`
+    editing_jump: String,
+    /// Saved session groups, listed below the folders
+    pub session_groups: Vec<SessionGroup>,
+    /// Session group being created or edited
+    editing_session_group: Option<SessionGroup>,
+    /// Profiles ticked in the OpenSSH export dialog, while it is open
+    ssh_export: Option<HashSet<String>>,
+    /// Filtered and sorted rows from the last frame, reused until the
+    /// search, group, order or connections change
+    list_rows: Option<(ListKey, Vec<ListRow>)>,
 }
 
 impl Default for ConnectionManagerScreen {
@@ -67,59 +854,619 @@ impl Default for ConnectionManagerScreen {
 
 impl ConnectionManagerScreen {
     pub fn new() -> Self {
-        // Sample connections for demo
-        let connections = vec![
-            ConnectionProfile {
-                id: "1".to_string(),
-                name: "Production Server".to_string(),
-                host: "prod.example.com".to_string(),
-                port: 22,
-                username: "admin".to_string(),
-                auth_type: AuthType::PublicKey,
-                group: Some("Production".to_string()),
-                last_connected: Some("2024-01-15 14:30".to_string()),
-                is_favorite: true,
-            },
-            ConnectionProfile {
-                id: "2".to_string(),
-                name: "Dev Server".to_string(),
-                host: "dev.example.com".to_string(),
-                port: 22,
-                username: "developer".to_string(),
-                auth_type: AuthType::Password,
-                group: Some("Development".to_string()),
-                last_connected: Some("2024-01-14 09:15".to_string()),
-                is_favorite: false,
-            },
-            ConnectionProfile {
-                id: "3".to_string(),
-                name: "Database Server".to_string(),
-                host: "db.example.com".to_string(),
-                port: 2222,
-                username: "dba".to_string(),
-                auth_type: AuthType::PublicKey,
-                group: Some("Production".to_string()),
-                last_connected: None,
-                is_favorite: true,
-            },
-        ];
-
-        let groups = vec![
-            "All Connections".to_string(),
-            "Favorites".to_string(),
-            "Production".to_string(),
-            "Development".to_string(),
-        ];
-
-        Self {
-            connections,
+        let mut screen = Self {
+            connections: Vec::new(),
             search_query: String::new(),
+            quick_connect: String::new(),
             selected_connection_id: None,
             selected_group: Some("All Connections".to_string()),
-            groups,
+            groups: Vec::new(),
+            sort: ConnectionSort::default(),
+            custom_actions: Vec::new(),
+            plugin_actions: Vec::new(),
+            history_suggestions: Vec::new(),
+            discovered: None,
+            discovering: false,
+            layout: ConnectionLayout::default(),
+            table_sort: (TableColumn::Name, true),
+            folders: Vec::new(),
+            collapsed: HashSet::new(),
+            dragging: None,
+            new_folder: None,
+            editing_folder: None,
+            editing_jump: String::new(),
+            session_groups: Vec::new(),
+            editing_session_group: None,
+            ssh_export: None,
+            list_rows: None,
+        };
+        #[cfg(feature = "demo-data")]
+        screen.set_connections(sample_connections());
+        #[cfg(not(feature = "demo-data"))]
+        screen.set_connections(Vec::new());
+        screen
+    }
+
+    /// Replace the displayed connections and rebuild the folder tree from them
+    pub fn set_connections(&mut self, connections: Vec<ConnectionProfile>) {
+        self.connections = connections;
+        self.list_rows = None;
+        self.rebuild_groups();
+    }
+
+    /// The filtered, sorted rows for this frame. Rebuilt only when what they
+    /// depend on changed; put back into `list_rows` after rendering.
+    fn take_list_rows(&mut self) -> (ListKey, Vec<ListRow>) {
+        let key = ListKey {
+            query: self.search_query.to_lowercase(),
+            group: self.selected_group.clone(),
+            layout: self.layout,
+            sort: self.sort,
+            table_sort: self.table_sort,
+        };
+        if let Some((cached, rows)) = self.list_rows.take() {
+            if cached == key {
+                return (cached, rows);
+            }
+        }
+
+        let mut matching: Vec<usize> = (0..self.connections.len())
+            .filter(|&i| {
+                let c = &self.connections[i];
+                let matches_search = key.query.is_empty()
+                    || c.name.to_lowercase().contains(&key.query)
+                    || c.host.to_lowercase().contains(&key.query)
+                    || c.tags.iter().any(|t| t.to_lowercase().contains(&key.query));
+
+                let matches_group = match key.group.as_deref() {
+                    Some("All Connections") => true,
+                    Some("Favorites") => c.is_favorite,
+                    Some(g) => c.group.as_deref().map_or(false, |group| folders::contains(g, group)),
+                    None => true,
+                };
+
+                matches_search && matches_group
+            })
+            .collect();
+        let connections = &self.connections;
+        match key.layout {
+            ConnectionLayout::Cards => matching.sort_by(|&a, &b| key.sort.compare(&connections[a], &connections[b])),
+            ConnectionLayout::Table => {
+                let (column, ascending) = key.table_sort;
+                matching.sort_by(|&a, &b| {
+                    let order = column.compare(&connections[a], &connections[b]);
+                    if ascending { order } else { order.reverse() }
+                });
+            }
+        }
+
+        let rows = matching
+            .into_iter()
+            .map(|index| {
+                let conn = &connections[index];
+                let text = match key.layout {
+                    ConnectionLayout::Cards => {
+                        let last = conn.last_connected_display().map(|last| {
+                            let used = match conn.connection_count {
+                                0 | 1 => String::new(),
+                                n => format!(" \u{00B7} {} connects", n),
+                            };
+                            format!("Last: {}{}", last, used)
+                        });
+                        vec![conn.address_display(), last.unwrap_or_default()]
+                    }
+                    ConnectionLayout::Table => TableColumn::ALL.iter().map(|column| column.cell(conn)).collect(),
+                };
+                ListRow { index, text }
+            })
+            .collect();
+        (key, rows)
+    }
+
+    /// Replace the saved folders (including empty ones) and their defaults
+    pub fn set_folders(&mut self, folders: Vec<Folder>) {
+        self.folders = folders;
+        self.rebuild_groups();
+    }
+
+    fn rebuild_groups(&mut self) {
+        let paths = self.connections.iter()
+            .filter_map(|c| c.group.as_deref())
+            .chain(self.folders.iter().map(|f| f.path.as_str()));
+        let mut groups = folders::with_ancestors(paths);
+        groups.insert(0, "Favorites".to_string());
+        groups.insert(0, "All Connections".to_string());
+
+        if !self.selected_group.as_ref().map_or(false, |g| groups.contains(g)) {
+            self.selected_group = Some("All Connections".to_string());
+        }
+        self.groups = groups;
+    }
+
+    /// Folders below `parent` (top level for `None`), indented by depth
+    fn render_folder_tree(
+        &mut self,
+        ui: &mut egui::Ui,
+        parent: Option<&str>,
+        depth: usize,
+        action: &mut Option<ConnectionManagerAction>,
+    ) {
+        let children: Vec<String> = self.groups.iter()
+            .skip(2)
+            .filter(|g| folders::parent(g) == parent)
+            .cloned()
+            .collect();
+
+        for path in children {
+            let has_children = self.groups.iter().any(|g| folders::parent(g) == Some(path.as_str()));
+            let collapsed = self.collapsed.contains(&path);
+            let arrow = match (has_children, collapsed) {
+                (false, _) => "  ",
+                (true, true) => "\u{25B8}",
+                (true, false) => "\u{25BE}",
+            };
+            let selected = self.selected_group.as_deref() == Some(path.as_str());
+            let label = format!("{} \u{1F4C2} {}", arrow, folders::leaf_name(&path));
+            let response = sidebar_button(ui, &label, selected, depth);
+
+            // Drop a dragged profile onto this folder
+            if let Some(id) = &self.dragging {
+                if ui.rect_contains_pointer(response.rect) {
+                    ui.painter().rect_stroke(response.rect, 4.0, egui::Stroke::new(1.0, colors::PRIMARY));
+                    if ui.input(|i| i.pointer.any_released()) {
+                        *action = Some(ConnectionManagerAction::MoveToFolder(id.clone(), Some(path.clone())));
+                    }
+                }
+            }
+
+            if response.clicked() {
+                if selected && has_children {
+                    if collapsed { self.collapsed.remove(&path); } else { self.collapsed.insert(path.clone()); }
+                }
+                self.selected_group = Some(path.clone());
+            }
+            response.context_menu(|ui| {
+                if ui.button("Connect all").clicked() {
+                    *action = Some(ConnectionManagerAction::ConnectAll(path.clone()));
+                    ui.close_menu();
+                }
+                ui.separator();
+                if ui.button("New subfolder...").clicked() {
+                    self.new_folder = Some((Some(path.clone()), String::new()));
+                    ui.close_menu();
+                }
+                if ui.button("Folder defaults...").clicked() {
+                    let folder = self.folders.iter()
+                        .find(|f| f.path == path)
+                        .cloned()
+                        .unwrap_or_else(|| Folder { path: path.clone(), defaults: FolderDefaults::default() });
+                    self.editing_jump = folder.defaults.jump_host.as_ref().map(|j| match &j.username {
+                        Some(user) => format!("{}@{}:{}", user, j.host, j.port),
+                        None => format!("{}:{}", j.host, j.port),
+                    }).unwrap_or_default();
+                    self.editing_folder = Some(folder);
+                    ui.close_menu();
+                }
+                ui.separator();
+                if ui.button("Delete folder").on_hover_text("Connections move to the parent folder").clicked() {
+                    *action = Some(ConnectionManagerAction::DeleteFolder(path.clone()));
+                    ui.close_menu();
+                }
+            });
+
+            if has_children && !collapsed {
+                self.render_folder_tree(ui, Some(&path), depth + 1, action);
+            }
+        }
+    }
+
+    /// Dialogs for creating a folder and editing folder defaults
+    fn render_folder_dialogs(&mut self, ctx: &egui::Context, action: &mut Option<ConnectionManagerAction>) {
+        if let Some((parent, name)) = &mut self.new_folder {
+            let mut open = true;
+            let mut done = false;
+            egui::Window::new("New Folder")
+                .collapsible(false)
+                .resizable(false)
+                .open(&mut open)
+                .show(ctx, |ui| {
+                    if let Some(parent) = parent {
+                        ui.label(RichText::new(format!("Inside {}", parent)).color(colors::TEXT_MUTED));
+                    }
+                    ui.text_edit_singleline(name);
+                    let valid = !name.trim().is_empty() && !name.contains('/');
+                    if ui.add_enabled(valid, egui::Button::new("Create")).clicked() {
+                        let path = match parent {
+                            Some(parent) => format!("{}/{}", parent, name.trim()),
+                            None => name.trim().to_string(),
+                        };
+                        *action = Some(ConnectionManagerAction::SaveFolder(Folder { path, defaults: FolderDefaults::default() }));
+                        done = true;
+                    }
+                });
+            if !open || done {
+                self.new_folder = None;
+            }
+        }
+
+        if let Some(folder) = &mut self.editing_folder {
+            let mut open = true;
+            let mut done = false;
+            egui::Window::new(format!("Defaults for {}", folder.path))
+                .collapsible(false)
+                .resizable(false)
+                .open(&mut open)
+                .show(ctx, |ui| {
+                    ui.label(RichText::new("Profiles in this folder and below use these unless they set their own")
+                        .color(colors::TEXT_MUTED)
+                        .size(11.0));
+                    ui.add_space(spacing::SM);
+                    let defaults = &mut folder.defaults;
+                    egui::Grid::new("folder_defaults").num_columns(2).show(ui, |ui| {
+                        optional_text(ui, "Username", &mut defaults.username);
+                        ui.label("Port");
+                        ui.horizontal(|ui| {
+                            let mut set = defaults.port.is_some();
+                            ui.checkbox(&mut set, "");
+                            let mut value = defaults.port.unwrap_or(22);
+                            ui.add_enabled(set, egui::DragValue::new(&mut value).clamp_range(1..=65535));
+                            defaults.port = set.then_some(value);
+                        });
+                        ui.end_row();
+                        optional_text(ui, "Private key", &mut defaults.key_path);
+                        ui.label("Jump host");
+                        ui.add(egui::TextEdit::singleline(&mut self.editing_jump).hint_text("user@bastion:22"));
+                        defaults.jump_host = parse_quick_connect(&self.editing_jump).map(|(username, host, port)| {
+                            FolderJumpHost { host, port, username }
+                        });
+                        ui.end_row();
+                        optional_text(ui, "Terminal type", &mut defaults.terminal_type);
+                        optional_text(ui, "Encoding", &mut defaults.encoding);
+                        optional_text(ui, "Initial command", &mut defaults.initial_command);
+                        ui.label("Keepalive (s)");
+                        ui.horizontal(|ui| {
+                            let mut set = defaults.keepalive_interval.is_some();
+                            ui.checkbox(&mut set, "");
+                            let mut value = defaults.keepalive_interval.unwrap_or(30);
+                            ui.add_enabled(set, egui::DragValue::new(&mut value).clamp_range(0..=3600));
+                            defaults.keepalive_interval = set.then_some(value);
+                        });
+                        ui.end_row();
+                    });
+                    ui.add_space(spacing::SM);
+                    if primary_button(ui, "Save").clicked() {
+                        *action = Some(ConnectionManagerAction::SaveFolder(folder.clone()));
+                        done = true;
+                    }
+                });
+            if !open || done {
+                self.editing_folder = None;
+            }
+        }
+    }
+
+    pub fn set_session_groups(&mut self, groups: Vec<SessionGroup>) {
+        self.session_groups = groups;
+    }
+
+    /// Session groups below the folder tree; clicking one opens all its terminals
+    fn render_session_groups(&mut self, ui: &mut egui::Ui, action: &mut Option<ConnectionManagerAction>) {
+        ui.label(RichText::new("Session Groups").color(colors::TEXT_SECONDARY).size(12.0));
+        ui.add_space(spacing::SM);
+
+        for group in &self.session_groups {
+            let label = format!("\u{25A6} {} ({})", group.name, group.profile_ids.len());
+            let response = sidebar_button(ui, &label, false, 0)
+                .on_hover_text(format!("{}{}", group.layout, if group.broadcast { ", broadcast on" } else { "" }));
+            if response.clicked() {
+                *action = Some(ConnectionManagerAction::OpenSessionGroup(group.clone()));
+            }
+            response.context_menu(|ui| {
+                if ui.button("Open").clicked() {
+                    *action = Some(ConnectionManagerAction::OpenSessionGroup(group.clone()));
+                    ui.close_menu();
+                }
+                if ui.button("Edit...").clicked() {
+                    self.editing_session_group = Some(group.clone());
+                    ui.close_menu();
+                }
+                ui.separator();
+                if ui.button("Delete group").on_hover_text("The connections themselves are kept").clicked() {
+                    *action = Some(ConnectionManagerAction::DeleteSessionGroup(group.id.clone()));
+                    ui.close_menu();
+                }
+            });
+        }
+
+        if secondary_button(ui, "+ New Session Group").clicked() {
+            let mut group = SessionGroup::new("");
+            // Start from the folder being viewed, the usual "cluster" shape
+            if let Some(path) = self.selected_group.as_deref() {
+                group.profile_ids = self.connections.iter()
+                    .filter(|c| c.group.as_deref().map_or(false, |g| folders::contains(path, g)))
+                    .map(|c| c.id.clone())
+                    .collect();
+            }
+            self.editing_session_group = Some(group);
+        }
+    }
+
+    fn render_session_group_dialog(&mut self, ctx: &egui::Context, action: &mut Option<ConnectionManagerAction>) {
+        let Some(group) = &mut self.editing_session_group else {
+            return;
+        };
+        let mut open = true;
+        let mut done = false;
+        egui::Window::new("Session Group")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                egui::Grid::new("session_group").num_columns(2).show(ui, |ui| {
+                    ui.label("Name");
+                    ui.add(egui::TextEdit::singleline(&mut group.name).hint_text("web-cluster"));
+                    ui.end_row();
+                    ui.label("Layout");
+                    egui::ComboBox::from_id_source("session_group_layout")
+                        .selected_text(group.layout.to_string())
+                        .show_ui(ui, |ui| {
+                            for layout in PaneLayout::ALL {
+                                ui.selectable_value(&mut group.layout, layout, layout.to_string());
+                            }
+                        });
+                    ui.end_row();
+                    ui.label("");
+                    ui.add_enabled(
+                        group.layout != PaneLayout::Tabs,
+                        egui::Checkbox::new(&mut group.broadcast, "Broadcast input to all panes"),
+                    );
+                    ui.end_row();
+                });
+
+                ui.add_space(spacing::SM);
+                ui.label(RichText::new("Connections, in pane order").color(colors::TEXT_MUTED).size(11.0));
+                egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                    for conn in &self.connections {
+                        let mut member = group.profile_ids.contains(&conn.id);
+                        if ui.checkbox(&mut member, format!("{}  ({})", conn.name, conn.address_display())).changed() {
+                            if member {
+                                group.profile_ids.push(conn.id.clone());
+                            } else {
+                                group.profile_ids.retain(|id| id != &conn.id);
+                            }
+                        }
+                    }
+                });
+
+                ui.add_space(spacing::SM);
+                let valid = !group.name.trim().is_empty() && !group.profile_ids.is_empty();
+                if ui.add_enabled(valid, egui::Button::new("Save")).clicked() {
+                    group.name = group.name.trim().to_string();
+                    if group.layout == PaneLayout::Tabs {
+                        group.broadcast = false;
+                    }
+                    *action = Some(ConnectionManagerAction::SaveSessionGroup(group.clone()));
+                    done = true;
+                }
+            });
+        if !open || done {
+            self.editing_session_group = None;
+        }
+    }
+
+    /// Pick the profiles to write as `Host` blocks
+    fn render_ssh_export(&mut self, ctx: &egui::Context, action: &mut Option<ConnectionManagerAction>) {
+        let Some(chosen) = &mut self.ssh_export else {
+            return;
+        };
+        let mut open = true;
+        let mut done = false;
+        egui::Window::new("Export to OpenSSH config")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(RichText::new("Save as e.g. ~/.ssh/config.d/tabssh and add \"Include config.d/*\" \
+                    to ~/.ssh/config, then re-export after changes. Passwords are never written.")
+                    .color(colors::TEXT_MUTED)
+                    .size(11.0));
+                ui.add_space(spacing::SM);
+                egui::ScrollArea::vertical().max_height(280.0).show(ui, |ui| {
+                    for conn in self.connections.iter().filter(|c| c.kubernetes.is_none()) {
+                        let mut ticked = chosen.contains(&conn.id);
+                        if ui.checkbox(&mut ticked, format!("{}  ({})", conn.name, conn.address_display())).changed() {
+                            if ticked {
+                                chosen.insert(conn.id.clone());
+                            } else {
+                                chosen.remove(&conn.id);
+                            }
+                        }
+                    }
+                });
+                ui.add_space(spacing::SM);
+                let label = format!("Export {} host(s)...", chosen.len());
+                if ui.add_enabled(!chosen.is_empty(), egui::Button::new(label)).clicked() {
+                    // Keep the list order rather than the set's
+                    let hosts: Vec<HostConfig> = self.connections.iter()
+                        .filter(|c| chosen.contains(&c.id))
+                        .filter_map(|c| c.to_host_config(&self.folders))
+                        .collect();
+                    *action = Some(ConnectionManagerAction::ExportSshConfig(write_config(&hosts)));
+                    done = true;
+                }
+            });
+        if !open || done {
+            self.ssh_export = None;
         }
     }
 
+    /// Reflect a favorite change that has been saved
+    pub fn set_favorite(&mut self, id: &str, favorite: bool) {
+        if let Some(conn) = self.connections.iter_mut().find(|c| c.id == id) {
+            conn.is_favorite = favorite;
+        }
+        self.list_rows = None;
+    }
+
+    /// Reflect a successful connect that has been recorded
+    pub fn mark_connected(&mut self, id: &str, at: String) {
+        if let Some(conn) = self.connections.iter_mut().find(|c| c.id == id) {
+            conn.last_connected = Some(at);
+            conn.connection_count += 1;
+        }
+        self.list_rows = None;
+    }
+
+    /// Offer history targets used at least twice that no saved profile covers
+    pub fn set_history_suggestions(&mut self, targets: Vec<HistoryTarget>) {
+        let existing: Vec<_> = self.connections.iter()
+            .map(|c| (c.username.clone(), c.host.clone(), c.port))
+            .collect();
+        self.history_suggestions = suggest_profiles(targets, &existing, 2)
+            .into_iter()
+            .map(|t| (t, true))
+            .collect();
+    }
+
+    fn render_history_suggestions(&mut self, ui: &mut egui::Ui, action: &mut Option<ConnectionManagerAction>) {
+        egui::Frame::none()
+            .fill(colors::BG_SECONDARY)
+            .rounding(egui::Rounding::same(6.0))
+            .inner_margin(egui::Margin::same(spacing::MD))
+            .stroke(egui::Stroke::new(1.0, colors::PRIMARY))
+            .show(ui, |ui| {
+                ui.label(RichText::new("Suggested from shell history").color(colors::TEXT_PRIMARY).strong());
+                ui.add_space(spacing::XS);
+                for (target, selected) in &mut self.history_suggestions {
+                    ui.checkbox(selected, RichText::new(format!("{}  ({} times)", target.label(), target.count))
+                        .color(colors::TEXT_SECONDARY));
+                }
+                ui.add_space(spacing::SM);
+                ui.horizontal(|ui| {
+                    let chosen: Vec<HistoryTarget> = self.history_suggestions.iter()
+                        .filter(|(_, selected)| *selected)
+                        .map(|(target, _)| target.clone())
+                        .collect();
+                    let label = format!("Create {} profile(s)", chosen.len());
+                    if ui.add_enabled(!chosen.is_empty(), egui::Button::new(label)).clicked() {
+                        *action = Some(ConnectionManagerAction::CreateFromHistory(chosen));
+                        self.history_suggestions.clear();
+                    }
+                    if secondary_button(ui, "Dismiss").clicked() {
+                        self.history_suggestions.clear();
+                    }
+                });
+            });
+        ui.add_space(spacing::SM);
+    }
+
+    /// Show the result of a LAN browse
+    pub fn set_discovered(&mut self, hosts: Vec<DiscoveredHost>) {
+        self.discovering = false;
+        self.discovered = Some(hosts);
+    }
+
+    fn render_discovery(&mut self, ui: &mut egui::Ui, action: &mut Option<ConnectionManagerAction>) {
+        let Some(hosts) = &self.discovered else {
+            return;
+        };
+        let mut close = false;
+        egui::Frame::none()
+            .fill(colors::BG_SECONDARY)
+            .rounding(egui::Rounding::same(6.0))
+            .inner_margin(egui::Margin::same(spacing::MD))
+            .stroke(egui::Stroke::new(1.0, colors::PRIMARY))
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("SSH hosts on this network").color(colors::TEXT_PRIMARY).strong());
+                    if self.discovering {
+                        ui.spinner();
+                    }
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.small_button("\u{2715}").clicked() {
+                            close = true;
+                        }
+                        if ui.add_enabled(!self.discovering, egui::Button::new("Rescan").small()).clicked() {
+                            *action = Some(ConnectionManagerAction::DiscoverLan);
+                        }
+                    });
+                });
+                ui.add_space(spacing::XS);
+                if hosts.is_empty() && !self.discovering {
+                    ui.label(RichText::new("No hosts announce _ssh._tcp via mDNS here").color(colors::TEXT_MUTED));
+                }
+                for host in hosts {
+                    ui.horizontal(|ui| {
+                        ui.label(RichText::new(&host.instance).color(colors::TEXT_PRIMARY));
+                        ui.label(RichText::new(format!("{}:{}", host.connect_host(), host.port))
+                            .color(colors::TEXT_SECONDARY)
+                            .size(12.0));
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.small_button("Save").clicked() {
+                                *action = Some(ConnectionManagerAction::SaveDiscovered(host.clone()));
+                            }
+                            if ui.small_button("Connect").clicked() {
+                                let target = match host.connect_host() {
+                                    h if h.contains(':') => format!("[{}]:{}", h, host.port),
+                                    h => format!("{}:{}", h, host.port),
+                                };
+                                *action = Some(ConnectionManagerAction::QuickConnect(target));
+                            }
+                        });
+                    });
+                }
+            });
+        if close {
+            self.discovered = None;
+        }
+        ui.add_space(spacing::SM);
+    }
+
+    /// First-run screen offering every way to get a connection in
+    fn render_onboarding(&mut self, ui: &mut egui::Ui, action: &mut Option<ConnectionManagerAction>) {
+        empty_state(
+            ui,
+            "\u{1F4E1}",
+            "No Saved Connections",
+            "Add a server, import the ones you already have, or connect once without saving"
+        );
+
+        ui.vertical_centered(|ui| {
+            ui.horizontal(|ui| {
+                // Center the fixed-width button row
+                ui.add_space(((ui.available_width() - 460.0) / 2.0).max(0.0));
+                if primary_button(ui, "+ New Connection").clicked() {
+                    *action = Some(ConnectionManagerAction::NewConnection);
+                }
+                if secondary_button(ui, "Import ~/.ssh/config").clicked() {
+                    *action = Some(ConnectionManagerAction::ImportSshConfig);
+                }
+            });
+
+            ui.add_space(spacing::LG);
+            ui.label(RichText::new("Quick connect").color(colors::TEXT_SECONDARY).size(12.0));
+            ui.add_space(spacing::XS);
+            self.render_quick_connect(ui, action);
+        });
+    }
+
+    fn render_quick_connect(&mut self, ui: &mut egui::Ui, action: &mut Option<ConnectionManagerAction>) {
+        let valid = parse_quick_connect(&self.quick_connect).is_some();
+        ui.horizontal(|ui| {
+            ui.add_space(((ui.available_width() - 340.0) / 2.0).max(0.0));
+            let response = ui.add(egui::TextEdit::singleline(&mut self.quick_connect)
+                .hint_text(RichText::new("user@host:port").color(colors::TEXT_MUTED))
+                .text_color(colors::TEXT_PRIMARY)
+                .desired_width(260.0)
+                .margin(egui::Margin::symmetric(8.0, 6.0)));
+            let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            let clicked = ui.add_enabled(valid, egui::Button::new("Connect")).clicked();
+            if valid && (submitted || clicked) {
+                *action = Some(ConnectionManagerAction::QuickConnect(self.quick_connect.trim().to_string()));
+            }
+        });
+    }
+
     /// Render the connection manager
     pub fn render(&mut self, ui: &mut egui::Ui) -> Option<ConnectionManagerAction> {
         let mut action = None;
@@ -130,40 +1477,28 @@ impl ConnectionManagerScreen {
                 ui.set_min_width(180.0);
                 ui.set_max_width(180.0);
 
-                ui.label(RichText::new("Groups").color(colors::TEXT_SECONDARY).size(12.0));
+                ui.label(RichText::new("Folders").color(colors::TEXT_SECONDARY).size(12.0));
                 ui.add_space(spacing::SM);
 
-                for group in &self.groups.clone() {
-                    let selected = self.selected_group.as_ref() == Some(group);
-                    let icon = match group.as_str() {
-                        "All Connections" => "\u{1F4C1}",
-                        "Favorites" => "\u{2B50}",
-                        _ => "\u{1F4C2}",
-                    };
-
-                    let bg = if selected { colors::BG_TERTIARY } else { egui::Color32::TRANSPARENT };
-                    let text_color = if selected { colors::TEXT_PRIMARY } else { colors::TEXT_SECONDARY };
-
-                    let button = egui::Button::new(
-                        RichText::new(format!("{} {}", icon, group))
-                            .color(text_color)
-                            .size(13.0)
-                    )
-                        .fill(bg)
-                        .stroke(egui::Stroke::NONE)
-                        .rounding(egui::Rounding::same(4.0))
-                        .min_size(Vec2::new(ui.available_width(), 32.0));
-
-                    if ui.add(button).clicked() {
-                        self.selected_group = Some(group.clone());
+                for (group, icon) in [("All Connections", "\u{1F4C1}"), ("Favorites", "\u{2B50}")] {
+                    let selected = self.selected_group.as_deref() == Some(group);
+                    if sidebar_button(ui, &format!("{} {}", icon, group), selected, 0).clicked() {
+                        self.selected_group = Some(group.to_string());
                     }
                 }
 
+                egui::ScrollArea::vertical().id_source("folder_tree").show(ui, |ui| {
+                    self.render_folder_tree(ui, None, 0, &mut action);
+                });
+
                 ui.add_space(spacing::LG);
 
-                if secondary_button(ui, "+ New Group").clicked() {
-                    // TODO: Add new group
+                if secondary_button(ui, "+ New Folder").clicked() {
+                    self.new_folder = Some((None, String::new()));
                 }
+
+                ui.add_space(spacing::LG);
+                self.render_session_groups(ui, &mut action);
             });
 
             ui.separator();
@@ -182,6 +1517,24 @@ impl ConnectionManagerScreen {
                         .margin(egui::Margin::symmetric(8.0, 6.0));
                     ui.add(search_input);
 
+                    ui.add_space(spacing::SM);
+                    // The table sorts by its column headers instead
+                    if self.layout == ConnectionLayout::Cards {
+                        egui::ComboBox::from_id_source("connection_sort")
+                            .selected_text(format!("Sort: {}", self.sort))
+                            .show_ui(ui, |ui| {
+                                for sort in ConnectionSort::ALL {
+                                    ui.selectable_value(&mut self.sort, sort, sort.to_string());
+                                }
+                            });
+                    }
+
+                    ui.add_space(spacing::SM);
+                    ui.selectable_value(&mut self.layout, ConnectionLayout::Cards, "\u{25A6}")
+                        .on_hover_text("Card layout");
+                    ui.selectable_value(&mut self.layout, ConnectionLayout::Table, "\u{2630}")
+                        .on_hover_text("Table layout");
+
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                         if primary_button(ui, "+ New Connection").clicked() {
                             action = Some(ConnectionManagerAction::NewConnection);
@@ -192,6 +1545,34 @@ impl ConnectionManagerScreen {
                         if icon_button(ui, "\u{2699}", "Import connections").clicked() {
                             // TODO: Import
                         }
+
+                        ui.menu_button("\u{1F517}", |ui| {
+                            if ui.button("Suggest from shell history...").clicked() {
+                                action = Some(ConnectionManagerAction::ImportShellHistory);
+                                ui.close_menu();
+                            }
+                            if ui.button("Export to OpenSSH config...").clicked() {
+                                // Start from the folder being viewed, or everything
+                                let folder = self.selected_group.as_deref()
+                                    .filter(|g| self.groups.iter().skip(2).any(|known| known == g));
+                                self.ssh_export = Some(self.connections.iter()
+                                    .filter(|c| c.kubernetes.is_none())
+                                    .filter(|c| folder.map_or(true, |path| {
+                                        c.group.as_deref().map_or(false, |g| folders::contains(path, g))
+                                    }))
+                                    .map(|c| c.id.clone())
+                                    .collect());
+                                ui.close_menu();
+                            }
+                            if ui.button("Discover on local network...").clicked() {
+                                action = Some(ConnectionManagerAction::DiscoverLan);
+                                ui.close_menu();
+                            }
+                            if ui.button("Browse cloud instances...").clicked() {
+                                action = Some(ConnectionManagerAction::OpenCloudBrowser);
+                                ui.close_menu();
+                            }
+                        });
                     });
                 });
 
@@ -199,50 +1580,62 @@ impl ConnectionManagerScreen {
                 ui.separator();
                 ui.add_space(spacing::SM);
 
-                // Connection list
-                let filtered: Vec<_> = self.connections.iter()
-                    .filter(|c| {
-                        let matches_search = self.search_query.is_empty()
-                            || c.name.to_lowercase().contains(&self.search_query.to_lowercase())
-                            || c.host.to_lowercase().contains(&self.search_query.to_lowercase());
-
-                        let matches_group = match self.selected_group.as_deref() {
-                            Some("All Connections") => true,
-                            Some("Favorites") => c.is_favorite,
-                            Some(g) => c.group.as_deref() == Some(g),
-                            None => true,
-                        };
-
-                        matches_search && matches_group
-                    })
-                    .collect();
+                if !self.history_suggestions.is_empty() {
+                    self.render_history_suggestions(ui, &mut action);
+                }
+                self.render_discovery(ui, &mut action);
 
-                if filtered.is_empty() {
+                // Connection list
+                let (key, rows) = self.take_list_rows();
+                if self.connections.is_empty() {
+                    self.render_onboarding(ui, &mut action);
+                } else if rows.is_empty() {
                     empty_state(
                         ui,
-                        "\u{1F4E1}",
-                        "No Connections Found",
-                        "Create a new connection to get started"
+                        "\u{1F50D}",
+                        "No Matching Connections",
+                        "Nothing in this group matches your search"
                     );
+                    ui.vertical_centered(|ui| {
+                        if secondary_button(ui, "Clear search").clicked() {
+                            self.search_query.clear();
+                            self.selected_group = Some("All Connections".to_string());
+                        }
+                    });
+                } else if self.layout == ConnectionLayout::Table {
+                    render_table(ui, &self.connections, &rows, &self.folders, &mut self.table_sort, &mut self.selected_connection_id, &mut action);
                 } else {
-                    egui::ScrollArea::vertical().show(ui, |ui| {
-                        for conn in filtered {
+                    // Cards have a fixed height so only the visible ones are laid out
+                    egui::ScrollArea::vertical().show_rows(ui, CARD_ROW_HEIGHT, rows.len(), |ui, visible| {
+                        for row in &rows[visible] {
+                            let conn = &self.connections[row.index];
                             let is_selected = self.selected_connection_id.as_ref() == Some(&conn.id);
 
-                            egui::Frame::none()
+                            let card = egui::Frame::none()
                                 .fill(if is_selected { colors::BG_TERTIARY } else { colors::BG_SECONDARY })
                                 .rounding(egui::Rounding::same(6.0))
                                 .inner_margin(egui::Margin::same(spacing::MD))
+                                .outer_margin(egui::Margin { bottom: spacing::SM, ..Default::default() })
                                 .stroke(egui::Stroke::new(
                                     1.0,
                                     if is_selected { colors::PRIMARY } else { colors::BORDER }
                                 ))
                                 .show(ui, |ui| {
+                                    ui.set_height(CARD_INNER_HEIGHT);
                                     ui.horizontal(|ui| {
+                                        let handle = ui.add(egui::Label::new(
+                                            RichText::new("\u{2807}").color(colors::TEXT_MUTED)
+                                        ).sense(egui::Sense::drag()))
+                                            .on_hover_text("Drag onto a folder");
+                                        if handle.drag_started() {
+                                            self.dragging = Some(conn.id.clone());
+                                        }
+
                                         // Favorite star
                                         let star = if conn.is_favorite { "\u{2B50}" } else { "\u{2606}" };
-                                        if ui.add(egui::Button::new(star).frame(false)).clicked() {
-                                            // TODO: Toggle favorite
+                                        let hint = if conn.is_favorite { "Remove from favorites" } else { "Add to favorites" };
+                                        if ui.add(egui::Button::new(star).frame(false)).on_hover_text(hint).clicked() {
+                                            action = Some(ConnectionManagerAction::SetFavorite(conn.id.clone(), !conn.is_favorite));
                                         }
 
                                         ui.vertical(|ui| {
@@ -259,16 +1652,17 @@ impl ConnectionManagerScreen {
                                                     AuthType::Password => "\u{1F511}",
                                                     AuthType::PublicKey => "\u{1F5DD}",
                                                     AuthType::KeyboardInteractive => "\u{2328}",
+                                                    AuthType::Agent => "\u{1F5DD}",
                                                 };
                                                 ui.label(RichText::new(auth_badge).size(12.0));
                                             });
 
-                                            ui.label(RichText::new(format!("{}@{}:{}", conn.username, conn.host, conn.port))
+                                            ui.label(RichText::new(&row.text[0])
                                                 .color(colors::TEXT_SECONDARY)
                                                 .size(12.0));
 
-                                            if let Some(last) = &conn.last_connected {
-                                                ui.label(RichText::new(format!("Last: {}", last))
+                                            if !row.text[1].is_empty() {
+                                                ui.label(RichText::new(&row.text[1])
                                                     .color(colors::TEXT_MUTED)
                                                     .size(11.0));
                                             }
@@ -276,7 +1670,7 @@ impl ConnectionManagerScreen {
 
                                         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                                             if primary_button(ui, "Connect").clicked() {
-                                                action = Some(ConnectionManagerAction::Connect(conn.clone()));
+                                                action = Some(ConnectionManagerAction::Connect(conn.with_folder_defaults(&self.folders)));
                                             }
 
                                             ui.add_space(spacing::XS);
@@ -288,35 +1682,281 @@ impl ConnectionManagerScreen {
                                             if icon_button(ui, "\u{1F5D1}", "Delete").clicked() {
                                                 action = Some(ConnectionManagerAction::Delete(conn.id.clone()));
                                             }
+
+                                            let ctx = ActionContext {
+                                                host: conn.host.clone(),
+                                                port: conn.port,
+                                                user: conn.username.clone(),
+                                                ..Default::default()
+                                            };
+                                            let available: Vec<_> = self.custom_actions.iter()
+                                                .filter(|a| a.in_connection_menu() && a.is_available(&ctx))
+                                                .collect();
+                                            ui.menu_button("\u{22EF}", |ui| {
+                                                if ui.button("Copy share link").clicked() {
+                                                    action = Some(ConnectionManagerAction::CopyShareLink(conn.to_shared()));
+                                                    ui.close_menu();
+                                                }
+                                                ui.menu_button("Move to", |ui| {
+                                                    if ui.button("(No folder)").clicked() {
+                                                        action = Some(ConnectionManagerAction::MoveToFolder(conn.id.clone(), None));
+                                                        ui.close_menu();
+                                                    }
+                                                    for group in self.groups.iter().skip(2) {
+                                                        if ui.button(group).clicked() {
+                                                            action = Some(ConnectionManagerAction::MoveToFolder(conn.id.clone(), Some(group.clone())));
+                                                            ui.close_menu();
+                                                        }
+                                                    }
+                                                });
+                                                if !available.is_empty() || !self.plugin_actions.is_empty() {
+                                                    ui.separator();
+                                                }
+                                                for custom in available {
+                                                    if ui.button(&custom.name).clicked() {
+                                                        action = Some(ConnectionManagerAction::RunCustomAction(custom.clone(), ctx.clone()));
+                                                        ui.close_menu();
+                                                    }
+                                                }
+                                                for (plugin_id, plugin_action) in &self.plugin_actions {
+                                                    if ui.button(&plugin_action.label).clicked() {
+                                                        action = Some(ConnectionManagerAction::RunPluginAction(
+                                                            plugin_id.clone(),
+                                                            plugin_action.id.clone(),
+                                                            conn.id.clone(),
+                                                        ));
+                                                        ui.close_menu();
+                                                    }
+                                                }
+                                            });
                                         });
                                     });
-                                });
-
-                            ui.add_space(spacing::SM);
+                                }).response;
 
                             // Select on click
-                            let response = ui.interact(
-                                ui.min_rect(),
-                                ui.id().with(&conn.id),
-                                egui::Sense::click()
-                            );
+                            let response = ui.interact(card.rect, ui.id().with(&conn.id), egui::Sense::click());
                             if response.clicked() {
                                 self.selected_connection_id = Some(conn.id.clone());
                             }
                         }
                     });
                 }
+                self.list_rows = Some((key, rows));
             });
         });
 
+        self.render_folder_dialogs(ui.ctx(), &mut action);
+        self.render_session_group_dialog(ui.ctx(), &mut action);
+        self.render_ssh_export(ui.ctx(), &mut action);
+
+        if let Some(id) = &self.dragging {
+            if let Some(conn) = self.connections.iter().find(|c| &c.id == id) {
+                egui::show_tooltip_at_pointer(ui.ctx(), egui::Id::new("dragged_connection"), |ui| {
+                    ui.label(&conn.name);
+                });
+            }
+            if ui.input(|i| i.pointer.any_released()) {
+                self.dragging = None;
+            }
+        }
+
         action
     }
 }
 
+/// Sidebar entry, indented by folder depth
+fn sidebar_button(ui: &mut egui::Ui, label: &str, selected: bool, depth: usize) -> egui::Response {
+    let bg = if selected { colors::BG_TERTIARY } else { egui::Color32::TRANSPARENT };
+    let text_color = if selected { colors::TEXT_PRIMARY } else { colors::TEXT_SECONDARY };
+    ui.horizontal(|ui| {
+        ui.add_space(depth as f32 * spacing::MD);
+        let button = egui::Button::new(RichText::new(label).color(text_color).size(13.0))
+            .fill(bg)
+            .stroke(egui::Stroke::NONE)
+            .rounding(egui::Rounding::same(4.0))
+            .min_size(Vec2::new(ui.available_width(), 28.0));
+        ui.add(button)
+    }).inner
+}
+
+/// Grid row editing an optional text default; empty means inherit
+fn optional_text(ui: &mut egui::Ui, label: &str, value: &mut Option<String>) {
+    ui.label(label);
+    let mut text = value.clone().unwrap_or_default();
+    ui.add(egui::TextEdit::singleline(&mut text).hint_text("inherit"));
+    *value = Some(text).filter(|t| !t.trim().is_empty());
+    ui.end_row();
+}
+
+/// Dense table of connections; double-click connects, right-click for more
+fn render_table(
+    ui: &mut egui::Ui,
+    connections: &[ConnectionProfile],
+    rows: &[ListRow],
+    folders: &[Folder],
+    sort: &mut (TableColumn, bool),
+    selected: &mut Option<String>,
+    action: &mut Option<ConnectionManagerAction>,
+) {
+    let mut table = TableBuilder::new(ui)
+        .striped(true)
+        .resizable(true)
+        .cell_layout(egui::Layout::left_to_right(egui::Align::Center));
+    for column in TableColumn::ALL {
+        table = table.column(Column::initial(column.initial_width()).at_least(50.0).clip(true));
+    }
+
+    table
+        .header(24.0, |mut header| {
+            for column in TableColumn::ALL {
+                header.col(|ui| {
+                    let arrow = match *sort {
+                        (c, true) if c == column => " \u{25B2}",
+                        (c, false) if c == column => " \u{25BC}",
+                        _ => "",
+                    };
+                    let title = RichText::new(format!("{}{}", column.title(), arrow))
+                        .color(colors::TEXT_SECONDARY)
+                        .strong();
+                    if ui.add(egui::Button::new(title).frame(false)).clicked() {
+                        *sort = if sort.0 == column { (column, !sort.1) } else { (column, true) };
+                    }
+                });
+            }
+        })
+        .body(|body| {
+            body.rows(22.0, rows.len(), |mut row| {
+                let row_index = row.index();
+                let conn = &connections[rows[row_index].index];
+                let cells = &rows[row_index].text;
+                let is_selected = selected.as_deref() == Some(conn.id.as_str());
+                for (column, cell) in TableColumn::ALL.into_iter().zip(cells) {
+                    row.col(|ui| {
+                        let text = RichText::new(cell).color(if column == TableColumn::Name {
+                            colors::TEXT_PRIMARY
+                        } else {
+                            colors::TEXT_SECONDARY
+                        });
+                        let response = ui.add(egui::SelectableLabel::new(is_selected, text));
+                        if response.clicked() {
+                            *selected = Some(conn.id.clone());
+                        }
+                        if response.double_clicked() {
+                            *action = Some(ConnectionManagerAction::Connect(conn.with_folder_defaults(folders)));
+                        }
+                        response.context_menu(|ui| {
+                            if ui.button("Connect").clicked() {
+                                *action = Some(ConnectionManagerAction::Connect(conn.with_folder_defaults(folders)));
+                                ui.close_menu();
+                            }
+                            if ui.button("Edit").clicked() {
+                                *action = Some(ConnectionManagerAction::Edit(conn.id.clone()));
+                                ui.close_menu();
+                            }
+                            let favorite = if conn.is_favorite { "Remove from favorites" } else { "Add to favorites" };
+                            if ui.button(favorite).clicked() {
+                                *action = Some(ConnectionManagerAction::SetFavorite(conn.id.clone(), !conn.is_favorite));
+                                ui.close_menu();
+                            }
+                            if ui.button("Copy share link").clicked() {
+                                *action = Some(ConnectionManagerAction::CopyShareLink(conn.to_shared()));
+                                ui.close_menu();
+                            }
+                            ui.separator();
+                            if ui.button("Delete").clicked() {
+                                *action = Some(ConnectionManagerAction::Delete(conn.id.clone()));
+                                ui.close_menu();
+                            }
+                        });
+                    });
+                }
+            });
+        });
+}
+
 /// Actions that can be triggered from the connection manager
 pub enum ConnectionManagerAction {
     Connect(ConnectionProfile),
     Edit(String),
     Delete(String),
+    /// Connection id and the new favorite state; persist, then call `set_favorite`
+    SetFavorite(String, bool),
     NewConnection,
+    RunCustomAction(CustomAction, ActionContext),
+    /// Plugin id, action id, connection id
+    RunPluginAction(String, String, String),
+    /// Credential-less copy of the connection; pass to `AppState::copy_share_link`
+    CopyShareLink(SharedProfile),
+    /// Connect to every profile in the folder and below
+    ConnectAll(String),
+    /// Profile id and its new folder; persist, then reload
+    MoveToFolder(String, Option<String>),
+    /// Create a folder or save its defaults
+    SaveFolder(Folder),
+    /// Delete a folder; its profiles move to the parent
+    DeleteFolder(String),
+    ImportSshConfig,
+    /// Ask for a file and write this OpenSSH config text to it
+    ExportSshConfig(String),
+    /// Scan shell history, then call `set_history_suggestions`
+    ImportShellHistory,
+    /// Save a profile for each chosen history target
+    CreateFromHistory(Vec<HistoryTarget>),
+    /// Browse mDNS for SSH hosts; set `discovering`, then call `set_discovered`
+    DiscoverLan,
+    /// Save a profile for a host found on the LAN
+    SaveDiscovered(DiscoveredHost),
+    /// Show the AWS/GCP/Azure instance browser
+    OpenCloudBrowser,
+    /// Connect without saving, to a `user@host:port` target
+    QuickConnect(String),
+    /// Connect to every profile of the group and arrange the terminals
+    /// with `terminal_view::render_panes`, arming broadcast if it is set
+    OpenSessionGroup(SessionGroup),
+    /// Create or update a session group; persist, then call `set_session_groups`
+    SaveSessionGroup(SessionGroup),
+    DeleteSessionGroup(String),
+}
+
+/// Fake hosts for working on the UI without a database
+#[cfg(feature = "demo-data")]
+fn sample_connections() -> Vec<ConnectionProfile> {
+    vec![
+        ConnectionProfile {
+            id: "1".to_string(),
+            name: "Production Server".to_string(),
+            host: "prod.example.com".to_string(),
+            port: 22,
+            username: "admin".to_string(),
+            auth_type: AuthType::PublicKey,
+            group: Some("Production".to_string()),
+            last_connected: Some("2024-01-15 14:30".to_string()),
+            is_favorite: true,
+            ..Default::default()
+        },
+        ConnectionProfile {
+            id: "2".to_string(),
+            name: "Dev Server".to_string(),
+            host: "dev.example.com".to_string(),
+            port: 22,
+            username: "developer".to_string(),
+            auth_type: AuthType::Password,
+            group: Some("Development".to_string()),
+            last_connected: Some("2024-01-14 09:15".to_string()),
+            is_favorite: false,
+            ..Default::default()
+        },
+        ConnectionProfile {
+            id: "3".to_string(),
+            name: "Database Server".to_string(),
+            host: "db.example.com".to_string(),
+            port: 2222,
+            username: "dba".to_string(),
+            auth_type: AuthType::PublicKey,
+            group: Some("Production".to_string()),
+            last_connected: None,
+            is_favorite: true,
+            ..Default::default()
+        },
+    ]
 }
@@ -0,0 +1,109 @@
+//! Diagnostics Screen - memory held by each open session
+
+use std::time::{Duration, Instant};
+use eframe::egui::{self, RichText};
+use crate::sftp::format_file_size;
+use crate::ui::components::{colors, spacing, secondary_button, empty_state};
+use crate::utils::memory::{self, SessionMemory};
+use super::usage_stats::figure;
+
+/// The figures change with every read, so refreshing faster only flickers
+const REFRESH: Duration = Duration::from_secs(1);
+
+pub struct DiagnosticsScreen {
+    sessions: Vec<(String, SessionMemory)>,
+    refreshed: Option<Instant>,
+}
+
+impl DiagnosticsScreen {
+    pub fn new() -> Self {
+        Self {
+            sessions: Vec::new(),
+            refreshed: None,
+        }
+    }
+
+    pub fn render(&mut self, ui: &mut egui::Ui) -> Option<DiagnosticsAction> {
+        let mut action = None;
+        if self.refreshed.map_or(true, |at| at.elapsed() >= REFRESH) {
+            self.sessions = memory::snapshot();
+            self.sessions.sort_by(|a, b| b.1.total_bytes().cmp(&a.1.total_bytes()));
+            self.refreshed = Some(Instant::now());
+        }
+        ui.ctx().request_repaint_after(REFRESH);
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("Diagnostics")
+                .color(colors::TEXT_PRIMARY)
+                .strong()
+                .size(16.0));
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if secondary_button(ui, "Close").clicked() {
+                    action = Some(DiagnosticsAction::Close);
+                }
+            });
+        });
+        ui.label(RichText::new("Memory held by terminal scrollback and running transfers, per session.")
+            .color(colors::TEXT_MUTED)
+            .size(11.0));
+        ui.separator();
+
+        if self.sessions.is_empty() {
+            empty_state(ui, "\u{1F5A5}", "No Sessions", "Open a terminal or start a transfer to see its memory here");
+            return action;
+        }
+
+        let totals = memory::totals(&self.sessions);
+        ui.horizontal_wrapped(|ui| {
+            figure(ui, "Total", &format_file_size(totals.total_bytes() as u64));
+            figure(ui, "Scrollback", &format_file_size(totals.scrollback_bytes as u64));
+            figure(ui, "Lines kept", &totals.scrollback_lines.to_string());
+            figure(ui, "Transfer buffers", &format_file_size(totals.transfer_bytes as u64));
+        });
+
+        ui.add_space(spacing::LG);
+        egui::Grid::new("diagnostics_sessions")
+            .num_columns(5)
+            .striped(true)
+            .spacing([spacing::MD, spacing::XS])
+            .show(ui, |ui| {
+                for caption in ["Session", "Scrollback", "Of cap", "Lines", "Transfers"] {
+                    ui.label(RichText::new(caption).strong().color(colors::TEXT_SECONDARY));
+                }
+                ui.end_row();
+
+                for (_, session) in &self.sessions {
+                    ui.label(&session.label);
+                    ui.label(format_file_size(session.scrollback_bytes as u64));
+                    if session.scrollback_cap > 0 && session.scrollback_cap < usize::MAX {
+                        let used = session.scrollback_bytes as f32 / session.scrollback_cap as f32;
+                        ui.add(egui::ProgressBar::new(used.min(1.0))
+                            .desired_width(120.0)
+                            .text(format!("of {}", format_file_size(session.scrollback_cap as u64))));
+                    } else {
+                        ui.label(RichText::new("\u{2014}").color(colors::TEXT_MUTED));
+                    }
+                    ui.label(session.scrollback_lines.to_string());
+                    let transfers = match session.transfers {
+                        0 => "\u{2014}".to_string(),
+                        n => format!("{} ({})", n, format_file_size(session.transfer_bytes as u64)),
+                    };
+                    ui.label(RichText::new(transfers).color(colors::TEXT_MUTED));
+                    ui.end_row();
+                }
+            });
+
+        action
+    }
+}
+
+impl Default for DiagnosticsScreen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Actions from the diagnostics screen
+pub enum DiagnosticsAction {
+    Close,
+}
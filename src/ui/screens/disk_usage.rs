@@ -0,0 +1,168 @@
+//! Disk Usage Screen - sorted size breakdown of a remote directory
+
+#![allow(dead_code)]
+
+use eframe::egui::{self, RichText};
+use crate::sftp::{format_file_size, DiskUsageReport};
+use crate::ui::components::{colors, spacing, secondary_button, empty_state};
+
+/// Disk usage view state
+pub struct DiskUsageScreen {
+    connection_name: String,
+    report: Option<DiskUsageReport>,
+    loading_path: Option<String>,
+    error: Option<String>,
+}
+
+impl DiskUsageScreen {
+    pub fn new(connection_name: String) -> Self {
+        Self {
+            connection_name,
+            report: None,
+            loading_path: None,
+            error: None,
+        }
+    }
+
+    /// Mark a scan of `path` as in progress
+    pub fn set_loading(&mut self, path: String) {
+        self.loading_path = Some(path);
+        self.error = None;
+    }
+
+    pub fn set_report(&mut self, report: DiskUsageReport) {
+        self.report = Some(report);
+        self.loading_path = None;
+    }
+
+    pub fn set_error(&mut self, error: String) {
+        self.error = Some(error);
+        self.loading_path = None;
+    }
+
+    /// Render the disk usage view
+    pub fn render(&mut self, ui: &mut egui::Ui) -> Option<DiskUsageAction> {
+        let mut action = None;
+        let current_path = self.report.as_ref().map(|r| r.path.clone());
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(format!("Disk usage - {}", self.connection_name))
+                .color(colors::TEXT_PRIMARY)
+                .strong()
+                .size(16.0));
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if secondary_button(ui, "Close").clicked() {
+                    action = Some(DiskUsageAction::Close);
+                }
+                if let Some(path) = &current_path {
+                    if secondary_button(ui, "Rescan").clicked() {
+                        action = Some(DiskUsageAction::Scan(path.clone()));
+                    }
+                    if path != "/" && secondary_button(ui, "Up").clicked() {
+                        action = Some(DiskUsageAction::Scan(parent_path(path)));
+                    }
+                }
+            });
+        });
+
+        if let Some(path) = &current_path {
+            ui.horizontal_wrapped(|ui| {
+                for (label, target) in breadcrumbs(path) {
+                    if ui.link(label).clicked() {
+                        action = Some(DiskUsageAction::Scan(target));
+                    }
+                    ui.label(RichText::new("/").color(colors::TEXT_MUTED));
+                }
+            });
+        }
+
+        ui.add_space(spacing::SM);
+        ui.separator();
+
+        if let Some(error) = &self.error {
+            ui.label(RichText::new(format!("Scan failed: {}", error)).color(colors::ERROR));
+        }
+
+        if let Some(path) = &self.loading_path {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(RichText::new(format!("Scanning {}...", path)).color(colors::TEXT_MUTED));
+            });
+            return action;
+        }
+
+        let Some(report) = &self.report else {
+            empty_state(ui, "\u{1F4CA}", "No Scan Yet", "Choose \"Disk usage\" on a remote folder");
+            return action;
+        };
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(format!("Total: {}", format_file_size(report.total_bytes)))
+                .color(colors::TEXT_PRIMARY));
+            if report.via_sftp {
+                ui.label(RichText::new("(measured over SFTP - du not available)")
+                    .color(colors::TEXT_MUTED)
+                    .size(11.0));
+            }
+        });
+        ui.add_space(spacing::SM);
+
+        let largest = report.entries.first().map(|e| e.bytes).unwrap_or(0).max(1);
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for entry in &report.entries {
+                ui.horizontal(|ui| {
+                    let fraction = entry.bytes as f32 / largest as f32;
+                    ui.add(egui::ProgressBar::new(fraction).desired_width(160.0));
+
+                    ui.label(RichText::new(format_file_size(entry.bytes))
+                        .color(colors::TEXT_SECONDARY)
+                        .monospace());
+
+                    if entry.is_directory {
+                        if ui.link(format!("\u{1F4C1} {}", entry.name)).clicked() {
+                            action = Some(DiskUsageAction::Scan(entry.path.clone()));
+                        }
+                    } else {
+                        ui.label(format!("\u{1F4C4} {}", entry.name));
+                    }
+
+                    if report.total_bytes > 0 {
+                        let percent = entry.bytes as f64 * 100.0 / report.total_bytes as f64;
+                        ui.label(RichText::new(format!("{:.1}%", percent))
+                            .color(colors::TEXT_MUTED)
+                            .size(11.0));
+                    }
+                });
+            }
+        });
+
+        action
+    }
+}
+
+fn parent_path(path: &str) -> String {
+    match path.trim_end_matches('/').rsplit_once('/') {
+        Some(("", _)) | None => "/".to_string(),
+        Some((parent, _)) => parent.to_string(),
+    }
+}
+
+/// Clickable path segments, each paired with the path it navigates to
+fn breadcrumbs(path: &str) -> Vec<(String, String)> {
+    let mut crumbs = vec![("/".to_string(), "/".to_string())];
+    let mut acc = String::new();
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        acc.push('/');
+        acc.push_str(segment);
+        crumbs.push((segment.to_string(), acc.clone()));
+    }
+    crumbs
+}
+
+/// Actions from the disk usage screen
+pub enum DiskUsageAction {
+    Scan(String),
+    Close,
+}
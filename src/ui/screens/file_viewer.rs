@@ -0,0 +1,271 @@
+//! Remote File Viewer - read-only view of a remote file with follow mode
+
+use egui::text::{LayoutJob, TextFormat};
+use egui::{FontId, RichText};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use crate::sftp::TailEvent;
+use crate::ui::components::{colors, spacing, secondary_button};
+use crate::utils::helpers::format_file_size;
+
+/// Oldest lines are dropped past this, so a busy log can be left open
+const MAX_LINES: usize = 200_000;
+const FONT_SIZE: f32 = 12.5;
+
+pub struct FileViewerScreen {
+    connection_name: String,
+    path: String,
+    stream: Option<mpsc::UnboundedReceiver<TailEvent>>,
+    follow: Arc<AtomicBool>,
+    lines: VecDeque<String>,
+    /// Text after the last newline, shown as the final line
+    partial: String,
+    /// Bytes not shown: skipped at the start or dropped past `MAX_LINES`
+    skipped_bytes: u64,
+    dropped_lines: usize,
+    error: Option<String>,
+
+    search: String,
+    /// Lines containing `search`, recomputed when either changes
+    matches: Vec<usize>,
+    matches_stale: bool,
+    current_match: usize,
+    scroll_to: Option<usize>,
+}
+
+impl FileViewerScreen {
+    pub fn new(connection_name: String, path: String) -> Self {
+        Self {
+            connection_name,
+            path,
+            stream: None,
+            follow: Arc::new(AtomicBool::new(false)),
+            lines: VecDeque::new(),
+            partial: String::new(),
+            skipped_bytes: 0,
+            dropped_lines: 0,
+            error: None,
+            search: String::new(),
+            matches: Vec::new(),
+            matches_stale: false,
+            current_match: 0,
+            scroll_to: None,
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Flag handed to `sftp::tail_file`; set while following
+    pub fn follow_flag(&self) -> Arc<AtomicBool> {
+        self.follow.clone()
+    }
+
+    /// Show what `sftp::tail_file` sends; dropping the receiver stops it
+    pub fn set_stream(&mut self, stream: mpsc::UnboundedReceiver<TailEvent>) {
+        self.stream = Some(stream);
+        self.error = None;
+    }
+
+    /// The stream ended with an error
+    pub fn set_error(&mut self, error: String) {
+        self.error = Some(error);
+    }
+
+    fn poll_stream(&mut self) {
+        let Some(stream) = &mut self.stream else { return };
+        let mut events = Vec::new();
+        while let Ok(event) = stream.try_recv() {
+            events.push(event);
+        }
+        for event in events {
+            match event {
+                TailEvent::Skipped(bytes) => self.skipped_bytes = bytes,
+                TailEvent::Text(text) => self.append(&text),
+                TailEvent::Restarted => {
+                    self.lines.clear();
+                    self.partial.clear();
+                    self.skipped_bytes = 0;
+                    self.dropped_lines = 0;
+                    self.matches_stale = true;
+                }
+                TailEvent::Failed(error) => self.error = Some(error),
+            }
+        }
+    }
+
+    fn append(&mut self, text: &str) {
+        let mut rest = text;
+        while let Some(newline) = rest.find('\n') {
+            let mut line = std::mem::take(&mut self.partial);
+            line.push_str(rest[..newline].trim_end_matches('\r'));
+            self.lines.push_back(line);
+            rest = &rest[newline + 1..];
+        }
+        self.partial.push_str(rest);
+
+        let excess = self.lines.len().saturating_sub(MAX_LINES);
+        if excess > 0 {
+            self.lines.drain(..excess);
+            self.dropped_lines += excess;
+        }
+        self.matches_stale = true;
+    }
+
+    fn line(&self, index: usize) -> &str {
+        self.lines.get(index).map_or(self.partial.as_str(), String::as_str)
+    }
+
+    fn line_count(&self) -> usize {
+        self.lines.len() + usize::from(!self.partial.is_empty())
+    }
+
+    fn update_matches(&mut self) {
+        if !self.matches_stale {
+            return;
+        }
+        self.matches_stale = false;
+        let needle = self.search.to_lowercase();
+        self.matches = if needle.is_empty() {
+            Vec::new()
+        } else {
+            (0..self.line_count()).filter(|&i| self.line(i).to_lowercase().contains(&needle)).collect()
+        };
+        self.current_match = self.current_match.min(self.matches.len().saturating_sub(1));
+    }
+
+    fn jump(&mut self, forward: bool) {
+        if self.matches.is_empty() {
+            return;
+        }
+        let count = self.matches.len();
+        self.current_match = if forward { (self.current_match + 1) % count } else { (self.current_match + count - 1) % count };
+        self.scroll_to = Some(self.matches[self.current_match]);
+        // Looking back through the file; don't get pulled to the end
+        self.follow.store(false, Ordering::Relaxed);
+    }
+
+    pub fn render(&mut self, ui: &mut egui::Ui) -> Option<FileViewerAction> {
+        let mut action = None;
+        self.poll_stream();
+        let following = self.follow.load(Ordering::Relaxed);
+        if following {
+            ui.ctx().request_repaint_after(std::time::Duration::from_millis(500));
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(&self.path).color(colors::TEXT_PRIMARY).strong().size(16.0));
+            ui.label(RichText::new(format!("on {} \u{00B7} read-only", self.connection_name)).color(colors::TEXT_MUTED).size(11.0));
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if secondary_button(ui, "Close").clicked() {
+                    action = Some(FileViewerAction::Close);
+                }
+                let mut follow = following;
+                if ui.toggle_value(&mut follow, "Follow").on_hover_text("Keep appending as the file grows, like tail -f").changed() {
+                    self.follow.store(follow, Ordering::Relaxed);
+                }
+            });
+        });
+
+        ui.horizontal(|ui| {
+            let response = ui.add(egui::TextEdit::singleline(&mut self.search).hint_text("Search").desired_width(220.0));
+            if response.changed() {
+                self.matches_stale = true;
+                self.current_match = 0;
+            }
+            self.update_matches();
+            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                let backwards = ui.input(|i| i.modifiers.shift);
+                self.jump(!backwards);
+                response.request_focus();
+            }
+            if !self.search.is_empty() {
+                let label = if self.matches.is_empty() {
+                    "No matches".to_string()
+                } else {
+                    format!("{} of {}", self.current_match + 1, self.matches.len())
+                };
+                ui.label(RichText::new(label).color(colors::TEXT_MUTED).size(11.0));
+                if ui.small_button("\u{25B2}").clicked() {
+                    self.jump(false);
+                }
+                if ui.small_button("\u{25BC}").clicked() {
+                    self.jump(true);
+                }
+            }
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let mut notes = Vec::new();
+                if self.skipped_bytes > 0 {
+                    notes.push(format!("first {} not shown", format_file_size(self.skipped_bytes)));
+                }
+                if self.dropped_lines > 0 {
+                    notes.push(format!("{} older lines dropped", self.dropped_lines));
+                }
+                if !notes.is_empty() {
+                    ui.label(RichText::new(notes.join(", ")).color(colors::TEXT_MUTED).size(11.0));
+                }
+            });
+        });
+        if let Some(error) = &self.error {
+            ui.label(RichText::new(error).color(colors::ERROR));
+        }
+        ui.add_space(spacing::SM);
+
+        self.render_lines(ui, following);
+        action
+    }
+
+    fn render_lines(&mut self, ui: &mut egui::Ui, following: bool) {
+        if self.stream.is_none() && self.line_count() == 0 {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Opening...");
+            });
+            return;
+        }
+
+        let font = FontId::monospace(FONT_SIZE);
+        let row_height = ui.fonts(|f| f.row_height(&font));
+        let needle = self.search.to_lowercase();
+        let current = self.matches.get(self.current_match).copied();
+
+        let mut area = egui::ScrollArea::both().auto_shrink([false, false]).stick_to_bottom(following);
+        if let Some(row) = self.scroll_to.take() {
+            let spacing = ui.spacing().item_spacing.y;
+            area = area.vertical_scroll_offset((row as f32 * (row_height + spacing) - ui.available_height() / 2.0).max(0.0));
+        }
+        area.show_rows(ui, row_height, self.line_count(), |ui, rows| {
+            for index in rows {
+                let line = self.line(index);
+                let mut job = LayoutJob::default();
+                let plain = TextFormat { font_id: font.clone(), color: colors::TEXT_PRIMARY, ..Default::default() };
+                let found = (!needle.is_empty()).then(|| line.to_lowercase().find(&needle)).flatten()
+                    // Lowercasing can shift byte offsets; only highlight when it didn't
+                    .filter(|&at| line.is_char_boundary(at) && line.is_char_boundary(at + needle.len()));
+                match found {
+                    Some(at) => {
+                        let highlight = TextFormat {
+                            background: if current == Some(index) { colors::WARNING } else { colors::BG_HIGHLIGHT },
+                            ..plain.clone()
+                        };
+                        job.append(&line[..at], 0.0, plain.clone());
+                        job.append(&line[at..at + needle.len()], 0.0, highlight);
+                        job.append(&line[at + needle.len()..], 0.0, plain);
+                    }
+                    None => job.append(line, 0.0, plain),
+                }
+                ui.label(job);
+            }
+        });
+    }
+}
+
+/// Actions from the file viewer
+pub enum FileViewerAction {
+    Close,
+}
@@ -1,7 +1,10 @@
 //! Port forwarding management screen
 
 use egui::{Context, Ui};
-use crate::ssh::{PortForward, ForwardType};
+use crate::ssh::{local_port_available, suggest_free_port, PortForward, PortInUse, ForwardType};
+use crate::ui::components::colors;
+use crate::utils::http_preview::{self, HttpPreview};
+use std::collections::HashMap;
 
 pub struct ForwardingScreen {
     forwards: Vec<PortForward>,
@@ -9,6 +12,10 @@ pub struct ForwardingScreen {
     edit_remote_host: String,
     edit_remote_port: String,
     forward_type: ForwardType,
+    /// Local port found taken, with a free one to offer instead
+    port_conflict: Option<(u16, Option<u16>)>,
+    /// Fetched root page per forward; `None` while the fetch runs
+    previews: HashMap<uuid::Uuid, Option<Result<HttpPreview, String>>>,
 }
 
 impl ForwardingScreen {
@@ -19,6 +26,8 @@ impl ForwardingScreen {
             edit_remote_host: "localhost".to_string(),
             edit_remote_port: "80".to_string(),
             forward_type: ForwardType::Local,
+            port_conflict: None,
+            previews: HashMap::new(),
         }
     }
     
@@ -55,8 +64,25 @@ impl ForwardingScreen {
                 });
             }
             
+            if let Some((busy, suggestion)) = self.port_conflict {
+                ui.horizontal(|ui| {
+                    ui.colored_label(colors::WARNING, format!("Port {} is already in use on this computer.", busy));
+                    if let Some(port) = suggestion {
+                        if ui.button(format!("Use {} instead", port)).clicked() {
+                            self.edit_local_port = port.to_string();
+                            self.port_conflict = None;
+                        }
+                    }
+                });
+            }
+
             if ui.button("➕ Add Forward").clicked() {
-                if let Ok(local_port) = self.edit_local_port.parse::<u16>() {
+                // Remote forwards listen on the server; only local ports can be checked here
+                let local_listen = !matches!(self.forward_type, ForwardType::Remote);
+                let busy = self.edit_local_port.parse::<u16>().ok()
+                    .filter(|&port| local_listen && !local_port_available("127.0.0.1", port));
+                self.port_conflict = busy.map(|port| (port, suggest_free_port("127.0.0.1", port)));
+                if let Some(local_port) = self.edit_local_port.parse::<u16>().ok().filter(|_| busy.is_none()) {
                     let forward = match self.forward_type {
                         ForwardType::Local => {
                             if let Ok(remote_port) = self.edit_remote_port.parse::<u16>() {
@@ -127,10 +153,34 @@ impl ForwardingScreen {
                     
                     ui.label(desc);
                     
+                    let url = match forward.forward_type {
+                        ForwardType::Local => http_preview::forward_url(forward.listen_port, forward.remote_port),
+                        _ => None,
+                    };
+                    if let Some(url) = &url {
+                        if ui.button("🌐 Open in browser").clicked() {
+                            ui.ctx().open_url(egui::OpenUrl::new_tab(url));
+                        }
+                        let fetching = matches!(self.previews.get(&forward.id), Some(None));
+                        if ui.add_enabled(forward.active && !fetching, egui::Button::new("Preview"))
+                            .on_hover_text("Fetch the page through the tunnel and show the response")
+                            .clicked()
+                        {
+                            self.previews.insert(forward.id, None);
+                            action = Some(ForwardingAction::Preview(forward.id, url.clone()));
+                        }
+                    }
+                    
                     if ui.button("🗑 Remove").clicked() {
                         to_remove = Some(idx);
                     }
                 });
+                if let Some(error) = &forward.error {
+                    ui.colored_label(colors::ERROR, format!("    {}", error));
+                }
+                if let Some(preview) = self.previews.get(&forward.id) {
+                    render_preview(ui, forward.id, preview);
+                }
             }
         });
         
@@ -147,6 +197,31 @@ impl ForwardingScreen {
     pub fn set_forwards(&mut self, forwards: Vec<PortForward>) {
         self.forwards = forwards;
     }
+
+    /// Result of a `Preview` action
+    pub fn set_preview(&mut self, id: uuid::Uuid, result: anyhow::Result<HttpPreview>) {
+        self.previews.insert(id, Some(result.map_err(|e| format!("{:#}", e))));
+    }
+
+    /// Show why a forward failed to start next to it; a taken local port
+    /// also brings up the offer of a free one
+    pub fn report_start_error(&mut self, id: uuid::Uuid, error: &anyhow::Error) {
+        let message = match error.downcast_ref::<PortInUse>() {
+            Some(conflict) if conflict.remote => format!(
+                "The server could not listen on port {}; another program there is probably using it",
+                conflict.port
+            ),
+            Some(conflict) => {
+                self.port_conflict = Some((conflict.port, conflict.suggestion));
+                conflict.to_string()
+            }
+            None => format!("{:#}", error),
+        };
+        if let Some(forward) = self.forwards.iter_mut().find(|f| f.id == id) {
+            forward.active = false;
+            forward.error = Some(message);
+        }
+    }
 }
 
 impl Default for ForwardingScreen {
@@ -155,8 +230,51 @@ impl Default for ForwardingScreen {
     }
 }
 
+/// Status line, headers and the start of the body under a forward
+fn render_preview(ui: &mut Ui, id: uuid::Uuid, preview: &Option<Result<HttpPreview, String>>) {
+    ui.indent(("forward_preview", id), |ui| match preview {
+        None => {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Fetching…");
+            });
+        }
+        Some(Err(error)) => {
+            ui.colored_label(colors::ERROR, error);
+        }
+        Some(Ok(preview)) => {
+            let color = if preview.status < 400 { colors::SUCCESS } else { colors::WARNING };
+            ui.colored_label(color, format!(
+                "{} {} from {} in {} ms",
+                preview.status,
+                preview.status_text,
+                preview.url,
+                preview.elapsed.as_millis()
+            ));
+            egui::CollapsingHeader::new(format!("{} headers", preview.headers.len()))
+                .id_source(("forward_preview_headers", id))
+                .show(ui, |ui| {
+                    for (name, value) in &preview.headers {
+                        ui.monospace(format!("{}: {}", name, value));
+                    }
+                });
+            if !preview.body.is_empty() {
+                egui::CollapsingHeader::new("Body")
+                    .id_source(("forward_preview_body", id))
+                    .show(ui, |ui| {
+                        egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                            ui.monospace(&preview.body);
+                        });
+                    });
+            }
+        }
+    });
+}
+
 #[derive(Debug, Clone)]
 pub enum ForwardingAction {
     Add(PortForward),
     Remove(uuid::Uuid),
+    /// Fetch this URL through the forward; answer with `set_preview`
+    Preview(uuid::Uuid, String),
 }
@@ -0,0 +1,129 @@
+//! Host Key Dialog - decide on a new server's key, or review and replace a
+//! changed one
+
+use eframe::egui::{self, RichText};
+use crate::ssh::{HostKeyPrompt, HostKeyQuestion};
+use crate::storage::database::HostKeyChange;
+use crate::ui::components::{colors, spacing, checkbox, danger_button, primary_button, secondary_button};
+
+/// Dialog state for one key; the handshake waits until it is answered
+pub struct HostKeyDialog {
+    prompt: Option<HostKeyPrompt>,
+    /// Earlier replacements for this host, shown for context
+    history: Vec<HostKeyChange>,
+    /// Must equal the host name before a changed key can be replaced
+    confirm_text: String,
+    update_openssh: bool,
+}
+
+impl HostKeyDialog {
+    pub fn new(prompt: HostKeyPrompt, history: Vec<HostKeyChange>) -> Self {
+        Self {
+            prompt: Some(prompt),
+            history,
+            confirm_text: String::new(),
+            update_openssh: false,
+        }
+    }
+
+    /// Render as a modal window; false once answered or cancelled
+    pub fn render(&mut self, ctx: &egui::Context) -> bool {
+        let Some(prompt) = &self.prompt else { return false };
+        let mut trust = false;
+        let mut cancel = false;
+
+        match &prompt.question {
+            HostKeyQuestion::Unknown(offered) => {
+                egui::Window::new(format!("New host {}", offered.host))
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                    .show(ctx, |ui| {
+                        ui.label(RichText::new(format!(
+                            "This is the first connection to {}:{}.",
+                            offered.host, offered.port
+                        )).color(colors::TEXT_PRIMARY));
+                        ui.label(RichText::new(
+                            "Check the fingerprint with the server's owner before trusting it."
+                        ).color(colors::TEXT_SECONDARY).size(12.0));
+                        ui.add_space(spacing::MD);
+                        ui.label(RichText::new(format!("{} {}", offered.key_type, offered.fingerprint))
+                            .monospace()
+                            .color(colors::TEXT_PRIMARY));
+
+                        ui.add_space(spacing::MD);
+                        ui.horizontal(|ui| {
+                            trust = primary_button(ui, "Trust and connect").clicked();
+                            cancel = secondary_button(ui, "Cancel").clicked();
+                        });
+                    });
+            }
+            HostKeyQuestion::Changed(mismatch) => {
+                let host = mismatch.host.clone();
+                let confirmed = self.confirm_text.trim() == host;
+                egui::Window::new("⚠ Host key changed")
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                    .show(ctx, |ui| {
+                        ui.label(RichText::new(format!(
+                            "The key presented by {}:{} does not match the one on record.",
+                            host, mismatch.port
+                        )).color(colors::TEXT_PRIMARY));
+                        ui.label(RichText::new(
+                            "This is expected after a server was reinstalled. It can also mean someone is \
+                             intercepting the connection. Check the new fingerprint with the server's owner."
+                        ).color(colors::TEXT_SECONDARY).size(12.0));
+
+                        ui.add_space(spacing::MD);
+                        egui::Grid::new("host_key_fingerprints").num_columns(2).spacing([spacing::MD, spacing::XS]).show(ui, |ui| {
+                            ui.label(RichText::new("On record").color(colors::TEXT_SECONDARY));
+                            ui.label(RichText::new(&mismatch.expected).monospace().color(colors::DANGER));
+                            ui.end_row();
+                            ui.label(RichText::new("Offered").color(colors::TEXT_SECONDARY));
+                            ui.label(RichText::new(format!("{} {}", mismatch.offered.key_type, mismatch.offered.fingerprint))
+                                .monospace()
+                                .color(colors::WARNING));
+                            ui.end_row();
+                        });
+
+                        if !self.history.is_empty() {
+                            ui.add_space(spacing::SM);
+                            ui.collapsing(format!("Previous key changes ({})", self.history.len()), |ui| {
+                                for change in &self.history {
+                                    ui.label(RichText::new(format!("{}: {} → {}", change.changed_at, change.old_fingerprint, change.new_fingerprint))
+                                        .monospace()
+                                        .size(11.0)
+                                        .color(colors::TEXT_MUTED));
+                                }
+                            });
+                        }
+
+                        ui.add_space(spacing::MD);
+                        ui.label(RichText::new(format!("Type {} to confirm", host)).color(colors::TEXT_PRIMARY));
+                        ui.add(egui::TextEdit::singleline(&mut self.confirm_text)
+                            .hint_text(&host)
+                            .desired_width(280.0));
+                        checkbox(ui, &mut self.update_openssh, "Also update ~/.ssh/known_hosts");
+
+                        ui.add_space(spacing::MD);
+                        ui.horizontal(|ui| {
+                            trust = ui.add_enabled_ui(confirmed, |ui| danger_button(ui, "Replace key")).inner.clicked();
+                            cancel = secondary_button(ui, "Cancel").clicked();
+                        });
+                    });
+            }
+        }
+
+        if trust {
+            if let Some(prompt) = self.prompt.take() {
+                prompt.trust(self.update_openssh);
+            }
+        } else if cancel {
+            if let Some(prompt) = self.prompt.take() {
+                prompt.reject();
+            }
+        }
+        self.prompt.is_some()
+    }
+}
@@ -0,0 +1,95 @@
+//! Verification Dialog - answer a server's login questions, such as a jump
+//! host's MFA challenge
+
+use eframe::egui::{self, RichText};
+use crate::ssh::bastion::MfaPrompt;
+use crate::ui::components::{colors, spacing, primary_button, secondary_button};
+
+/// Dialog state for one round of questions
+pub struct MfaDialog {
+    prompt: Option<MfaPrompt>,
+    answers: Vec<String>,
+    /// Focus the first field once
+    focused: bool,
+}
+
+impl MfaDialog {
+    pub fn new(prompt: MfaPrompt) -> Self {
+        Self {
+            answers: vec![String::new(); prompt.prompts.len()],
+            prompt: Some(prompt),
+            focused: false,
+        }
+    }
+
+    /// Render as a modal window; false once answered or cancelled
+    pub fn render(&mut self, ctx: &egui::Context) -> bool {
+        let Some(prompt) = &self.prompt else { return false };
+        let mut submit = false;
+        let mut cancel = false;
+
+        egui::Window::new(format!("\u{1F510} Verify {}", prompt.bastion.host))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                let asking = if prompt.jump_host {
+                    format!("The jump host {} asks you to verify again.", prompt.bastion)
+                } else {
+                    format!("Logging in to {}.", prompt.bastion)
+                };
+                ui.label(RichText::new(asking).color(colors::TEXT_PRIMARY));
+                if !prompt.leaf_hosts.is_empty() {
+                    ui.label(RichText::new(format!(
+                        "This keeps the way to {} open. Open sessions are not interrupted while you answer.",
+                        prompt.leaf_hosts.join(", ")
+                    )).color(colors::TEXT_SECONDARY).size(12.0));
+                }
+                for text in [&prompt.name, &prompt.instructions] {
+                    if !text.trim().is_empty() {
+                        ui.add_space(spacing::XS);
+                        ui.label(RichText::new(text.trim()).color(colors::TEXT_SECONDARY));
+                    }
+                }
+
+                ui.add_space(spacing::MD);
+                egui::Grid::new("mfa_prompts").num_columns(2).spacing([spacing::MD, spacing::XS]).show(ui, |ui| {
+                    for (i, (text, echo)) in prompt.prompts.iter().enumerate() {
+                        ui.label(RichText::new(text.trim()).color(colors::TEXT_PRIMARY));
+                        let field = ui.add(egui::TextEdit::singleline(&mut self.answers[i])
+                            .password(!echo)
+                            .desired_width(200.0));
+                        if i == 0 && !self.focused {
+                            field.request_focus();
+                            self.focused = true;
+                        }
+                        if field.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+                            submit = true;
+                        }
+                        ui.end_row();
+                    }
+                });
+
+                ui.add_space(spacing::MD);
+                ui.horizontal(|ui| {
+                    if primary_button(ui, "Verify").clicked() {
+                        submit = true;
+                    }
+                    if secondary_button(ui, "Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if submit {
+            if let Some(prompt) = self.prompt.take() {
+                prompt.answer(std::mem::take(&mut self.answers));
+            }
+        } else if cancel {
+            if let Some(prompt) = self.prompt.take() {
+                prompt.cancel();
+            }
+        }
+        self.prompt.is_some()
+    }
+}
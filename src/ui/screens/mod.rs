@@ -1,11 +1,35 @@
 //! UI screens
 
+pub mod cloud_browser;
+pub mod diagnostics;
 pub mod connection_list;
+pub mod disk_usage;
+pub mod file_viewer;
 pub mod forwarding_screen;
+pub mod host_key_dialog;
+pub mod mfa_dialog;
+pub mod plugin_manager;
+pub mod security_audit;
 pub mod settings_screen;
+pub mod share_viewer;
 pub mod sftp_browser_ui;
+pub mod theme_editor;
+pub mod transfer_history;
+pub mod usage_stats;
 
+pub use cloud_browser::{CloudBrowserScreen, CloudBrowserAction};
+pub use diagnostics::{DiagnosticsScreen, DiagnosticsAction};
 pub use connection_list::{ConnectionListScreen, ConnectionAction};
+pub use disk_usage::{DiskUsageScreen, DiskUsageAction};
+pub use file_viewer::{FileViewerScreen, FileViewerAction};
 pub use forwarding_screen::{ForwardingScreen, ForwardingAction};
+pub use host_key_dialog::HostKeyDialog;
+pub use mfa_dialog::MfaDialog;
+pub use plugin_manager::{PluginManagerScreen, PluginManagerAction, render_plugin_panel};
+pub use security_audit::{SecurityAuditScreen, SecurityAuditAction};
 pub use settings_screen::{SettingsScreen, SettingsAction};
 pub use sftp_browser_ui::SftpBrowserScreen;
+pub use share_viewer::{ShareViewerScreen, ShareViewerAction};
+pub use theme_editor::{ThemeEditorScreen, ThemeEditorAction};
+pub use transfer_history::{TransferHistoryScreen, TransferHistoryAction};
+pub use usage_stats::{UsageStatsScreen, UsageStatsAction};
@@ -0,0 +1,183 @@
+//! Plugin Manager Screen - enable/disable plugins and review their permissions
+
+#![allow(dead_code)]
+
+use eframe::egui::{self, RichText};
+use crate::plugins::{PanelContent, PanelItem, PluginRegistry};
+use crate::ui::components::{colors, spacing, secondary_button, empty_state, card};
+
+/// Plugin manager view state
+pub struct PluginManagerScreen {
+    /// Plugin awaiting confirmation of its permissions before enabling
+    confirm_enable: Option<String>,
+}
+
+impl PluginManagerScreen {
+    pub fn new() -> Self {
+        Self { confirm_enable: None }
+    }
+
+    /// Render the plugin list
+    pub fn render(&mut self, ui: &mut egui::Ui, registry: &PluginRegistry) -> Option<PluginManagerAction> {
+        let mut action = None;
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("Plugins")
+                .color(colors::TEXT_PRIMARY)
+                .strong()
+                .size(16.0));
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if secondary_button(ui, "Rescan").clicked() {
+                    action = Some(PluginManagerAction::Rescan);
+                }
+                if secondary_button(ui, "Open plugins folder").clicked() {
+                    action = Some(PluginManagerAction::OpenFolder);
+                }
+            });
+        });
+
+        ui.label(RichText::new(registry.dir().display().to_string())
+            .color(colors::TEXT_MUTED)
+            .size(11.0));
+        ui.add_space(spacing::SM);
+
+        if registry.plugins.is_empty() {
+            empty_state(ui, "\u{1F9E9}", "No Plugins Installed", "Copy a plugin folder into the plugins directory");
+            return action;
+        }
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for plugin in &registry.plugins {
+                let manifest = &plugin.manifest;
+
+                card(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(RichText::new(&manifest.name).color(colors::TEXT_PRIMARY).strong());
+                        ui.label(RichText::new(format!("v{}", manifest.version)).color(colors::TEXT_MUTED).size(11.0));
+
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            let mut enabled = plugin.enabled;
+                            if ui.checkbox(&mut enabled, "Enabled").changed() {
+                                if enabled && !manifest.permissions.is_empty() {
+                                    self.confirm_enable = Some(manifest.id.clone());
+                                } else {
+                                    action = Some(PluginManagerAction::SetEnabled(manifest.id.clone(), enabled));
+                                }
+                            }
+                        });
+                    });
+
+                    if !manifest.description.is_empty() {
+                        ui.label(RichText::new(&manifest.description).color(colors::TEXT_SECONDARY));
+                    }
+                    if !manifest.author.is_empty() {
+                        ui.label(RichText::new(format!("by {}", manifest.author)).color(colors::TEXT_MUTED).size(11.0));
+                    }
+
+                    let contributes = [
+                        (manifest.panels.len(), "panel"),
+                        (manifest.context_actions.len(), "menu action"),
+                        (manifest.connection_types.len(), "connection type"),
+                    ];
+                    let summary: Vec<_> = contributes.iter()
+                        .filter(|(n, _)| *n > 0)
+                        .map(|(n, what)| format!("{} {}{}", n, what, if *n == 1 { "" } else { "s" }))
+                        .collect();
+                    if !summary.is_empty() {
+                        ui.label(RichText::new(format!("Adds {}", summary.join(", "))).color(colors::TEXT_MUTED).size(11.0));
+                    }
+
+                    permissions_list(ui, &manifest.permissions.filesystem, &manifest.permissions.network);
+
+                    if let Some(error) = &plugin.error {
+                        ui.label(RichText::new(format!("Failed to load: {}", error)).color(colors::ERROR));
+                    }
+                });
+                ui.add_space(spacing::SM);
+            }
+        });
+
+        if let Some(id) = self.confirm_enable.clone() {
+            if let Some(plugin) = registry.plugins.iter().find(|p| p.manifest.id == id) {
+                let mut close = false;
+                egui::Window::new(format!("Enable {}?", plugin.manifest.name))
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                    .show(ui.ctx(), |ui| {
+                        ui.label("This plugin asks for the following access:");
+                        ui.add_space(spacing::SM);
+                        permissions_list(ui, &plugin.manifest.permissions.filesystem, &plugin.manifest.permissions.network);
+                        ui.add_space(spacing::SM);
+                        ui.horizontal(|ui| {
+                            if ui.button("Cancel").clicked() {
+                                close = true;
+                            }
+                            if ui.button("Enable").clicked() {
+                                action = Some(PluginManagerAction::SetEnabled(id.clone(), true));
+                                close = true;
+                            }
+                        });
+                    });
+                if close {
+                    self.confirm_enable = None;
+                }
+            } else {
+                self.confirm_enable = None;
+            }
+        }
+
+        action
+    }
+}
+
+impl Default for PluginManagerScreen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn permissions_list(ui: &mut egui::Ui, filesystem: &[String], network: &[String]) {
+    if filesystem.is_empty() && network.is_empty() {
+        ui.label(RichText::new("No filesystem or network access").color(colors::TEXT_MUTED).size(11.0));
+        return;
+    }
+    for path in filesystem {
+        ui.label(RichText::new(format!("\u{1F4C1} Read/write {}", path)).color(colors::WARNING).size(11.0));
+    }
+    for host in network {
+        ui.label(RichText::new(format!("\u{1F310} Connect to {}", host)).color(colors::WARNING).size(11.0));
+    }
+}
+
+/// Draw plugin-provided panel content; returns the id of a clicked button
+pub fn render_plugin_panel(ui: &mut egui::Ui, content: &PanelContent) -> Option<String> {
+    let mut clicked = None;
+    for item in &content.items {
+        match item {
+            PanelItem::Heading { text } => {
+                ui.label(RichText::new(text).color(colors::TEXT_PRIMARY).strong());
+            }
+            PanelItem::Label { text } => {
+                ui.label(RichText::new(text).color(colors::TEXT_SECONDARY));
+            }
+            PanelItem::Button { id, label } => {
+                if ui.button(label).clicked() {
+                    clicked = Some(id.clone());
+                }
+            }
+            PanelItem::Separator => {
+                ui.separator();
+            }
+        }
+    }
+    clicked
+}
+
+/// Actions from the plugin manager
+pub enum PluginManagerAction {
+    SetEnabled(String, bool),
+    OpenFolder,
+    Rescan,
+}
@@ -0,0 +1,142 @@
+//! Security Screen - review and revoke stored secrets
+
+use chrono::Local;
+use eframe::egui::{self, RichText};
+use crate::crypto::audit::{SecretHandle, SecretLocation, StoredSecret};
+use crate::ui::components::{colors, spacing, secondary_button, empty_state};
+
+/// Stored secrets audit view state
+pub struct SecurityAuditScreen {
+    secrets: Vec<StoredSecret>,
+    /// Item waiting for a second click to revoke
+    confirm: Option<SecretHandle>,
+    error: Option<String>,
+}
+
+impl SecurityAuditScreen {
+    pub fn new() -> Self {
+        Self {
+            secrets: Vec::new(),
+            confirm: None,
+            error: None,
+        }
+    }
+
+    pub fn set_secrets(&mut self, secrets: Vec<StoredSecret>) {
+        self.secrets = secrets;
+        self.confirm = None;
+    }
+
+    /// Show why the last revoke failed
+    pub fn set_error(&mut self, error: Option<String>) {
+        self.error = error;
+    }
+
+    /// Render the audit view
+    pub fn render(&mut self, ui: &mut egui::Ui) -> Option<SecurityAuditAction> {
+        let mut action = None;
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("Security")
+                .color(colors::TEXT_PRIMARY)
+                .strong()
+                .size(16.0));
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if secondary_button(ui, "Close").clicked() {
+                    action = Some(SecurityAuditAction::Close);
+                }
+                if secondary_button(ui, "Refresh").clicked() {
+                    action = Some(SecurityAuditAction::Refresh);
+                }
+            });
+        });
+
+        ui.label(RichText::new("Everything sensitive TabSSH stores on this machine")
+            .color(colors::TEXT_MUTED)
+            .size(11.0));
+        let plaintext = self.secrets.iter().filter(|s| s.location == SecretLocation::Plaintext).count();
+        if plaintext > 0 {
+            ui.label(RichText::new(format!("{} item(s) are stored unencrypted", plaintext))
+                .color(colors::WARNING)
+                .size(11.0));
+        }
+        if let Some(error) = &self.error {
+            ui.label(RichText::new(error).color(colors::ERROR));
+        }
+
+        ui.add_space(spacing::SM);
+        ui.separator();
+
+        if self.secrets.is_empty() {
+            empty_state(ui, "\u{1F512}", "Nothing Stored", "Saved passwords, keys and session logs show up here");
+            return action;
+        }
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("security_audit_grid")
+                .num_columns(5)
+                .striped(true)
+                .spacing([spacing::MD, spacing::XS])
+                .show(ui, |ui| {
+                    for header in ["Item", "For", "Stored in", "Last used", ""] {
+                        ui.label(RichText::new(header).strong().color(colors::TEXT_SECONDARY));
+                    }
+                    ui.end_row();
+
+                    for secret in &self.secrets {
+                        ui.label(secret.kind.label());
+                        ui.label(&secret.name).on_hover_text(&secret.name);
+
+                        let location_color = match secret.location {
+                            SecretLocation::Plaintext => colors::WARNING,
+                            _ => colors::TEXT_SECONDARY,
+                        };
+                        let location = ui.label(RichText::new(secret.location.label()).color(location_color));
+                        if let SecretHandle::File(path) = &secret.handle {
+                            location.on_hover_text(path.display().to_string());
+                        }
+
+                        let last_used = secret.last_used
+                            .map(|t| t.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+                            .unwrap_or_else(|| "Never".to_string());
+                        ui.label(RichText::new(last_used).color(colors::TEXT_MUTED));
+
+                        if self.confirm.as_ref() == Some(&secret.handle) {
+                            ui.horizontal(|ui| {
+                                if ui.small_button(RichText::new("Confirm").color(colors::ERROR)).clicked() {
+                                    action = Some(SecurityAuditAction::Revoke(secret.clone()));
+                                }
+                                if ui.small_button("Cancel").clicked() {
+                                    self.confirm = None;
+                                }
+                            });
+                        } else {
+                            let label = if matches!(secret.handle, SecretHandle::File(_)) { "Delete" } else { "Revoke" };
+                            if ui.small_button(label).clicked() {
+                                self.confirm = Some(secret.handle.clone());
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+
+        action
+    }
+}
+
+impl Default for SecurityAuditScreen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Actions from the security screen
+pub enum SecurityAuditAction {
+    /// Collect the secrets again
+    Refresh,
+    /// Delete the secret, then refresh
+    Revoke(StoredSecret),
+    Close,
+}
@@ -2,7 +2,16 @@
 
 use eframe::egui::{self, RichText};
 use crate::ui::components::{colors, spacing, primary_button, secondary_button, danger_button,
-    labeled_toggle, labeled_dropdown, labeled_number, section_header, card, form_row, nav_item};
+    labeled_toggle, labeled_dropdown, labeled_number, labeled_input, section_header, card, form_row, nav_item};
+use crate::storage::Database;
+use crate::storage::database::TrustedCa;
+use crate::ssh::key_health::{self, KeyReport};
+use crate::storage::sync::{store_webdav_password, ConflictStrategy, SyncConfig, SyncTarget};
+use crate::utils::custom_actions::{ActionScope, CustomAction, example_actions};
+use crate::terminal::benchmark::{self, BenchmarkOptions, BenchmarkReport};
+use crate::terminal::prediction::PredictionMode;
+use crate::terminal::renderer::run_benchmark;
+use crate::terminal::triggers::{example_triggers, OutputTrigger};
 
 /// Settings category
 #[derive(Clone, Copy, PartialEq)]
@@ -13,6 +22,7 @@ pub enum SettingsCategory {
     SSH,
     Security,
     KeyManagement,
+    CustomActions,
     Backup,
 }
 
@@ -25,6 +35,7 @@ impl SettingsCategory {
             SettingsCategory::SSH => "\u{1F511}",
             SettingsCategory::Security => "\u{1F512}",
             SettingsCategory::KeyManagement => "\u{1F5DD}",
+            SettingsCategory::CustomActions => "\u{1F6E0}",
             SettingsCategory::Backup => "\u{1F4BE}",
         }
     }
@@ -37,6 +48,7 @@ impl SettingsCategory {
             SettingsCategory::SSH => "SSH",
             SettingsCategory::Security => "Security",
             SettingsCategory::KeyManagement => "Key Management",
+            SettingsCategory::CustomActions => "Custom Actions",
             SettingsCategory::Backup => "Backup & Sync",
         }
     }
@@ -136,6 +148,19 @@ pub struct SettingsScreen {
     pub word_wrap: bool,
     pub copy_on_select: bool,
     pub paste_on_right_click: bool,
+    /// Send modified keys in CSI-u form even when not requested
+    pub csi_u_keys: bool,
+    /// Alt/Option+key sends an ESC prefix
+    pub alt_sends_escape: bool,
+    /// Local echo prediction on laggy links
+    pub echo_prediction: PredictionMode,
+    /// Reply to ENQ
+    pub answerback: String,
+    /// Raw recording to benchmark with; empty uses the built-in corpus
+    pub benchmark_corpus: String,
+    /// Benchmark running on its own thread, so the window stays responsive
+    benchmark: Option<std::thread::JoinHandle<anyhow::Result<BenchmarkReport>>>,
+    benchmark_result: Option<Result<BenchmarkReport, String>>,
 
     // SSH settings
     pub default_port: u16,
@@ -153,11 +178,24 @@ pub struct SettingsScreen {
     pub auto_lock_timeout: u16,
     pub clear_clipboard_after: u16,
     pub log_session_data: bool,
+    /// Host CAs whose certificates skip the per-host key prompt
+    pub trusted_cas: Vec<TrustedCa>,
+    /// `@cert-authority` line being entered
+    pub new_ca_entry: String,
+    pub ca_error: Option<String>,
 
     // Key management settings
     pub default_key_path: String,
     pub agent_enabled: bool,
     pub auto_add_keys: bool,
+    /// Last key health check; run when Key Management first opens
+    key_health: Option<Vec<KeyReport>>,
+
+    // Custom actions
+    pub custom_actions: Vec<CustomAction>,
+
+    // Output highlighting and triggers
+    pub output_triggers: Vec<OutputTrigger>,
 
     // Backup settings
     pub auto_backup_enabled: bool,
@@ -165,6 +203,16 @@ pub struct SettingsScreen {
     pub backup_interval_days: u16,
     pub encrypt_backups: bool,
 
+    // Sync settings
+    pub sync_config: SyncConfig,
+    /// WebDAV password entered here; stored in the keychain on save
+    pub sync_password: String,
+    /// Saved connections offered as SFTP targets, as `(id, name)`
+    pub sync_connections: Vec<(String, String)>,
+    pub sync_running: bool,
+    /// Result of the last sync, shown under the button
+    pub sync_status: Option<Result<String, String>>,
+
     // Track changes
     pub has_unsaved_changes: bool,
 }
@@ -204,6 +252,13 @@ impl SettingsScreen {
             word_wrap: false,
             copy_on_select: false,
             paste_on_right_click: true,
+            csi_u_keys: false,
+            alt_sends_escape: crate::terminal::input::default_alt_sends_escape(),
+            echo_prediction: PredictionMode::default(),
+            answerback: String::new(),
+            benchmark_corpus: String::new(),
+            benchmark: None,
+            benchmark_result: None,
 
             // SSH defaults
             default_port: 22,
@@ -221,11 +276,21 @@ impl SettingsScreen {
             auto_lock_timeout: 5,
             clear_clipboard_after: 30,
             log_session_data: false,
+            trusted_cas: Vec::new(),
+            new_ca_entry: String::new(),
+            ca_error: None,
 
             // Key management defaults
             default_key_path: "~/.ssh/id_ed25519".to_string(),
             agent_enabled: true,
             auto_add_keys: true,
+            key_health: None,
+
+            // Custom actions
+            custom_actions: Vec::new(),
+
+            // Output triggers
+            output_triggers: Vec::new(),
 
             // Backup defaults
             auto_backup_enabled: false,
@@ -233,6 +298,13 @@ impl SettingsScreen {
             backup_interval_days: 7,
             encrypt_backups: true,
 
+            // Sync defaults
+            sync_config: SyncConfig::default(),
+            sync_password: String::new(),
+            sync_connections: Vec::new(),
+            sync_running: false,
+            sync_status: None,
+
             has_unsaved_changes: false,
         }
     }
@@ -257,6 +329,7 @@ impl SettingsScreen {
                     SettingsCategory::SSH,
                     SettingsCategory::Security,
                     SettingsCategory::KeyManagement,
+                    SettingsCategory::CustomActions,
                     SettingsCategory::Backup,
                 ];
 
@@ -294,9 +367,18 @@ impl SettingsScreen {
                     SettingsCategory::Appearance => self.render_appearance(ui),
                     SettingsCategory::Terminal => self.render_terminal(ui),
                     SettingsCategory::SSH => self.render_ssh(ui),
-                    SettingsCategory::Security => self.render_security(ui),
+                    SettingsCategory::Security => {
+                        if let Some(security_action) = self.render_security(ui) {
+                            action = Some(security_action);
+                        }
+                    }
                     SettingsCategory::KeyManagement => self.render_key_management(ui),
-                    SettingsCategory::Backup => self.render_backup(ui),
+                    SettingsCategory::CustomActions => self.render_custom_actions(ui),
+                    SettingsCategory::Backup => {
+                        if let Some(backup_action) = self.render_backup(ui) {
+                            action = Some(backup_action);
+                        }
+                    }
                 }
             });
         });
@@ -482,6 +564,31 @@ impl SettingsScreen {
                 labeled_toggle(ui, "Visual bell (flash screen)", &mut self.bell_visual);
             });
 
+            section_header(ui, "Keyboard");
+
+            form_row(ui, |ui| {
+                labeled_toggle(ui, "Send modified keys as CSI-u (e.g. Ctrl+Enter)", &mut self.csi_u_keys);
+            });
+
+            let alt_label = if cfg!(target_os = "macos") {
+                "Option key sends ESC (instead of composing characters)"
+            } else {
+                "Alt key sends ESC prefix"
+            };
+            form_row(ui, |ui| {
+                labeled_toggle(ui, alt_label, &mut self.alt_sends_escape);
+            });
+
+            form_row(ui, |ui| {
+                labeled_dropdown(ui, "Predict local echo", "echo_prediction", &mut self.echo_prediction, &PredictionMode::ALL);
+            });
+
+            section_header(ui, "Identification");
+
+            form_row(ui, |ui| {
+                labeled_input(ui, "Answerback message (sent on ENQ)", &mut self.answerback, "Empty sends nothing");
+            });
+
             section_header(ui, "Text Behavior");
 
             form_row(ui, |ui| {
@@ -496,6 +603,175 @@ impl SettingsScreen {
                 labeled_toggle(ui, "Paste on right-click", &mut self.paste_on_right_click);
             });
         });
+
+        ui.add_space(spacing::MD);
+        self.render_triggers(ui);
+
+        ui.add_space(spacing::MD);
+        self.render_benchmark(ui);
+    }
+
+    fn render_benchmark(&mut self, ui: &mut egui::Ui) {
+        if self.benchmark.as_ref().is_some_and(|handle| handle.is_finished()) {
+            let result = match self.benchmark.take().map(|handle| handle.join()) {
+                Some(Ok(result)) => result.map_err(|e| e.to_string()),
+                _ => Err("The benchmark stopped unexpectedly".to_string()),
+            };
+            if let Ok(report) = &result {
+                log::info!("Terminal benchmark: {}", report);
+            }
+            self.benchmark_result = Some(result);
+        }
+
+        let mut run = false;
+        card(ui, |ui| {
+            section_header(ui, "Performance");
+            ui.label(RichText::new(
+                "Feeds a large block of terminal output through the parser and renderer \
+                 and reports throughput and frame times.",
+            ).color(colors::TEXT_MUTED).size(12.0));
+            ui.add_space(spacing::SM);
+
+            form_row(ui, |ui| {
+                labeled_input(ui, "Recorded output (optional)", &mut self.benchmark_corpus, "Built-in corpus");
+            });
+
+            ui.horizontal(|ui| {
+                if self.benchmark.is_some() {
+                    ui.spinner();
+                    ui.label(RichText::new("Running...").color(colors::TEXT_SECONDARY));
+                    ui.ctx().request_repaint_after(std::time::Duration::from_millis(200));
+                } else if secondary_button(ui, "Run benchmark").clicked() {
+                    run = true;
+                }
+            });
+
+            match &self.benchmark_result {
+                Some(Ok(report)) => {
+                    let ms = |d: std::time::Duration| format!("{:.2} ms", d.as_secs_f64() * 1000.0);
+                    egui::Grid::new("benchmark_report").num_columns(2).spacing([spacing::LG, spacing::XS]).show(ui, |ui| {
+                        let rows = [
+                            ("Data", format!("{:.1} MB in {:.2} s", report.bytes as f64 / 1e6, report.total_time.as_secs_f64())),
+                            ("Parsing", format!("{:.1} MB/s", report.parse_mb_per_sec())),
+                            ("With drawing", format!("{:.1} MB/s", report.total_mb_per_sec())),
+                            ("Frames", report.frames.count.to_string()),
+                            ("Frame time p50 / p95 / p99", format!("{} / {} / {}", ms(report.frames.p50), ms(report.frames.p95), ms(report.frames.p99))),
+                            ("Slowest frame", ms(report.frames.max)),
+                            ("Frames over 16.7 ms", report.frames.over_budget.to_string()),
+                        ];
+                        for (label, value) in rows {
+                            ui.label(RichText::new(label).color(colors::TEXT_SECONDARY));
+                            ui.label(RichText::new(value).color(colors::TEXT_PRIMARY).monospace());
+                            ui.end_row();
+                        }
+                    });
+                    if ui.small_button("Copy report").clicked() {
+                        ui.output_mut(|o| o.copied_text = report.to_string());
+                    }
+                }
+                Some(Err(e)) => {
+                    ui.label(RichText::new(e).color(colors::ERROR).size(12.0));
+                }
+                None => {}
+            }
+        });
+
+        if run {
+            let options = BenchmarkOptions { scrollback: self.scrollback_lines as usize, ..Default::default() };
+            let recording = self.benchmark_corpus.trim().to_string();
+            self.benchmark_result = None;
+            self.benchmark = Some(std::thread::spawn(move || {
+                let corpus = if recording.is_empty() {
+                    benchmark::corpus(options.corpus_bytes)
+                } else {
+                    let path = shellexpand::tilde(&recording).into_owned();
+                    benchmark::load_corpus(std::path::Path::new(&path), options.corpus_bytes)?
+                };
+                Ok(run_benchmark(&corpus, &options))
+            }));
+        }
+    }
+
+    fn render_triggers(&mut self, ui: &mut egui::Ui) {
+        card(ui, |ui| {
+            section_header(ui, "Highlighting & Triggers");
+
+            ui.label(RichText::new("Regular expressions matched against each line of output")
+                .color(colors::TEXT_MUTED)
+                .size(11.0));
+            ui.add_space(spacing::SM);
+
+            let mut remove = None;
+
+            for (idx, trigger) in self.output_triggers.iter_mut().enumerate() {
+                form_row(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        let mut changed = ui.checkbox(&mut trigger.enabled, "").changed();
+                        changed |= ui.add(egui::TextEdit::singleline(&mut trigger.name)
+                            .hint_text("Name")
+                            .desired_width(120.0)).changed();
+                        changed |= ui.add(egui::TextEdit::singleline(&mut trigger.pattern)
+                            .hint_text("Pattern")
+                            .code_editor()
+                            .desired_width(220.0)).changed();
+
+                        if ui.small_button("\u{1F5D1}").on_hover_text("Remove").clicked() {
+                            remove = Some(idx);
+                        }
+                        if changed {
+                            self.has_unsaved_changes = true;
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        let mut highlight = trigger.highlight.is_some();
+                        if ui.checkbox(&mut highlight, "Highlight").changed() {
+                            trigger.highlight = highlight.then_some([127, 29, 29]);
+                            self.has_unsaved_changes = true;
+                        }
+                        if let Some(color) = trigger.highlight.as_mut() {
+                            if ui.color_edit_button_srgb(color).changed() {
+                                self.has_unsaved_changes = true;
+                            }
+                        }
+                        let toggles = [
+                            ui.checkbox(&mut trigger.notify, "Notify").changed(),
+                            ui.checkbox(&mut trigger.mark_tab, "Mark tab").changed(),
+                            ui.checkbox(&mut trigger.case_sensitive, "Match case").changed(),
+                        ];
+                        if toggles.contains(&true) {
+                            self.has_unsaved_changes = true;
+                        }
+                    });
+
+                    if let Some(error) = trigger.validate() {
+                        ui.label(RichText::new(error).color(colors::ERROR).size(11.0));
+                    }
+                });
+            }
+
+            if let Some(idx) = remove {
+                self.output_triggers.remove(idx);
+                self.has_unsaved_changes = true;
+            }
+
+            ui.add_space(spacing::SM);
+
+            ui.horizontal(|ui| {
+                if primary_button(ui, "Add Trigger").clicked() {
+                    self.output_triggers.push(OutputTrigger::new("", ""));
+                    self.has_unsaved_changes = true;
+                }
+
+                if self.output_triggers.is_empty() {
+                    ui.add_space(spacing::SM);
+                    if secondary_button(ui, "Add Examples").clicked() {
+                        self.output_triggers = example_triggers();
+                        self.has_unsaved_changes = true;
+                    }
+                }
+            });
+        });
     }
 
     fn render_ssh(&mut self, ui: &mut egui::Ui) {
@@ -572,7 +848,9 @@ impl SettingsScreen {
         });
     }
 
-    fn render_security(&mut self, ui: &mut egui::Ui) {
+    fn render_security(&mut self, ui: &mut egui::Ui) -> Option<SettingsAction> {
+        let mut action = None;
+
         ui.heading(RichText::new("Security Settings").color(colors::TEXT_PRIMARY));
         ui.add_space(spacing::LG);
 
@@ -623,6 +901,79 @@ impl SettingsScreen {
                 .color(colors::WARNING)
                 .size(11.0));
         });
+
+        ui.add_space(spacing::MD);
+
+        card(ui, |ui| {
+            section_header(ui, "Trusted Host CAs");
+
+            ui.label(RichText::new("Hosts presenting a certificate signed by one of these CAs are accepted without a host key prompt")
+                .color(colors::TEXT_MUTED)
+                .size(11.0));
+            ui.add_space(spacing::SM);
+
+            if self.trusted_cas.is_empty() {
+                ui.label(RichText::new("No trusted CAs").color(colors::TEXT_SECONDARY));
+            }
+            for ca in &self.trusted_cas {
+                form_row(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.vertical(|ui| {
+                            ui.label(RichText::new(&ca.name).color(colors::TEXT_PRIMARY));
+                            ui.label(RichText::new(format!("{} · {}", ca.key_type, ca.host_patterns.join(", ")))
+                                .color(colors::TEXT_MUTED)
+                                .size(11.0));
+                        });
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if danger_button(ui, "Remove").clicked() {
+                                action = Some(SettingsAction::RemoveTrustedCa(ca.id.clone()));
+                            }
+                        });
+                    });
+                });
+            }
+
+            ui.add_space(spacing::SM);
+            ui.label(RichText::new("Add a CA (@cert-authority line from known_hosts)").color(colors::TEXT_PRIMARY));
+            ui.add(egui::TextEdit::singleline(&mut self.new_ca_entry)
+                .hint_text("@cert-authority *.example.com ssh-ed25519 AAAA... fleet CA")
+                .text_color(colors::TEXT_PRIMARY)
+                .desired_width(f32::INFINITY)
+                .margin(egui::Margin::symmetric(8.0, 6.0)));
+            if let Some(err) = &self.ca_error {
+                ui.label(RichText::new(err).color(colors::ERROR).size(11.0));
+            }
+            ui.add_space(spacing::XS);
+            if secondary_button(ui, "Trust CA").clicked() && !self.new_ca_entry.trim().is_empty() {
+                action = Some(SettingsAction::AddTrustedCa);
+            }
+        });
+
+        action
+    }
+
+    /// Reload the trusted host CA list
+    pub fn load_trusted_cas(&mut self, db: &Database) -> anyhow::Result<()> {
+        self.trusted_cas = db.list_trusted_cas()?;
+        Ok(())
+    }
+
+    /// Trust the CA entered in the security section
+    pub fn add_trusted_ca(&mut self, db: &Database) -> anyhow::Result<()> {
+        match crate::ssh::host_ca::trust_cert_authority_line(db, &self.new_ca_entry) {
+            Ok(()) => {
+                self.new_ca_entry.clear();
+                self.ca_error = None;
+            }
+            Err(e) => self.ca_error = Some(e.to_string()),
+        }
+        self.load_trusted_cas(db)
+    }
+
+    /// Stop trusting a host CA
+    pub fn remove_trusted_ca(&mut self, db: &Database, id: &str) -> anyhow::Result<()> {
+        db.remove_trusted_ca(id)?;
+        self.load_trusted_cas(db)
     }
 
     fn render_key_management(&mut self, ui: &mut egui::Ui) {
@@ -678,12 +1029,276 @@ impl SettingsScreen {
                 }
             });
         });
+
+        ui.add_space(spacing::LG);
+        self.render_key_health(ui);
+    }
+
+    /// Problems with the keys in ~/.ssh and those named in ~/.ssh/config
+    fn render_key_health(&mut self, ui: &mut egui::Ui) {
+        let reports = self.key_health.get_or_insert_with(key_health::scan);
+        let mut recheck = false;
+
+        card(ui, |ui| {
+            ui.horizontal(|ui| {
+                section_header(ui, "Key Health");
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if secondary_button(ui, "Check again").clicked() {
+                        recheck = true;
+                    }
+                });
+            });
+
+            if reports.is_empty() {
+                ui.label(RichText::new("No keys in ~/.ssh or referenced by ~/.ssh/config")
+                    .color(colors::TEXT_MUTED));
+                return;
+            }
+
+            for report in reports.iter() {
+                form_row(ui, |ui| {
+                    ui.vertical(|ui| {
+                        ui.horizontal(|ui| {
+                            let (icon, color) = if report.is_healthy() {
+                                ("\u{2714}", colors::SUCCESS)
+                            } else if report.problems.iter().any(|p| p.is_blocking()) {
+                                ("\u{2716}", colors::ERROR)
+                            } else {
+                                ("\u{26A0}", colors::WARNING)
+                            };
+                            ui.label(RichText::new(icon).color(color));
+                            ui.label(RichText::new(report.path.display().to_string()).color(colors::TEXT_PRIMARY).monospace());
+                            let kind = match (&report.key_type, report.bits) {
+                                (Some(kind), Some(bits)) => format!("{} {}", kind, bits),
+                                (Some(kind), None) => kind.clone(),
+                                _ => String::new(),
+                            };
+                            ui.label(RichText::new(kind).color(colors::TEXT_MUTED).size(11.0));
+                        });
+                        ui.label(RichText::new(report.sources.join(", ")).color(colors::TEXT_MUTED).size(11.0));
+                        for problem in &report.problems {
+                            let color = if problem.is_blocking() { colors::ERROR } else { colors::WARNING };
+                            ui.label(RichText::new(format!("\u{2022} {}", problem)).color(color).size(12.0));
+                        }
+                    });
+                });
+            }
+        });
+
+        if recheck {
+            self.key_health = Some(key_health::scan());
+        }
+    }
+
+    fn render_custom_actions(&mut self, ui: &mut egui::Ui) {
+        ui.heading(RichText::new("Custom Actions").color(colors::TEXT_PRIMARY));
+        ui.add_space(spacing::LG);
+
+        card(ui, |ui| {
+            section_header(ui, "Actions");
+
+            ui.label(RichText::new("Placeholders: %host, %port, %user, %path (remote file), %local_file")
+                .color(colors::TEXT_MUTED)
+                .size(11.0));
+            ui.add_space(spacing::SM);
+
+            let scopes = [ActionScope::Connection, ActionScope::File, ActionScope::Both];
+            let mut remove = None;
+
+            for (idx, custom) in self.custom_actions.iter_mut().enumerate() {
+                form_row(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        let name = egui::TextEdit::singleline(&mut custom.name)
+                            .hint_text("Name")
+                            .desired_width(150.0);
+                        let command = egui::TextEdit::singleline(&mut custom.command)
+                            .hint_text("Command")
+                            .code_editor()
+                            .desired_width(260.0);
+                        if ui.add(name).changed() | ui.add(command).changed() {
+                            self.has_unsaved_changes = true;
+                        }
+
+                        egui::ComboBox::from_id_source(("custom_action_scope", idx))
+                            .selected_text(custom.scope.to_string())
+                            .show_ui(ui, |ui| {
+                                for scope in scopes {
+                                    if ui.selectable_value(&mut custom.scope, scope, scope.to_string()).changed() {
+                                        self.has_unsaved_changes = true;
+                                    }
+                                }
+                            });
+
+                        if ui.small_button("\u{1F5D1}").on_hover_text("Remove").clicked() {
+                            remove = Some(idx);
+                        }
+                    });
+                });
+            }
+
+            if let Some(idx) = remove {
+                self.custom_actions.remove(idx);
+                self.has_unsaved_changes = true;
+            }
+
+            ui.add_space(spacing::SM);
+
+            ui.horizontal(|ui| {
+                if primary_button(ui, "Add Action").clicked() {
+                    self.custom_actions.push(CustomAction::new("", "", ActionScope::Connection));
+                    self.has_unsaved_changes = true;
+                }
+
+                if self.custom_actions.is_empty() {
+                    ui.add_space(spacing::SM);
+                    if secondary_button(ui, "Add Examples").clicked() {
+                        self.custom_actions = example_actions();
+                        self.has_unsaved_changes = true;
+                    }
+                }
+            });
+        });
     }
 
-    fn render_backup(&mut self, ui: &mut egui::Ui) {
+    fn render_backup(&mut self, ui: &mut egui::Ui) -> Option<SettingsAction> {
+        let mut action = None;
+
         ui.heading(RichText::new("Backup & Sync").color(colors::TEXT_PRIMARY));
         ui.add_space(spacing::LG);
 
+        card(ui, |ui| {
+            section_header(ui, "Sync Across Machines");
+
+            ui.label(RichText::new("Connections, custom themes and settings are merged with a copy on a target you control. SSH keys and passwords are never synced.")
+                .color(colors::TEXT_MUTED)
+                .size(11.0));
+            ui.add_space(spacing::SM);
+
+            let kinds = ["Off", "SFTP", "Git", "WebDAV"];
+            let current = match &self.sync_config.target {
+                None => "Off",
+                Some(SyncTarget::Sftp { .. }) => "SFTP",
+                Some(SyncTarget::Git { .. }) => "Git",
+                Some(SyncTarget::WebDav { .. }) => "WebDAV",
+            };
+            form_row(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("Sync Target").color(colors::TEXT_PRIMARY));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        egui::ComboBox::from_id_source("sync_target")
+                            .selected_text(RichText::new(current).color(colors::TEXT_PRIMARY))
+                            .width(200.0)
+                            .show_ui(ui, |ui: &mut egui::Ui| {
+                                for kind in kinds {
+                                    if ui.selectable_label(current == kind, kind).clicked() && current != kind {
+                                        self.sync_config.target = match kind {
+                                            "SFTP" => Some(SyncTarget::Sftp {
+                                                connection_id: String::new(),
+                                                directory: "~/.tabssh".to_string(),
+                                            }),
+                                            "Git" => Some(SyncTarget::Git { url: String::new(), branch: "main".to_string() }),
+                                            "WebDAV" => Some(SyncTarget::WebDav { url: String::new(), username: String::new() }),
+                                            _ => None,
+                                        };
+                                        self.has_unsaved_changes = true;
+                                    }
+                                }
+                            });
+                    });
+                });
+            });
+
+            let mut changed = false;
+            match &mut self.sync_config.target {
+                None => {}
+                Some(SyncTarget::Sftp { connection_id, directory }) => {
+                    let selected = self.sync_connections.iter()
+                        .find(|(id, _)| id == connection_id)
+                        .map(|(_, name)| name.clone())
+                        .unwrap_or_else(|| "Choose connection".to_string());
+                    form_row(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("Connection").color(colors::TEXT_PRIMARY));
+                            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                egui::ComboBox::from_id_source("sync_connection")
+                                    .selected_text(RichText::new(selected).color(colors::TEXT_PRIMARY))
+                                    .width(200.0)
+                                    .show_ui(ui, |ui: &mut egui::Ui| {
+                                        for (id, name) in &self.sync_connections {
+                                            if ui.selectable_label(connection_id == id, name).clicked() {
+                                                *connection_id = id.clone();
+                                                changed = true;
+                                            }
+                                        }
+                                    });
+                            });
+                        });
+                    });
+                    changed |= sync_text_row(ui, "Remote Directory", directory, false);
+                }
+                Some(SyncTarget::Git { url, branch }) => {
+                    changed |= sync_text_row(ui, "Repository URL", url, false);
+                    changed |= sync_text_row(ui, "Branch", branch, false);
+                }
+                Some(SyncTarget::WebDav { url, username }) => {
+                    changed |= sync_text_row(ui, "WebDAV URL", url, false);
+                    changed |= sync_text_row(ui, "Username", username, false);
+                    changed |= sync_text_row(ui, "Password", &mut self.sync_password, true);
+                }
+            }
+
+            if self.sync_config.target.is_some() {
+                let strategies = [ConflictStrategy::PreferNewer, ConflictStrategy::PreferLocal, ConflictStrategy::PreferRemote];
+                form_row(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(RichText::new("When both sides changed").color(colors::TEXT_PRIMARY));
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            egui::ComboBox::from_id_source("sync_strategy")
+                                .selected_text(RichText::new(self.sync_config.strategy.to_string()).color(colors::TEXT_PRIMARY))
+                                .width(200.0)
+                                .show_ui(ui, |ui: &mut egui::Ui| {
+                                    for strategy in strategies {
+                                        if ui.selectable_label(self.sync_config.strategy == strategy, strategy.to_string()).clicked() {
+                                            self.sync_config.strategy = strategy;
+                                            changed = true;
+                                        }
+                                    }
+                                });
+                        });
+                    });
+                });
+
+                ui.add_space(spacing::SM);
+                ui.horizontal(|ui| {
+                    let button = ui.add_enabled(!self.sync_running, egui::Button::new(
+                        if self.sync_running { "Syncing..." } else { "Sync Now" }
+                    ));
+                    if button.clicked() {
+                        action = Some(SettingsAction::SyncNow);
+                    }
+                    if self.sync_running {
+                        ui.spinner();
+                    }
+                });
+
+                match &self.sync_status {
+                    Some(Ok(summary)) => {
+                        ui.label(RichText::new(summary).color(colors::SUCCESS).size(11.0));
+                    }
+                    Some(Err(error)) => {
+                        ui.label(RichText::new(error).color(colors::ERROR).size(11.0));
+                    }
+                    None => {}
+                }
+            }
+
+            if changed {
+                self.has_unsaved_changes = true;
+            }
+        });
+
+        ui.add_space(spacing::MD);
+
         card(ui, |ui| {
             section_header(ui, "Automatic Backup");
 
@@ -747,11 +1362,49 @@ impl SettingsScreen {
                 .color(colors::TEXT_MUTED)
                 .size(11.0));
         });
+
+        action
+    }
+
+    /// Persist the sync configuration, moving any entered WebDAV password to the keychain
+    pub fn save_sync_config(&mut self, db: &Database) -> anyhow::Result<()> {
+        self.sync_config.save(db)?;
+        if let Some(SyncTarget::WebDav { url, .. }) = &self.sync_config.target {
+            if !self.sync_password.is_empty() {
+                store_webdav_password(url, &self.sync_password)?;
+                self.sync_password.clear();
+            }
+        }
+        Ok(())
     }
 }
 
+fn sync_text_row(ui: &mut egui::Ui, label: &str, value: &mut String, password: bool) -> bool {
+    let mut changed = false;
+    form_row(ui, |ui| {
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(label).color(colors::TEXT_PRIMARY));
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let input = egui::TextEdit::singleline(value)
+                    .password(password)
+                    .text_color(colors::TEXT_PRIMARY)
+                    .desired_width(260.0)
+                    .margin(egui::Margin::symmetric(8.0, 6.0));
+                changed = ui.add(input).changed();
+            });
+        });
+    });
+    changed
+}
+
 /// Actions from the settings screen
 pub enum SettingsAction {
     Save,
     Reset,
+    /// Run a sync with the configured target now
+    SyncNow,
+    /// Trust the CA in `new_ca_entry`
+    AddTrustedCa,
+    /// Stop trusting the CA with this id
+    RemoveTrustedCa(String),
 }
@@ -1,23 +1,148 @@
 //! Settings screen UI
 
 use egui::{Context, Ui};
-use crate::storage::settings::{Settings, CursorStyle, BellStyle};
+use crate::config::keymap::{self, Keymap, CUSTOM_PRESET};
+use crate::platform::transparency::TransparencySupport;
+use crate::platform::PlatformManager;
+use crate::sftp::format_file_size;
+use crate::storage::retention::{self, LogRetention, RetentionOutcome, StorageCategory};
+use crate::storage::settings::{Settings, PrivacyCover};
+use crate::storage::settings_profile::{ProfileFormat, SettingsProfile};
+use crate::terminal::status::{self, SegmentAction};
+use crate::utils::undo::UndoHistory;
 
 pub struct SettingsScreen {
     settings: Settings,
     modified: bool,
+    history: UndoHistory<Settings>,
+    /// Saved connections (id, name) the drop-down terminal can open
+    profiles: Vec<(String, String)>,
+    /// Names of the built-in and custom themes
+    themes: Vec<String>,
+    /// What the desktop allows for opacity and blur
+    transparency: Option<TransparencySupport>,
+    /// Whether Touch ID / Windows Hello can be offered
+    biometric_available: bool,
+    /// Whether a master password is set, and a new one being typed
+    master_password_set: bool,
+    new_master_password: String,
+    confirm_master_password: String,
+    /// Asking whether to save before leaving
+    confirm_close: bool,
+    /// Leave once the save the user chose has been handed out
+    close_after_save: bool,
+    /// Names of the settings profiles saved on this machine
+    saved_profiles: Vec<String>,
+    new_profile_name: String,
+    /// What the data directory holds, read when the Storage section is first shown
+    storage: Option<Vec<StorageCategory>>,
+    /// What the last cleanup did, or why it failed
+    cleanup_result: Option<String>,
 }
 
 impl SettingsScreen {
     pub fn new(settings: Settings) -> Self {
         Self {
+            history: UndoHistory::new(settings.clone()),
             settings,
             modified: false,
+            profiles: Vec::new(),
+            themes: [
+                "Default Dark", "Dracula", "Solarized Dark", "Solarized Light",
+                "Nord", "Monokai", "Gruvbox Dark", "One Dark", "Tokyo Night"
+            ].map(String::from).to_vec(),
+            transparency: None,
+            biometric_available: false,
+            master_password_set: false,
+            new_master_password: String::new(),
+            confirm_master_password: String::new(),
+            confirm_close: false,
+            close_after_save: false,
+            saved_profiles: Vec::new(),
+            new_profile_name: String::new(),
+            storage: None,
+            cleanup_result: None,
         }
     }
+
+    /// Outcome of a [`SettingsAction::CleanUpStorage`]
+    pub fn set_cleanup_result(&mut self, result: anyhow::Result<RetentionOutcome>) {
+        self.cleanup_result = Some(match result {
+            Ok(outcome) => outcome.to_string(),
+            Err(e) => format!("Cleanup failed: {}", e),
+        });
+        self.storage = None;
+    }
+
+    /// Saved profile names to switch between, e.g. `SettingsProfile::list_saved`
+    pub fn set_saved_profiles(&mut self, names: Vec<String>) {
+        self.saved_profiles = names;
+    }
+
+    /// Load an imported or saved profile into the editor; it takes effect
+    /// once the user saves
+    pub fn apply_profile(&mut self, profile: &SettingsProfile) {
+        self.settings = profile.applied_to(&self.settings);
+        self.modified = true;
+    }
+
+    /// Saved connections as (id, name), offered for the drop-down terminal
+    pub fn set_profiles(&mut self, profiles: Vec<(String, String)>) {
+        self.profiles = profiles;
+    }
+
+    /// Themes to choose from, e.g. `ThemeManager::list_themes`
+    pub fn set_themes(&mut self, themes: Vec<String>) {
+        self.themes = themes;
+    }
+
+    /// Explain opacity and blur limits of this desktop next to the controls
+    pub fn set_transparency_support(&mut self, support: TransparencySupport) {
+        self.transparency = Some(support);
+    }
+
+    /// What the lock screen can unlock with on this machine
+    pub fn set_lock_support(&mut self, master_password_set: bool, biometric_available: bool) {
+        self.master_password_set = master_password_set;
+        self.biometric_available = biometric_available;
+    }
+
+    /// Settings as edited so far, for previewing before they are saved
+    pub fn preview(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Whether there are edits that were not saved yet
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.modified
+    }
+
+    /// Call before navigating away or closing the window. Returns true when
+    /// the screen can go right away; otherwise the user is asked first and
+    /// the answer comes back as `SettingsAction::Close`.
+    pub fn request_close(&mut self) -> bool {
+        if self.modified {
+            self.confirm_close = true;
+        }
+        !self.modified
+    }
     
     pub fn render(&mut self, ctx: &Context, ui: &mut Ui) -> Option<SettingsAction> {
         let mut action = None;
+
+        if std::mem::take(&mut self.close_after_save) {
+            return Some(SettingsAction::Close);
+        }
+
+        let save_shortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::S);
+        if ui.input_mut(|i| i.consume_shortcut(&save_shortcut)) && self.modified {
+            action = Some(SettingsAction::Save(self.settings.clone()));
+            self.modified = false;
+        }
+        if let Some(settings) = self.history.handle_keys(ui) {
+            self.settings = settings;
+            self.modified = true;
+        }
         
         ui.heading("Settings");
         ui.separator();
@@ -39,6 +164,35 @@ impl SettingsScreen {
                 if ui.checkbox(&mut self.settings.restore_previous_sessions, "Restore previous sessions").changed() {
                     self.modified = true;
                 }
+
+                if ui.checkbox(&mut self.settings.check_for_updates, "Check for updates automatically")
+                    .on_hover_text("Asks the release feed once a day; nothing is installed without asking")
+                    .changed()
+                {
+                    self.modified = true;
+                }
+
+                if ui.checkbox(&mut self.settings.crash_reports, "Save crash reports").changed() {
+                    self.modified = true;
+                }
+                ui.label(egui::RichText::new(
+                    "After a crash, saves the version, OS, backtrace and last log lines on this computer, \
+                     with host names, user names, addresses and home paths masked. Nothing is sent: \
+                     on the next start you can read the report and choose to file it as an issue."
+                ).small().weak());
+
+                if let Some(dir) = crate::platform::portable::dir() {
+                    ui.label(egui::RichText::new(format!("Portable mode: all data is kept in {}", dir.display()))
+                        .small()
+                        .weak());
+                }
+
+                if ui.checkbox(&mut self.settings.lock_tab_titles, "Lock tab titles")
+                    .on_hover_text("New tabs ignore titles set by the remote; unlock a tab from its menu")
+                    .changed()
+                {
+                    self.modified = true;
+                }
             });
             
             ui.separator();
@@ -67,7 +221,36 @@ impl SettingsScreen {
                         self.modified = true;
                     }
                 });
+
+                ui.horizontal(|ui| {
+                    ui.label("Scrollback memory per tab:");
+                    let memory = egui::DragValue::new(&mut self.settings.scrollback_memory_mb)
+                        .speed(4)
+                        .clamp_range(8..=4096)
+                        .suffix(" MiB");
+                    if ui.add(memory)
+                        .on_hover_text("The oldest lines are dropped once a tab's scrollback takes this much")
+                        .changed()
+                    {
+                        self.modified = true;
+                    }
+                });
                 
+                ui.horizontal(|ui| {
+                    if ui.checkbox(&mut self.settings.annotate_long_commands, "Note the duration of commands that run")
+                        .on_hover_text("Needs shell integration (OSC 133 marks) on the host")
+                        .changed()
+                    {
+                        self.modified = true;
+                    }
+                    let seconds = egui::DragValue::new(&mut self.settings.long_command_seconds)
+                        .clamp_range(1..=3600)
+                        .suffix(" s or longer");
+                    if ui.add_enabled(self.settings.annotate_long_commands, seconds).changed() {
+                        self.modified = true;
+                    }
+                });
+
                 if ui.checkbox(&mut self.settings.cursor_blink, "Cursor blink").changed() {
                     self.modified = true;
                 }
@@ -82,19 +265,128 @@ impl SettingsScreen {
                     egui::ComboBox::from_id_source("theme_select")
                         .selected_text(&self.settings.selected_theme)
                         .show_ui(ui, |ui| {
-                            let themes = vec![
-                                "Default Dark", "Dracula", "Solarized Dark", "Solarized Light",
-                                "Nord", "Monokai", "Gruvbox Dark", "One Dark", "Tokyo Night"
-                            ];
-                            for theme in themes {
-                                if ui.selectable_value(&mut self.settings.selected_theme, theme.to_string(), theme).changed() {
+                            for theme in &self.themes {
+                                if ui.selectable_value(&mut self.settings.selected_theme, theme.clone(), theme).changed() {
                                     self.modified = true;
                                 }
                             }
                         });
+                    if ui.button("Edit colors...").on_hover_text("Open the theme editor; built-in themes are copied first").clicked() {
+                        action = Some(SettingsAction::EditTheme(self.settings.selected_theme.clone()));
+                    }
                 });
+
+                ui.horizontal(|ui| {
+                    ui.label("Background opacity:");
+                    if ui.add(egui::Slider::new(&mut self.settings.window_opacity, 50..=100).suffix("%")).changed() {
+                        self.modified = true;
+                    }
+                });
+                ui.add_enabled_ui(self.settings.window_opacity < 100, |ui| {
+                    if ui.checkbox(&mut self.settings.background_blur, "Blur what shows through").changed() {
+                        self.modified = true;
+                    }
+                });
+                if let Some(limitation) = self.transparency.and_then(|support| support.limitation()) {
+                    ui.label(egui::RichText::new(limitation).weak().small());
+                }
             });
             
+            ui.separator();
+
+            // Status bar
+            ui.collapsing("Status Bar", |ui| {
+                ui.label(egui::RichText::new("Segments under each terminal, in order").weak());
+                let mut layout = status::normalize(&self.settings.status_bar);
+                let before = layout.clone();
+                let mut move_up = None;
+                egui::Grid::new("status_bar_segments").num_columns(4).show(ui, |ui| {
+                    let count = layout.len();
+                    for (i, config) in layout.iter_mut().enumerate() {
+                        ui.checkbox(&mut config.visible, config.segment.label());
+                        ui.horizontal(|ui| {
+                            if ui.add_enabled(i > 0, egui::Button::new("\u{2191}").small()).clicked() {
+                                move_up = Some(i);
+                            }
+                            if ui.add_enabled(i + 1 < count, egui::Button::new("\u{2193}").small()).clicked() {
+                                move_up = Some(i + 1);
+                            }
+                        });
+                        ui.label("On click:");
+                        egui::ComboBox::from_id_source(("status_segment_action", i))
+                            .selected_text(config.on_click.label())
+                            .show_ui(ui, |ui| {
+                                for action in SegmentAction::ALL {
+                                    ui.selectable_value(&mut config.on_click, action, action.label());
+                                }
+                            });
+                        ui.end_row();
+                    }
+                });
+                if let Some(i) = move_up {
+                    layout.swap(i - 1, i);
+                }
+                if layout != before {
+                    self.settings.status_bar = layout;
+                    self.modified = true;
+                }
+                if ui.button("Reset to default").clicked() && self.settings.status_bar != status::default_layout() {
+                    self.settings.status_bar = status::default_layout();
+                    self.modified = true;
+                }
+            });
+
+            ui.separator();
+
+            // Drop-down terminal
+            ui.collapsing("Drop-down Terminal", |ui| {
+                let quake = &mut self.settings.quake;
+                if ui.checkbox(&mut quake.enabled, "Summon a drop-down terminal with a global hotkey").changed() {
+                    self.modified = true;
+                }
+
+                ui.add_enabled_ui(quake.enabled, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Hotkey:");
+                        if ui.add(egui::TextEdit::singleline(&mut quake.hotkey).desired_width(120.0)).changed() {
+                            self.modified = true;
+                        }
+                        ui.label(egui::RichText::new("e.g. Ctrl+` or Alt+Space").weak());
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Opens:");
+                        let selected = quake.profile_id.as_ref()
+                            .and_then(|id| self.profiles.iter().find(|(pid, _)| pid == id))
+                            .map_or("Local shell", |(_, name)| name.as_str());
+                        egui::ComboBox::from_id_source("quake_profile")
+                            .selected_text(selected)
+                            .show_ui(ui, |ui| {
+                                if ui.selectable_value(&mut quake.profile_id, None, "Local shell").changed() {
+                                    self.modified = true;
+                                }
+                                for (id, name) in &self.profiles {
+                                    if ui.selectable_value(&mut quake.profile_id, Some(id.clone()), name).changed() {
+                                        self.modified = true;
+                                    }
+                                }
+                            });
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Height (% of screen):");
+                        if ui.add(egui::Slider::new(&mut quake.height_percent, 20..=100)).changed() {
+                            self.modified = true;
+                        }
+                    });
+                });
+            });
+
+            ui.separator();
+
+            // Keyboard
+            ui.collapsing("Keyboard Shortcuts", |ui| self.render_keymap(ui));
+
             ui.separator();
             
             // Connection
@@ -129,6 +421,19 @@ impl SettingsScreen {
                 if ui.checkbox(&mut self.settings.compression, "Enable compression").changed() {
                     self.modified = true;
                 }
+
+                if ui.checkbox(&mut self.settings.preserve_upload_attributes, "Preserve mode and modification time on SFTP uploads")
+                    .changed()
+                {
+                    self.modified = true;
+                }
+
+                if ui.checkbox(&mut self.settings.watch_local_directory, "Refresh the local SFTP pane when files change")
+                    .on_hover_text("Turn off if watching network drives is slow; the pane can still be refreshed by hand")
+                    .changed()
+                {
+                    self.modified = true;
+                }
             });
             
             ui.separator();
@@ -144,17 +449,116 @@ impl SettingsScreen {
                     }
                 });
                 
+                ui.horizontal(|ui| {
+                    ui.label("Cover idle terminals:");
+                    egui::ComboBox::from_id_source("privacy_cover")
+                        .selected_text(self.settings.privacy_cover.to_string())
+                        .show_ui(ui, |ui| {
+                            for cover in PrivacyCover::ALL {
+                                if ui.selectable_value(&mut self.settings.privacy_cover, cover, cover.to_string()).changed() {
+                                    self.modified = true;
+                                }
+                            }
+                        });
+                    let minutes = egui::DragValue::new(&mut self.settings.privacy_idle_minutes)
+                        .clamp_range(1..=240)
+                        .prefix("after ")
+                        .suffix(" min");
+                    if ui.add_enabled(self.settings.privacy_cover != PrivacyCover::Off, minutes)
+                        .on_hover_text("Only the terminal is covered, once it has been unfocused this long. Click it or press a key to show it again.")
+                        .changed()
+                    {
+                        self.modified = true;
+                    }
+                });
+
                 if ui.checkbox(&mut self.settings.remember_passwords, "Remember passwords").changed() {
                     self.modified = true;
                 }
+                if crate::platform::portable::is_portable() {
+                    ui.label(egui::RichText::new(
+                        "Portable mode: remembered secrets are encrypted with the master password \
+                         instead of going into this computer's keychain. Set one to save passwords."
+                    ).small().weak());
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label(if self.master_password_set { "Change master password:" } else { "Master password:" });
+                    ui.add(egui::TextEdit::singleline(&mut self.new_master_password).password(true).desired_width(140.0));
+                    ui.add(egui::TextEdit::singleline(&mut self.confirm_master_password)
+                        .password(true)
+                        .hint_text("Repeat")
+                        .desired_width(140.0));
+                    let matches = !self.new_master_password.is_empty()
+                        && self.new_master_password == self.confirm_master_password;
+                    if ui.add_enabled(matches, egui::Button::new("Set")).clicked() {
+                        action = Some(SettingsAction::SetMasterPassword(Some(std::mem::take(&mut self.new_master_password))));
+                        self.confirm_master_password.clear();
+                        self.master_password_set = true;
+                    }
+                    if self.master_password_set && ui.button("Remove").clicked() {
+                        action = Some(SettingsAction::SetMasterPassword(None));
+                        self.master_password_set = false;
+                    }
+                });
+
+                let biometric = crate::platform::biometric::name();
+                ui.add_enabled_ui(self.biometric_available, |ui| {
+                    if ui.checkbox(&mut self.settings.biometric_unlock, format!("Unlock with {}", biometric))
+                        .on_disabled_hover_text(format!("{} is not set up on this machine", biometric))
+                        .changed()
+                    {
+                        self.modified = true;
+                    }
+                });
+                if ui.add_enabled(
+                    self.settings.biometric_unlock,
+                    egui::Checkbox::new(&mut self.settings.require_password_for_key_export, "Require master password for key export"),
+                ).changed() {
+                    self.modified = true;
+                }
                 
                 if ui.checkbox(&mut self.settings.strict_host_key_checking, "Strict host key checking").changed() {
                     self.modified = true;
                 }
+
+                if ui.checkbox(&mut self.settings.allow_session_sharing, "Allow sharing terminals")
+                    .on_hover_text("Viewers see the screen read-only and need the share's token")
+                    .changed()
+                {
+                    self.modified = true;
+                }
+                if ui.add_enabled(
+                    self.settings.allow_session_sharing,
+                    egui::Checkbox::new(&mut self.settings.share_on_lan, "Let other machines on the network watch"),
+                )
+                .on_hover_text("Shares are sent unencrypted; without this only this machine can connect")
+                .changed()
+                {
+                    self.modified = true;
+                }
             });
             
             ui.separator();
             
+            // Profiles
+            ui.collapsing("Settings Profiles", |ui| {
+                if let Some(profile_action) = self.render_profiles(ui) {
+                    action = Some(profile_action);
+                }
+            });
+
+            ui.separator();
+
+            // Storage
+            ui.collapsing("Storage", |ui| {
+                if let Some(storage_action) = self.render_storage(ui) {
+                    action = Some(storage_action);
+                }
+            });
+
+            ui.separator();
+            
             // Advanced
             ui.collapsing("Advanced", |ui| {
                 ui.horizontal(|ui| {
@@ -185,17 +589,269 @@ impl SettingsScreen {
                 self.settings = Settings::default();
                 self.modified = true;
             }
+
+            if ui.add_enabled(self.history.can_undo(), egui::Button::new("↶ Undo"))
+                .on_hover_text("Ctrl+Z")
+                .clicked()
+            {
+                self.undo();
+            }
+            if ui.add_enabled(self.history.can_redo(), egui::Button::new("↷ Redo"))
+                .on_hover_text("Ctrl+Shift+Z")
+                .clicked()
+            {
+                self.redo();
+            }
             
             if self.modified {
                 ui.colored_label(egui::Color32::YELLOW, "● Modified");
             }
         });
+
+        self.history.record(&self.settings);
+
+        if self.confirm_close {
+            let mut open = true;
+            egui::Window::new("Unsaved Changes")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+                .open(&mut open)
+                .show(ctx, |ui| {
+                    ui.label("Your settings have changes that were not saved.");
+                    ui.add_space(8.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("💾 Save").clicked() {
+                            action = Some(SettingsAction::Save(self.settings.clone()));
+                            self.modified = false;
+                            self.close_after_save = true;
+                            self.confirm_close = false;
+                        }
+                        if ui.button("Discard").clicked() {
+                            action = Some(SettingsAction::Close);
+                            self.modified = false;
+                            self.confirm_close = false;
+                        }
+                        if ui.button("Keep editing").clicked() {
+                            self.confirm_close = false;
+                        }
+                    });
+                });
+            if !open {
+                self.confirm_close = false;
+            }
+        }
         
         action
     }
 }
 
-#[derive(Debug, Clone)]
+impl SettingsScreen {
+    /// Preset picker and one editable shortcut per action
+    fn render_keymap(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Preset:");
+            egui::ComboBox::from_id_source("keymap_preset")
+                .selected_text(&self.settings.keymap.preset)
+                .show_ui(ui, |ui| {
+                    for (name, description) in keymap::PRESETS {
+                        let selected = self.settings.keymap.preset == name;
+                        if ui.selectable_label(selected, name).on_hover_text(description).clicked() && !selected {
+                            if let Some(preset) = Keymap::preset(name) {
+                                self.settings.keymap = preset;
+                                self.modified = true;
+                            }
+                        }
+                    }
+                    if self.settings.keymap.preset == CUSTOM_PRESET {
+                        ui.selectable_label(true, CUSTOM_PRESET);
+                    }
+                });
+        });
+
+        egui::Grid::new("keymap_bindings").num_columns(2).striped(true).show(ui, |ui| {
+            for (action, label) in keymap::ACTIONS {
+                ui.label(label);
+                let mut spec = self.settings.keymap.binding(action).unwrap_or_default().to_string();
+                let valid = spec.is_empty() || keymap::parse(&spec).is_some();
+                let edit = egui::TextEdit::singleline(&mut spec)
+                    .desired_width(140.0)
+                    .hint_text("Unbound")
+                    .text_color_opt((!valid).then_some(egui::Color32::LIGHT_RED));
+                if ui.add(edit).changed() {
+                    self.settings.keymap.set(action, &spec);
+                    self.modified = true;
+                }
+                ui.end_row();
+            }
+        });
+        ui.label(egui::RichText::new("e.g. Ctrl+Shift+T, Alt+L or Cmd+, (Cmd only works on macOS)").weak());
+        for problem in self.settings.keymap.problems() {
+            ui.colored_label(egui::Color32::YELLOW, format!("\u{26A0} {}", problem));
+        }
+    }
+
+    /// Export, import and named profiles
+    fn render_profiles(&mut self, ui: &mut Ui) -> Option<SettingsAction> {
+        let mut action = None;
+        ui.label(egui::RichText::new(
+            "All settings and shortcuts in one file, to take to another machine. \
+             Passwords and connections are not included."
+        ).weak());
+        ui.horizontal(|ui| {
+            for format in [ProfileFormat::Json, ProfileFormat::Toml] {
+                if ui.button(format!("Export as {}...", format.extension().to_uppercase())).clicked() {
+                    action = Some(SettingsAction::ExportProfile(format, self.settings.clone()));
+                }
+            }
+            if ui.button("Import...").on_hover_text("Review the imported settings, then save").clicked() {
+                action = Some(SettingsAction::ImportProfile);
+            }
+        });
+
+        ui.add_space(8.0);
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.new_profile_name)
+                .hint_text("Profile name")
+                .desired_width(160.0));
+            let name = self.new_profile_name.trim().to_string();
+            if ui.add_enabled(!name.is_empty(), egui::Button::new("Save current as profile")).clicked() {
+                if !self.saved_profiles.contains(&name) {
+                    self.saved_profiles.push(name.clone());
+                }
+                action = Some(SettingsAction::SaveProfile(name, self.settings.clone()));
+                self.new_profile_name.clear();
+            }
+        });
+        let mut deleted = None;
+        for name in &self.saved_profiles {
+            ui.horizontal(|ui| {
+                ui.label(name);
+                if ui.small_button("Switch").clicked() {
+                    action = Some(SettingsAction::SwitchProfile(name.clone()));
+                }
+                if ui.small_button("Delete").clicked() {
+                    action = Some(SettingsAction::DeleteProfile(name.clone()));
+                    deleted = Some(name.clone());
+                }
+            });
+        }
+        if let Some(name) = deleted {
+            self.saved_profiles.retain(|n| *n != name);
+        }
+        action
+    }
+
+    /// Default log retention, what the data directory holds and a cleanup button
+    fn render_storage(&mut self, ui: &mut Ui) -> Option<SettingsAction> {
+        let mut action = None;
+        ui.label(egui::RichText::new(
+            "Limits on session logs and traffic captures, applied at startup. \
+             Profiles can set their own for their host's logs."
+        ).weak());
+        if retention_limits(ui, &mut self.settings.log_retention) {
+            self.modified = true;
+        }
+
+        ui.add_space(8.0);
+        let storage = self.storage.get_or_insert_with(|| {
+            PlatformManager::get_data_directory()
+                .map(|dir| retention::storage_usage(&dir))
+                .unwrap_or_default()
+        });
+        egui::Grid::new("storage_usage").num_columns(3).striped(true).show(ui, |ui| {
+            for category in storage.iter() {
+                ui.label(category.label).on_hover_text(category.path.display().to_string());
+                ui.label(format!("{} files", category.files));
+                ui.label(format_file_size(category.bytes));
+                ui.end_row();
+            }
+            let total: u64 = storage.iter().map(|category| category.bytes).sum();
+            ui.label(egui::RichText::new("Total").strong());
+            ui.label("");
+            ui.label(egui::RichText::new(format_file_size(total)).strong());
+            ui.end_row();
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Refresh").clicked() {
+                self.storage = None;
+            }
+            if ui.button("Clean up now")
+                .on_hover_text("Apply these limits and the profiles' own to the logs right away")
+                .clicked()
+            {
+                action = Some(SettingsAction::CleanUpStorage(self.settings.log_retention));
+                self.cleanup_result = None;
+            }
+            if let Some(result) = &self.cleanup_result {
+                ui.label(egui::RichText::new(result).weak());
+            }
+        });
+        action
+    }
+
+    fn undo(&mut self) {
+        if let Some(settings) = self.history.undo() {
+            self.settings = settings;
+            self.modified = true;
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(settings) = self.history.redo() {
+            self.settings = settings;
+            self.modified = true;
+        }
+    }
+}
+
+/// No `Debug`: `SetMasterPassword` carries the password
+#[derive(Clone)]
 pub enum SettingsAction {
     Save(Settings),
+    /// Leave the screen; any save the user wanted was sent just before
+    Close,
+    /// Open the theme editor on this theme
+    EditTheme(String),
+    /// Store a new master password, or remove it with `None`
+    SetMasterPassword(Option<String>),
+    /// Ask where to save these settings as a profile, then `SettingsProfile::export`
+    ExportProfile(ProfileFormat, Settings),
+    /// Ask for a profile file, then `apply_profile` what `SettingsProfile::import` read
+    ImportProfile,
+    /// Save these settings under the name with `SettingsProfile::save_named`
+    SaveProfile(String, Settings),
+    /// Load the named profile and `apply_profile` it
+    SwitchProfile(String),
+    DeleteProfile(String),
+    /// Apply this retention and the profiles' own with `retention::enforce`,
+    /// then report back through `set_cleanup_result`
+    CleanUpStorage(LogRetention),
+}
+
+/// One optional limit per row, checkbox to switch it on; true when changed
+pub(super) fn retention_limits(ui: &mut Ui, retention: &mut LogRetention) -> bool {
+    let mut changed = false;
+    let rows = [
+        ("Keep at most", " files", LogRetention::SUGGESTED.max_files, &mut retention.max_files),
+        ("Delete after", " days", LogRetention::SUGGESTED.max_age_days, &mut retention.max_age_days),
+        ("Compress after", " days", LogRetention::SUGGESTED.compress_after_days, &mut retention.compress_after_days),
+    ];
+    for (label, suffix, suggested, limit) in rows {
+        ui.horizontal(|ui| {
+            let mut on = limit.is_some();
+            if ui.checkbox(&mut on, label).changed() {
+                *limit = if on { suggested } else { None };
+                changed = true;
+            }
+            let mut value = limit.unwrap_or(suggested.unwrap_or(1));
+            let drag = egui::DragValue::new(&mut value).clamp_range(1..=100_000).suffix(suffix);
+            if ui.add_enabled(on, drag).changed() {
+                *limit = Some(value);
+                changed = true;
+            }
+        });
+    }
+    changed
 }
@@ -2,28 +2,92 @@
 
 #![allow(dead_code)]
 
-use crate::sftp::{FileEntry, FileType, TransferDirection, TransferState, TransferTask, format_file_size};
+use crate::sftp::{
+    ArchiveFormat, FileEntry, FileType, FolderSize, LocalWatcher, PostAction, RelayMethod, TransferDirection, TransferOptions, TransferSchedule,
+    TransferState, TransferTask, format_file_size,
+};
 use crate::ui::components::{colors, spacing};
+use crate::platform::PlatformManager;
+use crate::plugins::ContextActionContribution;
+use crate::utils::custom_actions::{ActionContext, CustomAction};
+use crate::utils::helpers::sftp_url;
+use crate::storage::bookmarks;
+use crate::storage::database::{Database, TransferRecord};
+use crate::utils::helpers::format_permissions;
 use eframe::egui::{self, RichText};
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
 use uuid::Uuid;
 
+#[cfg(target_os = "macos")]
+const REVEAL_LABEL: &str = "Reveal in Finder";
+#[cfg(target_os = "windows")]
+const REVEAL_LABEL: &str = "Show in Explorer";
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const REVEAL_LABEL: &str = "Open containing folder";
+
+/// Memory slot holding the remote entries being dragged between browsers
+const RELAY_DRAG_ID: &str = "sftp_relay_drag";
+
+/// Remote entries dragged out of a browser, for dropping on another host
+#[derive(Debug, Clone)]
+struct RelayDrag {
+    connection_id: Uuid,
+    paths: Vec<String>,
+}
+
 /// Actions emitted by the SFTP browser
 #[derive(Debug, Clone)]
 pub enum SftpBrowserAction {
     NavigateLocal(PathBuf),
     NavigateRemote(String),
     Download(String),
+    /// Open a remote file in the read-only viewer, via `AppState::open_file_viewer`
+    View(String),
     Upload(PathBuf),
     CreateRemoteDir(String),
     CreateLocalDir(PathBuf),
     DeleteRemote(String),
     DeleteLocal(PathBuf),
     Rename(String, String),
+    /// Copy remote paths of another session into a directory of this one
+    RelayFrom { source: Uuid, paths: Vec<String>, dest_dir: String },
+    Compress(String, ArchiveFormat),
+    Extract(String),
+    DiskUsage(String),
+    /// Save or forget a remote path bookmark for this connection
+    AddBookmark(String),
+    RemoveBookmark(String),
+    /// Walk a remote folder with `sftp::folder_size`, stopping when `cancel`
+    /// is set, and report back through `set_folder_size`
+    CalculateFolderSize { path: String, cancel: Arc<AtomicBool> },
+    /// Open the transfer history tab (`TabType::TransferHistory`)
+    ShowHistory,
+    RunCustomAction(CustomAction, ActionContext),
+    /// Plugin id, action id, remote path
+    RunPluginAction(String, String, String),
     Refresh,
     Close,
 }
 
+/// Start of a queued transfer, for its history entry
+struct TransferStart {
+    started: Instant,
+    source: String,
+    destination: String,
+}
+
+/// On-demand size of a remote folder, kept for the browsing session
+pub enum FolderSizeState {
+    /// Walk running; setting the flag cancels it
+    Calculating(Arc<AtomicBool>),
+    Done(FolderSize),
+    Failed(String),
+}
+
 /// Sorting options for file lists
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SortColumn {
@@ -44,19 +108,42 @@ pub struct SftpBrowserScreen {
     connection_id: Uuid,
     connection_name: String,
 
+    remote_user: String,
+    remote_host: String,
+    remote_port: u16,
+
     local_path: PathBuf,
     local_entries: Vec<FileEntry>,
     local_selected: Option<usize>,
     local_sort: (SortColumn, SortOrder),
     local_loading: bool,
+    /// Reload the local pane when its directory changes
+    watch_local: bool,
+    local_watcher: Option<LocalWatcher>,
+    /// Directory the watcher was last set up for, even if that failed
+    watched_path: Option<PathBuf>,
 
     remote_path: String,
     remote_entries: Vec<FileEntry>,
     remote_selected: Option<usize>,
     remote_sort: (SortColumn, SortOrder),
     remote_loading: bool,
+    /// Bookmarked remote paths of this connection
+    bookmarks: Vec<String>,
 
     transfers: Vec<TransferTask>,
+    /// When and between which paths each transfer started, for its history entry
+    transfer_starts: HashMap<Uuid, TransferStart>,
+    /// Saved connection being browsed, recorded with each transfer so
+    /// `AppState::rerun_transfer` can find a session for it again
+    profile_id: Option<String>,
+    transfer_options: HashMap<Uuid, TransferOptions>,
+    /// Schedule label and post-action summary per transfer, formatted when
+    /// the options are set instead of on every frame
+    transfer_notes: HashMap<Uuid, (String, Option<String>)>,
+    options_form: Option<TransferOptionsForm>,
+    /// Uploads without options of their own preserve mode and mtime
+    preserve_uploads: bool,
     show_hidden: bool,
     show_transfers: bool,
 
@@ -68,6 +155,15 @@ pub struct SftpBrowserScreen {
     rename_new_name: String,
     show_rename_dialog: bool,
 
+    archive_status: Option<String>,
+    relay_status: Option<(RelayMethod, u64, u64)>,
+    /// Folder sizes by remote path
+    folder_sizes: HashMap<String, FolderSizeState>,
+    /// Remote path and entry shown in the properties dialog
+    properties: Option<(String, FileEntry)>,
+    custom_actions: Vec<CustomAction>,
+    plugin_actions: Vec<(String, ContextActionContribution)>,
+
     error_message: Option<String>,
 }
 
@@ -77,6 +173,134 @@ enum NewDirTarget {
     Remote,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScheduleKind {
+    Now,
+    AfterQueue,
+    AtTime,
+}
+
+/// Editable copy of a transfer's schedule and post-actions
+struct TransferOptionsForm {
+    id: Uuid,
+    is_upload: bool,
+    /// Connected as root, so ownership can be set
+    is_root: bool,
+    schedule: ScheduleKind,
+    time: String,
+    delete_source: bool,
+    preserve: bool,
+    chown: bool,
+    uid: String,
+    gid: String,
+    chmod: bool,
+    chmod_mode: String,
+    run_command: bool,
+    command: String,
+    notify: bool,
+}
+
+impl TransferOptionsForm {
+    fn from_options(id: Uuid, is_upload: bool, is_root: bool, options: &TransferOptions) -> Self {
+        let mut form = Self {
+            id,
+            is_upload,
+            is_root,
+            schedule: ScheduleKind::Now,
+            time: String::new(),
+            delete_source: false,
+            preserve: false,
+            chown: false,
+            uid: String::new(),
+            gid: String::new(),
+            chmod: false,
+            chmod_mode: "644".to_string(),
+            run_command: false,
+            command: String::new(),
+            notify: false,
+        };
+
+        match &options.schedule {
+            TransferSchedule::Immediately => {}
+            TransferSchedule::AfterQueue => form.schedule = ScheduleKind::AfterQueue,
+            TransferSchedule::At(at) => {
+                form.schedule = ScheduleKind::AtTime;
+                form.time = at.format("%H:%M").to_string();
+            }
+        }
+
+        for action in &options.post_actions {
+            match action {
+                PostAction::DeleteSource => form.delete_source = true,
+                PostAction::PreserveAttributes => form.preserve = true,
+                PostAction::Chown { uid, gid } => {
+                    form.chown = true;
+                    form.uid = uid.map(|id| id.to_string()).unwrap_or_default();
+                    form.gid = gid.map(|id| id.to_string()).unwrap_or_default();
+                }
+                PostAction::Chmod(mode) => {
+                    form.chmod = true;
+                    form.chmod_mode = format!("{:o}", mode);
+                }
+                PostAction::RemoteCommand(cmd) => {
+                    form.run_command = true;
+                    form.command = cmd.clone();
+                }
+                PostAction::Notify => form.notify = true,
+            }
+        }
+
+        form
+    }
+
+    fn to_options(&self) -> Result<TransferOptions, String> {
+        let schedule = match self.schedule {
+            ScheduleKind::Now => TransferSchedule::Immediately,
+            ScheduleKind::AfterQueue => TransferSchedule::AfterQueue,
+            ScheduleKind::AtTime => TransferSchedule::at_time_of_day(&self.time, chrono::Local::now())
+                .ok_or_else(|| "Start time must be HH:MM".to_string())?,
+        };
+
+        let mut post_actions = Vec::new();
+        // An explicit mode below overrides the preserved one
+        if self.preserve && self.is_upload {
+            post_actions.push(PostAction::PreserveAttributes);
+        }
+        if self.chown && self.is_upload && self.is_root {
+            let id = |text: &str, what: &str| -> Result<Option<u32>, String> {
+                let text = text.trim();
+                if text.is_empty() {
+                    return Ok(None);
+                }
+                text.parse().map(Some).map_err(|_| format!("{} must be a numeric id", what))
+            };
+            let (uid, gid) = (id(&self.uid, "Owner")?, id(&self.gid, "Group")?);
+            if uid.is_some() || gid.is_some() {
+                post_actions.push(PostAction::Chown { uid, gid });
+            }
+        }
+        if self.chmod && self.is_upload {
+            let mode = u32::from_str_radix(self.chmod_mode.trim(), 8)
+                .ok()
+                .filter(|m| *m <= 0o7777)
+                .ok_or_else(|| "Mode must be octal, e.g. 644".to_string())?;
+            post_actions.push(PostAction::Chmod(mode));
+        }
+        if self.run_command && !self.command.trim().is_empty() {
+            post_actions.push(PostAction::RemoteCommand(self.command.trim().to_string()));
+        }
+        // Deleting the source goes last so a failed chmod or command leaves it in place
+        if self.delete_source {
+            post_actions.push(PostAction::DeleteSource);
+        }
+        if self.notify {
+            post_actions.push(PostAction::Notify);
+        }
+
+        Ok(TransferOptions { schedule, post_actions })
+    }
+}
+
 impl SftpBrowserScreen {
     pub fn new(connection_id: Uuid, connection_name: String) -> Self {
         let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"));
@@ -84,17 +308,30 @@ impl SftpBrowserScreen {
         Self {
             connection_id,
             connection_name,
+            remote_user: String::new(),
+            remote_host: String::new(),
+            remote_port: 22,
             local_path: home,
             local_entries: Vec::new(),
             local_selected: None,
             local_sort: (SortColumn::Name, SortOrder::Ascending),
             local_loading: false,
+            watch_local: true,
+            local_watcher: None,
+            watched_path: None,
             remote_path: "~".to_string(),
             remote_entries: Vec::new(),
             remote_selected: None,
             remote_sort: (SortColumn::Name, SortOrder::Ascending),
             remote_loading: false,
+            bookmarks: Vec::new(),
             transfers: Vec::new(),
+            transfer_starts: HashMap::new(),
+            profile_id: None,
+            transfer_options: HashMap::new(),
+            transfer_notes: HashMap::new(),
+            options_form: None,
+            preserve_uploads: false,
             show_hidden: false,
             show_transfers: false,
             new_dir_name: String::new(),
@@ -103,6 +340,12 @@ impl SftpBrowserScreen {
             rename_old_name: String::new(),
             rename_new_name: String::new(),
             show_rename_dialog: false,
+            archive_status: None,
+            relay_status: None,
+            folder_sizes: HashMap::new(),
+            properties: None,
+            custom_actions: Vec::new(),
+            plugin_actions: Vec::new(),
             error_message: None,
         }
     }
@@ -119,23 +362,244 @@ impl SftpBrowserScreen {
         self.remote_selected = None;
     }
 
+    /// Start a streamed listing of `path`; its batches arrive through
+    /// `append_remote_entries` and `finish_remote_listing` sorts them
+    pub fn begin_remote_listing(&mut self, path: String) {
+        self.remote_path = path;
+        self.remote_entries.clear();
+        self.remote_selected = None;
+        self.remote_loading = true;
+    }
+
+    /// One batch of a streamed listing, shown in arrival order until it ends
+    pub fn append_remote_entries(&mut self, mut batch: Vec<FileEntry>) {
+        if self.show_hidden {
+            batch.retain(|e| !e.name.starts_with('.'));
+        }
+        self.remote_entries.extend(batch);
+    }
+
+    pub fn finish_remote_listing(&mut self) {
+        let selected = self.remote_selected.and_then(|i| self.remote_entries.get(i)).map(|e| e.name.clone());
+        let entries = std::mem::take(&mut self.remote_entries);
+        self.remote_entries = self.sort_entries(entries, self.remote_sort, self.show_hidden);
+        self.remote_selected = selected.and_then(|name| self.remote_entries.iter().position(|e| e.name == name));
+        self.remote_loading = false;
+    }
+
     pub fn set_local_path(&mut self, path: PathBuf) {
         self.local_path = path;
     }
 
+    /// Bookmarked remote paths, from `PathBookmark::for_connection`
+    pub fn set_bookmarks(&mut self, bookmarks: Vec<String>) {
+        self.bookmarks = bookmarks;
+    }
+
+    /// Bookmark dropdown beside the remote path
+    fn show_bookmarks_menu(&mut self, ui: &mut egui::Ui) -> Option<SftpBrowserAction> {
+        let mut action = None;
+        let current = bookmarks::normalize(&self.remote_path);
+        let bookmarked = self.bookmarks.contains(&current);
+
+        ui.menu_button(if bookmarked { "\u{2605}" } else { "\u{2606}" }, |ui| {
+            if bookmarked {
+                if ui.button("Remove bookmark").clicked() {
+                    self.bookmarks.retain(|path| *path != current);
+                    action = Some(SftpBrowserAction::RemoveBookmark(current.clone()));
+                    ui.close_menu();
+                }
+            } else if ui.button("Bookmark this folder").clicked() {
+                self.bookmarks.push(current.clone());
+                self.bookmarks.sort();
+                action = Some(SftpBrowserAction::AddBookmark(current.clone()));
+                ui.close_menu();
+            }
+            if !self.bookmarks.is_empty() {
+                ui.separator();
+            }
+            for path in &self.bookmarks {
+                if ui.selectable_label(*path == current, path).clicked() {
+                    action = Some(SftpBrowserAction::NavigateRemote(path.clone()));
+                    ui.close_menu();
+                }
+            }
+        })
+        .response
+        .on_hover_text("Bookmarks");
+
+        action
+    }
+
+    /// Default from the `watch_local_directory` setting
+    pub fn set_watch_local(&mut self, enabled: bool) {
+        self.watch_local = enabled;
+    }
+
+    /// Keep a watcher on the local directory and reload the pane once its
+    /// changes settle
+    fn poll_local_watcher(&mut self, ctx: &egui::Context) -> Option<SftpBrowserAction> {
+        if !self.watch_local {
+            self.local_watcher = None;
+            self.watched_path = None;
+            return None;
+        }
+        if self.watched_path.as_ref() != Some(&self.local_path) {
+            let repaint = ctx.clone();
+            self.local_watcher = LocalWatcher::new(&self.local_path, move || repaint.request_repaint())
+                .map_err(|e| log::warn!("Live refresh unavailable: {:#}", e))
+                .ok();
+            self.watched_path = Some(self.local_path.clone());
+        }
+
+        let watcher = self.local_watcher.as_mut()?;
+        if let Some(after) = watcher.next_check() {
+            ctx.request_repaint_after(after);
+        }
+        (watcher.poll() && !self.local_loading).then(|| SftpBrowserAction::NavigateLocal(self.local_path.clone()))
+    }
+
     pub fn set_remote_path(&mut self, path: String) {
         self.remote_path = path;
     }
 
+    /// Set the user/host the remote pane is browsing, used to build sftp:// URLs
+    pub fn set_remote_target(&mut self, user: String, host: String, port: u16) {
+        self.remote_user = user;
+        self.remote_host = host;
+        self.remote_port = port;
+    }
+
+    /// User-defined actions offered in the file context menus
+    pub fn set_custom_actions(&mut self, actions: Vec<CustomAction>) {
+        self.custom_actions = actions;
+    }
+
+    /// File actions contributed by enabled plugins, as `(plugin id, action)`
+    pub fn set_plugin_actions(&mut self, actions: Vec<(String, ContextActionContribution)>) {
+        self.plugin_actions = actions;
+    }
+
+    fn action_context(&self, path: Option<String>, local_file: Option<PathBuf>) -> ActionContext {
+        ActionContext {
+            host: self.remote_host.clone(),
+            port: self.remote_port,
+            user: self.remote_user.clone(),
+            path,
+            local_file,
+        }
+    }
+
+    /// Absolute remote path of an entry in the current remote directory
+    fn remote_entry_path(&self, name: &str) -> String {
+        if self.remote_path.ends_with('/') {
+            format!("{}{}", self.remote_path, name)
+        } else {
+            format!("{}/{}", self.remote_path, name)
+        }
+    }
+
     pub fn set_error(&mut self, error: Option<String>) {
         self.error_message = error;
     }
 
+    /// Show the current stage of a running compress/extract job, or clear it
+    pub fn set_archive_status(&mut self, status: Option<String>) {
+        self.archive_status = status;
+    }
+
+    /// Show the method and combined progress (done, total bytes) of an
+    /// incoming relay from another session, or clear it
+    pub fn set_relay_status(&mut self, status: Option<(RelayMethod, u64, u64)>) {
+        self.relay_status = status;
+    }
+
+    /// Result of a `CalculateFolderSize` walk; dropped if it was cancelled
+    pub fn set_folder_size(&mut self, path: &str, result: Result<FolderSize, String>) {
+        let running = matches!(self.folder_sizes.get(path),
+            Some(FolderSizeState::Calculating(cancel)) if !cancel.load(Ordering::Relaxed));
+        if running {
+            let state = match result {
+                Ok(size) => FolderSizeState::Done(size),
+                Err(e) => FolderSizeState::Failed(e),
+            };
+            self.folder_sizes.insert(path.to_string(), state);
+        }
+    }
+
+    fn calculate_folder_size(&mut self, path: String) -> SftpBrowserAction {
+        self.cancel_folder_size(&path);
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.folder_sizes.insert(path.clone(), FolderSizeState::Calculating(cancel.clone()));
+        SftpBrowserAction::CalculateFolderSize { path, cancel }
+    }
+
+    fn cancel_folder_size(&mut self, path: &str) {
+        if let Some(FolderSizeState::Calculating(cancel)) = self.folder_sizes.remove(path) {
+            cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Saved connection the session was opened from, if any
+    pub fn set_profile_id(&mut self, id: Option<String>) {
+        self.profile_id = id;
+    }
+
+    /// Queue a transfer of `file_name` between the current local and remote directories
     pub fn add_transfer(&mut self, task: TransferTask) {
+        let local = self.local_path.join(&task.file_name).to_string_lossy().into_owned();
+        let remote = self.remote_entry_path(&task.file_name);
+        let (source, destination) = match task.direction {
+            TransferDirection::Upload => (local, remote),
+            TransferDirection::Download => (remote, local),
+        };
+        self.transfer_starts.insert(task.id, TransferStart { started: Instant::now(), source, destination });
         self.transfers.push(task);
         self.show_transfers = true;
     }
 
+    /// Schedule and post-actions configured for a transfer
+    pub fn transfer_options(&self, id: Uuid) -> TransferOptions {
+        if let Some(options) = self.transfer_options.get(&id) {
+            return options.clone();
+        }
+        let is_upload = self.transfers.iter()
+            .any(|t| t.id == id && t.direction == TransferDirection::Upload);
+        if is_upload {
+            TransferOptions::upload_default(self.preserve_uploads)
+        } else {
+            TransferOptions::default()
+        }
+    }
+
+    /// Default from the `preserve_upload_attributes` setting
+    pub fn set_preserve_uploads(&mut self, preserve: bool) {
+        self.preserve_uploads = preserve;
+    }
+
+    pub fn set_transfer_options(&mut self, id: Uuid, options: TransferOptions) {
+        self.transfer_notes.insert(id, transfer_notes(&options));
+        self.transfer_options.insert(id, options);
+    }
+
+    /// Pending transfers whose schedule allows them to start now, in queue order
+    pub fn due_transfers(&self, now: chrono::DateTime<chrono::Local>) -> Vec<Uuid> {
+        let running = self.transfers.iter().any(|t| t.state == TransferState::InProgress);
+        let pending: Vec<_> = self.transfers.iter()
+            .filter(|t| t.state == TransferState::Pending)
+            .map(|t| (t.id, self.transfer_options(t.id).schedule))
+            .collect();
+
+        let queue_busy = running || pending.iter().any(|(_, schedule)| {
+            *schedule != TransferSchedule::AfterQueue && schedule.is_due(now, false)
+        });
+
+        pending.into_iter()
+            .filter(|(_, schedule)| schedule.is_due(now, queue_busy))
+            .map(|(id, _)| id)
+            .collect()
+    }
+
     pub fn update_transfer_progress(&mut self, id: Uuid, transferred: u64) {
         if let Some(task) = self.transfers.iter_mut().find(|t| t.id == id) {
             task.transferred_bytes = transferred;
@@ -148,9 +612,35 @@ impl SftpBrowserScreen {
             task.state = if success {
                 TransferState::Completed
             } else {
-                TransferState::Failed(error.unwrap_or_else(|| "Unknown error".to_string()))
+                TransferState::Failed(error.clone().unwrap_or_else(|| "Unknown error".to_string()))
             };
         }
+        self.record_transfer(id, success, error);
+    }
+
+    /// Add a finished transfer to the history
+    fn record_transfer(&mut self, id: Uuid, success: bool, error: Option<String>) {
+        let Some(start) = self.transfer_starts.remove(&id) else { return };
+        let Some(task) = self.transfers.iter().find(|t| t.id == id) else { return };
+        let record = TransferRecord {
+            id: id.to_string(),
+            connection_id: self.profile_id.clone(),
+            host: self.remote_host.clone(),
+            direction: match task.direction {
+                TransferDirection::Upload => "upload",
+                TransferDirection::Download => "download",
+            }.to_string(),
+            source: start.source,
+            destination: start.destination,
+            size: task.transferred_bytes,
+            duration_ms: start.started.elapsed().as_millis() as u64,
+            success,
+            error,
+            finished_at: chrono::Utc::now().to_rfc3339(),
+        };
+        if let Err(e) = Database::open().and_then(|db| db.add_transfer_record(&record)) {
+            log::warn!("Could not record transfer of {}: {}", record.source, e);
+        }
     }
 
     fn sort_entries(
@@ -195,9 +685,17 @@ impl SftpBrowserScreen {
     pub fn show(&mut self, ui: &mut egui::Ui) -> Option<SftpBrowserAction> {
         let mut action: Option<SftpBrowserAction> = None;
 
+        // A drag that ended outside every browser leaves nothing behind
+        if ui.input(|i| !i.pointer.any_down() && !i.pointer.any_released()) {
+            ui.ctx().data_mut(|d| d.remove::<RelayDrag>(egui::Id::new(RELAY_DRAG_ID)));
+        }
+
         let toolbar_action = self.show_toolbar(ui);
         if action.is_none() { action = toolbar_action; }
 
+        let watch_action = self.poll_local_watcher(ui.ctx());
+        if action.is_none() { action = watch_action; }
+
         if let Some(ref error) = self.error_message.clone() {
             ui.add_space(spacing::SM);
             ui.horizontal(|ui| {
@@ -253,6 +751,84 @@ impl SftpBrowserScreen {
             if action.is_none() { action = rename_action; }
         }
 
+        if self.options_form.is_some() {
+            self.show_transfer_options_window(ui);
+        }
+
+        if self.properties.is_some() {
+            let properties_action = self.show_properties_window(ui);
+            if action.is_none() { action = properties_action; }
+        }
+
+        action
+    }
+
+    fn show_properties_window(&mut self, ui: &mut egui::Ui) -> Option<SftpBrowserAction> {
+        let (path, entry) = self.properties.clone()?;
+        let mut action = None;
+        let mut open = true;
+        let is_directory = entry.file_type == FileType::Directory;
+
+        egui::Window::new(format!("{} Properties", entry.name))
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ui.ctx(), |ui| {
+                egui::Grid::new("sftp_properties").num_columns(2).spacing([spacing::MD, spacing::XS]).show(ui, |ui| {
+                    let row = |ui: &mut egui::Ui, label: &str, value: String| {
+                        ui.label(RichText::new(label).color(colors::TEXT_MUTED));
+                        ui.label(value);
+                        ui.end_row();
+                    };
+                    row(ui, "Path", path.clone());
+                    row(ui, "Type", format!("{:?}", entry.file_type));
+                    if is_directory {
+                        ui.label(RichText::new("Size").color(colors::TEXT_MUTED));
+                        match self.folder_sizes.get(&path) {
+                            Some(FolderSizeState::Done(size)) => {
+                                ui.label(format!("{} ({} bytes)", format_file_size(size.bytes), size.bytes));
+                                ui.end_row();
+                                row(ui, "Contains", size.describe_counts());
+                            }
+                            Some(FolderSizeState::Calculating(_)) => {
+                                ui.horizontal(|ui| {
+                                    ui.spinner();
+                                    if ui.small_button("Cancel").clicked() {
+                                        self.cancel_folder_size(&path);
+                                    }
+                                });
+                                ui.end_row();
+                            }
+                            state => {
+                                let error = match state {
+                                    Some(FolderSizeState::Failed(e)) => Some(e.clone()),
+                                    _ => None,
+                                };
+                                ui.horizontal(|ui| {
+                                    if let Some(e) = error {
+                                        ui.label(RichText::new(e).color(colors::ERROR));
+                                    }
+                                    if ui.small_button("Calculate").clicked() {
+                                        action = Some(self.calculate_folder_size(path.clone()));
+                                    }
+                                });
+                                ui.end_row();
+                            }
+                        }
+                    } else {
+                        row(ui, "Size", format!("{} ({} bytes)", format_file_size(entry.size), entry.size));
+                    }
+                    row(ui, "Permissions", format_permissions(entry.permissions));
+                    row(ui, "Owner", format!("{}:{}", entry.owner, entry.group));
+                    if let Some(modified) = entry.modified {
+                        row(ui, "Modified", modified.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string());
+                    }
+                });
+            });
+
+        if !open {
+            self.properties = None;
+        }
         action
     }
 
@@ -284,6 +860,29 @@ impl SftpBrowserScreen {
             };
             ui.checkbox(&mut self.show_transfers, &transfers_label);
 
+            if ui.button("History").clicked() {
+                action = Some(SftpBrowserAction::ShowHistory);
+            }
+
+            if let Some(status) = &self.archive_status {
+                ui.separator();
+                ui.spinner();
+                ui.label(RichText::new(status).color(colors::TEXT_MUTED));
+            }
+
+            if let Some((method, done, total)) = self.relay_status {
+                ui.separator();
+                ui.spinner();
+                ui.label(RichText::new(method.to_string()).color(colors::TEXT_MUTED));
+                if total > 0 {
+                    ui.add(
+                        egui::ProgressBar::new(done as f32 / total as f32)
+                            .desired_width(120.0)
+                            .text(format!("{} / {}", format_file_size(done), format_file_size(total)))
+                    );
+                }
+            }
+
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                 if ui.button("Close").clicked() {
                     action = Some(SftpBrowserAction::Close);
@@ -296,6 +895,8 @@ impl SftpBrowserScreen {
 
     fn show_local_panel_content(&mut self, ui: &mut egui::Ui, _width: f32, _height: f32) -> Option<SftpBrowserAction> {
         let mut action = None;
+        // Local only, so shown right here rather than handed to the owner
+        let mut reveal_error = None;
 
         egui::Frame::group(ui.style())
             .fill(colors::BG_SECONDARY)
@@ -322,6 +923,9 @@ impl SftpBrowserScreen {
                                     action = Some(SftpBrowserAction::NavigateLocal(home));
                                 }
                             }
+
+                            ui.toggle_value(&mut self.watch_local, "\u{1F441}")
+                                .on_hover_text("Refresh when files change; turn off for slow network drives");
                         });
                     });
 
@@ -349,6 +953,7 @@ impl SftpBrowserScreen {
                                         &entry.name,
                                         &entry.file_type,
                                         entry.size,
+                                        None,
                                         selected,
                                     );
 
@@ -356,6 +961,24 @@ impl SftpBrowserScreen {
                                         self.local_selected = Some(idx);
                                     }
 
+                                    response.context_menu(|ui| {
+                                        let file_path = self.local_path.join(&entry.name);
+                                        if ui.button("Copy path").clicked() {
+                                            ui.output_mut(|o| o.copied_text = file_path.to_string_lossy().to_string());
+                                            ui.close_menu();
+                                        }
+                                        let ctx = self.action_context(None, Some(file_path.clone()));
+                                        if ui.button(REVEAL_LABEL).clicked() {
+                                            if let Err(e) = PlatformManager::reveal_in_file_manager(&file_path) {
+                                                reveal_error = Some(format!("Could not show {}: {}", file_path.display(), e));
+                                            }
+                                            ui.close_menu();
+                                        }
+                                        if let Some(custom) = custom_actions_menu(ui, &self.custom_actions, ctx) {
+                                            action = Some(custom);
+                                        }
+                                    });
+
                                     if response.double_clicked() {
                                         match entry.file_type {
                                             FileType::Directory => {
@@ -375,13 +998,16 @@ impl SftpBrowserScreen {
                 });
             });
 
+        if reveal_error.is_some() {
+            self.error_message = reveal_error;
+        }
         action
     }
 
     fn show_remote_panel_content(&mut self, ui: &mut egui::Ui, _width: f32, _height: f32) -> Option<SftpBrowserAction> {
         let mut action = None;
 
-        egui::Frame::group(ui.style())
+        let panel = egui::Frame::group(ui.style())
             .fill(colors::BG_SECONDARY)
             .show(ui, |ui| {
                 ui.vertical(|ui| {
@@ -402,33 +1028,59 @@ impl SftpBrowserScreen {
                             if ui.small_button("~").on_hover_text("Home directory").clicked() {
                                 action = Some(SftpBrowserAction::NavigateRemote("~".to_string()));
                             }
+
+                            if ui.small_button("\u{1F4CA}").on_hover_text("Disk usage").clicked() {
+                                action = Some(SftpBrowserAction::DiskUsage(self.remote_path.clone()));
+                            }
                         });
                     });
 
-                    ui.label(
-                        RichText::new(&self.remote_path)
-                            .small()
-                            .color(colors::TEXT_MUTED)
-                    );
+                    ui.horizontal(|ui| {
+                        if let Some(bookmark_action) = self.show_bookmarks_menu(ui) {
+                            action = Some(bookmark_action);
+                        }
+                        ui.label(
+                            RichText::new(&self.remote_path)
+                                .small()
+                                .color(colors::TEXT_MUTED)
+                        );
+                    });
 
                     ui.add_space(spacing::XS);
                     ui.separator();
 
+                    // Only the visible rows are laid out, so huge directories stay smooth
+                    let row_height = ui.spacing().interact_size.y + 4.0;
+                    let loading_more = self.remote_loading && !self.remote_entries.is_empty();
+                    let rows = self.remote_entries.len() + usize::from(loading_more);
                     egui::ScrollArea::vertical()
                         .auto_shrink([false, false])
-                        .show(ui, |ui| {
-                            if self.remote_loading {
+                        .show_rows(ui, row_height, rows.max(1), |ui, visible| {
+                            if self.remote_loading && self.remote_entries.is_empty() {
                                 ui.spinner();
                             } else if self.remote_entries.is_empty() {
                                 ui.label(RichText::new("Empty directory").color(colors::TEXT_MUTED));
                             } else {
-                                for (idx, entry) in self.remote_entries.iter().enumerate() {
+                                // Applied after the loop, which borrows the entries
+                                let mut size_request: Option<(String, bool)> = None;
+                                let mut properties_request = None;
+                                for idx in visible {
+                                    let Some(entry) = self.remote_entries.get(idx) else {
+                                        ui.horizontal(|ui| {
+                                            ui.add(egui::Spinner::new().size(10.0));
+                                            ui.label(RichText::new(format!("Loading more\u{2026} {} so far", self.remote_entries.len()))
+                                                .small()
+                                                .color(colors::TEXT_MUTED));
+                                        });
+                                        continue;
+                                    };
                                     let selected = self.remote_selected == Some(idx);
                                     let response = self.show_file_entry(
                                         ui,
                                         &entry.name,
                                         &entry.file_type,
                                         entry.size,
+                                        self.folder_sizes.get(&self.remote_entry_path(&entry.name)),
                                         selected,
                                     );
 
@@ -436,6 +1088,19 @@ impl SftpBrowserScreen {
                                         self.remote_selected = Some(idx);
                                     }
 
+                                    if response.drag_started() {
+                                        let drag = RelayDrag {
+                                            connection_id: self.connection_id,
+                                            paths: vec![self.remote_entry_path(&entry.name)],
+                                        };
+                                        ui.ctx().data_mut(|d| d.insert_temp(egui::Id::new(RELAY_DRAG_ID), drag));
+                                    }
+                                    if response.dragged() {
+                                        egui::show_tooltip_at_pointer(ui.ctx(), egui::Id::new(RELAY_DRAG_ID), |ui| {
+                                            ui.label(format!("📄 {}", entry.name));
+                                        });
+                                    }
+
                                     if response.double_clicked() {
                                         match entry.file_type {
                                             FileType::Directory => {
@@ -448,11 +1113,44 @@ impl SftpBrowserScreen {
                                         }
                                     }
 
+                                    let entry_path = self.remote_entry_path(&entry.name);
+                                    let entry_ctx = self.action_context(Some(entry_path.clone()), None);
                                     response.context_menu(|ui| {
                                         if ui.button("Download").clicked() {
                                             action = Some(SftpBrowserAction::Download(entry.name.clone()));
                                             ui.close_menu();
                                         }
+                                        if entry.file_type == FileType::File && ui.button("View").clicked() {
+                                            action = Some(SftpBrowserAction::View(entry_path.clone()));
+                                            ui.close_menu();
+                                        }
+                                        ui.separator();
+                                        if ui.button("Copy path").clicked() {
+                                            let path = entry_path.clone();
+                                            ui.output_mut(|o| o.copied_text = path);
+                                            ui.close_menu();
+                                        }
+                                        if ui.add_enabled(!self.remote_host.is_empty(), egui::Button::new("Copy URL")).clicked() {
+                                            let path = entry_path.clone();
+                                            ui.output_mut(|o| {
+                                                o.copied_text = sftp_url(&self.remote_user, &self.remote_host, self.remote_port, &path)
+                                            });
+                                            ui.close_menu();
+                                        }
+                                        if let Some(custom) = custom_actions_menu(ui, &self.custom_actions, entry_ctx.clone()) {
+                                            action = Some(custom);
+                                        }
+                                        for (plugin_id, plugin_action) in &self.plugin_actions {
+                                            if ui.button(&plugin_action.label).clicked() {
+                                                action = Some(SftpBrowserAction::RunPluginAction(
+                                                    plugin_id.clone(),
+                                                    plugin_action.id.clone(),
+                                                    entry_path.clone(),
+                                                ));
+                                                ui.close_menu();
+                                            }
+                                        }
+                                        ui.separator();
                                         if ui.button("Rename").clicked() {
                                             self.rename_old_name = entry.name.clone();
                                             self.rename_new_name = entry.name.clone();
@@ -460,17 +1158,77 @@ impl SftpBrowserScreen {
                                             ui.close_menu();
                                         }
                                         ui.separator();
+                                        ui.menu_button("Compress", |ui| {
+                                            for format in [ArchiveFormat::TarGz, ArchiveFormat::Zip] {
+                                                if ui.button(format!("to {}", format.extension())).clicked() {
+                                                    action = Some(SftpBrowserAction::Compress(entry.name.clone(), format));
+                                                    ui.close_menu();
+                                                }
+                                            }
+                                        });
+                                        if ArchiveFormat::detect(&entry.name).is_some()
+                                            && ui.button("Extract here").clicked()
+                                        {
+                                            action = Some(SftpBrowserAction::Extract(entry.name.clone()));
+                                            ui.close_menu();
+                                        }
+                                        if entry.file_type == FileType::Directory && ui.button("Disk usage").clicked() {
+                                            action = Some(SftpBrowserAction::DiskUsage(entry_path.clone()));
+                                            ui.close_menu();
+                                        }
+                                        if entry.file_type == FileType::Directory {
+                                            let state = self.folder_sizes.get(&entry_path);
+                                            let calculating = matches!(state, Some(FolderSizeState::Calculating(_)));
+                                            let label = match state {
+                                                Some(FolderSizeState::Calculating(_)) => "Cancel size calculation",
+                                                Some(FolderSizeState::Done(_)) => "Recalculate size",
+                                                _ => "Calculate size",
+                                            };
+                                            if ui.button(label).clicked() {
+                                                size_request = Some((entry_path.clone(), !calculating));
+                                                ui.close_menu();
+                                            }
+                                        }
+                                        if ui.button("Properties").clicked() {
+                                            properties_request = Some((entry_path.clone(), entry.clone()));
+                                            ui.close_menu();
+                                        }
+                                        ui.separator();
                                         if ui.button("Delete").clicked() {
                                             action = Some(SftpBrowserAction::DeleteRemote(entry.name.clone()));
                                             ui.close_menu();
                                         }
                                     });
                                 }
+
+                                match size_request {
+                                    Some((path, true)) => action = Some(self.calculate_folder_size(path)),
+                                    Some((path, false)) => self.cancel_folder_size(&path),
+                                    None => {}
+                                }
+                                if properties_request.is_some() {
+                                    self.properties = properties_request;
+                                }
                             }
                         });
                 });
             });
 
+        let drag = ui.ctx().data(|d| d.get_temp::<RelayDrag>(egui::Id::new(RELAY_DRAG_ID)));
+        if let Some(drag) = drag.filter(|d| d.connection_id != self.connection_id) {
+            if ui.rect_contains_pointer(panel.response.rect) {
+                ui.painter().rect_stroke(panel.response.rect, 4.0, egui::Stroke::new(2.0, colors::PRIMARY));
+                if ui.input(|i| i.pointer.any_released()) {
+                    ui.ctx().data_mut(|d| d.remove::<RelayDrag>(egui::Id::new(RELAY_DRAG_ID)));
+                    action = Some(SftpBrowserAction::RelayFrom {
+                        source: drag.connection_id,
+                        paths: drag.paths,
+                        dest_dir: self.remote_path.clone(),
+                    });
+                }
+            }
+        }
+
         action
     }
 
@@ -480,6 +1238,7 @@ impl SftpBrowserScreen {
         name: &str,
         file_type: &FileType,
         size: u64,
+        folder: Option<&FolderSizeState>,
         selected: bool,
     ) -> egui::Response {
         let icon = match file_type {
@@ -506,18 +1265,34 @@ impl SftpBrowserScreen {
                     ui.label(RichText::new(name).color(colors::TEXT_PRIMARY));
 
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        if matches!(file_type, FileType::File) {
-                            ui.label(
-                                RichText::new(format_file_size(size))
-                                    .small()
-                                    .color(colors::TEXT_MUTED)
-                            );
+                        match (file_type, folder) {
+                            (FileType::File, _) => {
+                                ui.label(
+                                    RichText::new(format_file_size(size))
+                                        .small()
+                                        .color(colors::TEXT_MUTED)
+                                );
+                            }
+                            (FileType::Directory, Some(FolderSizeState::Done(total))) => {
+                                ui.label(
+                                    RichText::new(format_file_size(total.bytes))
+                                        .small()
+                                        .color(colors::TEXT_MUTED)
+                                ).on_hover_text(total.describe());
+                            }
+                            (FileType::Directory, Some(FolderSizeState::Calculating(_))) => {
+                                ui.add(egui::Spinner::new().size(10.0));
+                            }
+                            (FileType::Directory, Some(FolderSizeState::Failed(e))) => {
+                                ui.label(RichText::new("?").small().color(colors::ERROR)).on_hover_text(e);
+                            }
+                            _ => {}
                         }
                     });
                 });
         });
 
-        response.response.interact(egui::Sense::click())
+        response.response.interact(egui::Sense::click_and_drag())
     }
 
     fn show_transfers_panel(&mut self, ui: &mut egui::Ui) {
@@ -538,13 +1313,17 @@ impl SftpBrowserScreen {
 
                 ui.separator();
 
+                // Rows share one height so thousands of queued transfers only
+                // cost the ones on screen
+                let row_height = ui.spacing().interact_size.y;
                 egui::ScrollArea::vertical()
                     .max_height(100.0)
-                    .show(ui, |ui| {
+                    .show_rows(ui, row_height, self.transfers.len().max(1), |ui, visible| {
                         if self.transfers.is_empty() {
                             ui.label(RichText::new("No active transfers").color(colors::TEXT_MUTED));
                         } else {
-                            for transfer in &self.transfers {
+                            let mut edit_request = None;
+                            for transfer in &self.transfers[visible] {
                                 ui.horizontal(|ui| {
                                     let icon = match transfer.direction {
                                         TransferDirection::Upload => "⬆",
@@ -555,7 +1334,13 @@ impl SftpBrowserScreen {
 
                                     match &transfer.state {
                                         TransferState::Pending => {
-                                            ui.label(RichText::new("Pending").color(colors::TEXT_MUTED));
+                                            let label = self.transfer_notes.get(&transfer.id)
+                                                .map_or("Pending", |(schedule, _)| schedule.as_str());
+                                            ui.label(RichText::new(label).color(colors::TEXT_MUTED));
+
+                                            if ui.small_button("\u{2699}").on_hover_text("Schedule and post-actions").clicked() {
+                                                edit_request = Some((transfer.id, transfer.direction == TransferDirection::Upload));
+                                            }
                                         }
                                         TransferState::InProgress => {
                                             let progress = transfer.progress_percent();
@@ -571,13 +1356,109 @@ impl SftpBrowserScreen {
                                             ui.label(RichText::new("Cancelled").color(colors::WARNING));
                                         }
                                     }
+
+                                    if let Some((_, Some(then))) = self.transfer_notes.get(&transfer.id) {
+                                        ui.label(RichText::new(then).small().color(colors::TEXT_MUTED));
+                                    }
                                 });
                             }
+
+                            if let Some((id, is_upload)) = edit_request {
+                                let options = self.transfer_options(id);
+                                let is_root = self.remote_user == "root";
+                                self.options_form = Some(TransferOptionsForm::from_options(id, is_upload, is_root, &options));
+                            }
                         }
                     });
             });
     }
 
+    fn show_transfer_options_window(&mut self, ui: &mut egui::Ui) {
+        let Some(form) = &mut self.options_form else {
+            return;
+        };
+        let mut close_dialog = false;
+        let mut save = false;
+
+        egui::Window::new("Transfer Options")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ui.ctx(), |ui| {
+                ui.label(RichText::new("Start").strong());
+                ui.radio_value(&mut form.schedule, ScheduleKind::Now, "When its turn comes");
+                ui.radio_value(&mut form.schedule, ScheduleKind::AfterQueue, "After the current queue finishes");
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut form.schedule, ScheduleKind::AtTime, "At");
+                    ui.add_enabled(
+                        form.schedule == ScheduleKind::AtTime,
+                        egui::TextEdit::singleline(&mut form.time).hint_text("HH:MM").desired_width(60.0),
+                    );
+                });
+
+                if form.is_upload {
+                    ui.add_space(spacing::SM);
+                    ui.label(RichText::new("Uploaded file").strong());
+                    ui.checkbox(&mut form.preserve, "Keep local mode and modification time");
+                    ui.horizontal(|ui| {
+                        ui.add_enabled(form.is_root, egui::Checkbox::new(&mut form.chown, "Owner"))
+                            .on_disabled_hover_text("Only root can change ownership");
+                        let editable = form.is_root && form.chown;
+                        ui.add_enabled(editable, egui::TextEdit::singleline(&mut form.uid).hint_text("uid").desired_width(50.0));
+                        ui.label("Group");
+                        ui.add_enabled(editable, egui::TextEdit::singleline(&mut form.gid).hint_text("gid").desired_width(50.0));
+                    });
+                }
+
+                ui.add_space(spacing::SM);
+                ui.label(RichText::new("When complete").strong());
+                ui.horizontal(|ui| {
+                    ui.add_enabled(form.is_upload, egui::Checkbox::new(&mut form.chmod, "Set mode"));
+                    ui.add_enabled(
+                        form.is_upload && form.chmod,
+                        egui::TextEdit::singleline(&mut form.chmod_mode).desired_width(50.0),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut form.run_command, "Run on server");
+                    ui.add_enabled(
+                        form.run_command,
+                        egui::TextEdit::singleline(&mut form.command).hint_text("e.g. sha256sum {path}"),
+                    );
+                });
+                ui.checkbox(&mut form.delete_source, "Delete source");
+                ui.checkbox(&mut form.notify, "Notify me");
+
+                ui.add_space(spacing::SM);
+
+                ui.horizontal(|ui| {
+                    if ui.button("Cancel").clicked() {
+                        close_dialog = true;
+                    }
+                    if ui.button("Save").clicked() {
+                        save = true;
+                    }
+                });
+            });
+
+        if save {
+            match form.to_options() {
+                Ok(options) => {
+                    let id = form.id;
+                    self.transfer_notes.insert(id, transfer_notes(&options));
+                    self.transfer_options.insert(id, options);
+                    self.error_message = None;
+                    close_dialog = true;
+                }
+                Err(e) => self.error_message = Some(e),
+            }
+        }
+
+        if close_dialog {
+            self.options_form = None;
+        }
+    }
+
     fn show_new_dir_dialog_window(&mut self, ui: &mut egui::Ui) -> Option<SftpBrowserAction> {
         let mut action = None;
         let mut close_dialog = false;
@@ -667,3 +1548,36 @@ impl SftpBrowserScreen {
         action
     }
 }
+
+/// Text for a transfer row: its schedule and what runs afterwards
+fn transfer_notes(options: &TransferOptions) -> (String, Option<String>) {
+    let then = (!options.post_actions.is_empty()).then(|| {
+        let summary: Vec<_> = options.post_actions.iter().map(|a| a.describe()).collect();
+        format!("then {}", summary.join(", "))
+    });
+    (options.schedule.label(), then)
+}
+
+/// Custom actions whose placeholders can be filled from `ctx`, under a separator
+fn custom_actions_menu(
+    ui: &mut egui::Ui,
+    actions: &[CustomAction],
+    ctx: ActionContext,
+) -> Option<SftpBrowserAction> {
+    let available: Vec<_> = actions.iter()
+        .filter(|a| a.in_file_menu() && a.is_available(&ctx))
+        .collect();
+    if available.is_empty() {
+        return None;
+    }
+
+    let mut action = None;
+    ui.separator();
+    for custom in available {
+        if ui.button(&custom.name).clicked() {
+            action = Some(SftpBrowserAction::RunCustomAction(custom.clone(), ctx.clone()));
+            ui.close_menu();
+        }
+    }
+    action
+}
@@ -153,12 +153,18 @@ impl SftpBrowserScreen {
             ui.separator();
             ui.heading("Transfers");
             
-            for transfer in &self.transfer_progress {
-                ui.horizontal(|ui| {
-                    ui.label(&transfer.filename);
-                    ui.add(egui::ProgressBar::new(transfer.progress).text(&transfer.status));
+            let row_height = ui.spacing().interact_size.y;
+            egui::ScrollArea::vertical()
+                .id_source("transfers")
+                .max_height(160.0)
+                .show_rows(ui, row_height, self.transfer_progress.len(), |ui, visible| {
+                    for transfer in &self.transfer_progress[visible] {
+                        ui.horizontal(|ui| {
+                            ui.label(&transfer.filename);
+                            ui.add(egui::ProgressBar::new(transfer.progress).text(&transfer.status));
+                        });
+                    }
                 });
-            }
         }
     }
 }
@@ -0,0 +1,162 @@
+//! Shared Session Viewer - watch a terminal shared from another TabSSH
+
+use egui::text::{LayoutJob, TextFormat};
+use egui::{Color32, FontId, RichText};
+use tokio::sync::watch;
+use crate::terminal::share::{ShareFrame, ViewerState};
+use crate::ui::components::{colors, spacing, secondary_button, empty_state};
+
+const FONT_SIZE: f32 = 13.0;
+const DEFAULT_FG: Color32 = Color32::from_rgb(229, 229, 229);
+const DEFAULT_BG: Color32 = Color32::from_rgb(30, 30, 30);
+
+/// Viewer state: the address form until connected, then the live screen
+pub struct ShareViewerScreen {
+    address: String,
+    token: String,
+    connection: Option<watch::Receiver<ViewerState>>,
+}
+
+impl ShareViewerScreen {
+    pub fn new() -> Self {
+        Self {
+            address: String::new(),
+            token: String::new(),
+            connection: None,
+        }
+    }
+
+    /// Follow a connection started for `ShareViewerAction::Connect`
+    pub fn set_connection(&mut self, connection: watch::Receiver<ViewerState>) {
+        self.connection = Some(connection);
+    }
+
+    pub fn render(&mut self, ui: &mut egui::Ui) -> Option<ShareViewerAction> {
+        let mut action = None;
+        let state = self.connection.as_ref().map(|c| c.borrow().clone());
+
+        ui.horizontal(|ui| {
+            let title = match &state {
+                Some(ViewerState::Live(frame)) if !frame.title.is_empty() => format!("Watching {}", frame.title),
+                _ => "Shared Session".to_string(),
+            };
+            ui.label(RichText::new(title).color(colors::TEXT_PRIMARY).strong().size(16.0));
+            ui.label(RichText::new("view only").color(colors::TEXT_MUTED).size(11.0));
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if secondary_button(ui, "Close").clicked() {
+                    action = Some(ShareViewerAction::Close);
+                }
+                if self.connection.is_some() && secondary_button(ui, "Disconnect").clicked() {
+                    // Dropping the receiver ends the connection
+                    self.connection = None;
+                }
+            });
+        });
+        ui.add_space(spacing::SM);
+
+        match state {
+            None => {
+                ui.label("Ask the person sharing for the address and token shown in their status bar.");
+                ui.add_space(spacing::SM);
+                ui.horizontal(|ui| {
+                    ui.label("Address:");
+                    ui.add(egui::TextEdit::singleline(&mut self.address).hint_text("192.168.1.20:41234").desired_width(180.0));
+                    ui.label("Token:");
+                    ui.add(egui::TextEdit::singleline(&mut self.token).password(true).desired_width(240.0));
+                    let ready = !self.address.trim().is_empty() && !self.token.trim().is_empty();
+                    if ui.add_enabled(ready, egui::Button::new("Connect")).clicked() {
+                        action = Some(ShareViewerAction::Connect {
+                            address: self.address.trim().to_string(),
+                            token: self.token.trim().to_string(),
+                        });
+                    }
+                });
+            }
+            Some(ViewerState::Connecting) => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(format!("Connecting to {}...", self.address));
+                });
+            }
+            Some(ViewerState::Live(frame)) => render_frame(ui, &frame),
+            Some(ViewerState::Ended(reason)) => {
+                empty_state(ui, "\u{1F4E1}", "Disconnected", &reason);
+                ui.vertical_centered(|ui| {
+                    if ui.button("Try again").clicked() {
+                        self.connection = None;
+                    }
+                });
+            }
+        }
+
+        action
+    }
+}
+
+impl Default for ShareViewerScreen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The shared screen with its colors and a block cursor
+fn render_frame(ui: &mut egui::Ui, frame: &ShareFrame) {
+    let font = FontId::monospace(FONT_SIZE);
+    let rgb = |c: Option<[u8; 3]>, default: Color32| c.map_or(default, |[r, g, b]| Color32::from_rgb(r, g, b));
+
+    let mut job = LayoutJob::default();
+    for (row, line) in frame.lines.iter().enumerate() {
+        let mut col = 0;
+        for span in line {
+            let (mut fg, mut bg) = (rgb(span.fg, DEFAULT_FG), rgb(span.bg, DEFAULT_BG));
+            if span.inverse {
+                std::mem::swap(&mut fg, &mut bg);
+            }
+            if span.dim {
+                fg = fg.gamma_multiply(0.6);
+            }
+            let format = |fg: Color32, bg: Color32| TextFormat {
+                font_id: font.clone(),
+                color: fg,
+                background: if bg == DEFAULT_BG { Color32::TRANSPARENT } else { bg },
+                italics: span.italic,
+                underline: if span.underline { egui::Stroke::new(1.0, fg) } else { egui::Stroke::NONE },
+                ..Default::default()
+            };
+            let len = span.text.chars().count();
+            let (cursor_col, cursor_row) = (frame.cursor.0 as usize, frame.cursor.1 as usize);
+            if cursor_row == row && (col..col + len).contains(&cursor_col) {
+                // Split around the cursor so it can be drawn inverted
+                let split = cursor_col - col;
+                let before: String = span.text.chars().take(split).collect();
+                let at: String = span.text.chars().skip(split).take(1).collect();
+                let after: String = span.text.chars().skip(split + 1).collect();
+                job.append(&before, 0.0, format(fg, bg));
+                job.append(&at, 0.0, format(DEFAULT_BG, DEFAULT_FG));
+                job.append(&after, 0.0, format(fg, bg));
+            } else {
+                job.append(&span.text, 0.0, format(fg, bg));
+            }
+            col += len;
+        }
+        if row + 1 < frame.lines.len() {
+            job.append("\n", 0.0, TextFormat { font_id: font.clone(), ..Default::default() });
+        }
+    }
+
+    egui::Frame::none()
+        .fill(DEFAULT_BG)
+        .inner_margin(spacing::SM)
+        .show(ui, |ui| {
+            ui.set_min_size(ui.available_size());
+            egui::ScrollArea::both().show(ui, |ui| ui.label(job));
+        });
+}
+
+/// Actions from the shared session viewer
+pub enum ShareViewerAction {
+    /// Start `share::watch` and hand the receiver to `set_connection`
+    Connect { address: String, token: String },
+    Close,
+}
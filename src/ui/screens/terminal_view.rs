@@ -3,12 +3,121 @@
 #![allow(dead_code)]
 
 use eframe::egui::{self, RichText};
-use crate::ssh::{ActiveSession, SessionEvent};
-use crate::terminal::{Terminal, TerminalSize, RendererConfig, CursorStyle};
+use crate::ssh::knock::{self, Knock};
+use crate::ssh::{prefer_method, ActiveSession, AuthProgress, ConnectionConfig, Credentials, PtyOptions, SessionEvent, SessionInfo, SshConnection};
+use crate::ssh::bastion::BastionHop;
+use crate::integrations::docker::{self, Container};
+use crate::integrations::processes::{self, Process, ProcessSort, Signal};
+use crate::integrations::services::ServiceAction;
+use crate::integrations::kubernetes::KubeTarget;
+use crate::terminal::{Color, Terminal, TerminalSize, RendererConfig, CursorStyle};
+use crate::terminal::charset::SessionCharset;
+use crate::terminal::renderer::{self, GUTTER_CHARS};
+use crate::terminal::input::{encode_key, focus_report, is_navigation_key, KeyEvents, KeyboardOptions, ModifiedKeyEncoding};
+use crate::terminal::prediction::{EchoPredictor, PredictionMode};
+use crate::terminal::export::{self, ExportCell, ExportFormat, ExportPalette, ExportScheme};
+use crate::terminal::scratchpad::{self, ScratchpadFormat};
+use crate::terminal::capture::{CaptureOptions, TrafficCapture};
+use crate::terminal::escapes::SequenceLog;
+use crate::terminal::filter::{FilteredLine, FilterOptions, LineFilter};
+use crate::terminal::triggers::TriggerSet;
+use crate::terminal::command_timer::format_command_duration;
+use crate::terminal::foreground::{self, Activity, ForegroundJob};
+use crate::terminal::approval;
+use crate::terminal::status::{self, SegmentAction, StatusSegment, StatusSegmentConfig};
+use crate::terminal::share::{self, ShareFrame, ShareServer};
+use crate::storage::database::TrustedCa;
+use crate::storage::Database;
+use crate::storage::folders::Folder;
+use crate::storage::session_groups::PaneLayout;
+use crate::storage::settings::{self, PrivacyCover, Settings, SettingsChange};
+use crate::sftp::SftpClient;
+use super::connection_manager::{AutoSftp, ConnectionProfile};
 use crate::ui::components::{colors, spacing};
+use crate::ui::app_state::{SettingsEvent, TerminalRequest, ViewEvent, ViewReporter};
+use crate::ui::markdown;
 use uuid::Uuid;
+use crate::utils::executor::{Executor, Task};
+use crate::utils::helpers::{format_file_size, format_idle};
+use crate::utils::memory;
+use crate::platform::PlatformManager;
+use crate::crypto::audit::SESSION_LOG_DIR;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::runtime::Runtime;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+/// Give up on a session that has not connected by then
+const CONNECT_WATCHDOG: Duration = Duration::from_secs(45);
+
+/// Sequences the inspector keeps on screen
+const INSPECTOR_ENTRIES: usize = 1000;
+
+/// How often the process list refreshes while it is open
+const PROCESS_REFRESH: Duration = Duration::from_secs(3);
+
+/// Scratchpad edits are handed over for saving once typing pauses this long
+const SCRATCHPAD_AUTOSAVE: Duration = Duration::from_secs(2);
+
+/// Credentials kept for reconnecting after the network dropped the session
+#[derive(Clone)]
+enum ReconnectAuth {
+    Password(String),
+    Key { path: String, passphrase: Option<String> },
+    /// A profile's methods, tried in order
+    Methods(Vec<Credentials>),
+    Kubernetes(KubeTarget),
+}
+
+/// A service shortcut that ran, shown in a pane until dismissed
+struct ServiceRun {
+    action: ServiceAction,
+    started: Instant,
+    /// Stdout once finished, or the error with stderr
+    output: Option<Result<String, String>>,
+    task: Option<Task<anyhow::Result<String>>>,
+}
+
+/// Input that would run a command on a critical host, held for the
+/// confirmation bar
+struct HeldCommand {
+    /// Command line as shell integration saw it; `None` without it
+    command: Option<String>,
+    /// The bytes that run it, sent unchanged once approved
+    data: Vec<u8>,
+}
+
+/// Compiled filter and the rows it gave, reused until the options or
+/// the buffer change
+struct FilterCache {
+    options: FilterOptions,
+    filter: Result<LineFilter, String>,
+    /// Terminal generation `lines` and `rows` were taken at
+    generation: Option<u64>,
+    lines: Vec<String>,
+    rows: Vec<FilteredLine>,
+    matches: usize,
+}
+
+/// Part of the output to export
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExportRange {
+    Screen,
+    Scrollback,
+}
+
+/// Choices in the export dialog
+struct ExportDialog {
+    range: ExportRange,
+    format: ExportFormat,
+    scheme: ExportScheme,
+}
+
+/// Rendered export for the owner to save
+pub struct TerminalExport {
+    pub file_name: String,
+    pub contents: Vec<u8>,
+}
 
 /// Connection state for the terminal
 #[derive(Clone, PartialEq)]
@@ -43,14 +152,236 @@ pub struct TerminalViewScreen {
     /// Active SSH session
     active_session: Option<ActiveSession>,
 
+    /// Session being set up in the background
+    pending_session: Option<Task<anyhow::Result<ActiveSession>>>,
+
     /// Connection state
     connection_state: ConnectionState,
 
+    /// When the current connection attempt started, for the watchdog
+    connecting_since: Option<Instant>,
+
     /// Pending password for connection
     pending_password: Option<String>,
 
     /// Pending key path for connection
     pending_key_path: Option<String>,
+
+    /// Modified-key encoding and Alt handling
+    pub keyboard: KeyboardOptions,
+
+    /// Uncommitted IME composition, drawn at the cursor
+    preedit: String,
+
+    /// Typed characters drawn ahead of the server's echo
+    predictor: EchoPredictor,
+
+    /// Server charset; data is converted to and from UTF-8 at the session boundary
+    charset: SessionCharset,
+
+    /// `TERM` requested for the PTY
+    pub terminal_type: String,
+
+    /// Focus state last seen, to detect changes for mode 1004
+    had_focus: bool,
+
+    /// When the server last sent output
+    last_output: Option<Instant>,
+
+    /// When the user last typed into the session
+    last_input: Option<Instant>,
+
+    /// What hides the content once the terminal has been left alone
+    privacy_cover: PrivacyCover,
+    privacy_after: Duration,
+    /// Last time the terminal had focus
+    attended: Instant,
+    /// Content hidden until a click or keypress
+    covered: bool,
+
+    /// Set while a probe after a network change is outstanding
+    stale_since: Option<Instant>,
+
+    /// How the current session authenticated, for a fast reconnect
+    reconnect_auth: Option<ReconnectAuth>,
+
+    /// The session died while stale; the owner should call `reconnect`
+    reconnect_requested: bool,
+
+    /// Bastion the profile's connections are tunnelled through
+    jump: Option<BastionHop>,
+
+    /// Methods of a connect waiting for the bastion login; the owner hands
+    /// it over with `connect_through`
+    tunnel_wait: Option<Vec<Credentials>>,
+
+    /// Saved connection being connected, which remembers the method that worked
+    connection_id: Option<String>,
+
+    /// Negotiated algorithms of the current session
+    session_info: Option<SessionInfo>,
+
+    /// Tab title instead of the host, e.g. for a container shell
+    pub title: Option<String>,
+
+    /// Run in the PTY instead of the login shell
+    remote_command: Option<String>,
+
+    /// Port knocks sent before every connect, reconnects included
+    knock_sequence: Vec<Knock>,
+    /// The profile's host certificate and the CAs it is checked against
+    host_certificate: Option<Vec<u8>>,
+    trusted_cas: Vec<TrustedCa>,
+    /// The profile's scrollback length, kept over the one in settings
+    scrollback_override: Option<usize>,
+
+    /// Running containers on the host, once listed
+    containers: Option<Result<Vec<Container>, String>>,
+    container_task: Option<Task<anyhow::Result<Vec<Container>>>>,
+
+    /// The user opened the container list; the owner should call `list_containers`
+    containers_requested: bool,
+
+    /// Container the user picked; the owner opens it with `for_container`
+    container_request: Option<Container>,
+
+    /// Processes panel: the last `ps` listing, or why it failed
+    processes_open: bool,
+    processes: Option<Result<Vec<Process>, String>>,
+    process_task: Option<Task<anyhow::Result<Vec<Process>>>>,
+    processes_listed: Option<Instant>,
+    /// Due for a refresh; the owner should call `list_processes`
+    processes_requested: bool,
+    process_filter: String,
+    process_sort: ProcessSort,
+    process_sort_descending: bool,
+    /// Signal waiting for the user to confirm, with the command line shown
+    signal_confirm: Option<(u32, String, Signal)>,
+    /// Confirmed signal; the owner should call `send_signal`
+    signal_request: Option<(u32, Signal)>,
+    signal_task: Option<Task<anyhow::Result<String>>>,
+    /// Outcome of the last signal, shown in the panel
+    signal_result: Option<Result<String, String>>,
+
+    /// Service shortcuts of the session's profile
+    service_actions: Vec<ServiceAction>,
+    /// Shortcut the user clicked; the owner should call `run_service_action`
+    service_request: Option<ServiceAction>,
+    service_run: Option<ServiceRun>,
+
+    /// Notes of the session's profile; None for sessions without one
+    notes: Option<String>,
+    notes_open: bool,
+    /// Text being edited in the notes panel
+    notes_draft: Option<String>,
+    /// Typing goes to the notes editor rather than the session
+    notes_focused: bool,
+    /// Why the last save to the profile failed
+    notes_error: Option<String>,
+
+    /// Debug capture of the channel's bytes, while running
+    capture: Option<TrafficCapture>,
+    /// Options being chosen before a capture starts
+    capture_dialog: Option<CaptureOptions>,
+    /// Where the last capture was saved, shown for a while
+    capture_saved: Option<(PathBuf, Instant)>,
+    /// Session log the host's output is written to, while logging
+    session_log: Option<std::io::BufWriter<std::fs::File>>,
+
+    /// Developer panel decoding the escape sequences as they arrive;
+    /// toggled with Ctrl+Shift+F12
+    inspector: Option<SequenceLog>,
+    inspector_paused: bool,
+    /// Leave colors and attributes out of the list
+    inspector_hide_sgr: bool,
+
+    /// Per-tab scratchpad, saved with the workspace rather than the profile
+    scratchpad: String,
+    scratchpad_format: ScratchpadFormat,
+    scratchpad_open: bool,
+    /// Rendered instead of edited; only for Markdown
+    scratchpad_preview: bool,
+    /// Last unsaved edit, for the autosave pause
+    scratchpad_edited: Option<Instant>,
+    /// Typing goes to the scratchpad rather than the session
+    scratchpad_focused: bool,
+
+    /// Timestamp gutter next to the output
+    show_timestamps: bool,
+
+    /// Output highlighting and triggers from settings
+    triggers: Option<Arc<TriggerSet>>,
+    /// Lines already checked for triggers, counting trimmed scrollback
+    scanned_lines: usize,
+
+    /// Grep mode: shows only matching lines while set
+    filter: Option<FilterOptions>,
+    filter_cache: Option<FilterCache>,
+
+    /// Export dialog, open while set
+    export: Option<ExportDialog>,
+    /// Theme colors used by the "Terminal theme" export scheme
+    export_palette: ExportPalette,
+    /// Export waiting for the owner to save
+    export_ready: Option<TerminalExport>,
+
+    /// Where the profile wants its SFTP browser when this terminal connects
+    pub auto_sftp: AutoSftp,
+    /// Connected with `auto_sftp` set; the owner has not opened the browser yet
+    sftp_requested: bool,
+
+    /// The user asked for another shell over this connection
+    shell_requested: bool,
+    /// This session is a channel on another tab's connection; if the server
+    /// refuses it, connect separately instead
+    shared: bool,
+
+    /// Typed input is mirrored to the other broadcasting panes
+    pub broadcast: bool,
+    /// Input typed this frame that `render_panes` still has to relay
+    broadcast_input: Vec<u8>,
+    /// Scrolling and navigation keys are repeated in the other synced
+    /// panes, for comparing output side by side
+    pub sync: bool,
+    /// Navigation keys pressed here that `render_panes` still has to relay;
+    /// each pane encodes them for its own cursor mode
+    sync_keys: Vec<(egui::Key, egui::Modifiers)>,
+    /// Observer mode: output is shown, typed input never reaches the channel
+    read_only: bool,
+    /// Critical profile: hold each command at the prompt until confirmed
+    pub confirm_commands: bool,
+    /// Input held back for the confirmation bar
+    pending_command: Option<HeldCommand>,
+    /// Sharing is enabled in settings
+    sharing_allowed: bool,
+    /// Shares listen on the LAN rather than loopback only
+    share_on_lan: bool,
+    /// The user asked to share this terminal; the owner starts it
+    share_requested: bool,
+    /// Live read-only view for viewers, and when it was last updated
+    share: Option<ShareServer>,
+    share_published: Option<Instant>,
+    /// When a keystroke was last dropped, to flash the banner
+    blocked_at: Option<Instant>,
+
+    /// Settings-changed bus, applied as events arrive
+    settings_events: Option<broadcast::Receiver<SettingsEvent>>,
+    /// Session id this view reports under, and where to
+    reporter: Option<(String, ViewReporter)>,
+    /// A job was reported last time, so its end must be reported too
+    job_reported: bool,
+    /// Keepalive interval from settings, given to each new session
+    keepalive: Option<Duration>,
+
+    /// Status bar segments in display order
+    status_bar: Vec<StatusSegmentConfig>,
+    /// Round trip of the last answered probe
+    last_rtt: Option<Duration>,
+    /// When the current session connected, for the uptime
+    connected_at: Option<Instant>,
+    /// Bytes received from and typed into the session since it connected
+    bytes_in: u64,
+    bytes_out: u64,
 }
 
 impl Default for TerminalViewScreen {
@@ -59,6 +390,12 @@ impl Default for TerminalViewScreen {
     }
 }
 
+impl Drop for TerminalViewScreen {
+    fn drop(&mut self) {
+        memory::forget(&self.id.to_string());
+    }
+}
+
 impl TerminalViewScreen {
     pub fn new() -> Self {
         let config = RendererConfig {
@@ -67,6 +404,7 @@ impl TerminalViewScreen {
             cursor_style: CursorStyle::Block,
             cursor_blink: true,
             show_scrollbar: true,
+            show_timestamps: false,
         };
 
         let mut screen = Self {
@@ -79,17 +417,117 @@ impl TerminalViewScreen {
             is_connected: false,
             last_size: (80, 24),
             active_session: None,
+            pending_session: None,
             connection_state: ConnectionState::Disconnected,
+            connecting_since: None,
             pending_password: None,
             pending_key_path: None,
+            keyboard: KeyboardOptions::default(),
+            preedit: String::new(),
+            predictor: EchoPredictor::new(PredictionMode::default()),
+            charset: SessionCharset::utf8(),
+            terminal_type: "xterm-256color".to_string(),
+            had_focus: false,
+            last_output: None,
+            last_input: None,
+            privacy_cover: PrivacyCover::Off,
+            privacy_after: Duration::from_secs(600),
+            attended: Instant::now(),
+            covered: false,
+            stale_since: None,
+            reconnect_auth: None,
+            reconnect_requested: false,
+            jump: None,
+            tunnel_wait: None,
+            connection_id: None,
+            session_info: None,
+            title: None,
+            remote_command: None,
+            knock_sequence: Vec::new(),
+            host_certificate: None,
+            trusted_cas: Vec::new(),
+            scrollback_override: None,
+            containers: None,
+            container_task: None,
+            containers_requested: false,
+            container_request: None,
+            processes_open: false,
+            processes: None,
+            process_task: None,
+            processes_listed: None,
+            processes_requested: false,
+            process_filter: String::new(),
+            process_sort: ProcessSort::Cpu,
+            process_sort_descending: true,
+            signal_confirm: None,
+            signal_request: None,
+            signal_task: None,
+            signal_result: None,
+            service_actions: Vec::new(),
+            service_request: None,
+            service_run: None,
+            notes: None,
+            notes_open: false,
+            notes_draft: None,
+            notes_focused: false,
+            notes_error: None,
+            capture: None,
+            capture_dialog: None,
+            capture_saved: None,
+            session_log: None,
+            inspector: None,
+            inspector_paused: false,
+            inspector_hide_sgr: false,
+            scratchpad: String::new(),
+            scratchpad_format: ScratchpadFormat::Plain,
+            scratchpad_open: false,
+            scratchpad_preview: false,
+            scratchpad_edited: None,
+            scratchpad_focused: false,
+            show_timestamps: false,
+            triggers: None,
+            scanned_lines: 0,
+            filter: None,
+            filter_cache: None,
+            export: None,
+            export_palette: ExportPalette::default(),
+            export_ready: None,
+            auto_sftp: AutoSftp::Off,
+            sftp_requested: false,
+            shell_requested: false,
+            shared: false,
+            broadcast: false,
+            broadcast_input: Vec::new(),
+            sync: false,
+            sync_keys: Vec::new(),
+            read_only: false,
+            confirm_commands: false,
+            pending_command: None,
+            sharing_allowed: false,
+            share_on_lan: false,
+            share_requested: false,
+            share: None,
+            share_published: None,
+            blocked_at: None,
+            settings_events: None,
+            reporter: None,
+            job_reported: false,
+            keepalive: None,
+            status_bar: status::default_layout(),
+            last_rtt: None,
+            connected_at: None,
+            bytes_in: 0,
+            bytes_out: 0,
         };
 
         screen.add_welcome_message();
         screen
     }
 
-    pub fn for_session(host: &str, user: &str, port: u16) -> Self {
+    /// Terminal for a new session, sized to the tab's content area
+    pub fn for_session(host: &str, user: &str, port: u16, available: egui::Vec2) -> Self {
         let mut screen = Self::new();
+        screen.fit_to(available);
         screen.session_host = host.to_string();
         screen.session_user = user.to_string();
         screen.session_port = port;
@@ -99,350 +537,2603 @@ impl TerminalViewScreen {
         screen
     }
 
-    pub fn connection_state(&self) -> &ConnectionState {
-        &self.connection_state
+    /// Terminal for a shell in a Kubernetes pod; call `connect_kubernetes` next
+    pub fn for_kubernetes(target: &KubeTarget, available: egui::Vec2) -> Self {
+        let mut screen = Self::new();
+        screen.fit_to(available);
+        screen.session_host = target.label();
+        screen.session_port = 0;
+        screen.terminal.clear();
+        screen.write_line(&format!("Opening a shell in {}...\r\n", target.label()));
+        screen.connection_state = ConnectionState::Connecting;
+        screen
     }
 
-    pub fn connect_with_password(&mut self, runtime: Arc<Runtime>, password: String) {
-        let host = self.session_host.clone();
-        let port = self.session_port;
-        let username = self.session_user.clone();
-        self.connection_state = ConnectionState::Connecting;
-        self.write_line("Authenticating with password...\r\n");
+    /// Terminal for a shell in `container` on this tab's host, reusing its
+    /// credentials; call `reconnect` next
+    pub fn for_container(&self, container: &Container, available: egui::Vec2) -> Self {
+        let mut screen = Self::for_session(&self.session_host, &self.session_user, self.session_port, available);
+        screen.terminal_type = self.terminal_type.clone();
+        screen.set_encoding(self.charset.name());
+        screen.keyboard = self.keyboard;
+        screen.reconnect_auth = self.reconnect_auth.clone();
+        screen.jump = self.jump.clone();
+        screen.host_certificate = self.host_certificate.clone();
+        screen.trusted_cas = self.trusted_cas.clone();
+        screen.remote_command = Some(container.shell_command());
+        screen.title = Some(format!("{} @ {}", container.name, self.session_host));
+        screen.write_line(&format!("Attaching to container {} ({})...\r\n", container.name, container.short_id()));
+        screen
+    }
 
-        let session_result = runtime.block_on(async {
-            ActiveSession::connect_password(host, port, username, password).await
-        });
+    /// Terminal for another shell over this tab's connection, without a new
+    /// handshake or login. Falls back to a separate connection if the
+    /// server caps channels per connection.
+    pub fn share_connection(&self, executor: &Executor, available: egui::Vec2) -> Self {
+        let mut screen = Self::for_session(&self.session_host, &self.session_user, self.session_port, available);
+        screen.terminal_type = self.terminal_type.clone();
+        screen.set_encoding(self.charset.name());
+        screen.keyboard = self.keyboard;
+        screen.reconnect_auth = self.reconnect_auth.clone();
+        screen.jump = self.jump.clone();
+        screen.host_certificate = self.host_certificate.clone();
+        screen.trusted_cas = self.trusted_cas.clone();
 
-        match session_result {
-            Ok(session) => {
-                self.active_session = Some(session);
-            }
-            Err(e) => {
-                self.connection_state = ConnectionState::Error(e.to_string());
-                self.write_line(&format!("\x1b[31mConnection failed: {}\x1b[0m\r\n", e));
+        match &self.active_session {
+            Some(session) => {
+                screen.write_line("Opening another shell on the existing connection...\r\n");
+                screen.connection_state = ConnectionState::Connecting;
+                screen.connecting_since = Some(Instant::now());
+                screen.shared = true;
+                screen.pending_session = Some(executor.spawn(session.open_shell(screen.pty_options())));
             }
+            None => screen.reconnect(executor),
         }
+        screen
     }
 
-    pub fn connect_with_key(&mut self, runtime: Arc<Runtime>, key_path: String, passphrase: Option<String>) {
-        let host = self.session_host.clone();
-        let port = self.session_port;
-        let username = self.session_user.clone();
-        self.connection_state = ConnectionState::Connecting;
-        self.write_line(&format!("Authenticating with key: {}...\r\n", key_path));
+    /// Whether the user asked for another shell on this host; the owner
+    /// opens it in a new tab with `share_connection`
+    pub fn take_shell_request(&mut self) -> bool {
+        std::mem::take(&mut self.shell_requested)
+    }
 
-        let session_result = runtime.block_on(async {
-            ActiveSession::connect_key(host, port, username, key_path, passphrase).await
-        });
+    /// Theme colors for exports that keep the on-screen look
+    pub fn set_export_palette(&mut self, palette: ExportPalette) {
+        self.export_palette = palette;
+    }
 
-        match session_result {
-            Ok(session) => {
-                self.active_session = Some(session);
-            }
-            Err(e) => {
-                self.connection_state = ConnectionState::Error(e.to_string());
-                self.write_line(&format!("\x1b[31mConnection failed: {}\x1b[0m\r\n", e));
-            }
+    /// HTML, PDF or scratchpad export the user asked for; the owner picks where to save it
+    pub fn take_export(&mut self) -> Option<TerminalExport> {
+        self.export_ready.take()
+    }
+
+    /// Title the remote set since the last call, for the tab; the tab
+    /// decides whether it is shown
+    pub fn take_title_change(&mut self) -> Option<String> {
+        self.terminal.take_title()
+    }
+
+    /// Where to open the SFTP browser now that the terminal connected. Asked
+    /// again after a reconnect, so the owner should reattach an open browser.
+    pub fn take_sftp_request(&mut self) -> Option<AutoSftp> {
+        std::mem::take(&mut self.sftp_requested).then_some(self.auto_sftp)
+    }
+
+    /// SFTP on this terminal's connection, without logging in again
+    pub fn open_sftp(&self, executor: &Executor) -> Option<Task<anyhow::Result<SftpClient>>> {
+        let session = self.active_session.as_ref()?;
+        Some(executor.spawn(session.open_sftp()))
+    }
+
+    /// Show the profile's notes; the panel opens by itself when there are any
+    pub fn set_notes(&mut self, notes: String) {
+        self.notes_open = !notes.trim().is_empty();
+        self.notes = Some(notes);
+    }
+
+    /// Knock on these ports before connecting, from the profile
+    pub fn set_knock_sequence(&mut self, sequence: Vec<Knock>) {
+        self.knock_sequence = sequence;
+    }
+
+    /// Check the host's key against this certificate and the trusted CAs
+    /// instead of known_hosts, from the profile
+    pub fn set_host_certificate(&mut self, certificate: Option<Vec<u8>>, trusted_cas: Vec<TrustedCa>) {
+        self.host_certificate = certificate;
+        self.trusted_cas = trusted_cas;
+    }
+
+    /// Where and how the next connect dials
+    fn connection_config(&self) -> ConnectionConfig {
+        let config = ConnectionConfig::new(self.session_host.clone(), self.session_user.clone())
+            .with_port(self.session_port);
+        match &self.host_certificate {
+            Some(certificate) => config.with_host_certificate(certificate.clone(), self.trusted_cas.clone()),
+            None => config,
         }
     }
 
-    pub fn poll_session(&mut self) {
-        let mut events = Vec::new();
-        let mut should_clear_session = false;
+    /// Keep this many lines whatever settings say, from the profile
+    pub fn set_scrollback_override(&mut self, lines: Option<usize>) {
+        self.scrollback_override = lines;
+        if let Some(lines) = lines {
+            self.terminal.set_max_scrollback(lines);
+        }
+    }
 
-        if let Some(session) = &mut self.active_session {
-            while let Some(event) = session.try_recv() {
-                events.push(event);
+    /// The knock sequence for the next connect, announced in the terminal
+    fn knocks(&mut self) -> Vec<Knock> {
+        if !self.knock_sequence.is_empty() {
+            self.write_line(&format!("Knocking {}...\r\n", knock::format_sequence(&self.knock_sequence)));
+        }
+        self.knock_sequence.clone()
+    }
+
+    /// Buttons for the profile's service shortcuts
+    pub fn set_service_actions(&mut self, actions: Vec<ServiceAction>) {
+        self.service_actions = actions;
+    }
+
+    /// Follow settings changes from `AppState::subscribe_settings`
+    pub fn subscribe_settings(&mut self, events: broadcast::Receiver<SettingsEvent>) {
+        self.settings_events = Some(events);
+    }
+
+    fn poll_settings(&mut self) {
+        let Some(events) = &mut self.settings_events else { return };
+        let mut received = Vec::new();
+        let mut lagged = false;
+        loop {
+            match events.try_recv() {
+                Ok(event) => received.push(event),
+                Err(broadcast::error::TryRecvError::Lagged(_)) => lagged = true,
+                Err(_) => break,
             }
         }
 
-        for event in events {
-            match event {
-                SessionEvent::Connected => {
-                    self.connection_state = ConnectionState::Connected;
-                    self.is_connected = true;
-                    self.terminal.process(b"\x1b[32mConnected!\x1b[0m\r\n");
+        if lagged {
+            // Changes were missed; every event carries the full settings,
+            // so applying the newest one from scratch catches up
+            if let Some(event) = received.pop() {
+                self.apply_settings(&event.settings, &SettingsChange::all(&event.settings));
+            }
+            return;
+        }
+        for event in received {
+            self.apply_settings(&event.settings, &event.changes);
+        }
+    }
+
+    /// Apply changed settings to this terminal and its live session
+    pub fn apply_settings(&mut self, settings: &Settings, changes: &[SettingsChange]) {
+        for change in changes {
+            match *change {
+                SettingsChange::Appearance => {
+                    self.font_size = settings.font_size;
+                    let cursor_style = match settings.cursor_style {
+                        settings::CursorStyle::Block => CursorStyle::Block,
+                        settings::CursorStyle::Beam => CursorStyle::Beam,
+                        settings::CursorStyle::Underline => CursorStyle::Underline,
+                    };
+                    self.terminal.set_appearance(&settings.font_family, settings.font_size, cursor_style, settings.cursor_blink);
                 }
-                SessionEvent::Data(data) => {
-                    self.terminal.process(&data);
+                SettingsChange::Scrollback(lines) => {
+                    self.terminal.set_max_scrollback(self.scrollback_override.unwrap_or(lines));
                 }
-                SessionEvent::Disconnected => {
-                    self.connection_state = ConnectionState::Disconnected;
-                    self.is_connected = false;
-                    self.terminal.process(b"\r\n\x1b[33mConnection closed.\x1b[0m\r\n");
-                    should_clear_session = true;
+                SettingsChange::ScrollbackMemory(mb) => self.terminal.set_max_scrollback_bytes(mb * 1024 * 1024),
+                SettingsChange::Keepalive(secs) => {
+                    self.keepalive = (secs > 0).then(|| Duration::from_secs(secs as u64));
+                    if let Some(session) = &self.active_session {
+                        session.set_keepalive(self.keepalive);
+                    }
                 }
-                SessionEvent::Error(err) => {
-                    self.connection_state = ConnectionState::Error(err.clone());
-                    let msg = format!("\r\n\x1b[31mError: {}\x1b[0m\r\n", err);
-                    self.terminal.process(msg.as_bytes());
+                SettingsChange::Input => {
+                    self.keyboard = KeyboardOptions {
+                        encoding: if settings.csi_u_keys { ModifiedKeyEncoding::CsiU } else { ModifiedKeyEncoding::Auto },
+                        alt_sends_escape: settings.alt_sends_escape,
+                    };
+                    self.set_echo_prediction(settings.echo_prediction);
+                    self.set_answerback(&settings.answerback);
+                }
+                SettingsChange::OutputTriggers => {
+                    self.set_triggers(Some(Arc::new(TriggerSet::new(&settings.output_triggers))));
+                }
+                SettingsChange::StatusBar => self.status_bar = status::normalize(&settings.status_bar),
+                SettingsChange::Sharing => {
+                    self.sharing_allowed = settings.allow_session_sharing;
+                    self.share_on_lan = settings.share_on_lan;
+                    let wrong_scope = self.share.as_ref().is_some_and(|share| share.is_lan() != self.share_on_lan);
+                    if !self.sharing_allowed || wrong_scope {
+                        self.stop_sharing();
+                    }
+                }
+                SettingsChange::CommandAnnotations => {
+                    let min = settings.annotate_long_commands
+                        .then(|| Duration::from_secs(settings.long_command_seconds as u64));
+                    self.terminal.set_command_annotation(min);
+                }
+                SettingsChange::PrivacyCover => {
+                    self.privacy_cover = settings.privacy_cover;
+                    match settings.privacy_idle() {
+                        Some(after) => self.privacy_after = after,
+                        None => self.covered = false,
+                    }
                 }
             }
         }
+    }
 
-        if should_clear_session {
-            self.active_session = None;
-        }
+    /// Report the foreground job, trigger hits and scratchpad edits as
+    /// `session_id` from now on, through `AppState::view_reporter`
+    pub fn report_to(&mut self, session_id: String, reporter: ViewReporter) {
+        self.reporter = Some((session_id, reporter));
     }
 
-    pub fn send_input(&self, data: &[u8]) {
-        if let Some(session) = &self.active_session {
-            session.send_data(data.to_vec());
+    fn report(&self, event: ViewEvent) {
+        if let Some((session_id, reporter)) = &self.reporter {
+            let _ = reporter.send((session_id.clone(), event));
         }
     }
 
-    pub fn send_resize(&self, cols: u32, rows: u32) {
-        if let Some(session) = &self.active_session {
-            session.resize(cols, rows);
+    /// Each frame while something seems to run, and once when it stops, so
+    /// idle tabs send nothing
+    fn report_job(&mut self) {
+        if self.reporter.is_none() {
+            return;
+        }
+        let job = self.foreground_job();
+        if job.is_some() || self.job_reported {
+            self.job_reported = job.is_some();
+            self.report(ViewEvent::Job(job));
         }
     }
 
-    pub fn disconnect(&mut self) {
-        if let Some(session) = &self.active_session {
-            session.disconnect();
+    fn report_scratchpad(&mut self) {
+        if self.reporter.is_none() {
+            return;
+        }
+        if let Some((text, format)) = self.take_scratchpad_edit() {
+            self.report(ViewEvent::Scratchpad(text, format));
         }
-        self.active_session = None;
-        self.is_connected = false;
-        self.connection_state = ConnectionState::Disconnected;
     }
 
-    fn add_welcome_message(&mut self) {
-        self.write_line("TabSSH Terminal Emulator\r\n");
-        self.write_line("========================\r\n");
-        self.write_line("\r\n");
-        self.write_line("Full VT100/xterm terminal emulation with:\r\n");
-        self.write_line("  - 256-color and true color support\r\n");
-        self.write_line("  - UTF-8 text rendering\r\n");
-        self.write_line("  - Mouse support (SGR mouse mode)\r\n");
-        self.write_line("  - Scrollback buffer (10,000 lines)\r\n");
-        self.write_line("  - Text selection and clipboard\r\n");
-        self.write_line("  - Alternate screen buffer\r\n");
-        self.write_line("\r\n");
-        self.write_line("Connect to an SSH server to begin.\r\n");
+    /// What is probably running in the session, so closing the tab or the
+    /// app can ask first; see `report_to`
+    pub fn foreground_job(&self) -> Option<ForegroundJob> {
+        if !self.is_connected {
+            return None;
+        }
+        let timer = self.terminal.command_timer();
+        foreground::guess(&Activity {
+            shell_integration: timer.is_active(),
+            running: timer.running_for().map(|running_for| {
+                (self.terminal.running_command().map(str::to_string), running_for)
+            }),
+            alternate_screen: self.terminal.buffer().is_alternate_screen(),
+            output_ago: self.last_output.map(|at| at.elapsed()),
+            input_ago: self.last_input.map(|at| at.elapsed()),
+        })
     }
 
-    /// Write text to the terminal (processed through parser)
-    pub fn write(&mut self, data: &[u8]) {
-        self.terminal.process(data);
+    /// Restore the tab's scratchpad from the saved workspace
+    pub fn set_scratchpad(&mut self, text: String, format: ScratchpadFormat) {
+        self.scratchpad_preview = format == ScratchpadFormat::Markdown && !text.trim().is_empty();
+        self.scratchpad = text;
+        self.scratchpad_format = format;
+        self.scratchpad_edited = None;
     }
 
-    /// Write a line of text
-    pub fn write_line(&mut self, text: &str) {
-        self.terminal.process(text.as_bytes());
+    pub fn scratchpad(&self) -> (&str, ScratchpadFormat) {
+        (&self.scratchpad, self.scratchpad_format)
     }
 
-    /// Set connection status
-    pub fn set_connected(&mut self, connected: bool) {
-        self.is_connected = connected;
-        if connected {
-            self.write_line("\x1b[32mConnected successfully!\x1b[0m\r\n");
-            self.write_line("\r\n");
-            let now = chrono::Local::now();
-            self.write_line(&format!("Last login: {}\r\n", now.format("%c")));
-            self.write_line("\r\n");
-        } else {
-            self.write_line("\x1b[31mDisconnected.\x1b[0m\r\n");
+    /// The scratchpad once an edit has settled, to be stored with the workspace
+    fn take_scratchpad_edit(&mut self) -> Option<(String, ScratchpadFormat)> {
+        let edited = self.scratchpad_edited?;
+        if edited.elapsed() < SCRATCHPAD_AUTOSAVE && self.scratchpad_open {
+            return None;
         }
+        self.scratchpad_edited = None;
+        Some((self.scratchpad.clone(), self.scratchpad_format))
     }
 
-    /// Get terminal size
-    pub fn size(&self) -> TerminalSize {
-        self.terminal.size()
+    /// Use the profile's character encoding for output and input; unknown
+    /// labels fall back to UTF-8
+    pub fn set_encoding(&mut self, label: &str) {
+        self.charset = SessionCharset::from_label(label).unwrap_or_else(|| {
+            log::warn!("Unknown character encoding '{}', using UTF-8", label);
+            SessionCharset::utf8()
+        });
     }
 
-    /// Resize terminal to new dimensions
-    pub fn resize(&mut self, cols: u16, rows: u16) {
-        if (cols, rows) != self.last_size {
-            self.terminal.resize(cols, rows);
-            self.last_size = (cols, rows);
-        }
+    /// Apply the output triggers from settings
+    pub fn set_triggers(&mut self, triggers: Option<Arc<TriggerSet>>) {
+        let triggers = triggers.filter(|set| !set.is_empty());
+        self.terminal.set_triggers(triggers.clone());
+        self.triggers = triggers;
     }
 
-    /// Scroll to bottom
-    pub fn scroll_to_bottom(&mut self) {
-        self.terminal.scroll_to_bottom();
+    /// Check lines completed since the last scan, reporting hits with
+    /// `report_to`; the cursor line may still grow
+    fn scan_triggers(&mut self) {
+        let Some(triggers) = &self.triggers else { return };
+        let buffer = self.terminal.buffer();
+        let trimmed = buffer.trimmed_lines();
+        let completed = trimmed + buffer.scrollback_len() + buffer.cursor_position().1;
+        // Fewer lines after a clear or a cursor move up: what is written
+        // from there on is new output
+        self.scanned_lines = self.scanned_lines.min(completed);
+        let mut hits = Vec::new();
+        for line in self.scanned_lines.max(trimmed)..completed {
+            hits.extend(triggers.hits(&buffer.line_text(line - trimmed)));
+        }
+        self.scanned_lines = completed;
+        for hit in hits {
+            self.report(ViewEvent::Trigger(hit));
+        }
     }
 
-    /// Clear terminal
-    pub fn clear(&mut self) {
-        self.terminal.clear();
+    /// When to draw typed characters before the server echoes them
+    pub fn set_echo_prediction(&mut self, mode: PredictionMode) {
+        self.predictor.set_mode(mode);
     }
 
-    /// Render the terminal view
-    pub fn render(&mut self, ui: &mut egui::Ui) {
-        self.poll_session();
+    /// Set the reply to ENQ (empty sends nothing)
+    pub fn set_answerback(&mut self, answerback: &str) {
+        self.terminal.set_answerback(answerback.to_string());
+    }
 
-        let available = ui.available_size();
+    /// PTY request for the current grid
+    fn pty_options(&self) -> PtyOptions {
+        PtyOptions {
+            term: self.terminal_type.clone(),
+            cols: self.last_size.0 as u32,
+            rows: self.last_size.1 as u32,
+            command: self.remote_command.clone(),
+        }
+    }
 
-        let response = egui::Frame::none()
-            .fill(egui::Color32::from_rgb(30, 30, 30))
-            .rounding(egui::Rounding::ZERO)
-            .show(ui, |ui| {
-                ui.set_min_size(available);
+    pub fn connection_state(&self) -> &ConnectionState {
+        &self.connection_state
+    }
 
-                let char_width = self.font_size * 0.6;
-                let char_height = self.font_size * 1.2;
+    pub fn connect_with_password(&mut self, executor: &Executor, password: String) {
+        let mut config = self.connection_config();
+        let pty = self.pty_options();
+        self.connection_state = ConnectionState::Connecting;
+        self.connecting_since = Some(Instant::now());
+        self.write_line("Authenticating with password...\r\n");
+        self.reconnect_auth = Some(ReconnectAuth::Password(password.clone()));
+        let knocks = self.knocks();
 
-                let new_cols = (available.x / char_width) as u16;
-                let new_rows = (available.y / char_height) as u16;
+        self.pending_session = Some(executor.spawn(async move {
+            config.knock(&knocks).await?;
+            ActiveSession::connect_password(config, password, pty).await
+        }));
+    }
 
-                let old_size = self.last_size;
-                self.resize(new_cols.max(1), new_rows.max(1));
+    pub fn connect_with_key(&mut self, executor: &Executor, key_path: String, passphrase: Option<String>) {
+        let mut config = self.connection_config();
+        let pty = self.pty_options();
+        self.connection_state = ConnectionState::Connecting;
+        self.connecting_since = Some(Instant::now());
+        self.write_line(&format!("Authenticating with key: {}...\r\n", key_path));
+        self.reconnect_auth = Some(ReconnectAuth::Key { path: key_path.clone(), passphrase: passphrase.clone() });
+        let knocks = self.knocks();
 
-                if old_size != self.last_size {
-                    self.send_resize(new_cols as u32, new_rows as u32);
-                }
+        self.pending_session = Some(executor.spawn(async move {
+            config.knock(&knocks).await?;
+            ActiveSession::connect_key(config, key_path, passphrase, pty).await
+        }));
+    }
 
-                self.terminal.render(ui);
-            });
+    /// Tunnel this tab's connections through a bastion, as the profile's
+    /// jump host asks
+    pub fn set_jump_host(&mut self, hop: Option<BastionHop>) {
+        self.jump = hop;
+    }
 
-        let rect = response.response.rect;
-        let terminal_response = ui.interact(rect, ui.id().with("terminal_input"), egui::Sense::click());
+    /// Try the profile's methods in order; each attempt is written to the
+    /// terminal. Behind a jump host the connect waits for the bastion login,
+    /// see `tunnel_request`.
+    pub fn connect_with_methods(&mut self, executor: &Executor, methods: Vec<Credentials>) {
+        self.connection_state = ConnectionState::Connecting;
+        self.connecting_since = Some(Instant::now());
+        self.reconnect_auth = Some(ReconnectAuth::Methods(methods.clone()));
 
-        if terminal_response.clicked() {
-            ui.memory_mut(|mem| mem.request_focus(ui.id().with("terminal_input")));
+        if let Some(hop) = &self.jump {
+            self.write_line(&format!("Logging in to {}...\r\n", hop.id));
+            self.tunnel_wait = Some(methods);
+            return;
         }
 
-        self.handle_keyboard_input(ui);
+        let mut config = self.connection_config();
+        let pty = self.pty_options();
+        let knocks = self.knocks();
+
+        self.pending_session = Some(executor.spawn(async move {
+            config.knock(&knocks).await?;
+            ActiveSession::connect(config, methods, pty, None).await
+        }));
     }
 
-    fn handle_keyboard_input(&self, ui: &mut egui::Ui) {
-        if !self.is_connected {
-            return;
-        }
+    /// The bastion a connect is waiting for; the owner passes its newest
+    /// login from `AppState::bastion_tunnel` to `connect_through`
+    pub fn tunnel_request(&self) -> Option<&BastionHop> {
+        self.tunnel_wait.as_ref().and(self.jump.as_ref())
+    }
 
-        ui.input(|i| {
-            for event in &i.events {
-                match event {
-                    egui::Event::Text(text) => {
-                        self.send_input(text.as_bytes());
-                    }
-                    egui::Event::Key { key, pressed: true, modifiers, .. } => {
-                        if let Some(data) = key_to_escape_sequence(*key, modifiers) {
-                            self.send_input(&data);
-                        }
-                    }
-                    _ => {}
-                }
-            }
+    /// Finish the waiting connect through `bastion`, or fail it if the
+    /// bastion could not be logged in to. Knocks are skipped: they would
+    /// go to the host from here, not from the bastion.
+    pub fn connect_through(&mut self, executor: &Executor, bastion: anyhow::Result<Arc<SshConnection>>) {
+        let Some(methods) = self.tunnel_wait.take() else {
+            return;
+        };
+        let bastion = match bastion {
+            Ok(bastion) => bastion,
+            Err(e) => {
+                self.connection_state = ConnectionState::Error(e.to_string());
+                self.connecting_since = None;
+                self.write_line(&format!("\x1b[31mConnection failed: {}\x1b[0m\r\n", e));
+                return;
+            }
+        };
+        let config = self.connection_config();
+        let pty = self.pty_options();
+
+        self.pending_session = Some(executor.spawn(
+            ActiveSession::connect(config, methods, pty, Some(bastion)),
+        ));
+    }
+
+    /// Connect to a saved connection with every method it allows, the one
+    /// that worked last time first; the one that works now is saved on it.
+    /// Its folders' user, port, key and encoding apply where the profile
+    /// leaves them unset.
+    pub fn connect_profile(
+        &mut self,
+        executor: &Executor,
+        profile: &ConnectionProfile,
+        password: Option<&str>,
+        passphrase: Option<&str>,
+    ) {
+        let profile = match Database::open().and_then(|db| Folder::load_all(&db)) {
+            Ok(folders) => profile.with_folder_defaults(&folders),
+            Err(e) => {
+                log::warn!("Could not load folder defaults for {}: {}", profile.name, e);
+                profile.clone()
+            }
+        };
+        self.session_user = profile.username.trim().to_string();
+        self.session_port = profile.port;
+        self.set_encoding(&profile.encoding);
+        self.connection_id = Some(profile.id.clone());
+        self.connect_with_methods(executor, profile.auth_methods(password, passphrase));
+    }
+
+    /// Remember on the saved connection which method authenticated
+    fn record_auth(&self, method: &str) {
+        let Some(id) = &self.connection_id else { return };
+        if let Err(e) = Database::open().and_then(|db| ConnectionProfile::record_auth(id, method, &db)) {
+            log::warn!("Could not remember how {} logged in: {}", self.session_host, e);
+        }
+    }
+
+    /// Start `kubectl exec` into the pod as this tab's session
+    pub fn connect_kubernetes(&mut self, target: KubeTarget) {
+        let (program, args) = target.command(self.last_size.0 as u32, self.last_size.1 as u32);
+        self.connection_state = ConnectionState::Connecting;
+        self.connecting_since = Some(Instant::now());
+        self.reconnect_auth = Some(ReconnectAuth::Kubernetes(target.clone()));
+
+        match ActiveSession::spawn_process(target.label(), program, args) {
+            Ok(session) => self.active_session = Some(session),
+            Err(e) => {
+                self.connection_state = ConnectionState::Error(e.to_string());
+                self.connecting_since = None;
+                self.write_line(&format!("\x1b[31mCould not start kubectl: {}\x1b[0m\r\n", e));
+            }
+        }
+    }
+
+    /// The network changed or the machine woke up: probe the session now
+    /// instead of waiting for TCP to notice a dead path
+    pub fn mark_stale(&mut self) {
+        if let Some(session) = &self.active_session {
+            self.stale_since.get_or_insert_with(Instant::now);
+            session.probe();
+        }
+    }
+
+    /// Whether the session was lost after a network change and can be reopened
+    pub fn take_reconnect_request(&mut self) -> bool {
+        std::mem::take(&mut self.reconnect_requested)
+    }
+
+    /// Whether the user asked for the host's container list
+    pub fn take_containers_request(&mut self) -> bool {
+        std::mem::take(&mut self.containers_requested)
+    }
+
+    /// Container picked from the list, to open in a new tab
+    pub fn take_container_request(&mut self) -> Option<Container> {
+        self.container_request.take()
+    }
+
+    /// Run `docker ps` on the host over the current connection
+    pub fn list_containers(&mut self, executor: &Executor) {
+        let Some(session) = &self.active_session else {
+            return;
+        };
+        let output = session.exec(docker::LIST_COMMAND.to_string());
+        self.containers = None;
+        self.container_task = Some(executor.spawn(async move {
+            Ok(docker::parse_ps(&output.await?))
+        }));
+    }
+
+    fn poll_containers(&mut self) {
+        if let Some(result) = self.container_task.as_mut().and_then(|task| task.poll()) {
+            self.container_task = None;
+            self.containers = Some(result.and_then(|r| r).map_err(|e| e.to_string()));
+        }
+    }
+
+    /// Whether the process list is due for a refresh
+    pub fn take_processes_request(&mut self) -> bool {
+        std::mem::take(&mut self.processes_requested)
+    }
+
+    /// Signal the user confirmed in the processes panel
+    pub fn take_signal_request(&mut self) -> Option<(u32, Signal)> {
+        self.signal_request.take()
+    }
+
+    /// Run `ps` on the host over the current connection; the last listing
+    /// stays on screen until the new one arrives
+    pub fn list_processes(&mut self, executor: &Executor) {
+        let Some(session) = &self.active_session else {
+            return;
+        };
+        let output = session.exec(processes::LIST_COMMAND.to_string());
+        self.processes_listed = Some(Instant::now());
+        self.process_task = Some(executor.spawn(async move {
+            Ok(processes::parse_ps(&output.await?))
+        }));
+    }
+
+    /// Send `signal` to `pid` with `kill` on the host
+    pub fn send_signal(&mut self, executor: &Executor, pid: u32, signal: Signal) {
+        let Some(session) = &self.active_session else {
+            return;
+        };
+        let output = session.exec(processes::kill_command(pid, signal));
+        self.signal_result = None;
+        self.signal_task = Some(executor.spawn(async move {
+            output.await?;
+            Ok(format!("Sent {} to {}", signal.name(), pid))
+        }));
+    }
+
+    fn poll_processes(&mut self) {
+        if let Some(result) = self.process_task.as_mut().and_then(|task| task.poll()) {
+            self.process_task = None;
+            self.processes = Some(result.and_then(|r| r).map_err(|e| e.to_string()));
+        }
+        if let Some(result) = self.signal_task.as_mut().and_then(|task| task.poll()) {
+            self.signal_task = None;
+            self.signal_result = Some(result.and_then(|r| r).map_err(|e| e.to_string()));
+            // Show the process gone, or still there, right away
+            self.processes_listed = None;
+        }
+
+        if !self.processes_open || !self.is_connected {
+            // Nothing polls while the panel is hidden; a listing in flight is
+            // dropped and the next one starts when the panel opens again
+            self.process_task = None;
+            self.processes_requested = false;
+            self.processes_listed = None;
+            return;
+        }
+        let due = self.processes_listed.map_or(true, |at| at.elapsed() >= PROCESS_REFRESH);
+        if self.process_task.is_none() && due {
+            self.processes_requested = true;
+        }
+    }
+
+    /// Service shortcut the user clicked
+    pub fn take_service_request(&mut self) -> Option<ServiceAction> {
+        self.service_request.take()
+    }
+
+    /// Run a service shortcut on an exec channel; its output replaces the
+    /// pane of the previous one
+    pub fn run_service_action(&mut self, executor: &Executor, action: ServiceAction) {
+        let Some(session) = &self.active_session else {
+            return;
+        };
+        let output = session.exec(action.command.clone());
+        self.service_run = Some(ServiceRun {
+            action,
+            started: Instant::now(),
+            output: None,
+            task: Some(executor.spawn(output)),
+        });
+    }
+
+    fn poll_service_run(&mut self) {
+        let Some(run) = &mut self.service_run else { return };
+        if let Some(result) = run.task.as_mut().and_then(|task| task.poll()) {
+            run.task = None;
+            run.output = Some(result.and_then(|r| r).map_err(|e| e.to_string()));
+        }
+    }
+
+    /// Reconnect with the credentials of the last session
+    pub fn reconnect(&mut self, executor: &Executor) {
+        match self.reconnect_auth.clone() {
+            Some(ReconnectAuth::Password(password)) => self.connect_with_password(executor, password),
+            Some(ReconnectAuth::Key { path, passphrase }) => self.connect_with_key(executor, path, passphrase),
+            Some(ReconnectAuth::Methods(methods)) => self.connect_with_methods(executor, methods),
+            Some(ReconnectAuth::Kubernetes(target)) => self.connect_kubernetes(target),
+            None => self.connection_state = ConnectionState::WaitingForCredentials,
+        }
+    }
+
+    /// Pick up the session once its background setup has finished
+    fn poll_pending_session(&mut self) {
+        let Some(result) = self.pending_session.as_mut().and_then(|task| task.poll()) else {
+            return;
+        };
+        self.pending_session = None;
+
+        match result.and_then(|session| session) {
+            Ok(session) => {
+                self.active_session = Some(session);
+            }
+            Err(e) if std::mem::take(&mut self.shared) && self.reconnect_auth.is_some() => {
+                log::info!("Not sharing the connection to {}: {}", self.session_host, e);
+                self.write_line(&format!("\x1b[33m{}; connecting separately...\x1b[0m\r\n", e));
+                self.reconnect_requested = true;
+            }
+            Err(e) => {
+                self.connection_state = ConnectionState::Error(e.to_string());
+                self.connecting_since = None;
+                self.write_line(&format!("\x1b[31mConnection failed: {}\x1b[0m\r\n", e));
+            }
+        }
+    }
+
+    pub fn poll_session(&mut self) {
+        self.poll_settings();
+        self.poll_pending_session();
+        self.poll_containers();
+        self.poll_processes();
+        self.poll_service_run();
+        self.report_memory();
+        self.report_job();
+        self.report_scratchpad();
+
+        let mut events = Vec::new();
+        let mut should_clear_session = false;
+
+        if let Some(session) = &mut self.active_session {
+            while let Some(event) = session.try_recv() {
+                events.push(event);
+            }
+        }
+
+        for event in events {
+            match event {
+                SessionEvent::Connected => {
+                    self.connecting_since = None;
+                    self.connection_state = ConnectionState::Connected;
+                    self.is_connected = true;
+                    self.last_output = Some(Instant::now());
+                    self.last_input = None;
+                    self.last_rtt = None;
+                    self.connected_at = Some(Instant::now());
+                    self.terminal.reset_command_timer();
+                    self.bytes_in = 0;
+                    self.bytes_out = 0;
+                    self.terminal.process(b"\x1b[32mConnected!\x1b[0m\r\n");
+                    self.sftp_requested = self.auto_sftp != AutoSftp::Off;
+                    if let Some(session) = &self.active_session {
+                        session.set_keepalive(self.keepalive);
+                    }
+                }
+                SessionEvent::Info(info) => {
+                    self.session_info = Some(info);
+                }
+                SessionEvent::Auth(AuthProgress::Trying(method)) => {
+                    self.write_line(&format!("Authenticating with {}...\r\n", method));
+                }
+                SessionEvent::Auth(AuthProgress::Failed(method, reason)) => {
+                    self.write_line(&format!("\x1b[33m{} failed: {}\x1b[0m\r\n", method, reason));
+                }
+                SessionEvent::Auth(AuthProgress::Succeeded(id)) => {
+                    // Reconnects go straight to what worked
+                    if let Some(ReconnectAuth::Methods(methods)) = &mut self.reconnect_auth {
+                        prefer_method(methods, &id);
+                    }
+                    self.record_auth(&id);
+                }
+                SessionEvent::Alive(rtt) => {
+                    self.last_rtt = Some(rtt);
+                    self.predictor.observe_rtt(rtt);
+                    if self.stale_since.take().is_some() {
+                        log::info!("{} still reachable ({} ms)", self.session_host, rtt.as_millis());
+                    }
+                }
+                SessionEvent::Data(data) => {
+                    self.last_output = Some(Instant::now());
+                    self.bytes_in += data.len() as u64;
+                    if let Some(capture) = &mut self.capture {
+                        capture.record_output(&data);
+                    }
+                    if let Some(file) = &mut self.session_log {
+                        if let Err(e) = std::io::Write::write_all(file, &data) {
+                            log::warn!("Stopped the session log: {}", e);
+                            self.session_log = None;
+                        }
+                    }
+                    let data = self.charset.decode(&data);
+                    if let Some(inspector) = self.inspector.as_mut().filter(|_| !self.inspector_paused) {
+                        inspector.feed(&data);
+                    }
+                    self.predictor.on_output(&data);
+                    self.terminal.process(&data);
+                    let responses = self.terminal.take_responses();
+                    if !responses.is_empty() {
+                        self.bytes_out += responses.len() as u64;
+                        self.send_reply(&responses);
+                    }
+                    self.scan_triggers();
+                }
+                SessionEvent::Disconnected => {
+                    self.connection_state = ConnectionState::Disconnected;
+                    self.is_connected = false;
+                    self.terminal.process(b"\r\n\x1b[33mConnection closed.\x1b[0m\r\n");
+                    should_clear_session = true;
+                }
+                SessionEvent::Error(err) => {
+                    self.connection_state = ConnectionState::Error(err.clone());
+                    self.is_connected = false;
+                    let msg = format!("\r\n\x1b[31mError: {}\x1b[0m\r\n", err);
+                    self.terminal.process(msg.as_bytes());
+                    should_clear_session = true;
+                    if self.stale_since.take().is_some() && self.reconnect_auth.is_some() {
+                        self.terminal.process(b"\x1b[33mNetwork changed, reconnecting...\x1b[0m\r\n");
+                        self.reconnect_requested = true;
+                    }
+                }
+            }
+        }
+
+        let stalled = self.connecting_since.map_or(false, |since| since.elapsed() >= CONNECT_WATCHDOG);
+        if stalled && self.connection_state == ConnectionState::Connecting {
+            log::warn!("Connection to {}@{} timed out", self.session_user, self.session_host);
+            self.connection_state = ConnectionState::Error("Connection timed out".to_string());
+            self.terminal.process(b"\r\n\x1b[31mConnection timed out\x1b[0m\r\n");
+            if let Some(session) = &self.active_session {
+                session.disconnect();
+            }
+            self.pending_session = None;
+            should_clear_session = true;
+        }
+
+        if should_clear_session {
+            self.active_session = None;
+            self.session_info = None;
+            self.connecting_since = None;
+        }
+
+        self.terminal.tick();
+    }
+
+    pub fn send_input(&mut self, data: &[u8]) {
+        if let Some(capture) = &mut self.capture {
+            capture.record_input(data);
+        }
+        if let Some(session) = &self.active_session {
+            session.send_data(self.charset.encode(data));
+        }
+    }
+
+    /// Send something the terminal answers by itself, like a status report;
+    /// unlike typed input, a capture keeps it
+    fn send_reply(&mut self, data: &[u8]) {
+        if let Some(capture) = &mut self.capture {
+            capture.record_reply(data);
+        }
+        if let Some(session) = &self.active_session {
+            session.send_data(self.charset.encode(data));
+        }
+    }
+
+    /// Start capturing the channel's bytes to a file in the data directory
+    fn start_capture(&mut self, options: CaptureOptions) {
+        let Some(dir) = PlatformManager::get_data_directory().map(|dir| dir.join("captures")) else {
+            log::warn!("No data directory for the traffic capture");
+            return;
+        };
+        let name = format!("{}-{}.log", self.session_host, chrono::Local::now().format("%Y%m%d-%H%M%S"));
+        let session = format!("{}@{}:{}", self.session_user, self.session_host, self.session_port);
+        match TrafficCapture::start(&dir.join(name), &session, options) {
+            Ok(capture) => {
+                log::info!("Capturing traffic of {} to {}", session, capture.path().display());
+                self.capture = Some(capture);
+            }
+            Err(e) => log::warn!("Could not start the traffic capture: {:#}", e),
+        }
+    }
+
+    fn stop_capture(&mut self) {
+        let Some(capture) = self.capture.take() else { return };
+        match capture.finish() {
+            Ok(path) => self.capture_saved = Some((path, Instant::now())),
+            Err(e) => log::warn!("Traffic capture did not finish cleanly: {}", e),
+        }
+    }
+
+    /// Start writing the host's output to `<host>-<time>.log` in the session
+    /// log directory, or stop
+    pub fn set_logging(&mut self, logging: bool) {
+        if !logging {
+            self.session_log = None;
+            return;
+        }
+        if self.session_log.is_some() {
+            return;
+        }
+        let Some(dir) = PlatformManager::get_data_directory().map(|dir| dir.join(SESSION_LOG_DIR)) else {
+            log::warn!("No data directory for the session log");
+            return;
+        };
+        let path = dir.join(format!("{}-{}.log", self.session_host, chrono::Local::now().format("%Y%m%d-%H%M%S")));
+        let file = std::fs::create_dir_all(&dir).and_then(|_| std::fs::File::create(&path));
+        match file {
+            Ok(file) => {
+                log::info!("Logging {} to {}", self.session_host, path.display());
+                self.session_log = Some(std::io::BufWriter::new(file));
+            }
+            Err(e) => log::warn!("Could not start the session log: {}", e),
+        }
+    }
+
+    pub fn is_logging(&self) -> bool {
+        self.session_log.is_some()
+    }
+
+    /// Carry out what the tab bar asked of this terminal
+    pub fn apply_request(&mut self, request: TerminalRequest) {
+        match request {
+            TerminalRequest::ClearScrollback => {
+                self.terminal.buffer_mut().clear_scrollback();
+                self.terminal.scroll_to_bottom();
+            }
+            TerminalRequest::SetLogging(logging) => self.set_logging(logging),
+            TerminalRequest::SetReadOnly(read_only) => self.set_read_only(read_only),
+            TerminalRequest::SendText(text) => self.type_input(text.as_bytes()),
+        }
+    }
+
+    /// Show output only and drop everything typed, e.g. while supervising
+    /// a job or sharing the screen
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+        self.blocked_at = None;
+        if read_only {
+            self.pending_command = None;
+        }
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Whether the user asked to share this terminal; the owner
+    /// calls `start_sharing`
+    pub fn take_share_request(&mut self) -> bool {
+        std::mem::take(&mut self.share_requested)
+    }
+
+    /// Stream a read-only view of the screen to viewers on this machine,
+    /// or on the LAN when settings allow it
+    pub fn start_sharing(&mut self, runtime: &tokio::runtime::Runtime) -> anyhow::Result<()> {
+        if self.share.is_none() {
+            self.share = Some(ShareServer::start(runtime, self.share_on_lan, 0, self.share_frame())?);
+        }
+        Ok(())
+    }
+
+    pub fn stop_sharing(&mut self) {
+        self.share = None;
+        self.share_published = None;
+    }
+
+    /// The visible screen as viewers see it
+    fn share_frame(&self) -> ShareFrame {
+        let size = self.terminal.size();
+        let (x, y) = self.terminal.buffer().cursor_position();
+        ShareFrame {
+            title: format!("{}@{}", self.session_user, self.session_host),
+            cols: size.cols,
+            rows: size.rows,
+            cursor: (x as u16, y as u16),
+            lines: self.export_rows(ExportRange::Screen).iter().map(|row| share::line_from_cells(row)).collect(),
+        }
+    }
+
+    /// Send the screen to viewers, at most every 50 ms
+    fn publish_share(&mut self) {
+        let Some(share) = &self.share else { return };
+        if self.share_published.is_some_and(|at| at.elapsed() < Duration::from_millis(50)) {
+            return;
+        }
+        share.publish(self.share_frame());
+        self.share_published = Some(Instant::now());
+    }
+
+    /// Send what the user typed, noting the time for the idle indicator
+    fn type_input(&mut self, data: &[u8]) {
+        if self.admit_input(data) {
+            self.send_typed(data);
+        }
+    }
+
+    /// The one gate for input meant for the shell, whether typed, sent over
+    /// IPC or relayed from another pane: read-only drops it, and on a host
+    /// that confirms commands anything that would run one is held for the
+    /// confirmation bar. Whether `data` may be sent now.
+    fn admit_input(&mut self, data: &[u8]) -> bool {
+        if self.read_only {
+            self.blocked_at = Some(Instant::now());
+            return false;
+        }
+        if self.pending_command.is_some() {
+            // Nothing may slip in behind a held command
+            return false;
+        }
+        if !self.confirm_commands || !approval::submits(data) {
+            return true;
+        }
+        let command = self.terminal.command_line();
+        // Enter at an empty prompt runs nothing
+        if command.as_deref() == Some("") && data.iter().all(|&b| b == b'\r' || b == b'\n') {
+            return true;
+        }
+        // Text typed along with the Enter, e.g. a pasted line, is part of the command
+        let typed: String = if data.contains(&0x1b) {
+            String::new()
+        } else {
+            String::from_utf8_lossy(data).replace(['\r', '\n'], " ").chars().filter(|c| !c.is_control()).collect()
+        };
+        self.pending_command = Some(HeldCommand {
+            command: command.map(|line| format!("{}{}", line, typed).trim_end().to_string()),
+            data: data.to_vec(),
+        });
+        false
+    }
+
+    fn send_typed(&mut self, data: &[u8]) {
+        self.last_input = Some(Instant::now());
+        self.bytes_out += data.len() as u64;
+        self.send_input(data);
+        self.predictor.on_input(data);
+        if self.broadcast {
+            self.broadcast_input.extend_from_slice(data);
+        }
+    }
+
+    /// Exercise the connection without sending anything to the shell
+    pub fn send_keepalive(&self) {
+        if let Some(session) = &self.active_session {
+            session.send_keepalive();
+        }
+    }
+
+    pub fn send_resize(&self, cols: u32, rows: u32) {
+        if let Some(session) = &self.active_session {
+            session.resize(cols, rows);
+        }
+    }
+
+    /// Keep the diagnostics view's figures for this tab current
+    fn report_memory(&self) {
+        let buffer = self.terminal.buffer();
+        let label = if self.session_host.is_empty() {
+            "Local".to_string()
+        } else {
+            format!("{}@{}", self.session_user, self.session_host)
+        };
+        memory::report_scrollback(
+            &self.id.to_string(),
+            &label,
+            buffer.total_lines(),
+            buffer.memory_usage(),
+            buffer.max_scrollback_bytes(),
+        );
+    }
+
+    pub fn disconnect(&mut self) {
+        if let Some(session) = &self.active_session {
+            session.disconnect();
+        }
+        self.stale_since = None;
+        self.reconnect_auth = None;
+        self.tunnel_wait = None;
+        self.active_session = None;
+        self.pending_session = None;
+        self.pending_command = None;
+        self.is_connected = false;
+        self.connecting_since = None;
+        self.connection_state = ConnectionState::Disconnected;
+    }
+
+    fn add_welcome_message(&mut self) {
+        self.write_line("TabSSH Terminal Emulator\r\n");
+        self.write_line("========================\r\n");
+        self.write_line("\r\n");
+        self.write_line("Full VT100/xterm terminal emulation with:\r\n");
+        self.write_line("  - 256-color and true color support\r\n");
+        self.write_line("  - UTF-8 text rendering\r\n");
+        self.write_line("  - Mouse support (SGR mouse mode)\r\n");
+        self.write_line("  - Scrollback buffer (10,000 lines)\r\n");
+        self.write_line("  - Text selection and clipboard\r\n");
+        self.write_line("  - Alternate screen buffer\r\n");
+        self.write_line("\r\n");
+        self.write_line("Connect to an SSH server to begin.\r\n");
+    }
+
+    /// Write text to the terminal (processed through parser)
+    pub fn write(&mut self, data: &[u8]) {
+        self.terminal.process(data);
+    }
+
+    /// Write a line of text
+    pub fn write_line(&mut self, text: &str) {
+        self.terminal.process(text.as_bytes());
+    }
+
+    /// Set connection status
+    pub fn set_connected(&mut self, connected: bool) {
+        self.is_connected = connected;
+        if connected {
+            self.write_line("\x1b[32mConnected successfully!\x1b[0m\r\n");
+            self.write_line("\r\n");
+            let now = chrono::Local::now();
+            self.write_line(&format!("Last login: {}\r\n", now.format("%c")));
+            self.write_line("\r\n");
+        } else {
+            self.write_line("\x1b[31mDisconnected.\x1b[0m\r\n");
+        }
+    }
+
+    /// Get terminal size
+    pub fn size(&self) -> TerminalSize {
+        self.terminal.size()
+    }
+
+    /// Resize terminal to new dimensions
+    pub fn resize(&mut self, cols: u16, rows: u16) {
+        if (cols, rows) != self.last_size {
+            self.terminal.resize(cols, rows);
+            self.last_size = (cols, rows);
+        }
+    }
+
+    /// Scroll to bottom
+    pub fn scroll_to_bottom(&mut self) {
+        self.terminal.scroll_to_bottom();
+    }
+
+    /// Clear terminal
+    pub fn clear(&mut self) {
+        self.terminal.clear();
+    }
+
+    /// Render the terminal view
+    pub fn render(&mut self, ui: &mut egui::Ui) {
+        // `render_panes` relays input in the frame it was typed; outside a
+        // pane group, or after broadcasting stopped, nothing will
+        self.broadcast_input.clear();
+        self.poll_session();
+        if self.terminal.is_synchronized() || self.connecting_since.is_some() {
+            // Keep frames coming so a stalled update or connect still times out
+            ui.ctx().request_repaint_after(Duration::from_millis(50));
+        }
+
+        if self.read_only {
+            self.render_read_only_banner(ui);
+        }
+        if self.pending_command.is_some() {
+            self.render_command_approval(ui);
+        }
+
+        let available = ui.available_size();
+
+        let response = egui::Frame::none()
+            .fill(egui::Color32::from_rgb(30, 30, 30))
+            .rounding(egui::Rounding::ZERO)
+            .show(ui, |ui| {
+                ui.set_min_size(available);
+
+                let old_size = self.last_size;
+                self.fit_to(available);
+
+                if old_size != self.last_size {
+                    self.send_resize(self.last_size.0 as u32, self.last_size.1 as u32);
+                }
+
+                if self.filter.is_some() {
+                    self.render_filter(ui);
+                } else {
+                    self.terminal.render(ui);
+                }
+            });
+
+        let rect = response.response.rect;
+        let input_id = ui.id().with("terminal_input");
+        let terminal_response = ui.interact(rect, input_id, egui::Sense::click());
+
+        if terminal_response.clicked() {
+            ui.memory_mut(|mem| mem.request_focus(input_id));
+        }
+
+        // The click or key that lifts the cover goes no further
+        let mut revealing = false;
+        if self.covered {
+            let key = ui.input(|i| {
+                i.focused && i.events.iter().any(|e| matches!(e, egui::Event::Key { pressed: true, .. } | egui::Event::Text(_)))
+            });
+            if terminal_response.clicked() || key {
+                self.covered = false;
+                self.attended = Instant::now();
+                revealing = true;
+            } else {
+                self.paint_cover(ui, rect);
+            }
+        }
+        terminal_response.context_menu(|ui| {
+            if self.is_connected && self.session_port != 0 {
+                if ui.button("New shell on this host").clicked() {
+                    self.shell_requested = true;
+                    ui.close_menu();
+                }
+                ui.separator();
+            }
+            let mut read_only = self.read_only;
+            if ui.checkbox(&mut read_only, "Read-only").on_hover_text("Show output but send nothing typed here").changed() {
+                self.set_read_only(read_only);
+                ui.close_menu();
+            }
+            if self.sharing_allowed {
+                if self.share.is_some() {
+                    if ui.button("Stop sharing").clicked() {
+                        self.stop_sharing();
+                        ui.close_menu();
+                    }
+                } else if ui.button("Share view...").clicked() {
+                    self.share_requested = true;
+                    ui.close_menu();
+                }
+            }
+            if ui.checkbox(&mut self.show_timestamps, "Show timestamps").changed() {
+                self.terminal.set_show_timestamps(self.show_timestamps);
+                ui.close_menu();
+            }
+            if ui.button("Filter output...").clicked() {
+                self.filter = Some(FilterOptions::default());
+                ui.close_menu();
+            }
+            if ui.button("Export as HTML or PDF...").clicked() {
+                self.export = Some(ExportDialog {
+                    range: ExportRange::Screen,
+                    format: ExportFormat::Html,
+                    scheme: ExportScheme::Theme,
+                });
+                ui.close_menu();
+            }
+            if self.capture.is_some() {
+                if ui.button("Stop traffic capture").clicked() {
+                    self.stop_capture();
+                    ui.close_menu();
+                }
+            } else if ui.button("Capture traffic...").on_hover_text("Record the raw output to a file to debug rendering").clicked() {
+                self.capture_dialog = Some(CaptureOptions::default());
+                ui.close_menu();
+            }
+            if ui.button("Copy all with timestamps").clicked() {
+                let buffer = self.terminal.buffer();
+                let last = buffer.total_lines().saturating_sub(1);
+                let text = buffer.text_range_with_stamps((0, 0), (last, usize::MAX - 1));
+                ui.output_mut(|o| o.copied_text = text);
+                ui.close_menu();
+            }
+        });
+
+        if self.capture_dialog.is_some() {
+            self.render_capture_dialog(ui.ctx());
+        }
+
+        if self.export.is_some() {
+            self.render_export(ui.ctx());
+        }
+
+        // Keystrokes belong to the notes editor, scratchpad or filter bar while focused
+        if !self.notes_focused && !self.scratchpad_focused && self.filter.is_none() && !self.covered && !revealing {
+            self.handle_keyboard_input(ui);
+        }
+
+        if self.filter.is_none() && !self.covered {
+            self.show_predictions(ui, rect);
+        }
+
+        // Focused means both the window and this terminal have keyboard focus
+        let focused = ui.memory(|mem| mem.has_focus(input_id)) && ui.input(|i| i.focused);
+        if focused && !self.covered {
+            self.show_ime(ui, rect);
+        }
+        self.update_focus(focused);
+        self.update_privacy(ui, focused);
+        self.publish_share();
+        if self.share.is_some() {
+            ui.ctx().request_repaint_after(Duration::from_millis(50));
+        }
+    }
+
+    /// Sharing indicator; click for the address and token to give viewers
+    fn render_share_status(&mut self, ui: &mut egui::Ui) {
+        let Some(share) = &self.share else { return };
+        let text = RichText::new(format!("\u{1F4E1} Sharing ({})", share.viewers())).size(11.0).color(colors::WARNING);
+        let hover = if share.is_lan() {
+            "Read-only view shared on the local network"
+        } else {
+            "Read-only view shared with viewers on this machine"
+        };
+        let response = ui.add(egui::Button::new(text).frame(false)).on_hover_text(hover);
+        let popup_id = ui.make_persistent_id("share_popup");
+        if response.clicked() {
+            ui.memory_mut(|m| m.toggle_popup(popup_id));
+        }
+        let (address, token) = (share.address().to_string(), share.token().to_string());
+        let mut stop = false;
+        egui::popup::popup_above_or_below_widget(ui, popup_id, &response, egui::AboveOrBelow::Above, |ui| {
+            ui.set_min_width(280.0);
+            egui::Grid::new("share_details").num_columns(3).show(ui, |ui| {
+                for (label, value) in [("Address", &address), ("Token", &token)] {
+                    ui.label(label);
+                    ui.monospace(value);
+                    if ui.small_button("Copy").clicked() {
+                        ui.output_mut(|o| o.copied_text = value.clone());
+                    }
+                    ui.end_row();
+                }
+            });
+            ui.label(RichText::new("Anyone with both can watch this terminal").color(colors::TEXT_MUTED).size(11.0));
+            stop = ui.button("Stop sharing").clicked();
+        });
+        if stop {
+            self.stop_sharing();
+        }
+    }
+
+    /// Strip above the grid while input is blocked; flashes when a
+    /// keystroke was dropped
+    fn render_read_only_banner(&mut self, ui: &mut egui::Ui) {
+        let flashing = self.blocked_at.is_some_and(|at| at.elapsed() < Duration::from_millis(600));
+        if flashing {
+            ui.ctx().request_repaint_after(Duration::from_millis(100));
+        }
+        egui::Frame::none()
+            .fill(if flashing { colors::DANGER } else { colors::WARNING })
+            .inner_margin(egui::Margin::symmetric(spacing::SM, 2.0))
+            .show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("\u{1F441} Read-only: keyboard input is not sent to this session")
+                        .color(egui::Color32::BLACK)
+                        .size(12.0));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.small_button("Allow input").clicked() {
+                            self.set_read_only(false);
+                        }
+                    });
+                });
+            });
+    }
+
+    /// Confirmation bar for a held command; red when it looks destructive
+    fn render_command_approval(&mut self, ui: &mut egui::Ui) {
+        let Some(command) = self.pending_command.as_ref().map(|held| held.command.clone()) else { return };
+        let warning = command.as_deref().and_then(approval::risk);
+        egui::Frame::none()
+            .fill(if warning.is_some() { colors::DANGER } else { colors::WARNING })
+            .inner_margin(egui::Margin::symmetric(spacing::SM, 4.0))
+            .show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("Run on critical host?").color(egui::Color32::BLACK).strong().size(12.0));
+                    match &command {
+                        Some(command) => ui.label(RichText::new(command).monospace().color(egui::Color32::BLACK)),
+                        None => ui.label(RichText::new("Command not known without shell integration").italics().color(egui::Color32::BLACK)),
+                    };
+                    if let Some(warning) = warning {
+                        ui.label(RichText::new(format!("\u{26A0} {}", warning)).color(egui::Color32::BLACK).size(12.0));
+                    }
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.small_button("Cancel (Esc)").clicked() {
+                            self.pending_command = None;
+                        }
+                        if ui.small_button("Run (Enter)").clicked() {
+                            self.approve_command();
+                        }
+                    });
+                });
+            });
+    }
+
+    /// Send the held input
+    fn approve_command(&mut self) {
+        if let Some(held) = self.pending_command.take() {
+            self.send_typed(&held.data);
+        }
+    }
+
+    /// Filter bar and the lines it matches, shown in place of the grid.
+    /// The buffer is untouched; closing the bar brings the terminal back.
+    fn render_filter(&mut self, ui: &mut egui::Ui) {
+        let Some(options) = self.filter.as_mut() else { return };
+        let mut close = false;
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("\u{1F50E} Filter").color(colors::TEXT_SECONDARY));
+            let edit = ui.add(egui::TextEdit::singleline(&mut options.pattern)
+                .hint_text("Pattern")
+                .desired_width(240.0));
+            if options.pattern.is_empty() && !edit.has_focus() {
+                edit.request_focus();
+            }
+            ui.checkbox(&mut options.regex, "Regex");
+            ui.checkbox(&mut options.case_sensitive, "Match case");
+            ui.checkbox(&mut options.invert, "Invert");
+            ui.label(RichText::new("Context").color(colors::TEXT_SECONDARY));
+            ui.add(egui::DragValue::new(&mut options.context).clamp_range(0..=20));
+            close = ui.small_button("\u{2715}").on_hover_text("Close filter (Esc)").clicked()
+                || ui.input(|i| i.key_pressed(egui::Key::Escape));
+        });
+        if close {
+            self.filter = None;
+            self.filter_cache = None;
+            return;
+        }
+        ui.separator();
+
+        if options.pattern.is_empty() {
+            ui.label(RichText::new("Type a pattern to show only matching lines").color(colors::TEXT_MUTED));
+            return;
+        }
+
+        // The regex is compiled again only when the options change, and
+        // the lines are matched again only when the output does
+        let cache = match self.filter_cache.take() {
+            Some(cache) if cache.options == *options => cache,
+            _ => FilterCache {
+                options: options.clone(),
+                filter: LineFilter::new(options).map_err(|e| format!("{:#}", e)),
+                generation: None,
+                lines: Vec::new(),
+                rows: Vec::new(),
+                matches: 0,
+            },
+        };
+        let cache = self.filter_cache.insert(cache);
+        let filter = match &cache.filter {
+            Ok(filter) => filter,
+            Err(e) => {
+                ui.label(RichText::new(e.as_str()).color(colors::ERROR));
+                return;
+            }
+        };
+        let generation = self.terminal.generation();
+        if cache.generation != Some(generation) {
+            let buffer = self.terminal.buffer();
+            cache.lines = (0..buffer.total_lines()).map(|index| buffer.line_text(index)).collect();
+            cache.rows = filter.apply(&cache.lines);
+            cache.matches = cache.rows.iter()
+                .filter(|row| matches!(row, FilteredLine::Line { matched: true, .. }))
+                .count();
+            cache.generation = Some(generation);
+        }
+        let (lines, rows) = (&cache.lines, &cache.rows);
+        ui.label(RichText::new(format!("{} matching line(s) of {}", cache.matches, lines.len()))
+            .color(colors::TEXT_MUTED)
+            .size(11.0));
+
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        let mut jump = None;
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .stick_to_bottom(true)
+            .show_rows(ui, row_height, rows.len(), |ui, range| {
+                for row in &rows[range] {
+                    match *row {
+                        FilteredLine::Line { index, matched } => {
+                            let color = if matched { colors::TEXT_PRIMARY } else { colors::TEXT_MUTED };
+                            let text = RichText::new(format!("{:>6}  {}", index + 1, lines[index]))
+                                .monospace()
+                                .color(color);
+                            let response = ui.add(egui::Label::new(text).sense(egui::Sense::click()));
+                            if response.on_hover_text("Double-click to show in the terminal").double_clicked() {
+                                jump = Some(index);
+                            }
+                        }
+                        FilteredLine::Gap => {
+                            ui.label(RichText::new("      --").monospace().color(colors::TEXT_MUTED));
+                        }
+                    }
+                }
+            });
+
+        if let Some(index) = jump {
+            self.terminal.set_scroll_offset(index);
+            self.filter = None;
+            self.filter_cache = None;
+        }
+    }
+
+    /// Report focus changes to applications that asked for them (mode 1004)
+    /// Dialog choosing what to export and how it should look
+    fn render_capture_dialog(&mut self, ctx: &egui::Context) {
+        let Some(options) = self.capture_dialog.as_mut() else { return };
+        let mut open = true;
+        let mut start = false;
+        egui::Window::new("Capture Traffic")
+            .id(self.id.with("capture"))
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.checkbox(&mut options.annotate, "Name the escape sequences in each read");
+                let mut megabytes = options.max_bytes / (1024 * 1024);
+                ui.horizontal(|ui| {
+                    ui.label("Stop at");
+                    ui.add(egui::DragValue::new(&mut megabytes).clamp_range(1..=1024).suffix(" MB"));
+                });
+                options.max_bytes = megabytes * 1024 * 1024;
+                ui.label(RichText::new("Only the length of what you type is recorded, and clipboard contents are left out.")
+                    .size(11.0)
+                    .color(colors::TEXT_MUTED));
+                ui.add_space(spacing::SM);
+                start = ui.button("Start capture").clicked();
+            });
+
+        if start {
+            let options = *options;
+            self.start_capture(options);
+        }
+        if start || !open {
+            self.capture_dialog = None;
+        }
+    }
+
+    fn render_export(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = self.export.as_mut() else { return };
+        let mut open = true;
+        let mut done = false;
+        egui::Window::new("Export Output")
+            .id(self.id.with("export"))
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                egui::Grid::new(self.id.with("export_grid")).num_columns(2).show(ui, |ui| {
+                    ui.label("Include");
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut dialog.range, ExportRange::Screen, "Visible screen");
+                        ui.radio_value(&mut dialog.range, ExportRange::Scrollback, "All scrollback");
+                    });
+                    ui.end_row();
+                    ui.label("Format");
+                    ui.horizontal(|ui| {
+                        for format in ExportFormat::ALL {
+                            ui.radio_value(&mut dialog.format, format, format.to_string());
+                        }
+                    });
+                    ui.end_row();
+                    ui.label("Colors");
+                    ui.horizontal(|ui| {
+                        for scheme in [ExportScheme::Theme, ExportScheme::Printer] {
+                            ui.radio_value(&mut dialog.scheme, scheme, scheme.to_string());
+                        }
+                    });
+                    ui.end_row();
+                });
+                ui.add_space(spacing::SM);
+                done = ui.button("Export").clicked();
+            });
+
+        if done {
+            let (range, format, scheme) = (dialog.range, dialog.format, dialog.scheme);
+            let rows = self.export_rows(range);
+            let title = format!("{}@{}", self.session_user, self.session_host);
+            let contents = match format {
+                ExportFormat::Html => export::to_html(&rows, &self.export_palette, scheme, &title).into_bytes(),
+                ExportFormat::Pdf => export::to_pdf(&rows, &self.export_palette, scheme, &title),
+            };
+            self.export_ready = Some(TerminalExport {
+                file_name: format!(
+                    "{}-{}.{}",
+                    self.session_host,
+                    chrono::Local::now().format("%Y%m%d-%H%M%S"),
+                    format.extension(),
+                ),
+                contents,
+            });
+        }
+        if done || !open {
+            self.export = None;
+        }
+    }
+
+    /// Buffer lines as export cells; colors equal to the defaults stay unset
+    /// so the chosen scheme decides them
+    fn export_rows(&self, range: ExportRange) -> Vec<Vec<ExportCell>> {
+        let buffer = self.terminal.buffer();
+        let lines = match range {
+            ExportRange::Screen => {
+                let start = self.terminal.scroll_offset();
+                start..(start + self.terminal.size().rows as usize).min(buffer.total_lines())
+            }
+            ExportRange::Scrollback => 0..buffer.total_lines(),
+        };
+        lines
+            .filter_map(|index| buffer.get_line(index))
+            .map(|row| {
+                row.iter()
+                    .map(|cell| ExportCell {
+                        c: cell.character,
+                        fg: (cell.fg != Color::WHITE).then(|| [cell.fg.r, cell.fg.g, cell.fg.b]),
+                        bg: (cell.bg != Color::BLACK).then(|| [cell.bg.r, cell.bg.g, cell.bg.b]),
+                        bold: cell.attrs.bold,
+                        italic: cell.attrs.italic,
+                        underline: cell.attrs.underline,
+                        dim: cell.attrs.dim,
+                        inverse: cell.attrs.inverse,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Put the cover up once the terminal has gone unfocused for long enough
+    fn update_privacy(&mut self, ui: &egui::Ui, focused: bool) {
+        if self.privacy_cover == PrivacyCover::Off || self.covered {
+            return;
+        }
+        if focused {
+            self.attended = Instant::now();
+            return;
+        }
+        match PrivacyCover::wait(self.attended.elapsed(), self.privacy_after) {
+            Some(left) => ui.ctx().request_repaint_after(left),
+            None => {
+                self.covered = true;
+                ui.ctx().request_repaint();
+            }
+        }
+    }
+
+    /// egui cannot blur, so Blur is a veil that leaves only the shape of the
+    /// output; Blank paints the background over everything
+    fn paint_cover(&self, ui: &egui::Ui, rect: egui::Rect) {
+        let fill = match self.privacy_cover {
+            PrivacyCover::Blank => egui::Color32::from_rgb(30, 30, 30),
+            _ => egui::Color32::from_rgba_unmultiplied(30, 30, 30, 238),
+        };
+        let painter = ui.painter();
+        painter.rect_filled(rect, 0.0, fill);
+        painter.text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            "\u{1F512} Hidden while idle \u{2014} click or press a key to show",
+            egui::FontId::proportional(13.0),
+            colors::TEXT_MUTED,
+        );
+    }
+
+    /// The screen shrunk into `rect` for the tab overview; a covered
+    /// terminal stays covered there too
+    pub fn paint_thumbnail(&self, painter: &egui::Painter, rect: egui::Rect) {
+        if self.covered {
+            painter.text(rect.center(), egui::Align2::CENTER_CENTER, "\u{1F512}", egui::FontId::proportional(18.0), colors::TEXT_MUTED);
+            return;
+        }
+        renderer::paint_thumbnail(painter, rect, self.terminal.buffer());
+        let state = match &self.connection_state {
+            ConnectionState::Connected => return,
+            ConnectionState::Connecting => "Connecting...",
+            ConnectionState::WaitingForCredentials => "Waiting for credentials",
+            ConnectionState::Disconnected => "Disconnected",
+            ConnectionState::Error(_) => "Connection failed",
+        };
+        painter.rect_filled(rect, 0.0, egui::Color32::from_rgba_unmultiplied(30, 30, 30, 200));
+        painter.text(rect.center(), egui::Align2::CENTER_CENTER, state, egui::FontId::proportional(12.0), colors::TEXT_SECONDARY);
+    }
+
+    pub fn update_focus(&mut self, focused: bool) {
+        if focused == self.had_focus {
+            return;
+        }
+        self.had_focus = focused;
+        if self.is_connected && self.terminal.input_modes().focus_reporting {
+            self.send_reply(focus_report(focused));
+        }
+    }
+
+    /// Width and height of one character cell
+    fn char_size(&self) -> (f32, f32) {
+        (self.font_size * 0.6, self.font_size * 1.2)
+    }
+
+    /// Columns and rows that fit in `available`
+    pub fn grid_for(&self, available: egui::Vec2) -> (u16, u16) {
+        let (char_width, char_height) = self.char_size();
+        let gutter = if self.show_timestamps { GUTTER_CHARS * char_width } else { 0.0 };
+        let cols = ((available.x - gutter) / char_width) as u16;
+        let rows = (available.y / char_height) as u16;
+        (cols.max(1), rows.max(1))
+    }
+
+    /// Size the grid to `available` without telling the server.
+    ///
+    /// Call with the tab's content size before connecting so the PTY is
+    /// requested at its real size instead of 80x24.
+    pub fn fit_to(&mut self, available: egui::Vec2) {
+        let (cols, rows) = self.grid_for(available);
+        self.resize(cols, rows);
+    }
+
+    /// Screen rectangle of the character cell under the cursor
+    fn cursor_rect(&self, origin: egui::Pos2) -> egui::Rect {
+        let (char_width, char_height) = self.char_size();
+        let (x, y) = self.terminal.buffer().cursor_position();
+        egui::Rect::from_min_size(
+            origin + egui::vec2(x as f32 * char_width, y as f32 * char_height),
+            egui::vec2(char_width, char_height),
+        )
+    }
+
+    /// Place the platform's candidate window at the cursor and draw any preedit text
+    fn show_ime(&self, ui: &mut egui::Ui, rect: egui::Rect) {
+        let cursor = self.cursor_rect(rect.min);
+        ui.ctx().output_mut(|o| {
+            o.ime = Some(egui::output::IMEOutput { rect, cursor_rect: cursor });
         });
+
+        if self.preedit.is_empty() {
+            return;
+        }
+        let font = egui::FontId::monospace(self.font_size);
+        let galley = ui.painter().layout_no_wrap(self.preedit.clone(), font, colors::TEXT_PRIMARY);
+        let text_rect = egui::Rect::from_min_size(cursor.min, galley.size());
+        let painter = ui.painter();
+        painter.rect_filled(text_rect, 0.0, egui::Color32::from_rgb(30, 30, 30));
+        painter.galley(cursor.min, galley);
+        painter.line_segment(
+            [text_rect.left_bottom(), text_rect.right_bottom()],
+            egui::Stroke::new(1.0, colors::TEXT_PRIMARY),
+        );
+    }
+
+    /// Draw predicted echo at the cursor, dimmed and underlined until confirmed
+    fn show_predictions(&mut self, ui: &mut egui::Ui, rect: egui::Rect) {
+        let Some(predicted) = self.predictor.display() else {
+            return;
+        };
+        let cursor = self.cursor_rect(rect.min);
+        let font = egui::FontId::monospace(self.font_size);
+        let galley = ui.painter().layout_no_wrap(predicted, font, colors::TEXT_MUTED);
+        let text_rect = egui::Rect::from_min_size(cursor.min, galley.size());
+        let painter = ui.painter();
+        painter.rect_filled(text_rect, 0.0, egui::Color32::from_rgb(30, 30, 30));
+        painter.galley(cursor.min, galley);
+        painter.line_segment(
+            [text_rect.left_bottom(), text_rect.right_bottom()],
+            egui::Stroke::new(1.0, colors::TEXT_MUTED),
+        );
+        // Expire predictions the server never confirms
+        ui.ctx().request_repaint_after(Duration::from_millis(250));
+    }
+
+    fn handle_keyboard_input(&mut self, ui: &mut egui::Ui) {
+        if !self.is_connected {
+            return;
+        }
+
+        if self.pending_command.is_some() {
+            // The confirmation bar has the keyboard until it is answered
+            let (run, cancel) = ui.input(|i| (i.key_pressed(egui::Key::Enter), i.key_pressed(egui::Key::Escape)));
+            if run {
+                self.approve_command();
+            } else if cancel {
+                self.pending_command = None;
+            }
+            return;
+        }
+
+        let modes = self.terminal.input_modes();
+        let (events, current_modifiers) = ui.input(|i| (i.events.clone(), i.modifiers));
+        let mut keys = KeyEvents::default();
+        for event in events {
+            match event {
+                egui::Event::CompositionStart => {
+                    self.preedit.clear();
+                }
+                egui::Event::CompositionUpdate(text) => {
+                    self.preedit = text;
+                }
+                egui::Event::CompositionEnd(text) => {
+                    // Composed text is committed as UTF-8 without an Alt prefix
+                    self.preedit.clear();
+                    self.type_input(text.as_bytes());
+                }
+                egui::Event::Text(text) => {
+                    if let Some(data) = keys.text(&text, &current_modifiers, &self.keyboard) {
+                        self.type_input(&data);
+                    }
+                }
+                // Keys pressed while composing belong to the input method
+                egui::Event::Key { .. } if !self.preedit.is_empty() => {}
+                egui::Event::Key { key, pressed: true, modifiers, .. } => {
+                    if let Some(data) = keys.key(key, &modifiers, &modes, &self.keyboard) {
+                        self.type_input(&data);
+                        if self.sync && !self.read_only && is_navigation_key(key) {
+                            self.sync_keys.push((key, modifiers));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
     }
 
     /// Render terminal with status bar
     pub fn render_with_status(&mut self, ui: &mut egui::Ui) {
-        let (status_color, status_text) = match &self.connection_state {
-            ConnectionState::Connected => (colors::SUCCESS, "Connected"),
-            ConnectionState::Connecting => (colors::WARNING, "Connecting..."),
-            ConnectionState::WaitingForCredentials => (colors::WARNING, "Awaiting credentials"),
-            ConnectionState::Disconnected => (colors::TEXT_MUTED, "Disconnected"),
-            ConnectionState::Error(_) => (colors::DANGER, "Error"),
-        };
+        let inspector_key = egui::Modifiers::CTRL | egui::Modifiers::SHIFT;
+        if self.had_focus && ui.input_mut(|i| i.consume_key(inspector_key, egui::Key::F12)) {
+            self.inspector = match self.inspector {
+                Some(_) => None,
+                None => Some(SequenceLog::new(INSPECTOR_ENTRIES)),
+            };
+        }
 
-        egui::TopBottomPanel::bottom("terminal_status")
+        egui::TopBottomPanel::bottom(self.id.with("terminal_status"))
             .exact_height(24.0)
             .frame(egui::Frame::none().fill(colors::BG_SECONDARY))
             .show_inside(ui, |ui| {
                 ui.horizontal_centered(|ui| {
                     ui.add_space(spacing::SM);
 
-                    ui.label(RichText::new("\u{25CF}").color(status_color).size(10.0));
-                    ui.label(RichText::new(status_text).color(status_color).size(11.0));
+                    for config in self.status_bar.clone() {
+                        if config.visible && self.render_segment(ui, config) {
+                            ui.separator();
+                        }
+                    }
+
+                    if self.is_connected {
+                        self.render_idle(ui);
+                        ui.separator();
+                        if let Some(info) = &self.session_info {
+                            render_session_info(ui, info);
+                            ui.separator();
+                        }
+                        // Only SSH sessions without a command of their own can list containers
+                        if self.session_port != 0 && self.remote_command.is_none() {
+                            self.render_containers(ui);
+                            ui.separator();
+                            if !self.service_actions.is_empty() {
+                                self.render_service_actions(ui);
+                                ui.separator();
+                            }
+                            let label = RichText::new("\u{2699} Processes").size(11.0).color(if self.processes_open {
+                                colors::TEXT_PRIMARY
+                            } else {
+                                colors::TEXT_MUTED
+                            });
+                            if ui.add(egui::Button::new(label).frame(false))
+                                .on_hover_text("Processes on this host, refreshed every few seconds")
+                                .clicked()
+                            {
+                                self.processes_open = !self.processes_open;
+                            }
+                            ui.separator();
+                        }
+                    }
 
-                    ui.separator();
+                    let broadcast = RichText::new("\u{1F4E2} Broadcast").size(11.0).color(if self.broadcast {
+                        colors::WARNING
+                    } else {
+                        colors::TEXT_MUTED
+                    });
+                    if ui.add(egui::Button::new(broadcast).frame(false))
+                        .on_hover_text("Send what you type here to every broadcasting pane")
+                        .clicked()
+                    {
+                        self.broadcast = !self.broadcast;
+                    }
 
-                    if !self.session_host.is_empty() {
-                        ui.label(RichText::new(format!(
-                            "{}@{}:{}",
-                            self.session_user, self.session_host, self.session_port
-                        ))
-                        .color(colors::TEXT_SECONDARY)
-                        .size(11.0));
+                    let sync = RichText::new("\u{1F517} Sync").size(11.0).color(if self.sync {
+                        colors::PRIMARY
+                    } else {
+                        colors::TEXT_MUTED
+                    });
+                    if ui.add(egui::Button::new(sync).frame(false))
+                        .on_hover_text("Scroll and move through output together with the other synced panes")
+                        .clicked()
+                    {
+                        self.sync = !self.sync;
+                    }
+
+                    if self.share.is_some() {
                         ui.separator();
+                        self.render_share_status(ui);
                     }
 
-                    let size = self.terminal.size();
-                    ui.label(RichText::new(format!("{}x{}", size.cols, size.rows))
-                        .color(colors::TEXT_MUTED)
-                        .size(11.0));
+                    if let Some(capture) = &self.capture {
+                        ui.separator();
+                        let text = if capture.is_full() {
+                            "\u{23FA} Capture stopped".to_string()
+                        } else {
+                            format!("\u{23FA} Capturing {}", format_file_size(capture.written()))
+                        };
+                        if ui.add(egui::Button::new(RichText::new(text).size(11.0).color(colors::DANGER)).frame(false))
+                            .on_hover_text(format!("{}\nClick to stop", capture.path().display()))
+                            .clicked()
+                        {
+                            self.stop_capture();
+                        }
+                    } else if let Some((path, at)) = &self.capture_saved {
+                        if at.elapsed() < Duration::from_secs(10) {
+                            ui.separator();
+                            ui.label(RichText::new("Capture saved").size(11.0).color(colors::TEXT_MUTED))
+                                .on_hover_text(path.display().to_string());
+                        }
+                    }
 
-                    ui.separator();
+                    if self.notes.is_some() {
+                        ui.separator();
+                        let notes = RichText::new("\u{1F4DD} Notes").size(11.0).color(if self.notes_open {
+                            colors::TEXT_PRIMARY
+                        } else {
+                            colors::TEXT_MUTED
+                        });
+                        if ui.add(egui::Button::new(notes).frame(false)).clicked() {
+                            self.notes_open = !self.notes_open;
+                        }
+                    }
 
-                    let scrollback = self.terminal.buffer().scrollback_len();
-                    ui.label(RichText::new(format!("{} lines in scrollback", scrollback))
-                        .color(colors::TEXT_MUTED)
-                        .size(11.0));
+                    ui.separator();
+                    let scratchpad = RichText::new("\u{1F5D2} Scratchpad").size(11.0).color(if self.scratchpad_open {
+                        colors::TEXT_PRIMARY
+                    } else {
+                        colors::TEXT_MUTED
+                    });
+                    if ui.add(egui::Button::new(scratchpad).frame(false))
+                        .on_hover_text("Jot down commands and findings for this tab")
+                        .clicked()
+                    {
+                        self.scratchpad_open = !self.scratchpad_open;
+                    }
                 });
             });
 
+        if self.inspector.is_some() {
+            egui::TopBottomPanel::bottom(self.id.with("terminal_inspector"))
+                .resizable(true)
+                .default_height(240.0)
+                .frame(egui::Frame::none().fill(colors::BG_SECONDARY).inner_margin(spacing::SM))
+                .show_inside(ui, |ui| self.render_inspector(ui));
+        }
+
+        if self.service_run.is_some() {
+            egui::TopBottomPanel::bottom(self.id.with("terminal_service_output"))
+                .resizable(true)
+                .default_height(180.0)
+                .frame(egui::Frame::none().fill(colors::BG_SECONDARY).inner_margin(spacing::SM))
+                .show_inside(ui, |ui| self.render_service_output(ui));
+        }
+
+        egui::SidePanel::right(self.id.with("terminal_processes"))
+            .resizable(true)
+            .default_width(460.0)
+            .frame(egui::Frame::none().fill(colors::BG_SECONDARY).inner_margin(spacing::SM))
+            .show_animated_inside(ui, self.processes_open && self.is_connected, |ui| self.render_processes(ui));
+
+        self.scratchpad_focused = false;
+        egui::SidePanel::right(self.id.with("terminal_scratchpad"))
+            .resizable(true)
+            .default_width(300.0)
+            .frame(egui::Frame::none().fill(colors::BG_SECONDARY).inner_margin(spacing::SM))
+            .show_animated_inside(ui, self.scratchpad_open, |ui| self.render_scratchpad(ui));
+
+        self.notes_focused = false;
+        if self.notes.is_some() {
+            egui::SidePanel::right(self.id.with("terminal_notes"))
+                .resizable(true)
+                .default_width(300.0)
+                .frame(egui::Frame::none().fill(colors::BG_SECONDARY).inner_margin(spacing::SM))
+                .show_animated_inside(ui, self.notes_open, |ui| self.render_notes(ui));
+        }
+
         egui::CentralPanel::default()
             .frame(egui::Frame::none())
             .show_inside(ui, |ui| {
                 self.render(ui);
             });
     }
+
+    /// Text and color of a status bar segment, or None when it has nothing to show
+    fn segment_text(&self, segment: StatusSegment) -> Option<(String, egui::Color32)> {
+        match segment {
+            StatusSegment::State => {
+                let (color, text) = match &self.connection_state {
+                    ConnectionState::Connected if self.stale_since.is_some() => (colors::WARNING, "Checking connection..."),
+                    ConnectionState::Connected => (colors::SUCCESS, "Connected"),
+                    ConnectionState::Connecting => (colors::WARNING, "Connecting..."),
+                    ConnectionState::WaitingForCredentials => (colors::WARNING, "Awaiting credentials"),
+                    ConnectionState::Disconnected => (colors::TEXT_MUTED, "Disconnected"),
+                    ConnectionState::Error(_) => (colors::DANGER, "Error"),
+                };
+                Some((format!("\u{25CF} {}", text), color))
+            }
+            StatusSegment::UserHost if self.session_host.is_empty() => None,
+            // Local process sessions have no user or port
+            StatusSegment::UserHost if self.session_port == 0 => Some((self.session_host.clone(), colors::TEXT_SECONDARY)),
+            StatusSegment::UserHost => Some((
+                format!("{}@{}:{}", self.session_user, self.session_host, self.session_port),
+                colors::TEXT_SECONDARY,
+            )),
+            StatusSegment::Size => {
+                let size = self.terminal.size();
+                Some((format!("{}x{}", size.cols, size.rows), colors::TEXT_MUTED))
+            }
+            StatusSegment::Scrollback => Some((
+                format!("{} lines in scrollback", self.terminal.buffer().scrollback_len()),
+                colors::TEXT_MUTED,
+            )),
+            StatusSegment::Latency => {
+                let text = self.last_rtt.map_or("- ms".to_string(), |rtt| format!("{} ms", rtt.as_millis()));
+                Some((text, colors::TEXT_MUTED))
+            }
+            StatusSegment::Bytes => Some((
+                format!("\u{2193}{} \u{2191}{}", status::format_bytes(self.bytes_in), status::format_bytes(self.bytes_out)),
+                colors::TEXT_MUTED,
+            )),
+            StatusSegment::Clock => Some((chrono::Local::now().format("%H:%M").to_string(), colors::TEXT_MUTED)),
+            StatusSegment::Uptime => {
+                let connected = self.connected_at.filter(|_| self.is_connected)?;
+                Some((format!("up {}", format_idle(connected.elapsed())), colors::TEXT_MUTED))
+            }
+            StatusSegment::LastCommand => {
+                let timer = self.terminal.command_timer();
+                if let Some(running) = timer.running_for() {
+                    return Some((format!("running {}", format_command_duration(running)), colors::WARNING));
+                }
+                let last = timer.last()?;
+                let color = match last.exit_code {
+                    Some(code) if code != 0 => colors::DANGER,
+                    _ => colors::TEXT_MUTED,
+                };
+                Some((format!("last {}", format_command_duration(last.duration)), color))
+            }
+        }
+    }
+
+    /// Draw one segment and run its click action; false if it drew nothing
+    fn render_segment(&mut self, ui: &mut egui::Ui, config: StatusSegmentConfig) -> bool {
+        let Some((text, color)) = self.segment_text(config.segment) else {
+            return false;
+        };
+        let label = RichText::new(&text).color(color).size(11.0);
+        if config.on_click == SegmentAction::Nothing {
+            ui.label(label);
+        } else {
+            let response = ui.add(egui::Button::new(label).frame(false))
+                .on_hover_text(config.on_click.label());
+            if response.clicked() {
+                self.run_segment_action(ui, config.on_click, text);
+            }
+        }
+        if matches!(config.segment, StatusSegment::Clock | StatusSegment::Uptime | StatusSegment::LastCommand) {
+            ui.ctx().request_repaint_after(Duration::from_secs(1));
+        }
+        true
+    }
+
+    fn run_segment_action(&mut self, ui: &mut egui::Ui, action: SegmentAction, text: String) {
+        match action {
+            SegmentAction::Nothing => {}
+            SegmentAction::Copy => ui.output_mut(|o| o.copied_text = text),
+            SegmentAction::Probe => {
+                if let Some(session) = &self.active_session {
+                    session.probe();
+                }
+            }
+            // The owner reconnects through `take_reconnect_request`
+            SegmentAction::Reconnect if self.reconnect_auth.is_some() => {
+                if let Some(session) = self.active_session.take() {
+                    session.disconnect();
+                }
+                self.is_connected = false;
+                self.stale_since = None;
+                self.reconnect_requested = true;
+            }
+            SegmentAction::Reconnect => {}
+            SegmentAction::ResetCounters => {
+                self.bytes_in = 0;
+                self.bytes_out = 0;
+            }
+        }
+    }
+
+    /// Store edited notes on the saved connection
+    fn save_notes(&self, notes: &str) -> anyhow::Result<()> {
+        let id = self.connection_id.as_deref().ok_or_else(|| anyhow::anyhow!("This session has no saved connection"))?;
+        ConnectionProfile::save_notes(id, notes, &Database::open()?)
+    }
+
+    /// Profile notes as Markdown, editable in place
+    fn render_notes(&mut self, ui: &mut egui::Ui) {
+        if self.notes.is_none() {
+            return;
+        }
+
+        let mut save = None;
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("Notes").strong().color(colors::TEXT_PRIMARY));
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                match &self.notes_draft {
+                    Some(draft) => {
+                        if ui.small_button("Save").clicked() {
+                            save = Some(draft.clone());
+                        } else if ui.small_button("Cancel").clicked() {
+                            self.notes_draft = None;
+                            self.notes_error = None;
+                        }
+                    }
+                    None => {
+                        if ui.small_button("Edit").clicked() {
+                            self.notes_draft = self.notes.clone();
+                        }
+                    }
+                }
+            });
+        });
+        // The draft stays open when saving fails, so nothing typed is lost
+        if let Some(draft) = save {
+            match self.save_notes(&draft) {
+                Ok(()) => {
+                    self.notes = Some(draft);
+                    self.notes_draft = None;
+                    self.notes_error = None;
+                }
+                Err(e) => self.notes_error = Some(format!("Could not save notes: {}", e)),
+            }
+        }
+        if let Some(error) = &self.notes_error {
+            ui.label(RichText::new(error).color(colors::DANGER).size(12.0));
+        }
+        ui.separator();
+
+        let Some(notes) = &self.notes else { return };
+        egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+            match &mut self.notes_draft {
+                Some(draft) => {
+                    let response = ui.add(egui::TextEdit::multiline(draft)
+                        .code_editor()
+                        .desired_width(f32::INFINITY)
+                        .desired_rows(20)
+                        .hint_text("Markdown: # headings, - lists, `code`, **bold**, [links](https://...)"));
+                    self.notes_focused = response.has_focus();
+                }
+                None if notes.trim().is_empty() => {
+                    ui.label(RichText::new("No notes yet. Runbook steps and contacts for this host go here.")
+                        .color(colors::TEXT_MUTED)
+                        .size(12.0));
+                }
+                None => markdown::render(ui, notes),
+            }
+        });
+    }
+
+    /// Live decoded escape sequences, and what the emulator did not handle
+    fn render_inspector(&mut self, ui: &mut egui::Ui) {
+        let Some(log) = &self.inspector else { return };
+        let mut close = false;
+        let mut clear = false;
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("Escape Sequences").strong().color(colors::TEXT_PRIMARY));
+            ui.label(RichText::new(format!(
+                "{} seen \u{00B7} {} unknown \u{00B7} {} malformed \u{00B7} {} of text",
+                log.total,
+                log.unknown,
+                log.malformed,
+                format_file_size(log.text_bytes)
+            ))
+            .size(11.0)
+            .color(colors::TEXT_MUTED));
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                close = ui.small_button("\u{2715}").on_hover_text("Ctrl+Shift+F12").clicked();
+                clear = ui.small_button("Clear").clicked();
+                ui.checkbox(&mut self.inspector_hide_sgr, "Hide SGR");
+                ui.checkbox(&mut self.inspector_paused, "Pause");
+            });
+        });
+        ui.separator();
+
+        egui::SidePanel::right(self.id.with("inspector_unhandled"))
+            .resizable(true)
+            .default_width(220.0)
+            .frame(egui::Frame::none().inner_margin(spacing::XS))
+            .show_inside(ui, |ui| {
+                ui.label(RichText::new("Not handled by the emulator").size(11.0).color(colors::TEXT_SECONDARY));
+                let mut unhandled: Vec<(&String, &u64)> = self.terminal.unhandled().iter().collect();
+                unhandled.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+                egui::ScrollArea::vertical().id_source("inspector_unhandled_list").show(ui, |ui| {
+                    if unhandled.is_empty() {
+                        ui.label(RichText::new("Nothing so far").size(11.0).color(colors::TEXT_MUTED));
+                    }
+                    egui::Grid::new(self.id.with("inspector_unhandled_grid")).striped(true).show(ui, |ui| {
+                        for (what, count) in unhandled {
+                            ui.label(RichText::new(what).monospace().size(11.0).color(colors::WARNING));
+                            ui.label(RichText::new(count.to_string()).monospace().size(11.0));
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+
+        egui::ScrollArea::vertical()
+            .id_source("inspector_stream")
+            .auto_shrink([false; 2])
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for (_, sequence) in log.entries() {
+                    if self.inspector_hide_sgr && sequence.name == Some("SGR") {
+                        continue;
+                    }
+                    let color = if sequence.malformed {
+                        colors::DANGER
+                    } else if sequence.name.is_none() {
+                        colors::WARNING
+                    } else {
+                        colors::TEXT_SECONDARY
+                    };
+                    ui.label(RichText::new(sequence.to_string()).monospace().size(11.0).color(color));
+                }
+            });
+
+        if clear {
+            if let Some(log) = &mut self.inspector {
+                log.clear();
+            }
+            self.terminal.clear_unhandled();
+        }
+        if close {
+            self.inspector = None;
+        }
+    }
+
+    /// Wrench button with the profile's service shortcuts
+    fn render_service_actions(&mut self, ui: &mut egui::Ui) {
+        let response = ui.add(egui::Button::new(RichText::new("\u{1F6E0} Services").size(11.0)).frame(false))
+            .on_hover_text("Service shortcuts of this connection");
+        let popup_id = ui.make_persistent_id("services_popup");
+        if response.clicked() {
+            ui.memory_mut(|m| m.toggle_popup(popup_id));
+        }
+
+        egui::popup::popup_above_or_below_widget(ui, popup_id, &response, egui::AboveOrBelow::Above, |ui| {
+            ui.set_min_width(220.0);
+            let running = self.service_run.as_ref().map_or(false, |run| run.task.is_some());
+            for action in &self.service_actions {
+                let button = ui.add_enabled(!running, egui::Button::new(RichText::new(action.title()).size(11.0)))
+                    .on_hover_text(RichText::new(&action.command).monospace());
+                if button.clicked() {
+                    self.service_request = Some(action.clone());
+                    ui.memory_mut(|m| m.close_popup());
+                }
+            }
+        });
+    }
+
+    /// Output of the last service shortcut, until closed
+    fn render_service_output(&mut self, ui: &mut egui::Ui) {
+        let Some(run) = &self.service_run else { return };
+        let mut close = false;
+        let mut again = false;
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(run.action.title()).strong().color(colors::TEXT_PRIMARY));
+            ui.label(RichText::new(&run.action.command).monospace().size(11.0).color(colors::TEXT_MUTED));
+            if run.task.is_some() {
+                ui.spinner();
+                ui.label(RichText::new(format_idle(run.started.elapsed())).size(11.0).color(colors::TEXT_MUTED));
+                ui.ctx().request_repaint_after(Duration::from_secs(1));
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                close = ui.small_button("\u{2715}").clicked();
+                again = ui.add_enabled(run.task.is_none(), egui::Button::new("Run again").small()).clicked();
+            });
+        });
+        ui.separator();
+
+        egui::ScrollArea::both().auto_shrink([false; 2]).stick_to_bottom(true).show(ui, |ui| {
+            match &run.output {
+                None => {}
+                Some(Ok(output)) if output.trim().is_empty() => {
+                    ui.label(RichText::new("Done, no output").size(11.0).color(colors::SUCCESS));
+                }
+                Some(Ok(output)) => {
+                    ui.label(RichText::new(output.trim_end()).monospace().size(12.0).color(colors::TEXT_PRIMARY));
+                }
+                Some(Err(e)) => {
+                    ui.label(RichText::new(e).monospace().size(12.0).color(colors::DANGER));
+                }
+            }
+        });
+
+        if again {
+            self.service_request = Some(run.action.clone());
+        }
+        if close {
+            self.service_run = None;
+        }
+    }
+
+    /// Process list with sorting, a filter and TERM/KILL behind a confirmation
+    fn render_processes(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("Processes").strong().color(colors::TEXT_PRIMARY));
+            if self.process_task.is_some() {
+                ui.spinner();
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.small_button("\u{2715}").clicked() {
+                    self.processes_open = false;
+                }
+                ui.add(egui::TextEdit::singleline(&mut self.process_filter)
+                    .hint_text("Filter")
+                    .desired_width(140.0));
+            });
+        });
+
+        if let Some((pid, command, signal)) = self.signal_confirm.clone() {
+            egui::Frame::none()
+                .fill(colors::BG_TERTIARY)
+                .inner_margin(spacing::SM)
+                .show(ui, |ui| {
+                    ui.label(RichText::new(format!("Send {} to {}?", signal.name(), pid)).color(colors::WARNING));
+                    ui.label(RichText::new(command).monospace().size(11.0).color(colors::TEXT_MUTED));
+                    ui.horizontal(|ui| {
+                        if ui.button(format!("Send {}", signal.name())).clicked() {
+                            self.signal_request = Some((pid, signal));
+                            self.signal_confirm = None;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.signal_confirm = None;
+                        }
+                    });
+                });
+        }
+        match &self.signal_result {
+            Some(Ok(message)) => {
+                ui.label(RichText::new(message).size(11.0).color(colors::SUCCESS));
+            }
+            Some(Err(e)) => {
+                ui.label(RichText::new(e).size(11.0).color(colors::DANGER));
+            }
+            None => {}
+        }
+        ui.separator();
+
+        let mut list = match &self.processes {
+            None => {
+                ui.label(RichText::new("Running ps...").color(colors::TEXT_MUTED).size(11.0));
+                return;
+            }
+            Some(Err(e)) => {
+                ui.label(RichText::new(e).color(colors::DANGER).size(11.0));
+                return;
+            }
+            Some(Ok(list)) => list
+                .iter()
+                .filter(|process| process.matches(&self.process_filter))
+                .cloned()
+                .collect::<Vec<_>>(),
+        };
+        processes::sort(&mut list, self.process_sort, self.process_sort_descending);
+
+        egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+            egui::Grid::new(self.id.with("process_grid"))
+                .striped(true)
+                .spacing([spacing::SM, 2.0])
+                .show(ui, |ui| {
+                    for (label, sort) in [
+                        ("PID", ProcessSort::Pid),
+                        ("CPU%", ProcessSort::Cpu),
+                        ("MEM", ProcessSort::Memory),
+                        ("Command", ProcessSort::Command),
+                    ] {
+                        let arrow = match (self.process_sort == sort, self.process_sort_descending) {
+                            (false, _) => "",
+                            (true, true) => " \u{25BC}",
+                            (true, false) => " \u{25B2}",
+                        };
+                        let text = RichText::new(format!("{}{}", label, arrow)).strong().size(11.0);
+                        if ui.add(egui::Button::new(text).frame(false)).clicked() {
+                            if self.process_sort == sort {
+                                self.process_sort_descending = !self.process_sort_descending;
+                            } else {
+                                self.process_sort = sort;
+                                self.process_sort_descending = sort.descending_by_default();
+                            }
+                        }
+                    }
+                    ui.label("");
+                    ui.end_row();
+
+                    for process in &list {
+                        let small = |text: String| RichText::new(text).monospace().size(11.0);
+                        ui.label(small(process.pid.to_string()));
+                        ui.label(small(format!("{:.1}", process.cpu)));
+                        ui.label(small(format_file_size(process.rss_kb * 1024)))
+                            .on_hover_text(format!("{:.1}% of memory", process.mem));
+                        let command: String = process.command.chars().take(60).collect();
+                        ui.label(small(command).color(colors::TEXT_PRIMARY))
+                            .on_hover_text(format!("{}\n{} \u{00B7} running {}", process.command, process.user, process.elapsed));
+                        ui.horizontal(|ui| {
+                            for signal in [Signal::Term, Signal::Kill] {
+                                if ui.small_button(signal.name()).clicked() {
+                                    self.signal_confirm = Some((process.pid, process.command.clone(), signal));
+                                }
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+        });
+        ui.ctx().request_repaint_after(PROCESS_REFRESH);
+    }
+
+    /// Scratchpad for this tab: plain text, or Markdown with a preview
+    fn render_scratchpad(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("Scratchpad").strong().color(colors::TEXT_PRIMARY));
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.small_button("Export").on_hover_text("Save the scratchpad with the session log").clicked() {
+                    self.export_scratchpad();
+                }
+                if self.scratchpad_format == ScratchpadFormat::Markdown {
+                    let label = if self.scratchpad_preview { "Edit" } else { "Preview" };
+                    if ui.small_button(label).clicked() {
+                        self.scratchpad_preview = !self.scratchpad_preview;
+                    }
+                }
+                let mut markdown = self.scratchpad_format == ScratchpadFormat::Markdown;
+                if ui.checkbox(&mut markdown, "Markdown").changed() {
+                    self.scratchpad_format = if markdown { ScratchpadFormat::Markdown } else { ScratchpadFormat::Plain };
+                    self.scratchpad_preview = false;
+                    self.scratchpad_edited = Some(Instant::now());
+                }
+            });
+        });
+        ui.separator();
+
+        egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+            if self.scratchpad_preview {
+                markdown::render(ui, &self.scratchpad);
+                return;
+            }
+            let response = ui.add(egui::TextEdit::multiline(&mut self.scratchpad)
+                .code_editor()
+                .desired_width(f32::INFINITY)
+                .desired_rows(20)
+                .hint_text("Commands tried, findings, timestamps... Kept with this tab until you close it."));
+            if response.changed() {
+                self.scratchpad_edited = Some(Instant::now());
+            }
+            self.scratchpad_focused = response.has_focus();
+        });
+    }
+
+    /// Scratchpad followed by the whole scrollback as plain text
+    fn export_scratchpad(&mut self) {
+        let buffer = self.terminal.buffer();
+        let log: Vec<String> = (0..buffer.total_lines()).map(|index| buffer.line_text(index)).collect();
+        let now = chrono::Local::now();
+        let title = format!("{}@{}", self.session_user, self.session_host);
+        let contents = scratchpad::export(
+            &title,
+            &now.format("%Y-%m-%d %H:%M:%S").to_string(),
+            &self.scratchpad,
+            self.scratchpad_format,
+            &log,
+        );
+        self.export_ready = Some(TerminalExport {
+            file_name: format!(
+                "{}-notes-{}.{}",
+                self.session_host,
+                now.format("%Y%m%d-%H%M%S"),
+                self.scratchpad_format.extension(),
+            ),
+            contents: contents.into_bytes(),
+        });
+    }
+
+    /// Whale button listing the host's running containers
+    fn render_containers(&mut self, ui: &mut egui::Ui) {
+        let response = ui.add(egui::Button::new(RichText::new("\u{1F433}").size(11.0)).frame(false))
+            .on_hover_text("Docker containers on this host");
+        let popup_id = ui.make_persistent_id("containers_popup");
+        if response.clicked() {
+            ui.memory_mut(|m| m.toggle_popup(popup_id));
+            self.containers_requested = true;
+        }
+
+        egui::popup::popup_above_or_below_widget(ui, popup_id, &response, egui::AboveOrBelow::Above, |ui| {
+            ui.set_min_width(320.0);
+            match &self.containers {
+                None => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(RichText::new("Running docker ps...").color(colors::TEXT_MUTED).size(11.0));
+                    });
+                }
+                Some(Err(e)) => {
+                    ui.label(RichText::new(e).color(colors::DANGER).size(11.0));
+                }
+                Some(Ok(containers)) if containers.is_empty() => {
+                    ui.label(RichText::new("No running containers").color(colors::TEXT_MUTED).size(11.0));
+                }
+                Some(Ok(containers)) => {
+                    for container in containers {
+                        let label = format!("{}  {}  ({})", container.name, container.image, container.status);
+                        if ui.button(RichText::new(label).size(11.0)).on_hover_text(container.short_id()).clicked() {
+                            self.container_request = Some(container.clone());
+                            ui.memory_mut(|m| m.close_popup());
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Time since the last output and input, so a silent session can be told
+    /// apart: a dead link vs. a command that is still running
+    fn render_idle(&mut self, ui: &mut egui::Ui) {
+        let since = |at: Option<Instant>| at.map_or("-".to_string(), |t| format_idle(t.elapsed()));
+        let response = ui.label(RichText::new(format!(
+            "out {} \u{00B7} in {}",
+            since(self.last_output),
+            since(self.last_input)
+        ))
+        .color(colors::TEXT_MUTED)
+        .size(11.0))
+        .on_hover_text("Time since the last output and the last keystroke; right-click to probe the connection");
+
+        response.context_menu(|ui| {
+            if ui.button("Send keepalive").clicked() {
+                self.send_keepalive();
+                ui.close_menu();
+            }
+            if ui.button("Send newline").clicked() {
+                self.type_input(b"\r");
+                ui.close_menu();
+            }
+        });
+
+        // Keep the counters ticking while nothing else repaints
+        ui.ctx().request_repaint_after(Duration::from_secs(1));
+    }
+}
+
+/// Render a session group's terminals split per `layout`, relaying input
+/// typed in a broadcasting pane to the other broadcasting panes, and
+/// scrolling and navigation keys of a synced pane to the other synced ones.
+/// `PaneLayout::Tabs` is opened as separate tabs and never gets here.
+pub fn render_panes(ui: &mut egui::Ui, panes: &mut [TerminalViewScreen], layout: PaneLayout) {
+    if panes.is_empty() {
+        return;
+    }
+    let (cols, rows) = layout.cells(panes.len());
+    let area = ui.available_rect_before_wrap();
+    let cell = egui::vec2(area.width() / cols as f32, area.height() / rows as f32);
+
+    for (index, pane) in panes.iter_mut().enumerate() {
+        let origin = area.min + egui::vec2((index % cols) as f32 * cell.x, (index / cols) as f32 * cell.y);
+        let rect = egui::Rect::from_min_size(origin, cell).shrink(1.0);
+        let mut child = ui.child_ui(rect, egui::Layout::top_down(egui::Align::Min));
+        child.set_clip_rect(rect);
+        child.push_id(pane.id, |ui| pane.render_with_status(ui));
+        if pane.broadcast {
+            ui.painter().rect_stroke(rect, 0.0, egui::Stroke::new(1.0, colors::WARNING));
+        } else if pane.sync {
+            ui.painter().rect_stroke(rect, 0.0, egui::Stroke::new(1.0, colors::PRIMARY));
+        }
+    }
+    ui.allocate_rect(area, egui::Sense::hover());
+
+    for index in 0..panes.len() {
+        let scrolled = panes[index].terminal.take_scroll_delta();
+        let keys = std::mem::take(&mut panes[index].sync_keys);
+        if !panes[index].sync || (scrolled == 0 && keys.is_empty()) {
+            continue;
+        }
+        let from_broadcast = panes[index].broadcast;
+        for (other, pane) in panes.iter_mut().enumerate() {
+            if other == index || !pane.sync {
+                continue;
+            }
+            if scrolled != 0 {
+                pane.terminal.scroll_by(scrolled);
+            }
+            // Broadcasting panes already got the keys with the rest of the input
+            if pane.read_only || (from_broadcast && pane.broadcast) {
+                continue;
+            }
+            let modes = pane.terminal.input_modes();
+            for (key, modifiers) in &keys {
+                if let Some(data) = encode_key(*key, modifiers, &modes, &pane.keyboard) {
+                    if pane.admit_input(&data) {
+                        pane.send_input(&data);
+                    }
+                }
+            }
+        }
+    }
+
+    for index in 0..panes.len() {
+        let input = std::mem::take(&mut panes[index].broadcast_input);
+        if input.is_empty() {
+            continue;
+        }
+        for (other, pane) in panes.iter_mut().enumerate() {
+            if other != index && pane.broadcast && pane.admit_input(&input) {
+                pane.send_input(&input);
+            }
+        }
+    }
 }
 
-fn key_to_escape_sequence(key: egui::Key, modifiers: &egui::Modifiers) -> Option<Vec<u8>> {
-    if modifiers.ctrl {
-        match key {
-            egui::Key::A => return Some(vec![0x01]),
-            egui::Key::B => return Some(vec![0x02]),
-            egui::Key::C => return Some(vec![0x03]),
-            egui::Key::D => return Some(vec![0x04]),
-            egui::Key::E => return Some(vec![0x05]),
-            egui::Key::F => return Some(vec![0x06]),
-            egui::Key::G => return Some(vec![0x07]),
-            egui::Key::H => return Some(vec![0x08]),
-            egui::Key::I => return Some(vec![0x09]),
-            egui::Key::J => return Some(vec![0x0A]),
-            egui::Key::K => return Some(vec![0x0B]),
-            egui::Key::L => return Some(vec![0x0C]),
-            egui::Key::M => return Some(vec![0x0D]),
-            egui::Key::N => return Some(vec![0x0E]),
-            egui::Key::O => return Some(vec![0x0F]),
-            egui::Key::P => return Some(vec![0x10]),
-            egui::Key::Q => return Some(vec![0x11]),
-            egui::Key::R => return Some(vec![0x12]),
-            egui::Key::S => return Some(vec![0x13]),
-            egui::Key::T => return Some(vec![0x14]),
-            egui::Key::U => return Some(vec![0x15]),
-            egui::Key::V => return Some(vec![0x16]),
-            egui::Key::W => return Some(vec![0x17]),
-            egui::Key::X => return Some(vec![0x18]),
-            egui::Key::Y => return Some(vec![0x19]),
-            egui::Key::Z => return Some(vec![0x1A]),
-            _ => {}
-        }
-    }
-
-    match key {
-        egui::Key::Enter => Some(vec![0x0D]),
-        egui::Key::Tab => Some(vec![0x09]),
-        egui::Key::Backspace => Some(vec![0x7F]),
-        egui::Key::Escape => Some(vec![0x1B]),
-        egui::Key::ArrowUp => Some(b"\x1b[A".to_vec()),
-        egui::Key::ArrowDown => Some(b"\x1b[B".to_vec()),
-        egui::Key::ArrowRight => Some(b"\x1b[C".to_vec()),
-        egui::Key::ArrowLeft => Some(b"\x1b[D".to_vec()),
-        egui::Key::Home => Some(b"\x1b[H".to_vec()),
-        egui::Key::End => Some(b"\x1b[F".to_vec()),
-        egui::Key::PageUp => Some(b"\x1b[5~".to_vec()),
-        egui::Key::PageDown => Some(b"\x1b[6~".to_vec()),
-        egui::Key::Insert => Some(b"\x1b[2~".to_vec()),
-        egui::Key::Delete => Some(b"\x1b[3~".to_vec()),
-        egui::Key::F1 => Some(b"\x1bOP".to_vec()),
-        egui::Key::F2 => Some(b"\x1bOQ".to_vec()),
-        egui::Key::F3 => Some(b"\x1bOR".to_vec()),
-        egui::Key::F4 => Some(b"\x1bOS".to_vec()),
-        egui::Key::F5 => Some(b"\x1b[15~".to_vec()),
-        egui::Key::F6 => Some(b"\x1b[17~".to_vec()),
-        egui::Key::F7 => Some(b"\x1b[18~".to_vec()),
-        egui::Key::F8 => Some(b"\x1b[19~".to_vec()),
-        egui::Key::F9 => Some(b"\x1b[20~".to_vec()),
-        egui::Key::F10 => Some(b"\x1b[21~".to_vec()),
-        egui::Key::F11 => Some(b"\x1b[23~".to_vec()),
-        egui::Key::F12 => Some(b"\x1b[24~".to_vec()),
-        _ => None,
+/// Lock button opening the negotiated crypto details
+fn render_session_info(ui: &mut egui::Ui, info: &SessionInfo) {
+    let response = ui.add(egui::Button::new(RichText::new("\u{1F512}").size(11.0)).frame(false))
+        .on_hover_text("Connection details");
+    let popup_id = ui.make_persistent_id("session_info_popup");
+    if response.clicked() {
+        ui.memory_mut(|m| m.toggle_popup(popup_id));
     }
+    egui::popup::popup_above_or_below_widget(ui, popup_id, &response, egui::AboveOrBelow::Above, |ui| {
+        ui.set_min_width(320.0);
+        egui::Grid::new("session_info_grid").num_columns(2).spacing([spacing::MD, spacing::XS]).show(ui, |ui| {
+            let connected = info.connected_at.format("%Y-%m-%d %H:%M:%S").to_string();
+            for (label, value) in [
+                ("Server", info.server_version.as_str()),
+                ("Key exchange", info.kex.as_str()),
+                ("Host key", info.host_key.as_str()),
+                ("Cipher", info.cipher.as_str()),
+                ("MAC", info.mac.as_str()),
+                ("Compression", info.compression.as_str()),
+                ("Connected", connected.as_str()),
+            ] {
+                ui.label(RichText::new(label).color(colors::TEXT_MUTED).size(11.0));
+                ui.label(RichText::new(value).color(colors::TEXT_PRIMARY).size(11.0).monospace());
+                ui.end_row();
+            }
+        });
+    });
 }
@@ -0,0 +1,244 @@
+//! Theme editor - tweak terminal colors with a live preview
+
+use egui::text::{LayoutJob, TextFormat};
+use egui::{Color32, FontId, RichText};
+use crate::config::themes::Theme;
+use crate::ui::components::{colors, spacing, secondary_button};
+
+/// Where a span of the sample output takes its color from
+#[derive(Clone, Copy)]
+enum Paint {
+    Foreground,
+    Ansi(usize),
+    /// Foreground on the selection color
+    Selected,
+    /// Block cursor
+    Cursor,
+}
+
+/// Sample output for the preview, as (text, paint) spans per line
+const SAMPLE: &[&[(&str, Paint)]] = &[
+    &[("user@web-01", Paint::Ansi(10)), (":", Paint::Foreground), ("~/app", Paint::Ansi(12)), ("$ ls --color", Paint::Foreground)],
+    &[("Cargo.toml  ", Paint::Foreground), ("README.md", Paint::Selected), ("  ", Paint::Foreground), ("src/", Paint::Ansi(12)), ("  ", Paint::Foreground), ("deploy.sh", Paint::Ansi(10)), ("  ", Paint::Foreground), ("app.tar.gz", Paint::Ansi(9))],
+    &[("user@web-01", Paint::Ansi(10)), (":", Paint::Foreground), ("~/app", Paint::Ansi(12)), ("$ git diff --stat", Paint::Foreground)],
+    &[(" src/main.rs | 12 ", Paint::Foreground), ("++++++", Paint::Ansi(2)), ("------", Paint::Ansi(1))],
+    &[(" 1 file changed, 6 insertions(+), 6 deletions(-)", Paint::Foreground)],
+    &[("user@web-01", Paint::Ansi(10)), (":", Paint::Foreground), ("~/app", Paint::Ansi(12)), ("$ systemctl status nginx", Paint::Foreground)],
+    &[("\u{25CF} ", Paint::Ansi(2)), ("nginx.service", Paint::Foreground), (" - A high performance web server", Paint::Ansi(8))],
+    &[("     Active: ", Paint::Foreground), ("active (running)", Paint::Ansi(10)), (" since Mon 09:12:44 UTC", Paint::Foreground)],
+    &[("warning", Paint::Ansi(11)), (": worker_connections exceed open file limit", Paint::Foreground)],
+    &[("error", Paint::Ansi(9)), (": upstream ", Paint::Foreground), ("\"api\"", Paint::Ansi(6)), (" timed out after ", Paint::Foreground), ("30s", Paint::Ansi(5))],
+    &[("user@web-01", Paint::Ansi(10)), (":", Paint::Foreground), ("~/app", Paint::Ansi(12)), ("$ ", Paint::Foreground), (" ", Paint::Cursor)],
+];
+
+const PREVIEW_FONT_SIZE: f32 = 13.0;
+
+/// Editor for one custom theme
+pub struct ThemeEditorScreen {
+    /// Row in the themes table; `None` until first saved
+    id: Option<String>,
+    theme: Theme,
+    /// As last saved or loaded, to tell whether there are edits
+    saved: Theme,
+    /// Names of the other themes, which this one may not take
+    taken_names: Vec<String>,
+    error: Option<String>,
+    confirm_delete: bool,
+}
+
+impl ThemeEditorScreen {
+    /// Start a new custom theme from a copy of `base`
+    pub fn new_from(base: &Theme, taken_names: Vec<String>) -> Self {
+        let mut theme = base.clone();
+        let mut name = format!("{} Custom", base.name);
+        let mut n = 2;
+        while taken_names.contains(&name) {
+            name = format!("{} Custom {}", base.name, n);
+            n += 1;
+        }
+        theme.name = name;
+        Self {
+            id: None,
+            saved: theme.clone(),
+            theme,
+            taken_names,
+            error: None,
+            confirm_delete: false,
+        }
+    }
+
+    /// Edit a saved custom theme
+    pub fn edit(id: String, theme: Theme, mut taken_names: Vec<String>) -> Self {
+        taken_names.retain(|name| *name != theme.name);
+        Self {
+            id: Some(id),
+            saved: theme.clone(),
+            theme,
+            taken_names,
+            error: None,
+            confirm_delete: false,
+        }
+    }
+
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.theme != self.saved || self.id.is_none()
+    }
+
+    /// Why the theme cannot be saved as it is
+    fn validate(&self) -> Option<String> {
+        let name = self.theme.name.trim();
+        if name.is_empty() {
+            return Some("Give the theme a name".to_string());
+        }
+        if self.taken_names.iter().any(|taken| taken == name) {
+            return Some(format!("A theme named '{}' already exists", name));
+        }
+        self.theme.colors().into_iter()
+            .find(|(_, hex)| Theme::parse_color(hex).is_none())
+            .map(|(label, hex)| format!("{} is not a #rrggbb color: '{}'", label, hex))
+    }
+
+    pub fn render(&mut self, ui: &mut egui::Ui) -> Option<ThemeEditorAction> {
+        let mut action = None;
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("Theme Editor").color(colors::TEXT_PRIMARY).strong().size(16.0));
+            if self.has_unsaved_changes() {
+                ui.label(RichText::new("\u{25CF} Unsaved changes").color(colors::WARNING).size(12.0));
+            }
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if secondary_button(ui, "Close").clicked() {
+                    action = Some(ThemeEditorAction::Close);
+                }
+                if let Some(id) = &self.id {
+                    if self.confirm_delete {
+                        if ui.button(RichText::new("Confirm delete").color(colors::ERROR)).clicked() {
+                            action = Some(ThemeEditorAction::Delete { id: id.clone(), name: self.saved.name.clone() });
+                        }
+                    } else if secondary_button(ui, "Delete").clicked() {
+                        self.confirm_delete = true;
+                    }
+                }
+                if ui.add_enabled(self.has_unsaved_changes(), egui::Button::new("Save")).clicked() {
+                    action = self.save();
+                }
+            });
+        });
+
+        if let Some(error) = &self.error {
+            ui.label(RichText::new(error).color(colors::ERROR));
+        }
+        ui.add_space(spacing::SM);
+
+        ui.horizontal(|ui| {
+            ui.label("Name:");
+            ui.add(egui::TextEdit::singleline(&mut self.theme.name).desired_width(240.0));
+        });
+        ui.separator();
+
+        ui.columns(2, |columns| {
+            egui::ScrollArea::vertical()
+                .id_source("theme_editor_colors")
+                .show(&mut columns[0], |ui| self.render_colors(ui));
+            columns[1].label(RichText::new("Preview").color(colors::TEXT_SECONDARY).strong());
+            render_preview(&mut columns[1], &self.theme);
+        });
+
+        action
+    }
+
+    fn save(&mut self) -> Option<ThemeEditorAction> {
+        if let Some(error) = self.validate() {
+            self.error = Some(error);
+            return None;
+        }
+        self.error = None;
+        self.theme.name = self.theme.name.trim().to_string();
+        let id = self.id.get_or_insert_with(|| uuid::Uuid::new_v4().to_string()).clone();
+        let previous_name = (self.saved.name != self.theme.name).then(|| self.saved.name.clone());
+        self.saved = self.theme.clone();
+        Some(ThemeEditorAction::Save { id, previous_name, theme: self.theme.clone() })
+    }
+
+    /// Color picker and hex field for every slot
+    fn render_colors(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("theme_editor_grid")
+            .num_columns(3)
+            .spacing([spacing::MD, spacing::XS])
+            .show(ui, |ui| {
+                for (i, (label, hex)) in self.theme.colors_mut().into_iter().enumerate() {
+                    if i == 4 {
+                        ui.label(RichText::new("ANSI colors").color(colors::TEXT_SECONDARY).strong());
+                        ui.end_row();
+                    }
+                    ui.label(label);
+                    let mut rgb = Theme::parse_color(hex).map_or([0, 0, 0], |(r, g, b)| [r, g, b]);
+                    if egui::color_picker::color_edit_button_srgb(ui, &mut rgb).changed() {
+                        *hex = Theme::format_color((rgb[0], rgb[1], rgb[2]));
+                    }
+                    let valid = Theme::parse_color(hex).is_some();
+                    ui.add(egui::TextEdit::singleline(hex)
+                        .desired_width(72.0)
+                        .font(egui::TextStyle::Monospace)
+                        .text_color(if valid { colors::TEXT_PRIMARY } else { colors::ERROR }));
+                    ui.end_row();
+                }
+            });
+    }
+}
+
+/// Sample terminal output drawn in `theme`'s colors
+fn render_preview(ui: &mut egui::Ui, theme: &Theme) {
+    let color = |hex: &str| Theme::parse_color(hex).map_or(Color32::BLACK, |(r, g, b)| Color32::from_rgb(r, g, b));
+    let background = color(&theme.background);
+    let foreground = color(&theme.foreground);
+    let ansi = theme.ansi().map(color);
+    let font = FontId::monospace(PREVIEW_FONT_SIZE);
+
+    let mut job = LayoutJob::default();
+    for (i, line) in SAMPLE.iter().enumerate() {
+        for &(text, paint) in line.iter() {
+            let (fg, bg) = match paint {
+                Paint::Foreground => (foreground, Color32::TRANSPARENT),
+                Paint::Ansi(n) => (ansi[n], Color32::TRANSPARENT),
+                Paint::Selected => (foreground, color(&theme.selection)),
+                Paint::Cursor => (background, color(&theme.cursor)),
+            };
+            job.append(text, 0.0, TextFormat { font_id: font.clone(), color: fg, background: bg, ..Default::default() });
+        }
+        if i + 1 < SAMPLE.len() {
+            job.append("\n", 0.0, TextFormat { font_id: font.clone(), ..Default::default() });
+        }
+    }
+
+    egui::Frame::none()
+        .fill(background)
+        .inner_margin(spacing::MD)
+        .rounding(4.0)
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width());
+            ui.label(job);
+            ui.add_space(spacing::SM);
+
+            // The palette itself, normal over bright
+            for row in ansi.chunks(8) {
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().item_spacing.x = 2.0;
+                    for &swatch in row {
+                        let (rect, _) = ui.allocate_exact_size(egui::vec2(22.0, 14.0), egui::Sense::hover());
+                        ui.painter().rect_filled(rect, 2.0, swatch);
+                    }
+                });
+            }
+        });
+}
+
+/// Actions from the theme editor
+pub enum ThemeEditorAction {
+    /// Store the theme in the themes table; `previous_name` is set when it
+    /// was renamed, so the old entry can be dropped from the theme list
+    Save { id: String, previous_name: Option<String>, theme: Theme },
+    Delete { id: String, name: String },
+    Close,
+}
@@ -0,0 +1,191 @@
+//! Transfer History Screen - finished transfers with filtering and re-run
+
+#![allow(dead_code)]
+
+use chrono::{Duration, Local};
+use eframe::egui::{self, RichText};
+use crate::sftp::format_file_size;
+use crate::storage::database::{TransferHistoryFilter, TransferRecord};
+use crate::ui::components::{colors, spacing, secondary_button, empty_state};
+
+/// Date range presets for the history filter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HistoryRange {
+    All,
+    Today,
+    LastWeek,
+    LastMonth,
+}
+
+impl HistoryRange {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::All => "All time",
+            Self::Today => "Today",
+            Self::LastWeek => "Last 7 days",
+            Self::LastMonth => "Last 30 days",
+        }
+    }
+
+    pub fn since(&self) -> Option<String> {
+        let now = Local::now();
+        let since = match self {
+            Self::All => return None,
+            Self::Today => now.date_naive().and_hms_opt(0, 0, 0)?.and_local_timezone(Local).earliest()?,
+            Self::LastWeek => now - Duration::days(7),
+            Self::LastMonth => now - Duration::days(30),
+        };
+        Some(since.to_rfc3339())
+    }
+}
+
+/// Transfer history view state
+pub struct TransferHistoryScreen {
+    records: Vec<TransferRecord>,
+    host_filter: String,
+    range: HistoryRange,
+    confirm_clear: bool,
+}
+
+impl TransferHistoryScreen {
+    pub fn new() -> Self {
+        Self {
+            records: Vec::new(),
+            host_filter: String::new(),
+            range: HistoryRange::All,
+            confirm_clear: false,
+        }
+    }
+
+    pub fn set_records(&mut self, records: Vec<TransferRecord>) {
+        self.records = records;
+    }
+
+    /// Database filter for the current host/date selection
+    pub fn filter(&self) -> TransferHistoryFilter {
+        let host = self.host_filter.trim();
+        TransferHistoryFilter {
+            host: (!host.is_empty()).then(|| host.to_string()),
+            since: self.range.since(),
+            until: None,
+            limit: Some(500),
+        }
+    }
+
+    /// Render the history view
+    pub fn render(&mut self, ui: &mut egui::Ui) -> Option<TransferHistoryAction> {
+        let mut action = None;
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("Transfer History")
+                .color(colors::TEXT_PRIMARY)
+                .strong()
+                .size(16.0));
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if secondary_button(ui, "Close").clicked() {
+                    action = Some(TransferHistoryAction::Close);
+                }
+                if self.confirm_clear {
+                    if ui.button(RichText::new("Confirm clear").color(colors::ERROR)).clicked() {
+                        self.confirm_clear = false;
+                        action = Some(TransferHistoryAction::Clear);
+                    }
+                } else if secondary_button(ui, "Clear history").clicked() {
+                    self.confirm_clear = true;
+                }
+            });
+        });
+
+        ui.add_space(spacing::SM);
+
+        let mut filter_changed = false;
+        ui.horizontal(|ui| {
+            ui.label("Host:");
+            filter_changed |= ui.add(egui::TextEdit::singleline(&mut self.host_filter)
+                .hint_text("any")
+                .desired_width(180.0))
+                .changed();
+
+            egui::ComboBox::from_id_source("transfer_history_range")
+                .selected_text(self.range.label())
+                .show_ui(ui, |ui| {
+                    for range in [HistoryRange::All, HistoryRange::Today, HistoryRange::LastWeek, HistoryRange::LastMonth] {
+                        filter_changed |= ui.selectable_value(&mut self.range, range, range.label()).changed();
+                    }
+                });
+        });
+        if filter_changed {
+            action = Some(TransferHistoryAction::Refresh(self.filter()));
+        }
+
+        ui.separator();
+
+        if self.records.is_empty() {
+            empty_state(ui, "\u{1F4E6}", "No Transfers", "Finished uploads and downloads show up here");
+            return action;
+        }
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("transfer_history_grid")
+                .num_columns(8)
+                .striped(true)
+                .spacing([spacing::MD, spacing::XS])
+                .show(ui, |ui| {
+                    for header in ["", "Host", "Source", "Destination", "Size", "Speed", "Finished", ""] {
+                        ui.label(RichText::new(header).strong().color(colors::TEXT_SECONDARY));
+                    }
+                    ui.end_row();
+
+                    for record in &self.records {
+                        let icon = if record.direction == "upload" { "⬆" } else { "⬇" };
+                        ui.label(icon);
+                        ui.label(&record.host);
+                        ui.label(&record.source).on_hover_text(&record.source);
+                        ui.label(&record.destination).on_hover_text(&record.destination);
+                        ui.label(format_file_size(record.size));
+                        ui.label(format!("{}/s", format_file_size(record.bytes_per_second())));
+
+                        let finished = chrono::DateTime::parse_from_rfc3339(&record.finished_at)
+                            .map(|t| t.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+                            .unwrap_or_else(|_| record.finished_at.clone());
+                        if record.success {
+                            ui.label(RichText::new(finished).color(colors::TEXT_MUTED));
+                        } else {
+                            ui.label(RichText::new(format!("{} (failed)", finished)).color(colors::ERROR))
+                                .on_hover_text(record.error.as_deref().unwrap_or("Unknown error"));
+                        }
+
+                        ui.horizontal(|ui| {
+                            if ui.small_button("Run again").clicked() {
+                                action = Some(TransferHistoryAction::Rerun(record.clone()));
+                            }
+                            if ui.small_button("\u{1F5D1}").on_hover_text("Remove from history").clicked() {
+                                action = Some(TransferHistoryAction::Delete(record.id.clone()));
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+        });
+
+        action
+    }
+}
+
+impl Default for TransferHistoryScreen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Actions from the transfer history screen
+pub enum TransferHistoryAction {
+    /// Reload with a new filter
+    Refresh(TransferHistoryFilter),
+    /// Enqueue the same source/destination again
+    Rerun(TransferRecord),
+    Delete(String),
+    Clear,
+    Close,
+}
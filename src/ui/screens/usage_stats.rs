@@ -0,0 +1,143 @@
+//! Usage Statistics Screen - local figures from the session and transfer history
+
+use eframe::egui::{self, RichText};
+use crate::sftp::format_file_size;
+use crate::storage::stats::{format_connected, UsageStats};
+use crate::ui::components::{colors, spacing, secondary_button, empty_state};
+use super::transfer_history::HistoryRange;
+
+pub struct UsageStatsScreen {
+    stats: Option<UsageStats>,
+    range: HistoryRange,
+    confirm_clear: bool,
+}
+
+impl UsageStatsScreen {
+    pub fn new() -> Self {
+        Self {
+            stats: None,
+            range: HistoryRange::All,
+            confirm_clear: false,
+        }
+    }
+
+    pub fn set_stats(&mut self, stats: UsageStats) {
+        self.stats = Some(stats);
+    }
+
+    /// Start of the selected range, for `UsageStats::load`
+    pub fn since(&self) -> Option<String> {
+        self.range.since()
+    }
+
+    pub fn render(&mut self, ui: &mut egui::Ui) -> Option<UsageStatsAction> {
+        let mut action = None;
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("Usage Statistics")
+                .color(colors::TEXT_PRIMARY)
+                .strong()
+                .size(16.0));
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if secondary_button(ui, "Close").clicked() {
+                    action = Some(UsageStatsAction::Close);
+                }
+                if self.confirm_clear {
+                    if ui.button(RichText::new("Confirm clear").color(colors::ERROR)).clicked() {
+                        self.confirm_clear = false;
+                        action = Some(UsageStatsAction::ClearSessions);
+                    }
+                } else if secondary_button(ui, "Clear session history").clicked() {
+                    self.confirm_clear = true;
+                }
+
+                egui::ComboBox::from_id_source("usage_stats_range")
+                    .selected_text(self.range.label())
+                    .show_ui(ui, |ui| {
+                        for range in [HistoryRange::All, HistoryRange::Today, HistoryRange::LastWeek, HistoryRange::LastMonth] {
+                            if ui.selectable_value(&mut self.range, range, range.label()).changed() {
+                                action = Some(UsageStatsAction::Refresh(self.range.since()));
+                            }
+                        }
+                    });
+            });
+        });
+        ui.label(RichText::new("Worked out on this computer from your history; nothing is sent anywhere.")
+            .color(colors::TEXT_MUTED)
+            .size(11.0));
+        ui.separator();
+
+        let Some(stats) = &self.stats else {
+            ui.spinner();
+            return action;
+        };
+        if stats.sessions == 0 && stats.transfers == 0 {
+            empty_state(ui, "\u{1F4CA}", "Nothing Yet", "Sessions and transfers are counted as they finish");
+            return action;
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            figure(ui, "Sessions", &stats.sessions.to_string());
+            figure(ui, "Connected", &format_connected(stats.connected_secs));
+            figure(ui, "Uploaded", &format_file_size(stats.uploaded_bytes));
+            figure(ui, "Downloaded", &format_file_size(stats.downloaded_bytes));
+            let transfers = match stats.failed_transfers {
+                0 => stats.transfers.to_string(),
+                failed => format!("{} ({} failed)", stats.transfers, failed),
+            };
+            figure(ui, "Transfers", &transfers);
+        });
+
+        ui.add_space(spacing::LG);
+        ui.label(RichText::new("Most used hosts").strong().color(colors::TEXT_SECONDARY));
+        ui.add_space(spacing::XS);
+
+        let busiest = stats.top_hosts.first().map_or(1, |h| h.connected_secs.max(1));
+        egui::Grid::new("usage_stats_hosts")
+            .num_columns(4)
+            .striped(true)
+            .spacing([spacing::MD, spacing::XS])
+            .show(ui, |ui| {
+                for host in &stats.top_hosts {
+                    ui.label(&host.host);
+                    ui.add(egui::ProgressBar::new(host.connected_secs as f32 / busiest as f32).desired_width(160.0));
+                    ui.label(format_connected(host.connected_secs));
+                    let sessions = if host.sessions == 1 { "1 session".to_string() } else { format!("{} sessions", host.sessions) };
+                    ui.label(RichText::new(sessions).color(colors::TEXT_MUTED));
+                    ui.end_row();
+                }
+            });
+
+        action
+    }
+}
+
+/// One headline number with its caption
+pub(super) fn figure(ui: &mut egui::Ui, caption: &str, value: &str) {
+    egui::Frame::group(ui.style())
+        .fill(colors::BG_SECONDARY)
+        .inner_margin(egui::Margin::same(spacing::MD))
+        .show(ui, |ui| {
+            ui.set_min_width(110.0);
+            ui.vertical(|ui| {
+                ui.label(RichText::new(value).color(colors::TEXT_PRIMARY).strong().size(20.0));
+                ui.label(RichText::new(caption).color(colors::TEXT_MUTED).size(11.0));
+            });
+        });
+}
+
+impl Default for UsageStatsScreen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Actions from the usage statistics screen
+pub enum UsageStatsAction {
+    /// Reload for the range starting at this RFC 3339 timestamp, or all time
+    Refresh(Option<String>),
+    /// Forget recorded sessions; transfer history has its own screen
+    ClearSessions,
+    Close,
+}
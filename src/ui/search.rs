@@ -2,6 +2,8 @@
 
 use egui::{Context, Window};
 
+/// Find bar state; each tab keeps its own so switching tabs doesn't lose it
+#[derive(Debug, Clone)]
 pub struct SearchWidget {
     pub open: bool,
     pub query: String,
@@ -3,6 +3,7 @@
 #![allow(dead_code)]
 
 use uuid::Uuid;
+use super::search::SearchWidget;
 
 /// Connection status for a tab
 #[derive(Debug, Clone, PartialEq)]
@@ -28,6 +29,53 @@ impl std::fmt::Display for TabStatus {
     }
 }
 
+/// Accent color a user can give a tab
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabColor {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+}
+
+impl TabColor {
+    pub const ALL: [TabColor; 6] = [
+        TabColor::Red,
+        TabColor::Orange,
+        TabColor::Yellow,
+        TabColor::Green,
+        TabColor::Blue,
+        TabColor::Purple,
+    ];
+
+    /// RGB value used for the tab accent
+    pub fn rgb(&self) -> (u8, u8, u8) {
+        match self {
+            TabColor::Red => (239, 68, 68),
+            TabColor::Orange => (249, 115, 22),
+            TabColor::Yellow => (234, 179, 8),
+            TabColor::Green => (34, 197, 94),
+            TabColor::Blue => (59, 130, 246),
+            TabColor::Purple => (168, 85, 247),
+        }
+    }
+}
+
+impl std::fmt::Display for TabColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TabColor::Red => write!(f, "Red"),
+            TabColor::Orange => write!(f, "Orange"),
+            TabColor::Yellow => write!(f, "Yellow"),
+            TabColor::Green => write!(f, "Green"),
+            TabColor::Blue => write!(f, "Blue"),
+            TabColor::Purple => write!(f, "Purple"),
+        }
+    }
+}
+
 /// A tab representing an SSH session
 #[derive(Debug, Clone)]
 pub struct Tab {
@@ -43,14 +91,44 @@ pub struct Tab {
     /// SSH port
     port: u16,
 
-    /// Tab title (displayed in tab bar)
+    /// Default tab title (user@host)
     title: String,
 
+    /// Name of the saved connection the tab was opened from
+    profile_name: Option<String>,
+
+    /// Title the remote set with OSC 0/2
+    remote_title: Option<String>,
+
+    /// Title set by the user, shown instead of any other
+    custom_title: Option<String>,
+
+    /// Ignore titles from the remote
+    title_locked: bool,
+
+    /// Title the remote tried to set while locked
+    blocked_title: Option<String>,
+
+    /// Accent color set by the user
+    color: Option<TabColor>,
+
+    /// Session output is being written to a log file
+    logging: bool,
+
+    /// Typed input is not sent to the session
+    read_only: bool,
+
     /// Connection status
     status: TabStatus,
 
     /// Has unread output since last view
     has_unread: bool,
+
+    /// Name of the trigger that flagged the tab, until it is viewed
+    alert: Option<String>,
+
+    /// This tab's find bar: query, options and current match
+    search: SearchWidget,
 }
 
 impl Tab {
@@ -63,19 +141,51 @@ impl Tab {
             user: user.to_string(),
             port,
             title,
+            profile_name: None,
+            remote_title: None,
+            custom_title: None,
+            title_locked: false,
+            blocked_title: None,
+            color: None,
+            logging: false,
+            read_only: false,
             status: TabStatus::Disconnected,
             has_unread: false,
+            alert: None,
+            search: SearchWidget::new(),
         }
     }
 
+    /// Tab bar entry for an app tab that has its own id and title
+    pub fn for_app_tab(id: Uuid, title: &str) -> Self {
+        let mut tab = Self::new_ssh("", "", 0);
+        tab.id = id;
+        tab.title = title.to_string();
+        tab
+    }
+
     /// Get the tab's unique identifier
     pub fn id(&self) -> Uuid {
         self.id
     }
 
-    /// Get the tab title
+    /// Get the tab title: the user's name for it, else the remote's title,
+    /// else the profile name, else user@host
     pub fn title(&self) -> &str {
-        &self.title
+        self.custom_title.as_deref()
+            .or(self.remote_title.as_deref())
+            .or(self.profile_name.as_deref())
+            .unwrap_or(&self.title)
+    }
+
+    /// Get the user's name for the tab, if renamed
+    pub fn custom_title(&self) -> Option<&str> {
+        self.custom_title.as_deref()
+    }
+
+    /// Rename the tab; `None` or a blank name restores the default title
+    pub fn set_custom_title(&mut self, title: Option<String>) {
+        self.custom_title = title.filter(|t| !t.trim().is_empty());
     }
 
     /// Set the tab title
@@ -83,6 +193,86 @@ impl Tab {
         self.title = title;
     }
 
+    /// Name the tab after the saved connection it was opened from
+    pub fn set_profile_name(&mut self, name: Option<String>) {
+        self.profile_name = name.filter(|n| !n.trim().is_empty());
+    }
+
+    /// Title from an OSC 0/2 sequence; blank clears it. While the title is
+    /// locked it is only remembered, for the indicator.
+    pub fn set_remote_title(&mut self, title: String) {
+        let title = Some(title).filter(|t| !t.trim().is_empty());
+        if self.title_locked {
+            self.blocked_title = title;
+        } else {
+            self.remote_title = title;
+        }
+    }
+
+    /// Whether titles from the remote are ignored
+    pub fn is_title_locked(&self) -> bool {
+        self.title_locked
+    }
+
+    /// Lock or unlock the title; unlocking takes the last title the remote
+    /// tried to set
+    pub fn set_title_locked(&mut self, locked: bool) {
+        if !locked {
+            if let Some(title) = self.blocked_title.take() {
+                self.remote_title = Some(title);
+            }
+        } else if !self.title_locked {
+            self.blocked_title = None;
+        }
+        self.title_locked = locked;
+    }
+
+    /// Title the remote tried to set while the title was locked
+    pub fn blocked_title(&self) -> Option<&str> {
+        self.blocked_title.as_deref()
+    }
+
+    /// Get the tab accent color
+    pub fn color(&self) -> Option<TabColor> {
+        self.color
+    }
+
+    /// Set the tab accent color
+    pub fn set_color(&mut self, color: Option<TabColor>) {
+        self.color = color;
+    }
+
+    /// Check if session output is being logged
+    pub fn is_logging(&self) -> bool {
+        self.logging
+    }
+
+    /// Record whether session output is being logged
+    pub fn set_logging(&mut self, logging: bool) {
+        self.logging = logging;
+    }
+
+    /// Check if typed input is blocked
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Record whether typed input is blocked
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// New disconnected tab for the same connection, keeping name and color
+    pub fn duplicate(&self) -> Self {
+        let mut tab = Self::new_ssh(&self.host, &self.user, self.port);
+        tab.title = self.title.clone();
+        tab.profile_name = self.profile_name.clone();
+        tab.custom_title = self.custom_title.clone();
+        tab.title_locked = self.title_locked;
+        tab.color = self.color;
+        tab
+    }
+
     /// Get the SSH host
     pub fn host(&self) -> &str {
         &self.host
@@ -118,8 +308,61 @@ impl Tab {
         self.has_unread = true;
     }
 
-    /// Clear unread flag
+    /// Clear unread flag and any trigger alert
     pub fn clear_unread(&mut self) {
         self.has_unread = false;
+        self.alert = None;
+    }
+
+    /// Trigger that flagged the tab, if any
+    pub fn alert(&self) -> Option<&str> {
+        self.alert.as_deref()
+    }
+
+    /// Flag the tab because output matched a trigger
+    pub fn set_alert(&mut self, trigger: String) {
+        self.alert = Some(trigger);
+    }
+
+    /// The find bar to show while this tab is active
+    pub fn search(&self) -> &SearchWidget {
+        &self.search
+    }
+
+    pub fn search_mut(&mut self) -> &mut SearchWidget {
+        &mut self.search
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title_precedence() {
+        let mut tab = Tab::new_ssh("web-01", "deploy", 22);
+        assert_eq!(tab.title(), "deploy@web-01");
+        tab.set_profile_name(Some("Production web".to_string()));
+        assert_eq!(tab.title(), "Production web");
+        tab.set_remote_title("deploy@web-01: ~/app".to_string());
+        assert_eq!(tab.title(), "deploy@web-01: ~/app");
+        tab.set_custom_title(Some("Deploys".to_string()));
+        assert_eq!(tab.title(), "Deploys");
+        tab.set_custom_title(None);
+        tab.set_remote_title(String::new());
+        assert_eq!(tab.title(), "Production web");
+    }
+
+    #[test]
+    fn test_locked_title() {
+        let mut tab = Tab::new_ssh("web-01", "deploy", 22);
+        tab.set_title_locked(true);
+        tab.set_remote_title("vim".to_string());
+        assert_eq!(tab.title(), "deploy@web-01");
+        assert_eq!(tab.blocked_title(), Some("vim"));
+
+        tab.set_title_locked(false);
+        assert_eq!(tab.title(), "vim");
+        assert_eq!(tab.blocked_title(), None);
     }
 }
@@ -2,6 +2,7 @@
 
 #![allow(dead_code)]
 
+use super::components::TabBarAction;
 use super::Tab;
 use uuid::Uuid;
 
@@ -12,6 +13,9 @@ pub struct TabManager {
 
     /// Currently active tab ID
     active_tab_id: Option<Uuid>,
+
+    /// New tabs start with their title locked
+    lock_titles: bool,
 }
 
 impl TabManager {
@@ -20,6 +24,7 @@ impl TabManager {
         Self {
             tabs: Vec::new(),
             active_tab_id: None,
+            lock_titles: false,
         }
     }
 
@@ -50,8 +55,16 @@ impl TabManager {
         self.tabs.iter_mut().find(|t| t.id() == id)
     }
 
+    /// Whether new tabs ignore titles from the remote
+    pub fn set_lock_titles(&mut self, locked: bool) {
+        self.lock_titles = locked;
+    }
+
     /// Add a new tab and make it active
-    pub fn add_tab(&mut self, tab: Tab) {
+    pub fn add_tab(&mut self, mut tab: Tab) {
+        if self.lock_titles {
+            tab.set_title_locked(true);
+        }
         let id = tab.id();
         self.tabs.push(tab);
         self.active_tab_id = Some(id);
@@ -77,6 +90,16 @@ impl TabManager {
         }
     }
 
+    /// Open a copy of a tab right after it and make it active
+    pub fn duplicate_tab(&mut self, id: Uuid) -> Option<Uuid> {
+        let pos = self.tabs.iter().position(|t| t.id() == id)?;
+        let tab = self.tabs[pos].duplicate();
+        let new_id = tab.id();
+        self.tabs.insert(pos + 1, tab);
+        self.active_tab_id = Some(new_id);
+        Some(new_id)
+    }
+
     /// Set the active tab by ID
     pub fn set_active_tab(&mut self, id: Uuid) {
         if self.tabs.iter().any(|t| t.id() == id) {
@@ -129,6 +152,42 @@ impl TabManager {
         }
     }
 
+    /// Apply the tab-only parts of a tab bar action.
+    ///
+    /// Actions that need a session (duplicate, disconnect, reconnect,
+    /// scrollback, logging, read-only) are handed back for the caller to
+    /// carry out.
+    pub fn apply_tab_bar_action(&mut self, action: TabBarAction) -> Option<TabBarAction> {
+        match action {
+            TabBarAction::Select(id) => self.set_active_tab(id),
+            TabBarAction::Close(id) => self.close_tab(id),
+            TabBarAction::Rename(id, title) => {
+                if let Some(tab) = self.get_tab_mut(id) {
+                    tab.set_custom_title(title);
+                }
+            }
+            TabBarAction::SetColor(id, color) => {
+                if let Some(tab) = self.get_tab_mut(id) {
+                    tab.set_color(color);
+                }
+            }
+            TabBarAction::LockTitle(id, locked) => {
+                if let Some(tab) = self.get_tab_mut(id) {
+                    tab.set_title_locked(locked);
+                }
+            }
+            // The caller also blocks input in the tab's terminal
+            TabBarAction::SetReadOnly(id, read_only) => {
+                if let Some(tab) = self.get_tab_mut(id) {
+                    tab.set_read_only(read_only);
+                }
+                return Some(TabBarAction::SetReadOnly(id, read_only));
+            }
+            other => return Some(other),
+        }
+        None
+    }
+
     /// Get a tab by ID
     pub fn get_tab(&self, id: Uuid) -> Option<&Tab> {
         self.tabs.iter().find(|t| t.id() == id)
@@ -145,3 +204,71 @@ impl Default for TabManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::tab::TabColor;
+
+    fn manager() -> (TabManager, Uuid, Uuid) {
+        let mut tabs = TabManager::new();
+        tabs.add_tab(Tab::new_ssh("web-01", "deploy", 22));
+        tabs.add_tab(Tab::new_ssh("db-01", "admin", 22));
+        let ids = (tabs.tabs()[0].id(), tabs.tabs()[1].id());
+        (tabs, ids.0, ids.1)
+    }
+
+    #[test]
+    fn test_tab_only_actions_are_applied() {
+        let (mut tabs, web, db) = manager();
+
+        assert!(tabs.apply_tab_bar_action(TabBarAction::Select(web)).is_none());
+        assert_eq!(tabs.active_tab_id(), Some(web));
+
+        assert!(tabs.apply_tab_bar_action(TabBarAction::Rename(web, Some("Deploys".into()))).is_none());
+        assert!(tabs.apply_tab_bar_action(TabBarAction::SetColor(web, Some(TabColor::Red))).is_none());
+        assert!(tabs.apply_tab_bar_action(TabBarAction::LockTitle(web, true)).is_none());
+        let tab = tabs.get_tab(web).unwrap();
+        assert_eq!(tab.title(), "Deploys");
+        assert_eq!(tab.color(), Some(TabColor::Red));
+        assert!(tab.is_title_locked());
+
+        assert!(tabs.apply_tab_bar_action(TabBarAction::Close(web)).is_none());
+        assert_eq!(tabs.tab_count(), 1);
+        assert_eq!(tabs.active_tab_id(), Some(db));
+    }
+
+    #[test]
+    fn test_session_actions_are_handed_back() {
+        let (mut tabs, web, _) = manager();
+
+        for action in [
+            TabBarAction::Duplicate(web),
+            TabBarAction::Disconnect(web),
+            TabBarAction::Reconnect(web),
+            TabBarAction::ClearScrollback(web),
+            TabBarAction::SetLogging(web, true),
+        ] {
+            let returned = tabs.apply_tab_bar_action(action.clone());
+            assert_eq!(format!("{:?}", returned), format!("{:?}", Some(action)));
+        }
+        assert_eq!(tabs.tab_count(), 2);
+
+        // Read-only is recorded on the tab and still handed back for the terminal
+        let returned = tabs.apply_tab_bar_action(TabBarAction::SetReadOnly(web, true));
+        assert!(matches!(returned, Some(TabBarAction::SetReadOnly(id, true)) if id == web));
+        assert!(tabs.get_tab(web).unwrap().is_read_only());
+    }
+
+    #[test]
+    fn test_duplicate_goes_next_to_original() {
+        let (mut tabs, web, db) = manager();
+        tabs.apply_tab_bar_action(TabBarAction::SetColor(web, Some(TabColor::Blue)));
+
+        let copy = tabs.duplicate_tab(web).unwrap();
+        let order: Vec<Uuid> = tabs.tabs().iter().map(Tab::id).collect();
+        assert_eq!(order, vec![web, copy, db]);
+        assert_eq!(tabs.active_tab_id(), Some(copy));
+        assert_eq!(tabs.get_tab(copy).unwrap().color(), Some(TabColor::Blue));
+    }
+}
@@ -0,0 +1,237 @@
+//! Crash reports
+//!
+//! With `crash_reports` on, a panic writes a report to the data directory:
+//! version, OS, the panic message and backtrace, and the last log lines.
+//! Host names, user names, addresses and home paths are masked before it
+//! is written: saved hosts by name, others where log lines name them.
+//! Nothing leaves the machine by itself; on the next start the app shows
+//! the report and lets the user open an issue with it, copy it, or delete
+//! it.
+
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use crate::platform::PlatformManager;
+use crate::utils::logging;
+use crate::utils::updater::APP_VERSION;
+
+const ISSUES_URL: &str = "https://github.com/tabssh/desktop/issues/new";
+/// Browsers and servers reject much longer URLs; the rest can be pasted
+const MAX_ISSUE_BODY: usize = 6000;
+
+/// Set from the `crash_reports` setting once settings are loaded
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Saved host names; log lines mention them bare, e.g. "Connecting to web-01:22"
+static KNOWN_HOSTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Mask these host names in reports, from the connections and known_hosts tables
+pub fn set_known_hosts(hosts: Vec<String>) {
+    if let Ok(mut known) = KNOWN_HOSTS.lock() {
+        *known = hosts;
+    }
+}
+
+/// Write a report for panics from here on, then run the default hook
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if ENABLED.load(Ordering::Relaxed) {
+            let report = build_report(info);
+            match save_report(&report) {
+                Ok(path) => eprintln!("Crash report saved to {}", path.display()),
+                Err(e) => eprintln!("Could not save crash report: {}", e),
+            }
+        }
+        default_hook(info);
+    }));
+}
+
+fn build_report(info: &std::panic::PanicHookInfo<'_>) -> String {
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "(no message)".to_string());
+    let location = info.location().map_or_else(String::new, |l| format!(" at {}:{}", l.file(), l.line()));
+    let thread = std::thread::current().name().unwrap_or("unnamed").to_string();
+
+    let report = format!(
+        "TabSSH {}\nOS: {} {}\nTime: {}\nThread: {}\n\nPanic: {}{}\n\nBacktrace:\n{}\n\nRecent log:\n{}\n",
+        APP_VERSION,
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        chrono::Utc::now().to_rfc3339(),
+        thread,
+        message,
+        location,
+        std::backtrace::Backtrace::force_capture(),
+        logging::recent_lines().join("\n"),
+    );
+    // The panic may have come while the list was held
+    let hosts = KNOWN_HOSTS.try_lock().map(|hosts| hosts.clone()).unwrap_or_default();
+    redact(&report, dirs::home_dir().as_deref(), &hosts)
+}
+
+fn crash_dir() -> Option<PathBuf> {
+    PlatformManager::get_data_directory().map(|dir| dir.join("crashes"))
+}
+
+fn save_report(report: &str) -> std::io::Result<PathBuf> {
+    let dir = crash_dir().ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no data directory"))?;
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("crash-{}.txt", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+    std::fs::write(&path, report)?;
+    Ok(path)
+}
+
+/// Reports left by earlier crashes, oldest first
+pub fn pending_reports() -> Vec<PathBuf> {
+    let Some(dir) = crash_dir() else { return Vec::new() };
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    let mut reports: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .collect();
+    reports.sort();
+    reports
+}
+
+/// The oldest report with its text
+pub fn next_report() -> Option<(PathBuf, String)> {
+    pending_reports()
+        .into_iter()
+        .find_map(|path| std::fs::read_to_string(&path).ok().map(|report| (path, report)))
+}
+
+/// Delete a report once the user has dealt with it
+pub fn discard(path: &Path) {
+    if let Err(e) = std::fs::remove_file(path) {
+        log::warn!("Could not delete crash report {}: {}", path.display(), e);
+    }
+}
+
+/// New-issue link prefilled with the report
+pub fn issue_url(report: &str) -> String {
+    let title = report
+        .lines()
+        .find_map(|line| line.strip_prefix("Panic: "))
+        .unwrap_or("Crash report");
+    let mut body = report.to_string();
+    if body.len() > MAX_ISSUE_BODY {
+        let mut cut = MAX_ISSUE_BODY;
+        while !body.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        body.truncate(cut);
+        body.push_str("\n(truncated; paste the rest from the copied report)");
+    }
+    format!(
+        "{}?labels=crash&title={}&body={}",
+        ISSUES_URL,
+        encode_query(&format!("Crash: {}", title)),
+        encode_query(&format!("```\n{}\n```", body)),
+    )
+}
+
+fn encode_query(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Mask what could identify the user or their hosts; `hosts` are names to
+/// mask wherever they appear
+pub fn redact(text: &str, home: Option<&Path>, hosts: &[String]) -> String {
+    static PATTERNS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    let patterns = PATTERNS.get_or_init(|| {
+        [
+            (r"[\w.+-]+@[\w-]+(\.[\w-]+)*", "<user>@<host>"),
+            (r"\b\d{1,3}(\.\d{1,3}){3}\b", "<ip>"),
+            (r"\b([0-9a-fA-F]{1,4}:){7}[0-9a-fA-F]{1,4}\b", "<ip>"),
+            // Compressed IPv6, at least one `::`
+            (r"\b[0-9a-fA-F]{1,4}(:[0-9a-fA-F]{1,4})*::([0-9a-fA-F]{1,4}(:[0-9a-fA-F]{1,4})*)?", "<ip>"),
+            (r"(?i)\b(host|hostname|user|username|password|passphrase)([=:]\s*)\S+", "$1$2<redacted>"),
+            // Hosts the log names without a label, saved or not
+            (
+                r"(?i)\b(connecting to|connection to|server key for|verified for|new host|certificate for|exec on|watching)\s+[^\s:@<][^\s:@]*",
+                "$1 <host>",
+            ),
+        ]
+        .into_iter()
+        .map(|(pattern, replacement)| (Regex::new(pattern).expect("valid pattern"), replacement))
+        .collect()
+    });
+
+    let mut text = match home.map(|h| h.to_string_lossy().to_string()) {
+        Some(home) if home.len() > 1 => text.replace(&home, "~"),
+        _ => text.to_string(),
+    };
+    // Longest first, so `web-01.example.com` goes before `web-01`; very
+    // short names would mask ordinary words
+    let mut hosts: Vec<&String> = hosts.iter().filter(|host| host.len() >= 3).collect();
+    hosts.sort_by_key(|host| std::cmp::Reverse(host.len()));
+    for host in hosts {
+        if let Ok(pattern) = Regex::new(&format!(r"(?i)\b{}\b", regex::escape(host))) {
+            text = pattern.replace_all(&text, "<host>").into_owned();
+        }
+    }
+    for (pattern, replacement) in patterns {
+        text = pattern.replace_all(&text, *replacement).into_owned();
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let text = "Connecting to deploy@web-01.example.com (10.0.4.17)\n\
+                    Reading /home/alice/.ssh/id_ed25519\n\
+                    password=hunter2 user: alice\n\
+                    Peer fe80::1c2b:3aff:fe4d:5e6f";
+        let redacted = redact(text, Some(Path::new("/home/alice")), &[]);
+        assert!(redacted.contains("<user>@<host> (<ip>)"));
+        assert!(redacted.contains("~/.ssh/id_ed25519"));
+        assert!(redacted.contains("password=<redacted> user: <redacted>"));
+        assert!(redacted.contains("Peer <ip>"));
+        for secret in ["deploy", "example.com", "10.0.4.17", "alice", "hunter2", "fe80"] {
+            assert!(!redacted.contains(secret), "{} left in {}", secret, redacted);
+        }
+    }
+
+    #[test]
+    fn test_redact_bare_hosts_in_log_lines() {
+        // As `logging::recent_lines` keeps them
+        let log = "2026-10-16T09:12:03Z INFO tabssh::ssh::connection: Connecting to bastion-eu:22\n\
+                   2026-10-16T09:12:04Z INFO tabssh::storage::database: Added known host: db-primary:5022 (SHA256:abc)\n\
+                   2026-10-16T09:12:05Z INFO tabssh::ssh::connection: Host key verified for quick-box:22\n\
+                   2026-10-16T09:12:06Z WARN tabssh::ui: Reconnecting to db-primary soon";
+        let hosts = ["db-primary".to_string(), "ab".to_string()];
+        let redacted = redact(log, None, &hosts);
+        for secret in ["bastion-eu", "db-primary", "quick-box"] {
+            assert!(!redacted.contains(secret), "{} left in {}", secret, redacted);
+        }
+        assert!(redacted.contains("Connecting to <host>:22"));
+        assert!(redacted.contains("tabssh::ssh::connection"));
+        assert!(redacted.contains("09:12:03Z"));
+    }
+
+    #[test]
+    fn test_issue_url() {
+        let url = issue_url("TabSSH 0.1.0\nPanic: index out of bounds at src/x.rs:3\n");
+        assert!(url.starts_with("https://github.com/tabssh/desktop/issues/new?labels=crash&title=Crash%3A%20index"));
+        assert!(!url.contains(' ') && !url.contains('\n'));
+    }
+}
@@ -0,0 +1,249 @@
+//! User-defined external actions
+//!
+//! A custom action is a local command template shown in connection and file
+//! context menus. The template is split into words and run without a shell,
+//! values going in as plain arguments: file names come from the server, and
+//! no quoting is safe once a value such as `$(...)` reaches `sh -c` or
+//! `cmd /C`. Placeholders:
+//!
+//! - `%host`, `%port`, `%user` - the connection
+//! - `%path` - remote path of the selected file or folder
+//! - `%local_file` - local path of the selected file
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Where a custom action is offered
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ActionScope {
+    Connection,
+    File,
+    Both,
+}
+
+impl std::fmt::Display for ActionScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActionScope::Connection => write!(f, "Connections"),
+            ActionScope::File => write!(f, "Files"),
+            ActionScope::Both => write!(f, "Connections and files"),
+        }
+    }
+}
+
+/// A user-defined command template
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomAction {
+    pub id: String,
+    pub name: String,
+    pub command: String,
+    pub scope: ActionScope,
+}
+
+impl CustomAction {
+    pub fn new(name: &str, command: &str, scope: ActionScope) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            command: command.to_string(),
+            scope,
+        }
+    }
+
+    pub fn in_connection_menu(&self) -> bool {
+        matches!(self.scope, ActionScope::Connection | ActionScope::Both)
+    }
+
+    pub fn in_file_menu(&self) -> bool {
+        matches!(self.scope, ActionScope::File | ActionScope::Both)
+    }
+
+    /// Whether every placeholder the template uses has a value in `ctx`
+    pub fn is_available(&self, ctx: &ActionContext) -> bool {
+        self.expand_args(ctx).is_some()
+    }
+
+    /// Program and arguments to run without a shell: the template is split
+    /// into words (double quotes group words) and values are substituted
+    /// into each word as they are. `None` if the template needs a value
+    /// `ctx` lacks.
+    pub fn expand_args(&self, ctx: &ActionContext) -> Option<Vec<String>> {
+        split_words(&self.command)
+            .iter()
+            .map(|word| substitute(word, ctx))
+            .collect()
+    }
+
+    /// Expand and start the command without waiting for it
+    pub fn launch(&self, ctx: &ActionContext) -> Result<()> {
+        let args = self.expand_args(ctx)
+            .ok_or_else(|| anyhow!("'{}' needs a file selection", self.name))?;
+        let (program, args) = args.split_first()
+            .ok_or_else(|| anyhow!("'{}' has no command", self.name))?;
+        log::info!("Running custom action '{}': {} {:?}", self.name, program, args);
+        Command::new(program).args(args).spawn()?;
+        Ok(())
+    }
+}
+
+/// Substitute placeholders in one word of a template.
+///
+/// Expansion is a single pass so substituted values are never re-expanded.
+fn substitute(template: &str, ctx: &ActionContext) -> Option<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(idx) = rest.find('%') {
+        out.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+
+        let placeholder = ["%local_file", "%host", "%port", "%user", "%path"]
+            .into_iter()
+            .find(|p| rest.starts_with(p));
+
+        match placeholder {
+            Some(p) => {
+                let value = match p {
+                    "%local_file" => ctx.local_file.as_ref()?.to_string_lossy().into_owned(),
+                    "%host" => ctx.host.clone(),
+                    "%port" => ctx.port.to_string(),
+                    "%user" => ctx.user.clone(),
+                    _ => ctx.path.clone()?,
+                };
+                out.push_str(&value);
+                rest = &rest[p.len()..];
+            }
+            // Unknown sequences such as `%20` are copied through
+            None => {
+                out.push('%');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+
+    Some(out)
+}
+
+/// Split a template on whitespace; double quotes keep spaces in a word
+fn split_words(template: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quoted = false;
+    for c in template.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_word = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
+
+/// Values available to placeholders at the point an action is invoked
+#[derive(Debug, Clone, Default)]
+pub struct ActionContext {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub path: Option<String>,
+    pub local_file: Option<PathBuf>,
+}
+
+/// Starter actions offered when the user has none configured
+pub fn example_actions() -> Vec<CustomAction> {
+    vec![
+        CustomAction::new(
+            "Open in VS Code Remote",
+            "code --remote ssh-remote+%user@%host %path",
+            ActionScope::File,
+        ),
+        CustomAction::new(
+            "Run Ansible playbook",
+            "ansible-playbook -i %host, -u %user site.yml",
+            ActionScope::Connection,
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx() -> ActionContext {
+        ActionContext {
+            host: "web01".to_string(),
+            port: 2222,
+            user: "deploy".to_string(),
+            path: Some("/var/www/my site".to_string()),
+            local_file: None,
+        }
+    }
+
+    #[test]
+    fn test_expand_placeholders() {
+        let action = CustomAction::new("Edit", "code --remote ssh-remote+%user@%host:%port %path", ActionScope::File);
+        assert_eq!(
+            action.expand_args(&ctx()).unwrap(),
+            vec!["code", "--remote", "ssh-remote+deploy@web01:2222", "/var/www/my site"]
+        );
+    }
+
+    #[test]
+    fn test_missing_placeholder_hides_action() {
+        let action = CustomAction::new("Diff", "meld %local_file", ActionScope::File);
+        assert!(!action.is_available(&ctx()));
+
+        let mut with_file = ctx();
+        with_file.local_file = Some(PathBuf::from("/tmp/a.txt"));
+        assert_eq!(action.expand_args(&with_file).unwrap(), vec!["meld", "/tmp/a.txt"]);
+    }
+
+    #[test]
+    fn test_hostile_values_stay_single_arguments() {
+        let hostile = ActionContext {
+            host: "x & calc.exe".to_string(),
+            port: 22,
+            user: "%USERPROFILE%^|del".to_string(),
+            path: Some("a\" && echo pwned \"b".to_string()),
+            local_file: None,
+        };
+        let action = CustomAction::new("Edit", "code \"--remote=ssh-remote+%user@%host\" %path", ActionScope::File);
+        assert_eq!(
+            action.expand_args(&hostile).unwrap(),
+            vec![
+                "code".to_string(),
+                "--remote=ssh-remote+%USERPROFILE%^|del@x & calc.exe".to_string(),
+                "a\" && echo pwned \"b".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_server_file_names_are_never_run() {
+        let mut hostile = ctx();
+        hostile.path = Some("$(rm -rf ~)`id`; reboot".to_string());
+        let action = CustomAction::new("Open", "xdg-open \"sftp://%host%path\"", ActionScope::File);
+        assert_eq!(
+            action.expand_args(&hostile).unwrap(),
+            vec!["xdg-open", "sftp://web01$(rm -rf ~)`id`; reboot"]
+        );
+    }
+}
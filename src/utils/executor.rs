@@ -0,0 +1,93 @@
+//! App-owned async executor
+//!
+//! One tokio runtime serves the whole app. UI code never blocks on it:
+//! work is spawned here and its result picked up from a [`Task`] on a later
+//! frame, with a repaint requested as soon as the result is ready.
+
+use anyhow::{anyhow, Result};
+use eframe::egui;
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+use tokio::runtime::Runtime;
+use tokio::sync::oneshot;
+
+/// Shared handle to the app runtime; cheap to clone
+#[derive(Clone)]
+pub struct Executor {
+    runtime: Arc<Runtime>,
+    repaint: Arc<OnceLock<egui::Context>>,
+}
+
+impl Executor {
+    pub fn new() -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .thread_name("tabssh-worker")
+            .enable_all()
+            .build()?;
+
+        Ok(Self {
+            runtime: Arc::new(runtime),
+            repaint: Arc::new(OnceLock::new()),
+        })
+    }
+
+    /// Wake the UI whenever a task finishes; set once the egui context exists
+    pub fn set_repaint_context(&self, ctx: egui::Context) {
+        let _ = self.repaint.set(ctx);
+    }
+
+    /// The underlying runtime, for services that start their own listeners
+    pub fn runtime(&self) -> &Runtime {
+        &self.runtime
+    }
+
+    /// Run a future in the background and collect its output from the UI thread
+    pub fn spawn<F>(&self, future: F) -> Task<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let repaint = self.repaint.clone();
+        self.runtime.spawn(async move {
+            let _ = tx.send(future.await);
+            if let Some(ctx) = repaint.get() {
+                ctx.request_repaint();
+            }
+        });
+        Task { rx: Some(rx) }
+    }
+
+    /// Run a future in the background without waiting for its output
+    pub fn spawn_detached<F>(&self, future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.runtime.spawn(future);
+    }
+}
+
+/// Output of a spawned future, polled once per frame
+pub struct Task<T> {
+    rx: Option<oneshot::Receiver<T>>,
+}
+
+impl<T> Task<T> {
+    /// The output once ready; an error if the task panicked or was cancelled.
+    /// Returns `None` while running and after the result has been taken.
+    pub fn poll(&mut self) -> Option<Result<T>> {
+        let rx = self.rx.as_mut()?;
+        let result = match rx.try_recv() {
+            Ok(value) => Ok(value),
+            Err(oneshot::error::TryRecvError::Empty) => return None,
+            Err(oneshot::error::TryRecvError::Closed) => Err(anyhow!("Background task failed")),
+        };
+        self.rx = None;
+        Some(result)
+    }
+
+    /// Whether the output has already been taken
+    pub fn is_done(&self) -> bool {
+        self.rx.is_none()
+    }
+}
@@ -70,6 +70,103 @@ pub fn format_transfer_speed(bytes_per_second: f64) -> String {
     format_file_size(bytes_per_second as u64) + "/s"
 }
 
+/// Compact idle time, e.g. "42s", "3m 12s" or "2h 05m"
+pub fn format_idle(idle: std::time::Duration) -> String {
+    let secs = idle.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// Quote a string for safe use as a single POSIX shell word
+pub fn shell_quote(s: &str) -> String {
+    if !s.is_empty()
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:@%+,".contains(c))
+    {
+        return s.to_string();
+    }
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Build an `sftp://user@host[:port]/path` URL for a remote path
+pub fn sftp_url(user: &str, host: &str, port: u16, path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            ' ' => encoded.push_str("%20"),
+            '#' => encoded.push_str("%23"),
+            '%' => encoded.push_str("%25"),
+            '?' => encoded.push_str("%3F"),
+            c => encoded.push(c),
+        }
+    }
+    if !encoded.starts_with('/') {
+        encoded.insert(0, '/');
+    }
+
+    let authority = if user.is_empty() {
+        host.to_string()
+    } else {
+        format!("{}@{}", user, host)
+    };
+
+    if port == 22 {
+        format!("sftp://{}{}", authority, encoded)
+    } else {
+        format!("sftp://{}:{}{}", authority, port, encoded)
+    }
+}
+
+/// Parse a quick-connect target of the form `[user@]host[:port]`
+pub fn parse_quick_connect(target: &str) -> Option<(Option<String>, String, u16)> {
+    let target = target.trim();
+    let (user, rest) = match target.rsplit_once('@') {
+        Some((user, rest)) if !user.is_empty() => (Some(user.to_string()), rest),
+        Some(_) => return None,
+        None => (None, target),
+    };
+
+    // Bracketed IPv6 literal, optionally followed by a port
+    if let Some(inner) = rest.strip_prefix('[') {
+        let (host, after) = inner.split_once(']')?;
+        let port = match after.strip_prefix(':') {
+            Some(port) => port.parse().ok()?,
+            None if after.is_empty() => 22,
+            None => return None,
+        };
+        return (!host.is_empty()).then(|| (user, host.to_string(), port));
+    }
+
+    let (host, port) = match rest.split_once(':') {
+        Some((host, port)) => (host, port.parse().ok().filter(|p| *p != 0)?),
+        None => (rest, 22),
+    };
+    if host.is_empty() || host.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((user, host.to_string(), port))
+}
+
+/// Whether `host` is an IP address or a syntactically valid DNS name
+pub fn is_valid_hostname(host: &str) -> bool {
+    if host.parse::<std::net::IpAddr>().is_ok() {
+        return true;
+    }
+    let name = host.strip_suffix('.').unwrap_or(host);
+    if name.is_empty() || name.len() > 253 {
+        return false;
+    }
+    name.split('.').all(|label| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -95,4 +192,52 @@ mod tests {
         assert_eq!(sanitize_filename("test/file.txt"),"test_file.txt");
         assert_eq!(sanitize_filename("test:file?.txt"),"test_file_.txt");
     }
+
+    #[test]
+    fn test_format_idle() {
+        use std::time::Duration;
+        assert_eq!(format_idle(Duration::from_secs(42)), "42s");
+        assert_eq!(format_idle(Duration::from_secs(192)), "3m 12s");
+        assert_eq!(format_idle(Duration::from_secs(7500)), "2h 05m");
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("plain.txt"), "plain.txt");
+        assert_eq!(shell_quote("my file"), "'my file'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn test_sftp_url() {
+        assert_eq!(sftp_url("root", "example.com", 22, "/var/log/syslog"), "sftp://root@example.com/var/log/syslog");
+        assert_eq!(sftp_url("admin", "10.0.0.1", 2222, "/srv/my file"), "sftp://admin@10.0.0.1:2222/srv/my%20file");
+        assert_eq!(sftp_url("", "host", 22, "~/notes.txt"), "sftp://host/~/notes.txt");
+    }
+
+    #[test]
+    fn test_parse_quick_connect() {
+        assert_eq!(parse_quick_connect("web01"), Some((None, "web01".to_string(), 22)));
+        assert_eq!(
+            parse_quick_connect("deploy@10.0.0.5:2222"),
+            Some((Some("deploy".to_string()), "10.0.0.5".to_string(), 2222))
+        );
+        assert_eq!(parse_quick_connect("root@[::1]:22"), Some((Some("root".to_string()), "::1".to_string(), 22)));
+        assert_eq!(parse_quick_connect("@host"), None);
+        assert_eq!(parse_quick_connect("host:abc"), None);
+        assert_eq!(parse_quick_connect(""), None);
+    }
+
+    #[test]
+    fn test_is_valid_hostname() {
+        assert!(is_valid_hostname("example.com"));
+        assert!(is_valid_hostname("web-01.internal."));
+        assert!(is_valid_hostname("10.0.0.5"));
+        assert!(is_valid_hostname("fe80::1"));
+        assert!(!is_valid_hostname(""));
+        assert!(!is_valid_hostname("bad host"));
+        assert!(!is_valid_hostname("-web.example.com"));
+        assert!(!is_valid_hostname("a..b"));
+    }
 }
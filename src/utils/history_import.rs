@@ -0,0 +1,170 @@
+//! Profile suggestions from shell history
+//!
+//! Scans the local bash and zsh history for `ssh` invocations and counts the
+//! targets, so frequently used hosts can be offered as new profiles.
+
+use crate::utils::helpers::{is_valid_hostname, parse_quick_connect};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+/// Options of ssh(1) that take an argument
+const OPTIONS_WITH_ARG: &str = "BbcDEeFIiJLlmOoPpQRSWw";
+
+/// An ssh target seen in shell history
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryTarget {
+    pub user: Option<String>,
+    pub host: String,
+    pub port: u16,
+    /// Invocations found
+    pub count: usize,
+}
+
+impl HistoryTarget {
+    /// `user@host[:port]` for display
+    pub fn label(&self) -> String {
+        let mut label = match &self.user {
+            Some(user) => format!("{}@{}", user, self.host),
+            None => self.host.clone(),
+        };
+        if self.port != 22 {
+            label.push_str(&format!(":{}", self.port));
+        }
+        label
+    }
+}
+
+/// History files of the current user that exist
+pub fn default_history_files() -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::env::var_os("HISTFILE").map(PathBuf::from).into_iter().collect();
+    if let Some(home) = dirs::home_dir() {
+        files.push(home.join(".bash_history"));
+        files.push(home.join(".zsh_history"));
+        files.push(home.join(".zhistory"));
+    }
+    // HISTFILE usually names one of the defaults; keep the first of each
+    let mut seen = HashSet::new();
+    files.into_iter().filter(|p| seen.insert(p.clone()) && p.is_file()).collect()
+}
+
+/// Target of an `ssh` command line, if the line is one
+pub fn parse_ssh_invocation(line: &str) -> Option<(Option<String>, String, u16)> {
+    // zsh extended history: ": <start>:<elapsed>;<command>"
+    let line = match line.strip_prefix(": ") {
+        Some(rest) => rest.split_once(';')?.1,
+        None => line,
+    };
+
+    let mut words = line.split_whitespace().skip_while(|w| *w == "sudo" || w.contains('='));
+    let command = words.next()?;
+    if command.rsplit('/').next() != Some("ssh") {
+        return None;
+    }
+
+    let mut user = None;
+    let mut port = None;
+    while let Some(word) = words.next() {
+        if let Some(flags) = word.strip_prefix('-') {
+            // Flags may be grouped ("-tp 2222") and an argument may be attached ("-p2222")
+            for (i, flag) in flags.char_indices() {
+                if OPTIONS_WITH_ARG.contains(flag) {
+                    let attached = &flags[i + flag.len_utf8()..];
+                    let value = if attached.is_empty() { words.next()? } else { attached };
+                    match flag {
+                        'p' => port = Some(value.parse().ok()?),
+                        'l' => user = Some(value.to_string()),
+                        _ => {}
+                    }
+                    break;
+                }
+            }
+            continue;
+        }
+
+        let (dest_user, host, dest_port) = match word.strip_prefix("ssh://") {
+            Some(url) => parse_quick_connect(url.trim_end_matches('/'))?,
+            None => match word.rsplit_once('@') {
+                Some((u, host)) if !u.is_empty() => (Some(u.to_string()), host.to_string(), 22),
+                Some(_) => return None,
+                None => (None, word.to_string(), 22),
+            },
+        };
+        if !is_valid_hostname(&host) {
+            return None;
+        }
+        let port = port.unwrap_or(dest_port);
+        return Some((dest_user.or(user), host, port));
+    }
+    None
+}
+
+/// Count ssh targets across history files, most used first
+pub fn scan_history(files: &[PathBuf]) -> Vec<HistoryTarget> {
+    let mut counts: HashMap<(Option<String>, String, u16), usize> = HashMap::new();
+    for path in files {
+        // zsh metafies non-ASCII bytes, so the file is not always valid UTF-8
+        let Ok(data) = std::fs::read(path) else {
+            log::warn!("Cannot read shell history {}", path.display());
+            continue;
+        };
+        for line in String::from_utf8_lossy(&data).lines() {
+            if let Some(target) = parse_ssh_invocation(line) {
+                *counts.entry(target).or_default() += 1;
+            }
+        }
+    }
+
+    let mut targets: Vec<HistoryTarget> = counts.into_iter()
+        .map(|((user, host, port), count)| HistoryTarget { user, host, port, count })
+        .collect();
+    targets.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.host.cmp(&b.host)));
+    targets
+}
+
+/// Targets used at least `min_count` times that no existing profile covers.
+///
+/// `existing` holds `(user, host, port)` of the saved profiles; hosts compare
+/// case-insensitively and a target without a user matches any user.
+pub fn suggest_profiles(
+    targets: Vec<HistoryTarget>,
+    existing: &[(String, String, u16)],
+    min_count: usize,
+) -> Vec<HistoryTarget> {
+    targets.into_iter()
+        .filter(|t| t.count >= min_count)
+        .filter(|t| !existing.iter().any(|(user, host, port)| {
+            host.eq_ignore_ascii_case(&t.host)
+                && *port == t.port
+                && t.user.as_ref().map_or(true, |u| u == user)
+        }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ssh_invocation() {
+        assert_eq!(parse_ssh_invocation("ssh deploy@web01"), Some((Some("deploy".into()), "web01".into(), 22)));
+        assert_eq!(parse_ssh_invocation("ssh -p 2222 -i ~/.ssh/id db.example.com uptime"), Some((None, "db.example.com".into(), 2222)));
+        assert_eq!(parse_ssh_invocation(": 1700000000:0;ssh -tp2200 -l admin 10.0.0.5"), Some((Some("admin".into()), "10.0.0.5".into(), 2200)));
+        assert_eq!(parse_ssh_invocation("/usr/bin/ssh ssh://root@host.lan:2022"), Some((Some("root".into()), "host.lan".into(), 2022)));
+        assert_eq!(parse_ssh_invocation("ssh-keygen -t ed25519"), None);
+        assert_eq!(parse_ssh_invocation("ssh $HOST"), None);
+        assert_eq!(parse_ssh_invocation("git push"), None);
+    }
+
+    #[test]
+    fn test_suggest_profiles() {
+        let targets = vec![
+            HistoryTarget { user: Some("deploy".into()), host: "web01".into(), port: 22, count: 9 },
+            HistoryTarget { user: None, host: "db01".into(), port: 22, count: 4 },
+            HistoryTarget { user: None, host: "once".into(), port: 22, count: 1 },
+        ];
+        let existing = vec![("root".to_string(), "DB01".to_string(), 22)];
+        let suggested = suggest_profiles(targets, &existing, 2);
+        assert_eq!(suggested.len(), 1);
+        assert_eq!(suggested[0].label(), "deploy@web01");
+    }
+}
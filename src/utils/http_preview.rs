@@ -0,0 +1,69 @@
+//! Quick look at a web app through a forward
+//!
+//! Fetches the root URL of a forwarded port and keeps the status line,
+//! headers and the start of the body, so users can see the tunnel works
+//! without leaving the app.
+
+use anyhow::{anyhow, Result};
+use std::io::Read;
+use std::time::{Duration, Instant};
+
+/// Remote ports that usually serve HTTP; TLS ones get an https:// URL
+const HTTP_PORTS: [u16; 12] = [80, 3000, 4200, 5000, 5173, 8000, 8008, 8080, 8081, 8888, 9000, 9090];
+const HTTPS_PORTS: [u16; 3] = [443, 8443, 9443];
+/// Enough of the body to recognise the page
+const BODY_PREVIEW: usize = 2048;
+
+#[derive(Debug, Clone)]
+pub struct HttpPreview {
+    pub url: String,
+    pub status: u16,
+    pub status_text: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    pub elapsed: Duration,
+}
+
+/// Browser URL for a local forward to `remote_port`, when that port
+/// usually speaks HTTP
+pub fn forward_url(local_port: u16, remote_port: u16) -> Option<String> {
+    if HTTPS_PORTS.contains(&remote_port) {
+        Some(format!("https://localhost:{}/", local_port))
+    } else if HTTP_PORTS.contains(&remote_port) {
+        Some(format!("http://localhost:{}/", local_port))
+    } else {
+        None
+    }
+}
+
+/// GET `url` and keep what the preview shows; blocks. Error statuses are
+/// a result too: a 404 still proves the tunnel reaches a server.
+pub fn fetch(url: &str) -> Result<HttpPreview> {
+    let started = Instant::now();
+    let response = match ureq::get(url).timeout(Duration::from_secs(10)).call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(_, response)) => response,
+        Err(e) => return Err(anyhow!("No answer from {}: {}", url, e)),
+    };
+    let elapsed = started.elapsed();
+
+    let headers = response
+        .headers_names()
+        .into_iter()
+        .filter_map(|name| response.header(&name).map(|value| (name.clone(), value.to_string())))
+        .collect();
+    let status = response.status();
+    let status_text = response.status_text().to_string();
+
+    let mut body = Vec::new();
+    response.into_reader().take(BODY_PREVIEW as u64).read_to_end(&mut body)?;
+
+    Ok(HttpPreview {
+        url: url.to_string(),
+        status,
+        status_text,
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+        elapsed,
+    })
+}
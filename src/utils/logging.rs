@@ -2,7 +2,13 @@
 
 use env_logger::Builder;
 use log::LevelFilter;
+use std::collections::VecDeque;
 use std::io::Write;
+use std::sync::Mutex;
+
+/// Log lines kept in memory for crash reports
+const RECENT_LINES: usize = 200;
+static RECENT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
 
 pub fn init_logging(level: &str) {
     let log_level = match level.to_lowercase().as_str() {
@@ -17,20 +23,37 @@ pub fn init_logging(level: &str) {
     Builder::new()
         .filter_level(log_level)
         .format(|buf, record| {
-            writeln!(
-                buf,
+            let line = format!(
                 "[{} {} {}] {}",
                 chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
                 record.level(),
                 record.target(),
                 record.args()
-            )
+            );
+            writeln!(buf, "{}", line)?;
+            remember(line);
+            Ok(())
         })
         .init();
     
     log::info!("Logginginitializedat{}level",log_level);
 }
 
+fn remember(line: String) {
+    if let Ok(mut recent) = RECENT.lock() {
+        if recent.len() == RECENT_LINES {
+            recent.pop_front();
+        }
+        recent.push_back(line);
+    }
+}
+
+/// The last lines logged, oldest first. Never waits: a panic while logging
+/// would otherwise deadlock the crash report.
+pub fn recent_lines() -> Vec<String> {
+    RECENT.try_lock().map(|recent| recent.iter().cloned().collect()).unwrap_or_default()
+}
+
 pub fn log_session_start(host: &str, user: &str) {
     log::info!("===Sessionstarted:{}@{}===",user,host);
 }
@@ -0,0 +1,143 @@
+//! Memory accounting per session
+//!
+//! Terminals report what their scrollback holds as they draw, and SFTP
+//! transfers hold a lease on each buffer they allocate, so the diagnostics
+//! view can show where memory goes after a session has run for days.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Scrollback memory a terminal may use unless the settings say otherwise
+pub const DEFAULT_SCROLLBACK_CAP: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionMemory {
+    /// Tab title or host, for display
+    pub label: String,
+    pub scrollback_lines: usize,
+    /// Screen and scrollback cells, including what the containers reserve
+    pub scrollback_bytes: usize,
+    /// Cap the terminal evicts scrollback at
+    pub scrollback_cap: usize,
+    /// Buffers held by transfers running right now
+    pub transfer_bytes: usize,
+    pub transfers: usize,
+}
+
+impl SessionMemory {
+    pub fn total_bytes(&self) -> usize {
+        self.scrollback_bytes + self.transfer_bytes
+    }
+}
+
+static SESSIONS: Mutex<BTreeMap<String, SessionMemory>> = Mutex::new(BTreeMap::new());
+
+fn with_session(session: &str, update: impl FnOnce(&mut SessionMemory)) {
+    if let Ok(mut sessions) = SESSIONS.lock() {
+        update(sessions.entry(session.to_string()).or_default());
+    }
+}
+
+/// Latest scrollback figures of a terminal
+pub fn report_scrollback(session: &str, label: &str, lines: usize, bytes: usize, cap: usize) {
+    with_session(session, |memory| {
+        memory.label = label.to_string();
+        memory.scrollback_lines = lines;
+        memory.scrollback_bytes = bytes;
+        memory.scrollback_cap = cap;
+    });
+}
+
+/// Stop listing a session once its tab is closed. Transfers still running
+/// keep theirs until they end.
+pub fn forget(session: &str) {
+    if let Ok(mut sessions) = SESSIONS.lock() {
+        let idle = sessions.get(session).map_or(false, |memory| memory.transfers == 0);
+        if idle {
+            sessions.remove(session);
+        } else if let Some(memory) = sessions.get_mut(session) {
+            memory.scrollback_lines = 0;
+            memory.scrollback_bytes = 0;
+            memory.scrollback_cap = 0;
+        }
+    }
+}
+
+/// Count `bytes` of transfer buffer against `session` until the lease drops
+pub fn lease_transfer(session: &str, bytes: usize) -> TransferLease {
+    with_session(session, |memory| {
+        if memory.label.is_empty() {
+            memory.label = "SFTP".to_string();
+        }
+        memory.transfer_bytes += bytes;
+        memory.transfers += 1;
+    });
+    TransferLease { session: session.to_string(), bytes }
+}
+
+pub struct TransferLease {
+    session: String,
+    bytes: usize,
+}
+
+impl Drop for TransferLease {
+    fn drop(&mut self) {
+        if let Ok(mut sessions) = SESSIONS.lock() {
+            if let Some(memory) = sessions.get_mut(&self.session) {
+                memory.transfer_bytes = memory.transfer_bytes.saturating_sub(self.bytes);
+                memory.transfers = memory.transfers.saturating_sub(1);
+                // A session with no tab left only stayed for its transfers
+                if memory.transfers == 0 && memory.scrollback_cap == 0 {
+                    sessions.remove(&self.session);
+                }
+            }
+        }
+    }
+}
+
+/// Every session with its figures, sorted by id
+pub fn snapshot() -> Vec<(String, SessionMemory)> {
+    SESSIONS
+        .lock()
+        .map(|sessions| sessions.iter().map(|(id, memory)| (id.clone(), memory.clone())).collect())
+        .unwrap_or_default()
+}
+
+/// Sum over all sessions
+pub fn totals(sessions: &[(String, SessionMemory)]) -> SessionMemory {
+    sessions.iter().fold(SessionMemory { label: "All sessions".to_string(), ..Default::default() }, |mut sum, (_, memory)| {
+        sum.scrollback_lines += memory.scrollback_lines;
+        sum.scrollback_bytes += memory.scrollback_bytes;
+        sum.scrollback_cap = sum.scrollback_cap.saturating_add(memory.scrollback_cap);
+        sum.transfer_bytes += memory.transfer_bytes;
+        sum.transfers += memory.transfers;
+        sum
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leases_and_forget() {
+        report_scrollback("memory-test", "me@host", 100, 4096, 8192);
+        let lease = lease_transfer("memory-test", 32768);
+        let second = lease_transfer("memory-test", 32768);
+        let find = || snapshot().into_iter().find(|(id, _)| id == "memory-test").map(|(_, memory)| memory);
+
+        let memory = find().unwrap();
+        assert_eq!(memory.transfers, 2);
+        assert_eq!(memory.total_bytes(), 4096 + 65536);
+        drop(second);
+        assert_eq!(find().unwrap().transfer_bytes, 32768);
+
+        // Closing the tab keeps the running transfer listed
+        forget("memory-test");
+        let memory = find().unwrap();
+        assert_eq!(memory.scrollback_bytes, 0);
+        assert_eq!(memory.transfers, 1);
+        drop(lease);
+        assert!(find().is_none());
+    }
+}
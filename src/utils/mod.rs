@@ -1,7 +1,17 @@
 //! Utility functions
 
+pub mod crash;
+pub mod custom_actions;
 pub mod errors;
+pub mod executor;
 pub mod helpers;
+pub mod history_import;
+pub mod http_preview;
 pub mod logging;
+pub mod memory;
+pub mod profile_share;
+pub mod startup;
+pub mod undo;
+pub mod updater;
 
 pub use errors::{TabSshError, Result};
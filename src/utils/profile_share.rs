@@ -0,0 +1,167 @@
+//! Credential-less connection profile sharing
+//!
+//! A shared profile carries only what a teammate needs to reach a host:
+//! address, user, jump host and port forwards. Passwords, key paths and
+//! passphrases are never part of it. Profiles travel either as a
+//! `tabssh://import?profile=...` link or as a JSON file holding a whole group.
+
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// URL scheme of share links
+pub const LINK_PREFIX: &str = "tabssh://import?profile=";
+const FORMAT_VERSION: u32 = 1;
+
+/// Jump host of a shared profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SharedJumpHost {
+    pub host: String,
+    pub port: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+}
+
+/// Port forward of a shared profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SharedForward {
+    /// "local" or "remote"
+    pub kind: String,
+    pub local_port: u16,
+    pub remote_host: String,
+    pub remote_port: u16,
+}
+
+/// A connection profile stripped of secrets
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SharedProfile {
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    /// Auth method name only, so the importer knows what to set up
+    pub auth_method: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jump_host: Option<SharedJumpHost>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub forwards: Vec<SharedForward>,
+}
+
+/// File format for bulk export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedProfileBundle {
+    pub version: u32,
+    pub exported_at: String,
+    pub profiles: Vec<SharedProfile>,
+}
+
+impl SharedProfile {
+    /// Encode as a `tabssh://` deep link
+    pub fn to_link(&self) -> Result<String> {
+        let json = serde_json::to_vec(self)?;
+        Ok(format!("{}{}", LINK_PREFIX, base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json)))
+    }
+
+    /// Decode a `tabssh://` deep link
+    pub fn from_link(link: &str) -> Result<Self> {
+        let payload = link.trim()
+            .strip_prefix(LINK_PREFIX)
+            .ok_or_else(|| anyhow!("Not a TabSSH share link"))?;
+        let json = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(payload)
+            .context("Share link is damaged")?;
+        let profile: Self = serde_json::from_slice(&json).context("Share link is damaged")?;
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.host.trim().is_empty() {
+            return Err(anyhow!("Shared profile has no host"));
+        }
+        if self.port == 0 {
+            return Err(anyhow!("Shared profile has an invalid port"));
+        }
+        Ok(())
+    }
+}
+
+/// Write profiles to a share file, e.g. a whole group for onboarding
+pub fn export_bundle(path: &Path, profiles: Vec<SharedProfile>) -> Result<()> {
+    let bundle = SharedProfileBundle {
+        version: FORMAT_VERSION,
+        exported_at: chrono::Local::now().to_rfc3339(),
+        profiles,
+    };
+    std::fs::write(path, serde_json::to_string_pretty(&bundle)?)?;
+    log::info!("Exported {} shared profile(s) to {}", bundle.profiles.len(), path.display());
+    Ok(())
+}
+
+/// Read profiles from a share file
+pub fn import_bundle(path: &Path) -> Result<Vec<SharedProfile>> {
+    let data = std::fs::read_to_string(path)?;
+    let bundle: SharedProfileBundle = serde_json::from_str(&data).context("Not a TabSSH share file")?;
+    if bundle.version > FORMAT_VERSION {
+        return Err(anyhow!("Share file was written by a newer TabSSH"));
+    }
+    for profile in &bundle.profiles {
+        profile.validate()?;
+    }
+    Ok(bundle.profiles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile() -> SharedProfile {
+        SharedProfile {
+            name: "web".to_string(),
+            host: "web01.internal".to_string(),
+            port: 22,
+            username: "deploy".to_string(),
+            auth_method: "Public Key".to_string(),
+            group: Some("Production".to_string()),
+            jump_host: Some(SharedJumpHost {
+                host: "bastion.example.com".to_string(),
+                port: 2222,
+                username: None,
+            }),
+            forwards: vec![SharedForward {
+                kind: "local".to_string(),
+                local_port: 5432,
+                remote_host: "localhost".to_string(),
+                remote_port: 5432,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_link_round_trip() {
+        let link = profile().to_link().unwrap();
+        assert!(link.starts_with(LINK_PREFIX));
+        assert_eq!(SharedProfile::from_link(&link).unwrap(), profile());
+    }
+
+    #[test]
+    fn test_rejects_foreign_links() {
+        assert!(SharedProfile::from_link("https://example.com").is_err());
+        assert!(SharedProfile::from_link("tabssh://import?profile=!!!").is_err());
+
+        let mut no_host = profile();
+        no_host.host = String::new();
+        assert!(SharedProfile::from_link(&no_host.to_link().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("team.json");
+        export_bundle(&path, vec![profile(), profile()]).unwrap();
+        assert_eq!(import_bundle(&path).unwrap(), vec![profile(), profile()]);
+    }
+}
@@ -0,0 +1,138 @@
+//! Startup profiling
+//!
+//! Times each initialization phase against process start so slow phases show
+//! up in the log. The window paints before the database, themes and fonts are
+//! loaded; first paint is expected within `FIRST_PAINT_TARGET`.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// First paint later than this is logged as a warning
+pub const FIRST_PAINT_TARGET: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone)]
+struct Phase {
+    name: &'static str,
+    /// When the phase started, relative to process start
+    started: Duration,
+    took: Duration,
+}
+
+#[derive(Default)]
+struct Inner {
+    phases: Vec<Phase>,
+    first_paint: Option<Duration>,
+}
+
+/// Shared between the UI thread and the background loader
+#[derive(Clone)]
+pub struct StartupProfiler {
+    start: Instant,
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl StartupProfiler {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            inner: Arc::new(Mutex::new(Inner::default())),
+        }
+    }
+
+    /// Run `f` as the phase `name` and record how long it took
+    pub fn phase<T>(&self, name: &'static str, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let value = f();
+        self.record(name, started.duration_since(self.start), started.elapsed());
+        value
+    }
+
+    fn record(&self, name: &'static str, started: Duration, took: Duration) {
+        log::debug!("Startup phase {} took {:?}", name, took);
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.phases.push(Phase { name, started, took });
+        }
+    }
+
+    /// Note the first frame; only the first call counts
+    pub fn first_paint(&self) {
+        let elapsed = self.start.elapsed();
+        let Ok(mut inner) = self.inner.lock() else { return };
+        if inner.first_paint.is_some() {
+            return;
+        }
+        inner.first_paint = Some(elapsed);
+        if elapsed > FIRST_PAINT_TARGET {
+            log::warn!("First paint after {} ms (target {} ms)", elapsed.as_millis(), FIRST_PAINT_TARGET.as_millis());
+        } else {
+            log::info!("First paint after {} ms", elapsed.as_millis());
+        }
+    }
+
+    /// Log the phase breakdown once loading has finished
+    pub fn finish(&self) {
+        let elapsed = self.start.elapsed();
+        if let Ok(inner) = self.inner.lock() {
+            log::info!("Startup finished after {} ms\n{}", elapsed.as_millis(), report(&inner));
+        }
+    }
+}
+
+impl Default for StartupProfiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// One line per phase in start order, slowest marked
+fn report(inner: &Inner) -> String {
+    let mut phases = inner.phases.clone();
+    phases.sort_by_key(|phase| phase.started);
+    let slowest = phases.iter().map(|phase| phase.took).max().unwrap_or_default();
+
+    let mut lines: Vec<String> = phases.iter()
+        .map(|phase| format!(
+            "  {:>6.1} ms  {:<12} {:>6.1} ms{}",
+            phase.started.as_secs_f64() * 1000.0,
+            phase.name,
+            phase.took.as_secs_f64() * 1000.0,
+            if phase.took == slowest && phases.len() > 1 { "  (slowest)" } else { "" },
+        ))
+        .collect();
+    if let Some(paint) = inner.first_paint {
+        lines.push(format!("  {:>6.1} ms  first paint", paint.as_secs_f64() * 1000.0));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_orders_phases() {
+        let inner = Inner {
+            phases: vec![
+                Phase { name: "themes", started: Duration::from_millis(40), took: Duration::from_millis(5) },
+                Phase { name: "database", started: Duration::from_millis(2), took: Duration::from_millis(30) },
+            ],
+            first_paint: Some(Duration::from_millis(120)),
+        };
+        let report = report(&inner);
+        let lines: Vec<&str> = report.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("database") && lines[0].ends_with("(slowest)"));
+        assert!(lines[1].contains("themes") && !lines[1].contains("slowest"));
+        assert!(lines[2].contains("first paint"));
+    }
+
+    #[test]
+    fn test_first_paint_counts_once() {
+        let profiler = StartupProfiler::new();
+        profiler.first_paint();
+        let first = profiler.inner.lock().unwrap().first_paint;
+        profiler.first_paint();
+        assert_eq!(profiler.inner.lock().unwrap().first_paint, first);
+    }
+}
@@ -0,0 +1,131 @@
+//! Undo/redo history for editable forms
+//!
+//! The form hands over its whole state once per frame; a state that differs
+//! from the last one becomes an undo step. Changes made in quick succession,
+//! like the keystrokes of one word, merge into a single step.
+
+use std::time::{Duration, Instant};
+
+/// Changes closer together than this are undone together
+const MERGE_WINDOW: Duration = Duration::from_millis(800);
+/// Oldest steps are dropped beyond this
+const MAX_STEPS: usize = 100;
+
+pub struct UndoHistory<T> {
+    undo: Vec<T>,
+    redo: Vec<T>,
+    /// State after the last recorded change
+    current: T,
+    last_change: Option<Instant>,
+}
+
+impl<T: Clone + PartialEq> UndoHistory<T> {
+    pub fn new(state: T) -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            current: state,
+            last_change: None,
+        }
+    }
+
+    /// Start over from `state`, e.g. after loading or saving
+    pub fn reset(&mut self, state: T) {
+        *self = Self::new(state);
+    }
+
+    /// Record the form state; call after the widgets ran
+    pub fn record(&mut self, state: &T) {
+        self.record_at(state, Instant::now());
+    }
+
+    fn record_at(&mut self, state: &T, now: Instant) {
+        if *state == self.current {
+            return;
+        }
+        let merge = self.last_change.is_some_and(|at| now.duration_since(at) < MERGE_WINDOW)
+            && !self.undo.is_empty();
+        if !merge {
+            self.undo.push(self.current.clone());
+            if self.undo.len() > MAX_STEPS {
+                self.undo.remove(0);
+            }
+        }
+        self.current = state.clone();
+        self.redo.clear();
+        self.last_change = Some(now);
+    }
+
+    /// State to go back to, if any
+    pub fn undo(&mut self) -> Option<T> {
+        let previous = self.undo.pop()?;
+        self.redo.push(std::mem::replace(&mut self.current, previous.clone()));
+        self.last_change = None;
+        Some(previous)
+    }
+
+    /// State an undo went back from, if any
+    pub fn redo(&mut self) -> Option<T> {
+        let next = self.redo.pop()?;
+        self.undo.push(std::mem::replace(&mut self.current, next.clone()));
+        self.last_change = None;
+        Some(next)
+    }
+
+    /// Ctrl+Z and Ctrl+Shift+Z, taken before any text field sees them;
+    /// returns the state to show when either was pressed
+    pub fn handle_keys(&mut self, ui: &mut egui::Ui) -> Option<T> {
+        let redo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z);
+        let undo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+        if ui.input_mut(|i| i.consume_shortcut(&redo)) {
+            self.redo()
+        } else if ui.input_mut(|i| i.consume_shortcut(&undo)) {
+            self.undo()
+        } else {
+            None
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_undo_redo() {
+        let start = Instant::now();
+        let mut history = UndoHistory::new("~/.ssh/id_ed25519".to_string());
+        history.record_at(&String::new(), start);
+        history.record_at(&"22".to_string(), start + Duration::from_secs(2));
+
+        assert_eq!(history.undo().as_deref(), Some(""));
+        assert_eq!(history.undo().as_deref(), Some("~/.ssh/id_ed25519"));
+        assert!(!history.can_undo());
+        assert_eq!(history.redo().as_deref(), Some(""));
+
+        // A new change drops what could have been redone
+        history.record_at(&"x".to_string(), start + Duration::from_secs(5));
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn test_quick_changes_merge() {
+        let start = Instant::now();
+        let mut history = UndoHistory::new(String::new());
+        for (i, text) in ["w", "we", "web"].iter().enumerate() {
+            history.record_at(&text.to_string(), start + Duration::from_millis(100 * i as u64));
+        }
+        history.record_at(&"web".to_string(), start + Duration::from_secs(1));
+
+        assert_eq!(history.undo().as_deref(), Some(""));
+        assert!(!history.can_undo());
+    }
+}
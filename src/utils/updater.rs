@@ -0,0 +1,267 @@
+//! Update checks
+//!
+//! While `check_for_updates` is on, the project's release feed is asked for
+//! the latest release shortly after startup and then once a day. A release
+//! newer than `APP_VERSION` that the user has not skipped is offered with
+//! its notes and a download for this platform. Nothing is installed
+//! without the user: the download opens in the browser.
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+use crate::storage::Database;
+use crate::utils::executor::{Executor, Task};
+
+pub const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const RELEASES_URL: &str = "https://api.github.com/repos/tabssh/desktop/releases/latest";
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Leave startup alone before the first check
+const STARTUP_DELAY: Duration = Duration::from_secs(10);
+
+const LAST_CHECK_KEY: &str = "update_last_checked";
+const SKIPPED_KEY: &str = "update_skipped_version";
+
+/// A published release
+#[derive(Debug, Clone, PartialEq)]
+pub struct Release {
+    /// Without the leading `v` of the tag
+    pub version: String,
+    /// Markdown
+    pub notes: String,
+    pub page_url: String,
+    pub assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    #[serde(rename = "browser_download_url")]
+    pub url: String,
+}
+
+/// The parts of the feed's release object that are used
+#[derive(Deserialize)]
+struct FeedRelease {
+    tag_name: String,
+    #[serde(default)]
+    body: Option<String>,
+    html_url: String,
+    #[serde(default)]
+    assets: Vec<ReleaseAsset>,
+}
+
+impl Release {
+    /// Where to get this release: the installer for this platform, or the
+    /// release page when none fits
+    pub fn download_url(&self) -> &str {
+        pick_asset(&self.assets, &platform_suffixes(), std::env::consts::ARCH)
+            .map_or(self.page_url.as_str(), |asset| asset.url.as_str())
+    }
+}
+
+/// Ask the release feed for the latest release; blocks
+pub fn fetch_latest() -> Result<Release> {
+    let response = ureq::get(RELEASES_URL)
+        .set("Accept", "application/vnd.github+json")
+        .set("User-Agent", &format!("TabSSH/{}", APP_VERSION))
+        .timeout(Duration::from_secs(15))
+        .call()
+        .map_err(|e| anyhow!("Update check failed: {}", e))?;
+    let feed: FeedRelease = serde_json::from_reader(response.into_reader()).context("Unexpected release feed")?;
+    Ok(Release {
+        version: feed.tag_name.trim_start_matches('v').to_string(),
+        notes: feed.body.unwrap_or_default(),
+        page_url: feed.html_url,
+        assets: feed.assets,
+    })
+}
+
+/// Whether `candidate` is a later version than `current`. Versions are
+/// dotted numbers with an optional `-pre` suffix, which sorts before the
+/// release itself.
+pub fn is_newer(candidate: &str, current: &str) -> bool {
+    parse_version(candidate) > parse_version(current)
+}
+
+/// Numeric parts, then whether it is a full release, then the pre-release tag
+fn parse_version(version: &str) -> (Vec<u64>, bool, String) {
+    let version = version.trim().trim_start_matches('v');
+    let (numbers, pre) = match version.split_once('-') {
+        Some((numbers, pre)) => (numbers, pre),
+        None => (version, ""),
+    };
+    let mut parts: Vec<u64> = numbers.split('.').map(|part| part.parse().unwrap_or(0)).collect();
+    // 1.2 and 1.2.0 are the same version
+    while parts.len() > 1 && parts.last() == Some(&0) {
+        parts.pop();
+    }
+    (parts, pre.is_empty(), pre.to_string())
+}
+
+/// Installer file endings for this platform, best first
+fn platform_suffixes() -> Vec<&'static str> {
+    if cfg!(target_os = "macos") {
+        vec![".dmg", ".pkg"]
+    } else if cfg!(target_os = "windows") {
+        vec![".msi", ".exe"]
+    } else if std::env::var_os("APPIMAGE").is_some() {
+        // Running from an AppImage; replace it like for like
+        vec![".AppImage"]
+    } else if std::path::Path::new("/etc/debian_version").exists() {
+        vec![".deb", ".AppImage"]
+    } else if std::path::Path::new("/etc/redhat-release").exists() {
+        vec![".rpm", ".AppImage"]
+    } else {
+        vec![".AppImage", ".tar.gz"]
+    }
+}
+
+/// The asset with the best-ranked suffix, preferring one built for `arch`
+fn pick_asset<'a>(assets: &'a [ReleaseAsset], suffixes: &[&str], arch: &str) -> Option<&'a ReleaseAsset> {
+    let arch_names: &[&str] = match arch {
+        "x86_64" => &["x86_64", "amd64", "x64"],
+        "aarch64" => &["aarch64", "arm64"],
+        _ => &[],
+    };
+    let other_arch = |name: &str| {
+        ["x86_64", "amd64", "x64", "aarch64", "arm64", "i686", "armv7"]
+            .iter()
+            .any(|a| name.contains(a) && !arch_names.contains(a))
+    };
+    suffixes.iter().find_map(|suffix| {
+        let mut candidates = assets.iter().filter(|a| a.name.ends_with(suffix) && !other_arch(&a.name.to_lowercase()));
+        let first = candidates.clone().next()?;
+        Some(candidates.find(|a| arch_names.iter().any(|n| a.name.to_lowercase().contains(n))).unwrap_or(first))
+    })
+}
+
+/// Runs the checks on schedule and holds the release to offer
+pub struct UpdateChecker {
+    task: Option<Task<Result<Release>>>,
+    next_check: Instant,
+    /// Newer release waiting for the user
+    available: Option<Release>,
+    skipped: Option<String>,
+}
+
+impl UpdateChecker {
+    /// Picks up when the last check ran, so restarts don't check again
+    pub fn new(db: &Database) -> Self {
+        let since_last = read_key(db, LAST_CHECK_KEY)
+            .and_then(|at| chrono::DateTime::parse_from_rfc3339(&at).ok())
+            .and_then(|at| (chrono::Utc::now() - at.with_timezone(&chrono::Utc)).to_std().ok());
+        let wait = match since_last {
+            Some(elapsed) => CHECK_INTERVAL.saturating_sub(elapsed).max(STARTUP_DELAY),
+            None => STARTUP_DELAY,
+        };
+        Self {
+            task: None,
+            next_check: Instant::now() + wait,
+            available: None,
+            skipped: read_key(db, SKIPPED_KEY),
+        }
+    }
+
+    /// Start a due check and collect a finished one; call once per frame.
+    /// Returns how long until the next check, for scheduling a repaint.
+    pub fn poll(&mut self, executor: &Executor, db: &Database, enabled: bool) -> Option<Duration> {
+        if let Some(result) = self.task.as_mut().and_then(|task| task.poll()) {
+            self.task = None;
+            match result.and_then(|fetched| fetched) {
+                Ok(release) => {
+                    write_key(db, LAST_CHECK_KEY, &chrono::Utc::now().to_rfc3339());
+                    let skipped = self.skipped.as_deref() == Some(release.version.as_str());
+                    if is_newer(&release.version, APP_VERSION) && !skipped {
+                        log::info!("Update available: {}", release.version);
+                        self.available = Some(release);
+                    }
+                }
+                Err(e) => log::debug!("{:#}", e),
+            }
+        }
+
+        if !enabled || self.task.is_some() {
+            return None;
+        }
+        let now = Instant::now();
+        if now < self.next_check {
+            return Some(self.next_check - now);
+        }
+        self.next_check = now + CHECK_INTERVAL;
+        self.task = Some(executor.spawn(async {
+            tokio::task::spawn_blocking(fetch_latest)
+                .await
+                .unwrap_or_else(|e| Err(anyhow!("Update check failed: {}", e)))
+        }));
+        None
+    }
+
+    pub fn available(&self) -> Option<&Release> {
+        self.available.as_ref()
+    }
+
+    /// Hide the offer until the next check finds it again
+    pub fn dismiss(&mut self) {
+        self.available = None;
+    }
+
+    /// Never offer this release again; a later one is still offered
+    pub fn skip(&mut self, db: &Database) {
+        if let Some(release) = self.available.take() {
+            write_key(db, SKIPPED_KEY, &release.version);
+            self.skipped = Some(release.version);
+        }
+    }
+}
+
+fn read_key(db: &Database, key: &str) -> Option<String> {
+    db.connection()
+        .query_row("SELECT value FROM settings WHERE key = ?1", [key], |row| row.get(0))
+        .ok()
+}
+
+fn write_key(db: &Database, key: &str, value: &str) {
+    if let Err(e) = db.connection().execute(
+        "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
+        [key, value],
+    ) {
+        log::warn!("Could not save {}: {}", key, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("0.2.0", "0.1.0"));
+        assert!(is_newer("v1.10.0", "1.9.3"));
+        assert!(is_newer("1.0.0", "1.0.0-beta.2"));
+        assert!(is_newer("1.0.0-rc.1", "1.0.0-beta.2"));
+        assert!(!is_newer("1.0", "1.0.0"));
+        assert!(!is_newer("0.9.9", "1.0.0"));
+        assert!(!is_newer("1.0.0-beta", "1.0.0"));
+    }
+
+    #[test]
+    fn test_pick_asset() {
+        let asset = |name: &str| ReleaseAsset { name: name.to_string(), url: format!("https://dl/{}", name) };
+        let assets = vec![
+            asset("tabssh-0.2.0-aarch64.dmg"),
+            asset("tabssh-0.2.0-x86_64.dmg"),
+            asset("tabssh-0.2.0-x64.msi"),
+            asset("tabssh_0.2.0_amd64.deb"),
+            asset("tabssh-0.2.0.AppImage"),
+        ];
+        let name = |suffixes: &[&str], arch| pick_asset(&assets, suffixes, arch).map(|a| a.name.as_str());
+
+        assert_eq!(name(&[".dmg"], "aarch64"), Some("tabssh-0.2.0-aarch64.dmg"));
+        assert_eq!(name(&[".dmg"], "x86_64"), Some("tabssh-0.2.0-x86_64.dmg"));
+        assert_eq!(name(&[".msi", ".exe"], "x86_64"), Some("tabssh-0.2.0-x64.msi"));
+        // No arm64 package; the AppImage is next best
+        assert_eq!(name(&[".deb", ".AppImage"], "aarch64"), Some("tabssh-0.2.0.AppImage"));
+        assert_eq!(name(&[".rpm"], "x86_64"), None);
+    }
+}